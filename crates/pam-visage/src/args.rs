@@ -0,0 +1,501 @@
+//! Module arguments from the pam.d line.
+//!
+//! ```text
+//! auth sufficient pam_visage.so timeout=5 debug max_tries=2 device=/dev/video2
+//! ```
+//!
+//! Parsing never fails: an unknown or malformed argument is reported as a
+//! warning (logged by the caller) and the default is kept, so a typo in
+//! pam.d can never lock anyone out.
+
+/// Default D-Bus method timeout — long enough for one capture, short enough
+/// that a stuck daemon does not hang the login.
+const DEFAULT_TIMEOUT_SECS: u64 = 3;
+const MAX_TIMEOUT_SECS: u64 = 60;
+/// Longest allowed `dbus_timeout=` — two full verify timeouts.
+const MAX_DBUS_TIMEOUT_SECS: u64 = 2 * MAX_TIMEOUT_SECS;
+const MAX_TRIES_LIMIT: u32 = 10;
+/// Longest allowed `retry_delay=` — a pause, not a second timeout.
+const MAX_RETRY_DELAY_MS: u64 = 3000;
+/// Longest allowed `grace=` window — matches sudo's default 15-minute timestamp.
+const MAX_GRACE_SECS: u64 = 900;
+/// Longest allowed `min_interval=` — a pause between attempts, not a lockout.
+const MAX_MIN_INTERVAL_SECS: u64 = 300;
+/// Longest allowed `max_age=` — ten years.
+const MAX_ENROLL_AGE_DAYS: u64 = 3650;
+
+use crate::policy::ErrorPolicy;
+
+/// What `pam_sm_acct_mgmt` does when the enrollment is older than `max_age`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum StaleAction {
+    /// Tell the user and log it; the account check is unaffected.
+    #[default]
+    Warn,
+    /// Also return `PAM_NEW_AUTHTOK_REQD`, as for an expired password.
+    Expire,
+}
+
+/// When `PAM_RHOST` makes the module skip face auth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum RhostPolicy {
+    /// Never; only the other session checks apply.
+    Ignore,
+    /// When it names a host other than the loopback.
+    #[default]
+    Remote,
+    /// Whenever it is set, `localhost` included (`ssh localhost`).
+    Any,
+}
+
+/// When `PAM_TTY` makes the module skip face auth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum TtyPolicy {
+    /// Never; only the other session checks apply.
+    Ignore,
+    /// When it is OpenSSH's `ssh` placeholder.
+    #[default]
+    Remote,
+    /// Unless it is a virtual console or an X display — a pseudo-terminal
+    /// (`su` in a terminal window) or no TTY at all is skipped.
+    Local,
+}
+
+/// Options parsed from the module's `argv`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PamArgs {
+    /// `timeout=N` — seconds to wait for the daemon per attempt (1–60).
+    pub timeout_secs: u64,
+    /// `dbus_timeout=N` — D-Bus method timeout in seconds (1–120), when it
+    /// should differ from `timeout`; see [`PamArgs::dbus_timeout`].
+    pub dbus_timeout_secs: Option<u64>,
+    /// `debug` — log per-attempt detail to syslog at `LOG_DEBUG`.
+    pub debug: bool,
+    /// `max_tries=N` — verify attempts before giving up (1–10).
+    pub max_tries: u32,
+    /// `retry_delay=MS` — pause after the retry prompt so the user can face
+    /// the camera before the next attempt captures (0–3000; 0 disables).
+    pub retry_delay_ms: u64,
+    /// `device=PATH` — camera the daemon is expected to use.
+    pub device: Option<String>,
+    /// `strict` — return `PAM_AUTH_ERR` (not `PAM_IGNORE`) on a definitive
+    /// non-match, so the failure is counted by pam_faillock / pam_tally2.
+    pub strict: bool,
+    /// `race` — prompt for the password while the face is being verified and
+    /// take whichever finishes first.
+    pub race: bool,
+    /// `authtok` — after a face match, fetch the user's stored keyring
+    /// password from the daemon and set it as `PAM_AUTHTOK` (see the
+    /// `keyring` module).
+    pub authtok: bool,
+    /// `grace=N` — after a face match, skip the camera for this user and
+    /// session for N seconds (0–900; 0 disables).
+    pub grace_secs: u64,
+    /// `min_interval=N` — after a failed verify, skip the camera for this
+    /// user for N seconds (0–300; 0 disables); see the `debounce` module.
+    pub min_interval_secs: u64,
+    /// `second_factor` — face as an additional factor: record the outcome as
+    /// PAM data and never end the stack (see the `second_factor` module).
+    pub second_factor: bool,
+    /// `require_face` — capture nothing; pass only if a `second_factor` line
+    /// earlier in this PAM transaction recorded a match. Read from the pam.d
+    /// line only (see the `second_factor` module).
+    pub require_face: bool,
+    /// `quiet` — send no `PAM_TEXT_INFO` or `PAM_ERROR_MSG` messages; syslog
+    /// logging is kept.
+    pub quiet: bool,
+    /// `audit` — also send each decision record to the `LOG_AUTH` facility
+    /// (see the `audit` module).
+    pub audit: bool,
+    /// `max_age=DAYS` — in the account stack, flag an enrollment whose newest
+    /// face model is older than this (1–3650; unset disables).
+    pub max_age_days: Option<u64>,
+    /// `max_age_action=warn|expire` — what a stale enrollment does.
+    pub max_age_action: StaleAction,
+    /// `local_only` — besides always skipping remote sessions, also skip any
+    /// session not attached to a local seat (VT, X display, logind seat).
+    pub local_only: bool,
+    /// `rhost=ignore|remote|any` — which `PAM_RHOST` values skip face auth.
+    pub rhost: RhostPolicy,
+    /// `tty=ignore|remote|local` — which `PAM_TTY` values skip face auth.
+    pub tty: TtyPolicy,
+    /// `ignore_lid` — verify even with the laptop lid closed (external camera).
+    pub ignore_lid: bool,
+    /// `services=a,b` — only these PAM services may use face auth.
+    pub services: Option<Vec<String>>,
+    /// `deny_services=a,b` — these PAM services never use face auth; wins
+    /// over `services`.
+    pub deny_services: Vec<String>,
+    /// `allow_users=a,/path` — only these users may use face auth. An entry
+    /// starting with `/` names a root-owned file of user names.
+    pub allow_users: Option<Vec<String>>,
+    /// `deny_users=a,/path` — these users never use face auth; wins over
+    /// `allow_users`.
+    pub deny_users: Vec<String>,
+    /// `socket=PATH` — daemon's Unix socket, used when the system bus is
+    /// unavailable (initramfs, greeters started before the broker).
+    pub socket: Option<String>,
+    /// `on_error=CLASS:RESULT,…` — PAM result per failure class (see the
+    /// `policy` module).
+    pub on_error: ErrorPolicy,
+}
+
+impl Default for PamArgs {
+    fn default() -> Self {
+        Self {
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            dbus_timeout_secs: None,
+            debug: false,
+            max_tries: 1,
+            retry_delay_ms: 0,
+            device: None,
+            strict: false,
+            race: false,
+            authtok: false,
+            grace_secs: 0,
+            min_interval_secs: 0,
+            second_factor: false,
+            require_face: false,
+            quiet: false,
+            audit: false,
+            max_age_days: None,
+            max_age_action: StaleAction::Warn,
+            local_only: false,
+            rhost: RhostPolicy::Remote,
+            tty: TtyPolicy::Remote,
+            ignore_lid: false,
+            services: None,
+            deny_services: Vec::new(),
+            allow_users: None,
+            deny_users: Vec::new(),
+            socket: None,
+            on_error: ErrorPolicy::default(),
+        }
+    }
+}
+
+impl PamArgs {
+    /// Options from [`CONFIG_PATH`](crate::conf::CONFIG_PATH) overlaid with
+    /// the pam.d arguments, which take precedence. `Err` when the config file
+    /// exists but cannot be trusted or read; the caller then skips face auth.
+    pub fn load<S: AsRef<str>>(args: &[S]) -> Result<(Self, Vec<String>), String> {
+        let file_args = crate::conf::read(std::path::Path::new(crate::conf::CONFIG_PATH))?;
+        let merged: Vec<&str> = file_args
+            .iter()
+            .map(String::as_str)
+            .chain(args.iter().map(AsRef::as_ref))
+            .collect();
+        Ok(Self::parse(&merged))
+    }
+
+    /// Parse module arguments. Returns the options plus one warning per
+    /// argument that was ignored.
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> (Self, Vec<String>) {
+        let mut opts = Self::default();
+        let mut warnings = Vec::new();
+
+        for arg in args {
+            let arg = arg.as_ref();
+            let (key, value) = match arg.split_once('=') {
+                Some((k, v)) => (k, Some(v)),
+                None => (arg, None),
+            };
+            match (key, value) {
+                ("debug", None) => opts.debug = true,
+                ("strict", None) => opts.strict = true,
+                ("race", None) => opts.race = true,
+                ("authtok", None) => opts.authtok = true,
+                ("quiet", None) => opts.quiet = true,
+                ("second_factor", None) => opts.second_factor = true,
+                ("require_face", None) => opts.require_face = true,
+                ("audit", None) => opts.audit = true,
+                ("local_only", None) => opts.local_only = true,
+                ("ignore_lid", None) => opts.ignore_lid = true,
+                ("timeout", Some(v)) => match v.parse::<u64>() {
+                    Ok(n) if (1..=MAX_TIMEOUT_SECS).contains(&n) => opts.timeout_secs = n,
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': timeout must be 1-{MAX_TIMEOUT_SECS} seconds"
+                    )),
+                },
+                ("dbus_timeout", Some(v)) => match v.parse::<u64>() {
+                    Ok(n) if (1..=MAX_DBUS_TIMEOUT_SECS).contains(&n) => {
+                        opts.dbus_timeout_secs = Some(n)
+                    }
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': dbus_timeout must be 1-{MAX_DBUS_TIMEOUT_SECS} seconds"
+                    )),
+                },
+                ("grace", Some(v)) => match v.parse::<u64>() {
+                    Ok(n) if n <= MAX_GRACE_SECS => opts.grace_secs = n,
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': grace must be 0-{MAX_GRACE_SECS} seconds"
+                    )),
+                },
+                ("min_interval", Some(v)) => match v.parse::<u64>() {
+                    Ok(n) if n <= MAX_MIN_INTERVAL_SECS => opts.min_interval_secs = n,
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': min_interval must be 0-{MAX_MIN_INTERVAL_SECS} seconds"
+                    )),
+                },
+                ("max_age", Some(v)) => match v.parse::<u64>() {
+                    Ok(n) if (1..=MAX_ENROLL_AGE_DAYS).contains(&n) => opts.max_age_days = Some(n),
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': max_age must be 1-{MAX_ENROLL_AGE_DAYS} days"
+                    )),
+                },
+                ("max_age_action", Some("warn")) => opts.max_age_action = StaleAction::Warn,
+                ("max_age_action", Some("expire")) => opts.max_age_action = StaleAction::Expire,
+                ("max_age_action", _) => warnings.push(format!(
+                    "ignoring '{arg}': max_age_action must be warn or expire"
+                )),
+                ("rhost", Some("ignore")) => opts.rhost = RhostPolicy::Ignore,
+                ("rhost", Some("remote")) => opts.rhost = RhostPolicy::Remote,
+                ("rhost", Some("any")) => opts.rhost = RhostPolicy::Any,
+                ("rhost", _) => warnings.push(format!(
+                    "ignoring '{arg}': rhost must be ignore, remote or any"
+                )),
+                ("tty", Some("ignore")) => opts.tty = TtyPolicy::Ignore,
+                ("tty", Some("remote")) => opts.tty = TtyPolicy::Remote,
+                ("tty", Some("local")) => opts.tty = TtyPolicy::Local,
+                ("tty", _) => warnings.push(format!(
+                    "ignoring '{arg}': tty must be ignore, remote or local"
+                )),
+                ("max_tries", Some(v)) => match v.parse::<u32>() {
+                    Ok(n) if (1..=MAX_TRIES_LIMIT).contains(&n) => opts.max_tries = n,
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': max_tries must be 1-{MAX_TRIES_LIMIT}"
+                    )),
+                },
+                ("retry_delay", Some(v)) => match v.parse::<u64>() {
+                    Ok(ms) if ms <= MAX_RETRY_DELAY_MS => opts.retry_delay_ms = ms,
+                    _ => warnings.push(format!(
+                        "ignoring '{arg}': retry_delay must be 0-{MAX_RETRY_DELAY_MS} ms"
+                    )),
+                },
+                ("services", Some(v)) => match parse_list(v) {
+                    Some(list) => opts.services = Some(list),
+                    None => warnings.push(format!("ignoring '{arg}': empty service list")),
+                },
+                ("deny_services", Some(v)) => match parse_list(v) {
+                    Some(list) => opts.deny_services = list,
+                    None => warnings.push(format!("ignoring '{arg}': empty service list")),
+                },
+                ("allow_users", Some(v)) => match parse_list(v) {
+                    Some(list) => opts.allow_users = Some(list),
+                    None => warnings.push(format!("ignoring '{arg}': empty user list")),
+                },
+                ("deny_users", Some(v)) => match parse_list(v) {
+                    Some(list) => opts.deny_users = list,
+                    None => warnings.push(format!("ignoring '{arg}': empty user list")),
+                },
+                ("on_error", Some(v)) => {
+                    if let Err(e) = opts.on_error.merge(v) {
+                        warnings.push(format!("ignoring '{arg}': {e}"));
+                    }
+                }
+                ("device", Some(v)) if v.starts_with("/dev/") => opts.device = Some(v.to_string()),
+                ("socket", Some(v)) if v.starts_with('/') => opts.socket = Some(v.to_string()),
+                ("socket", _) => {
+                    warnings.push(format!("ignoring '{arg}': socket must be an absolute path"))
+                }
+                ("device", _) => {
+                    warnings.push(format!("ignoring '{arg}': device must be a /dev path"))
+                }
+                _ => warnings.push(format!("ignoring unknown argument '{arg}'")),
+            }
+        }
+
+        // `race` hands a typed password to the next module and stops the
+        // capture; as a second factor the face must always be checked.
+        if opts.second_factor && opts.race {
+            warnings.push("ignoring 'race': not compatible with second_factor".to_string());
+            opts.race = false;
+        }
+        // A released password would satisfy the password module that the
+        // second factor is meant to require.
+        if opts.second_factor && opts.authtok {
+            warnings.push("ignoring 'authtok': not compatible with second_factor".to_string());
+            opts.authtok = false;
+        }
+
+        (opts, warnings)
+    }
+
+    /// How long to wait for the daemon's reply to a verify call: `dbus_timeout`
+    /// if set, otherwise `timeout`. The daemon's own capture deadline is always
+    /// `timeout`, so a longer `dbus_timeout` leaves room for a slow camera
+    /// start or a queued request without keeping the camera on longer.
+    pub fn dbus_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.dbus_timeout_secs.unwrap_or(self.timeout_secs))
+    }
+
+    /// Whether face auth may run for the calling PAM `service`. An unknown
+    /// service passes only when there is no allow list.
+    pub fn allows_service(&self, service: Option<&str>) -> bool {
+        match service {
+            Some(s) if self.deny_services.iter().any(|d| d == s) => false,
+            Some(s) => match &self.services {
+                Some(allow) => allow.iter().any(|a| a == s),
+                None => true,
+            },
+            None => self.services.is_none(),
+        }
+    }
+}
+
+/// Split a comma-separated list; `None` if it has no entries.
+fn parse_list(value: &str) -> Option<Vec<String>> {
+    let list: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+    (!list.is_empty()).then_some(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_args_give_defaults() {
+        let (opts, warnings) = PamArgs::parse::<&str>(&[]);
+        assert_eq!(opts, PamArgs::default());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn parses_documented_example() {
+        let (opts, warnings) = PamArgs::parse(&[
+            "timeout=5",
+            "dbus_timeout=8",
+            "debug",
+            "max_tries=2",
+            "retry_delay=700",
+            "device=/dev/video2",
+            "strict",
+            "race",
+            "authtok",
+            "grace=120",
+            "min_interval=10",
+            "local_only",
+            "rhost=any",
+            "tty=local",
+            "ignore_lid",
+            "quiet",
+            "audit",
+            "require_face",
+            "max_age=180",
+            "max_age_action=expire",
+            "services=sudo,gdm-password",
+            "deny_services=su",
+            "allow_users=alice,/etc/security/visage.users",
+            "deny_users=backup",
+            "socket=/run/visage/visaged.sock",
+            "on_error=unreachable:unavail,no_face:fail",
+        ]);
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert!(opts.strict);
+        assert!(opts.race);
+        assert!(opts.authtok);
+        assert_eq!(opts.grace_secs, 120);
+        assert_eq!(opts.min_interval_secs, 10);
+        assert!(opts.local_only);
+        assert_eq!(opts.rhost, RhostPolicy::Any);
+        assert_eq!(opts.tty, TtyPolicy::Local);
+        assert!(opts.ignore_lid);
+        assert!(opts.quiet);
+        assert!(opts.audit);
+        assert!(opts.require_face);
+        assert_eq!(opts.max_age_days, Some(180));
+        assert_eq!(opts.max_age_action, StaleAction::Expire);
+        assert_eq!(
+            opts.services,
+            Some(vec!["sudo".to_string(), "gdm-password".to_string()])
+        );
+        assert_eq!(opts.deny_services, vec!["su"]);
+        assert_eq!(
+            opts.allow_users,
+            Some(vec![
+                "alice".to_string(),
+                "/etc/security/visage.users".to_string()
+            ])
+        );
+        assert_eq!(opts.deny_users, vec!["backup"]);
+        assert_eq!(opts.socket.as_deref(), Some("/run/visage/visaged.sock"));
+        assert_eq!(
+            opts.on_error
+                .result(Some(crate::policy::ErrorClass::Unreachable)),
+            Some(crate::PAM_AUTHINFO_UNAVAIL)
+        );
+        assert_eq!(opts.timeout_secs, 5);
+        assert_eq!(opts.dbus_timeout(), std::time::Duration::from_secs(8));
+        assert!(opts.debug);
+        assert_eq!(opts.max_tries, 2);
+        assert_eq!(opts.retry_delay_ms, 700);
+        assert_eq!(opts.device.as_deref(), Some("/dev/video2"));
+    }
+
+    #[test]
+    fn malformed_values_keep_defaults_and_warn() {
+        let (opts, warnings) = PamArgs::parse(&[
+            "timeout=0",
+            "timeout=abc",
+            "dbus_timeout=0",
+            "dbus_timeout=121",
+            "max_tries=99",
+            "retry_delay=5000",
+            "device=video2",
+            "debug=1",
+            "strict=yes",
+            "grace=3600",
+            "min_interval=-1",
+            "services=,",
+            "deny_users=",
+            "socket=visaged.sock",
+            "max_age=0",
+            "max_age_action=deny",
+            "on_error=no_face:deny",
+            "rhost=never",
+            "tty",
+            "bogus",
+        ]);
+        assert_eq!(opts, PamArgs::default());
+        assert_eq!(warnings.len(), 20);
+        assert_eq!(opts.dbus_timeout(), std::time::Duration::from_secs(3));
+    }
+
+    #[test]
+    fn later_arguments_win() {
+        let (opts, warnings) = PamArgs::parse(&["timeout=5", "grace=60", "timeout=8"]);
+        assert!(warnings.is_empty());
+        assert_eq!((opts.timeout_secs, opts.grace_secs), (8, 60));
+    }
+
+    #[test]
+    fn second_factor_disables_race_and_authtok() {
+        let (opts, warnings) = PamArgs::parse(&["second_factor", "race", "authtok"]);
+        assert!(opts.second_factor);
+        assert!(!opts.race);
+        assert!(!opts.authtok);
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn service_policy() {
+        let open = PamArgs::default();
+        assert!(open.allows_service(Some("polkit-1")));
+        assert!(open.allows_service(None));
+
+        let (opts, _) = PamArgs::parse(&["services=sudo,xscreensaver", "deny_services=sudo"]);
+        assert!(opts.allows_service(Some("xscreensaver")));
+        assert!(!opts.allows_service(Some("sudo")), "deny wins");
+        assert!(!opts.allows_service(Some("su")));
+        assert!(!opts.allows_service(None));
+
+        let (opts, _) = PamArgs::parse(&["deny_services=su,polkit-1"]);
+        assert!(!opts.allows_service(Some("polkit-1")));
+        assert!(opts.allows_service(Some("gdm-password")));
+    }
+}
@@ -0,0 +1,181 @@
+//! Structured decision records — face-auth outcomes a SIEM can parse.
+//!
+//! Every decision `pam_sm_authenticate` reaches after the service and user
+//! policy lets it through is logged once, as a human-readable lead followed by
+//! `key=value` fields:
+//!
+//! ```text
+//! face matched for user 'alice': service=sudo user=alice uid=1000 tty=/dev/pts/2 rhost=? result=success reason=? latency_ms=812
+//! ```
+//!
+//! Values made only of `[A-Za-z0-9_./:@+-]` are bare; anything else is
+//! double-quoted with `\"`, `\\`, and control characters (`\x0a`) escaped,
+//! and a missing value is `?`, as in
+//! the Linux audit log. With the `audit` argument the same fields are also
+//! sent to the `LOG_AUTH` facility at `LOG_NOTICE`, prefixed `audit:`, for
+//! collectors that only read `auth`.
+
+use std::borrow::Cow;
+use std::time::Instant;
+
+use crate::session::{PAM_RHOST, PAM_TTY};
+use crate::{get_item_str, lookup_uid, syslog_msg};
+
+const LOG_AUTH: libc::c_int = 4 << 3;
+const LOG_NOTICE: libc::c_int = 5;
+
+/// How a face-auth attempt ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// The face matched.
+    Success,
+    /// A match from earlier in the session was reused (`grace=`).
+    Grace,
+    /// The daemon answered but did not match; `reason` carries its code.
+    Failure,
+    /// The daemon could not be reached or the call failed.
+    Error,
+    /// The password was entered first (`race`).
+    Password,
+    /// Skipped before contacting the daemon, e.g. a remote session.
+    Skipped,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Grace => "grace",
+            Self::Failure => "failure",
+            Self::Error => "error",
+            Self::Password => "password",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// Where an authentication came from, captured when it starts.
+pub(crate) struct Context {
+    service: Option<String>,
+    user: String,
+    uid: Option<libc::uid_t>,
+    tty: Option<String>,
+    rhost: Option<String>,
+    started: Instant,
+    audit: bool,
+}
+
+impl Context {
+    pub(crate) fn new(
+        pamh: *mut libc::c_void,
+        service: Option<String>,
+        user: &crate::PamUser,
+        started: Instant,
+        audit: bool,
+    ) -> Self {
+        Self {
+            service,
+            user: user.name.clone(),
+            uid: lookup_uid(&user.raw),
+            tty: get_item_str(pamh, PAM_TTY),
+            rhost: get_item_str(pamh, PAM_RHOST),
+            started,
+            audit,
+        }
+    }
+
+    /// Log the decision at `priority`: `lead` for people, then the fields.
+    pub(crate) fn log(
+        &self,
+        priority: libc::c_int,
+        lead: &str,
+        outcome: Outcome,
+        reason: Option<&str>,
+    ) {
+        let fields = self.fields(outcome, reason, self.started.elapsed().as_millis() as u64);
+        syslog_msg(priority, &format!("{lead}: {fields}"));
+        if self.audit {
+            syslog_msg(LOG_AUTH | LOG_NOTICE, &format!("audit: {fields}"));
+        }
+    }
+
+    fn fields(&self, outcome: Outcome, reason: Option<&str>, latency_ms: u64) -> String {
+        let uid = self.uid.map(|uid| uid.to_string());
+        let latency_ms = latency_ms.to_string();
+        [
+            ("service", self.service.as_deref()),
+            ("user", Some(self.user.as_str())),
+            ("uid", uid.as_deref()),
+            ("tty", self.tty.as_deref()),
+            ("rhost", self.rhost.as_deref()),
+            ("result", Some(outcome.as_str())),
+            ("reason", reason),
+            ("latency_ms", Some(latency_ms.as_str())),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{key}={}", value.map_or(Cow::Borrowed("?"), quote)))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// `value` bare if it is safe to split on spaces, otherwise double-quoted
+/// with quotes, backslashes, and control characters escaped, so a value can
+/// never end the field or the line early.
+fn quote(value: &str) -> Cow<'_, str> {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_./:@+-".contains(c));
+    if bare {
+        return Cow::Borrowed(value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_parseable() {
+        let context = Context {
+            service: Some("sudo".to_string()),
+            user: "alice".to_string(),
+            uid: Some(1000),
+            tty: Some("/dev/pts/2".to_string()),
+            rhost: None,
+            started: Instant::now(),
+            audit: false,
+        };
+        assert_eq!(
+            context.fields(Outcome::Failure, Some("too_dark"), 812),
+            "service=sudo user=alice uid=1000 tty=/dev/pts/2 rhost=? result=failure \
+             reason=too_dark latency_ms=812"
+        );
+    }
+
+    #[test]
+    fn awkward_values_are_quoted() {
+        assert_eq!(quote("gdm-password"), "gdm-password");
+        assert_eq!(quote(":0"), ":0");
+        assert_eq!(quote(""), "\"\"");
+        assert_eq!(quote("a b"), "\"a b\"");
+        assert_eq!(quote(r#"x" y=1"#), r#""x\" y=1""#);
+        assert_eq!(quote(r"jos\xe9"), r#""jos\\xe9""#);
+        assert_eq!(quote("a\nb"), r#""a\x0ab""#);
+    }
+}
@@ -0,0 +1,158 @@
+//! Why a daemon call failed, by D-Bus error name.
+//!
+//! `visaged` fails a call with an error named after its kind
+//! (`org.freedesktop.Visage1.Error.TooDark`, `.CameraBusy`, `.NotEnrolled`,
+//! `.RateLimited`, ...; the table is in `docs/architecture.md`), and the bus
+//! itself with the standard `org.freedesktop.DBus.Error` names. The module
+//! decides what to show and which `on_error=` class applies from the name
+//! alone; the message is only logged.
+//!
+//! Faults found without a reply get the name a D-Bus client would have seen:
+//! a daemon that is neither running nor activatable — or a bus or socket that
+//! cannot be connected to at all — is `ServiceUnknown`, and a call or probe
+//! that ran out of time is `NoReply`.
+
+use std::fmt;
+
+pub(crate) const SERVICE_UNKNOWN: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
+pub(crate) const NO_REPLY: &str = "org.freedesktop.DBus.Error.NoReply";
+
+/// A failed verify call.
+#[derive(Debug)]
+pub(crate) enum CallError {
+    /// The `device=` camera does not exist; found before calling the daemon.
+    NoCamera(String),
+    /// The bus or the daemon answered with the D-Bus error `name`.
+    Named { name: String, message: String },
+    /// Anything else: no bus, a broken socket, a bug.
+    Other(String),
+}
+
+impl CallError {
+    pub(crate) fn named(name: &str, message: impl Into<String>) -> Self {
+        Self::Named {
+            name: name.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// The daemon could not be connected to: no system bus, or nothing
+    /// listening on the socket. Named like a daemon that is not running, so
+    /// `on_error=unreachable:` applies.
+    pub(crate) fn unreachable(e: impl fmt::Display) -> Self {
+        Self::named(SERVICE_UNKNOWN, e.to_string())
+    }
+
+    /// The D-Bus error name, if the failure has one.
+    pub(crate) fn name(&self) -> Option<&str> {
+        match self {
+            Self::Named { name, .. } => Some(name),
+            Self::NoCamera(_) | Self::Other(_) => None,
+        }
+    }
+
+    /// Parse an error the daemon sent as text, `NAME: message`, as its socket
+    /// transport does. Text without a name is [`CallError::Other`].
+    pub(crate) fn from_text(text: &str) -> Self {
+        match text.split_once(": ") {
+            Some((name, message)) if is_error_name(name) => Self::named(name, message),
+            _ => Self::Other(text.to_string()),
+        }
+    }
+}
+
+/// Whether `name` looks like a D-Bus error name: at least two dot-separated
+/// elements of ASCII letters, digits and underscores.
+fn is_error_name(name: &str) -> bool {
+    name.contains('.')
+        && name.split('.').all(|element| {
+            !element.is_empty()
+                && !element.starts_with(|c: char| c.is_ascii_digit())
+                && element
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCamera(device) => write!(f, "camera {device} not present"),
+            Self::Named { name, message } => write!(f, "{name}: {message}"),
+            Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl From<zbus::Error> for CallError {
+    fn from(e: zbus::Error) -> Self {
+        match e {
+            zbus::Error::MethodError(name, message, _) => {
+                Self::named(name.as_str(), message.unwrap_or_default())
+            }
+            zbus::Error::FDO(e) => (*e).into(),
+            zbus::Error::InputOutput(ref io) if is_timeout(io) => {
+                Self::named(NO_REPLY, e.to_string())
+            }
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for CallError {
+    fn from(e: zbus::fdo::Error) -> Self {
+        use zbus::DBusError;
+        match e {
+            zbus::fdo::Error::ZBus(e) => e.into(),
+            e => Self::named(e.name().as_str(), e.description().unwrap_or_default()),
+        }
+    }
+}
+
+impl From<zbus::names::Error> for CallError {
+    fn from(e: zbus::names::Error) -> Self {
+        Self::Other(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for CallError {
+    fn from(e: std::io::Error) -> Self {
+        if is_timeout(&e) {
+            Self::named(NO_REPLY, e.to_string())
+        } else {
+            Self::Other(e.to_string())
+        }
+    }
+}
+
+/// A socket read timeout is `WouldBlock` on Linux.
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_come_from_replies_and_text() {
+        let fdo = CallError::from(zbus::fdo::Error::ServiceUnknown("gone".into()));
+        assert_eq!(fdo.name(), Some(SERVICE_UNKNOWN));
+        let text = CallError::from_text("org.freedesktop.Visage1.Error.TooDark: no usable frames");
+        assert_eq!(text.name(), Some("org.freedesktop.Visage1.Error.TooDark"));
+        assert_eq!(
+            text.to_string(),
+            "org.freedesktop.Visage1.Error.TooDark: no usable frames"
+        );
+        assert_eq!(CallError::from_text("rate limited: wait").name(), None);
+        assert_eq!(CallError::from_text("malformed request").name(), None);
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert_eq!(CallError::from(timeout).name(), Some(NO_REPLY));
+        assert_eq!(CallError::NoCamera("/dev/video2".into()).name(), None);
+    }
+}
@@ -0,0 +1,80 @@
+//! `/etc/security/pam_visage.conf` — module options shared by every stack.
+//!
+//! Distributions ship several pam.d files (sudo, the display manager, the
+//! screen locker); tuning `timeout` or `grace` used to mean editing each.
+//! The config file takes the same options, one per line, in the style of
+//! `faillock.conf`:
+//!
+//! ```text
+//! # /etc/security/pam_visage.conf
+//! timeout = 5
+//! grace = 120
+//! deny_services = su,polkit-1
+//! quiet
+//! ```
+//!
+//! Arguments on a pam.d line are applied after the file, so they win for
+//! options that take a value. A flag (`strict`, `quiet`, …) set in the file
+//! applies to every stack. Like a user list, the file must be a regular file
+//! owned by root and not writable by group or others; a file that fails
+//! that check or cannot be read makes the module skip face auth rather than
+//! run with half its policy. A missing file is the same as an empty one.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+pub(crate) const CONFIG_PATH: &str = "/etc/security/pam_visage.conf";
+
+/// The options in the config file at `path`, as pam.d-style arguments.
+pub(crate) fn read(path: &Path) -> Result<Vec<String>, String> {
+    let display = path.display();
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{display}: {e}")),
+    };
+    if !meta.file_type().is_file() {
+        return Err(format!("{display}: not a regular file"));
+    }
+    if meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+        return Err(format!(
+            "{display}: must be owned by root and not group/world-writable"
+        ));
+    }
+    let text = fs::read_to_string(path).map_err(|e| format!("{display}: {e}"))?;
+    Ok(parse(&text))
+}
+
+/// One argument per non-empty line; `#` starts a comment, and spaces around
+/// `=` are dropped.
+fn parse(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once('=') {
+            Some((key, value)) => format!("{}={}", key.trim(), value.trim()),
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_become_arguments() {
+        let text = "# shared settings\n\ntimeout = 5\ngrace=120  # sudo\n  quiet\n";
+        assert_eq!(parse(text), ["timeout=5", "grace=120", "quiet"]);
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        assert_eq!(
+            read(Path::new("/nonexistent/pam_visage.conf")),
+            Ok(Vec::new())
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! `min_interval=SECONDS` — don't restart the camera right after a failure.
+//!
+//! Some screen lockers call `pam_authenticate` again on every keypress, and
+//! each call would start a new capture. After a verify for a user fails, the
+//! next calls for that user within the window return `PAM_IGNORE` without
+//! contacting the daemon, so the password module answers at once. A match
+//! clears the record.
+//!
+//! The failure is kept in the process, which covers lockers that stay
+//! resident, and, when the module runs as root, in
+//! `/run/pam_visage/<uid>.failed` for services that start a fresh process per
+//! attempt (`sudo`, `login`). Timestamps are `CLOCK_BOOTTIME` seconds, like
+//! the `grace` tokens, and the file is only trusted under the same
+//! root-owned, private directory.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::grace::{boottime_secs, dir_is_trusted, GRACE_DIR};
+use crate::lookup_uid;
+
+/// Last failed verify per uid, in boottime seconds, for this process.
+static FAILURES: Mutex<Option<HashMap<libc::uid_t, u64>>> = Mutex::new(None);
+
+fn failure_path(uid: libc::uid_t) -> PathBuf {
+    Path::new(GRACE_DIR).join(format!("{uid}.failed"))
+}
+
+/// Returns `true` if a verify for `username` failed within `interval_secs`.
+pub(crate) fn failed_recently(username: &[u8], interval_secs: u64) -> bool {
+    let (Some(uid), Some(now)) = (lookup_uid(username), boottime_secs()) else {
+        return false;
+    };
+    let in_process = FAILURES
+        .lock()
+        .ok()
+        .and_then(|failures| failures.as_ref()?.get(&uid).copied());
+    in_process
+        .or_else(|| read_failure(uid))
+        .is_some_and(|failed| is_recent(failed, now, interval_secs))
+}
+
+/// Record a failed verify for `username`. Best-effort.
+pub(crate) fn record_failure(username: &[u8]) {
+    let (Some(uid), Some(now)) = (lookup_uid(username), boottime_secs()) else {
+        return;
+    };
+    if let Ok(mut failures) = FAILURES.lock() {
+        failures.get_or_insert_with(HashMap::new).insert(uid, now);
+    }
+    // SAFETY: geteuid has no preconditions.
+    if unsafe { libc::geteuid() } != 0 {
+        return;
+    }
+    let dir = Path::new(GRACE_DIR);
+    if !dir.exists() {
+        let _ = fs::DirBuilder::new().mode(0o700).create(dir);
+    }
+    if !dir_is_trusted(dir) {
+        return;
+    }
+    let path = failure_path(uid);
+    let _ = fs::remove_file(&path);
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path);
+    if let Ok(mut file) = file {
+        let _ = writeln!(file, "{now}");
+    }
+}
+
+/// Forget a failure for `username` after a match.
+pub(crate) fn clear(username: &[u8]) {
+    let Some(uid) = lookup_uid(username) else {
+        return;
+    };
+    if let Ok(mut failures) = FAILURES.lock() {
+        if let Some(failures) = failures.as_mut() {
+            failures.remove(&uid);
+        }
+    }
+    if dir_is_trusted(Path::new(GRACE_DIR)) {
+        let _ = fs::remove_file(failure_path(uid));
+    }
+}
+
+/// The failure time in the root-owned record file for `uid`, if trusted.
+fn read_failure(uid: libc::uid_t) -> Option<u64> {
+    if !dir_is_trusted(Path::new(GRACE_DIR)) {
+        return None;
+    }
+    let path = failure_path(uid);
+    let meta = fs::symlink_metadata(&path).ok()?;
+    if !meta.file_type().is_file() || meta.uid() != 0 {
+        return None;
+    }
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether a failure at `failed` is less than `interval_secs` before `now`.
+fn is_recent(failed: u64, now: u64, interval_secs: u64) -> bool {
+    failed <= now && now - failed < interval_secs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_expire_after_the_interval() {
+        assert!(is_recent(1000, 1000, 10));
+        assert!(is_recent(1000, 1009, 10));
+        assert!(!is_recent(1000, 1010, 10), "expired");
+        assert!(!is_recent(1000, 999, 10), "recorded in the future");
+        assert!(!is_recent(1000, 1000, 0), "disabled");
+    }
+}
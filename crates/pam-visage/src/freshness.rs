@@ -0,0 +1,138 @@
+//! `max_age=DAYS` in the account stack — flag stale face enrollments.
+//!
+//! Faces change: an enrollment from years ago matches less reliably, and a
+//! policy may require periodic re-enrollment. `pam_sm_acct_mgmt` asks the
+//! daemon how old the user's newest face model is (`EnrollmentAge`) and, past
+//! `max_age` days, tells the user to re-enroll and logs a warning. With
+//! `max_age_action=expire` it also returns `PAM_NEW_AUTHTOK_REQD`, the result
+//! an expired password gives, for the pam.d control syntax to act on.
+//!
+//! Users without an enrollment, an unreachable daemon, and any error are
+//! `PAM_IGNORE`: the account stack is never decided by this module otherwise.
+
+use crate::args::{PamArgs, StaleAction};
+use crate::{
+    get_item_str, get_user, send_text_info, syslog_msg, syslog_open, users, VisageProxy, LOG_DEBUG,
+    LOG_WARNING, PAM_IGNORE, PAM_NEW_AUTHTOK_REQD, PAM_SERVICE,
+};
+
+/// D-Bus timeout for the age query; the account stack runs on every login.
+const AGE_QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Body of `pam_sm_acct_mgmt`.
+pub(crate) fn account_hook(pamh: *mut libc::c_void, raw_args: &[String]) -> libc::c_int {
+    syslog_open();
+    let (args, warnings) = match PamArgs::load(raw_args) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            syslog_msg(LOG_WARNING, &format!("config file rejected: {}", e));
+            return PAM_IGNORE;
+        }
+    };
+    for warning in &warnings {
+        syslog_msg(LOG_WARNING, warning);
+    }
+    let Some(max_age_days) = args.max_age_days else {
+        return PAM_IGNORE;
+    };
+    let debug = |msg: &str| {
+        if args.debug {
+            syslog_msg(LOG_DEBUG, msg);
+        }
+    };
+
+    // Only nag users who may use face auth through this service.
+    if !args.allows_service(get_item_str(pamh, PAM_SERVICE).as_deref()) {
+        return PAM_IGNORE;
+    }
+    let username = match get_user(pamh) {
+        Ok(user) => user.name,
+        Err((priority, msg)) => {
+            syslog_msg(priority, &msg);
+            return PAM_IGNORE;
+        }
+    };
+    if !matches!(users::allows_user(&args, &username), Ok(true)) {
+        return PAM_IGNORE;
+    }
+
+    let age_secs = match enrollment_age(&username) {
+        Ok(Some(age_secs)) => age_secs,
+        Ok(None) => {
+            debug(&format!("user '{}' has no face enrollment", username));
+            return PAM_IGNORE;
+        }
+        Err(e) => {
+            debug(&format!("enrollment age query failed: {}", e));
+            return PAM_IGNORE;
+        }
+    };
+    let Some(days) = stale_days(age_secs, max_age_days) else {
+        debug(&format!(
+            "face enrollment for user '{}' is {} days old",
+            username,
+            age_secs / SECS_PER_DAY
+        ));
+        return PAM_IGNORE;
+    };
+
+    syslog_msg(
+        LOG_WARNING,
+        &format!(
+            "face enrollment for user '{}' is {} days old (max_age={})",
+            username, days, max_age_days
+        ),
+    );
+    if !args.quiet {
+        send_text_info(
+            pamh,
+            &format!("Visage: your face enrollment is {days} days old — please re-enroll"),
+        );
+    }
+    match args.max_age_action {
+        StaleAction::Warn => PAM_IGNORE,
+        StaleAction::Expire => PAM_NEW_AUTHTOK_REQD,
+    }
+}
+
+/// Whole days since enrollment when `age_secs` is past `max_age_days`.
+fn stale_days(age_secs: u64, max_age_days: u64) -> Option<u64> {
+    (age_secs > max_age_days.saturating_mul(SECS_PER_DAY)).then_some(age_secs / SECS_PER_DAY)
+}
+
+/// Seconds since `username`'s newest face model was enrolled; `None` when
+/// they have none.
+fn enrollment_age(username: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let conn = zbus::connection::Builder::system()?
+            .method_timeout(AGE_QUERY_TIMEOUT)
+            .build()
+            .await?;
+        let proxy = VisageProxy::new(&conn).await?;
+        let (enrolled, age_secs) = proxy.enrollment_age(username).await?;
+        Ok(enrolled.then_some(age_secs))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_only_past_the_limit() {
+        assert_eq!(stale_days(0, 1), None);
+        assert_eq!(
+            stale_days(SECS_PER_DAY, 1),
+            None,
+            "exactly max_age is fresh"
+        );
+        assert_eq!(stale_days(SECS_PER_DAY + 1, 1), Some(1));
+        assert_eq!(stale_days(400 * SECS_PER_DAY, 180), Some(400));
+        assert_eq!(stale_days(u64::MAX, u64::MAX), None);
+    }
+}
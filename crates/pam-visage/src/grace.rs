@@ -0,0 +1,171 @@
+//! `grace=SECONDS` — skip the camera for a recently verified session.
+//!
+//! After a face match the module writes a token to
+//! `/run/pam_visage/<uid>-<sid>`, keyed by the target user and the caller's
+//! session ID (the login shell's session, for `sudo`). A later call from the
+//! same session within the window succeeds without contacting the daemon.
+//!
+//! The token records the session leader's start time, so a recycled session
+//! ID never inherits someone else's grace, and a `CLOCK_BOOTTIME` timestamp,
+//! so changing the wall clock cannot extend the window. Only root can write
+//! the directory; if it is missing, foreign-owned or group/world accessible,
+//! caching is silently disabled.
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use crate::lookup_uid;
+
+/// Token directory. tmpfs, so tokens never survive a reboot.
+pub(crate) const GRACE_DIR: &str = "/run/pam_visage";
+
+/// Identity of the caller's session: target uid, session ID, and the session
+/// leader's start time (in clock ticks since boot).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SessionKey {
+    uid: libc::uid_t,
+    sid: libc::pid_t,
+    leader_start: u64,
+}
+
+impl SessionKey {
+    fn current(username: &[u8]) -> Option<Self> {
+        let uid = lookup_uid(username)?;
+        // SAFETY: getsid(0) only reads the calling process's session ID.
+        let sid = unsafe { libc::getsid(0) };
+        if sid <= 0 {
+            return None;
+        }
+        let stat = fs::read_to_string(format!("/proc/{sid}/stat")).ok()?;
+        Some(Self {
+            uid,
+            sid,
+            leader_start: parse_start_time(&stat)?,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        Path::new(GRACE_DIR).join(format!("{}-{}", self.uid, self.sid))
+    }
+}
+
+/// Returns `true` if `username` was verified in this session within `grace_secs`.
+pub(crate) fn is_fresh(username: &[u8], grace_secs: u64) -> bool {
+    let (Some(key), Some(now)) = (SessionKey::current(username), boottime_secs()) else {
+        return false;
+    };
+    if !dir_is_trusted(Path::new(GRACE_DIR)) {
+        return false;
+    }
+    let path = key.path();
+    let Ok(meta) = fs::symlink_metadata(&path) else {
+        return false;
+    };
+    if !meta.file_type().is_file() || meta.uid() != 0 {
+        return false;
+    }
+    fs::read_to_string(&path)
+        .map(|content| token_is_fresh(&content, key.leader_start, now, grace_secs))
+        .unwrap_or(false)
+}
+
+/// Record a successful face match for `username` in this session.
+/// Best-effort: any failure just means the next call uses the camera.
+pub(crate) fn record(username: &[u8]) {
+    let (Some(key), Some(now)) = (SessionKey::current(username), boottime_secs()) else {
+        return;
+    };
+    let dir = Path::new(GRACE_DIR);
+    if !dir.exists() {
+        let _ = fs::DirBuilder::new().mode(0o700).create(dir);
+    }
+    if !dir_is_trusted(dir) {
+        return;
+    }
+    let path = key.path();
+    let _ = fs::remove_file(&path);
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path);
+    if let Ok(mut file) = file {
+        let _ = file.write_all(token_content(key.leader_start, now).as_bytes());
+    }
+}
+
+/// Token body: `<leader start time> <boottime seconds>`.
+fn token_content(leader_start: u64, now: u64) -> String {
+    format!("{leader_start} {now}\n")
+}
+
+/// Whether a token body belongs to this session and is younger than `grace_secs`.
+fn token_is_fresh(content: &str, leader_start: u64, now: u64, grace_secs: u64) -> bool {
+    let mut fields = content.split_whitespace().map(str::parse::<u64>);
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(Ok(start)), Some(Ok(written)), None) => {
+            start == leader_start && written <= now && now - written <= grace_secs
+        }
+        _ => false,
+    }
+}
+
+/// Field 22 (`starttime`) of `/proc/<pid>/stat`. The command name in field 2
+/// may contain spaces and parentheses, so parsing starts after the last `)`.
+fn parse_start_time(stat: &str) -> Option<u64> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // `rest` begins at field 3 (state); starttime is field 22.
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// The directory must be a real directory owned by root with no group or
+/// other access, or a local user could plant tokens.
+pub(crate) fn dir_is_trusted(dir: &Path) -> bool {
+    fs::symlink_metadata(dir)
+        .map(|m| m.file_type().is_dir() && m.uid() == 0 && m.mode() & 0o077 == 0)
+        .unwrap_or(false)
+}
+
+/// Seconds on `CLOCK_BOOTTIME` — monotonic and unaffected by clock changes.
+pub(crate) fn boottime_secs() -> Option<u64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: ts is a valid, writable timespec.
+    if unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) } != 0 {
+        return None;
+    }
+    u64::try_from(ts.tv_sec).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_freshness() {
+        let token = token_content(4242, 1000);
+        assert!(token_is_fresh(&token, 4242, 1000, 60));
+        assert!(token_is_fresh(&token, 4242, 1060, 60));
+        assert!(!token_is_fresh(&token, 4242, 1061, 60), "expired");
+        assert!(!token_is_fresh(&token, 4243, 1010, 60), "recycled session");
+        assert!(
+            !token_is_fresh(&token, 4242, 999, 60),
+            "written in the future"
+        );
+        assert!(!token_is_fresh("garbage", 4242, 1000, 60));
+        assert!(!token_is_fresh("4242 1000 7", 4242, 1000, 60));
+    }
+
+    #[test]
+    fn start_time_survives_odd_command_names() {
+        let stat =
+            "1234 (sh) (x) S 1 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 987654 1000 200";
+        assert_eq!(parse_start_time(stat), Some(987654));
+        assert_eq!(parse_start_time("1234 (sh"), None);
+    }
+}
@@ -0,0 +1,55 @@
+//! `authtok` — unlock the login keyring after a face match.
+//!
+//! gnome-keyring and kwallet take the login password from `PAM_AUTHTOK`. With
+//! `authtok`, the verify asks the daemon for a release ticket
+//! (`release_authtok`), and after a match the module fetches the password the
+//! user stored with `visage authtok set` (`ReleaseAuthtok`) and sets it as
+//! `PAM_AUTHTOK`. The keyring module later in the stack then unlocks the
+//! keyring as if the password had been typed:
+//!
+//! ```text
+//! auth  [success=1 default=ignore]  pam_visage.so authtok
+//! auth  required                    pam_unix.so
+//! auth  optional                    pam_gnome_keyring.so
+//! ```
+//!
+//! Failing to fetch the password never fails the login; the keyring just
+//! stays locked.
+
+use std::ffi::CString;
+use std::time::Duration;
+
+use crate::{pam_set_item, VisageProxy, PAM_AUTHTOK, PAM_SUCCESS};
+
+/// D-Bus timeout for the release call; the ticket is only seconds old.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Fetch `username`'s stored password and set it as `PAM_AUTHTOK`.
+pub(crate) fn release(pamh: *mut libc::c_void, username: &str) -> Result<(), String> {
+    let secret = fetch(username).map_err(|e| e.to_string())?;
+    let secret = CString::new(secret).map_err(|_| "stored secret contains a NUL byte")?;
+    // SAFETY: `pamh` is the handle PAM passed in; PAM copies the string.
+    let rc = unsafe { pam_set_item(pamh, PAM_AUTHTOK, secret.as_ptr().cast()) };
+    // Don't leave the password in freed memory.
+    let mut bytes = secret.into_bytes();
+    bytes.fill(0);
+    if rc == PAM_SUCCESS {
+        Ok(())
+    } else {
+        Err(format!("pam_set_item(PAM_AUTHTOK) failed: {rc}"))
+    }
+}
+
+fn fetch(username: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let conn = zbus::connection::Builder::system()?
+            .method_timeout(RELEASE_TIMEOUT)
+            .build()
+            .await?;
+        let proxy = VisageProxy::new(&conn).await?;
+        Ok(proxy.release_authtok(username).await?)
+    })
+}
@@ -1,6 +1,7 @@
 //! pam_visage — PAM module for Visage biometric authentication.
 //!
-//! Thin D-Bus client that calls visaged over the system bus.
+//! Thin D-Bus client that calls visaged over the system bus (or, with
+//! `socket=`, over the daemon's Unix socket when the bus is unavailable).
 //! The PAM module never owns the camera or runs inference directly.
 //!
 //! # Safety
@@ -9,31 +10,71 @@
 //! `extern "C"` boundary is undefined behavior.
 //!
 //! Every error path returns `PAM_IGNORE` (25), which tells the PAM stack to
-//! skip this module and continue to the next (e.g., password). We never return
-//! `PAM_AUTH_ERR` to avoid locking the user out if the daemon is unavailable.
+//! skip this module and continue to the next (e.g., password). `PAM_AUTH_ERR`
+//! is returned only with the `strict` argument and only for a definitive
+//! non-match (see [`is_definitive_rejection`]) — never when the daemon is
+//! unavailable, so a dead daemon cannot lock the user out. A site can choose
+//! otherwise per failure class with `on_error=` (see the `policy` module).
+//! The exception is `second_factor`, where the face is required and every
+//! failure is `PAM_AUTH_ERR` (see the `second_factor` module).
 
 // Enforce explicit `unsafe {}` blocks inside `unsafe fn` bodies — catches
 // the Rust 2024 edition change before it lands.
 #![warn(unsafe_op_in_unsafe_fn)]
 
+mod args;
+mod audit;
+mod call_error;
+mod conf;
+mod debounce;
+mod freshness;
+mod grace;
+mod keyring;
+mod lid;
+mod policy;
+mod race;
+mod second_factor;
+mod session;
+mod socket;
+mod tty;
+mod users;
+
+use args::PamArgs;
+use call_error::CallError;
+use policy::ErrorClass;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
+use std::future::{poll_fn, Future};
 use std::panic;
+use std::pin::{pin, Pin};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Poll;
+use zbus::export::futures_core::Stream;
 
 // PAM return codes (POSIX / Linux-PAM values)
 const PAM_SUCCESS: libc::c_int = 0;
+const PAM_PERM_DENIED: libc::c_int = 6;
+const PAM_AUTH_ERR: libc::c_int = 7;
+const PAM_AUTHINFO_UNAVAIL: libc::c_int = 9;
+const PAM_NEW_AUTHTOK_REQD: libc::c_int = 12;
 const PAM_IGNORE: libc::c_int = 25;
 
 // PAM item types
+const PAM_SERVICE: libc::c_int = 1;
 const PAM_CONV: libc::c_int = 5;
+const PAM_AUTHTOK: libc::c_int = 6;
 
 // PAM message styles
+const PAM_PROMPT_ECHO_OFF: libc::c_int = 1;
+const PAM_ERROR_MSG: libc::c_int = 3;
 const PAM_TEXT_INFO: libc::c_int = 4;
 
 // syslog constants
 const LOG_PID: libc::c_int = 0x01;
 const LOG_AUTHPRIV: libc::c_int = 10 << 3;
 const LOG_INFO: libc::c_int = 6;
+const LOG_DEBUG: libc::c_int = 7;
 const LOG_WARNING: libc::c_int = 4;
 const LOG_ERR: libc::c_int = 3;
 
@@ -49,6 +90,12 @@ extern "C" {
         item_type: libc::c_int,
         item: *mut *const libc::c_void,
     ) -> libc::c_int;
+
+    fn pam_set_item(
+        pamh: *mut libc::c_void,
+        item_type: libc::c_int,
+        item: *const libc::c_void,
+    ) -> libc::c_int;
 }
 
 /// PAM message struct — mirrors `struct pam_message` from <security/pam_appl.h>.
@@ -65,29 +112,94 @@ struct PamResponse {
     resp_retcode: libc::c_int,
 }
 
+/// Signature of the application's conversation callback. `C-unwind`, since
+/// `race` mode cancels a prompt thread blocked inside it (see the `race`
+/// module).
+type ConvFn = unsafe extern "C-unwind" fn(
+    num_msg: libc::c_int,
+    msg: *mut *const PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut libc::c_void,
+) -> libc::c_int;
+
 /// PAM conversation struct — mirrors `struct pam_conv` from <security/pam_appl.h>.
 #[repr(C)]
 struct PamConv {
-    conv: Option<
-        unsafe extern "C" fn(
-            num_msg: libc::c_int,
-            msg: *mut *const PamMessage,
-            resp: *mut *mut PamResponse,
-            appdata_ptr: *mut libc::c_void,
-        ) -> libc::c_int,
-    >,
+    conv: Option<ConvFn>,
     appdata_ptr: *mut libc::c_void,
 }
 
-// D-Bus proxy — `#[zbus::proxy]` generates both `VisageProxy` (async) and
-// `VisageProxyBlocking` (synchronous). Only the blocking variant is used here.
+/// Look up the application's conversation callback and its `appdata_ptr`.
+fn conversation(pamh: *mut libc::c_void) -> Option<(ConvFn, *mut libc::c_void)> {
+    let mut conv_ptr: *const libc::c_void = ptr::null();
+    // SAFETY: pamh is a valid PAM handle. pam_get_item reads the conversation struct.
+    let ret = unsafe { pam_get_item(pamh, PAM_CONV, &mut conv_ptr) };
+    if ret != PAM_SUCCESS || conv_ptr.is_null() {
+        return None;
+    }
+    // SAFETY: pam_get_item with PAM_CONV returns a pointer to a pam_conv struct.
+    let conv = unsafe { &*(conv_ptr as *const PamConv) };
+    conv.conv.map(|f| (f, conv.appdata_ptr))
+}
+
+/// Read a string item (`PAM_SERVICE`, `PAM_TTY`, `PAM_RHOST`, ...) from the PAM handle.
+fn get_item_str(pamh: *mut libc::c_void, item_type: libc::c_int) -> Option<String> {
+    let mut item: *const libc::c_void = ptr::null();
+    // SAFETY: pamh is a valid PAM handle; the requested item is a C string.
+    let ret = unsafe { pam_get_item(pamh, item_type, &mut item) };
+    if ret != PAM_SUCCESS || item.is_null() {
+        return None;
+    }
+    // SAFETY: non-null string items are NUL-terminated and owned by PAM.
+    let value = unsafe { CStr::from_ptr(item.cast()) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+/// Well-known bus name of `visaged`.
+const VISAGE_BUS_NAME: &str = "org.freedesktop.Visage1";
+
+/// Budget for [`probe_daemon`]. A healthy bus answers in well under a
+/// millisecond; anything slower is not worth a login delay.
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(80);
+
+/// Method timeout for [`cancel_verify`].
+const CANCEL_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Method timeout for [`notify_session`]; a login must not wait on the daemon.
+const SESSION_NOTIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+// D-Bus proxy — only the async `VisageProxy` is generated; see `verify_face`.
 #[zbus::proxy(
+    gen_blocking = false,
     interface = "org.freedesktop.Visage1",
     default_service = "org.freedesktop.Visage1",
     default_path = "/org/freedesktop/Visage1"
 )]
 trait Visage {
-    async fn verify(&self, user: &str) -> zbus::Result<bool>;
+    async fn verify_with_options(
+        &self,
+        user: &str,
+        options: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<(bool, String)>;
+
+    async fn verify_bytes(
+        &self,
+        user: &[u8],
+        options: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<(bool, String)>;
+
+    async fn enrollment_age(&self, user: &str) -> zbus::Result<(bool, u64)>;
+
+    async fn cancel(&self, cookie: &str) -> zbus::Result<bool>;
+
+    async fn release_authtok(&self, user: &str) -> zbus::Result<String>;
+
+    async fn session_opened(&self, user: &str) -> zbus::Result<()>;
+
+    async fn session_closed(&self, user: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn verify_progress(&self, user: &str, stage: &str) -> zbus::Result<()>;
 }
 
 /// Open syslog with `pam_visage` ident and `LOG_AUTHPRIV` facility.
@@ -123,27 +235,27 @@ fn syslog_msg(priority: libc::c_int, msg: &str) {
 /// Fails silently if the conversation function is unavailable — this is non-critical
 /// feedback and must never block authentication.
 fn send_text_info(pamh: *mut libc::c_void, text: &str) {
+    send_message(pamh, PAM_TEXT_INFO, text);
+}
+
+/// Send a PAM_ERROR_MSG message — why face auth did not log the user in.
+/// Fails silently like [`send_text_info`].
+fn send_error_msg(pamh: *mut libc::c_void, text: &str) {
+    send_message(pamh, PAM_ERROR_MSG, text);
+}
+
+fn send_message(pamh: *mut libc::c_void, msg_style: libc::c_int, text: &str) {
     let c_text = match CString::new(text) {
         Ok(s) => s,
         Err(_) => return,
     };
 
-    let mut conv_ptr: *const libc::c_void = ptr::null();
-    // SAFETY: pamh is a valid PAM handle. pam_get_item reads the conversation struct.
-    let ret = unsafe { pam_get_item(pamh, PAM_CONV, &mut conv_ptr) };
-    if ret != PAM_SUCCESS || conv_ptr.is_null() {
+    let Some((conv_fn, appdata_ptr)) = conversation(pamh) else {
         return;
-    }
-
-    // SAFETY: pam_get_item with PAM_CONV returns a pointer to a pam_conv struct.
-    let conv = unsafe { &*(conv_ptr as *const PamConv) };
-    let conv_fn = match conv.conv {
-        Some(f) => f,
-        None => return,
     };
 
     let msg = PamMessage {
-        msg_style: PAM_TEXT_INFO,
+        msg_style,
         msg: c_text.as_ptr(),
     };
     let msg_ptr: *const PamMessage = &msg;
@@ -155,9 +267,9 @@ fn send_text_info(pamh: *mut libc::c_void, text: &str) {
             1,
             &msg_ptr as *const _ as *mut _,
             &mut resp_ptr,
-            conv.appdata_ptr,
+            appdata_ptr,
         );
-        // Free response array if allocated. TEXT_INFO rarely gets a response, but the spec
+        // Free response array if allocated. Messages rarely get a response, but the spec
         // requires us to free both the response string and the response struct if present.
         if !resp_ptr.is_null() {
             if !(*resp_ptr).resp.is_null() {
@@ -168,28 +280,455 @@ fn send_text_info(pamh: *mut libc::c_void, text: &str) {
     }
 }
 
-/// Connect to the system bus and call `Visage1.Verify(username)`.
+/// Connect to the system bus and call `Visage1.VerifyWithOptions(username, ..)`,
+/// or `VerifyBytes` for an account name that is not UTF-8.
 ///
-/// Uses a 3-second method timeout to prevent login hangs if the daemon is stuck.
-/// Returns `Ok(false)` if the daemon responds but finds no match.
-/// Returns `Err` if the daemon is not running, the call fails, or times out.
-fn verify_face(username: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    let conn = zbus::blocking::connection::Builder::system()?
-        .method_timeout(std::time::Duration::from_secs(3))
+/// Uses [`PamArgs::dbus_timeout`] (default: `timeout`, 3s) as the method
+/// timeout to prevent login hangs if the daemon is stuck, and passes
+/// `args.timeout_secs` to the daemon as its capture deadline. `args.device`,
+/// if set, is forwarded too.
+/// First runs [`probe_daemon`], so a missing daemon or camera costs
+/// milliseconds rather than the method timeout.
+/// While the call is in flight, each `VerifyProgress` stage the daemon sends
+/// this connection is passed to `on_progress`. With a `cookie`, the verify
+/// can be stopped from another thread with [`cancel_verify`]. If the system
+/// bus cannot be reached and `args.socket` is set, the verify goes over the
+/// daemon's Unix socket instead, without progress, and `cookie` records it.
+/// Returns `Ok((false, reason))` if the daemon responds but finds no match, where
+/// `reason` is the daemon's failure code (see [`failure_message`]).
+/// Returns `Err` if the daemon is not running, the call fails, or times out,
+/// named as described in [`call_error`].
+fn verify_face(
+    user: &PamUser,
+    args: &PamArgs,
+    cookie: Option<&Cookie>,
+    on_progress: &mut dyn FnMut(&str),
+) -> Result<(bool, String), CallError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
         .build()?;
-    let proxy = VisageProxyBlocking::new(&conn)?;
-    let matched = proxy.verify(username)?;
-    Ok(matched)
+    runtime.block_on(async {
+        let conn = match zbus::connection::Builder::system() {
+            Ok(builder) => builder.method_timeout(args.dbus_timeout()).build().await,
+            Err(e) => Err(e),
+        };
+        let over_socket = conn.is_err() && args.socket.is_some();
+        if let Some(cookie) = cookie {
+            cookie.over_socket.store(over_socket, Ordering::Release);
+        }
+        let conn = match (conn, &args.socket) {
+            (Ok(conn), _) => conn,
+            (Err(e), Some(path)) => {
+                syslog_msg(
+                    LOG_DEBUG,
+                    &format!("system bus unavailable ({e}); using {path}"),
+                );
+                return socket::verify(
+                    path,
+                    &user.raw,
+                    args.timeout_secs,
+                    args.second_factor,
+                    cookie.map(|c| c.value.as_str()),
+                );
+            }
+            (Err(e), None) => return Err(CallError::unreachable(e)),
+        };
+        tokio::time::timeout(PROBE_TIMEOUT, probe_daemon(&conn, args))
+            .await
+            .map_err(|_| CallError::named(call_error::NO_REPLY, "probe timed out"))??;
+        let proxy = VisageProxy::new(&conn).await?;
+
+        let mut options = HashMap::new();
+        // PamArgs bounds the timeout to 1–60s, so the narrowing is lossless.
+        options.insert(
+            "timeout",
+            zbus::zvariant::Value::from(args.timeout_secs as u32),
+        );
+        if let Some(device) = &args.device {
+            options.insert("device", zbus::zvariant::Value::from(device.as_str()));
+        }
+        if let Some(cookie) = cookie {
+            options.insert("cookie", zbus::zvariant::Value::from(cookie.value.as_str()));
+        }
+        if args.authtok {
+            options.insert("release_authtok", zbus::zvariant::Value::from(true));
+        }
+        if args.second_factor {
+            options.insert("second_factor", zbus::zvariant::Value::from(true));
+        }
+
+        // Subscribe before calling so the first stage cannot be missed.
+        let mut progress = proxy.receive_verify_progress().await?;
+        let mut call = pin!(async {
+            match std::str::from_utf8(&user.raw) {
+                Ok(name) => proxy.verify_with_options(name, options).await,
+                Err(_) => proxy.verify_bytes(&user.raw, options).await,
+            }
+        });
+        let mut progress_open = true;
+        let reply = poll_fn(|cx| {
+            while progress_open {
+                match Pin::new(&mut progress).poll_next(cx) {
+                    Poll::Ready(Some(signal)) => {
+                        if let Ok(signal) = signal.args() {
+                            on_progress(signal.stage());
+                        }
+                    }
+                    Poll::Ready(None) => progress_open = false,
+                    Poll::Pending => break,
+                }
+            }
+            call.as_mut().poll(cx)
+        })
+        .await?;
+        Ok(reply)
+    })
+}
+
+/// Cheap checks that a verify could possibly succeed: the `device=` camera
+/// exists, and `visaged` owns its bus name or can be activated. On a machine
+/// without the daemon or without the camera, every login then falls through
+/// to the password after one bus round-trip, bounded by [`PROBE_TIMEOUT`].
+async fn probe_daemon(conn: &zbus::Connection, args: &PamArgs) -> Result<(), CallError> {
+    if let Some(device) = &args.device {
+        if !std::path::Path::new(device).exists() {
+            return Err(CallError::NoCamera(device.clone()));
+        }
+    }
+    let dbus = zbus::fdo::DBusProxy::new(conn).await?;
+    let name = zbus::names::BusName::try_from(VISAGE_BUS_NAME)?;
+    if dbus.name_has_owner(name).await?
+        || dbus
+            .list_activatable_names()
+            .await?
+            .iter()
+            .any(|n| n.as_str() == VISAGE_BUS_NAME)
+    {
+        Ok(())
+    } else {
+        Err(CallError::named(
+            call_error::SERVICE_UNKNOWN,
+            "visaged is not running",
+        ))
+    }
+}
+
+/// Result of one or more verify attempts: `Ok((matched, reason))` from the
+/// daemon, or why the call failed.
+type FaceOutcome = Result<(bool, String), CallError>;
+
+/// Call [`verify_face`] up to `args.max_tries` times, retrying only a
+/// retryable non-match. Every attempt carries `cookie`, and none starts once
+/// it has been cancelled; `on_progress` sees every daemon progress stage;
+/// `on_retry(attempt, previous_reason)` runs before each retry, which then
+/// waits `args.retry_delay_ms`.
+fn verify_with_retries(
+    user: &PamUser,
+    args: &PamArgs,
+    cookie: Option<&Cookie>,
+    on_progress: &mut dyn FnMut(&str),
+    mut on_retry: impl FnMut(u32, &str),
+) -> FaceOutcome {
+    let mut outcome = verify_face(user, args, cookie, on_progress);
+    for attempt in 2..=args.max_tries {
+        match &outcome {
+            Ok((false, reason)) if is_retryable(reason) => on_retry(attempt, reason),
+            _ => break,
+        }
+        if args.retry_delay_ms > 0 {
+            std::thread::sleep(std::time::Duration::from_millis(args.retry_delay_ms));
+        }
+        if cookie.is_some_and(|c| c.cancelled.load(Ordering::Acquire)) {
+            break;
+        }
+        outcome = verify_face(user, args, cookie, on_progress);
+    }
+    outcome
+}
+
+/// Ask the daemon to stop the verify started with `cookie`, so the camera and
+/// emitter switch off at once, over the transport the verify went over.
+/// Best-effort, with a short timeout: the verify ends on its own deadline
+/// anyway.
+fn cancel_verify(cookie: &Cookie, args: &PamArgs) {
+    cookie.cancelled.store(true, Ordering::Release);
+    let result: Result<bool, Box<dyn std::error::Error>> = (|| {
+        if let (true, Some(path)) = (cookie.over_socket.load(Ordering::Acquire), &args.socket) {
+            return Ok(socket::cancel(path, &cookie.value, CANCEL_TIMEOUT)?);
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async {
+            let conn = zbus::connection::Builder::system()?
+                .method_timeout(CANCEL_TIMEOUT)
+                .build()
+                .await?;
+            Ok(VisageProxy::new(&conn).await?.cancel(&cookie.value).await?)
+        })
+    })();
+    if let Err(e) = result {
+        syslog_msg(LOG_DEBUG, &format!("cancel failed: {}", e));
+    }
+}
+
+/// Tell the daemon a login session for `username` opened (`opened`) or
+/// closed, so it can pre-warm for the first unlock. Best-effort, with a short
+/// timeout.
+fn notify_session(username: &str, opened: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(async {
+        let conn = zbus::connection::Builder::system()?
+            .method_timeout(SESSION_NOTIFY_TIMEOUT)
+            .build()
+            .await?;
+        let proxy = VisageProxy::new(&conn).await?;
+        if opened {
+            proxy.session_opened(username).await?;
+        } else {
+            proxy.session_closed(username).await?;
+        }
+        Ok(())
+    })
+}
+
+/// The PAM user's account name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PamUser {
+    /// Exactly as PAM returned it; need not be UTF-8.
+    raw: Vec<u8>,
+    /// The daemon's name for the account: the name itself, or for a legacy
+    /// name that is not UTF-8, its bytes with non-ASCII escaped as `\xNN`
+    /// (Latin-1 "josé" is `jos\xe9`). Used for
+    /// `allow_users=`/`deny_users=`, logs, and session calls.
+    name: String,
+}
+
+impl PamUser {
+    fn new(raw: &[u8]) -> Self {
+        let name = match std::str::from_utf8(raw) {
+            Ok(name) => name.to_string(),
+            Err(_) => raw.escape_ascii().to_string(),
+        };
+        Self {
+            raw: raw.to_vec(),
+            name,
+        }
+    }
+}
+
+/// The PAM user, or the syslog priority and message to log when there is
+/// none. In the session stack `pam_get_user` returns the authenticated user
+/// without prompting.
+fn get_user(pamh: *mut libc::c_void) -> Result<PamUser, (libc::c_int, String)> {
+    let mut user_ptr: *const libc::c_char = ptr::null();
+    // SAFETY: pamh is a valid PAM handle. pam_get_user writes a pointer
+    // that remains valid for the lifetime of the PAM conversation.
+    let ret = unsafe { pam_get_user(pamh, &mut user_ptr, ptr::null()) };
+    if ret != PAM_SUCCESS || user_ptr.is_null() {
+        return Err((LOG_ERR, format!("pam_get_user failed (ret={})", ret)));
+    }
+    // SAFETY: pam_get_user guarantees the pointer is non-null and points
+    // to a NUL-terminated string that lives for the PAM conversation.
+    Ok(PamUser::new(unsafe { CStr::from_ptr(user_ptr) }.to_bytes()))
+}
+
+/// Resolve an account name (exact bytes) to its uid with the reentrant
+/// `getpwnam_r`.
+pub(crate) fn lookup_uid(username: &[u8]) -> Option<libc::uid_t> {
+    let name = CString::new(username).ok()?;
+    // SAFETY: passwd is a plain C struct; all-zero is a valid initial value.
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    // SAFETY: all pointers are valid for the duration of the call; buf.len() is its size.
+    let rc = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    (rc == 0 && !result.is_null()).then_some(pwd.pw_uid)
+}
+
+/// Names a verify for [`cancel_verify`], which may run on another thread.
+struct Cookie {
+    /// The token sent with the verify. Only the calling UID (or root) can
+    /// cancel with it, so it needs to be unique, not secret.
+    value: String,
+    /// Whether the latest attempt went over `socket=` rather than the bus.
+    over_socket: AtomicBool,
+    /// Set by [`cancel_verify`]; no further attempt starts.
+    cancelled: AtomicBool,
+}
+
+fn new_cookie() -> Cookie {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    Cookie {
+        value: format!("pam-{}-{}", std::process::id(), nanos),
+        over_socket: AtomicBool::new(false),
+        cancelled: AtomicBool::new(false),
+    }
+}
+
+/// Whether a failure code means the daemon saw a face and positively rejected
+/// it — similarity below threshold, a liveness failure, or a burst rejected
+/// as a replayed still. Environmental outcomes (dark, no face, camera error,
+/// timeout) are never definitive.
+fn is_definitive_rejection(reason: &str) -> bool {
+    matches!(
+        reason,
+        "below_threshold" | "liveness_failed" | "static_scene"
+    )
+}
+
+/// Whether another attempt could plausibly succeed. Dark, blurry, no-face and
+/// below-threshold captures are worth retrying; a camera or daemon fault, a
+/// burst rejected as replayed, or a cancelled attempt, is not.
+fn is_retryable(reason: &str) -> bool {
+    !matches!(
+        reason,
+        "camera_error" | "camera_busy" | "internal_error" | "static_scene" | "cancelled"
+    )
+}
+
+/// Prompt shown before retry `attempt` (1-based) of `max_tries`.
+fn attempt_prompt(attempt: u32, max_tries: u32) -> String {
+    format!("Visage: attempt {attempt} of {max_tries} — look at the camera")
+}
+
+/// Collect the module arguments from PAM's `argc`/`argv`.
+///
+/// Arguments that are not valid UTF-8 are skipped.
+///
+/// # Safety
+///
+/// `argv` must point to `argc` valid NUL-terminated strings (or be null).
+unsafe fn collect_args(argc: libc::c_int, argv: *const *const libc::c_char) -> Vec<String> {
+    if argv.is_null() || argc <= 0 {
+        return Vec::new();
+    }
+    (0..argc as usize)
+        .filter_map(|i| {
+            // SAFETY: the caller guarantees argv holds argc valid pointers.
+            let arg = unsafe { *argv.add(i) };
+            if arg.is_null() {
+                return None;
+            }
+            // SAFETY: each non-null argv entry is a NUL-terminated C string.
+            unsafe { CStr::from_ptr(arg) }
+                .to_str()
+                .ok()
+                .map(String::from)
+        })
+        .collect()
+}
+
+/// Map a daemon failure code to the line shown to the user (`PAM_TEXT_INFO`
+/// before a retry, `PAM_ERROR_MSG` after the last attempt).
+///
+/// Unknown codes (a newer daemon) fall back to the generic message rather than
+/// leaking the raw code into a login prompt.
+fn failure_message(reason: &str) -> &'static str {
+    match reason {
+        "too_dark" => "Visage: face unlock unavailable: not enough light",
+        "poor_quality" => "Visage: image unclear, hold still",
+        "no_face" => "Visage: no face detected",
+        "face_too_small" => "Visage: face too far away, move closer",
+        "liveness_failed" => "Visage: liveness check failed",
+        "static_scene" => "Visage: camera feed rejected",
+        "camera_error" => "Visage: camera unavailable",
+        "camera_busy" => "Visage: camera busy — in use by another application",
+        "timeout" => "Visage: timed out",
+        "cancelled" => "Visage: cancelled",
+        _ => "Visage: face not recognized",
+    }
+}
+
+/// Whether a failure code is shown as a hint (`PAM_TEXT_INFO`) rather than an
+/// error: the surroundings stopped the attempt, not the user or the daemon,
+/// and a login screen should not flag it like a rejected face.
+fn is_hint(reason: &str) -> bool {
+    reason == "too_dark"
+}
+
+/// The failure code carried by a daemon error name, for errors shown like a
+/// failed attempt, e.g. `org.freedesktop.Visage1.Error.TooDark` from `Verify`.
+fn error_reason(error: &CallError) -> Option<&'static str> {
+    match error.name()? {
+        "org.freedesktop.Visage1.Error.TooDark" => Some("too_dark"),
+        _ => None,
+    }
+}
+
+/// Map a failed daemon call to the `PAM_ERROR_MSG` line shown before the
+/// password prompt, by error name, so a user can tell a missing daemon from a
+/// face that did not match. `None` for errors not worth interrupting the
+/// login for, such as a user who never enrolled.
+fn error_message(error: &CallError) -> Option<&'static str> {
+    let name = match error {
+        CallError::NoCamera(_) => return Some("Visage: camera not found"),
+        CallError::Named { name, .. } => name.as_str(),
+        CallError::Other(_) => return Some("Visage: face authentication unavailable"),
+    };
+    match name {
+        "org.freedesktop.Visage1.Error.NotEnrolled" | "org.freedesktop.Visage1.Error.Cancelled" => {
+            None
+        }
+        call_error::SERVICE_UNKNOWN | "org.freedesktop.DBus.Error.NameHasNoOwner" => {
+            Some("Visage: face authentication service not running")
+        }
+        "org.freedesktop.Visage1.Error.RateLimited" => {
+            Some("Visage: too many failed attempts — use your password")
+        }
+        "org.freedesktop.Visage1.Error.CameraBusy" => {
+            Some("Visage: camera busy — in use by another application")
+        }
+        "org.freedesktop.Visage1.Error.Camera"
+        | "org.freedesktop.Visage1.Error.CameraDisconnected" => Some("Visage: camera unavailable"),
+        "org.freedesktop.Visage1.Error.Timeout" => Some("Visage: timed out"),
+        call_error::NO_REPLY | "org.freedesktop.DBus.Error.TimedOut" => {
+            Some("Visage: face authentication service not responding")
+        }
+        _ => Some("Visage: face authentication unavailable"),
+    }
+}
+
+/// Map a daemon progress stage to the `PAM_TEXT_INFO` line shown while the
+/// attempt runs. Unknown stages (a newer daemon) are not shown.
+fn progress_message(stage: &str) -> Option<&'static str> {
+    match stage {
+        "capture_started" => Some("Visage: look at the camera"),
+        "face_detected" => Some("Visage: face detected"),
+        "matching" => Some("Visage: matching..."),
+        "camera_disconnected" => Some("Visage: camera disconnected — waiting for it"),
+        _ => None,
+    }
 }
 
 /// PAM authentication entry point.
 ///
 /// Called by the PAM stack when `auth sufficient pam_visage.so` is configured.
-/// Extracts the username via `pam_get_user`, then calls `visaged` over D-Bus.
+/// Extracts the username via `pam_get_user`, then calls `visaged` over D-Bus,
+/// up to `max_tries` times. Module arguments are described in [`args`].
 ///
 /// Returns:
 /// - `PAM_SUCCESS` (0) if face matched
-/// - `PAM_IGNORE` (25) on any failure — daemon down, no match, error, panic
+/// - `PAM_AUTH_ERR` (7) with `strict` on a definitive non-match
+/// - the result set with `on_error=` for a failure of that class
+/// - `PAM_IGNORE` (25) on any other failure — daemon down, no match, error, panic
+///
+/// A `require_face` line captures nothing: it returns `PAM_SUCCESS` if a
+/// `second_factor` line earlier in the transaction recorded a match, and
+/// `PAM_AUTH_ERR` otherwise.
 ///
 /// # Safety
 ///
@@ -201,46 +740,251 @@ fn verify_face(username: &str) -> Result<bool, Box<dyn std::error::Error>> {
 pub unsafe extern "C" fn pam_sm_authenticate(
     pamh: *mut libc::c_void,
     _flags: libc::c_int,
-    _argc: libc::c_int,
-    _argv: *const *const libc::c_char,
+    argc: libc::c_int,
+    argv: *const *const libc::c_char,
 ) -> libc::c_int {
     let result = panic::catch_unwind(|| {
+        let started = std::time::Instant::now();
         syslog_open();
 
-        // Extract username from PAM handle.
-        let mut user_ptr: *const libc::c_char = ptr::null();
-        // SAFETY: pamh is a valid PAM handle. pam_get_user writes a pointer
-        // that remains valid for the lifetime of the PAM conversation.
-        let ret = unsafe { pam_get_user(pamh, &mut user_ptr, ptr::null()) };
-        if ret != PAM_SUCCESS || user_ptr.is_null() {
-            syslog_msg(LOG_ERR, &format!("pam_get_user failed (ret={})", ret));
+        // SAFETY: PAM passes argc/argv straight from the pam.d module line.
+        let raw_args = unsafe { collect_args(argc, argv) };
+        let line_args = PamArgs::parse(&raw_args).0;
+        if line_args.require_face {
+            return second_factor::require(pamh, PAM_AUTH_ERR);
+        }
+        let (args, warnings) = match PamArgs::load(&raw_args) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                syslog_msg(
+                    LOG_WARNING,
+                    &format!("skipping face auth, config file rejected: {}", e),
+                );
+                // As a second factor the face is mandatory: fail closed.
+                return if line_args.second_factor {
+                    PAM_AUTH_ERR
+                } else {
+                    PAM_IGNORE
+                };
+            }
+        };
+        for warning in &warnings {
+            syslog_msg(LOG_WARNING, warning);
+        }
+        let debug = |msg: &str| {
+            if args.debug {
+                syslog_msg(LOG_DEBUG, msg);
+            }
+        };
+        debug(&format!("options: {:?}", args));
+        // With `quiet`, nothing reaches the conversation; syslog is unaffected.
+        let info = |text: &str| {
+            if !args.quiet {
+                send_text_info(pamh, text);
+            }
+        };
+        let error = |text: &str| {
+            if !args.quiet {
+                send_error_msg(pamh, text);
+            }
+        };
+
+        // Per-service policy comes first: a denied service (polkit, su) must
+        // not even trigger a username prompt from this module.
+        let service = get_item_str(pamh, PAM_SERVICE);
+        if !args.allows_service(service.as_deref()) {
+            debug(&format!(
+                "face auth not enabled for service '{}'",
+                service.as_deref().unwrap_or("?")
+            ));
             return PAM_IGNORE;
         }
 
-        // SAFETY: pam_get_user guarantees the pointer is non-null and points
-        // to a NUL-terminated string that lives for the PAM conversation.
-        let username = match unsafe { CStr::from_ptr(user_ptr) }.to_str() {
-            Ok(s) => s,
-            Err(_) => {
-                syslog_msg(LOG_WARNING, "username is not valid UTF-8");
+        // Extract username from PAM handle.
+        let user = match get_user(pamh) {
+            Ok(user) => user,
+            Err((priority, msg)) => {
+                syslog_msg(priority, &msg);
                 return PAM_IGNORE;
             }
         };
+        let username = user.name.as_str();
 
-        // Call visaged over D-Bus.
-        match verify_face(username) {
-            Ok(true) => {
-                syslog_msg(LOG_INFO, &format!("face matched for user '{}'", username));
-                send_text_info(pamh, "Visage: face recognized");
-                PAM_SUCCESS
-            }
+        match users::allows_user(&args, username) {
+            Ok(true) => {}
             Ok(false) => {
-                syslog_msg(LOG_INFO, &format!("no match for user '{}'", username));
-                PAM_IGNORE
+                debug(&format!("face auth not enabled for user '{}'", username));
+                return PAM_IGNORE;
             }
             Err(e) => {
-                syslog_msg(LOG_WARNING, &format!("D-Bus error: {}", e));
-                PAM_IGNORE
+                syslog_msg(
+                    LOG_WARNING,
+                    &format!("skipping face auth, user list rejected: {}", e),
+                );
+                return PAM_IGNORE;
+            }
+        }
+
+        // From here on every decision is logged with structured fields. As a
+        // second factor, the outcome also decides the result (see
+        // `second_factor`).
+        let context = audit::Context::new(pamh, service, &user, started, args.audit);
+        let decide = |outcome: &str, single_factor: libc::c_int| {
+            if args.second_factor {
+                second_factor::decide(pamh, outcome)
+            } else {
+                single_factor
+            }
+        };
+
+        // Nobody can be in front of the camera for an SSH login; don't make
+        // the user wait for a capture that cannot succeed.
+        if let Some(reason) = session::skip_reason(pamh, &args) {
+            context.log(
+                LOG_INFO,
+                &format!("skipping face auth for user '{}'", username),
+                audit::Outcome::Skipped,
+                Some(&reason),
+            );
+            return decide("skipped", PAM_IGNORE);
+        }
+
+        if args.grace_secs > 0 && grace::is_fresh(&user.raw, args.grace_secs) {
+            context.log(
+                LOG_INFO,
+                &format!("face match for user '{}' reused (grace period)", username),
+                audit::Outcome::Grace,
+                None,
+            );
+            return decide(second_factor::SUCCESS, PAM_SUCCESS);
+        }
+
+        if args.min_interval_secs > 0
+            && debounce::failed_recently(&user.raw, args.min_interval_secs)
+        {
+            context.log(
+                LOG_INFO,
+                &format!("skipping face auth for user '{}'", username),
+                audit::Outcome::Skipped,
+                Some("min_interval"),
+            );
+            return decide("skipped", PAM_IGNORE);
+        }
+
+        // A closed lid points the built-in camera at the keyboard.
+        if !args.ignore_lid && lid::is_closed() {
+            context.log(
+                LOG_INFO,
+                &format!("skipping face auth for user '{}'", username),
+                audit::Outcome::Skipped,
+                Some("lid closed"),
+            );
+            return decide("skipped", PAM_IGNORE);
+        }
+
+        // Call visaged over D-Bus, retrying a non-match up to max_tries times.
+        // Each retry tells the user why the last attempt failed and what to do.
+        // In race mode the password prompt runs concurrently instead.
+        let outcome = if args.race {
+            match race::run(pamh, &user, &args) {
+                race::Winner::Password => {
+                    context.log(
+                        LOG_INFO,
+                        &format!(
+                            "password entered before face verify finished for user '{}'",
+                            username
+                        ),
+                        audit::Outcome::Password,
+                        None,
+                    );
+                    return PAM_IGNORE;
+                }
+                race::Winner::Face(outcome) => outcome,
+            }
+        } else {
+            let mut on_progress = |stage: &str| {
+                if let Some(text) = progress_message(stage) {
+                    info(text);
+                }
+            };
+            verify_with_retries(&user, &args, None, &mut on_progress, |attempt, reason| {
+                debug(&format!(
+                    "attempt {} of {} for user '{}' (previous reason={})",
+                    attempt, args.max_tries, username, reason
+                ));
+                info(failure_message(reason));
+                info(&attempt_prompt(attempt, args.max_tries));
+            })
+        };
+
+        match outcome {
+            Ok((true, _)) => {
+                context.log(
+                    LOG_INFO,
+                    &format!("face matched for user '{}'", username),
+                    audit::Outcome::Success,
+                    None,
+                );
+                info("Visage: face recognized");
+                if args.grace_secs > 0 {
+                    grace::record(&user.raw);
+                }
+                if args.min_interval_secs > 0 {
+                    debounce::clear(&user.raw);
+                }
+                if args.authtok {
+                    match keyring::release(pamh, username) {
+                        Ok(()) => debug(&format!("PAM_AUTHTOK set for user '{}'", username)),
+                        Err(e) => syslog_msg(
+                            LOG_WARNING,
+                            &format!("keyring password not released for '{}': {}", username, e),
+                        ),
+                    }
+                }
+                decide(second_factor::SUCCESS, PAM_SUCCESS)
+            }
+            Ok((false, reason)) => {
+                context.log(
+                    LOG_INFO,
+                    &format!("no match for user '{}'", username),
+                    audit::Outcome::Failure,
+                    Some(&reason),
+                );
+                if args.min_interval_secs > 0 {
+                    debounce::record_failure(&user.raw);
+                }
+                if is_hint(&reason) {
+                    info(failure_message(&reason));
+                } else {
+                    error(failure_message(&reason));
+                }
+                let single_factor = args
+                    .on_error
+                    .result(ErrorClass::of_reason(&reason))
+                    .unwrap_or(if args.strict && is_definitive_rejection(&reason) {
+                        PAM_AUTH_ERR
+                    } else {
+                        PAM_IGNORE
+                    });
+                decide(&reason, single_factor)
+            }
+            Err(e) => {
+                context.log(
+                    LOG_WARNING,
+                    &format!("D-Bus error: {}", e),
+                    audit::Outcome::Error,
+                    None,
+                );
+                if let Some(reason) = error_reason(&e) {
+                    info(failure_message(reason));
+                } else if let Some(text) = error_message(&e) {
+                    error(text);
+                }
+                let single_factor = args
+                    .on_error
+                    .result(e.name().and_then(ErrorClass::of_error))
+                    .unwrap_or(PAM_IGNORE);
+                decide("error", single_factor)
             }
         }
     });
@@ -248,6 +992,143 @@ pub unsafe extern "C" fn pam_sm_authenticate(
     result.unwrap_or(PAM_IGNORE)
 }
 
+/// Shared body of [`pam_sm_open_session`] and [`pam_sm_close_session`]:
+/// apply the same service, user, and remote-session policy as
+/// authentication, then notify the daemon.
+fn session_hook(pamh: *mut libc::c_void, raw_args: &[String], opened: bool) -> libc::c_int {
+    syslog_open();
+    let (args, warnings) = match PamArgs::load(raw_args) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            syslog_msg(LOG_WARNING, &format!("config file rejected: {}", e));
+            return PAM_IGNORE;
+        }
+    };
+    for warning in &warnings {
+        syslog_msg(LOG_WARNING, warning);
+    }
+    let debug = |msg: &str| {
+        if args.debug {
+            syslog_msg(LOG_DEBUG, msg);
+        }
+    };
+
+    if !args.allows_service(get_item_str(pamh, PAM_SERVICE).as_deref()) {
+        return PAM_IGNORE;
+    }
+    let username = match get_user(pamh) {
+        Ok(user) => user.name,
+        Err((priority, msg)) => {
+            syslog_msg(priority, &msg);
+            return PAM_IGNORE;
+        }
+    };
+    if !matches!(users::allows_user(&args, &username), Ok(true)) {
+        return PAM_IGNORE;
+    }
+    // A remote session never unlocks with this machine's camera.
+    if let Some(reason) = session::skip_reason(pamh, &args) {
+        debug(&format!(
+            "not pre-warming for user '{}': {}",
+            username, reason
+        ));
+        return PAM_IGNORE;
+    }
+
+    let event = if opened { "opened" } else { "closed" };
+    match notify_session(&username, opened) {
+        Ok(()) => {
+            debug(&format!("session {} for user '{}'", event, username));
+            PAM_SUCCESS
+        }
+        Err(e) => {
+            debug(&format!("session {} notification failed: {}", event, e));
+            PAM_IGNORE
+        }
+    }
+}
+
+/// PAM session-open entry point, for `session optional pam_visage.so`.
+///
+/// Tells `visaged` the user has logged in so it can cache their gallery and
+/// warm up the engine; the first unlock of the session is then as fast as
+/// later ones. Never delays the login by more than a short D-Bus timeout.
+///
+/// Returns `PAM_SUCCESS` once the daemon is notified, `PAM_IGNORE` otherwise.
+///
+/// # Safety
+///
+/// `pamh` must be a valid PAM handle provided by the PAM framework. Panics
+/// are caught and converted to `PAM_IGNORE`.
+#[no_mangle]
+pub unsafe extern "C" fn pam_sm_open_session(
+    pamh: *mut libc::c_void,
+    _flags: libc::c_int,
+    argc: libc::c_int,
+    argv: *const *const libc::c_char,
+) -> libc::c_int {
+    panic::catch_unwind(|| {
+        // SAFETY: PAM passes argc/argv straight from the pam.d module line.
+        let raw_args = unsafe { collect_args(argc, argv) };
+        session_hook(pamh, &raw_args, true)
+    })
+    .unwrap_or(PAM_IGNORE)
+}
+
+/// PAM session-close entry point: tells `visaged` the session opened by
+/// [`pam_sm_open_session`] has ended.
+///
+/// # Safety
+///
+/// `pamh` must be a valid PAM handle provided by the PAM framework. Panics
+/// are caught and converted to `PAM_IGNORE`.
+#[no_mangle]
+pub unsafe extern "C" fn pam_sm_close_session(
+    pamh: *mut libc::c_void,
+    _flags: libc::c_int,
+    argc: libc::c_int,
+    argv: *const *const libc::c_char,
+) -> libc::c_int {
+    panic::catch_unwind(|| {
+        // SAFETY: PAM passes argc/argv straight from the pam.d module line.
+        let raw_args = unsafe { collect_args(argc, argv) };
+        session_hook(pamh, &raw_args, false)
+    })
+    .unwrap_or(PAM_IGNORE)
+}
+
+/// PAM account-management entry point, for `account optional pam_visage.so
+/// max_age=DAYS` or `account required pam_visage.so require_face`.
+///
+/// Warns when the user's face enrollment is older than `max_age` days and,
+/// with `max_age_action=expire`, returns `PAM_NEW_AUTHTOK_REQD` (see the
+/// `freshness` module). Without `max_age`, and whenever the age cannot be
+/// determined, returns `PAM_IGNORE`. With `require_face`, returns
+/// `PAM_SUCCESS` if a `second_factor` line recorded a match during
+/// authentication and `PAM_PERM_DENIED` otherwise (see `second_factor`).
+///
+/// # Safety
+///
+/// `pamh` must be a valid PAM handle provided by the PAM framework. Panics
+/// are caught and converted to `PAM_IGNORE`.
+#[no_mangle]
+pub unsafe extern "C" fn pam_sm_acct_mgmt(
+    pamh: *mut libc::c_void,
+    _flags: libc::c_int,
+    argc: libc::c_int,
+    argv: *const *const libc::c_char,
+) -> libc::c_int {
+    panic::catch_unwind(|| {
+        // SAFETY: PAM passes argc/argv straight from the pam.d module line.
+        let raw_args = unsafe { collect_args(argc, argv) };
+        if PamArgs::parse(&raw_args).0.require_face {
+            return second_factor::require(pamh, PAM_PERM_DENIED);
+        }
+        freshness::account_hook(pamh, &raw_args)
+    })
+    .unwrap_or(PAM_IGNORE)
+}
+
 /// PAM credential management entry point (required by the PAM ABI).
 ///
 /// Visage does not manage credentials — always returns `PAM_IGNORE`.
@@ -269,55 +1150,218 @@ pub unsafe extern "C" fn pam_sm_setcred(
 mod tests {
     use super::*;
 
+    #[test]
+    fn non_utf8_user_names_are_escaped_not_rejected() {
+        let user = PamUser::new(b"alice");
+        assert_eq!(
+            (user.raw.as_slice(), user.name.as_str()),
+            (&b"alice"[..], "alice")
+        );
+        let user = PamUser::new(b"jos\xe9");
+        assert_eq!(user.raw, b"jos\xe9");
+        assert_eq!(user.name, r"jos\xe9");
+    }
+
     #[test]
     fn pam_constants_match_spec() {
         // Verify against the values defined in <security/pam_modules.h>.
         // These are load-bearing: wrong values silently mis-route the PAM stack.
         assert_eq!(PAM_SUCCESS, 0, "PAM_SUCCESS must be 0");
+        assert_eq!(PAM_PERM_DENIED, 6, "PAM_PERM_DENIED must be 6");
+        assert_eq!(PAM_AUTH_ERR, 7, "PAM_AUTH_ERR must be 7");
+        assert_eq!(PAM_AUTHINFO_UNAVAIL, 9, "PAM_AUTHINFO_UNAVAIL must be 9");
+        assert_eq!(PAM_NEW_AUTHTOK_REQD, 12, "PAM_NEW_AUTHTOK_REQD must be 12");
         assert_eq!(PAM_IGNORE, 25, "PAM_IGNORE must be 25");
     }
 
+    #[test]
+    fn retry_feedback() {
+        assert_eq!(
+            attempt_prompt(2, 3),
+            "Visage: attempt 2 of 3 — look at the camera"
+        );
+        assert!(is_retryable("too_dark"));
+        assert!(is_retryable("poor_quality"));
+        assert!(is_retryable("below_threshold"));
+        assert!(!is_retryable("camera_error"));
+        assert!(!is_retryable("static_scene"));
+        assert!(!is_retryable("cancelled"));
+    }
+
+    #[test]
+    fn only_face_seen_rejections_are_definitive() {
+        assert!(is_definitive_rejection("below_threshold"));
+        assert!(is_definitive_rejection("liveness_failed"));
+        assert!(is_definitive_rejection("static_scene"));
+        for reason in [
+            "too_dark",
+            "no_face",
+            "camera_error",
+            "timeout",
+            "",
+            "unknown",
+        ] {
+            assert!(!is_definitive_rejection(reason), "{reason}");
+        }
+    }
+
     #[test]
     fn pam_conv_constant_matches_spec() {
         assert_eq!(PAM_CONV, 5, "PAM_CONV must be 5");
+        assert_eq!(PAM_AUTHTOK, 6, "PAM_AUTHTOK must be 6");
     }
 
     #[test]
     fn pam_text_info_matches_spec() {
+        assert_eq!(PAM_PROMPT_ECHO_OFF, 1, "PAM_PROMPT_ECHO_OFF must be 1");
         assert_eq!(PAM_TEXT_INFO, 4, "PAM_TEXT_INFO must be 4");
+        assert_eq!(PAM_ERROR_MSG, 3, "PAM_ERROR_MSG must be 3");
     }
 
     #[test]
     fn syslog_constants_match_spec() {
         assert_eq!(LOG_AUTHPRIV, 80, "LOG_AUTHPRIV must be 10 << 3 = 80");
         assert_eq!(LOG_INFO, 6, "LOG_INFO must be 6");
+        assert_eq!(LOG_DEBUG, 7, "LOG_DEBUG must be 7");
         assert_eq!(LOG_WARNING, 4, "LOG_WARNING must be 4");
         assert_eq!(LOG_ERR, 3, "LOG_ERR must be 3");
     }
 
+    #[test]
+    fn failure_message_covers_known_reasons() {
+        assert_eq!(
+            failure_message("too_dark"),
+            "Visage: face unlock unavailable: not enough light"
+        );
+        assert!(is_hint("too_dark"));
+        assert!(!is_hint("below_threshold"));
+        assert_eq!(
+            failure_message("face_too_small"),
+            "Visage: face too far away, move closer"
+        );
+        assert_eq!(
+            failure_message("below_threshold"),
+            "Visage: face not recognized"
+        );
+        // Unknown codes from a newer daemon must not leak into the prompt.
+        assert_eq!(
+            failure_message("some_new_code"),
+            "Visage: face not recognized"
+        );
+    }
+
+    #[test]
+    fn error_message_tells_daemon_faults_apart() {
+        let named = |name: &str| CallError::named(name, "any message");
+        assert_eq!(
+            error_message(&named(call_error::SERVICE_UNKNOWN)),
+            Some("Visage: face authentication service not running")
+        );
+        assert_eq!(
+            error_message(&named("org.freedesktop.DBus.Error.NameHasNoOwner")),
+            Some("Visage: face authentication service not running")
+        );
+        assert_eq!(
+            error_message(&CallError::NoCamera("/dev/video2".into())),
+            Some("Visage: camera not found")
+        );
+        assert_eq!(
+            error_message(&named("org.freedesktop.Visage1.Error.CameraBusy")),
+            Some("Visage: camera busy — in use by another application")
+        );
+        assert_eq!(
+            error_message(&named(call_error::NO_REPLY)),
+            Some("Visage: face authentication service not responding")
+        );
+        assert_eq!(
+            error_message(&named("org.freedesktop.Visage1.Error.RateLimited")),
+            Some("Visage: too many failed attempts — use your password")
+        );
+        assert_eq!(
+            error_message(&named("org.freedesktop.Visage1.Error.NotEnrolled")),
+            None
+        );
+        // The message text no longer decides anything.
+        assert_eq!(
+            error_message(&CallError::named(
+                "org.freedesktop.DBus.Error.Failed",
+                "no enrolled models; camera busy"
+            )),
+            Some("Visage: face authentication unavailable")
+        );
+        assert_eq!(
+            error_message(&CallError::Other("something new".into())),
+            Some("Visage: face authentication unavailable")
+        );
+        assert_eq!(
+            error_reason(&named("org.freedesktop.Visage1.Error.TooDark")),
+            Some("too_dark")
+        );
+        assert_eq!(
+            error_reason(&named("org.freedesktop.DBus.Error.Failed")),
+            None
+        );
+    }
+
+    #[test]
+    fn progress_message_covers_daemon_stages() {
+        for stage in [
+            "capture_started",
+            "face_detected",
+            "matching",
+            "camera_disconnected",
+        ] {
+            assert!(progress_message(stage).is_some(), "{stage}");
+        }
+        assert_eq!(progress_message("some_new_stage"), None);
+    }
+
+    #[test]
+    fn collect_args_reads_argv_and_tolerates_null() {
+        let a = CString::new("debug").unwrap();
+        let b = CString::new("timeout=5").unwrap();
+        let argv = [a.as_ptr(), b.as_ptr()];
+        // SAFETY: argv holds two valid C strings.
+        let args = unsafe { collect_args(2, argv.as_ptr()) };
+        assert_eq!(args, vec!["debug", "timeout=5"]);
+        // SAFETY: a null argv is explicitly handled.
+        assert!(unsafe { collect_args(3, ptr::null()) }.is_empty());
+    }
+
     #[test]
     fn verify_face_errors_when_daemon_not_running() {
         // When visaged is not on the system bus, verify_face must return Err,
-        // not panic. This exercises the ServiceUnknown / NameHasNoOwner path.
+        // not panic. This exercises the probe's "not running" path (and the
+        // ServiceUnknown / NameHasNoOwner path if the bus cannot be probed).
         //
         // This test will pass in any environment where visaged is not running,
         // including CI. If the daemon happens to be running, the test is skipped
         // to avoid a real camera capture during unit testing.
-        let result = verify_face("_pam_visage_unit_test_user_");
+        let result = verify_face(
+            &PamUser::new(b"_pam_visage_unit_test_user_"),
+            &PamArgs::default(),
+            None,
+            &mut |_| {},
+        );
         // If the daemon is running we get Ok(true/false); that's also fine —
         // the important property is no panic.
         match result {
+            // A running daemon that does not know the test user.
+            Err(e)
+                if matches!(
+                    e.name(),
+                    Some(
+                        "org.freedesktop.Visage1.Error.NotEnrolled"
+                            | "org.freedesktop.Visage1.Error.NotFound"
+                    )
+                ) => {}
             Err(e) => {
-                // Expected: daemon not present
-                let msg = e.to_string();
-                assert!(
-                    msg.contains("ServiceUnknown")
-                        || msg.contains("NameHasNoOwner")
-                        || msg.contains("not provided")
-                        || msg.contains("Failed to connect")
-                        || msg.contains("no enrolled models")
-                        || msg.contains("unknown user"),
-                    "unexpected error message: {msg}"
+                // Expected: daemon not present, or no bus at all. Either way
+                // `on_error=unreachable:` must apply.
+                assert_eq!(
+                    e.name().and_then(ErrorClass::of_error),
+                    Some(ErrorClass::Unreachable),
+                    "unexpected error: {e}"
                 );
             }
             Ok(_) => {
@@ -0,0 +1,81 @@
+//! Lid-closed detection — skip the camera when the laptop is shut.
+//!
+//! Docked with an external monitor, a closed laptop's built-in camera faces
+//! the keyboard: every verify would run until its timeout before the password
+//! prompt appears. The lid state comes from `/proc/acpi/button/lid/*/state`
+//! and, on machines without an ACPI lid there (many ARM laptops, some newer
+//! firmware), from logind's `LidClosed` property. Set `ignore_lid` when the
+//! daemon uses an external camera that still sees the user.
+
+use std::fs;
+
+use crate::PROBE_TIMEOUT;
+
+#[zbus::proxy(
+    gen_blocking = false,
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    #[zbus(property)]
+    fn lid_closed(&self) -> zbus::Result<bool>;
+}
+
+/// Whether the laptop lid is closed. Unknown (no lid, logind unreachable)
+/// counts as open.
+pub(crate) fn is_closed() -> bool {
+    match acpi_lid_closed() {
+        Some(closed) => closed,
+        None => logind_lid_closed().unwrap_or(false),
+    }
+}
+
+/// Lid state from the ACPI button driver; `None` when it exposes no lid.
+fn acpi_lid_closed() -> Option<bool> {
+    let mut states = fs::read_dir("/proc/acpi/button/lid")
+        .ok()?
+        .filter_map(|entry| fs::read_to_string(entry.ok()?.path().join("state")).ok())
+        .filter_map(|state| parse_state(&state))
+        .peekable();
+    states.peek()?;
+    // Several lids is unusual; treat the machine as closed only if all are.
+    Some(states.all(|closed| closed))
+}
+
+/// `state:      closed` → `Some(true)`.
+fn parse_state(state: &str) -> Option<bool> {
+    match state.strip_prefix("state:")?.trim() {
+        "closed" => Some(true),
+        "open" => Some(false),
+        _ => None,
+    }
+}
+
+/// logind's view, bounded by [`PROBE_TIMEOUT`]; `None` when it cannot be asked.
+fn logind_lid_closed() -> Option<bool> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .ok()?;
+    runtime.block_on(async {
+        let query = async {
+            let conn = zbus::Connection::system().await?;
+            Login1ManagerProxy::new(&conn).await?.lid_closed().await
+        };
+        tokio::time::timeout(PROBE_TIMEOUT, query).await.ok()?.ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_acpi_state() {
+        assert_eq!(parse_state("state:      closed\n"), Some(true));
+        assert_eq!(parse_state("state:      open\n"), Some(false));
+        assert_eq!(parse_state("state:      unknown\n"), None);
+        assert_eq!(parse_state(""), None);
+    }
+}
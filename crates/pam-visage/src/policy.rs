@@ -0,0 +1,189 @@
+//! `on_error=CLASS:RESULT,…` — the PAM result for each kind of failure.
+//!
+//! By default every failed attempt returns `PAM_IGNORE`, so the stack moves
+//! on to the password (`strict` turns a rejected face into `PAM_AUTH_ERR`).
+//! Sites that want a different fail-open/fail-closed trade-off per failure
+//! set it here:
+//!
+//! ```text
+//! auth sufficient pam_visage.so on_error=unreachable:unavail,below_threshold:fail
+//! ```
+//!
+//! | Class | Failure |
+//! |-------|---------|
+//! | `unreachable` | daemon not running or not answering |
+//! | `no_face` | no face in the captured frames |
+//! | `below_threshold` | a face was seen and did not match |
+//! | `camera_busy` | the camera is held by another application |
+//!
+//! Results are `ignore` (`PAM_IGNORE`), `unavail` (`PAM_AUTHINFO_UNAVAIL`)
+//! and `fail` (`PAM_AUTH_ERR`). Entries from several `on_error=` arguments
+//! (the config file, then the pam.d line) are merged, later ones winning per
+//! class. As a second factor every failure is already `PAM_AUTH_ERR`, so the
+//! table does not apply.
+
+use crate::call_error;
+use crate::{PAM_AUTHINFO_UNAVAIL, PAM_AUTH_ERR, PAM_IGNORE};
+
+/// A kind of failed attempt that can be given its own PAM result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorClass {
+    Unreachable,
+    NoFace,
+    BelowThreshold,
+    CameraBusy,
+}
+
+impl ErrorClass {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "unreachable" => Some(Self::Unreachable),
+            "no_face" => Some(Self::NoFace),
+            "below_threshold" => Some(Self::BelowThreshold),
+            "camera_busy" => Some(Self::CameraBusy),
+            _ => None,
+        }
+    }
+
+    /// The class of a daemon failure code, if it has one.
+    pub(crate) fn of_reason(reason: &str) -> Option<Self> {
+        match reason {
+            "no_face" => Some(Self::NoFace),
+            "below_threshold" => Some(Self::BelowThreshold),
+            "camera_busy" => Some(Self::CameraBusy),
+            _ => None,
+        }
+    }
+
+    /// The class of a failed daemon call, by its D-Bus error name.
+    pub(crate) fn of_error(name: &str) -> Option<Self> {
+        match name {
+            call_error::SERVICE_UNKNOWN
+            | call_error::NO_REPLY
+            | "org.freedesktop.DBus.Error.NameHasNoOwner"
+            | "org.freedesktop.DBus.Error.TimedOut" => Some(Self::Unreachable),
+            "org.freedesktop.Visage1.Error.CameraBusy" => Some(Self::CameraBusy),
+            _ => None,
+        }
+    }
+}
+
+/// Per-class PAM results set with `on_error=`; unset classes keep the default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ErrorPolicy {
+    entries: Vec<(ErrorClass, libc::c_int)>,
+}
+
+impl ErrorPolicy {
+    /// Merge the `CLASS:RESULT` pairs of one `on_error=` value. Nothing is
+    /// applied if any pair is malformed.
+    pub(crate) fn merge(&mut self, value: &str) -> Result<(), String> {
+        let mut parsed = Vec::new();
+        for pair in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (class, result) = pair
+                .split_once(':')
+                .ok_or_else(|| format!("'{pair}' is not CLASS:RESULT"))?;
+            let class = ErrorClass::parse(class).ok_or_else(|| {
+                format!(
+                    "unknown class '{class}' \
+                     (unreachable, no_face, below_threshold, camera_busy)"
+                )
+            })?;
+            let code = match result {
+                "ignore" => PAM_IGNORE,
+                "unavail" => PAM_AUTHINFO_UNAVAIL,
+                "fail" => PAM_AUTH_ERR,
+                _ => return Err(format!("unknown result '{result}' (ignore, unavail, fail)")),
+            };
+            parsed.push((class, code));
+        }
+        if parsed.is_empty() {
+            return Err("empty list".to_string());
+        }
+        for (class, code) in parsed {
+            self.entries.retain(|(c, _)| *c != class);
+            self.entries.push((class, code));
+        }
+        Ok(())
+    }
+
+    /// The PAM result set for `class`, if any.
+    pub(crate) fn result(&self, class: Option<ErrorClass>) -> Option<libc::c_int> {
+        let class = class?;
+        self.entries
+            .iter()
+            .find(|(c, _)| *c == class)
+            .map(|&(_, code)| code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_pairs_later_winning() {
+        let mut policy = ErrorPolicy::default();
+        policy
+            .merge("unreachable:unavail, no_face:ignore,below_threshold:fail")
+            .unwrap();
+        policy.merge("no_face:fail").unwrap();
+        assert_eq!(
+            policy.result(Some(ErrorClass::Unreachable)),
+            Some(PAM_AUTHINFO_UNAVAIL)
+        );
+        assert_eq!(policy.result(Some(ErrorClass::NoFace)), Some(PAM_AUTH_ERR));
+        assert_eq!(
+            policy.result(Some(ErrorClass::BelowThreshold)),
+            Some(PAM_AUTH_ERR)
+        );
+        assert_eq!(policy.result(Some(ErrorClass::CameraBusy)), None);
+        assert_eq!(policy.result(None), None);
+    }
+
+    #[test]
+    fn malformed_values_change_nothing() {
+        let mut policy = ErrorPolicy::default();
+        for bad in [
+            "",
+            ",",
+            "no_face",
+            "no_face:deny",
+            "dark:fail",
+            "no_face:fail,bogus",
+        ] {
+            assert!(policy.merge(bad).is_err(), "{bad}");
+        }
+        assert_eq!(policy, ErrorPolicy::default());
+    }
+
+    #[test]
+    fn classifies_reasons_and_errors() {
+        assert_eq!(ErrorClass::of_reason("no_face"), Some(ErrorClass::NoFace));
+        assert_eq!(
+            ErrorClass::of_reason("below_threshold"),
+            Some(ErrorClass::BelowThreshold)
+        );
+        assert_eq!(ErrorClass::of_reason("too_dark"), None);
+        assert_eq!(
+            ErrorClass::of_error("org.freedesktop.DBus.Error.ServiceUnknown"),
+            Some(ErrorClass::Unreachable)
+        );
+        assert_eq!(
+            ErrorClass::of_error("org.freedesktop.DBus.Error.NoReply"),
+            Some(ErrorClass::Unreachable)
+        );
+        assert_eq!(
+            ErrorClass::of_error("org.freedesktop.Visage1.Error.CameraBusy"),
+            Some(ErrorClass::CameraBusy)
+        );
+        assert_eq!(
+            ErrorClass::of_error("org.freedesktop.DBus.Error.Failed"),
+            None
+        );
+        assert_eq!(
+            ErrorClass::of_error("org.freedesktop.Visage1.Error.NotEnrolled"),
+            None
+        );
+    }
+}
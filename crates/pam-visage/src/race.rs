@@ -0,0 +1,370 @@
+//! `race` mode — ask for the password while the face is being verified.
+//!
+//! PAM runs modules one after another, so a face attempt normally delays the
+//! password prompt by the whole capture. In race mode the module prompts for
+//! the password itself on a background thread while a second thread waits for
+//! the daemon. Whichever finishes first wins:
+//!
+//! - face matched → the prompt is cancelled and the caller returns `PAM_SUCCESS`;
+//! - password entered → it is stored as `PAM_AUTHTOK` and the caller returns
+//!   `PAM_IGNORE`, so the next module (`pam_unix.so try_first_pass`) checks it
+//!   without prompting again.
+//!
+//! If the face attempt fails first, the prompt simply stays up.
+//!
+//! When the application runs on a terminal, the module reads the password
+//! from it directly (see the `tty` module) and cancels the in-flight verify
+//! at the first keystroke, through the daemon's `Cancel` method over the
+//! transport the verify used, so the camera and IR emitter switch off as soon
+//! as the user starts typing. Other applications are prompted through their
+//! conversation function, which hands over the password only once it is
+//! submitted; the verify is cancelled then.
+//!
+//! The conversation prompt runs on a raw pthread so it can be cancelled with
+//! `pthread_cancel` while blocked inside the application's conversation
+//! function (a greeter round-trip, a `read`). The forced unwind that follows
+//! passes through the conversation function and the thread's entry function,
+//! so both are `extern "C-unwind"`, and the entry function owns no values
+//! with destructors.
+//!
+//! The face attempt runs on a `std` thread, which [`run`] joins before
+//! returning — `pam_end` may unload the module while it is still running. A
+//! verify still in flight by then is cancelled first.
+
+use crate::args::PamArgs;
+use crate::tty::{Password, Stop, Terminal};
+use crate::{
+    cancel_verify, conversation, new_cookie, pam_set_item, syslog_msg, verify_with_retries,
+    CallError, ConvFn, Cookie, FaceOutcome, PamMessage, PamResponse, PamUser, LOG_WARNING,
+    PAM_AUTHTOK, PAM_PROMPT_ECHO_OFF, PAM_SUCCESS,
+};
+use std::ffi::CString;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the waiting thread checks whether the prompt has been answered.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long [`run`] waits for the face thread to stop after cancelling its
+/// verify, before returning without it.
+const FACE_JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+extern "C" {
+    // libc declares the start routine `extern "C"`; the prompt thread's is
+    // `extern "C-unwind"` so that `pthread_cancel` may unwind through it.
+    fn pthread_create(
+        native: *mut libc::pthread_t,
+        attr: *const libc::pthread_attr_t,
+        f: extern "C-unwind" fn(*mut libc::c_void) -> *mut libc::c_void,
+        value: *mut libc::c_void,
+    ) -> libc::c_int;
+}
+
+/// Which side of the race decided the outcome.
+pub(crate) enum Winner {
+    /// The face attempt finished first, or the prompt could not be shown.
+    Face(FaceOutcome),
+    /// A password was entered and stored as `PAM_AUTHTOK`.
+    Password,
+}
+
+/// State shared with the prompt thread. Owned by [`run`], which frees it only
+/// after the thread has been joined.
+struct PromptJob {
+    conv: ConvFn,
+    appdata: *mut libc::c_void,
+    prompt: CString,
+    done: AtomicBool,
+    ret: AtomicI32,
+    resp: AtomicPtr<PamResponse>,
+}
+
+/// Prompt-thread entry point. Holds only plain data so that `pthread_cancel`
+/// can unwind through it.
+extern "C-unwind" fn prompt_thread(arg: *mut libc::c_void) -> *mut libc::c_void {
+    // SAFETY: `arg` is the `PromptJob` leaked by `run`, which outlives this thread.
+    let job = unsafe { &*(arg as *const PromptJob) };
+    let msg = PamMessage {
+        msg_style: PAM_PROMPT_ECHO_OFF,
+        msg: job.prompt.as_ptr(),
+    };
+    let msg_ptr: *const PamMessage = &msg;
+    let mut resp: *mut PamResponse = ptr::null_mut();
+    // SAFETY: msg_ptr points to a valid PamMessage; conv/appdata come from PAM_CONV.
+    let ret = unsafe { (job.conv)(1, &msg_ptr as *const _ as *mut _, &mut resp, job.appdata) };
+    job.resp.store(resp, Ordering::Release);
+    job.ret.store(ret, Ordering::Release);
+    job.done.store(true, Ordering::Release);
+    ptr::null_mut()
+}
+
+/// Run the face attempt and the password prompt concurrently.
+pub(crate) fn run(pamh: *mut libc::c_void, user: &PamUser, args: &PamArgs) -> Winner {
+    let Some((conv, appdata)) = conversation(pamh) else {
+        return Winner::Face(verify_with_retries(
+            user,
+            args,
+            None,
+            &mut |_| {},
+            |_, _| {},
+        ));
+    };
+
+    let cookie = Arc::new(new_cookie());
+    let (tx, rx) = mpsc::channel();
+    let (face_user, face_args, face_cookie) = (user.clone(), args.clone(), Arc::clone(&cookie));
+    let spawned = std::thread::Builder::new()
+        .name("pam-visage-face".into())
+        .spawn(move || {
+            let outcome = verify_with_retries(
+                &face_user,
+                &face_args,
+                Some(&face_cookie),
+                &mut |_| {},
+                |_, _| {},
+            );
+            let _ = tx.send(outcome);
+        });
+    let Ok(face) = spawned else {
+        return Winner::Face(verify_with_retries(
+            user,
+            args,
+            None,
+            &mut |_| {},
+            |_, _| {},
+        ));
+    };
+
+    let winner = match Terminal::open() {
+        Some((terminal, stop)) => race_terminal(pamh, terminal, &stop, &rx, &cookie, args),
+        None => race_conversation(pamh, conv, appdata, &rx),
+    };
+    join_face(face, &cookie, args);
+    winner
+}
+
+/// Race against a password read from the terminal, cancelling the verify at
+/// the first keystroke.
+fn race_terminal(
+    pamh: *mut libc::c_void,
+    terminal: Terminal,
+    stop: &Stop,
+    rx: &mpsc::Receiver<FaceOutcome>,
+    cookie: &Cookie,
+    args: &PamArgs,
+) -> Winner {
+    let typed = Arc::new(AtomicBool::new(false));
+    let key = Arc::clone(&typed);
+    let spawned = std::thread::Builder::new()
+        .name("pam-visage-prompt".into())
+        .spawn(move || terminal.read_password("Password: ", || key.store(true, Ordering::Release)));
+    let Ok(prompt) = spawned else {
+        return Winner::Face(wait_for_face(rx));
+    };
+
+    let mut cancelled = false;
+    loop {
+        if !cancelled && typed.load(Ordering::Acquire) {
+            // The user chose to type: switch the camera off now.
+            cancel_verify(cookie, args);
+            cancelled = true;
+        }
+        if prompt.is_finished() {
+            let password = prompt.join().ok().flatten();
+            break match password {
+                Some(password) if store_password(pamh, &password) => Winner::Password,
+                _ => Winner::Face(wait_for_face(rx)),
+            };
+        }
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok((true, reason))) => {
+                // The prompt restores the terminal as it returns.
+                stop.stop();
+                let _ = prompt.join();
+                break Winner::Face(Ok((true, reason)));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            // Face lost: the password alone decides now.
+            lost => {
+                let password = prompt.join().ok().flatten();
+                let outcome =
+                    lost.unwrap_or_else(|_| Err(CallError::Other("verify thread exited".into())));
+                break match password {
+                    Some(password) if store_password(pamh, &password) => Winner::Password,
+                    _ => Winner::Face(outcome),
+                };
+            }
+        }
+    }
+}
+
+/// Race against a password asked for through the application's conversation
+/// function, which hands it over only once submitted.
+fn race_conversation(
+    pamh: *mut libc::c_void,
+    conv: ConvFn,
+    appdata: *mut libc::c_void,
+    rx: &mpsc::Receiver<FaceOutcome>,
+) -> Winner {
+    let job = Box::into_raw(Box::new(PromptJob {
+        conv,
+        appdata,
+        prompt: CString::new("Password: ").expect("static prompt has no NUL"),
+        done: AtomicBool::new(false),
+        ret: AtomicI32::new(0),
+        resp: AtomicPtr::new(ptr::null_mut()),
+    }));
+    let saved_tty = save_tty();
+    let mut tid: libc::pthread_t = 0;
+    // SAFETY: `job` stays valid until the thread is joined below.
+    let rc = unsafe { pthread_create(&mut tid, ptr::null(), prompt_thread, job.cast()) };
+    if rc != 0 {
+        // SAFETY: the thread was never started, so `job` is exclusively ours.
+        drop(unsafe { Box::from_raw(job) });
+        return Winner::Face(wait_for_face(rx));
+    }
+    // SAFETY: `job` is freed only at the end of this function, after the join.
+    let job_ref = unsafe { &*job };
+
+    let winner = loop {
+        if job_ref.done.load(Ordering::Acquire) {
+            // SAFETY: `tid` is a joinable thread that has finished or is finishing.
+            unsafe { libc::pthread_join(tid, ptr::null_mut()) };
+            // A submitted password leaves the face thread running;
+            // `join_face` cancels it.
+            break if take_password(pamh, job_ref) {
+                Winner::Password
+            } else {
+                Winner::Face(wait_for_face(rx))
+            };
+        }
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok((true, reason))) => {
+                // SAFETY: `tid` is joinable; cancellation acts at the blocking
+                // read inside the conversation function.
+                unsafe {
+                    libc::pthread_cancel(tid);
+                    libc::pthread_join(tid, ptr::null_mut());
+                }
+                // The prompt may have completed just before the cancel landed.
+                free_response(job_ref.resp.swap(ptr::null_mut(), Ordering::AcqRel));
+                restore_tty(saved_tty.as_ref());
+                break Winner::Face(Ok((true, reason)));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            // Face lost: the password alone decides now.
+            lost => {
+                // SAFETY: `tid` is joinable; this blocks until the user answers.
+                unsafe { libc::pthread_join(tid, ptr::null_mut()) };
+                let outcome =
+                    lost.unwrap_or_else(|_| Err(CallError::Other("verify thread exited".into())));
+                break if take_password(pamh, job_ref) {
+                    Winner::Password
+                } else {
+                    Winner::Face(outcome)
+                };
+            }
+        }
+    };
+
+    // SAFETY: the prompt thread has been joined; nothing else references `job`.
+    drop(unsafe { Box::from_raw(job) });
+    winner
+}
+
+/// Wait for the face thread, cancelling its verify if it is still running
+/// and has not been cancelled yet. A thread that has not stopped within
+/// [`FACE_JOIN_TIMEOUT`] is left behind with a warning rather than holding
+/// up the login.
+fn join_face(face: JoinHandle<()>, cookie: &Cookie, args: &PamArgs) {
+    if !face.is_finished() {
+        // Only a password leaves the verify running: switch the camera off
+        // now.
+        if !cookie.cancelled.load(Ordering::Acquire) {
+            cancel_verify(cookie, args);
+        }
+        let deadline = Instant::now() + FACE_JOIN_TIMEOUT;
+        while !face.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+    if face.is_finished() {
+        let _ = face.join();
+    } else {
+        syslog_msg(
+            LOG_WARNING,
+            "verify thread did not stop after cancel; returning without it",
+        );
+    }
+}
+
+/// The terminal settings before the prompt, if stdin is a terminal.
+fn save_tty() -> Option<libc::termios> {
+    // SAFETY: tcgetattr on fd 0 fills the zeroed termios on success.
+    unsafe {
+        if libc::isatty(libc::STDIN_FILENO) != 1 {
+            return None;
+        }
+        let mut term: libc::termios = std::mem::zeroed();
+        (libc::tcgetattr(libc::STDIN_FILENO, &mut term) == 0).then_some(term)
+    }
+}
+
+/// A terminal conversation turns echo off for the password and is cancelled
+/// before it can turn it back on; put back the settings [`save_tty`] read so
+/// the shell stays usable.
+fn restore_tty(saved: Option<&libc::termios>) {
+    if let Some(term) = saved {
+        // SAFETY: `term` was filled by tcgetattr on the same descriptor.
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, term) };
+    }
+}
+
+/// Block for the face result.
+fn wait_for_face(rx: &mpsc::Receiver<FaceOutcome>) -> FaceOutcome {
+    rx.recv()
+        .unwrap_or_else(|_| Err(CallError::Other("verify thread exited".into())))
+}
+
+/// Store a password read from the terminal as `PAM_AUTHTOK`.
+fn store_password(pamh: *mut libc::c_void, password: &Password) -> bool {
+    // SAFETY: pamh is valid and the password is NUL-terminated; PAM copies it.
+    unsafe { pam_set_item(pamh, PAM_AUTHTOK, password.as_ptr()) == PAM_SUCCESS }
+}
+
+/// Store the prompt's answer as `PAM_AUTHTOK`. Returns `false` if the
+/// conversation failed or produced no answer.
+fn take_password(pamh: *mut libc::c_void, job: &PromptJob) -> bool {
+    let resp = job.resp.swap(ptr::null_mut(), Ordering::AcqRel);
+    let mut stored = false;
+    // SAFETY: a non-null `resp` is the response array the conversation allocated.
+    if job.ret.load(Ordering::Acquire) == PAM_SUCCESS
+        && !resp.is_null()
+        && !unsafe { (*resp).resp }.is_null()
+    {
+        // SAFETY: pamh is valid; PAM copies the token.
+        let rc = unsafe { pam_set_item(pamh, PAM_AUTHTOK, (*resp).resp.cast()) };
+        stored = rc == PAM_SUCCESS;
+    }
+    free_response(resp);
+    stored
+}
+
+/// Scrub and free a conversation response.
+fn free_response(resp: *mut PamResponse) {
+    if resp.is_null() {
+        return;
+    }
+    // SAFETY: `resp` and its string were malloc'd by the conversation function.
+    unsafe {
+        let text = (*resp).resp;
+        if !text.is_null() {
+            libc::explicit_bzero(text.cast(), libc::strlen(text));
+            libc::free(text.cast());
+        }
+        libc::free(resp.cast());
+    }
+}
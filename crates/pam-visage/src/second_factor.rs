@@ -0,0 +1,164 @@
+//! `second_factor` — face AND password instead of face OR password.
+//!
+//! Normally a match returns `PAM_SUCCESS`, which a `sufficient` line turns
+//! into a finished authentication. With `second_factor` the module never ends
+//! the stack: it records the outcome as PAM data under [`DATA_NAME`] and
+//! returns `PAM_IGNORE` for a match and `PAM_AUTH_ERR` for anything else —
+//! a non-match, an unreachable daemon, a remote session. On a `required`
+//! line, followed by the password module, both factors must then pass:
+//!
+//! ```text
+//! auth  required  pam_visage.so second_factor
+//! auth  required  pam_unix.so
+//! ```
+//!
+//! The recorded value is `success`, a failure reason code such as
+//! `below_threshold`, `error` (daemon unreachable), or `skipped` (remote
+//! session, closed lid), for later modules that want to inspect it with
+//! `pam_get_data`.
+//!
+//! A `require_face` line is the companion that consumes it: it captures
+//! nothing and passes only if a `second_factor` line earlier in the same
+//! PAM transaction recorded a match. In the account stack it catches an auth
+//! stack that finished before reaching the face — a `sufficient` module
+//! above it, say — and in the auth stack it lets the face line sit above
+//! the password as `optional` while the decision comes after it:
+//!
+//! ```text
+//! auth     optional  pam_visage.so second_factor
+//! auth     required  pam_unix.so
+//! auth     required  pam_visage.so require_face
+//! account  required  pam_visage.so require_face
+//! ```
+//!
+//! The verify itself carries the `second_factor` option, so a daemon set to
+//! `VISAGE_REQUIRE_SECOND_FACTOR` can refuse every other use of the camera.
+
+use std::ffi::{CStr, CString};
+
+use crate::{syslog_msg, LOG_WARNING, PAM_AUTH_ERR, PAM_IGNORE, PAM_SUCCESS};
+
+/// PAM data name the outcome is stored under.
+pub(crate) const DATA_NAME: &[u8] = b"pam_visage_face\0";
+
+/// Recorded value for a match (including a `grace=` reuse).
+pub(crate) const SUCCESS: &str = "success";
+
+extern "C" {
+    fn pam_set_data(
+        pamh: *mut libc::c_void,
+        module_data_name: *const libc::c_char,
+        data: *mut libc::c_void,
+        cleanup: Option<
+            unsafe extern "C" fn(
+                pamh: *mut libc::c_void,
+                data: *mut libc::c_void,
+                status: libc::c_int,
+            ),
+        >,
+    ) -> libc::c_int;
+
+    fn pam_get_data(
+        pamh: *const libc::c_void,
+        module_data_name: *const libc::c_char,
+        data: *mut *const libc::c_void,
+    ) -> libc::c_int;
+}
+
+/// Frees a value stored by [`record`] when PAM drops it.
+unsafe extern "C" fn free_value(
+    _pamh: *mut libc::c_void,
+    data: *mut libc::c_void,
+    _status: libc::c_int,
+) {
+    if !data.is_null() {
+        // SAFETY: `data` came from `CString::into_raw` in `record`, and PAM
+        // calls the cleanup exactly once.
+        drop(unsafe { CString::from_raw(data as *mut libc::c_char) });
+    }
+}
+
+/// Record `outcome` as PAM data and return the module's result for it.
+pub(crate) fn decide(pamh: *mut libc::c_void, outcome: &str) -> libc::c_int {
+    record(pamh, outcome);
+    result_for(outcome)
+}
+
+/// Body of a `require_face` line: `PAM_SUCCESS` if this transaction recorded
+/// a match, `fail` otherwise (nothing recorded included).
+pub(crate) fn require(pamh: *mut libc::c_void, fail: libc::c_int) -> libc::c_int {
+    let outcome = recorded(pamh);
+    if outcome.as_deref() == Some(SUCCESS) {
+        return PAM_SUCCESS;
+    }
+    syslog_msg(
+        LOG_WARNING,
+        &format!(
+            "face factor required but {}",
+            match outcome {
+                Some(outcome) => format!("not satisfied ({outcome})"),
+                None => "no second_factor line ran".to_string(),
+            }
+        ),
+    );
+    fail
+}
+
+/// The outcome a `second_factor` line recorded in this transaction.
+fn recorded(pamh: *mut libc::c_void) -> Option<String> {
+    let mut data: *const libc::c_void = std::ptr::null();
+    // SAFETY: `pamh` is the handle PAM passed in; the name is NUL-terminated
+    // and static; PAM writes a borrowed pointer to `data`.
+    let rc = unsafe { pam_get_data(pamh, DATA_NAME.as_ptr() as *const libc::c_char, &mut data) };
+    if rc != PAM_SUCCESS || data.is_null() {
+        return None;
+    }
+    // SAFETY: only `record` stores data under this name, always a
+    // NUL-terminated string that PAM keeps alive until the handle ends.
+    let value = unsafe { CStr::from_ptr(data as *const libc::c_char) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+fn result_for(outcome: &str) -> libc::c_int {
+    if outcome == SUCCESS {
+        PAM_IGNORE
+    } else {
+        PAM_AUTH_ERR
+    }
+}
+
+fn record(pamh: *mut libc::c_void, outcome: &str) {
+    let Ok(value) = CString::new(outcome) else {
+        return;
+    };
+    let value = value.into_raw();
+    // SAFETY: `pamh` is the handle PAM passed in; the name is NUL-terminated
+    // and static; on success PAM owns `value` and frees it via `free_value`
+    // (replacing an earlier value frees that one the same way).
+    let rc = unsafe {
+        pam_set_data(
+            pamh,
+            DATA_NAME.as_ptr() as *const libc::c_char,
+            value as *mut libc::c_void,
+            Some(free_value),
+        )
+    };
+    if rc != PAM_SUCCESS {
+        // SAFETY: PAM did not take ownership.
+        drop(unsafe { CString::from_raw(value) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_match_passes() {
+        assert_eq!(result_for(SUCCESS), PAM_IGNORE);
+        for outcome in ["below_threshold", "too_dark", "error", "skipped", ""] {
+            assert_eq!(result_for(outcome), PAM_AUTH_ERR, "{outcome}");
+        }
+        assert_eq!(DATA_NAME.last(), Some(&0));
+    }
+}
@@ -0,0 +1,219 @@
+//! Remote-session detection — skip the camera when nobody can be in front of it.
+//!
+//! A pam.d stack shared by `sshd` and local logins would otherwise run a
+//! capture on the server's camera for every SSH password login: a doomed
+//! D-Bus round-trip at best, and at worst a match from whoever happens to sit
+//! at the machine. Sessions are classified from `PAM_RHOST`, `PAM_TTY`, and
+//! the caller's systemd-logind session (`REMOTE=`, `SEAT=`), which unlike the
+//! environment cannot be set by the user. How strictly `PAM_RHOST` and
+//! `PAM_TTY` are read is set per item with `rhost=` and `tty=`.
+
+use crate::args::{PamArgs, RhostPolicy, TtyPolicy};
+use crate::get_item_str;
+use std::fs;
+
+// PAM item types
+pub(crate) const PAM_TTY: libc::c_int = 3;
+pub(crate) const PAM_RHOST: libc::c_int = 4;
+
+/// What logind records about the caller's session.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct LogindSession {
+    remote: bool,
+    seat: Option<String>,
+}
+
+/// Why face authentication should be skipped for this session, or `None` to
+/// proceed. `PAM_RHOST` and `PAM_TTY` are checked as `rhost=` and `tty=` say
+/// (by default, a remote host or an SSH TTY skips); a remote logind session
+/// always skips; with `local_only` a session must also be attached to a local
+/// seat (a VT, an X display, or a logind seat).
+pub(crate) fn skip_reason(pamh: *mut libc::c_void, args: &PamArgs) -> Option<String> {
+    let rhost = get_item_str(pamh, PAM_RHOST);
+    let tty = get_item_str(pamh, PAM_TTY);
+    let logind = logind_session();
+    classify(rhost.as_deref(), tty.as_deref(), logind.as_ref(), args)
+}
+
+fn classify(
+    rhost: Option<&str>,
+    tty: Option<&str>,
+    logind: Option<&LogindSession>,
+    args: &PamArgs,
+) -> Option<String> {
+    if let Some(host) = rhost.filter(|h| !h.is_empty()) {
+        match args.rhost {
+            RhostPolicy::Remote if !is_loopback(host) => {
+                return Some(format!("remote host '{host}'"))
+            }
+            RhostPolicy::Any => return Some(format!("remote host '{host}' (rhost=any)")),
+            _ => {}
+        }
+    }
+    match (args.tty, tty) {
+        // OpenSSH sets PAM_TTY to "ssh" until a pty is allocated.
+        (TtyPolicy::Remote, Some(tty)) if tty.starts_with("ssh") => {
+            return Some(format!("remote tty '{tty}'"))
+        }
+        (TtyPolicy::Local, Some(tty)) if !is_local_tty(tty) => {
+            return Some(format!("non-local tty '{tty}' (tty=local)"))
+        }
+        (TtyPolicy::Local, None) => return Some("no tty (tty=local)".to_string()),
+        _ => {}
+    }
+    if logind.is_some_and(|s| s.remote) {
+        return Some("remote logind session".to_string());
+    }
+    if args.local_only {
+        let on_seat = logind.is_some_and(|s| s.seat.is_some()) || tty.is_some_and(is_local_tty);
+        if !on_seat {
+            return Some("no local seat".to_string());
+        }
+    }
+    None
+}
+
+fn is_loopback(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// A virtual console (`/dev/tty3`, `tty3`) or an X display (`:0`).
+fn is_local_tty(tty: &str) -> bool {
+    let vt = tty.strip_prefix("/dev/").unwrap_or(tty);
+    match vt.strip_prefix("tty") {
+        Some(n) => !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()),
+        None => tty.starts_with(':'),
+    }
+}
+
+/// The logind session this process belongs to, if any (`sudo` in a desktop
+/// terminal: yes; `login` on a getty or `sshd` before the session opens: no).
+fn logind_session() -> Option<LogindSession> {
+    let cgroup = fs::read_to_string("/proc/self/cgroup").ok()?;
+    let id = session_id(&cgroup)?;
+    let state = fs::read_to_string(format!("/run/systemd/sessions/{id}")).ok()?;
+    Some(parse_session_file(&state))
+}
+
+/// Session ID from a `.../session-<id>.scope` component of `/proc/self/cgroup`.
+fn session_id(cgroup: &str) -> Option<&str> {
+    cgroup
+        .lines()
+        .flat_map(|line| line.split('/'))
+        .find_map(|part| part.strip_prefix("session-")?.strip_suffix(".scope"))
+        .filter(|id| id.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+fn parse_session_file(state: &str) -> LogindSession {
+    let mut session = LogindSession::default();
+    for line in state.lines() {
+        match line.split_once('=') {
+            Some(("REMOTE", v)) => session.remote = v == "1",
+            Some(("SEAT", v)) if !v.is_empty() => session.seat = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    session
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(args: &[&str]) -> PamArgs {
+        PamArgs::parse(args).0
+    }
+
+    #[test]
+    fn remote_sessions_are_skipped() {
+        assert!(classify(Some("203.0.113.7"), None, None, &policy(&[])).is_some());
+        assert!(classify(None, Some("ssh"), None, &policy(&[])).is_some());
+        let remote = LogindSession {
+            remote: true,
+            seat: None,
+        };
+        assert!(classify(None, Some("/dev/pts/3"), Some(&remote), &policy(&[])).is_some());
+
+        assert_eq!(
+            classify(Some(""), Some("/dev/pts/3"), None, &policy(&[])),
+            None
+        );
+        assert_eq!(
+            classify(
+                Some("localhost"),
+                Some("tty1"),
+                None,
+                &policy(&["local_only"])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn local_only_requires_a_seat() {
+        assert!(classify(None, Some("/dev/pts/3"), None, &policy(&["local_only"])).is_some());
+        assert!(classify(None, None, None, &policy(&["local_only"])).is_some());
+        assert_eq!(
+            classify(None, Some("/dev/tty2"), None, &policy(&["local_only"])),
+            None
+        );
+        assert_eq!(
+            classify(None, Some(":0"), None, &policy(&["local_only"])),
+            None
+        );
+        let seated = LogindSession {
+            remote: false,
+            seat: Some("seat0".into()),
+        };
+        assert_eq!(
+            classify(
+                None,
+                Some("/dev/pts/3"),
+                Some(&seated),
+                &policy(&["local_only"])
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn item_policies_are_configurable() {
+        let any = policy(&["rhost=any"]);
+        assert!(classify(Some("localhost"), Some("tty1"), None, &any).is_some());
+        let ignore = policy(&["rhost=ignore", "tty=ignore"]);
+        assert_eq!(
+            classify(Some("203.0.113.7"), Some("ssh"), None, &ignore),
+            None
+        );
+
+        let local = policy(&["tty=local"]);
+        assert!(classify(None, Some("/dev/pts/3"), None, &local).is_some());
+        assert!(classify(None, None, None, &local).is_some());
+        assert_eq!(classify(None, Some("/dev/tty2"), None, &local), None);
+        assert_eq!(classify(None, Some(":1"), None, &local), None);
+
+        // A remote logind session is skipped whatever the item policies say.
+        let remote = LogindSession {
+            remote: true,
+            seat: None,
+        };
+        assert!(classify(None, Some("tty1"), Some(&remote), &ignore).is_some());
+    }
+
+    #[test]
+    fn parses_logind_state() {
+        let cgroup = "0::/user.slice/user-1000.slice/session-4.scope\n";
+        assert_eq!(session_id(cgroup), Some("4"));
+        assert_eq!(session_id("0::/system.slice/sshd.service\n"), None);
+
+        let state = "# This is private data.\nUID=1000\nREMOTE=0\nSEAT=seat0\nTTY=tty2\n";
+        assert_eq!(
+            parse_session_file(state),
+            LogindSession {
+                remote: false,
+                seat: Some("seat0".into())
+            }
+        );
+        assert!(parse_session_file("REMOTE=1\nSEAT=\n").remote);
+    }
+}
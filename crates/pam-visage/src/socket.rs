@@ -0,0 +1,141 @@
+//! `socket=PATH` — reach visaged without D-Bus.
+//!
+//! Used only when the system bus cannot be reached, e.g. an initramfs unlock
+//! or a greeter started before the broker. The daemon listens on the same
+//! path when `VISAGE_SOCKET_PATH` is set. One request per connection, framed
+//! as a 4-byte big-endian length and NUL-separated fields (see the daemon's
+//! `socket` module). There are no progress messages; a verify sent with a
+//! cookie is cancelled with a `cancel` request on a second connection.
+
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::call_error::CallError;
+
+/// Largest reply accepted from the daemon.
+const MAX_FRAME_LEN: usize = 4096;
+/// Slack on top of the verify timeout for the daemon to reply.
+const REPLY_GRACE: Duration = Duration::from_secs(2);
+
+/// Verify the account named `username` (exact bytes, not necessarily UTF-8)
+/// over the socket at `path`, declaring the match a `second_factor` if so and
+/// naming it `cookie` for [`cancel`].
+/// Same result shape as the D-Bus `VerifyWithOptions` call: `(matched, reason)`;
+/// the daemon's error text carries the same D-Bus error name.
+pub(crate) fn verify(
+    path: &str,
+    username: &[u8],
+    timeout_secs: u64,
+    second_factor: bool,
+    cookie: Option<&str>,
+) -> Result<(bool, String), CallError> {
+    let mut request = [
+        &b"verify\0"[..],
+        username,
+        format!("\0{timeout_secs}").as_bytes(),
+        if second_factor {
+            &b"\0second_factor"[..]
+        } else {
+            &[]
+        },
+    ]
+    .concat();
+    if let Some(cookie) = cookie {
+        request.extend_from_slice(format!("\0cookie={cookie}").as_bytes());
+    }
+    let reply = call(
+        path,
+        &request,
+        Duration::from_secs(timeout_secs) + REPLY_GRACE,
+    )?;
+    parse_reply(&reply)
+}
+
+/// Stop the verify sent with `cookie`, waiting at most `timeout` for the
+/// daemon. Same result as the D-Bus `Cancel` call.
+pub(crate) fn cancel(path: &str, cookie: &str, timeout: Duration) -> Result<bool, CallError> {
+    let reply = call(path, format!("cancel\0{cookie}").as_bytes(), timeout)?;
+    match reply.split('\0').collect::<Vec<_>>().as_slice() {
+        ["ok", cancelled @ ("0" | "1")] => Ok(*cancelled == "1"),
+        ["error", message] => Err(CallError::from_text(message)),
+        _ => Err(CallError::Other("malformed reply from visaged".into())),
+    }
+}
+
+/// Send one request over a new connection and read the reply.
+fn call(path: &str, request: &[u8], reply_timeout: Duration) -> Result<String, CallError> {
+    let mut stream = UnixStream::connect(path).map_err(CallError::unreachable)?;
+    stream.set_read_timeout(Some(reply_timeout))?;
+    stream.set_write_timeout(Some(REPLY_GRACE))?;
+    stream.write_all(&(request.len() as u32).to_be_bytes())?;
+    stream.write_all(request)?;
+
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(CallError::Other("reply too large".into()));
+    }
+    let mut reply = vec![0; len];
+    stream.read_exact(&mut reply)?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+fn parse_reply(reply: &str) -> Result<(bool, String), CallError> {
+    let fields: Vec<&str> = reply.split('\0').collect();
+    match fields.as_slice() {
+        ["ok", matched @ ("0" | "1"), reason] => Ok((*matched == "1", reason.to_string())),
+        ["error", message] => Err(CallError::from_text(message)),
+        _ => Err(CallError::Other("malformed reply from visaged".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::ErrorClass;
+
+    #[test]
+    fn replies_are_parsed() {
+        assert_eq!(parse_reply("ok\x001\0").unwrap(), (true, String::new()));
+        assert_eq!(
+            parse_reply("ok\x000\0too_dark").unwrap(),
+            (false, "too_dark".to_string())
+        );
+        assert_eq!(
+            parse_reply("error\0rate limited").unwrap_err().to_string(),
+            "rate limited"
+        );
+        assert_eq!(
+            parse_reply("error\0org.freedesktop.Visage1.Error.RateLimited: try later")
+                .unwrap_err()
+                .name(),
+            Some("org.freedesktop.Visage1.Error.RateLimited")
+        );
+        assert!(parse_reply("ok\x002\0").is_err());
+        assert!(parse_reply("").is_err());
+    }
+
+    #[test]
+    fn missing_or_dead_socket_is_unreachable() {
+        let dir = std::env::temp_dir().join(format!("pam-visage-sock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("visaged.sock");
+        let _ = std::fs::remove_file(&path);
+        let path = path.to_str().unwrap();
+        // Nothing at the path (ENOENT), then a socket nobody listens on
+        // (ECONNREFUSED).
+        let missing = verify(path, b"alice", 1, false, None).unwrap_err();
+        drop(std::os::unix::net::UnixListener::bind(path).unwrap());
+        let refused = verify(path, b"alice", 1, false, None).unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        for e in [missing, refused] {
+            assert_eq!(
+                e.name().and_then(ErrorClass::of_error),
+                Some(ErrorClass::Unreachable),
+                "{e}"
+            );
+        }
+    }
+}
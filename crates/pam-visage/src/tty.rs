@@ -0,0 +1,237 @@
+//! Read the `race` password from the terminal a keystroke at a time.
+//!
+//! A terminal in canonical mode hands input to a reader only once Enter is
+//! pressed, so a conversation function blocked in `read` cannot tell that the
+//! user has started typing. When the application runs on a terminal, `race`
+//! mode reads the password itself instead: from `/dev/tty`, in non-canonical
+//! mode with echo off, doing the line editing the terminal would have done
+//! (erase, kill, end-of-file on an empty line). Signal characters keep
+//! working, so Ctrl-C interrupts the application as usual.
+//!
+//! The read waits on the terminal and on a pipe together, so another thread
+//! can stop it with [`Stop`] without cancelling the thread.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::OpenOptionsExt;
+
+/// Longest password kept; further keystrokes are dropped (`PAM_MAX_RESP_SIZE`).
+const MAX_PASSWORD_LEN: usize = 512;
+
+/// The application's terminal. Dropping it restores the terminal settings
+/// it was opened with.
+pub(crate) struct Terminal {
+    tty: File,
+    saved: libc::termios,
+    stop: File,
+}
+
+/// Stops a [`Terminal::read_password`] running on another thread.
+pub(crate) struct Stop(File);
+
+impl Stop {
+    pub(crate) fn stop(&self) {
+        let _ = (&self.0).write(&[0]);
+    }
+}
+
+impl Terminal {
+    /// `/dev/tty`, if the application's stdin is a terminal.
+    pub(crate) fn open() -> Option<(Self, Stop)> {
+        // SAFETY: isatty only inspects the descriptor.
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            return None;
+        }
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open("/dev/tty")
+            .ok()?;
+        // SAFETY: tcgetattr fills the zeroed termios on success.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(tty.as_raw_fd(), &mut saved) } != 0 {
+            return None;
+        }
+        let mut fds = [0; 2];
+        // SAFETY: pipe2 fills both descriptors on success.
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return None;
+        }
+        // SAFETY: both descriptors were just created and are owned by nobody else.
+        let (read_end, write_end) =
+            unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        let terminal = Self {
+            tty,
+            saved,
+            stop: File::from(read_end),
+        };
+        Some((terminal, Stop(File::from(write_end))))
+    }
+
+    /// Show `prompt` and read a password. `on_key` runs once, at the first
+    /// keystroke. `None` if the line is abandoned, the terminal fails, or
+    /// [`Stop::stop`] is called.
+    pub(crate) fn read_password(self, prompt: &str, on_key: impl FnOnce()) -> Option<Password> {
+        let mut raw = self.saved;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a complete termios read from this terminal.
+        if unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSAFLUSH, &raw) } != 0 {
+            return None;
+        }
+        (&self.tty).write_all(prompt.as_bytes()).ok()?;
+
+        let keys = Keys::of(&self.saved);
+        let mut line = Password(Vec::with_capacity(MAX_PASSWORD_LEN + 1));
+        let mut on_key = Some(on_key);
+        let mut chunk = [0u8; 64];
+        loop {
+            let mut fds = [self.tty.as_raw_fd(), self.stop.as_raw_fd()].map(|fd| libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+            // SAFETY: `fds` is an array of two initialized pollfds.
+            if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } < 0 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return None;
+            }
+            if fds[1].revents != 0 {
+                return None;
+            }
+            let n = match (&self.tty).read(&mut chunk) {
+                Ok(0) => return None,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return None,
+            };
+            if let Some(on_key) = on_key.take() {
+                on_key();
+            }
+            let done = chunk[..n].iter().find_map(|&b| keys.apply(&mut line.0, b));
+            scrub(&mut chunk);
+            match done {
+                Some(submitted) => {
+                    // Echo is off, so the Enter did not move the cursor either.
+                    let _ = (&self.tty).write_all(b"\n");
+                    line.0.push(0);
+                    return submitted.then_some(line);
+                }
+                None => continue,
+            }
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // SAFETY: `saved` was read from this terminal by tcgetattr.
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.saved) };
+    }
+}
+
+/// A password typed at the terminal, NUL-terminated and scrubbed when dropped.
+pub(crate) struct Password(Vec<u8>);
+
+impl Password {
+    /// The password as a C string, for `pam_set_item`.
+    pub(crate) fn as_ptr(&self) -> *const libc::c_void {
+        self.0.as_ptr().cast()
+    }
+}
+
+impl Drop for Password {
+    fn drop(&mut self) {
+        // Erased keystrokes linger past the length, so scrub the whole buffer.
+        // SAFETY: the allocation is `capacity` bytes long and owned by `self`.
+        unsafe { libc::explicit_bzero(self.0.as_mut_ptr().cast(), self.0.capacity()) };
+    }
+}
+
+fn scrub(bytes: &mut [u8]) {
+    // SAFETY: `bytes` is a valid, writable slice.
+    unsafe { libc::explicit_bzero(bytes.as_mut_ptr().cast(), bytes.len()) };
+}
+
+/// The terminal's line-editing characters.
+struct Keys {
+    erase: u8,
+    kill: u8,
+    eof: u8,
+}
+
+impl Keys {
+    fn of(term: &libc::termios) -> Self {
+        Self {
+            erase: term.c_cc[libc::VERASE],
+            kill: term.c_cc[libc::VKILL],
+            eof: term.c_cc[libc::VEOF],
+        }
+    }
+
+    /// Apply one typed byte to `line`: `Some(true)` when Enter submits it,
+    /// `Some(false)` when end-of-file on an empty line abandons it.
+    fn apply(&self, line: &mut Vec<u8>, byte: u8) -> Option<bool> {
+        match byte {
+            // A disabled editing character reads as NUL, which a C-string
+            // password cannot hold anyway.
+            0 => {}
+            b'\n' | b'\r' => return Some(true),
+            0x7f | 0x08 => {
+                line.pop();
+            }
+            b if b == self.erase => {
+                line.pop();
+            }
+            b if b == self.kill => line.clear(),
+            b if b == self.eof && line.is_empty() => return Some(false),
+            b if b == self.eof => {}
+            b if line.len() < MAX_PASSWORD_LEN => line.push(b),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &Keys, input: &[u8]) -> (Vec<u8>, Option<bool>) {
+        let mut line = Vec::new();
+        let done = input.iter().find_map(|&b| keys.apply(&mut line, b));
+        (line, done)
+    }
+
+    #[test]
+    fn keystrokes_are_edited_like_a_canonical_terminal() {
+        let keys = Keys {
+            erase: 0x7f,
+            kill: 0x15,
+            eof: 0x04,
+        };
+        assert_eq!(
+            typed(&keys, b"hunter2\n"),
+            (b"hunter2".to_vec(), Some(true))
+        );
+        assert_eq!(
+            typed(&keys, b"huntx\x7fer\x082\r"),
+            (b"hunte2".to_vec(), Some(true))
+        );
+        assert_eq!(
+            typed(&keys, b"wrong\x15right\n"),
+            (b"right".to_vec(), Some(true))
+        );
+        assert_eq!(typed(&keys, b"\x04"), (Vec::new(), Some(false)));
+        assert_eq!(typed(&keys, b"ab\x04c\n"), (b"abc".to_vec(), Some(true)));
+        assert_eq!(typed(&keys, b"ab"), (b"ab".to_vec(), None));
+
+        let long = vec![b'a'; MAX_PASSWORD_LEN + 10];
+        assert_eq!(typed(&keys, &long).0.len(), MAX_PASSWORD_LEN);
+    }
+}
@@ -0,0 +1,101 @@
+//! `allow_users=` / `deny_users=` — per-account policy, checked before D-Bus.
+//!
+//! Lets an admin exempt shared or service accounts (`backup`, `deploy`, a
+//! kiosk login) from the camera without touching the daemon's database.
+//! Entries are user names, or absolute paths to a file listing one name per
+//! line (`#` starts a comment). A list file must be a regular file owned by
+//! root and not writable by group or others; otherwise anyone able to edit it
+//! could opt an account in or out. A file that cannot be trusted or read
+//! makes the policy fail closed: face auth is skipped and the stack falls
+//! through to the password.
+
+use crate::args::PamArgs;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+/// Whether face auth may run for `username`. `Err` carries the reason a list
+/// file was rejected.
+pub(crate) fn allows_user(args: &PamArgs, username: &str) -> Result<bool, String> {
+    evaluate(args, username, read_list_file)
+}
+
+fn evaluate(
+    args: &PamArgs,
+    username: &str,
+    read: impl Fn(&str) -> Result<String, String>,
+) -> Result<bool, String> {
+    if list_contains(&args.deny_users, username, &read)? {
+        return Ok(false);
+    }
+    match &args.allow_users {
+        Some(allow) => list_contains(allow, username, &read),
+        None => Ok(true),
+    }
+}
+
+fn list_contains(
+    entries: &[String],
+    username: &str,
+    read: &impl Fn(&str) -> Result<String, String>,
+) -> Result<bool, String> {
+    for entry in entries {
+        let found = if entry.starts_with('/') {
+            read(entry)?
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or("").trim())
+                .any(|name| name == username)
+        } else {
+            entry == username
+        };
+        if found {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn read_list_file(path: &str) -> Result<String, String> {
+    let meta = fs::metadata(path).map_err(|e| format!("{path}: {e}"))?;
+    if !meta.file_type().is_file() {
+        return Err(format!("{path}: not a regular file"));
+    }
+    if meta.uid() != 0 || meta.mode() & 0o022 != 0 {
+        return Err(format!(
+            "{path}: must be owned by root and not group/world-writable"
+        ));
+    }
+    fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_file(path: &str) -> Result<String, String> {
+        match path {
+            "/etc/security/visage.deny" => {
+                Ok("# service accounts\nbackup\n  deploy  # CI\n".into())
+            }
+            _ => Err(format!("{path}: untrusted")),
+        }
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let (args, _) = PamArgs::parse(&["allow_users=alice,bob", "deny_users=bob"]);
+        assert_eq!(evaluate(&args, "alice", fake_file), Ok(true));
+        assert_eq!(evaluate(&args, "bob", fake_file), Ok(false));
+        assert_eq!(evaluate(&args, "carol", fake_file), Ok(false));
+        assert_eq!(evaluate(&PamArgs::default(), "carol", fake_file), Ok(true));
+    }
+
+    #[test]
+    fn list_files_are_read_and_fail_closed() {
+        let (args, _) = PamArgs::parse(&["deny_users=/etc/security/visage.deny"]);
+        assert_eq!(evaluate(&args, "deploy", fake_file), Ok(false));
+        assert_eq!(evaluate(&args, "alice", fake_file), Ok(true));
+
+        let (args, _) = PamArgs::parse(&["deny_users=/tmp/visage.deny"]);
+        assert!(evaluate(&args, "alice", fake_file).is_err());
+    }
+}
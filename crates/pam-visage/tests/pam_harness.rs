@@ -0,0 +1,514 @@
+//! End-to-end tests of the built module, without a camera or a real daemon.
+//!
+//! The module is loaded the way a PAM application loads it: a handle from
+//! libpam's `pam_start_confdir` with a private pam.d directory (what
+//! pam_wrapper's config redirection does, built into Linux-PAM since 1.4), a
+//! recording conversation, and `libpam_visage.so` from the target directory.
+//! `visaged` is played by a mock `org.freedesktop.Visage1` service on a
+//! private `dbus-daemon`, which the module reaches through
+//! `DBUS_SYSTEM_BUS_ADDRESS`. Its answer is scripted by user name:
+//!
+//! | User | Reply |
+//! |------|-------|
+//! | `alice` | match, after a `capture_started` progress signal |
+//! | `bob` | `below_threshold` |
+//! | `carol` | `no_face` on the first call, then a match |
+//! | `dave` | a `camera busy` D-Bus error |
+//! | `erin` | `too_dark` |
+//!
+//! Most tests call `pam_sm_authenticate` directly on the handle, to see the
+//! module's exact return code; one runs a whole stack through
+//! `pam_authenticate`. Without `dbus-daemon` or a libpam that has
+//! `pam_start_confdir`, the tests are skipped with a note on stderr.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io::{BufRead, BufReader};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
+
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
+
+const PAM_SUCCESS: c_int = 0;
+const PAM_AUTH_ERR: c_int = 7;
+const PAM_AUTHINFO_UNAVAIL: c_int = 9;
+const PAM_IGNORE: c_int = 25;
+const PAM_ERROR_MSG: c_int = 3;
+const PAM_TEXT_INFO: c_int = 4;
+
+const SERVICE: &str = "visage-test";
+
+#[repr(C)]
+struct PamMessage {
+    msg_style: c_int,
+    msg: *const c_char,
+}
+
+#[repr(C)]
+struct PamResponse {
+    resp: *mut c_char,
+    resp_retcode: c_int,
+}
+
+#[repr(C)]
+struct PamConv {
+    conv: unsafe extern "C" fn(
+        c_int,
+        *mut *const PamMessage,
+        *mut *mut PamResponse,
+        *mut c_void,
+    ) -> c_int,
+    appdata_ptr: *mut c_void,
+}
+
+type PamStartConfdir = unsafe extern "C" fn(
+    *const c_char,
+    *const c_char,
+    *const PamConv,
+    *const c_char,
+    *mut *mut c_void,
+) -> c_int;
+type PamEnd = unsafe extern "C" fn(*mut c_void, c_int) -> c_int;
+type PamAuthenticate = unsafe extern "C" fn(*mut c_void, c_int) -> c_int;
+type PamSmAuthenticate =
+    unsafe extern "C" fn(*mut c_void, c_int, c_int, *const *const c_char) -> c_int;
+
+/// Messages the module sent, as `(style, text)`.
+type Transcript = Mutex<Vec<(c_int, String)>>;
+
+/// Conversation that records every message and answers with empty responses.
+unsafe extern "C" fn record(
+    num_msg: c_int,
+    msg: *mut *const PamMessage,
+    resp: *mut *mut PamResponse,
+    appdata_ptr: *mut c_void,
+) -> c_int {
+    // SAFETY: appdata_ptr is the Transcript passed to pam_start_confdir, and
+    // libpam hands over num_msg valid message pointers.
+    let transcript = unsafe { &*(appdata_ptr as *const Transcript) };
+    let count = usize::try_from(num_msg).unwrap_or(0);
+    for i in 0..count {
+        let message = unsafe { &**msg.add(i) };
+        let text = unsafe { CStr::from_ptr(message.msg) }
+            .to_string_lossy()
+            .into_owned();
+        transcript.lock().unwrap().push((message.msg_style, text));
+    }
+    // The caller frees the responses with free(3).
+    unsafe { *resp = libc::calloc(count.max(1), std::mem::size_of::<PamResponse>()).cast() };
+    PAM_SUCCESS
+}
+
+/// What one authentication returned and said.
+#[derive(Debug)]
+struct Outcome {
+    code: c_int,
+    messages: Vec<(c_int, String)>,
+}
+
+impl Outcome {
+    fn said(&self, style: c_int, text: &str) -> bool {
+        self.messages.iter().any(|(s, t)| *s == style && t == text)
+    }
+}
+
+struct Harness {
+    start: PamStartConfdir,
+    end: PamEnd,
+    authenticate: PamAuthenticate,
+    sm_authenticate: PamSmAuthenticate,
+    confdir: CString,
+}
+
+impl Harness {
+    /// Call the module's `pam_sm_authenticate` for `user` with pam.d `args`.
+    fn module(&self, user: &str, args: &[&str]) -> Outcome {
+        let args: Vec<CString> = args.iter().map(|a| CString::new(*a).unwrap()).collect();
+        let argv: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+        self.with_handle(user, |pamh| {
+            // SAFETY: pamh is a live handle and argv holds argc C strings.
+            unsafe { (self.sm_authenticate)(pamh, 0, argv.len() as c_int, argv.as_ptr()) }
+        })
+    }
+
+    /// Call `pam_sm_authenticate` once per entry of `lines` on one handle, as
+    /// consecutive lines of a stack, and report the last result.
+    fn modules(&self, user: &str, lines: &[&[&str]]) -> Outcome {
+        let lines: Vec<Vec<CString>> = lines
+            .iter()
+            .map(|args| args.iter().map(|a| CString::new(*a).unwrap()).collect())
+            .collect();
+        self.with_handle(user, |pamh| {
+            let mut code = PAM_IGNORE;
+            for args in &lines {
+                let argv: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+                // SAFETY: pamh is a live handle and argv holds argc C strings.
+                code =
+                    unsafe { (self.sm_authenticate)(pamh, 0, argv.len() as c_int, argv.as_ptr()) };
+            }
+            code
+        })
+    }
+
+    /// Run the `visage-test` stack (the module as `sufficient`, then
+    /// `pam_deny`) through `pam_authenticate`.
+    fn stack(&self, user: &str) -> Outcome {
+        // SAFETY: pamh is a live handle.
+        self.with_handle(user, |pamh| unsafe { (self.authenticate)(pamh, 0) })
+    }
+
+    fn with_handle(&self, user: &str, call: impl FnOnce(*mut c_void) -> c_int) -> Outcome {
+        let transcript: Box<Transcript> = Box::default();
+        let conv = PamConv {
+            conv: record,
+            appdata_ptr: &*transcript as *const Transcript as *mut c_void,
+        };
+        let service = CString::new(SERVICE).unwrap();
+        let user = CString::new(user).unwrap();
+        let mut pamh = std::ptr::null_mut();
+        // SAFETY: every pointer outlives the handle, which is ended below.
+        let ret = unsafe {
+            (self.start)(
+                service.as_ptr(),
+                user.as_ptr(),
+                &conv,
+                self.confdir.as_ptr(),
+                &mut pamh,
+            )
+        };
+        assert_eq!(ret, PAM_SUCCESS, "pam_start_confdir");
+        let code = call(pamh);
+        // SAFETY: pamh came from pam_start_confdir and is not used again.
+        unsafe { (self.end)(pamh, code) };
+        Outcome {
+            code,
+            messages: transcript.into_inner().unwrap(),
+        }
+    }
+}
+
+/// The shared harness, or `None` (with the reason on stderr) when this
+/// machine cannot run it.
+fn harness() -> Option<&'static Harness> {
+    static HARNESS: OnceLock<Result<Harness, String>> = OnceLock::new();
+    match HARNESS.get_or_init(setup) {
+        Ok(harness) => Some(harness),
+        Err(reason) => {
+            eprintln!("skipping PAM harness test: {reason}");
+            None
+        }
+    }
+}
+
+fn setup() -> Result<Harness, String> {
+    let module = module_path()?;
+    let libpam = dlopen(Path::new("libpam.so.0"))?;
+    let start = dlsym(libpam, "pam_start_confdir")?;
+    let end = dlsym(libpam, "pam_end")?;
+    let authenticate = dlsym(libpam, "pam_authenticate")?;
+    let sm_authenticate = dlsym(dlopen(&module)?, "pam_sm_authenticate")?;
+
+    let confdir = module.with_file_name("pam-harness.d");
+    std::fs::create_dir_all(&confdir).map_err(|e| e.to_string())?;
+    std::fs::write(
+        confdir.join(SERVICE),
+        format!(
+            "auth sufficient {} ignore_lid\nauth required pam_deny.so\n",
+            module.display()
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let address = start_mock_daemon()?;
+    // Set once, before any test reaches the module.
+    std::env::set_var("DBUS_SYSTEM_BUS_ADDRESS", address);
+
+    // SAFETY: the symbols have the signatures of <security/pam_appl.h> and
+    // <security/pam_modules.h>; both libraries stay loaded.
+    unsafe {
+        Ok(Harness {
+            start: std::mem::transmute::<*mut c_void, PamStartConfdir>(start),
+            end: std::mem::transmute::<*mut c_void, PamEnd>(end),
+            authenticate: std::mem::transmute::<*mut c_void, PamAuthenticate>(authenticate),
+            sm_authenticate: std::mem::transmute::<*mut c_void, PamSmAuthenticate>(sm_authenticate),
+            confdir: CString::new(confdir.as_os_str().as_encoded_bytes()).unwrap(),
+        })
+    }
+}
+
+/// `libpam_visage.so` next to this test's `deps/` directory.
+fn module_path() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let path = exe
+        .parent()
+        .and_then(Path::parent)
+        .map(|dir| dir.join("libpam_visage.so"))
+        .ok_or("no target directory")?;
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("{} not built", path.display()))
+    }
+}
+
+fn dlopen(path: &Path) -> Result<*mut c_void, String> {
+    let name = CString::new(path.as_os_str().as_encoded_bytes()).unwrap();
+    // SAFETY: name is a valid C string; the library is never closed.
+    let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+    if handle.is_null() {
+        Err(format!("cannot load {}", path.display()))
+    } else {
+        Ok(handle)
+    }
+}
+
+fn dlsym(handle: *mut c_void, symbol: &str) -> Result<*mut c_void, String> {
+    let name = CString::new(symbol).unwrap();
+    // SAFETY: handle came from dlopen and name is a valid C string.
+    let sym = unsafe { libc::dlsym(handle, name.as_ptr()) };
+    if sym.is_null() {
+        Err(format!("{symbol} not found"))
+    } else {
+        Ok(sym)
+    }
+}
+
+/// Start a private bus and the mock service on a thread that lives as long
+/// as the test process; returns the bus address.
+fn start_mock_daemon() -> Result<String, String> {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut command = Command::new("dbus-daemon");
+        command
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        // SAFETY: prctl is async-signal-safe. The bus dies with this thread,
+        // which runs until the test process exits.
+        unsafe {
+            command.pre_exec(|| {
+                libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL);
+                Ok(())
+            });
+        }
+        let mut bus = match command.spawn() {
+            Ok(bus) => bus,
+            Err(e) => {
+                let _ = tx.send(Err(format!("cannot run dbus-daemon: {e}")));
+                return;
+            }
+        };
+        let mut address = String::new();
+        if let Some(stdout) = bus.stdout.take() {
+            let _ = BufReader::new(stdout).read_line(&mut address);
+        }
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            let _ = tx.send(Err("dbus-daemon printed no address".to_string()));
+            return;
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let connection = async {
+                zbus::connection::Builder::address(address.as_str())?
+                    .name("org.freedesktop.Visage1")?
+                    .serve_at("/org/freedesktop/Visage1", MockVisage::default())?
+                    .build()
+                    .await
+            }
+            .await;
+            match connection {
+                Ok(_connection) => {
+                    let _ = tx.send(Ok(address));
+                    std::future::pending::<()>().await;
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(format!("mock service: {e}")));
+                }
+            }
+        });
+    });
+    rx.recv().map_err(|e| e.to_string())?
+}
+
+#[derive(Default)]
+struct MockVisage {
+    carol_calls: AtomicU32,
+}
+
+#[zbus::interface(name = "org.freedesktop.Visage1")]
+impl MockVisage {
+    async fn verify_with_options(
+        &self,
+        user: &str,
+        _options: HashMap<String, OwnedValue>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<(bool, String)> {
+        Self::verify_progress(&emitter, user, "capture_started").await?;
+        let reply = |matched: bool, reason: &str| Ok((matched, reason.to_string()));
+        match user {
+            "alice" => reply(true, ""),
+            "bob" => reply(false, "below_threshold"),
+            "carol" if self.carol_calls.fetch_add(1, Ordering::SeqCst) == 0 => {
+                reply(false, "no_face")
+            }
+            "carol" => reply(true, ""),
+            "dave" => Err(zbus::fdo::Error::Failed("camera busy".to_string())),
+            "erin" => reply(false, "too_dark"),
+            _ => Err(zbus::fdo::Error::Failed(format!(
+                "no enrolled models for user '{user}'"
+            ))),
+        }
+    }
+
+    #[zbus(signal)]
+    async fn verify_progress(
+        emitter: &SignalEmitter<'_>,
+        user: &str,
+        stage: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[test]
+fn match_succeeds_with_progress_and_confirmation() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("alice", &["ignore_lid"]);
+    assert_eq!(outcome.code, PAM_SUCCESS, "{outcome:?}");
+    assert!(
+        outcome.said(PAM_TEXT_INFO, "Visage: look at the camera"),
+        "{outcome:?}"
+    );
+    assert!(
+        outcome.said(PAM_TEXT_INFO, "Visage: face recognized"),
+        "{outcome:?}"
+    );
+}
+
+#[test]
+fn quiet_keeps_the_conversation_silent() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("alice", &["ignore_lid", "quiet"]);
+    assert_eq!(outcome.code, PAM_SUCCESS);
+    assert!(outcome.messages.is_empty(), "{outcome:?}");
+}
+
+#[test]
+fn rejection_falls_through_unless_strict() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("bob", &["ignore_lid"]);
+    assert_eq!(outcome.code, PAM_IGNORE);
+    assert!(
+        outcome.said(PAM_ERROR_MSG, "Visage: face not recognized"),
+        "{outcome:?}"
+    );
+
+    let outcome = harness.module("bob", &["ignore_lid", "strict"]);
+    assert_eq!(outcome.code, PAM_AUTH_ERR);
+}
+
+#[test]
+fn retryable_failure_is_retried_with_a_prompt() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("carol", &["ignore_lid", "max_tries=2"]);
+    assert_eq!(outcome.code, PAM_SUCCESS, "{outcome:?}");
+    assert!(
+        outcome.said(PAM_TEXT_INFO, "Visage: no face detected"),
+        "{outcome:?}"
+    );
+    assert!(
+        outcome.said(PAM_TEXT_INFO, "Visage: attempt 2 of 2 — look at the camera"),
+        "{outcome:?}"
+    );
+}
+
+#[test]
+fn hints_are_informational() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("erin", &["ignore_lid", "strict"]);
+    assert_eq!(outcome.code, PAM_IGNORE, "too_dark is not a rejection");
+    assert!(
+        outcome.said(
+            PAM_TEXT_INFO,
+            "Visage: face unlock unavailable: not enough light"
+        ),
+        "{outcome:?}"
+    );
+}
+
+#[test]
+fn on_error_maps_daemon_errors() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("dave", &["ignore_lid"]);
+    assert_eq!(outcome.code, PAM_IGNORE);
+    assert!(
+        outcome.said(
+            PAM_ERROR_MSG,
+            "Visage: camera busy — in use by another application"
+        ),
+        "{outcome:?}"
+    );
+
+    let outcome = harness.module("dave", &["ignore_lid", "on_error=camera_busy:unavail"]);
+    assert_eq!(outcome.code, PAM_AUTHINFO_UNAVAIL);
+    let outcome = harness.module("bob", &["ignore_lid", "on_error=below_threshold:fail"]);
+    assert_eq!(outcome.code, PAM_AUTH_ERR);
+}
+
+#[test]
+fn missing_camera_is_reported_without_a_verify() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("alice", &["ignore_lid", "device=/dev/visage-harness-none"]);
+    assert_eq!(outcome.code, PAM_IGNORE);
+    assert!(
+        outcome.said(PAM_ERROR_MSG, "Visage: camera not found"),
+        "{outcome:?}"
+    );
+    assert!(!outcome.said(PAM_TEXT_INFO, "Visage: look at the camera"));
+}
+
+#[test]
+fn malformed_arguments_are_ignored() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("alice", &["ignore_lid", "max_tries=lots", "bogus"]);
+    assert_eq!(outcome.code, PAM_SUCCESS, "{outcome:?}");
+}
+
+#[test]
+fn second_factor_never_authenticates_alone() {
+    let Some(harness) = harness() else { return };
+    let outcome = harness.module("alice", &["ignore_lid", "second_factor"]);
+    assert_eq!(outcome.code, PAM_IGNORE);
+    let outcome = harness.module("bob", &["ignore_lid", "second_factor"]);
+    assert_eq!(outcome.code, PAM_AUTH_ERR);
+}
+
+#[test]
+fn require_face_passes_only_after_a_recorded_match() {
+    let Some(harness) = harness() else { return };
+    let face: &[&str] = &["ignore_lid", "second_factor"];
+    let outcome = harness.modules("alice", &[face, &["require_face"]]);
+    assert_eq!(outcome.code, PAM_SUCCESS, "{outcome:?}");
+    let outcome = harness.modules("bob", &[face, &["require_face"]]);
+    assert_eq!(outcome.code, PAM_AUTH_ERR);
+    // Nothing recorded: no face line ran.
+    assert_eq!(
+        harness.module("alice", &["require_face"]).code,
+        PAM_AUTH_ERR
+    );
+}
+
+#[test]
+fn stack_falls_through_to_the_next_module() {
+    let Some(harness) = harness() else { return };
+    assert_eq!(harness.stack("alice").code, PAM_SUCCESS);
+    // pam_deny answers once the face is not recognized.
+    assert_eq!(harness.stack("bob").code, PAM_AUTH_ERR);
+}
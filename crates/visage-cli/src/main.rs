@@ -1,8 +1,15 @@
+#[cfg(feature = "setup")]
 mod setup;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::time::Duration;
+use visage_hw::Calibration;
+use zbus::export::futures_core::Stream;
 
 #[zbus::proxy(
     interface = "org.freedesktop.Visage1",
@@ -11,10 +18,90 @@ use std::time::Duration;
 )]
 trait Visage {
     async fn enroll(&self, user: &str, label: &str) -> zbus::fdo::Result<String>;
-    async fn verify(&self, user: &str) -> zbus::fdo::Result<bool>;
+    async fn verify_with_reason(&self, user: &str) -> zbus::fdo::Result<(bool, String)>;
+    async fn verify_detailed(
+        &self,
+        user: &str,
+    ) -> zbus::fdo::Result<HashMap<String, zbus::zvariant::OwnedValue>>;
+    async fn identify_any(&self) -> zbus::fdo::Result<(String, String)>;
     async fn status(&self) -> zbus::fdo::Result<String>;
+    async fn health(&self) -> zbus::fdo::Result<String>;
+    async fn get_metrics(&self) -> zbus::fdo::Result<String>;
+    async fn stats(&self, since_secs: u64) -> zbus::fdo::Result<String>;
+    async fn export_scores(&self, tenant: &str, user: &str) -> zbus::fdo::Result<String>;
+    async fn export_user(&self, user: &str, passphrase: &str) -> zbus::fdo::Result<Vec<u8>>;
+    async fn import_user(
+        &self,
+        user: &str,
+        bundle: &[u8],
+        passphrase: &str,
+    ) -> zbus::fdo::Result<Vec<String>>;
+    async fn reload_quirks(&self) -> zbus::fdo::Result<String>;
+    async fn debug_profile(&self, user: &str) -> zbus::fdo::Result<String>;
     async fn list_models(&self, user: &str) -> zbus::fdo::Result<String>;
     async fn remove_model(&self, user: &str, model_id: &str) -> zbus::fdo::Result<bool>;
+    async fn enroll_in(&self, tenant: &str, user: &str, label: &str) -> zbus::fdo::Result<String>;
+    async fn verify_with_options(
+        &self,
+        user: &str,
+        options: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::fdo::Result<(bool, String)>;
+    async fn identify_in(&self, tenant: &str) -> zbus::fdo::Result<(String, String)>;
+    async fn list_models_in(&self, tenant: &str, user: &str) -> zbus::fdo::Result<String>;
+    async fn remove_model_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+    ) -> zbus::fdo::Result<bool>;
+    async fn set_threshold_offset(
+        &self,
+        user: &str,
+        model_id: &str,
+        offset: f64,
+    ) -> zbus::fdo::Result<bool>;
+    async fn set_threshold_offset_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        offset: f64,
+    ) -> zbus::fdo::Result<bool>;
+    async fn set_authtok(&self, user: &str, secret: &str) -> zbus::fdo::Result<()>;
+    async fn clear_authtok(&self, user: &str) -> zbus::fdo::Result<bool>;
+    async fn enroll_begin(
+        &self,
+        user: &str,
+        label: &str,
+        options: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::fdo::Result<String>;
+    async fn enroll_step(&self, session: &str) -> zbus::fdo::Result<String>;
+    async fn enroll_commit(&self, session: &str) -> zbus::fdo::Result<Vec<String>>;
+    async fn enroll_abort(&self, session: &str) -> zbus::fdo::Result<bool>;
+
+    #[zbus(signal)]
+    fn face_detected(&self, confidence: f64) -> zbus::Result<()>;
+    #[zbus(signal)]
+    fn enroll_progress(&self, step: u32, total: u32, hint: &str) -> zbus::Result<()>;
+    #[zbus(signal)]
+    fn gallery_outlier(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        similarity: f64,
+    ) -> zbus::Result<()>;
+    #[zbus(signal)]
+    fn model_expiring(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        purge_after: &str,
+    ) -> zbus::Result<()>;
+    #[zbus(signal)]
+    fn challenge_required(&self, tenant: &str, user: &str, narrow_matches: u32)
+        -> zbus::Result<()>;
 }
 
 #[derive(Parser)]
@@ -35,18 +122,57 @@ enum Commands {
         /// User to enroll for (defaults to $USER)
         #[arg(short, long)]
         user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+
+        /// Capture several poses (frontal, left, right, glasses on/off), one
+        /// model each, following on-screen instructions
+        #[arg(short, long)]
+        guided: bool,
+
+        /// Re-enroll: replace the user's existing models with the new ones,
+        /// in one step (nothing changes if the enrollment fails)
+        #[arg(long)]
+        replace: bool,
     },
     /// Verify your face against enrolled models
     Verify {
         /// User to verify as (defaults to $USER)
         #[arg(short, long)]
         user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+
+        /// Also print the best face's confidence, the frames captured and
+        /// skipped as too dark, and the time spent per stage
+        #[arg(short, long, conflicts_with = "tenant")]
+        details: bool,
+    },
+    /// Identify who is in front of the camera among all enrolled users
+    /// (requires VISAGE_IDENTIFY_ENABLED=1 on the daemon; root only)
+    Identify {
+        /// Identify within a named tenant instead (VISAGE_TENANTS on the daemon)
+        #[arg(short, long)]
+        tenant: Option<String>,
     },
     /// List enrolled face models
     List {
         /// User whose models to list (defaults to $USER)
         #[arg(short, long)]
         user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+
+        /// Also show who requested each enrollment, when, and how, and when
+        /// each model last matched
+        #[arg(short, long)]
+        details: bool,
     },
     /// Remove an enrolled face model
     Remove {
@@ -56,8 +182,81 @@ enum Commands {
         /// User who owns the model (defaults to $USER)
         #[arg(short, long)]
         user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+    },
+    /// Make one face model stricter (positive offset) or looser (negative)
+    /// than the similarity threshold
+    Offset {
+        /// Model ID to adjust
+        id: String,
+
+        /// Added to the threshold for this model (-0.10 to 0.30; 0 resets)
+        #[arg(allow_negative_numbers = true)]
+        offset: f64,
+
+        /// User who owns the model (defaults to $USER)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+    },
+    /// Store or clear the keyring password released by `pam_visage.so authtok`
+    Authtok {
+        #[command(subcommand)]
+        action: AuthtokAction,
+
+        /// User whose password it is (defaults to $USER)
+        #[arg(short, long, global = true)]
+        user: Option<String>,
+    },
+    /// Pairwise similarities among a user's face models and kept verify
+    /// probes (VISAGE_KEEP_PROBES on the daemon), as CSV for plotting (root)
+    ExportScores {
+        /// User whose scores to export (defaults to $USER)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(short, long)]
+        tenant: Option<String>,
+
+        /// Print the daemon's JSON export instead of CSV
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a user's face models to a passphrase-protected bundle, for
+    /// `visage import` on another machine or after a reinstall (root)
+    Export {
+        /// Bundle file to write; an existing file is not overwritten
+        file: PathBuf,
+
+        /// User whose models to export (defaults to $USER)
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// Add the face models of a `visage export` bundle to a user's models,
+    /// without enrolling again (root)
+    Import {
+        /// Bundle file written by `visage export`
+        file: PathBuf,
+
+        /// User to import the models for (defaults to $USER); need not be
+        /// the one they were exported from
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// Deployment reports for administrators (root)
+    Admin {
+        #[command(subcommand)]
+        action: AdminAction,
     },
     /// Download ONNX models required for face detection and recognition
+    #[cfg(feature = "setup")]
     Setup {
         /// Model directory (default: /var/lib/visage/models when root, ~/.local/share/visage/models otherwise)
         #[arg(short, long)]
@@ -65,6 +264,14 @@ enum Commands {
     },
     /// Show daemon status
     Status,
+    /// Check engine and database health without using the camera; exits 1 if unhealthy
+    Health,
+    /// Show verify and identify counters and stage latencies since the daemon started
+    Metrics {
+        /// Print the daemon's JSON snapshot instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
     /// List cameras and their IR emitter quirk status
     Discover,
     /// Run camera diagnostics
@@ -76,13 +283,193 @@ enum Commands {
         /// Number of frames to capture
         #[arg(short = 'n', long, default_value = "10")]
         frames: usize,
+
+        /// Directory the frames are saved to; replay it with `visage simulate`
+        #[arg(short, long, default_value = "/tmp/visage-test")]
+        out: PathBuf,
     },
+    /// Replay frames saved by `visage test` through the daemon's verify logic
+    /// against a user's gallery, printing the decision trace (root; runs
+    /// `visaged simulate`, without D-Bus or the camera)
+    Simulate {
+        /// Directory of recorded .pgm frames
+        bundle: PathBuf,
+
+        /// User whose gallery to match against (defaults to $USER)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Named tenant (VISAGE_TENANTS on the daemon); default tenant if omitted
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Tune exposure, emitter payload, orientation and CLAHE for a camera,
+    /// check them with a test capture, and save them for visaged
+    Calibrate {
+        /// Camera device path
+        #[arg(short, long, default_value = "/dev/video2")]
+        device: String,
+
+        /// Manual exposure (exposure_absolute, 100 µs units)
+        #[arg(long, conflicts_with = "auto_exposure")]
+        exposure: Option<i64>,
+
+        /// Go back to auto exposure
+        #[arg(long)]
+        auto_exposure: bool,
+
+        /// Emitter payload replacing the quirk's control bytes, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        emitter_bytes: Option<Vec<u8>>,
+
+        /// Clockwise rotation of every frame: 0, 90, 180 or 270
+        #[arg(long)]
+        rotate: Option<u16>,
+
+        /// CLAHE contrast enhancement: on or off
+        #[arg(long, value_parser = parse_switch)]
+        clahe: Option<bool>,
+
+        /// Number of frames in the test capture
+        #[arg(short = 'n', long, default_value = "10")]
+        frames: usize,
+
+        /// Calibration directory (VISAGE_CALIBRATION_DIR on the daemon)
+        #[arg(long, default_value = visage_hw::calibration::DEFAULT_CALIBRATION_DIR)]
+        dir: PathBuf,
+
+        /// Delete the saved calibration instead
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Show a desktop notification whenever visaged flags one of your face
+    /// models as unlike the others (run in your desktop session)
+    Notify,
+}
+
+#[derive(Subcommand)]
+enum AdminAction {
+    /// Success rate and latency per user, dark frames per camera, and
+    /// failures by reason, over recent verify attempts
+    Stats {
+        /// Period to report on: a number with s, m, h or d (e.g. 12h, 30d)
+        #[arg(short, long, default_value = "7d", value_parser = parse_period)]
+        since: u64,
+
+        /// Print the daemon's JSON report instead of tables
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-read the quirk files in VISAGE_QUIRKS_DIRS and look up the IR
+    /// emitter again, without restarting the daemon
+    ReloadQuirks,
+    /// Verify once while ONNX Runtime records the time of every operator,
+    /// and print where the profiles were written (needs VISAGE_PROFILE_DIR)
+    Profile {
+        /// User to verify (defaults to $USER)
+        #[arg(short, long)]
+        user: Option<String>,
+
+        /// Print the daemon's JSON reply
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthtokAction {
+    /// Store the login password (read from the terminal, or stdin)
+    Set,
+    /// Delete the stored password
+    Clear,
 }
 
 fn current_user() -> String {
     std::env::var("USER").unwrap_or_else(|_| "unknown".to_string())
 }
 
+/// Human-readable explanation for a `VerifyWithReason` failure code.
+fn describe_failure(reason: &str) -> String {
+    match reason {
+        "too_dark" => "too dark — every frame was underexposed (is the IR emitter working?)".into(),
+        "poor_quality" => "frames were blurred, moving, or overexposed — hold still".into(),
+        "no_face" => "no face detected in front of the camera".into(),
+        "face_too_small" => "face too far from the camera — move closer".into(),
+        "below_threshold" => "face not recognized".into(),
+        "liveness_failed" => "liveness check failed".into(),
+        "static_scene" => "frames looked replayed or frozen (virtual camera?)".into(),
+        "camera_error" => "camera error (see `journalctl -u visaged`)".into(),
+        "camera_busy" => "camera busy — another application is using it".into(),
+        "wrong_pose" => "head not in the requested position".into(),
+        "timeout" => "verification timed out".into(),
+        "cancelled" => "verification cancelled".into(),
+        "internal_error" => "internal daemon error (see `journalctl -u visaged`)".into(),
+        other => format!("unrecognized reason '{other}'"),
+    }
+}
+
+/// One-line summary of a model's enrollment consent record (`ListModels`).
+fn describe_consent(consent: &serde_json::Value) -> String {
+    if !consent.is_object() {
+        return "not recorded (enrolled before consent records were kept)".into();
+    }
+    // "alice (uid 1000)", or just the UID if the account had no name.
+    let account = |name: &str, uid: &str| match (consent[name].as_str(), consent[uid].as_u64()) {
+        (Some(name), Some(uid)) => Some(format!("{name} (uid {uid})")),
+        (None, Some(uid)) => Some(format!("uid {uid}")),
+        _ => None,
+    };
+    let mut line = format!(
+        "{} via {}",
+        consent["recorded_at"].as_str().unwrap_or("?"),
+        consent["method"].as_str().unwrap_or("?"),
+    );
+    match account("caller_user", "caller_uid") {
+        Some(caller) => line.push_str(&format!(" by {caller}")),
+        None => line.push_str(" by an unidentified caller"),
+    }
+    if let Some(command) = consent["command"].as_str() {
+        line.push_str(&format!(" running `{command}`"));
+    }
+    if let Some(login) = account("login_user", "login_uid") {
+        line.push_str(&format!(", logged in as {login}"));
+    }
+    line
+}
+
+/// Read one line from stdin without echo when it is a terminal.
+fn read_secret(prompt: &str) -> Result<String> {
+    use std::io::{BufRead, Write};
+
+    // SAFETY: isatty/tcgetattr/tcsetattr on fd 0 with a zeroed, then filled, termios.
+    let saved = unsafe {
+        let mut term: libc::termios = std::mem::zeroed();
+        if libc::isatty(0) == 1 && libc::tcgetattr(0, &mut term) == 0 {
+            let saved = term;
+            term.c_lflag &= !libc::ECHO;
+            libc::tcsetattr(0, libc::TCSANOW, &term);
+            eprint!("{prompt}");
+            std::io::stderr().flush().ok();
+            Some(saved)
+        } else {
+            None
+        }
+    };
+    let mut line = String::new();
+    let read = std::io::stdin().lock().read_line(&mut line);
+    if let Some(saved) = saved {
+        // SAFETY: restores the settings read above.
+        unsafe { libc::tcsetattr(0, libc::TCSANOW, &saved) };
+        eprintln!();
+    }
+    read?;
+    let secret = line.trim_end_matches(['\r', '\n']).to_string();
+    if secret.is_empty() {
+        anyhow::bail!("no password given");
+    }
+    Ok(secret)
+}
+
 fn verify_timeout_secs() -> u64 {
     std::env::var("VISAGE_VERIFY_TIMEOUT_SECS")
         .ok()
@@ -103,7 +490,15 @@ async fn connect_proxy() -> Result<VisageProxy<'static>> {
     .await
     .map_err(|e| anyhow::anyhow!("failed to connect to D-Bus: {e}"))?;
 
-    let proxy = VisageProxy::new(&conn)
+    // `visaged@NAME` serves the same interface under its own name and path.
+    let builder = match std::env::var("VISAGE_INSTANCE") {
+        Ok(name) if !name.is_empty() => VisageProxy::builder(&conn)
+            .destination(format!("org.freedesktop.Visage1.{name}"))?
+            .path(format!("/org/freedesktop/Visage1/{name}"))?,
+        _ => VisageProxy::builder(&conn),
+    };
+    let proxy = builder
+        .build()
         .await
         .map_err(|e| anyhow::anyhow!("failed to create proxy: {e} — is visaged running?"))?;
     Ok(proxy)
@@ -118,11 +513,32 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Enroll { label, user } => {
+        Commands::Enroll {
+            label,
+            user,
+            tenant,
+            guided,
+            replace,
+        } => {
             let user = user.unwrap_or_else(current_user);
             let proxy = connect_proxy().await?;
+            if guided || replace {
+                return run_session_enroll(
+                    &proxy,
+                    &user,
+                    &label,
+                    tenant.as_deref(),
+                    guided,
+                    replace,
+                )
+                .await;
+            }
             println!("Enrolling face model '{label}' for user '{user}'...");
-            match proxy.enroll(&user, &label).await {
+            let result = match &tenant {
+                Some(tenant) => proxy.enroll_in(tenant, &user, &label).await,
+                None => proxy.enroll(&user, &label).await,
+            };
+            match result {
                 Ok(model_id) => println!("Enrolled successfully. Model ID: {model_id}"),
                 Err(e) => {
                     eprintln!("Enrollment failed: {e}");
@@ -130,17 +546,58 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Verify { user } => {
+        Commands::Verify {
+            user,
+            tenant,
+            details,
+        } => {
             let user = user.unwrap_or_else(current_user);
             let proxy = connect_proxy().await?;
             println!("Verifying face for user '{user}'...");
-            match proxy.verify(&user).await {
-                Ok(true) => {
+            // Live feedback while the daemon works; best-effort.
+            let feedback = match proxy.receive_face_detected().await {
+                Ok(mut detected) => Some(tokio::spawn(async move {
+                    while let Some(signal) =
+                        poll_fn(|cx| Pin::new(&mut detected).poll_next(cx)).await
+                    {
+                        if let Ok(args) = signal.args() {
+                            println!(
+                                "Face detected (confidence {:.2}), matching...",
+                                args.confidence
+                            );
+                        }
+                    }
+                })),
+                Err(_) => None,
+            };
+            let mut detailed = None;
+            let result = match &tenant {
+                Some(tenant) => {
+                    let options = HashMap::from([("tenant", tenant.as_str().into())]);
+                    proxy.verify_with_options(&user, options).await
+                }
+                None if details => proxy.verify_detailed(&user).await.map(|reply| {
+                    let matched = reply.get("matched").and_then(|v| bool::try_from(v).ok());
+                    let reason = reply.get("reason").and_then(|v| <&str>::try_from(v).ok());
+                    let outcome = (matched.unwrap_or(false), reason.unwrap_or("").to_string());
+                    detailed = Some(reply);
+                    outcome
+                }),
+                None => proxy.verify_with_reason(&user).await,
+            };
+            if let Some(feedback) = feedback {
+                feedback.abort();
+            }
+            if let Some(reply) = &detailed {
+                print_verify_details(reply);
+            }
+            match result {
+                Ok((true, _)) => {
                     println!("Match: verified");
                     // Exit 0 on match (shell-friendly)
                 }
-                Ok(false) => {
-                    println!("No match");
+                Ok((false, reason)) => {
+                    println!("No match: {}", describe_failure(&reason));
                     std::process::exit(1);
                 }
                 Err(e) => {
@@ -149,10 +606,37 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::List { user } => {
+        Commands::Identify { tenant } => {
+            let proxy = connect_proxy().await?;
+            println!("Identifying face...");
+            let result = match &tenant {
+                Some(tenant) => proxy.identify_in(tenant).await,
+                None => proxy.identify_any().await,
+            };
+            match result {
+                Ok((user, _)) if !user.is_empty() => println!("Identified: {user}"),
+                Ok((_, reason)) => {
+                    println!("No match: {}", describe_failure(&reason));
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Identification failed: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::List {
+            user,
+            tenant,
+            details,
+        } => {
             let user = user.unwrap_or_else(current_user);
             let proxy = connect_proxy().await?;
-            match proxy.list_models(&user).await {
+            let result = match &tenant {
+                Some(tenant) => proxy.list_models_in(tenant, &user).await,
+                None => proxy.list_models(&user).await,
+            };
+            match result {
                 Ok(json) => {
                     let models: Vec<serde_json::Value> = serde_json::from_str(&json)?;
                     if models.is_empty() {
@@ -167,6 +651,34 @@ async fn main() -> Result<()> {
                                 m["quality_score"].as_f64().unwrap_or(0.0),
                                 m["created_at"].as_str().unwrap_or("?"),
                             );
+                            let offset = m["threshold_offset"].as_f64().unwrap_or(0.0);
+                            if offset != 0.0 {
+                                println!("      threshold offset: {offset:+.2}");
+                            }
+                            if m["outlier"].as_bool().unwrap_or(false) {
+                                println!(
+                                    "      WARNING: does not resemble the other models (similarity {:.3}) — remove it if you did not enroll it",
+                                    m["consistency"].as_f64().unwrap_or(0.0),
+                                );
+                            }
+                            // Older daemons do not report near misses.
+                            let near_misses = m["near_misses"].as_u64().unwrap_or(0);
+                            if near_misses > 0 {
+                                println!(
+                                    "      near misses: {}, mean similarity {:.3}, best {:.3}, last {}",
+                                    near_misses,
+                                    m["near_miss_mean_similarity"].as_f64().unwrap_or(0.0),
+                                    m["near_miss_max_similarity"].as_f64().unwrap_or(0.0),
+                                    m["last_near_miss_at"].as_str().unwrap_or("?"),
+                                );
+                            }
+                            if details {
+                                println!("      consent: {}", describe_consent(&m["consent"]));
+                                println!(
+                                    "      last matched: {}",
+                                    m["last_matched_at"].as_str().unwrap_or("never")
+                                );
+                            }
                         }
                     }
                 }
@@ -176,10 +688,14 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Remove { id, user } => {
+        Commands::Remove { id, user, tenant } => {
             let user = user.unwrap_or_else(current_user);
             let proxy = connect_proxy().await?;
-            match proxy.remove_model(&user, &id).await {
+            let result = match &tenant {
+                Some(tenant) => proxy.remove_model_in(tenant, &user, &id).await,
+                None => proxy.remove_model(&user, &id).await,
+            };
+            match result {
                 Ok(true) => println!("Model {id} removed"),
                 Ok(false) => {
                     eprintln!("Model {id} not found (or not owned by user '{user}')");
@@ -191,6 +707,190 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::Offset {
+            id,
+            offset,
+            user,
+            tenant,
+        } => {
+            let user = user.unwrap_or_else(current_user);
+            let proxy = connect_proxy().await?;
+            let result = match &tenant {
+                Some(tenant) => {
+                    proxy
+                        .set_threshold_offset_in(tenant, &user, &id, offset)
+                        .await
+                }
+                None => proxy.set_threshold_offset(&user, &id, offset).await,
+            };
+            match result {
+                Ok(true) => println!("Model {id} threshold offset set to {offset:+.2}"),
+                Ok(false) => {
+                    eprintln!("Model {id} not found (or not owned by user '{user}')");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to set threshold offset: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Authtok { action, user } => {
+            let user = user.unwrap_or_else(current_user);
+            let proxy = connect_proxy().await?;
+            match action {
+                AuthtokAction::Set => {
+                    let secret = read_secret("Login password (unlocks the keyring): ")?;
+                    if let Err(e) = proxy.set_authtok(&user, &secret).await {
+                        eprintln!("Failed to store password: {e}");
+                        std::process::exit(1);
+                    }
+                    println!("Keyring password stored for '{user}'");
+                }
+                AuthtokAction::Clear => match proxy.clear_authtok(&user).await {
+                    Ok(true) => println!("Keyring password cleared for '{user}'"),
+                    Ok(false) => println!("No keyring password stored for '{user}'"),
+                    Err(e) => {
+                        eprintln!("Failed to clear password: {e}");
+                        std::process::exit(1);
+                    }
+                },
+            }
+        }
+        Commands::ExportScores { user, tenant, json } => {
+            let user = user.unwrap_or_else(current_user);
+            let proxy = connect_proxy().await?;
+            match proxy
+                .export_scores(tenant.as_deref().unwrap_or(""), &user)
+                .await
+            {
+                Ok(export) if json => println!("{export}"),
+                Ok(export) => print_scores_csv(&serde_json::from_str(&export)?),
+                Err(e) => {
+                    eprintln!("Failed to export scores: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Export { file, user } => {
+            use std::io::{IsTerminal, Write};
+            use std::os::unix::fs::OpenOptionsExt;
+
+            let user = user.unwrap_or_else(current_user);
+            let passphrase = read_secret("Bundle passphrase: ")?;
+            if std::io::stdin().is_terminal() && read_secret("Repeat passphrase: ")? != passphrase {
+                anyhow::bail!("passphrases do not match");
+            }
+            let proxy = connect_proxy().await?;
+            let bundle = match proxy.export_user(&user, &passphrase).await {
+                Ok(bundle) => bundle,
+                Err(e) => {
+                    eprintln!("Failed to export face models: {e}");
+                    std::process::exit(1);
+                }
+            };
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&file)
+                .and_then(|mut f| f.write_all(&bundle))
+                .map_err(|e| anyhow::anyhow!("cannot write {}: {e}", file.display()))?;
+            println!(
+                "Face models of '{user}' exported to {} ({} bytes)",
+                file.display(),
+                bundle.len()
+            );
+            println!("Keep the passphrase: the bundle cannot be imported without it.");
+        }
+        Commands::Import { file, user } => {
+            let user = user.unwrap_or_else(current_user);
+            let bundle = std::fs::read(&file)
+                .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", file.display()))?;
+            let passphrase = read_secret("Bundle passphrase: ")?;
+            let proxy = connect_proxy().await?;
+            match proxy.import_user(&user, &bundle, &passphrase).await {
+                Ok(ids) => {
+                    println!("Imported {} face model(s) for '{user}':", ids.len());
+                    for id in ids {
+                        println!("  {id}");
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to import face models: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Admin {
+            action: AdminAction::Stats { since, json },
+        } => {
+            let proxy = connect_proxy().await?;
+            match proxy.stats(since).await {
+                Ok(report) if json => println!("{report}"),
+                Ok(report) => print_stats(&serde_json::from_str(&report)?),
+                Err(e) => {
+                    eprintln!("Failed to get statistics: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Admin {
+            action: AdminAction::ReloadQuirks,
+        } => {
+            let proxy = connect_proxy().await?;
+            let reloaded: serde_json::Value = match proxy.reload_quirks().await {
+                Ok(json) => serde_json::from_str(&json)?,
+                Err(e) => {
+                    eprintln!("Failed to reload quirks: {e}");
+                    std::process::exit(1);
+                }
+            };
+            for file in reloaded["files"].as_array().into_iter().flatten() {
+                println!("loaded   {}", file.as_str().unwrap_or(""));
+            }
+            for error in reloaded["errors"].as_array().into_iter().flatten() {
+                println!(
+                    "skipped  {}: {}",
+                    error["path"].as_str().unwrap_or(""),
+                    error["error"].as_str().unwrap_or("")
+                );
+            }
+            println!("{} quirks known", reloaded["quirks"]);
+            match reloaded["emitter"].as_str() {
+                Some(name) => println!("IR emitter: {name}"),
+                None => println!("IR emitter: none for the camera in use"),
+            }
+        }
+        Commands::Admin {
+            action: AdminAction::Profile { user, json },
+        } => {
+            let user = user.unwrap_or_else(current_user);
+            let proxy = connect_proxy().await?;
+            if !json {
+                println!("Verifying face for user '{user}' with profiling...");
+            }
+            let reply = match proxy.debug_profile(&user).await {
+                Ok(reply) if json => {
+                    println!("{reply}");
+                    return Ok(());
+                }
+                Ok(reply) => serde_json::from_str::<serde_json::Value>(&reply)?,
+                Err(e) => {
+                    eprintln!("Failed to profile a verify: {e}");
+                    std::process::exit(1);
+                }
+            };
+            match reply["reason"].as_str() {
+                Some(reason) if !reason.is_empty() => println!("not matched ({reason})"),
+                _ => println!("matched"),
+            }
+            println!("{} ms in total", reply["total_ms"]);
+            for file in reply["files"].as_array().into_iter().flatten() {
+                println!("profile  {}", file.as_str().unwrap_or(""));
+            }
+        }
+        #[cfg(feature = "setup")]
         Commands::Setup { model_dir } => {
             setup::run(model_dir)?;
         }
@@ -237,6 +937,12 @@ async fn main() -> Result<()> {
                     if let Some(v) = status.get("session_bus").and_then(|v| v.as_bool()) {
                         println!("  bus:        {}", if v { "session" } else { "system" });
                     }
+                    let outliers = status["gallery_outliers"].as_u64().unwrap_or(0);
+                    if outliers > 0 {
+                        println!(
+                            "  WARNING:    {outliers} face model(s) unlike the rest of their gallery; see `visage list`"
+                        );
+                    }
                 }
                 Err(e) => {
                     eprintln!("visaged: not reachable — {e}");
@@ -245,16 +951,308 @@ async fn main() -> Result<()> {
                 }
             }
         }
-        Commands::Test { device, frames } => {
-            run_camera_test(&device, frames)?;
+        Commands::Health => {
+            let proxy = connect_proxy().await?;
+            match proxy.health().await {
+                Ok(json) => {
+                    let health: serde_json::Value = serde_json::from_str(&json)?;
+                    let healthy = health["healthy"].as_bool().unwrap_or(false);
+                    println!("visaged: {}", if healthy { "healthy" } else { "UNHEALTHY" });
+                    println!("  engine:   {}", health["engine"].as_str().unwrap_or("?"));
+                    println!("  database: {}", health["database"].as_str().unwrap_or("?"));
+                    println!("  panics:   {}", health["panics"].as_u64().unwrap_or(0));
+                    if !healthy {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("visaged: not reachable — {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Metrics { json } => {
+            let proxy = connect_proxy().await?;
+            let snapshot = proxy.get_metrics().await?;
+            if json {
+                println!("{snapshot}");
+            } else {
+                print_metrics(&serde_json::from_str(&snapshot)?);
+            }
+        }
+        Commands::Test {
+            device,
+            frames,
+            out,
+        } => {
+            run_camera_test(&device, frames, &out)?;
+        }
+        Commands::Simulate {
+            bundle,
+            user,
+            tenant,
+        } => {
+            let user = user.unwrap_or_else(current_user);
+            let mut daemon = std::process::Command::new(visaged_path());
+            daemon.arg("simulate").arg(&bundle).arg(&user);
+            if let Some(tenant) = tenant {
+                daemon.arg("--tenant").arg(tenant);
+            }
+            let status = daemon
+                .status()
+                .map_err(|e| anyhow::anyhow!("cannot run visaged: {e}"))?;
+            if !status.success() {
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
+        Commands::Calibrate {
+            device,
+            exposure,
+            auto_exposure,
+            emitter_bytes,
+            rotate,
+            clahe,
+            frames,
+            dir,
+            reset,
+        } => {
+            if reset {
+                if Calibration::remove(&dir, &device)? {
+                    println!("Calibration for {device} removed; restart visaged to apply.");
+                } else {
+                    println!("No calibration saved for {device}.");
+                }
+                return Ok(());
+            }
+            let mut calibration = Calibration::load(&dir, &device)
+                .map(|(calibration, _)| calibration)
+                .unwrap_or_default();
+            if exposure.is_some() || auto_exposure {
+                calibration.exposure = exposure;
+            }
+            if emitter_bytes.is_some() {
+                calibration.emitter_bytes = emitter_bytes;
+            }
+            if let Some(degrees) = rotate {
+                calibration.rotation = degrees;
+            }
+            if let Some(on) = clahe {
+                calibration.clahe = on;
+            }
+            run_calibration(&device, calibration, frames, &dir)?;
+        }
+        Commands::Notify => run_notify().await?,
+    }
+
+    Ok(())
+}
+
+/// Pause between a pose instruction and its capture, to give the user time
+/// to move.
+const GUIDED_POSE_PAUSE: Duration = Duration::from_secs(2);
+
+/// `visage enroll --guided` and `--replace`: drive a step-wise enrollment
+/// session, printing each `EnrollProgress` instruction before its capture,
+/// and commit once every step (every pose, when guided) is accepted.
+async fn run_session_enroll(
+    proxy: &VisageProxy<'static>,
+    user: &str,
+    label: &str,
+    tenant: Option<&str>,
+    guided: bool,
+    replace: bool,
+) -> Result<()> {
+    let mut progress = proxy
+        .receive_enroll_progress()
+        .await
+        .map_err(|e| anyhow::anyhow!("cannot subscribe to visaged signals: {e}"))?;
+    let mut options = HashMap::new();
+    if guided {
+        options.insert("guided", zbus::zvariant::Value::from(true));
+    }
+    if replace {
+        options.insert("replace", true.into());
+    }
+    if let Some(tenant) = tenant {
+        options.insert("tenant", tenant.into());
+    }
+    match (guided, replace) {
+        (true, true) => println!("Guided re-enrollment of '{label}' for user '{user}'."),
+        (true, false) => println!("Guided enrollment of '{label}' for user '{user}'."),
+        _ => println!(
+            "Re-enrolling user '{user}' as '{label}'; existing models are replaced on success."
+        ),
+    }
+    let session = match proxy.enroll_begin(user, label, options).await {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Enrollment failed: {e}");
+            std::process::exit(1);
+        }
+    };
+    loop {
+        // The daemon sends the progress signal before replying, so it is
+        // already queued; the timeout only guards against an older daemon.
+        let next = tokio::time::timeout(
+            Duration::from_secs(5),
+            poll_fn(|cx| Pin::new(&mut progress).poll_next(cx)),
+        )
+        .await;
+        if let Ok(Some(signal)) = next {
+            if let Ok(args) = signal.args() {
+                if args.step >= args.total {
+                    break;
+                }
+                println!("[{}/{}] {}", args.step + 1, args.total, args.hint);
+            }
+        }
+        if guided {
+            tokio::time::sleep(GUIDED_POSE_PAUSE).await;
+        }
+        let feedback: serde_json::Value = match proxy.enroll_step(&session).await {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) => {
+                let _ = proxy.enroll_abort(&session).await;
+                eprintln!("Enrollment failed: {e}");
+                std::process::exit(1);
+            }
+        };
+        if feedback["accepted"].as_bool().unwrap_or(false) {
+            println!(
+                "      accepted (quality {:.2})",
+                feedback["quality"].as_f64().unwrap_or(0.0)
+            );
+        } else {
+            println!(
+                "      try again: {}",
+                describe_failure(feedback["reason"].as_str().unwrap_or(""))
+            );
+        }
+        if feedback["ready"].as_bool().unwrap_or(false) {
+            break;
         }
     }
+    match proxy.enroll_commit(&session).await {
+        Ok(model_ids) => {
+            println!("Enrolled successfully. Model IDs:");
+            for id in model_ids {
+                println!("  {id}");
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("Enrollment failed: {e}");
+            std::process::exit(1);
+        }
+    }
+}
 
+/// `visage notify`: turn `GalleryOutlier` and `ModelExpiring` signals about
+/// the current user's default-tenant models into desktop notifications, until
+/// killed.
+async fn run_notify() -> Result<()> {
+    let user = current_user();
+    let proxy = connect_proxy().await?;
+    let subscribe_error =
+        |e: zbus::Error| anyhow::anyhow!("cannot subscribe to visaged signals: {e}");
+    let mut outliers = proxy
+        .receive_gallery_outlier()
+        .await
+        .map_err(subscribe_error)?;
+    let mut expiring = proxy
+        .receive_model_expiring()
+        .await
+        .map_err(subscribe_error)?;
+    let mut challenges = proxy
+        .receive_challenge_required()
+        .await
+        .map_err(subscribe_error)?;
+    let session = zbus::Connection::session()
+        .await
+        .map_err(|e| anyhow::anyhow!("no desktop session bus: {e}"))?;
+    loop {
+        let (summary, body) = tokio::select! {
+            signal = poll_fn(|cx| Pin::new(&mut outliers).poll_next(cx)) => {
+                let Some(signal) = signal else { break };
+                let Ok(args) = signal.args() else { continue };
+                if args.user != user || !args.tenant.is_empty() {
+                    continue;
+                }
+                (
+                    "Unexpected face model",
+                    format!(
+                        "Face model {} does not look like your other models (similarity {:.2}). \
+                         If you did not enroll it, remove it with `visage remove {}`.",
+                        args.model_id, args.similarity, args.model_id
+                    ),
+                )
+            }
+            signal = poll_fn(|cx| Pin::new(&mut expiring).poll_next(cx)) => {
+                let Some(signal) = signal else { break };
+                let Ok(args) = signal.args() else { continue };
+                if args.user != user || !args.tenant.is_empty() {
+                    continue;
+                }
+                (
+                    "Face model will be deleted",
+                    format!(
+                        "Face model {} has not been used for a long time and will be deleted \
+                         after {}. Unlock with your face before then to keep it.",
+                        args.model_id, args.purge_after
+                    ),
+                )
+            }
+            signal = poll_fn(|cx| Pin::new(&mut challenges).poll_next(cx)) => {
+                let Some(signal) = signal else { break };
+                let Ok(args) = signal.args() else { continue };
+                if args.user != user || !args.tenant.is_empty() {
+                    continue;
+                }
+                (
+                    "Face unlock will check more carefully",
+                    format!(
+                        "Your face only just matched {} times recently. The next face unlock \
+                         uses stricter settings. If that was not you, change your password \
+                         and check `visage list`.",
+                        args.narrow_matches
+                    ),
+                )
+            }
+        };
+        let hints: HashMap<&str, zbus::zvariant::Value<'_>> =
+            HashMap::from([("urgency", zbus::zvariant::Value::from(2u8))]);
+        let sent = session
+            .call_method(
+                Some("org.freedesktop.Notifications"),
+                "/org/freedesktop/Notifications",
+                Some("org.freedesktop.Notifications"),
+                "Notify",
+                &(
+                    "Visage",
+                    0u32,
+                    "dialog-warning",
+                    summary,
+                    body.as_str(),
+                    Vec::<&str>::new(),
+                    hints,
+                    -1i32,
+                ),
+            )
+            .await;
+        if let Err(e) = sent {
+            eprintln!("visage notify: notification failed: {e}");
+        }
+    }
     Ok(())
 }
 
 fn cmd_discover() {
-    use visage_hw::quirks::{get_driver, get_usb_ids, is_ipu6_camera, lookup_quirk};
+    use visage_hw::quirks::{
+        get_driver, get_usb_ids, is_ipu6_camera, load_dirs, lookup_quirk, DEFAULT_QUIRKS_DIR,
+    };
+
+    // Local quirk files count too (the daemon's default directory).
+    load_dirs(&[std::path::PathBuf::from(DEFAULT_QUIRKS_DIR)]);
 
     let mut entries: Vec<_> = std::fs::read_dir("/dev")
         .expect("cannot read /dev")
@@ -317,7 +1315,17 @@ fn cmd_discover() {
     }
 }
 
-fn run_camera_test(device_path: &str, frame_count: usize) -> Result<()> {
+/// The `visaged` binary: next to this one when installed together, else
+/// whatever `PATH` finds.
+fn visaged_path() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("visaged")))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("visaged"))
+}
+
+fn run_camera_test(device_path: &str, frame_count: usize, out_dir: &std::path::Path) -> Result<()> {
     println!("Camera diagnostics");
     println!("==================");
 
@@ -338,18 +1346,31 @@ fn run_camera_test(device_path: &str, frame_count: usize) -> Result<()> {
         "  Format: {:?} {}x{}",
         camera.fourcc, camera.width, camera.height
     );
+    if let Some(reason) = camera.virtual_reason() {
+        println!("  WARNING: {reason} — visaged refuses it unless VISAGE_ALLOW_VIRTUAL_CAMERA=1");
+    }
 
-    // Prepare output directory
-    let out_dir = std::path::PathBuf::from("/tmp/visage-test");
-    std::fs::create_dir_all(&out_dir)?;
+    // Prepare output directory; frames of an earlier run would otherwise be
+    // replayed as part of this burst by `visage simulate`.
+    std::fs::create_dir_all(out_dir)?;
+    for entry in std::fs::read_dir(out_dir)?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("frame-") && name.ends_with(".pgm") {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
 
     // Capture frames
     println!("\nCapturing {frame_count} frames...");
-    let (captured_frames, dark_skipped) = camera.capture_frames(frame_count)?;
+    let (captured_frames, stats) = camera.capture_frames(frame_count)?;
     println!(
-        "  Captured: {} good, {} dark skipped",
+        "  Captured: {} good, {} dark, {} saturated, {} blurry, {} motion skipped",
         captured_frames.len(),
-        dark_skipped
+        stats.dark,
+        stats.saturated,
+        stats.blurry,
+        stats.motion
     );
 
     // Save as PGM and compute stats
@@ -379,6 +1400,300 @@ fn run_camera_test(device_path: &str, frame_count: usize) -> Result<()> {
     Ok(())
 }
 
+/// Seconds in a period such as `90s`, `15m`, `12h` or `30d`.
+fn parse_period(value: &str) -> Result<u64, String> {
+    let (count, unit) = value.split_at(value.len().saturating_sub(1));
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => {
+            return Err(format!(
+                "expected a number with s, m, h or d, got {value:?}"
+            ))
+        }
+    };
+    count
+        .parse::<u64>()
+        .ok()
+        .and_then(|count| count.checked_mul(scale))
+        .ok_or_else(|| format!("expected a number with s, m, h or d, got {value:?}"))
+}
+
+/// Print a `VerifyDetailed` reply, minus the verdict printed after it.
+fn print_verify_details(reply: &HashMap<String, zbus::zvariant::OwnedValue>) {
+    let count = |key: &str| {
+        reply
+            .get(key)
+            .and_then(|v| u64::try_from(v).ok())
+            .unwrap_or(0)
+    };
+    let real = |key: &str| {
+        reply
+            .get(key)
+            .and_then(|v| f64::try_from(v).ok())
+            .unwrap_or(0.0)
+    };
+    let text = |key: &str| {
+        reply
+            .get(key)
+            .and_then(|v| <&str>::try_from(v).ok())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("-")
+    };
+    println!("  similarity:  {:.3}", real("similarity"));
+    println!(
+        "  model:       {} ({})",
+        text("model_label"),
+        text("model_id")
+    );
+    println!("  confidence:  {:.2}", real("confidence"));
+    println!(
+        "  frames:      {} captured, {} too dark",
+        count("frames"),
+        count("dark_frames")
+    );
+    println!(
+        "  time:        {} ms capture, {} ms inference, {} ms total",
+        count("capture_ms"),
+        count("inference_ms"),
+        count("total_ms")
+    );
+}
+
+/// Print a `GetMetrics` snapshot: attempt counters, then the mean time and
+/// count per stage.
+fn print_metrics(metrics: &serde_json::Value) {
+    let count = |key: &str| metrics[key].as_u64().unwrap_or(0);
+    println!(
+        "verify:   {} attempts, {} matched",
+        count("verify_attempts"),
+        count("verify_matches")
+    );
+    println!(
+        "identify: {} attempts, {} matched",
+        count("identify_attempts"),
+        count("identify_matches")
+    );
+    println!(
+        "frames:   {} captured, {} too dark",
+        count("frames_captured"),
+        count("dark_frames_skipped")
+    );
+    println!();
+    println!("{:<10} {:>8} {:>10}", "STAGE", "COUNT", "MEAN");
+    for stage in ["verify", "identify", "capture", "inference"] {
+        let histogram = &metrics["latency"][stage];
+        let n = histogram["count"].as_u64().unwrap_or(0);
+        let sum = histogram["sum_seconds"].as_f64().unwrap_or(0.0);
+        let mean = if n == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.0} ms", sum * 1000.0 / n as f64)
+        };
+        println!("{stage:<10} {n:>8} {mean:>10}");
+    }
+}
+
+/// Print a `Stats` report as tables.
+fn print_stats(report: &serde_json::Value) {
+    let percent =
+        |ratio: &serde_json::Value| format!("{:.1}%", ratio.as_f64().unwrap_or(0.0) * 100.0);
+    let attempts = report["attempts"].as_u64().unwrap_or(0);
+    println!(
+        "Verify attempts since {}: {attempts}",
+        report["since"].as_str().unwrap_or("?")
+    );
+    if attempts == 0 {
+        return;
+    }
+    let matched = report["matched"].as_u64().unwrap_or(0);
+    println!(
+        "  matched {matched} ({:.1}%), median latency {} ms",
+        matched as f64 * 100.0 / attempts as f64,
+        report["median_latency_ms"].as_u64().unwrap_or(0)
+    );
+
+    println!();
+    println!(
+        "{:<24} {:>8} {:>8} {:>8} {:>10}",
+        "USER", "ATTEMPTS", "MATCHED", "SUCCESS", "MEDIAN"
+    );
+    for user in report["users"].as_array().into_iter().flatten() {
+        // Named tenants' users are shown as tenant/user.
+        let name = match user["tenant"].as_str() {
+            Some(tenant) if !tenant.is_empty() => {
+                format!("{tenant}/{}", user["user"].as_str().unwrap_or("?"))
+            }
+            _ => user["user"].as_str().unwrap_or("?").to_string(),
+        };
+        println!(
+            "{name:<24} {:>8} {:>8} {:>8} {:>7} ms",
+            user["attempts"].as_u64().unwrap_or(0),
+            user["matched"].as_u64().unwrap_or(0),
+            percent(&user["success_rate"]),
+            user["median_latency_ms"].as_u64().unwrap_or(0),
+        );
+    }
+
+    println!();
+    println!(
+        "{:<40} {:>8} {:>8} {:>8}",
+        "CAMERA", "ATTEMPTS", "FRAMES", "DARK"
+    );
+    for camera in report["cameras"].as_array().into_iter().flatten() {
+        println!(
+            "{:<40} {:>8} {:>8} {:>8}",
+            camera["camera"].as_str().unwrap_or("?"),
+            camera["attempts"].as_u64().unwrap_or(0),
+            camera["frames"].as_u64().unwrap_or(0),
+            percent(&camera["dark_ratio"]),
+        );
+    }
+
+    let Some(failures) = report["failures"].as_object().filter(|f| !f.is_empty()) else {
+        return;
+    };
+    let mut failures: Vec<(&String, u64)> = failures
+        .iter()
+        .map(|(reason, count)| (reason, count.as_u64().unwrap_or(0)))
+        .collect();
+    failures.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    println!();
+    println!("Failures:");
+    for (reason, count) in failures {
+        println!("  {count:>6}  {reason}: {}", describe_failure(reason));
+    }
+}
+
+/// Print an `ExportScores` export as CSV, one row per pair. A sample's label
+/// is a model's label, or `matched`/`rejected` for a probe.
+fn print_scores_csv(export: &serde_json::Value) {
+    let samples: HashMap<&str, &serde_json::Value> = export["samples"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|sample| Some((sample["id"].as_str()?, sample)))
+        .collect();
+    let describe = |id: &str| {
+        let sample = samples.get(id).copied().unwrap_or(&serde_json::Value::Null);
+        let label = match sample["matched"].as_bool() {
+            Some(true) => "matched",
+            Some(false) => "rejected",
+            None => sample["label"].as_str().unwrap_or(""),
+        };
+        format!(
+            "{},{},{}",
+            csv_field(id),
+            csv_field(label),
+            csv_field(sample["at"].as_str().unwrap_or(""))
+        )
+    };
+    println!("pair,a,a_label,a_at,b,b_label,b_at,similarity");
+    for score in export["scores"].as_array().into_iter().flatten() {
+        println!(
+            "{},{},{},{:.4}",
+            score["pair"].as_str().unwrap_or("?"),
+            describe(score["a"].as_str().unwrap_or("")),
+            describe(score["b"].as_str().unwrap_or("")),
+            score["similarity"].as_f64().unwrap_or(0.0)
+        );
+    }
+}
+
+/// `value` as a CSV field, quoted if it holds a comma, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("expected on or off, got {value:?}")),
+    }
+}
+
+/// Capture with `calibration` applied and save it if enough frames pass the
+/// quality gate.
+fn run_calibration(
+    device_path: &str,
+    calibration: Calibration,
+    frame_count: usize,
+    dir: &std::path::Path,
+) -> Result<()> {
+    calibration.validate().map_err(|e| anyhow::anyhow!(e))?;
+    let frame_count = frame_count.max(1);
+
+    let mut camera = visage_hw::Camera::open(device_path)?;
+    camera.set_calibration(calibration.clone());
+    let emitter = match visage_hw::IrEmitter::for_device(device_path) {
+        Some(mut emitter) => {
+            if let Some(bytes) = calibration.emitter_bytes.clone() {
+                emitter.set_control_bytes(bytes)?;
+            }
+            emitter.activate()?;
+            Some(emitter)
+        }
+        None if calibration.emitter_bytes.is_some() => {
+            anyhow::bail!("{device_path} has no emitter quirk; --emitter-bytes needs one")
+        }
+        None => None,
+    };
+    let (width, height) = camera.frame_size();
+    println!("Calibrating {device_path} ({width}x{height}):");
+    println!(
+        "  exposure={} rotation={} clahe={} emitter={}",
+        calibration
+            .exposure
+            .map_or_else(|| "auto".to_string(), |e| e.to_string()),
+        calibration.rotation,
+        if calibration.clahe { "on" } else { "off" },
+        calibration
+            .emitter_bytes
+            .as_ref()
+            .map_or_else(|| "quirk default".to_string(), |bytes| format!("{bytes:?}")),
+    );
+
+    let capture = camera.capture_burst(frame_count, false, &|| false);
+    if let Some(emitter) = &emitter {
+        let _ = emitter.deactivate();
+    }
+    let (captured, stats) = capture?;
+    println!(
+        "  Captured: {} good, {} dark, {} saturated, {} blurry, {} motion skipped",
+        captured.len(),
+        stats.dark,
+        stats.saturated,
+        stats.blurry,
+        stats.motion
+    );
+    if let Some(frame) = captured.first() {
+        let preview = std::env::temp_dir().join("visage-calibration.pgm");
+        save_pgm(&preview, &frame.data, frame.width, frame.height)?;
+        println!(
+            "  Brightness {:.1}; preview: {}",
+            frame.avg_brightness(),
+            preview.display()
+        );
+    }
+    if captured.len() * 2 < frame_count {
+        anyhow::bail!(
+            "only {} of {frame_count} frames were usable; calibration not saved",
+            captured.len()
+        );
+    }
+
+    let path = calibration.save(dir, device_path)?;
+    println!("Saved {}; restart visaged to apply.", path.display());
+    Ok(())
+}
+
 /// Write a grayscale image as PGM (Portable Gray Map) — no extra deps needed.
 fn save_pgm(path: &std::path::Path, data: &[u8], width: u32, height: u32) -> Result<()> {
     use std::io::Write;
@@ -4,7 +4,7 @@
 //! InsightFace reference landmarks and least-squares estimation.
 
 /// ArcFace reference landmarks for a 112×112 output.
-const REFERENCE_LANDMARKS_112: [(f32, f32); 5] = [
+pub const REFERENCE_LANDMARKS_112: [(f32, f32); 5] = [
     (38.2946, 51.6963), // left eye
     (73.5318, 51.5014), // right eye
     (56.0252, 71.7366), // nose
@@ -12,7 +12,29 @@ const REFERENCE_LANDMARKS_112: [(f32, f32); 5] = [
     (70.7299, 92.2041), // right mouth
 ];
 
-const ALIGNED_SIZE: usize = 112;
+/// Side length of the aligned face crop fed to ArcFace.
+pub const ALIGNED_SIZE: usize = 112;
+
+/// Shape of a margin-expanded crop for people to look at (thumbnails, debug
+/// dumps) rather than for the network. Uses the same alignment as the
+/// 112×112 crop, so faces line up across crops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayCrop {
+    /// Extra context on every side, as a fraction of the aligned crop
+    /// (0.25 adds a quarter of the face box left, right, above and below).
+    pub margin: f32,
+    /// Side length of the square output in pixels.
+    pub size: usize,
+}
+
+impl Default for DisplayCrop {
+    fn default() -> Self {
+        Self {
+            margin: 0.25,
+            size: 160,
+        }
+    }
+}
 
 /// Estimate a 2×3 similarity transform (4-DOF: scale, rotation, translation)
 /// from `src` landmarks to `dst` landmarks using least-squares.
@@ -115,54 +137,105 @@ fn warp_affine(
     matrix: &[f32; 6],
     out_size: usize,
 ) -> Vec<u8> {
+    let mut output = vec![0u8; out_size * out_size];
+    warp_affine_with(frame, src_width, src_height, matrix, out_size, |i, v| {
+        output[i] = v as u8;
+    });
+    output
+}
+
+/// Core of [`warp_affine`]: calls `emit(index, value)` for every output pixel
+/// in row-major order, with `value` the interpolated sample rounded to a whole
+/// grey level (so every consumer sees exactly what the `u8` crop holds).
+///
+/// The column terms of the inverse mapping are computed once per warp and the
+/// row terms once per row, and samples whose 2×2 neighbourhood lies inside
+/// the frame skip the per-tap bounds checks. Evaluation order matches the
+/// plain per-pixel formula, so results are bit-identical to it.
+fn warp_affine_with(
+    frame: &[u8],
+    src_width: usize,
+    src_height: usize,
+    matrix: &[f32; 6],
+    out_size: usize,
+    mut emit: impl FnMut(usize, f32),
+) {
     let (a, _neg_b, tx) = (matrix[0], matrix[1], matrix[2]);
     let (b, _a2, ty) = (matrix[3], matrix[4], matrix[5]);
 
     // Invert the 2x2 part: M = [[a, -b], [b, a]], det = a^2 + b^2
     let det = a * a + b * b;
     if det.abs() < 1e-12 {
-        return vec![0u8; out_size * out_size];
+        (0..out_size * out_size).for_each(|i| emit(i, 0.0));
+        return;
     }
     let inv_det = 1.0 / det;
     let ia = a * inv_det;
     let ib = b * inv_det;
 
-    let mut output = vec![0u8; out_size * out_size];
+    let (w, h) = (src_width as i32, src_height as i32);
+    let sample = |x: i32, y: i32| -> f32 {
+        if x >= 0 && x < w && y >= 0 && y < h {
+            frame[y as usize * src_width + x as usize] as f32
+        } else {
+            0.0
+        }
+    };
 
-    for oy in 0..out_size {
-        for ox in 0..out_size {
-            // Map output pixel back to source: src = M_inv * (dst - t)
+    // Map output pixel back to source: src = M_inv * (dst - t).
+    let columns: Vec<(f32, f32)> = (0..out_size)
+        .map(|ox| {
             let dx = ox as f32 - tx;
-            let dy = oy as f32 - ty;
-            let sx = ia * dx + ib * dy;
-            let sy = -ib * dx + ia * dy;
+            (ia * dx, -ib * dx)
+        })
+        .collect();
+
+    for oy in 0..out_size {
+        let dy = oy as f32 - ty;
+        let (row_x, row_y) = (ib * dy, ia * dy);
+        for (ox, &(col_x, col_y)) in columns.iter().enumerate() {
+            let sx = col_x + row_x;
+            let sy = col_y + row_y;
 
             // Bilinear interpolation
             let x0 = sx.floor() as i32;
             let y0 = sy.floor() as i32;
-            let x1 = x0 + 1;
-            let y1 = y0 + 1;
             let fx = sx - x0 as f32;
             let fy = sy - y0 as f32;
 
-            let sample = |x: i32, y: i32| -> f32 {
-                if x >= 0 && x < src_width as i32 && y >= 0 && y < src_height as i32 {
-                    frame[y as usize * src_width + x as usize] as f32
-                } else {
-                    0.0
-                }
+            let (tl, tr, bl, br) = if x0 >= 0 && y0 >= 0 && x0 + 1 < w && y0 + 1 < h {
+                let top = y0 as usize * src_width + x0 as usize;
+                let bottom = top + src_width;
+                (
+                    frame[top] as f32,
+                    frame[top + 1] as f32,
+                    frame[bottom] as f32,
+                    frame[bottom + 1] as f32,
+                )
+            } else {
+                (
+                    sample(x0, y0),
+                    sample(x0 + 1, y0),
+                    sample(x0, y0 + 1),
+                    sample(x0 + 1, y0 + 1),
+                )
             };
 
-            let val = sample(x0, y0) * (1.0 - fx) * (1.0 - fy)
-                + sample(x1, y0) * fx * (1.0 - fy)
-                + sample(x0, y1) * (1.0 - fx) * fy
-                + sample(x1, y1) * fx * fy;
+            let val = tl * (1.0 - fx) * (1.0 - fy)
+                + tr * fx * (1.0 - fy)
+                + bl * (1.0 - fx) * fy
+                + br * fx * fy;
 
-            output[oy * out_size + ox] = val.round().clamp(0.0, 255.0) as u8;
+            emit(oy * out_size + ox, val.round().clamp(0.0, 255.0));
         }
     }
+}
 
-    output
+/// The 2×3 similarity transform `[a, -b, tx, b, a, ty]` that [`align_face`]
+/// applies to map `landmarks` onto [`REFERENCE_LANDMARKS_112`]. Exposed so
+/// tools can draw or replay the exact production alignment.
+pub fn alignment_transform(landmarks: &[(f32, f32); 5]) -> [f32; 6] {
+    estimate_similarity_transform(landmarks, &REFERENCE_LANDMARKS_112)
 }
 
 /// Align a detected face to a canonical 112×112 crop.
@@ -171,7 +244,7 @@ fn warp_affine(
 /// similarity transform to reference positions, and warps the face region
 /// into a 112×112 aligned output suitable for ArcFace embedding extraction.
 pub fn align_face(frame: &[u8], width: u32, height: u32, landmarks: &[(f32, f32); 5]) -> Vec<u8> {
-    let matrix = estimate_similarity_transform(landmarks, &REFERENCE_LANDMARKS_112);
+    let matrix = alignment_transform(landmarks);
     warp_affine(
         frame,
         width as usize,
@@ -181,10 +254,144 @@ pub fn align_face(frame: &[u8], width: u32, height: u32, landmarks: &[(f32, f32)
     )
 }
 
+/// The transform for `crop`: [`alignment_transform`] widened by the margin
+/// and scaled to the output size.
+fn display_transform(landmarks: &[(f32, f32); 5], crop: DisplayCrop) -> [f32; 6] {
+    let [a, neg_b, tx, b, a2, ty] = alignment_transform(landmarks);
+    let pad = crop.margin.max(0.0) * ALIGNED_SIZE as f32;
+    let scale = crop.size as f32 / (ALIGNED_SIZE as f32 + 2.0 * pad);
+    [
+        a * scale,
+        neg_b * scale,
+        (tx + pad) * scale,
+        b * scale,
+        a2 * scale,
+        (ty + pad) * scale,
+    ]
+}
+
+/// A `crop.size`×`crop.size` grayscale crop of the aligned face with extra
+/// context around it; see [`DisplayCrop`]. With a zero margin and a size of
+/// 112 it is exactly [`align_face`].
+pub fn display_crop(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    landmarks: &[(f32, f32); 5],
+    crop: DisplayCrop,
+) -> Vec<u8> {
+    let matrix = display_transform(landmarks, crop);
+    warp_affine(frame, width as usize, height as usize, &matrix, crop.size)
+}
+
+/// [`align_face`] fused with network input normalization: writes
+/// `(pixel - mean) / std` for the aligned 112×112 crop straight into the
+/// first plane of `planes`, then copies it into every further 112×112 plane
+/// (grey replicated to R, G, B in an NCHW tensor). Avoids the intermediate
+/// crop and a second pass over it; values are identical to normalizing the
+/// output of [`align_face`].
+///
+/// # Panics
+///
+/// If `planes` is not a whole, non-zero number of 112×112 planes.
+pub fn align_face_normalized(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    landmarks: &[(f32, f32); 5],
+    mean: f32,
+    std: f32,
+    planes: &mut [f32],
+) {
+    warp_normalized(
+        frame,
+        width,
+        height,
+        landmarks,
+        mean,
+        std,
+        planes,
+        |_, _| {},
+    );
+}
+
+/// [`align_face_normalized`] that also returns the 112×112 `u8` crop it
+/// normalized — identical to [`align_face`] — from the same warp.
+///
+/// # Panics
+///
+/// If `planes` is not a whole, non-zero number of 112×112 planes.
+pub fn align_face_normalized_with_crop(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    landmarks: &[(f32, f32); 5],
+    mean: f32,
+    std: f32,
+    planes: &mut [f32],
+) -> Vec<u8> {
+    let mut crop = vec![0u8; ALIGNED_SIZE * ALIGNED_SIZE];
+    warp_normalized(
+        frame,
+        width,
+        height,
+        landmarks,
+        mean,
+        std,
+        planes,
+        |i, v| {
+            crop[i] = v as u8;
+        },
+    );
+    crop
+}
+
+/// Shared body of the normalized alignments; `tap` also sees every pixel.
+#[allow(clippy::too_many_arguments)]
+fn warp_normalized(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    landmarks: &[(f32, f32); 5],
+    mean: f32,
+    std: f32,
+    planes: &mut [f32],
+    mut tap: impl FnMut(usize, f32),
+) {
+    let plane = ALIGNED_SIZE * ALIGNED_SIZE;
+    assert!(
+        !planes.is_empty() && planes.chunks_exact(plane).remainder().is_empty(),
+        "output must hold whole {ALIGNED_SIZE}x{ALIGNED_SIZE} planes"
+    );
+    let matrix = alignment_transform(landmarks);
+    let (first, rest) = planes.split_at_mut(plane);
+    warp_affine_with(
+        frame,
+        width as usize,
+        height as usize,
+        &matrix,
+        ALIGNED_SIZE,
+        |i, v| {
+            tap(i, v);
+            first[i] = (v - mean) / std;
+        },
+    );
+    for copy in rest.chunks_exact_mut(plane) {
+        copy.copy_from_slice(first);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_alignment_transform_of_reference_is_identity() {
+        let m = alignment_transform(&REFERENCE_LANDMARKS_112);
+        assert!((m[0] - 1.0).abs() < 1e-3 && m[3].abs() < 1e-3);
+        assert!(m[2].abs() < 1e-2 && m[5].abs() < 1e-2);
+    }
+
     #[test]
     fn test_identity_transform() {
         // When src == dst, transform should be identity-like (a≈1, b≈0)
@@ -206,6 +413,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn test_scaled_transform() {
         // Source landmarks at 2x scale → transform should have a ≈ 0.5
         let src: [(f32, f32); 5] = [
@@ -229,6 +437,83 @@ mod tests {
         assert_eq!(out.len(), 112 * 112);
     }
 
+    #[test]
+    fn test_fused_normalization_matches_crop() {
+        let (w, h) = (160usize, 140usize);
+        let frame: Vec<u8> = (0..w * h).map(|i| ((i * 37) % 251) as u8).collect();
+        let landmarks: [(f32, f32); 5] = [
+            (60.0, 50.0),
+            (100.0, 54.0),
+            (80.0, 75.0),
+            (64.0, 98.0),
+            (96.0, 101.0),
+        ];
+        let crop = align_face(&frame, w as u32, h as u32, &landmarks);
+        let plane = ALIGNED_SIZE * ALIGNED_SIZE;
+        let mut planes = vec![0.0f32; 3 * plane];
+        align_face_normalized(
+            &frame,
+            w as u32,
+            h as u32,
+            &landmarks,
+            127.5,
+            127.5,
+            &mut planes,
+        );
+        for (c, chunk) in planes.chunks_exact(plane).enumerate() {
+            for (i, (&v, &p)) in chunk.iter().zip(&crop).enumerate() {
+                assert_eq!(v, (p as f32 - 127.5) / 127.5, "plane {c}, pixel {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_crops_share_the_alignment() {
+        let (w, h) = (160usize, 140usize);
+        let frame: Vec<u8> = (0..w * h).map(|i| ((i * 37) % 251) as u8).collect();
+        let landmarks: [(f32, f32); 5] = [
+            (60.0, 50.0),
+            (100.0, 54.0),
+            (80.0, 75.0),
+            (64.0, 98.0),
+            (96.0, 101.0),
+        ];
+        let crop = align_face(&frame, w as u32, h as u32, &landmarks);
+        let mut planes = vec![0.0f32; ALIGNED_SIZE * ALIGNED_SIZE];
+        let fused = align_face_normalized_with_crop(
+            &frame,
+            w as u32,
+            h as u32,
+            &landmarks,
+            127.5,
+            127.5,
+            &mut planes,
+        );
+        assert_eq!(fused, crop);
+
+        let tight = DisplayCrop {
+            margin: 0.0,
+            size: ALIGNED_SIZE,
+        };
+        assert_eq!(
+            display_crop(&frame, w as u32, h as u32, &landmarks, tight),
+            crop
+        );
+
+        // With a margin, the aligned crop sits scaled in the middle.
+        let wide = DisplayCrop::default();
+        let display = display_crop(&frame, w as u32, h as u32, &landmarks, wide);
+        assert_eq!(display.len(), wide.size * wide.size);
+        let apply = |m: [f32; 6], (x, y): (f32, f32)| {
+            (m[0] * x + m[1] * y + m[2], m[3] * x + m[4] * y + m[5])
+        };
+        let pad = wide.margin * ALIGNED_SIZE as f32;
+        let scale = wide.size as f32 / (ALIGNED_SIZE as f32 + 2.0 * pad);
+        let (ax, ay) = apply(alignment_transform(&landmarks), landmarks[0]);
+        let (dx, dy) = apply(display_transform(&landmarks, wide), landmarks[0]);
+        assert!((dx - (ax + pad) * scale).abs() < 1e-3 && (dy - (ay + pad) * scale).abs() < 1e-3);
+    }
+
     #[test]
     fn test_align_face_output_size() {
         let frame = vec![128u8; 640 * 480];
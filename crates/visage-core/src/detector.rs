@@ -3,6 +3,8 @@
 //! Implements the SCRFD (Sample and Computation Redistribution for Efficient Face
 //! Detection) model with 3-stride anchor-free decoding and NMS post-processing.
 
+use crate::error::{ErrorKind, VisageError};
+use crate::inference::InferenceOptions;
 use crate::types::BoundingBox;
 use ndarray::Array4;
 use ort::session::Session;
@@ -18,6 +20,10 @@ const SCRFD_CONFIDENCE_THRESHOLD: f32 = 0.5;
 const SCRFD_NMS_THRESHOLD: f32 = 0.4;
 const SCRFD_STRIDES: [usize; 3] = [8, 16, 32];
 const SCRFD_ANCHORS_PER_CELL: usize = 2;
+/// Most above-threshold anchors decoded per stride. A real frame yields a few
+/// dozen; IR speckle can light up thousands, which would make NMS quadratic
+/// in them. Only the highest-scoring candidates are kept.
+const SCRFD_MAX_CANDIDATES_PER_STRIDE: usize = 300;
 
 #[derive(Error, Debug)]
 pub enum DetectorError {
@@ -31,6 +37,23 @@ pub enum DetectorError {
     Ort(#[from] ort::Error),
 }
 
+impl DetectorError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DetectorError::ModelNotFound(_) => ErrorKind::Model,
+            DetectorError::InferenceFailed(_) | DetectorError::Ort(_) => ErrorKind::Inference,
+            DetectorError::NoFaceDetected => ErrorKind::NoFace,
+        }
+    }
+}
+
+impl From<DetectorError> for VisageError {
+    fn from(e: DetectorError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
 /// Metadata for coordinate de-mapping after letterbox resize.
 struct LetterboxInfo {
     scale: f32,
@@ -49,17 +72,50 @@ pub struct FaceDetector {
     /// Per-stride output indices [(score, bbox, kps)] for strides [8, 16, 32].
     /// Discovered by name at load time; falls back to positional ordering.
     stride_indices: [StrideOutputIndices; 3],
+    /// Gaussian Soft-NMS sigma; `None` selects classic (hard) NMS.
+    soft_nms_sigma: Option<f32>,
 }
 
 impl FaceDetector {
     /// Load the SCRFD ONNX model from the given path.
     pub fn load(model_path: &str) -> Result<Self, DetectorError> {
+        Self::load_with(model_path, InferenceOptions::default())
+    }
+
+    /// [`load`](Self::load) with explicit session settings, e.g.
+    /// [`InferenceOptions::reproducible`].
+    pub fn load_with(model_path: &str, options: InferenceOptions) -> Result<Self, DetectorError> {
+        Self::load_session(model_path, options, None)
+    }
+
+    /// [`load_with`](Self::load_with), recording ONNX Runtime's per-operator
+    /// profile of every detection until [`end_profiling`](Self::end_profiling)
+    /// writes it to `<prefix>_<timestamp>.json`.
+    pub fn load_profiled(
+        model_path: &str,
+        options: InferenceOptions,
+        prefix: &Path,
+    ) -> Result<Self, DetectorError> {
+        Self::load_session(model_path, options, Some(prefix))
+    }
+
+    /// Stop profiling and write the profile; returns the file's path. Only
+    /// for a detector from [`load_profiled`](Self::load_profiled).
+    pub fn end_profiling(&mut self) -> Result<String, DetectorError> {
+        Ok(self.session.end_profiling()?)
+    }
+
+    fn load_session(
+        model_path: &str,
+        options: InferenceOptions,
+        profile: Option<&Path>,
+    ) -> Result<Self, DetectorError> {
         if !Path::new(model_path).exists() {
             return Err(DetectorError::ModelNotFound(model_path.to_string()));
         }
 
-        let session = Session::builder()?
-            .with_intra_threads(2)?
+        let session = options
+            .session_builder(profile)?
             .commit_from_file(model_path)?;
 
         let output_names: Vec<String> = session
@@ -73,6 +129,7 @@ impl FaceDetector {
             path = model_path,
             inputs = ?session.inputs().iter().map(|i| (i.name(), i.dtype())).collect::<Vec<_>>(),
             outputs = ?output_names,
+            session = %options.summary(),
             "loaded SCRFD model"
         );
 
@@ -94,9 +151,18 @@ impl FaceDetector {
             input_height: SCRFD_INPUT_SIZE,
             input_width: SCRFD_INPUT_SIZE,
             stride_indices,
+            soft_nms_sigma: None,
         })
     }
 
+    /// Use Gaussian Soft-NMS with the given `sigma` (typically 0.5) instead of
+    /// hard NMS: overlapping detections have their confidence decayed rather
+    /// than being dropped outright, which keeps adjacent faces apart in crowded
+    /// frames. `None` restores hard NMS.
+    pub fn set_soft_nms(&mut self, sigma: Option<f32>) {
+        self.soft_nms_sigma = sigma.filter(|s| *s > 0.0);
+    }
+
     /// Detect faces in a grayscale frame, returning bounding boxes sorted by confidence.
     pub fn detect(
         &mut self,
@@ -140,14 +206,11 @@ impl FaceDetector {
             all_detections.extend(dets);
         }
 
-        let mut result = nms(all_detections, SCRFD_NMS_THRESHOLD);
-        result.sort_by(|a, b| {
-            b.confidence
-                .partial_cmp(&a.confidence)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        Ok(result)
+        // Both variants return detections by descending confidence.
+        Ok(match self.soft_nms_sigma {
+            Some(sigma) => soft_nms(all_detections, sigma, SCRFD_CONFIDENCE_THRESHOLD),
+            None => nms(all_detections, SCRFD_NMS_THRESHOLD),
+        })
     }
 
     /// Preprocess a grayscale frame into a NCHW float tensor with letterbox padding.
@@ -292,14 +355,14 @@ fn decode_stride(
     let grid_w = input_width / stride;
     let num_anchors = grid_h * grid_w * SCRFD_ANCHORS_PER_CELL;
 
-    let mut detections = Vec::new();
-
-    for idx in 0..num_anchors {
-        let score = scores.get(idx).copied().unwrap_or(0.0);
-        if score <= threshold {
-            continue;
-        }
+    let candidates = top_candidates(
+        &scores[..num_anchors.min(scores.len())],
+        threshold,
+        SCRFD_MAX_CANDIDATES_PER_STRIDE,
+    );
+    let mut detections = Vec::with_capacity(candidates.len());
 
+    for (idx, score) in candidates {
         let anchor_idx = idx / SCRFD_ANCHORS_PER_CELL;
         let cy = (anchor_idx / grid_w) as f32;
         let cx = (anchor_idx % grid_w) as f32;
@@ -353,56 +416,130 @@ fn decode_stride(
     detections
 }
 
-/// Non-Maximum Suppression: remove overlapping detections.
-fn nms(mut detections: Vec<BoundingBox>, iou_threshold: f32) -> Vec<BoundingBox> {
-    detections.sort_by(|a, b| {
-        b.confidence
-            .partial_cmp(&a.confidence)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+/// `(anchor index, score)` of the anchors scoring above `threshold`, limited
+/// to the `cap` best. Order is unspecified.
+fn top_candidates(scores: &[f32], threshold: f32, cap: usize) -> Vec<(usize, f32)> {
+    let mut candidates: Vec<(usize, f32)> = scores
+        .iter()
+        .enumerate()
+        .filter(|(_, &score)| score > threshold)
+        .map(|(idx, &score)| (idx, score))
+        .collect();
+    if candidates.len() > cap && cap > 0 {
+        tracing::debug!(
+            candidates = candidates.len(),
+            cap,
+            "SCRFD: too many candidate detections; keeping the best"
+        );
+        // Equal scores go to the earlier anchor, and the survivors are put
+        // back in anchor order, so the result never depends on how the
+        // selection algorithm happens to order ties.
+        candidates.select_nth_unstable_by(cap - 1, |a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates.truncate(cap);
+        candidates.sort_unstable_by_key(|&(idx, _)| idx);
+    }
+    candidates
+}
 
-    let mut keep = Vec::new();
-    let mut suppressed = vec![false; detections.len()];
+/// Sort detections by descending confidence.
+fn sort_by_confidence(detections: &mut [BoundingBox]) {
+    detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+}
 
-    for i in 0..detections.len() {
-        if suppressed[i] {
+/// Non-Maximum Suppression: remove overlapping detections. Returns the
+/// survivors by descending confidence.
+///
+/// Works on indices and a flat copy of the box corners, and moves the
+/// survivors out at the end instead of cloning them.
+fn nms(mut detections: Vec<BoundingBox>, iou_threshold: f32) -> Vec<BoundingBox> {
+    sort_by_confidence(&mut detections);
+    let corners: Vec<Corners> = detections.iter().map(Corners::of).collect();
+
+    let mut keep = vec![true; detections.len()];
+    for i in 0..corners.len() {
+        if !keep[i] {
             continue;
         }
-        keep.push(detections[i].clone());
-
-        for j in (i + 1)..detections.len() {
-            if suppressed[j] {
-                continue;
-            }
-            if iou(&detections[i], &detections[j]) > iou_threshold {
-                suppressed[j] = true;
+        for j in (i + 1)..corners.len() {
+            if keep[j] && corners[i].iou(&corners[j]) > iou_threshold {
+                keep[j] = false;
             }
         }
     }
 
-    keep
+    detections
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(det, kept)| kept.then_some(det))
+        .collect()
 }
 
-/// Compute Intersection-over-Union between two bounding boxes.
-fn iou(a: &BoundingBox, b: &BoundingBox) -> f32 {
-    let x1 = a.x.max(b.x);
-    let y1 = a.y.max(b.y);
-    let x2 = (a.x + a.width).min(b.x + b.width);
-    let y2 = (a.y + a.height).min(b.y + b.height);
+/// Gaussian Soft-NMS (Bodla et al., 2017): instead of discarding a box that
+/// overlaps a stronger one, decay its confidence by `exp(-iou² / sigma)` and
+/// drop it only once it falls to `score_threshold` or below. Returns the
+/// survivors, with decayed confidences, by descending confidence.
+fn soft_nms(detections: Vec<BoundingBox>, sigma: f32, score_threshold: f32) -> Vec<BoundingBox> {
+    let corners: Vec<Corners> = detections.iter().map(Corners::of).collect();
+    let mut scores: Vec<f32> = detections.iter().map(|d| d.confidence).collect();
+    let mut slots: Vec<Option<BoundingBox>> = detections.into_iter().map(Some).collect();
+    let mut remaining: Vec<usize> = (0..slots.len()).collect();
+    let mut keep = Vec::new();
 
-    let inter_w = (x2 - x1).max(0.0);
-    let inter_h = (y2 - y1).max(0.0);
-    let inter_area = inter_w * inter_h;
+    while let Some(pos) =
+        (0..remaining.len()).max_by(|&a, &b| scores[remaining[a]].total_cmp(&scores[remaining[b]]))
+    {
+        let best = remaining.swap_remove(pos);
+        if let Some(mut det) = slots[best].take() {
+            det.confidence = scores[best];
+            keep.push(det);
+        }
+        remaining.retain(|&j| {
+            let overlap = corners[best].iou(&corners[j]);
+            scores[j] *= (-(overlap * overlap) / sigma).exp();
+            scores[j] > score_threshold
+        });
+    }
 
-    let area_a = a.width * a.height;
-    let area_b = b.width * b.height;
-    let union_area = area_a + area_b - inter_area;
+    keep
+}
 
-    if union_area > 0.0 {
-        inter_area / union_area
-    } else {
-        0.0
+/// Box corners and area, precomputed once per NMS pass.
+struct Corners {
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    area: f32,
+}
+
+impl Corners {
+    fn of(b: &BoundingBox) -> Self {
+        Self {
+            x1: b.x,
+            y1: b.y,
+            x2: b.x + b.width,
+            y2: b.y + b.height,
+            area: b.width * b.height,
+        }
     }
+
+    fn iou(&self, other: &Self) -> f32 {
+        let inter_w = (self.x2.min(other.x2) - self.x1.max(other.x1)).max(0.0);
+        let inter_h = (self.y2.min(other.y2) - self.y1.max(other.y1)).max(0.0);
+        let inter_area = inter_w * inter_h;
+        let union_area = self.area + other.area - inter_area;
+        if union_area > 0.0 {
+            inter_area / union_area
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Compute Intersection-over-Union between two bounding boxes.
+#[cfg(test)]
+fn iou(a: &BoundingBox, b: &BoundingBox) -> f32 {
+    Corners::of(a).iou(&Corners::of(b))
 }
 
 #[cfg(test)]
@@ -465,6 +602,76 @@ mod tests {
         assert_eq!(result.len(), 2);
     }
 
+    #[test]
+    fn test_nms_handles_dense_detections() {
+        // A 60x60 grid of small, mostly overlapping boxes — the shape of IR
+        // speckle. Every box overlaps its neighbours heavily, so only a
+        // sparse subset survives, best first.
+        let detections: Vec<BoundingBox> = (0..3600)
+            .map(|i| {
+                let (gx, gy) = ((i % 60) as f32, (i / 60) as f32);
+                make_bbox(
+                    gx * 2.0,
+                    gy * 2.0,
+                    10.0,
+                    10.0,
+                    0.5 + (i % 97) as f32 / 200.0,
+                )
+            })
+            .collect();
+        let result = nms(detections, 0.4);
+        assert!(
+            !result.is_empty() && result.len() < 3600 / 4,
+            "{}",
+            result.len()
+        );
+        assert!(result
+            .windows(2)
+            .all(|w| w[0].confidence >= w[1].confidence));
+        for (i, a) in result.iter().enumerate() {
+            for b in &result[i + 1..] {
+                assert!(iou(a, b) <= 0.4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_soft_nms_decays_instead_of_dropping() {
+        let detections = vec![
+            make_bbox(0.0, 0.0, 100.0, 100.0, 0.9),
+            // IoU ≈ 0.43 with the first box: dropped by hard NMS at 0.4.
+            make_bbox(0.0, 40.0, 100.0, 100.0, 0.85),
+            make_bbox(200.0, 200.0, 50.0, 50.0, 0.7),
+            make_bbox(0.0, 1.0, 100.0, 100.0, 0.6),
+        ];
+        assert_eq!(nms(detections.clone(), 0.4).len(), 2);
+
+        let result = soft_nms(detections, 0.5, 0.5);
+        assert_eq!(result.len(), 3);
+        assert!((result[0].confidence - 0.9).abs() < 1e-6);
+        // exp(-0.43² / 0.5) ≈ 0.69, so 0.85 decays to ≈ 0.59 — below the
+        // 0.7 box. The near-duplicate (IoU ≈ 0.98) decays away entirely.
+        assert!((result[1].confidence - 0.7).abs() < 1e-6);
+        assert!(result[2].confidence < 0.7 && result[2].confidence > 0.5);
+    }
+
+    #[test]
+    fn test_top_candidates_caps_per_stride() {
+        let scores: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+        let top = top_candidates(&scores, 0.5, 10);
+        assert_eq!(top.len(), 10);
+        assert!(top.iter().all(|&(idx, score)| idx >= 990 && score >= 0.99));
+        assert_eq!(top_candidates(&scores, 0.995, 10).len(), 4);
+
+        // Ties at the cut go to the earliest anchors, returned in anchor order.
+        let flat = vec![0.9f32; 50];
+        let top = top_candidates(&flat, 0.5, 5);
+        assert_eq!(
+            top.iter().map(|&(idx, _)| idx).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+    }
+
     #[test]
     fn test_nms_empty() {
         let result = nms(vec![], 0.4);
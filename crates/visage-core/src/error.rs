@@ -0,0 +1,250 @@
+//! The error type shared by every Visage crate.
+//!
+//! Each crate keeps its own detailed error enum (`DetectorError`,
+//! `CameraError`, the daemon's `EngineError`, ...), and each of them sorts
+//! itself into one [`ErrorKind`]. At a boundary — the D-Bus API, the CLI, an
+//! embedding application — the error becomes a [`VisageError`]: the kind,
+//! with its stable [`code`](ErrorKind::code) and
+//! [D-Bus error name](ErrorKind::dbus_name), the original message, and the
+//! original error as its `source`. Clients match on the kind or the code,
+//! never on message text.
+
+use thiserror::Error;
+
+/// Prefix of the D-Bus error names specific to Visage.
+pub const DBUS_ERROR_PREFIX: &str = "org.freedesktop.Visage1.Error";
+
+/// What went wrong, independent of which crate noticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A malformed argument or option.
+    InvalidArgument,
+    /// The caller may not do this.
+    PermissionDenied,
+    /// No such user, model, tenant or session.
+    NotFound,
+    /// The user has no enrolled face models.
+    NotEnrolled,
+    /// Too many failed attempts; try again later.
+    RateLimited,
+    /// A size or count limit was reached, or a request exceeded its budget.
+    LimitExceeded,
+    /// Disabled by configuration or not built in.
+    NotSupported,
+    /// The camera could not be opened or failed during a capture.
+    Camera,
+    /// Another application is streaming from the camera.
+    CameraBusy,
+    /// The camera went away (unplugged, USB reset).
+    CameraDisconnected,
+    /// Every captured frame was too dark to use.
+    TooDark,
+    /// Frames were lit but too saturated, blurry or moving.
+    PoorQuality,
+    /// No face in any captured frame.
+    NoFace,
+    /// No face in the head pose asked for.
+    WrongPose,
+    /// The face failed a liveness check (landmarks, RGB, depth).
+    LivenessFailed,
+    /// The capture looked replayed.
+    StaticScene,
+    /// The deadline passed before a decision.
+    Timeout,
+    /// The caller withdrew the request.
+    Cancelled,
+    /// A model file is missing, corrupt or cannot be loaded.
+    Model,
+    /// Inference failed on a loaded model.
+    Inference,
+    /// The face model database or its key failed.
+    Storage,
+    /// A bug or an unexpected state — not the caller's fault.
+    Internal,
+}
+
+impl ErrorKind {
+    /// Every kind, for tables and tests.
+    pub const ALL: [ErrorKind; 22] = [
+        Self::InvalidArgument,
+        Self::PermissionDenied,
+        Self::NotFound,
+        Self::NotEnrolled,
+        Self::RateLimited,
+        Self::LimitExceeded,
+        Self::NotSupported,
+        Self::Camera,
+        Self::CameraBusy,
+        Self::CameraDisconnected,
+        Self::TooDark,
+        Self::PoorQuality,
+        Self::NoFace,
+        Self::WrongPose,
+        Self::LivenessFailed,
+        Self::StaticScene,
+        Self::Timeout,
+        Self::Cancelled,
+        Self::Model,
+        Self::Inference,
+        Self::Storage,
+        Self::Internal,
+    ];
+
+    /// Stable machine-readable code. Where a verify failure reason names the
+    /// same condition, the code is the same. Never change existing values.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::InvalidArgument => "invalid_argument",
+            Self::PermissionDenied => "permission_denied",
+            Self::NotFound => "not_found",
+            Self::NotEnrolled => "not_enrolled",
+            Self::RateLimited => "rate_limited",
+            Self::LimitExceeded => "limit_exceeded",
+            Self::NotSupported => "not_supported",
+            Self::Camera => "camera_error",
+            Self::CameraBusy => "camera_busy",
+            Self::CameraDisconnected => "camera_disconnected",
+            Self::TooDark => "too_dark",
+            Self::PoorQuality => "poor_quality",
+            Self::NoFace => "no_face",
+            Self::WrongPose => "wrong_pose",
+            Self::LivenessFailed => "liveness_failed",
+            Self::StaticScene => "static_scene",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Model => "model_error",
+            Self::Inference => "inference_error",
+            Self::Storage => "storage_error",
+            Self::Internal => "internal_error",
+        }
+    }
+
+    /// The kind with code `code`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// D-Bus error name. Kinds with a standard `org.freedesktop.DBus.Error`
+    /// equivalent use it; the others are `org.freedesktop.Visage1.Error.*`.
+    pub fn dbus_name(self) -> &'static str {
+        match self {
+            Self::InvalidArgument => "org.freedesktop.DBus.Error.InvalidArgs",
+            Self::PermissionDenied => "org.freedesktop.DBus.Error.AccessDenied",
+            Self::LimitExceeded => "org.freedesktop.DBus.Error.LimitsExceeded",
+            Self::NotSupported => "org.freedesktop.DBus.Error.NotSupported",
+            Self::NotFound => "org.freedesktop.Visage1.Error.NotFound",
+            Self::NotEnrolled => "org.freedesktop.Visage1.Error.NotEnrolled",
+            Self::RateLimited => "org.freedesktop.Visage1.Error.RateLimited",
+            Self::Camera => "org.freedesktop.Visage1.Error.Camera",
+            Self::CameraBusy => "org.freedesktop.Visage1.Error.CameraBusy",
+            Self::CameraDisconnected => "org.freedesktop.Visage1.Error.CameraDisconnected",
+            Self::TooDark => "org.freedesktop.Visage1.Error.TooDark",
+            Self::PoorQuality => "org.freedesktop.Visage1.Error.PoorQuality",
+            Self::NoFace => "org.freedesktop.Visage1.Error.NoFace",
+            Self::WrongPose => "org.freedesktop.Visage1.Error.WrongPose",
+            Self::LivenessFailed => "org.freedesktop.Visage1.Error.LivenessFailed",
+            Self::StaticScene => "org.freedesktop.Visage1.Error.StaticScene",
+            Self::Timeout => "org.freedesktop.Visage1.Error.Timeout",
+            Self::Cancelled => "org.freedesktop.Visage1.Error.Cancelled",
+            Self::Model => "org.freedesktop.Visage1.Error.Model",
+            Self::Inference => "org.freedesktop.Visage1.Error.Inference",
+            Self::Storage => "org.freedesktop.Visage1.Error.Storage",
+            Self::Internal => "org.freedesktop.Visage1.Error.Internal",
+        }
+    }
+
+    /// The kind whose [`dbus_name`](Self::dbus_name) is `name`.
+    pub fn from_dbus_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.dbus_name() == name)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// An error of any Visage crate, by [`ErrorKind`], with the message of the
+/// error it came from.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub struct VisageError {
+    kind: ErrorKind,
+    message: String,
+    #[source]
+    source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+}
+
+impl VisageError {
+    /// An error of `kind` with `message` and no underlying error.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// `source` as an error of `kind`, keeping its message.
+    pub fn wrap(kind: ErrorKind, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self {
+            kind,
+            message: source.to_string(),
+            source: Some(Box::new(source)),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Shorthand for `self.kind().code()`.
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_and_names_are_unique_and_round_trip() {
+        let mut codes = std::collections::HashSet::new();
+        let mut names = std::collections::HashSet::new();
+        for kind in ErrorKind::ALL {
+            assert!(codes.insert(kind.code()), "{kind}");
+            assert!(names.insert(kind.dbus_name()), "{kind}");
+            assert_eq!(ErrorKind::from_code(kind.code()), Some(kind));
+            assert_eq!(ErrorKind::from_dbus_name(kind.dbus_name()), Some(kind));
+            let name = kind.dbus_name();
+            assert!(
+                name.starts_with(DBUS_ERROR_PREFIX)
+                    || name.starts_with("org.freedesktop.DBus.Error."),
+                "{name}"
+            );
+        }
+        assert_eq!(ErrorKind::from_code("no_such_code"), None);
+    }
+
+    #[test]
+    fn wrapped_errors_keep_message_and_source() {
+        use std::error::Error as _;
+        let io = std::io::Error::other("disk on fire");
+        let err = VisageError::wrap(ErrorKind::Storage, io);
+        assert_eq!(err.kind(), ErrorKind::Storage);
+        assert_eq!(err.code(), "storage_error");
+        assert_eq!(err.to_string(), "disk on fire");
+        assert!(err.source().is_some());
+
+        let plain = VisageError::new(ErrorKind::NotEnrolled, "no enrolled models");
+        assert_eq!(plain.message(), "no enrolled models");
+        assert!(plain.source().is_none());
+    }
+}
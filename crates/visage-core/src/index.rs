@@ -0,0 +1,262 @@
+//! Approximate nearest-neighbour index for large identification galleries.
+//!
+//! [`OpenSetMatcher`] compares the probe against every enrolled model, which
+//! is what 1:1 verification and household-sized galleries want. A kiosk or
+//! door-entry gallery with hundreds of identities pays that cost on every
+//! frame; [`GalleryIndex`] builds an HNSW graph over the gallery once and
+//! [`IndexedMatcher`] narrows each comparison to a short list of candidates,
+//! which are then rescored exactly by the wrapped matcher.
+//!
+//! The index is a snapshot: it records a fingerprint of the gallery it was
+//! built from — model IDs and embeddings — and [`IndexedMatcher`] falls back
+//! to the exhaustive scan when handed a different gallery, including one
+//! whose models were re-embedded under the same IDs. Unlike [`CosineMatcher`](crate::CosineMatcher),
+//! the indexed path is *not* constant-time — use it for identification only.
+
+use crate::types::{Embedding, FaceModel, MatchResult, Matcher};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Number of candidates rescored exactly per probe.
+pub const DEFAULT_SHORTLIST: usize = 32;
+
+/// Embedding as an HNSW point; distance is `1 - cosine similarity`.
+#[derive(Clone)]
+struct IndexPoint(Embedding);
+
+impl instant_distance::Point for IndexPoint {
+    fn distance(&self, other: &Self) -> f32 {
+        1.0 - self.0.similarity(&other.0)
+    }
+}
+
+/// HNSW index over a gallery snapshot. Values are positions in that gallery.
+pub struct GalleryIndex {
+    map: instant_distance::HnswMap<IndexPoint, usize>,
+    fingerprint: u64,
+}
+
+impl GalleryIndex {
+    /// Build an index over `gallery`. Cost grows as `n log n`; build it off
+    /// the request path and reuse it until the gallery changes.
+    pub fn build(gallery: &[FaceModel]) -> Self {
+        let points = gallery
+            .iter()
+            .map(|m| IndexPoint(m.embedding.clone()))
+            .collect();
+        let values = (0..gallery.len()).collect();
+        Self {
+            map: instant_distance::Builder::default().build(points, values),
+            fingerprint: fingerprint(gallery),
+        }
+    }
+
+    /// Whether this index was built from exactly `gallery` (same models with
+    /// the same embeddings, same order).
+    pub fn matches(&self, gallery: &[FaceModel]) -> bool {
+        self.fingerprint == fingerprint(gallery)
+    }
+
+    /// Gallery positions of the (approximately) `k` nearest models to `probe`,
+    /// closest first.
+    pub fn shortlist(&self, probe: &Embedding, k: usize) -> Vec<usize> {
+        let mut search = instant_distance::Search::default();
+        self.map
+            .search(&IndexPoint(probe.clone()), &mut search)
+            .take(k)
+            .map(|item| *item.value)
+            .collect()
+    }
+
+    /// Gallery positions of the models nearest to `probe`, closest first, as
+    /// far as one search reaches.
+    fn nearest(&self, probe: &Embedding) -> Vec<usize> {
+        let mut search = instant_distance::Search::default();
+        self.map
+            .search(&IndexPoint(probe.clone()), &mut search)
+            .map(|item| *item.value)
+            .collect()
+    }
+}
+
+/// Order-sensitive fingerprint of a gallery's model IDs and embeddings.
+fn fingerprint(gallery: &[FaceModel]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    gallery.len().hash(&mut hasher);
+    for model in gallery {
+        model.id.hash(&mut hasher);
+        // The length prefix covers the dimension.
+        let bits: Vec<u32> = model.embedding.values.iter().map(|v| v.to_bits()).collect();
+        bits.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Runs `inner` over the index's shortlist instead of the whole gallery.
+///
+/// With a shortlist of a few dozen, the best match is among the candidates in
+/// practice. [`OpenSetMatcher`](crate::OpenSetMatcher) also needs the best
+/// score of another user for its margin, so a shortlist holding a single
+/// user's models is extended with the nearest model of anyone else — further
+/// along the index search, or by an exhaustive scan of the other users if the
+/// search ends first. If the gallery does not match the index, every entry is
+/// compared as usual.
+pub struct IndexedMatcher<'a, M> {
+    pub index: &'a GalleryIndex,
+    pub inner: M,
+    pub shortlist: usize,
+}
+
+impl<M: Matcher> Matcher for IndexedMatcher<'_, M> {
+    fn compare(&self, probe: &Embedding, gallery: &[FaceModel], threshold: f32) -> MatchResult {
+        if !self.index.matches(gallery) {
+            return self.inner.compare(probe, gallery, threshold);
+        }
+        let mut nearest = self.index.nearest(probe).into_iter();
+        let mut positions: Vec<usize> = nearest.by_ref().take(self.shortlist).collect();
+        if let Some(user) = sole_user(gallery, &positions) {
+            let other = nearest
+                .find(|&i| gallery.get(i).is_some_and(|m| m.user != user))
+                .or_else(|| best_of_others(probe, gallery, user));
+            positions.extend(other);
+        }
+        let candidates: Vec<FaceModel> = positions
+            .into_iter()
+            .filter_map(|i| gallery.get(i).cloned())
+            .collect();
+        self.inner.compare(probe, &candidates, threshold)
+    }
+}
+
+/// The user owning every model at `positions`, if there is exactly one.
+fn sole_user<'g>(gallery: &'g [FaceModel], positions: &[usize]) -> Option<&'g str> {
+    let mut users = positions
+        .iter()
+        .filter_map(|&i| gallery.get(i))
+        .map(|m| &m.user);
+    let first = users.next()?;
+    users.all(|u| u == first).then_some(first.as_str())
+}
+
+/// Position of the best-scoring model not owned by `user`, by an exhaustive scan.
+fn best_of_others(probe: &Embedding, gallery: &[FaceModel], user: &str) -> Option<usize> {
+    let score = |m: &FaceModel| probe.similarity(&m.embedding) - m.threshold_offset;
+    gallery
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.user != user)
+        .max_by(|(_, a), (_, b)| score(a).total_cmp(&score(b)))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenSetMatcher;
+
+    fn gallery(n: usize) -> Vec<FaceModel> {
+        // Unit vectors spread around a circle, each its own user.
+        (0..n)
+            .map(|i| {
+                let angle = i as f32 / n as f32 * std::f32::consts::TAU;
+                FaceModel {
+                    id: format!("m{i}"),
+                    user: format!("user{i}"),
+                    label: "default".into(),
+                    embedding: Embedding {
+                        values: vec![angle.cos(), angle.sin()],
+                        model_version: None,
+                    },
+                    created_at: "".into(),
+                    tenant: String::new(),
+                    threshold_offset: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn indexed_matcher_agrees_with_exhaustive_scan() {
+        let gallery = gallery(300);
+        let index = GalleryIndex::build(&gallery);
+        let matcher = IndexedMatcher {
+            index: &index,
+            inner: OpenSetMatcher { margin: 0.0 },
+            shortlist: DEFAULT_SHORTLIST,
+        };
+        let probe = gallery[123].embedding.clone();
+        let indexed = matcher.compare(&probe, &gallery, 0.9);
+        let exhaustive = OpenSetMatcher { margin: 0.0 }.compare(&probe, &gallery, 0.9);
+        assert!(indexed.matched);
+        assert_eq!(indexed.model_id, exhaustive.model_id);
+        assert_eq!(indexed.model_id.as_deref(), Some("m123"));
+    }
+
+    #[test]
+    fn one_user_filling_the_shortlist_still_faces_the_margin() {
+        // Around the circle of other users, alice has more models next to the
+        // probe than the shortlist holds, and bob one just beyond them: too
+        // close for the margin, which the exhaustive scan enforces. With 150
+        // models alice also outlasts the index search itself.
+        for alice_models in [40, 150] {
+            let mut gallery = gallery(200);
+            for i in 0..alice_models {
+                let angle = i as f32 * 0.0001;
+                let mut model = gallery[0].clone();
+                model.id = format!("alice{i}");
+                model.user = "alice".into();
+                model.embedding.values = vec![angle.cos(), angle.sin()];
+                gallery.push(model);
+            }
+            let mut bob = gallery[0].clone();
+            bob.id = "bob".into();
+            bob.user = "bob".into();
+            bob.embedding.values = vec![0.03f32.cos(), 0.03f32.sin()];
+            gallery.push(bob);
+            // Keep user0's own model out of the way.
+            gallery[0].embedding.values = vec![-1.0, 0.0];
+
+            let index = GalleryIndex::build(&gallery);
+            let matcher = IndexedMatcher {
+                index: &index,
+                inner: OpenSetMatcher { margin: 0.05 },
+                shortlist: DEFAULT_SHORTLIST,
+            };
+            let probe = Embedding {
+                values: vec![1.0, 0.0],
+                model_version: None,
+            };
+            let exhaustive = OpenSetMatcher { margin: 0.05 }.compare(&probe, &gallery, 0.9);
+            let indexed = matcher.compare(&probe, &gallery, 0.9);
+            assert!(!exhaustive.matched);
+            assert!(!indexed.matched, "{alice_models} models for alice");
+        }
+    }
+
+    #[test]
+    fn stale_index_falls_back_to_full_gallery() {
+        let old = gallery(10);
+        let index = GalleryIndex::build(&old);
+        let mut current = gallery(10);
+        current[0].id = "replaced".into();
+        assert!(index.matches(&old));
+        assert!(!index.matches(&current));
+
+        // Same IDs, one model re-embedded (a re-enrollment, a model upgrade).
+        let mut reembedded = gallery(10);
+        reembedded[3].embedding.values = vec![0.6, 0.8];
+        assert!(!index.matches(&reembedded));
+        let mut resized = gallery(10);
+        resized[3].embedding.values.push(0.0);
+        assert!(!index.matches(&resized));
+
+        let matcher = IndexedMatcher {
+            index: &index,
+            inner: OpenSetMatcher { margin: 0.0 },
+            shortlist: 1,
+        };
+        let probe = current[0].embedding.clone();
+        let result = matcher.compare(&probe, &current, 0.9);
+        assert_eq!(result.model_id.as_deref(), Some("replaced"));
+    }
+}
@@ -0,0 +1,73 @@
+//! ONNX Runtime session settings shared by the detector and recognizer.
+//!
+//! By default sessions favour latency: a small intra-op thread pool and every
+//! graph optimization ONNX Runtime offers, some of which pick kernels by CPU
+//! features. Reproducibility mode fixes those choices so two runs over the
+//! same frames produce bit-identical detections and embeddings — what an
+//! accuracy investigation comparing two versions or two machines needs. It is
+//! slower, and across CPUs with different vector extensions the MLAS kernels
+//! can still round differently; compare like with like.
+//!
+//! A session can also record ONNX Runtime's profile: the time of every
+//! operator of every run, written as Chrome trace JSON (`chrome://tracing`,
+//! Perfetto) when the session ends profiling. The detector and recognizer load
+//! such sessions with `load_profiled`.
+
+use std::path::Path;
+
+use ort::session::builder::{GraphOptimizationLevel, SessionBuilder};
+use ort::session::Session;
+
+/// Intra-op threads per session outside reproducibility mode.
+const DEFAULT_INTRA_THREADS: usize = 2;
+
+/// How [`FaceDetector`](crate::FaceDetector) and
+/// [`FaceRecognizer`](crate::FaceRecognizer) build their inference sessions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InferenceOptions {
+    /// One thread, sequential execution, deterministic kernels, and only the
+    /// hardware-independent (basic) graph optimizations.
+    pub reproducible: bool,
+}
+
+impl InferenceOptions {
+    /// Options for reproducibility mode.
+    pub fn reproducible() -> Self {
+        Self { reproducible: true }
+    }
+
+    /// The fixed settings as `key=value` pairs, for the model-load log line.
+    /// Inference always runs on the CPU execution provider.
+    pub fn summary(&self) -> String {
+        if self.reproducible {
+            "provider=cpu intra_threads=1 inter_threads=1 execution=sequential \
+             deterministic_compute=on graph_optimization=basic"
+                .to_string()
+        } else {
+            format!("provider=cpu intra_threads={DEFAULT_INTRA_THREADS} graph_optimization=all")
+        }
+    }
+
+    /// A session builder with these settings applied that, with a
+    /// `profile` prefix, records a profile to `<prefix>_<timestamp>.json`.
+    pub(crate) fn session_builder(&self, profile: Option<&Path>) -> ort::Result<SessionBuilder> {
+        let builder = self.base_builder()?;
+        match profile {
+            Some(prefix) => builder.with_profiling(prefix),
+            None => Ok(builder),
+        }
+    }
+
+    fn base_builder(&self) -> ort::Result<SessionBuilder> {
+        let builder = Session::builder()?;
+        if !self.reproducible {
+            return builder.with_intra_threads(DEFAULT_INTRA_THREADS);
+        }
+        builder
+            .with_intra_threads(1)?
+            .with_inter_threads(1)?
+            .with_parallel_execution(false)?
+            .with_deterministic_compute(true)?
+            .with_optimization_level(GraphOptimizationLevel::Level1)
+    }
+}
@@ -2,17 +2,57 @@
 //!
 //! Uses SCRFD for face detection and ArcFace for face recognition,
 //! both running via ONNX Runtime for CPU inference.
+//!
+//! With `default-features = false` the `onnx` feature is dropped: only the
+//! matching ([`types`]), [`alignment`] and [`pose`] modules remain, with no
+//! native dependencies, so the production math also builds for
+//! `wasm32-unknown-unknown` (admin dashboards, documentation demos).
+//!
+//! The `liveness` feature (on by default) adds the passive liveness checks in
+//! `liveness`; slim embedded builds of the daemon leave it out. The optional
+//! `ann` feature adds [`index`], an HNSW index that shortlists candidates for
+//! identification against very large galleries.
+//!
+//! With `onnx`, [`pad`] runs an optional anti-spoofing model supplied by the
+//! deployment.
 
 pub mod alignment;
+#[cfg(feature = "onnx")]
 pub mod detector;
+pub mod error;
+#[cfg(feature = "ann")]
+pub mod index;
+#[cfg(feature = "onnx")]
+pub mod inference;
+#[cfg(feature = "liveness")]
 pub mod liveness;
+#[cfg(feature = "onnx")]
+pub mod pad;
+pub mod pose;
+#[cfg(feature = "onnx")]
 pub mod recognizer;
 pub mod types;
 
+#[cfg(feature = "onnx")]
 pub use detector::FaceDetector;
-pub use liveness::{check_landmark_stability, LivenessResult};
-pub use recognizer::FaceRecognizer;
-pub use types::{BoundingBox, CosineMatcher, Embedding, FaceModel, MatchResult, Matcher};
+pub use error::{ErrorKind, VisageError};
+#[cfg(feature = "ann")]
+pub use index::{GalleryIndex, IndexedMatcher};
+#[cfg(feature = "onnx")]
+pub use inference::InferenceOptions;
+#[cfg(feature = "liveness")]
+pub use liveness::{
+    check_depth_planarity, check_landmark_stability, LivenessResult, PlanarityResult,
+};
+#[cfg(feature = "onnx")]
+pub use pad::PadModel;
+pub use pose::estimate_pose;
+#[cfg(feature = "onnx")]
+pub use recognizer::{FaceCrops, FaceRecognizer};
+pub use types::{
+    BoundingBox, CosineMatcher, Embedding, FaceModel, HeadPose, MatchResult, Matcher,
+    OpenSetMatcher,
+};
 
 /// Default model directory (XDG data home).
 pub fn default_model_dir() -> std::path::PathBuf {
@@ -14,6 +14,15 @@
 //! - **Blocks:** Printed photographs, static IR images held in front of camera.
 //! - **Does not block:** Video replay attacks (landmarks move in video),
 //!   high-quality 3D masks, or adversarial displays.
+//!
+//! # Depth planarity
+//!
+//! With a depth-capable camera, [`check_depth_planarity`] adds a geometric
+//! check: a photo or a screen is flat, a face is not. It fits a plane to the
+//! depth samples inside the face box and measures how far the face stands
+//! out of it. This blocks printed photos and displays, including video
+//! replays, however they move; a bent print or a 3D mask still needs the
+//! other checks.
 
 /// Result of a landmark stability liveness check.
 #[derive(Debug, Clone)]
@@ -106,6 +115,111 @@ pub fn check_landmark_stability(
     }
 }
 
+/// Result of a depth planarity liveness check.
+#[derive(Debug, Clone)]
+pub struct PlanarityResult {
+    /// Whether the face region has the relief of a real face (true = likely live).
+    pub is_live: bool,
+    /// Spread between the 5th and 95th percentile of the distances from the
+    /// best-fit plane, in millimetres.
+    pub relief_mm: f32,
+    /// Number of depth samples the plane was fitted to.
+    pub samples: usize,
+}
+
+/// Default minimum relief (millimetres) of a live face. A nose stands 20–30 mm
+/// out of the plane of the cheeks; a flat photo measured by a stereo depth
+/// camera at arm's length shows a few millimetres of noise.
+const DEFAULT_MIN_RELIEF_MM: f32 = 15.0;
+
+/// Fewest depth samples the plane fit accepts.
+const MIN_DEPTH_SAMPLES: usize = 50;
+
+/// Check whether the depth inside a face box is flat, as a photo or a screen is.
+///
+/// `samples` are `(u, v, z)`: pixel coordinates in the depth frame and depth
+/// in millimetres, from the face region only. A plane seen through a pinhole
+/// camera has an inverse depth linear in the pixel coordinates, so the fit is
+/// `1/z = a·u + b·v + c` — exact for a tilted photo, not only one held square
+/// to the camera. If `min_relief_mm` is `None`, uses [`DEFAULT_MIN_RELIEF_MM`].
+///
+/// **Fails closed** like [`check_landmark_stability`]: fewer than
+/// [`MIN_DEPTH_SAMPLES`] valid samples (no depth reading, out of range)
+/// returns `is_live = false`.
+pub fn check_depth_planarity(
+    samples: &[(f32, f32, f32)],
+    min_relief_mm: Option<f32>,
+) -> PlanarityResult {
+    let threshold = min_relief_mm.unwrap_or(DEFAULT_MIN_RELIEF_MM);
+    let flat = |samples| PlanarityResult {
+        is_live: false,
+        relief_mm: 0.0,
+        samples,
+    };
+
+    let points: Vec<(f64, f64, f64)> = samples
+        .iter()
+        .filter(|(_, _, z)| z.is_finite() && *z > 0.0)
+        .map(|&(u, v, z)| (f64::from(u), f64::from(v), f64::from(z)))
+        .collect();
+    if points.len() < MIN_DEPTH_SAMPLES {
+        return flat(points.len());
+    }
+    let Some(plane) = fit_inverse_depth_plane(&points) else {
+        return flat(points.len());
+    };
+
+    let mut residuals: Vec<f64> = points
+        .iter()
+        .filter_map(|&(u, v, z)| {
+            let inverse = plane.0 * u + plane.1 * v + plane.2;
+            (inverse > 0.0).then(|| z - 1.0 / inverse)
+        })
+        .collect();
+    if residuals.len() < MIN_DEPTH_SAMPLES {
+        return flat(residuals.len());
+    }
+    residuals.sort_by(f64::total_cmp);
+    let at = |q: f64| residuals[((residuals.len() - 1) as f64 * q).round() as usize];
+    let relief_mm = (at(0.95) - at(0.05)) as f32;
+
+    PlanarityResult {
+        is_live: relief_mm >= threshold,
+        relief_mm,
+        samples: points.len(),
+    }
+}
+
+/// Least-squares `(a, b, c)` of `1/z = a·u + b·v + c`; `None` when the
+/// samples are degenerate (all on one line).
+fn fit_inverse_depth_plane(points: &[(f64, f64, f64)]) -> Option<(f64, f64, f64)> {
+    // Centre the coordinates so the normal equations stay well conditioned.
+    let n = points.len() as f64;
+    let (mu, mv) = points
+        .iter()
+        .fold((0.0, 0.0), |(su, sv), &(u, v, _)| (su + u, sv + v));
+    let (mu, mv) = (mu / n, mv / n);
+    let (mut suu, mut suv, mut svv, mut suw, mut svw, mut sw) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for &(u, v, z) in points {
+        let (u, v, w) = (u - mu, v - mv, 1.0 / z);
+        suu += u * u;
+        suv += u * v;
+        svv += v * v;
+        suw += u * w;
+        svw += v * w;
+        sw += w;
+    }
+    // With centred u and v the constant term separates from the slopes.
+    let det = suu * svv - suv * suv;
+    if det.abs() < 1e-9 {
+        return None;
+    }
+    let a = (suw * svv - svw * suv) / det;
+    let b = (svw * suu - suw * suv) / det;
+    let c = sw / n - a * mu - b * mv;
+    Some((a, b, c))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,6 +351,57 @@ mod tests {
         assert!((result.mean_eye_displacement - 2.5).abs() < 1e-6);
     }
 
+    /// Depth samples over a 60×60 px face box of a plane tilted 30° about the
+    /// vertical axis, 500 mm away, seen by a 600 px focal length camera, plus
+    /// `bump(u, v)` millimetres towards the camera.
+    fn tilted_plane(bump: impl Fn(f32, f32) -> f32) -> Vec<(f32, f32, f32)> {
+        let (f, cx) = (600.0f32, 320.0f32);
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        let mut samples = Vec::new();
+        for v in (210..270).step_by(2) {
+            for u in (290..350).step_by(2) {
+                let (u, v) = (u as f32, v as f32);
+                // Ray (x, y, 1) meets the plane sin·x + cos·z = 500·cos.
+                let x = (u - cx) / f;
+                let z = 500.0 * cos / (sin * x + cos);
+                samples.push((u, v, z - bump(u, v)));
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_tilted_photo_is_flat() {
+        let result = check_depth_planarity(&tilted_plane(|_, _| 0.0), None);
+        assert!(!result.is_live);
+        assert!(result.relief_mm < 0.5, "relief {}", result.relief_mm);
+        assert_eq!(result.samples, 900);
+    }
+
+    #[test]
+    fn test_face_relief_passes() {
+        // A 25 mm nose in the middle of the face box.
+        let nose = |u: f32, v: f32| {
+            let d2 = (u - 320.0).powi(2) + (v - 240.0).powi(2);
+            25.0 * (-d2 / (2.0 * 10.0 * 10.0)).exp()
+        };
+        let result = check_depth_planarity(&tilted_plane(nose), None);
+        assert!(result.is_live, "relief {}", result.relief_mm);
+        assert!(result.relief_mm >= DEFAULT_MIN_RELIEF_MM);
+    }
+
+    #[test]
+    fn test_missing_depth_fails_closed() {
+        let mut samples = tilted_plane(|_, _| 0.0);
+        samples.truncate(MIN_DEPTH_SAMPLES - 1);
+        assert!(!check_depth_planarity(&samples, Some(0.0)).is_live);
+        assert!(!check_depth_planarity(&[], Some(0.0)).is_live);
+
+        // Samples without a reading do not count.
+        let holes = vec![(0.0, 0.0, 0.0); 100];
+        assert_eq!(check_depth_planarity(&holes, Some(0.0)).samples, 0);
+    }
+
     #[test]
     fn test_mean_across_multiple_pairs() {
         // 3 frames = 2 pairs
@@ -0,0 +1,227 @@
+//! Presentation attack detection (PAD) via ONNX Runtime.
+//!
+//! The liveness checks in `liveness` judge how a face moves or how flat it
+//! is; a PAD network judges the crop itself — paper texture, moiré, a screen
+//! edge. Visage ships no PAD model: this is a slot for one, with a small
+//! contract so most published anti-spoofing models fit after export:
+//!
+//! - one input, `[1, C, S, S]` float32 with C = 1 or 3 (grey replicated) and
+//!   S read from the model (112 when dynamic), fed the face aligned like the
+//!   ArcFace crop, scaled to S, with pixels divided by 255;
+//! - the first output either one value, the probability the face is live, or
+//!   one logit per class with class 1 "live", turned into a probability with
+//!   a softmax.
+
+use crate::alignment::{self, DisplayCrop};
+use crate::error::{ErrorKind, VisageError};
+use crate::inference::InferenceOptions;
+use crate::types::BoundingBox;
+use ndarray::Array4;
+use ort::session::Session;
+use ort::value::TensorRef;
+use std::path::Path;
+use thiserror::Error;
+
+/// Input side length when the model leaves it dynamic.
+const PAD_DEFAULT_SIZE: usize = alignment::ALIGNED_SIZE;
+
+#[derive(Error, Debug)]
+pub enum PadError {
+    #[error("PAD model file not found: {0}")]
+    ModelNotFound(String),
+    #[error("PAD model input must be [1, 1 or 3, S, S] float32, got {0}")]
+    UnsupportedInput(String),
+    #[error("inference failed: {0}")]
+    InferenceFailed(String),
+    #[error("face has no landmarks — detector must return landmarks for alignment")]
+    NoLandmarks,
+    #[error("ort: {0}")]
+    Ort(#[from] ort::Error),
+}
+
+impl PadError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PadError::ModelNotFound(_) | PadError::UnsupportedInput(_) => ErrorKind::Model,
+            PadError::InferenceFailed(_) | PadError::Ort(_) => ErrorKind::Inference,
+            PadError::NoLandmarks => ErrorKind::Internal,
+        }
+    }
+}
+
+impl From<PadError> for VisageError {
+    fn from(e: PadError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// An anti-spoofing model; see the module docs for the contract.
+pub struct PadModel {
+    session: Session,
+    channels: usize,
+    size: usize,
+}
+
+impl PadModel {
+    /// Load a PAD ONNX model from the given path.
+    pub fn load(model_path: &str) -> Result<Self, PadError> {
+        Self::load_with(model_path, InferenceOptions::default())
+    }
+
+    /// [`load`](Self::load) with explicit session settings.
+    pub fn load_with(model_path: &str, options: InferenceOptions) -> Result<Self, PadError> {
+        if !Path::new(model_path).exists() {
+            return Err(PadError::ModelNotFound(model_path.to_string()));
+        }
+
+        let session = options
+            .session_builder(None)?
+            .commit_from_file(model_path)?;
+
+        let input = session
+            .inputs()
+            .first()
+            .ok_or_else(|| PadError::UnsupportedInput("no inputs".into()))?;
+        let shape = input
+            .dtype()
+            .tensor_shape()
+            .ok_or_else(|| PadError::UnsupportedInput(format!("{:?}", input.dtype())))?;
+        let (channels, size) = input_layout(shape)
+            .ok_or_else(|| PadError::UnsupportedInput(format!("{:?}", &shape[..])))?;
+
+        tracing::info!(
+            path = model_path,
+            channels,
+            size,
+            outputs = ?session.outputs().iter().map(|o| o.name()).collect::<Vec<_>>(),
+            session = %options.summary(),
+            "loaded PAD model"
+        );
+
+        Ok(Self {
+            session,
+            channels,
+            size,
+        })
+    }
+
+    /// The probability, 0–1, that `face` in the grayscale frame is a live
+    /// person rather than a photo, screen or mask.
+    pub fn score(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        face: &BoundingBox,
+    ) -> Result<f32, PadError> {
+        let landmarks = face.landmarks.as_ref().ok_or(PadError::NoLandmarks)?;
+        let input = preprocess(frame, width, height, landmarks, self.channels, self.size);
+        let outputs = self
+            .session
+            .run(ort::inputs![TensorRef::from_array_view(input.view())?])?;
+        let (_, raw) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(|e| PadError::InferenceFailed(format!("PAD score extraction: {e}")))?;
+        live_probability(raw).ok_or_else(|| {
+            PadError::InferenceFailed(format!("unusable PAD output of {} values", raw.len()))
+        })
+    }
+}
+
+/// Channels and side length from an NCHW input shape; dynamic (negative)
+/// dimensions take the defaults.
+fn input_layout(shape: &[i64]) -> Option<(usize, usize)> {
+    let [_, c, h, w] = shape else {
+        return None;
+    };
+    let channels = match *c {
+        1 => 1,
+        3 | -1 => 3,
+        _ => return None,
+    };
+    let size = match (*h, *w) {
+        (h, w) if h > 0 && h == w => h as usize,
+        (h, w) if h < 0 && w < 0 => PAD_DEFAULT_SIZE,
+        _ => return None,
+    };
+    Some((channels, size))
+}
+
+/// The aligned face at `size`×`size`, scaled to 0–1, in `channels` identical
+/// planes.
+fn preprocess(
+    frame: &[u8],
+    width: u32,
+    height: u32,
+    landmarks: &[(f32, f32); 5],
+    channels: usize,
+    size: usize,
+) -> Array4<f32> {
+    let crop = alignment::display_crop(
+        frame,
+        width,
+        height,
+        landmarks,
+        DisplayCrop { margin: 0.0, size },
+    );
+    let plane: Vec<f32> = crop.iter().map(|&p| f32::from(p) / 255.0).collect();
+    let mut tensor = Array4::<f32>::zeros((1, channels, size, size));
+    let data = tensor
+        .as_slice_mut()
+        .expect("freshly allocated tensor is contiguous");
+    for chunk in data.chunks_exact_mut(size * size) {
+        chunk.copy_from_slice(&plane);
+    }
+    tensor
+}
+
+/// The live probability from the model's output: a single value as is
+/// (clamped to 0–1), or the softmax weight of class 1.
+fn live_probability(output: &[f32]) -> Option<f32> {
+    match output {
+        [] => None,
+        [p] => p.is_finite().then(|| p.clamp(0.0, 1.0)),
+        logits => {
+            let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let exps: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+            let sum: f32 = exps.iter().sum();
+            let p = exps[1] / sum;
+            p.is_finite().then_some(p)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_layouts() {
+        assert_eq!(input_layout(&[1, 3, 112, 112]), Some((3, 112)));
+        assert_eq!(input_layout(&[-1, 1, 80, 80]), Some((1, 80)));
+        assert_eq!(input_layout(&[1, 3, -1, -1]), Some((3, PAD_DEFAULT_SIZE)));
+        assert_eq!(input_layout(&[1, 3, 80, 112]), None);
+        assert_eq!(input_layout(&[1, 4, 112, 112]), None);
+        assert_eq!(input_layout(&[1, 112, 112]), None);
+    }
+
+    #[test]
+    fn preprocess_scales_and_replicates() {
+        let frame = vec![255u8; 200 * 200];
+        let tensor = preprocess(&frame, 200, 200, &alignment::REFERENCE_LANDMARKS_112, 3, 80);
+        assert_eq!(tensor.shape(), &[1, 3, 80, 80]);
+        assert_eq!(tensor[[0, 0, 40, 40]], 1.0);
+        assert_eq!(tensor[[0, 0, 40, 40]], tensor[[0, 2, 40, 40]]);
+    }
+
+    #[test]
+    fn probabilities_from_outputs() {
+        assert_eq!(live_probability(&[0.8]), Some(0.8));
+        assert_eq!(live_probability(&[1.7]), Some(1.0));
+        assert_eq!(live_probability(&[f32::NAN]), None);
+        assert_eq!(live_probability(&[]), None);
+        assert!((live_probability(&[0.0, 0.0]).unwrap() - 0.5).abs() < 1e-6);
+        assert!(live_probability(&[-4.0, 6.0, 0.0]).unwrap() > 0.99);
+    }
+}
@@ -0,0 +1,165 @@
+//! Head-pose estimation from the five SCRFD landmarks.
+//!
+//! A geometric approximation rather than a full PnP solve: roll is the angle
+//! of the eye line; yaw and pitch come from where the nose tip sits between
+//! the eyes and between the eye and mouth lines, compared with the frontal
+//! [`REFERENCE_LANDMARKS_112`] and converted to angles with a generic face
+//! depth model. Within about ±40° the estimate is good to a few degrees —
+//! enough to gate frame quality, guide enrollment, or check that someone
+//! faces the screen; it is not a gaze tracker. Yaw is underestimated when the
+//! face looks down steeply (pitch beyond ~25°).
+
+use crate::alignment::REFERENCE_LANDMARKS_112;
+use crate::types::HeadPose;
+
+/// Depth of the nose tip in front of the eye plane, in units of half the
+/// distance between the eyes (generic adult face).
+const NOSE_DEPTH: f32 = 0.8;
+
+/// Estimate yaw, pitch and roll from `[left_eye, right_eye, nose,
+/// left_mouth, right_mouth]` in image coordinates. `None` when the
+/// landmarks are degenerate — eyes on top of each other, or the mouth not
+/// below the eyes once the face is turned upright.
+pub fn estimate_pose(landmarks: &[(f32, f32); 5]) -> Option<HeadPose> {
+    let (roll, ratios) = upright_ratios(landmarks)?;
+    let (_, reference) = upright_ratios(&REFERENCE_LANDMARKS_112)?;
+
+    // Tilting the head by `pitch` moves the nose down by NOSE_DEPTH·sin(pitch)
+    // while the eye–mouth distance shrinks by cos(pitch). Yaw leaves vertical
+    // positions alone, so pitch comes first.
+    let pitch =
+        ((ratios.nose_height - reference.nose_height) * reference.eye_mouth / NOSE_DEPTH).atan();
+    // Turning the head by `yaw` moves the nose sideways by depth·sin(yaw)
+    // while the eye span shrinks by cos(yaw), so the offset ratio is
+    // depth·tan(yaw) — where depth is the nose's, after the pitch. Looking
+    // down steeply brings the nose tip back towards the eye plane and yaw
+    // becomes unobservable; the floor keeps the estimate finite.
+    let nose_y = reference.nose_height * reference.eye_mouth;
+    let depth = (NOSE_DEPTH * pitch.cos() - nose_y * pitch.sin()).max(NOSE_DEPTH / 4.0);
+    let yaw = ((ratios.nose_offset - reference.nose_offset) / depth).atan();
+
+    Some(HeadPose {
+        yaw: yaw.to_degrees(),
+        pitch: pitch.to_degrees(),
+        roll: roll.to_degrees(),
+    })
+}
+
+/// Landmark ratios of a face rotated upright about the eye midpoint.
+struct Ratios {
+    /// Nose distance right of the eye midpoint, in eye half-spans.
+    nose_offset: f32,
+    /// Nose distance below the eye line, as a fraction of the eye–mouth distance.
+    nose_height: f32,
+    /// Eye–mouth distance, in eye half-spans.
+    eye_mouth: f32,
+}
+
+/// The roll angle (radians) and the [`Ratios`] of the face undone of it.
+fn upright_ratios(landmarks: &[(f32, f32); 5]) -> Option<(f32, Ratios)> {
+    let [left_eye, right_eye, nose, left_mouth, right_mouth] = *landmarks;
+    let roll = (right_eye.1 - left_eye.1).atan2(right_eye.0 - left_eye.0);
+    let centre = (
+        (left_eye.0 + right_eye.0) / 2.0,
+        (left_eye.1 + right_eye.1) / 2.0,
+    );
+    let (sin, cos) = (-roll).sin_cos();
+    let upright = |(x, y): (f32, f32)| {
+        let (dx, dy) = (x - centre.0, y - centre.1);
+        (dx * cos - dy * sin, dx * sin + dy * cos)
+    };
+
+    let half_span = upright(right_eye).0;
+    let nose = upright(nose);
+    let mouth_y = (upright(left_mouth).1 + upright(right_mouth).1) / 2.0;
+    if !(half_span > f32::EPSILON && mouth_y > f32::EPSILON) {
+        return None;
+    }
+    Some((
+        roll,
+        Ratios {
+            nose_offset: nose.0 / half_span,
+            nose_height: nose.1 / mouth_y,
+            eye_mouth: mouth_y / half_span,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Orthographic projection of a face model built from the reference
+    /// landmarks, tilted by `pitch`, then turned by `yaw`, then rolled by
+    /// `roll` (degrees).
+    fn project(yaw: f32, pitch: f32, roll: f32) -> [(f32, f32); 5] {
+        let r = REFERENCE_LANDMARKS_112;
+        let centre = ((r[0].0 + r[1].0) / 2.0, (r[0].1 + r[1].1) / 2.0);
+        let half_span = (r[1].0 - r[0].0) / 2.0;
+        let (sy, cy) = yaw.to_radians().sin_cos();
+        let (sp, cp) = pitch.to_radians().sin_cos();
+        let (sr, cr) = roll.to_radians().sin_cos();
+        let mut out = [(0.0, 0.0); 5];
+        for (i, &(x, y)) in r.iter().enumerate() {
+            let z = if i == 2 { NOSE_DEPTH * half_span } else { 0.0 };
+            let (x, y) = (x - centre.0, y - centre.1);
+            let (y, z) = (y * cp + z * sp, z * cp - y * sp);
+            let x = x * cy + z * sy;
+            out[i] = (x * cr - y * sr + 200.0, x * sr + y * cr + 150.0);
+        }
+        out
+    }
+
+    fn assert_pose(pose: HeadPose, yaw: f32, pitch: f32, roll: f32, tolerance: f32) {
+        assert!(
+            (pose.yaw - yaw).abs() <= tolerance
+                && (pose.pitch - pitch).abs() <= tolerance
+                && (pose.roll - roll).abs() <= tolerance,
+            "{pose:?} vs yaw={yaw} pitch={pitch} roll={roll}"
+        );
+    }
+
+    #[test]
+    fn reference_landmarks_are_frontal() {
+        let pose = estimate_pose(&REFERENCE_LANDMARKS_112).unwrap();
+        assert_pose(pose, 0.0, 0.0, -0.3, 0.1);
+    }
+
+    #[test]
+    fn recovers_single_axis_rotations() {
+        for angle in [-40.0, -15.0, 10.0, 35.0] {
+            assert_pose(
+                estimate_pose(&project(angle, 0.0, 0.0)).unwrap(),
+                angle,
+                0.0,
+                -0.3,
+                0.5,
+            );
+            assert_pose(
+                estimate_pose(&project(0.0, angle, 0.0)).unwrap(),
+                0.0,
+                angle,
+                -0.3,
+                0.5,
+            );
+            let pose = estimate_pose(&project(0.0, 0.0, angle)).unwrap();
+            assert_pose(pose, 0.0, 0.0, angle - 0.3, 0.5);
+        }
+    }
+
+    #[test]
+    fn combined_rotation_is_approximate() {
+        let pose = estimate_pose(&project(25.0, -15.0, 10.0)).unwrap();
+        assert_pose(pose, 25.0, -15.0, 10.0 - 0.3, 4.0);
+    }
+
+    #[test]
+    fn degenerate_landmarks_have_no_pose() {
+        assert!(estimate_pose(&[(10.0, 10.0); 5]).is_none());
+        let mut upside_down = REFERENCE_LANDMARKS_112;
+        for p in &mut upside_down[3..] {
+            p.1 = 20.0;
+        }
+        assert!(estimate_pose(&upside_down).is_none());
+    }
+}
@@ -4,6 +4,8 @@
 //! using the w600k_r50 ArcFace model.
 
 use crate::alignment;
+use crate::error::{ErrorKind, VisageError};
+use crate::inference::InferenceOptions;
 use crate::types::{BoundingBox, Embedding};
 use ndarray::Array4;
 use ort::session::Session;
@@ -30,6 +32,34 @@ pub enum RecognizerError {
     Ort(#[from] ort::Error),
 }
 
+impl RecognizerError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            RecognizerError::ModelNotFound(_) => ErrorKind::Model,
+            RecognizerError::InferenceFailed(_) | RecognizerError::Ort(_) => ErrorKind::Inference,
+            RecognizerError::NoLandmarks => ErrorKind::Internal,
+        }
+    }
+}
+
+impl From<RecognizerError> for VisageError {
+    fn from(e: RecognizerError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// An embedding together with the crops it came from; see
+/// [`FaceRecognizer::extract_with_crops`].
+#[derive(Debug, Clone)]
+pub struct FaceCrops {
+    pub embedding: Embedding,
+    /// The aligned 112×112 grayscale crop the network saw.
+    pub aligned: Vec<u8>,
+    /// The margin-expanded display crop, if one was requested.
+    pub display: Option<Vec<u8>>,
+}
+
 /// ArcFace-based face recognizer.
 pub struct FaceRecognizer {
     session: Session,
@@ -38,18 +68,50 @@ pub struct FaceRecognizer {
 impl FaceRecognizer {
     /// Load the ArcFace ONNX model from the given path.
     pub fn load(model_path: &str) -> Result<Self, RecognizerError> {
+        Self::load_with(model_path, InferenceOptions::default())
+    }
+
+    /// [`load`](Self::load) with explicit session settings, e.g.
+    /// [`InferenceOptions::reproducible`].
+    pub fn load_with(model_path: &str, options: InferenceOptions) -> Result<Self, RecognizerError> {
+        Self::load_session(model_path, options, None)
+    }
+
+    /// [`load_with`](Self::load_with), recording ONNX Runtime's per-operator
+    /// profile of every embedding until [`end_profiling`](Self::end_profiling)
+    /// writes it to `<prefix>_<timestamp>.json`.
+    pub fn load_profiled(
+        model_path: &str,
+        options: InferenceOptions,
+        prefix: &Path,
+    ) -> Result<Self, RecognizerError> {
+        Self::load_session(model_path, options, Some(prefix))
+    }
+
+    /// Stop profiling and write the profile; returns the file's path. Only
+    /// for a recognizer from [`load_profiled`](Self::load_profiled).
+    pub fn end_profiling(&mut self) -> Result<String, RecognizerError> {
+        Ok(self.session.end_profiling()?)
+    }
+
+    fn load_session(
+        model_path: &str,
+        options: InferenceOptions,
+        profile: Option<&Path>,
+    ) -> Result<Self, RecognizerError> {
         if !Path::new(model_path).exists() {
             return Err(RecognizerError::ModelNotFound(model_path.to_string()));
         }
 
-        let session = Session::builder()?
-            .with_intra_threads(2)?
+        let session = options
+            .session_builder(profile)?
             .commit_from_file(model_path)?;
 
         tracing::info!(
             path = model_path,
             inputs = ?session.inputs().iter().map(|i| (i.name(), i.dtype())).collect::<Vec<_>>(),
             outputs = ?session.outputs().iter().map(|o| o.name()).collect::<Vec<_>>(),
+            session = %options.summary(),
             "loaded ArcFace model"
         );
 
@@ -72,13 +134,40 @@ impl FaceRecognizer {
             .as_ref()
             .ok_or(RecognizerError::NoLandmarks)?;
 
-        // Align face to canonical 112x112 position
-        let aligned = alignment::align_face(frame, width, height, landmarks);
+        // Align to the canonical 112x112 position and normalize in one pass
+        let input = Self::preprocess(frame, width, height, landmarks);
+        self.embed(&input)
+    }
+
+    /// [`extract`](Self::extract), also returning the aligned 112×112 crop —
+    /// taken from the same warp, not a second alignment — and, with
+    /// `display`, a margin-expanded crop for thumbnails or debug dumps.
+    pub fn extract_with_crops(
+        &mut self,
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        face: &BoundingBox,
+        display: Option<alignment::DisplayCrop>,
+    ) -> Result<FaceCrops, RecognizerError> {
+        let landmarks = face
+            .landmarks
+            .as_ref()
+            .ok_or(RecognizerError::NoLandmarks)?;
 
-        // Preprocess aligned crop
-        let input = Self::preprocess(&aligned);
+        let (input, aligned) = Self::preprocess_with_crop(frame, width, height, landmarks);
+        let embedding = self.embed(&input)?;
+        let display =
+            display.map(|crop| alignment::display_crop(frame, width, height, landmarks, crop));
+        Ok(FaceCrops {
+            embedding,
+            aligned,
+            display,
+        })
+    }
 
-        // Run inference
+    /// Run the network on a preprocessed input and L2-normalize the result.
+    fn embed(&mut self, input: &Array4<f32>) -> Result<Embedding, RecognizerError> {
         let outputs = self
             .session
             .run(ort::inputs![TensorRef::from_array_view(input.view())?])?;
@@ -110,35 +199,71 @@ impl FaceRecognizer {
         })
     }
 
-    /// Preprocess a 112x112 grayscale aligned face crop into a NCHW float tensor.
-    fn preprocess(aligned_face: &[u8]) -> Array4<f32> {
+    /// Align the face and write it, normalized, straight into a NCHW float
+    /// tensor — no intermediate crop. Grayscale is replicated to all three
+    /// channels: [R=Y, G=Y, B=Y].
+    fn preprocess(
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        landmarks: &[(f32, f32); 5],
+    ) -> Array4<f32> {
         let size = ARCFACE_INPUT_SIZE;
         let mut tensor = Array4::<f32>::zeros((1, 3, size, size));
-
-        for y in 0..size {
-            for x in 0..size {
-                let pixel = aligned_face.get(y * size + x).copied().unwrap_or(0) as f32;
-
-                let normalized = (pixel - ARCFACE_MEAN) / ARCFACE_STD;
-                // Grayscale → 3-channel: replicate Y → [R=Y, G=Y, B=Y]
-                tensor[[0, 0, y, x]] = normalized;
-                tensor[[0, 1, y, x]] = normalized;
-                tensor[[0, 2, y, x]] = normalized;
-            }
-        }
-
+        let planes = tensor
+            .as_slice_mut()
+            .expect("freshly allocated tensor is contiguous");
+        alignment::align_face_normalized(
+            frame,
+            width,
+            height,
+            landmarks,
+            ARCFACE_MEAN,
+            ARCFACE_STD,
+            planes,
+        );
         tensor
     }
+
+    /// [`preprocess`](Self::preprocess), also returning the aligned `u8` crop.
+    fn preprocess_with_crop(
+        frame: &[u8],
+        width: u32,
+        height: u32,
+        landmarks: &[(f32, f32); 5],
+    ) -> (Array4<f32>, Vec<u8>) {
+        let size = ARCFACE_INPUT_SIZE;
+        let mut tensor = Array4::<f32>::zeros((1, 3, size, size));
+        let planes = tensor
+            .as_slice_mut()
+            .expect("freshly allocated tensor is contiguous");
+        let crop = alignment::align_face_normalized_with_crop(
+            frame,
+            width,
+            height,
+            landmarks,
+            ARCFACE_MEAN,
+            ARCFACE_STD,
+            planes,
+        );
+        (tensor, crop)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// A uniform frame with landmarks at the reference positions: the
+    /// alignment is the identity and stays inside the frame.
+    fn uniform_input(value: u8) -> Array4<f32> {
+        let frame = vec![value; 200 * 200];
+        FaceRecognizer::preprocess(&frame, 200, 200, &alignment::REFERENCE_LANDMARKS_112)
+    }
+
     #[test]
     fn test_preprocess_output_shape() {
-        let aligned = vec![128u8; ARCFACE_INPUT_SIZE * ARCFACE_INPUT_SIZE];
-        let tensor = FaceRecognizer::preprocess(&aligned);
+        let tensor = uniform_input(128);
         assert_eq!(
             tensor.shape(),
             &[1, 3, ARCFACE_INPUT_SIZE, ARCFACE_INPUT_SIZE]
@@ -148,10 +273,9 @@ mod tests {
     #[test]
     fn test_preprocess_normalization() {
         // Pixel value 127.5 should normalize to 0.0
-        let aligned = vec![128u8; ARCFACE_INPUT_SIZE * ARCFACE_INPUT_SIZE];
-        let tensor = FaceRecognizer::preprocess(&aligned);
+        let tensor = uniform_input(128);
         // 128 - 127.5 = 0.5, / 127.5 ≈ 0.00392
-        let val = tensor[[0, 0, 0, 0]];
+        let val = tensor[[0, 0, 56, 56]];
         let expected = (128.0 - ARCFACE_MEAN) / ARCFACE_STD;
         assert!(
             (val - expected).abs() < 1e-6,
@@ -162,8 +286,7 @@ mod tests {
     #[test]
     fn test_preprocess_channels_identical() {
         // All 3 channels should be identical for grayscale input
-        let aligned = vec![100u8; ARCFACE_INPUT_SIZE * ARCFACE_INPUT_SIZE];
-        let tensor = FaceRecognizer::preprocess(&aligned);
+        let tensor = uniform_input(100);
         for y in 0..ARCFACE_INPUT_SIZE {
             for x in 0..ARCFACE_INPUT_SIZE {
                 let r = tensor[[0, 0, y, x]];
@@ -175,6 +298,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_preprocess_with_crop_matches_preprocess() {
+        let frame: Vec<u8> = (0..200 * 200).map(|i| (i % 253) as u8).collect();
+        let landmarks = alignment::REFERENCE_LANDMARKS_112;
+        let (tensor, crop) = FaceRecognizer::preprocess_with_crop(&frame, 200, 200, &landmarks);
+        assert_eq!(
+            tensor,
+            FaceRecognizer::preprocess(&frame, 200, 200, &landmarks)
+        );
+        assert_eq!(crop, alignment::align_face(&frame, 200, 200, &landmarks));
+    }
+
     #[test]
     fn test_extract_requires_landmarks() {
         // Cannot test full extract without a loaded model, but we can verify
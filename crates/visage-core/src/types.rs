@@ -12,6 +12,34 @@ pub struct BoundingBox {
     pub landmarks: Option<[(f32, f32); 5]>,
 }
 
+impl BoundingBox {
+    /// Head pose estimated from the landmarks; `None` without landmarks or
+    /// when they are degenerate. See [`crate::pose::estimate_pose`].
+    pub fn pose(&self) -> Option<HeadPose> {
+        self.landmarks.as_ref().and_then(crate::pose::estimate_pose)
+    }
+}
+
+/// Head orientation in degrees, relative to facing the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HeadPose {
+    /// Left–right turn; positive when the nose points towards the image's
+    /// right edge.
+    pub yaw: f32,
+    /// Up–down tilt; positive when the face looks down.
+    pub pitch: f32,
+    /// In-plane tilt of the eye line; positive when the image-right eye is lower.
+    pub roll: f32,
+}
+
+impl HeadPose {
+    /// Whether both yaw and pitch are within `max_degrees` of frontal. Roll
+    /// is ignored: alignment undoes it.
+    pub fn is_frontal(&self, max_degrees: f32) -> bool {
+        self.yaw.abs() <= max_degrees && self.pitch.abs() <= max_degrees
+    }
+}
+
 /// Face embedding vector (typically 512-dimensional for ArcFace).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Embedding {
@@ -52,6 +80,42 @@ impl Embedding {
         self.similarity(other)
     }
 
+    /// Weighted mean of several embeddings, L2-normalised — the enrollment
+    /// template. Falls back to an unweighted mean when every weight is zero.
+    /// Returns `None` for an empty slice.
+    pub fn weighted_mean(items: &[(Embedding, f32)]) -> Option<Embedding> {
+        let (first, _) = items.first()?;
+        let total_weight: f32 = items.iter().map(|(_, w)| *w).sum();
+        let (denom, use_weighted) = if total_weight > 0.0 {
+            (total_weight, true)
+        } else {
+            (items.len() as f32, false)
+        };
+
+        let mut avg = vec![0.0f32; first.values.len()];
+        for (emb, w) in items {
+            let w = if use_weighted { *w } else { 1.0 };
+            for (a, v) in avg.iter_mut().zip(emb.values.iter()) {
+                *a += v * w;
+            }
+        }
+        for v in &mut avg {
+            *v /= denom;
+        }
+
+        let norm: f32 = avg.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in &mut avg {
+                *v /= norm;
+            }
+        }
+
+        Some(Embedding {
+            values: avg,
+            model_version: first.model_version.clone(),
+        })
+    }
+
     /// Compute Euclidean distance between two embeddings.
     pub fn euclidean_distance(&self, other: &Embedding) -> f32 {
         self.values
@@ -71,18 +135,32 @@ pub struct FaceModel {
     pub label: String,
     pub embedding: Embedding,
     pub created_at: String,
+    /// Namespace the model belongs to (e.g. one kiosk location). Empty for the
+    /// default tenant; users in different tenants are unrelated.
+    #[serde(default)]
+    pub tenant: String,
+    /// Added to the match threshold for this model alone: positive demands a
+    /// closer match (a sunglasses template), negative accepts a looser one.
+    /// Matchers rank models by `similarity - threshold_offset`.
+    #[serde(default)]
+    pub threshold_offset: f32,
 }
 
 /// Result of matching a probe embedding against a gallery.
 #[derive(Debug, Clone)]
 pub struct MatchResult {
     pub matched: bool,
-    /// Cosine similarity of the best match [-1, 1].
+    /// Cosine similarity of the best match [-1, 1]. With per-model offsets,
+    /// "best" is the model whose similarity clears its own threshold by the
+    /// most, not necessarily the highest raw similarity.
     pub similarity: f32,
     /// ID of the matched model (if any).
     pub model_id: Option<String>,
     /// Label of the matched model (if any).
     pub model_label: Option<String>,
+    /// ID of the best-scoring model whether or not it matched; `None` only
+    /// for an empty gallery. Lets a caller attribute a near miss.
+    pub closest_model_id: Option<String>,
 }
 
 /// Strategy for comparing a probe embedding against a gallery of enrolled faces.
@@ -93,29 +171,34 @@ pub trait Matcher {
 /// Cosine similarity matcher with constant-time gallery traversal.
 ///
 /// Always iterates ALL gallery entries to prevent timing side-channels
-/// that could leak gallery size or match position.
+/// that could leak gallery size or match position. A model matches when its
+/// similarity reaches `threshold + threshold_offset`.
 pub struct CosineMatcher;
 
 impl Matcher for CosineMatcher {
     fn compare(&self, probe: &Embedding, gallery: &[FaceModel], threshold: f32) -> MatchResult {
+        let mut best_score = f32::NEG_INFINITY;
         let mut best_sim = f32::NEG_INFINITY;
         let mut best_idx: Option<usize> = None;
 
         // Constant-time: always iterate every entry, no early exit.
         for (i, model) in gallery.iter().enumerate() {
             let sim = probe.similarity(&model.embedding);
-            if sim > best_sim {
+            let score = sim - model.threshold_offset;
+            if score > best_score {
+                best_score = score;
                 best_sim = sim;
                 best_idx = Some(i);
             }
         }
 
         match best_idx {
-            Some(idx) if best_sim >= threshold => MatchResult {
+            Some(idx) if best_score >= threshold => MatchResult {
                 matched: true,
                 similarity: best_sim,
                 model_id: Some(gallery[idx].id.clone()),
                 model_label: Some(gallery[idx].label.clone()),
+                closest_model_id: Some(gallery[idx].id.clone()),
             },
             _ => MatchResult {
                 matched: false,
@@ -126,6 +209,76 @@ impl Matcher for CosineMatcher {
                 },
                 model_id: None,
                 model_label: None,
+                closest_model_id: best_idx.map(|idx| gallery[idx].id.clone()),
+            },
+        }
+    }
+}
+
+/// Open-set (1:N) matcher for identifying one of several enrolled users.
+///
+/// On top of the threshold, the best match must beat the best similarity of
+/// any *other* user by at least `margin`. A probe that sits between two
+/// household members — or resembles everyone a little — is rejected instead of
+/// being assigned to whichever account happens to score highest.
+/// Like [`CosineMatcher`], every gallery entry is always compared, and the
+/// threshold and margin apply to `similarity - threshold_offset`.
+pub struct OpenSetMatcher {
+    pub margin: f32,
+}
+
+impl Matcher for OpenSetMatcher {
+    fn compare(&self, probe: &Embedding, gallery: &[FaceModel], threshold: f32) -> MatchResult {
+        let sims: Vec<f32> = gallery
+            .iter()
+            .map(|model| probe.similarity(&model.embedding))
+            .collect();
+        let scores: Vec<f32> = gallery
+            .iter()
+            .zip(&sims)
+            .map(|(model, &sim)| sim - model.threshold_offset)
+            .collect();
+
+        let mut best_score = f32::NEG_INFINITY;
+        let mut best_idx: Option<usize> = None;
+        for (i, &score) in scores.iter().enumerate() {
+            if score > best_score {
+                best_score = score;
+                best_idx = Some(i);
+            }
+        }
+        let best_sim = best_idx.map_or(f32::NEG_INFINITY, |idx| sims[idx]);
+
+        // Best score of any model owned by a different user.
+        let mut runner_up = f32::NEG_INFINITY;
+        if let Some(idx) = best_idx {
+            for (model, &score) in gallery.iter().zip(&scores) {
+                if model.user != gallery[idx].user && score > runner_up {
+                    runner_up = score;
+                }
+            }
+        }
+
+        match best_idx {
+            Some(idx) if best_score >= threshold && best_score - runner_up >= self.margin => {
+                MatchResult {
+                    matched: true,
+                    similarity: best_sim,
+                    model_id: Some(gallery[idx].id.clone()),
+                    model_label: Some(gallery[idx].label.clone()),
+                    closest_model_id: Some(gallery[idx].id.clone()),
+                }
+            }
+            _ => MatchResult {
+                matched: false,
+                similarity: if best_sim == f32::NEG_INFINITY {
+                    0.0
+                } else {
+                    best_sim
+                },
+                model_id: None,
+                model_label: None,
+                closest_model_id: best_idx.map(|idx| gallery[idx].id.clone()),
             },
         }
     }
@@ -148,6 +301,21 @@ mod tests {
         assert!((a.similarity(&b) - 1.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_weighted_mean_is_normalised_and_weighted() {
+        let e = |values: Vec<f32>| Embedding {
+            values,
+            model_version: Some("w600k_r50".into()),
+        };
+        let mean = Embedding::weighted_mean(&[(e(vec![1.0, 0.0]), 3.0), (e(vec![0.0, 1.0]), 1.0)])
+            .unwrap();
+        let norm: f32 = mean.values.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+        assert!(mean.values[0] > mean.values[1]);
+        assert_eq!(mean.model_version.as_deref(), Some("w600k_r50"));
+        assert!(Embedding::weighted_mean(&[]).is_none());
+    }
+
     #[test]
     fn test_cosine_similarity_orthogonal() {
         let a = Embedding {
@@ -204,6 +372,8 @@ mod tests {
                     model_version: None,
                 },
                 created_at: "".into(),
+                tenant: String::new(),
+                threshold_offset: 0.0,
             },
             FaceModel {
                 id: "2".into(),
@@ -214,6 +384,8 @@ mod tests {
                     model_version: None,
                 },
                 created_at: "".into(),
+                tenant: String::new(),
+                threshold_offset: 0.0,
             },
             FaceModel {
                 id: "3".into(),
@@ -224,6 +396,8 @@ mod tests {
                     model_version: None,
                 },
                 created_at: "".into(),
+                tenant: String::new(),
+                threshold_offset: 0.0,
             },
         ];
 
@@ -249,11 +423,40 @@ mod tests {
                 model_version: None,
             },
             created_at: "".into(),
+            tenant: String::new(),
+            threshold_offset: 0.0,
         }];
 
         let result = CosineMatcher.compare(&probe, &gallery, 0.5);
         assert!(!result.matched);
         assert!(result.similarity.abs() < 1e-6);
+        assert!(result.model_id.is_none());
+        assert_eq!(result.closest_model_id.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_threshold_offset_applies_per_model() {
+        let probe = Embedding {
+            values: vec![1.0, 0.0],
+            model_version: None,
+        };
+        // Similarity 0.6 to each template.
+        let mut sunglasses = model("sun", "u", vec![0.6, 0.8]);
+        sunglasses.threshold_offset = 0.2;
+        let result = CosineMatcher.compare(&probe, &[sunglasses.clone()], 0.5);
+        assert!(!result.matched, "0.6 < 0.5 + 0.2");
+        assert!((result.similarity - 0.6).abs() < 1e-6);
+        assert_eq!(result.closest_model_id.as_deref(), Some("sun"));
+
+        // The plain template clears its threshold by more, so it is the match.
+        let plain = model("plain", "u", vec![0.6, 0.8]);
+        let result = CosineMatcher.compare(&probe, &[sunglasses, plain], 0.5);
+        assert!(result.matched);
+        assert_eq!(result.model_id.as_deref(), Some("plain"));
+
+        let mut loose = model("loose", "u", vec![0.4, 0.9165]);
+        loose.threshold_offset = -0.15;
+        assert!(CosineMatcher.compare(&probe, &[loose], 0.5).matched);
     }
 
     #[test]
@@ -264,6 +467,62 @@ mod tests {
         };
         let result = CosineMatcher.compare(&probe, &[], 0.5);
         assert!(!result.matched);
+        assert!(result.closest_model_id.is_none());
         assert_eq!(result.similarity, 0.0);
     }
+
+    fn model(id: &str, user: &str, values: Vec<f32>) -> FaceModel {
+        FaceModel {
+            id: id.into(),
+            user: user.into(),
+            label: "default".into(),
+            embedding: Embedding {
+                values,
+                model_version: None,
+            },
+            created_at: "".into(),
+            tenant: String::new(),
+            threshold_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_open_set_requires_margin_over_other_users() {
+        let probe = Embedding {
+            values: vec![1.0, 0.0],
+            model_version: None,
+        };
+        // alice ≈ 0.995, bob ≈ 0.98 — both above threshold but too close together.
+        let gallery = vec![
+            model("a", "alice", vec![1.0, 0.1]),
+            model("b", "bob", vec![1.0, 0.2]),
+        ];
+        let matcher = OpenSetMatcher { margin: 0.05 };
+        assert!(!matcher.compare(&probe, &gallery, 0.5).matched);
+
+        // Clear winner → match.
+        let gallery = vec![
+            model("a", "alice", vec![1.0, 0.1]),
+            model("b", "bob", vec![0.0, 1.0]),
+        ];
+        let result = matcher.compare(&probe, &gallery, 0.5);
+        assert!(result.matched);
+        assert_eq!(result.model_id.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_open_set_ignores_same_user_runner_up() {
+        let probe = Embedding {
+            values: vec![1.0, 0.0],
+            model_version: None,
+        };
+        // Two near-identical models of the same user must not block each other.
+        let gallery = vec![
+            model("a1", "alice", vec![1.0, 0.1]),
+            model("a2", "alice", vec![1.0, 0.12]),
+        ];
+        let result = OpenSetMatcher { margin: 0.05 }.compare(&probe, &gallery, 0.5);
+        assert!(result.matched);
+        assert_eq!(result.model_id.as_deref(), Some("a1"));
+    }
 }
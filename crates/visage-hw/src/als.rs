@@ -0,0 +1,100 @@
+//! Ambient light sensor — how dark the room is before the camera starts.
+//!
+//! Most laptops with an IR camera also have an ambient light sensor, exposed
+//! by the kernel's IIO subsystem under `/sys/bus/iio/devices/iio:deviceN`.
+//! Reading it costs a sysfs read, so the capture settings for a dark room can
+//! be chosen before the stream starts instead of after the first frame.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const IIO_DEVICES: &str = "/sys/bus/iio/devices";
+
+/// Channel name prefixes of an illuminance reading, most common first.
+const CHANNELS: [&str; 2] = ["in_illuminance", "in_illuminance0"];
+
+/// An IIO illuminance sensor.
+#[derive(Debug, Clone)]
+pub struct AmbientLight {
+    dir: PathBuf,
+    channel: &'static str,
+    name: String,
+}
+
+impl AmbientLight {
+    /// The first IIO device with an illuminance channel, if any.
+    pub fn discover() -> Option<Self> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(IIO_DEVICES)
+            .ok()?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect();
+        dirs.sort();
+        dirs.into_iter().find_map(|dir| Self::at(&dir))
+    }
+
+    /// The sensor at IIO device directory `dir`, if it measures illuminance.
+    pub fn at(dir: &Path) -> Option<Self> {
+        let channel = CHANNELS.into_iter().find(|channel| {
+            dir.join(format!("{channel}_input")).exists()
+                || dir.join(format!("{channel}_raw")).exists()
+        })?;
+        let name = fs::read_to_string(dir.join("name"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| dir.display().to_string());
+        Some(Self {
+            dir: dir.to_path_buf(),
+            channel,
+            name,
+        })
+    }
+
+    /// Driver name of the sensor (e.g. `als`, `acpi-als`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Current illuminance in lux; `None` when the sensor cannot be read.
+    pub fn lux(&self) -> Option<f32> {
+        let read = |suffix: &str| -> Option<f32> {
+            fs::read_to_string(self.dir.join(format!("{}_{suffix}", self.channel)))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+        lux_from(read("input"), read("raw"), read("scale"), read("offset"))
+    }
+}
+
+/// Lux from the IIO attributes: the processed `input` value when the driver
+/// provides one, else `(raw + offset) * scale`.
+fn lux_from(
+    input: Option<f32>,
+    raw: Option<f32>,
+    scale: Option<f32>,
+    offset: Option<f32>,
+) -> Option<f32> {
+    let lux = match input {
+        Some(input) => input,
+        None => (raw? + offset.unwrap_or(0.0)) * scale.unwrap_or(1.0),
+    };
+    (lux.is_finite() && lux >= 0.0).then_some(lux)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lux_from_iio_attributes() {
+        assert_eq!(lux_from(Some(12.5), Some(900.0), None, None), Some(12.5));
+        assert_eq!(lux_from(None, Some(100.0), Some(0.25), None), Some(25.0));
+        assert_eq!(
+            lux_from(None, Some(100.0), Some(0.5), Some(-20.0)),
+            Some(40.0)
+        );
+        assert_eq!(lux_from(None, Some(7.0), None, None), Some(7.0));
+        assert_eq!(lux_from(None, None, Some(1.0), None), None);
+        assert_eq!(lux_from(Some(-1.0), None, None, None), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Per-device calibration — capture settings tuned for one camera, kept
+//! across restarts.
+//!
+//! The quirk database describes a camera model; a calibration describes one
+//! unit as installed: the exposure that suits it, a brighter or dimmer
+//! emitter payload, how the sensor is mounted, and whether CLAHE helps. It is
+//! saved by `visage calibrate` as TOML under `/var/lib/visage/calibration`,
+//! named by USB `VID-PID` and, when the device reports one, its serial
+//! number, and applied whenever the daemon opens the camera.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Default directory for calibration files.
+pub const DEFAULT_CALIBRATION_DIR: &str = "/var/lib/visage/calibration";
+
+/// Tuned capture settings for one camera.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Manual exposure (V4L2 `exposure_absolute`, 100 µs units) for normal
+    /// captures; `None` keeps auto exposure. A night profile's exposure wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exposure: Option<i64>,
+    /// Emitter payload used instead of the quirk's `control_bytes`, e.g. a
+    /// different intensity byte. Must have the same length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emitter_bytes: Option<Vec<u8>>,
+    /// Clockwise rotation applied to every frame: 0, 90, 180 or 270 degrees.
+    #[serde(default)]
+    pub rotation: u16,
+    /// Whether CLAHE contrast enhancement runs on captured frames.
+    #[serde(default = "default_clahe")]
+    pub clahe: bool,
+}
+
+fn default_clahe() -> bool {
+    true
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            exposure: None,
+            emitter_bytes: None,
+            rotation: 0,
+            clahe: true,
+        }
+    }
+}
+
+impl Calibration {
+    /// Check the values a file or the command line can get wrong.
+    pub fn validate(&self) -> Result<(), String> {
+        if !matches!(self.rotation, 0 | 90 | 180 | 270) {
+            return Err(format!(
+                "rotation must be 0, 90, 180 or 270, not {}",
+                self.rotation
+            ));
+        }
+        if self.exposure.is_some_and(|e| e <= 0) {
+            return Err("exposure must be positive".into());
+        }
+        if self.emitter_bytes.as_ref().is_some_and(|b| b.is_empty()) {
+            return Err("emitter_bytes must not be empty".into());
+        }
+        Ok(())
+    }
+
+    /// The calibration saved for `device_path` in `dir`: the file for this
+    /// unit's serial number, else the one for its model. `None` when there is
+    /// none, or it cannot be read or is invalid (logged).
+    pub fn load(dir: &Path, device_path: &str) -> Option<(Self, PathBuf)> {
+        let path = candidate_files(dir, device_path)
+            .into_iter()
+            .find(|path| path.exists())?;
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<Self>(&text).map_err(|e| e.to_string()))
+            .and_then(|cal| cal.validate().map(|()| cal));
+        match parsed {
+            Ok(cal) => Some((cal, path)),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "ignoring calibration file");
+                None
+            }
+        }
+    }
+
+    /// Save as the calibration for `device_path` (its serial-specific file
+    /// when the device has a serial number). Returns the path written.
+    pub fn save(&self, dir: &Path, device_path: &str) -> io::Result<PathBuf> {
+        self.validate()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let path = candidate_files(dir, device_path)
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{device_path} has no USB VID:PID to name a calibration by"),
+                )
+            })?;
+        fs::create_dir_all(dir)?;
+        let text = toml::to_string(self).map_err(|e| io::Error::other(e.to_string()))?;
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    /// Delete every calibration file for `device_path`; `true` if one existed.
+    pub fn remove(dir: &Path, device_path: &str) -> io::Result<bool> {
+        let mut removed = false;
+        for path in candidate_files(dir, device_path) {
+            match fs::remove_file(&path) {
+                Ok(()) => removed = true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Calibration files for a device, most specific first.
+fn candidate_files(dir: &Path, device_path: &str) -> Vec<PathBuf> {
+    let Some((vid, pid)) = crate::quirks::get_usb_ids(device_path) else {
+        return Vec::new();
+    };
+    file_names(vid, pid, usb_serial(device_path).as_deref())
+        .into_iter()
+        .map(|name| dir.join(name))
+        .collect()
+}
+
+/// `vvvv-pppp-SERIAL.toml` (when there is a usable serial) and `vvvv-pppp.toml`.
+fn file_names(vid: u16, pid: u16, serial: Option<&str>) -> Vec<String> {
+    let model = format!("{vid:04x}-{pid:04x}");
+    let serial: Option<String> = serial.map(|s| {
+        s.trim()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect()
+    });
+    let mut names = Vec::with_capacity(2);
+    if let Some(serial) = serial.filter(|s| !s.is_empty()) {
+        names.push(format!("{model}-{serial}.toml"));
+    }
+    names.push(format!("{model}.toml"));
+    names
+}
+
+/// USB serial number of a `/dev/videoN` device from sysfs, if it reports one.
+fn usb_serial(device_path: &str) -> Option<String> {
+    let dev_name = Path::new(device_path).file_name()?.to_str()?;
+    let interface_dir =
+        fs::canonicalize(format!("/sys/class/video4linux/{dev_name}/device")).ok()?;
+    let serial = fs::read_to_string(interface_dir.parent()?.join("serial")).ok()?;
+    Some(serial.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// Rotate a grayscale `width`×`height` image clockwise by `degrees` (0, 90,
+/// 180 or 270). Returns the pixels and the new width and height.
+pub fn rotate(gray: &[u8], width: u32, height: u32, degrees: u16) -> (Vec<u8>, u32, u32) {
+    let (w, h) = (width as usize, height as usize);
+    if gray.len() < w * h {
+        return (gray.to_vec(), width, height);
+    }
+    match degrees {
+        90 => {
+            let mut out = vec![0u8; w * h];
+            for y in 0..h {
+                for x in 0..w {
+                    out[x * h + (h - 1 - y)] = gray[y * w + x];
+                }
+            }
+            (out, height, width)
+        }
+        180 => {
+            let mut out = gray[..w * h].to_vec();
+            out.reverse();
+            (out, width, height)
+        }
+        270 => {
+            let mut out = vec![0u8; w * h];
+            for y in 0..h {
+                for x in 0..w {
+                    out[(w - 1 - x) * h + y] = gray[y * w + x];
+                }
+            }
+            (out, height, width)
+        }
+        _ => (gray.to_vec(), width, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibration_round_trips_and_validates() {
+        let cal = Calibration {
+            exposure: Some(250),
+            emitter_bytes: Some(vec![1, 3, 3, 0]),
+            rotation: 180,
+            clahe: false,
+        };
+        let text = toml::to_string(&cal).unwrap();
+        assert_eq!(toml::from_str::<Calibration>(&text).unwrap(), cal);
+        assert_eq!(
+            toml::from_str::<Calibration>("").unwrap(),
+            Calibration::default()
+        );
+
+        assert!(cal.validate().is_ok());
+        for bad in [
+            Calibration {
+                rotation: 45,
+                ..Calibration::default()
+            },
+            Calibration {
+                exposure: Some(0),
+                ..Calibration::default()
+            },
+            Calibration {
+                emitter_bytes: Some(Vec::new()),
+                ..Calibration::default()
+            },
+        ] {
+            assert!(bad.validate().is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn test_file_names_prefer_the_serial() {
+        assert_eq!(
+            file_names(0x04f2, 0xb6d9, Some(" SN 01/a\n")),
+            ["04f2-b6d9-SN01a.toml", "04f2-b6d9.toml"]
+        );
+        assert_eq!(file_names(0x04f2, 0xb6d9, Some("/")), ["04f2-b6d9.toml"]);
+        assert_eq!(file_names(0x04f2, 0xb6d9, None), ["04f2-b6d9.toml"]);
+    }
+
+    #[test]
+    fn test_rotate() {
+        // 3×2:  1 2 3
+        //       4 5 6
+        let gray = [1, 2, 3, 4, 5, 6];
+        assert_eq!(rotate(&gray, 3, 2, 90), (vec![4, 1, 5, 2, 6, 3], 2, 3));
+        assert_eq!(rotate(&gray, 3, 2, 180), (vec![6, 5, 4, 3, 2, 1], 3, 2));
+        assert_eq!(rotate(&gray, 3, 2, 270), (vec![3, 6, 2, 5, 1, 4], 2, 3));
+        assert_eq!(rotate(&gray, 3, 2, 0), (gray.to_vec(), 3, 2));
+    }
+}
@@ -1,6 +1,7 @@
 //! V4L2 camera capture via the `v4l` crate.
 
-use crate::frame::{self, Frame};
+use crate::calibration::{self, Calibration};
+use crate::frame::{self, Frame, FrameReject, FrameRing, QualityGate};
 use std::path::Path;
 use thiserror::Error;
 use v4l::buffer::Type as BufType;
@@ -8,6 +9,7 @@ use v4l::io::traits::CaptureStream;
 use v4l::prelude::*;
 use v4l::video::Capture;
 use v4l::FourCC;
+use visage_core::error::{ErrorKind, VisageError};
 
 #[derive(Error, Debug)]
 pub enum CameraError {
@@ -21,8 +23,47 @@ pub enum CameraError {
     FormatNegotiationFailed(String),
     #[error("streaming not supported")]
     StreamingNotSupported,
+    #[error("refusing virtual camera {0}")]
+    VirtualDevice(String),
+    /// The device went away (`ENODEV`): unplugged, or its USB link reset.
+    #[error("camera disconnected: {0}")]
+    Disconnected(String),
 }
 
+impl CameraError {
+    /// A failed V4L2 call during `what`: [`CameraError::Disconnected`] when
+    /// the device is gone, [`CameraError::CaptureFailed`] otherwise.
+    pub fn from_io(what: &str, err: std::io::Error) -> Self {
+        if err.raw_os_error() == Some(ENODEV) {
+            CameraError::Disconnected(format!("{what}: {err}"))
+        } else {
+            CameraError::CaptureFailed(format!("{what}: {err}"))
+        }
+    }
+
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CameraError::DeviceBusy => ErrorKind::CameraBusy,
+            CameraError::Disconnected(_) => ErrorKind::CameraDisconnected,
+            CameraError::VirtualDevice(_) => ErrorKind::PermissionDenied,
+            CameraError::DeviceNotFound(_)
+            | CameraError::CaptureFailed(_)
+            | CameraError::FormatNegotiationFailed(_)
+            | CameraError::StreamingNotSupported => ErrorKind::Camera,
+        }
+    }
+}
+
+impl From<CameraError> for VisageError {
+    fn from(e: CameraError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// `errno` of a V4L2 call on a device that has been unplugged.
+const ENODEV: i32 = 19;
+
 /// Info about a discovered V4L2 device.
 #[derive(Debug, Clone)]
 pub struct DeviceInfo {
@@ -43,6 +84,81 @@ pub enum PixelFormat {
     Y16,
 }
 
+/// V4L2 exposure controls (`linux/v4l2-controls.h`).
+const V4L2_CID_EXPOSURE_AUTO: u32 = 0x009a_0901;
+const V4L2_CID_EXPOSURE_ABSOLUTE: u32 = 0x009a_0902;
+const V4L2_EXPOSURE_MANUAL: i64 = 1;
+/// Auto exposure with a fixed iris — the UVC default.
+const V4L2_EXPOSURE_APERTURE_PRIORITY: i64 = 3;
+
+/// Capture settings that differ between a lit room and a dark one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureProfile {
+    /// CLAHE clip limit: a higher limit stretches contrast further, which a
+    /// dim, noisy frame needs and a well-lit one does not.
+    pub clahe_clip_limit: f32,
+    /// Manual exposure (V4L2 `exposure_absolute`, 100 µs units) for the
+    /// burst; `None` leaves the camera's auto exposure alone.
+    pub exposure: Option<i64>,
+    /// Usable frames to collect on top of the requested count.
+    pub extra_frames: usize,
+    /// Frames discarded at the start of the stream; `None` uses the camera's
+    /// [stream warmup](Camera::set_stream_warmup). A preset exposure leaves
+    /// less for auto exposure to settle.
+    pub warmup_frames: Option<usize>,
+}
+
+impl Default for CaptureProfile {
+    fn default() -> Self {
+        Self {
+            clahe_clip_limit: 0.02,
+            exposure: None,
+            extra_frames: 0,
+            warmup_frames: None,
+        }
+    }
+}
+
+/// A second [`CaptureProfile`] for dark rooms and when it takes over.
+///
+/// The caller can force it for a burst (a wall-clock schedule); otherwise it
+/// switches on mid-burst when the first frame after warmup is darker than
+/// `below_brightness`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NightMode {
+    pub profile: CaptureProfile,
+    /// Mean raw brightness (0–255) of the first frame under which the night
+    /// profile is used; `None` switches only when forced.
+    pub below_brightness: Option<f32>,
+}
+
+/// Per-capture counts of raw frames that were dropped before detection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Frames rejected as too dark.
+    pub dark: usize,
+    /// Frames rejected as saturated.
+    pub saturated: usize,
+    /// Frames rejected as blurry.
+    pub blurry: usize,
+    /// Frames rejected for motion / exposure change against the previous frame.
+    pub motion: usize,
+    /// The burst ran with the [`NightMode`] profile.
+    pub night: bool,
+    /// The night profile was picked mid-burst, after a dark first frame.
+    pub night_switched: bool,
+    /// Exposure (`exposure_absolute`) the sensor was at when a night burst
+    /// ended; `None` for day bursts or if the camera does not report it.
+    pub exposure: Option<i64>,
+}
+
+impl CaptureStats {
+    /// Total frames dropped for any reason.
+    pub fn rejected(&self) -> usize {
+        self.dark + self.saturated + self.blurry + self.motion
+    }
+}
+
 /// V4L2 camera device handle.
 pub struct Camera {
     device: Device,
@@ -50,8 +166,25 @@ pub struct Camera {
     pub height: u32,
     pub device_path: String,
     pub fourcc: FourCC,
+    /// V4L2 driver name (e.g. `uvcvideo`).
+    pub driver: String,
+    /// V4L2 bus info (e.g. `usb-0000:00:14.0-5`).
+    pub bus_info: String,
     /// Negotiated pixel format.
     pixel_format: PixelFormat,
+    /// Pre-detector frame quality checks applied by [`Camera::capture_frames`].
+    quality_gate: QualityGate,
+    /// Frames discarded at the start of every stream while AGC/AE settles.
+    stream_warmup: usize,
+    /// Settings for normal captures.
+    profile: CaptureProfile,
+    /// Dark-room settings, if configured.
+    night: Option<NightMode>,
+    /// Settings tuned for this unit; see [`Calibration`].
+    calibration: Calibration,
+    /// Exposure for night bursts whose profile leaves exposure to the
+    /// camera; see [`Camera::set_learned_exposure`].
+    learned_exposure: Option<i64>,
 }
 
 impl Camera {
@@ -127,10 +260,136 @@ impl Camera {
             height: negotiated.height,
             device_path: device_path.to_string(),
             fourcc,
+            driver: caps.driver.clone(),
+            bus_info: caps.bus.clone(),
             pixel_format,
+            quality_gate: QualityGate::default(),
+            stream_warmup: 0,
+            profile: CaptureProfile::default(),
+            night: None,
+            calibration: Calibration::default(),
+            learned_exposure: None,
         })
     }
 
+    /// Replace the frame quality gate used by [`Camera::capture_frames`].
+    pub fn set_quality_gate(&mut self, gate: QualityGate) {
+        self.quality_gate = gate;
+    }
+
+    /// Set the normal capture profile and, optionally, a night profile.
+    pub fn set_profiles(&mut self, day: CaptureProfile, night: Option<NightMode>) {
+        self.profile = day;
+        self.night = night;
+    }
+
+    /// Apply a per-device calibration: its exposure replaces auto exposure
+    /// for normal captures, and its rotation and CLAHE setting apply to every
+    /// frame.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Preset `exposure` for night bursts when the night profile has no fixed
+    /// exposure, instead of letting auto exposure hunt from a daylight value.
+    pub fn set_learned_exposure(&mut self, exposure: Option<i64>) {
+        self.learned_exposure = exposure;
+    }
+
+    /// The night profile with any learned exposure filled in.
+    fn night_profile(&self, night: &NightMode) -> CaptureProfile {
+        let mut profile = night.profile;
+        profile.exposure = profile.exposure.or(self.learned_exposure);
+        profile
+    }
+
+    /// The current `exposure_absolute`, if the camera reports it.
+    fn read_exposure(&self) -> Option<i64> {
+        match self.device.control(V4L2_CID_EXPOSURE_ABSOLUTE).ok()?.value {
+            v4l::control::Value::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The calibration in effect.
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    /// Width and height of the frames this camera returns, after rotation.
+    pub fn frame_size(&self) -> (u32, u32) {
+        match self.calibration.rotation {
+            90 | 270 => (self.height, self.width),
+            _ => (self.width, self.height),
+        }
+    }
+
+    /// Rotate a processed frame as calibrated.
+    fn oriented(&self, gray: Vec<u8>) -> (Vec<u8>, u32, u32) {
+        if self.calibration.rotation == 0 {
+            return (gray, self.width, self.height);
+        }
+        calibration::rotate(&gray, self.width, self.height, self.calibration.rotation)
+    }
+
+    /// Switch exposure for a profile: manual at `exposure`, or back to auto.
+    /// Best-effort — many IR cameras expose no exposure controls.
+    fn apply_exposure(&self, exposure: Option<i64>) {
+        let controls = match exposure {
+            Some(value) => vec![
+                v4l::control::Control {
+                    id: V4L2_CID_EXPOSURE_AUTO,
+                    value: v4l::control::Value::Integer(V4L2_EXPOSURE_MANUAL),
+                },
+                v4l::control::Control {
+                    id: V4L2_CID_EXPOSURE_ABSOLUTE,
+                    value: v4l::control::Value::Integer(value),
+                },
+            ],
+            None => vec![v4l::control::Control {
+                id: V4L2_CID_EXPOSURE_AUTO,
+                value: v4l::control::Value::Integer(V4L2_EXPOSURE_APERTURE_PRIORITY),
+            }],
+        };
+        if let Err(e) = self.device.set_controls(controls) {
+            tracing::debug!(error = %e, ?exposure, "camera exposure control not applied");
+        }
+    }
+
+    /// Returns why this device looks like a virtual (software-fed) camera such
+    /// as v4l2loopback, or `None` if it looks like real hardware.
+    pub fn virtual_reason(&self) -> Option<String> {
+        crate::quirks::virtual_camera_reason(
+            &self.driver,
+            &self.bus_info,
+            crate::quirks::has_hardware_parent(&self.device_path),
+        )
+    }
+
+    /// The frame quality gate in effect.
+    pub fn quality_gate(&self) -> QualityGate {
+        self.quality_gate
+    }
+
+    /// Discard the first `count` frames of every capture stream.
+    ///
+    /// Each capture opens a fresh `MmapStream`, and the sensor's auto gain /
+    /// exposure restarts with it — so a one-off warmup at daemon start only
+    /// helped the very first capture. Default: 0.
+    pub fn set_stream_warmup(&mut self, count: usize) {
+        self.stream_warmup = count;
+    }
+
+    /// Dequeue and drop `count` warmup frames from a freshly started stream.
+    fn discard_warmup(&self, stream: &mut MmapStream<'_>, count: usize) -> Result<(), CameraError> {
+        for _ in 0..count {
+            stream
+                .next()
+                .map_err(|e| CameraError::from_io("failed to dequeue warmup buffer", e))?;
+        }
+        Ok(())
+    }
+
     /// Re-assert visage's negotiated capture format on the (possibly shared) device.
     ///
     /// The daemon holds one persistent fd but negotiates the format only once, at
@@ -146,9 +405,10 @@ impl Camera {
     /// so this is a no-op in the common, uncontended case. Runs before the
     /// `MmapStream` is created (before `REQBUFS`/`STREAMON`), where `S_FMT` is legal.
     fn reassert_format(&self) -> Result<(), CameraError> {
-        let current = self.device.format().map_err(|e| {
-            CameraError::CaptureFailed(format!("failed to query current format: {e}"))
-        })?;
+        let current = self
+            .device
+            .format()
+            .map_err(|e| CameraError::from_io("failed to query current format", e))?;
 
         // Fast path: device is still in our negotiated format.
         if current.fourcc == self.fourcc
@@ -203,25 +463,28 @@ impl Camera {
     /// Capture a single frame, converting to grayscale if needed.
     pub fn capture_frame(&self) -> Result<Frame, CameraError> {
         self.reassert_format()?;
-        let mut stream =
-            MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4).map_err(|e| {
-                CameraError::CaptureFailed(format!("failed to create mmap stream: {e}"))
-            })?;
+        let mut stream = MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4)
+            .map_err(|e| CameraError::from_io("failed to create mmap stream", e))?;
+        self.discard_warmup(&mut stream, self.stream_warmup)?;
 
         let (buf, meta) = stream
             .next()
-            .map_err(|e| CameraError::CaptureFailed(format!("failed to dequeue buffer: {e}")))?;
+            .map_err(|e| CameraError::from_io("failed to dequeue buffer", e))?;
 
         let gray = self.buf_to_grayscale(buf)?;
         let is_dark = frame::is_dark_frame(&gray, 0.95);
+        let (gray, width, height) = self.oriented(gray);
 
         Ok(Frame {
             data: gray,
-            width: self.width,
-            height: self.height,
+            width,
+            height,
             timestamp: std::time::Instant::now(),
             sequence: meta.sequence,
+            device_timestamp: device_timestamp(meta),
             is_dark,
+            exposure: None,
+            night: false,
         })
     }
 
@@ -262,52 +525,244 @@ impl Camera {
         }
     }
 
-    /// Capture multiple frames with dark-frame filtering and CLAHE enhancement.
+    /// Capture multiple frames with dark-frame filtering, quality gating, and
+    /// CLAHE enhancement.
     ///
-    /// Attempts up to `count * 3` raw captures to find `count` non-dark frames.
-    /// Each non-dark frame gets CLAHE contrast enhancement applied.
-    pub fn capture_frames(&self, count: usize) -> Result<(Vec<Frame>, usize), CameraError> {
-        self.reassert_format()?;
-        let max_attempts = count * 3;
-        let mut good_frames = Vec::with_capacity(count);
-        let mut dark_count = 0usize;
+    /// After the per-stream warmup frames are discarded, attempts up to
+    /// `count * 3` raw captures to find `count` usable frames.
+    /// Dark frames and frames rejected by the [`QualityGate`] (saturated,
+    /// blurry, moving) are dropped before CLAHE so they never reach the
+    /// detector; the returned [`CaptureStats`] says how many of each were seen.
+    pub fn capture_frames(&self, count: usize) -> Result<(Vec<Frame>, CaptureStats), CameraError> {
+        self.capture_frames_until(count, &|| false)
+    }
 
-        let mut stream =
-            MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4).map_err(|e| {
-                CameraError::CaptureFailed(format!("failed to create mmap stream: {e}"))
-            })?;
+    /// [`capture_frames`](Self::capture_frames) that stops early, returning the
+    /// frames gathered so far, as soon as `stop()` returns `true`. Checked
+    /// before every dequeue, so the stream closes within one frame interval.
+    pub fn capture_frames_until(
+        &self,
+        count: usize,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<(Vec<Frame>, CaptureStats), CameraError> {
+        self.capture_burst(count, false, stop)
+    }
 
-        for _ in 0..max_attempts {
-            if good_frames.len() >= count {
+    /// [`capture_frames_until`](Self::capture_frames_until) with the capture
+    /// profile chosen per burst: the night profile when `force_night` is set
+    /// (and one is configured), or from the first frame's brightness.
+    pub fn capture_burst(
+        &self,
+        count: usize,
+        force_night: bool,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<(Vec<Frame>, CaptureStats), CameraError> {
+        self.reassert_format()?;
+        let mut stats = CaptureStats::default();
+        let mut profile = self.profile;
+        profile.exposure = profile.exposure.or(self.calibration.exposure);
+        if let Some(night) = self.night.filter(|_| force_night) {
+            profile = self.night_profile(&night);
+            stats.night = true;
+        }
+        if profile.exposure.is_some() {
+            self.apply_exposure(profile.exposure);
+        }
+        let result = self.capture_with(count, profile, &mut stats, stop);
+        if stats.night {
+            stats.exposure = self.read_exposure();
+        }
+        // Hand exposure back to the camera's auto mode for the next user of
+        // the (possibly shared) device.
+        let night_exposure = stats.night
+            && self
+                .night
+                .is_some_and(|n| self.night_profile(&n).exposure.is_some());
+        if profile.exposure.is_some() || night_exposure {
+            self.apply_exposure(None);
+        }
+        result.map(|frames| (frames, stats))
+    }
+
+    fn capture_with(
+        &self,
+        count: usize,
+        mut profile: CaptureProfile,
+        stats: &mut CaptureStats,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<Vec<Frame>, CameraError> {
+        let mut target = count + profile.extra_frames;
+        let mut max_attempts = target * 3;
+        let mut good_frames = Vec::with_capacity(target);
+        let mut prev: Option<Vec<u8>> = None;
+
+        let mut stream = MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4)
+            .map_err(|e| CameraError::from_io("failed to create mmap stream", e))?;
+        self.discard_warmup(
+            &mut stream,
+            profile.warmup_frames.unwrap_or(self.stream_warmup),
+        )?;
+
+        let mut attempt = 0;
+        while attempt < max_attempts {
+            attempt += 1;
+            if good_frames.len() >= target || stop() {
                 break;
             }
 
-            let (buf, meta) = stream.next().map_err(|e| {
-                CameraError::CaptureFailed(format!("failed to dequeue buffer: {e}"))
-            })?;
+            let (buf, meta) = stream
+                .next()
+                .map_err(|e| CameraError::from_io("failed to dequeue buffer", e))?;
+
+            let gray = self.buf_to_grayscale(buf)?;
+
+            // A dark first frame switches the rest of the burst to the night
+            // profile; frames while a new exposure settles fail the motion gate.
+            if attempt == 1 && !stats.night {
+                if let Some(night) = self.night.filter(|n| {
+                    n.below_brightness
+                        .is_some_and(|limit| frame::mean_brightness(&gray) < limit)
+                }) {
+                    tracing::debug!(seq = meta.sequence, "dark first frame; using night profile");
+                    stats.night = true;
+                    stats.night_switched = true;
+                    profile = self.night_profile(&night);
+                    target = count + profile.extra_frames;
+                    max_attempts = target * 3;
+                    if profile.exposure.is_some() {
+                        self.apply_exposure(profile.exposure);
+                    }
+                }
+            }
 
-            let mut gray = self.buf_to_grayscale(buf)?;
+            if let Some(frame) = self.usable_frame(gray, meta, &mut prev, &profile, stats) {
+                good_frames.push(frame);
+            }
+        }
 
-            if frame::is_dark_frame(&gray, 0.95) {
-                dark_count += 1;
-                tracing::debug!(seq = meta.sequence, "skipping dark frame");
-                continue;
+        Ok(good_frames)
+    }
+
+    /// Keep a stream running and push every usable frame into `ring` until
+    /// `stop()` returns `true` (checked before every dequeue). The ring is
+    /// cleared first, so it never mixes frames from two streams. Uses the
+    /// day profile; returns the number of frames pushed.
+    pub fn stream_into(
+        &self,
+        ring: &mut FrameRing,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<usize, CameraError> {
+        self.reassert_format()?;
+        ring.clear();
+        let mut profile = self.profile;
+        profile.exposure = profile.exposure.or(self.calibration.exposure);
+        if profile.exposure.is_some() {
+            self.apply_exposure(profile.exposure);
+        }
+        let result = self.stream_with(ring, &profile, stop);
+        if profile.exposure.is_some() {
+            self.apply_exposure(None);
+        }
+        result
+    }
+
+    fn stream_with(
+        &self,
+        ring: &mut FrameRing,
+        profile: &CaptureProfile,
+        stop: &dyn Fn() -> bool,
+    ) -> Result<usize, CameraError> {
+        let mut stats = CaptureStats::default();
+        let mut prev: Option<Vec<u8>> = None;
+        let mut pushed = 0;
+
+        let mut stream = MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4)
+            .map_err(|e| CameraError::from_io("failed to create mmap stream", e))?;
+        self.discard_warmup(
+            &mut stream,
+            profile.warmup_frames.unwrap_or(self.stream_warmup),
+        )?;
+
+        while !stop() {
+            let (buf, meta) = stream
+                .next()
+                .map_err(|e| CameraError::from_io("failed to dequeue buffer", e))?;
+            let gray = self.buf_to_grayscale(buf)?;
+            if let Some(frame) = self.usable_frame(gray, meta, &mut prev, profile, &mut stats) {
+                ring.push(frame);
+                pushed += 1;
             }
+        }
+        Ok(pushed)
+    }
+
+    /// Apply CLAHE contrast enhancement, if the calibration enables it.
+    #[cfg(feature = "clahe")]
+    fn enhanced(&self, mut gray: Vec<u8>, profile: &CaptureProfile) -> Vec<u8> {
+        if self.calibration.clahe {
+            frame::clahe_enhance(
+                &mut gray,
+                self.width,
+                self.height,
+                8,
+                profile.clahe_clip_limit,
+            );
+        }
+        gray
+    }
 
-            // Apply CLAHE contrast enhancement
-            frame::clahe_enhance(&mut gray, self.width, self.height, 8, 0.02);
+    /// Built without the `clahe` feature: frames stay as captured.
+    #[cfg(not(feature = "clahe"))]
+    fn enhanced(&self, gray: Vec<u8>, _profile: &CaptureProfile) -> Vec<u8> {
+        gray
+    }
 
-            good_frames.push(Frame {
-                data: gray,
-                width: self.width,
-                height: self.height,
-                timestamp: std::time::Instant::now(),
-                sequence: meta.sequence,
-                is_dark: false,
-            });
+    /// Run the dark-frame check and the [`QualityGate`] on one raw frame,
+    /// counting rejections in `stats`, and return it enhanced and oriented
+    /// if it passes. `prev` tracks the previous raw frame of the stream.
+    fn usable_frame(
+        &self,
+        gray: Vec<u8>,
+        meta: &v4l::buffer::Metadata,
+        prev: &mut Option<Vec<u8>>,
+        profile: &CaptureProfile,
+        stats: &mut CaptureStats,
+    ) -> Option<Frame> {
+        if frame::is_dark_frame(&gray, 0.95) {
+            stats.dark += 1;
+            tracing::debug!(seq = meta.sequence, "skipping dark frame");
+            return None;
         }
 
-        Ok((good_frames, dark_count))
+        let verdict = self
+            .quality_gate
+            .check(&gray, self.width, self.height, prev.as_deref());
+        // Always compare against the latest raw frame, so an exposure ramp
+        // keeps being rejected until consecutive frames settle.
+        *prev = Some(gray.clone());
+        if let Err(reject) = verdict {
+            match reject {
+                FrameReject::Saturated => stats.saturated += 1,
+                FrameReject::Blurry => stats.blurry += 1,
+                FrameReject::Motion => stats.motion += 1,
+            }
+            tracing::debug!(seq = meta.sequence, ?reject, "skipping low-quality frame");
+            return None;
+        }
+
+        let (gray, width, height) = self.oriented(self.enhanced(gray, profile));
+
+        Some(Frame {
+            data: gray,
+            width,
+            height,
+            timestamp: std::time::Instant::now(),
+            sequence: meta.sequence,
+            device_timestamp: device_timestamp(meta),
+            is_dark: false,
+            exposure: profile.exposure,
+            night: stats.night,
+        })
     }
 
     /// List available V4L2 video capture devices.
@@ -342,3 +797,26 @@ impl Camera {
         devices
     }
 }
+
+/// Driver capture timestamp of a dequeued buffer.
+fn device_timestamp(meta: &v4l::buffer::Metadata) -> std::time::Duration {
+    let secs = u64::try_from(meta.timestamp.sec).unwrap_or(0);
+    let micros = u64::try_from(meta.timestamp.usec).unwrap_or(0);
+    std::time::Duration::from_secs(secs) + std::time::Duration::from_micros(micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enodev_is_a_disconnect() {
+        let gone = CameraError::from_io("dequeue", std::io::Error::from_raw_os_error(ENODEV));
+        assert!(matches!(gone, CameraError::Disconnected(_)));
+        let other = CameraError::from_io("dequeue", std::io::Error::from_raw_os_error(5));
+        assert!(matches!(other, CameraError::CaptureFailed(_)));
+
+        assert_eq!(gone.kind(), ErrorKind::CameraDisconnected);
+        assert_eq!(VisageError::from(other).code(), "camera_error");
+    }
+}
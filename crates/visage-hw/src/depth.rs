@@ -0,0 +1,136 @@
+//! Depth camera capture — the `Z16` stream of a depth-capable UVC device.
+//!
+//! RealSense-style cameras expose depth as a separate V4L2 node streaming
+//! 16-bit depth values. The depth is computed from the camera's IR imager,
+//! so a face found in the IR frame sits at the same place in the depth
+//! frame, up to the ratio of the two resolutions. Units and the usable range
+//! come from the `[depth]` section of the camera's quirk file, when it has one.
+
+use std::path::Path;
+
+use v4l::buffer::Type as BufType;
+use v4l::io::traits::CaptureStream;
+use v4l::prelude::*;
+use v4l::video::Capture;
+use v4l::FourCC;
+
+use crate::camera::CameraError;
+use crate::frame::{self, DepthFrame};
+use crate::quirks::{self, DepthInfo};
+
+/// V4L2 depth camera handle.
+pub struct DepthCamera {
+    device: Device,
+    pub width: u32,
+    pub height: u32,
+    pub device_path: String,
+    /// V4L2 driver name (e.g. `uvcvideo`).
+    pub driver: String,
+    /// V4L2 bus info (e.g. `usb-0000:00:14.0-3`).
+    pub bus_info: String,
+    /// Units and range of the depth values.
+    pub info: DepthInfo,
+    /// Frames discarded at the start of every stream while the projector settles.
+    stream_warmup: usize,
+}
+
+impl DepthCamera {
+    /// Open a depth node (e.g. "/dev/video0") and negotiate `Z16`.
+    pub fn open(device_path: &str) -> Result<Self, CameraError> {
+        if !Path::new(device_path).exists() {
+            return Err(CameraError::DeviceNotFound(device_path.to_string()));
+        }
+        let device = Device::with_path(device_path).map_err(|e| {
+            if e.to_string().contains("busy") || e.to_string().contains("EBUSY") {
+                CameraError::DeviceBusy
+            } else {
+                CameraError::DeviceNotFound(format!("{device_path}: {e}"))
+            }
+        })?;
+        let caps = device.query_caps().map_err(|e| {
+            CameraError::CaptureFailed(format!("failed to query capabilities: {e}"))
+        })?;
+        if !caps
+            .capabilities
+            .contains(v4l::capability::Flags::VIDEO_CAPTURE)
+        {
+            return Err(CameraError::StreamingNotSupported);
+        }
+
+        let mut fmt = device.format().map_err(|e| {
+            CameraError::FormatNegotiationFailed(format!("failed to get format: {e}"))
+        })?;
+        fmt.fourcc = FourCC::new(b"Z16 ");
+        let negotiated = device.set_format(&fmt).map_err(|e| {
+            CameraError::FormatNegotiationFailed(format!("failed to set format: {e}"))
+        })?;
+        if negotiated.fourcc != FourCC::new(b"Z16 ") {
+            return Err(CameraError::FormatNegotiationFailed(format!(
+                "{device_path} is not a depth stream: negotiated {:?}, need Z16",
+                negotiated.fourcc
+            )));
+        }
+
+        let info = quirks::get_usb_ids(device_path)
+            .and_then(|(vid, pid)| quirks::lookup_quirk(vid, pid))
+            .and_then(|quirk| quirk.depth)
+            .unwrap_or_default();
+        tracing::info!(
+            device = device_path,
+            driver = %caps.driver,
+            width = negotiated.width,
+            height = negotiated.height,
+            units_mm = info.units_mm,
+            "opened depth camera"
+        );
+
+        Ok(Self {
+            device,
+            width: negotiated.width,
+            height: negotiated.height,
+            device_path: device_path.to_string(),
+            driver: caps.driver.clone(),
+            bus_info: caps.bus.clone(),
+            info,
+            stream_warmup: 0,
+        })
+    }
+
+    /// Discard the first `count` frames of every capture stream. Default: 0.
+    pub fn set_stream_warmup(&mut self, count: usize) {
+        self.stream_warmup = count;
+    }
+
+    /// Returns why this device looks like a virtual camera, or `None` if it
+    /// looks like real hardware.
+    pub fn virtual_reason(&self) -> Option<String> {
+        quirks::virtual_camera_reason(
+            &self.driver,
+            &self.bus_info,
+            quirks::has_hardware_parent(&self.device_path),
+        )
+    }
+
+    /// Capture one depth frame after the stream warmup.
+    pub fn capture_frame(&self) -> Result<DepthFrame, CameraError> {
+        let mut stream = MmapStream::with_buffers(&self.device, BufType::VideoCapture, 4)
+            .map_err(|e| CameraError::from_io("failed to create mmap stream", e))?;
+        for _ in 0..self.stream_warmup {
+            stream
+                .next()
+                .map_err(|e| CameraError::from_io("failed to dequeue warmup buffer", e))?;
+        }
+        let (buf, meta) = stream
+            .next()
+            .map_err(|e| CameraError::from_io("failed to dequeue buffer", e))?;
+        let data = frame::z16_to_depth(buf, self.width, self.height)
+            .map_err(|e| CameraError::CaptureFailed(format!("Z16 conversion failed: {e}")))?;
+        Ok(DepthFrame {
+            data,
+            width: self.width,
+            height: self.height,
+            units_mm: self.info.units_mm,
+            sequence: meta.sequence,
+        })
+    }
+}
@@ -1,4 +1,5 @@
-//! Frame type and image processing — YUYV conversion, dark detection, CLAHE.
+//! Frame type and image processing — YUYV conversion, dark detection,
+//! quality gating, CLAHE.
 
 /// A captured grayscale camera frame.
 #[derive(Clone)]
@@ -8,8 +9,16 @@ pub struct Frame {
     pub width: u32,
     pub height: u32,
     pub timestamp: std::time::Instant,
+    /// Driver-reported sequence number within the capture stream.
     pub sequence: u32,
+    /// Driver-reported capture timestamp (zero if the driver does not set one).
+    pub device_timestamp: std::time::Duration,
     pub is_dark: bool,
+    /// Manual exposure (`exposure_absolute`, 100 µs units) the camera was set
+    /// to; `None` under auto exposure.
+    pub exposure: Option<i64>,
+    /// Captured with the night [`CaptureProfile`](crate::CaptureProfile).
+    pub night: bool,
 }
 
 impl Frame {
@@ -22,6 +31,98 @@ impl Frame {
     }
 }
 
+/// The most recent frames of a stream kept running between requests, oldest
+/// first; see [`Camera::stream_into`](crate::Camera::stream_into).
+pub struct FrameRing {
+    frames: std::collections::VecDeque<Frame>,
+    capacity: usize,
+}
+
+impl FrameRing {
+    /// An empty ring holding at most `capacity` frames.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: std::collections::VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add `frame`, dropping the oldest one once the ring is full.
+    pub fn push(&mut self, frame: Frame) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// Empty the ring and return the frames captured within `max_age`,
+    /// oldest first. Older frames are discarded.
+    pub fn take_recent(&mut self, max_age: std::time::Duration) -> Vec<Frame> {
+        let now = std::time::Instant::now();
+        self.frames
+            .drain(..)
+            .filter(|f| now.saturating_duration_since(f.timestamp) <= max_age)
+            .collect()
+    }
+
+    /// Discard every frame.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Number of frames held.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// A depth frame from a depth-capable camera (`Z16` stream).
+#[derive(Clone)]
+pub struct DepthFrame {
+    /// Raw depth values, row-major (width * height); 0 means no reading.
+    pub data: Vec<u16>,
+    pub width: u32,
+    pub height: u32,
+    /// Millimetres per depth unit (1.0 on most RealSense devices).
+    pub units_mm: f32,
+    /// Driver-reported sequence number within the capture stream.
+    pub sequence: u32,
+}
+
+impl DepthFrame {
+    /// Depth at pixel `(x, y)` in millimetres; `None` outside the frame or
+    /// where the sensor has no reading.
+    pub fn depth_mm(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let raw = *self.data.get((y * self.width + x) as usize)?;
+        (raw != 0).then(|| f32::from(raw) * self.units_mm)
+    }
+}
+
+/// Unpack a little-endian `Z16` buffer into depth values.
+pub fn z16_to_depth(buf: &[u8], width: u32, height: u32) -> Result<Vec<u16>, FrameError> {
+    let expected = (width * height * 2) as usize;
+    if buf.len() < expected {
+        return Err(FrameError::InvalidLength {
+            expected,
+            actual: buf.len(),
+        });
+    }
+    Ok(buf[..expected]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
 /// Convert packed YUYV (4:2:2) to grayscale by extracting the Y channel.
 ///
 /// YUYV packs two pixels per 4 bytes: [Y0, U, Y1, V].
@@ -37,6 +138,14 @@ pub fn yuyv_to_grayscale(yuyv: &[u8], width: u32, height: u32) -> Result<Vec<u8>
     Ok(yuyv[..expected].iter().step_by(2).copied().collect())
 }
 
+/// Mean value (0–255) of a grayscale buffer; 0 for an empty one.
+pub fn mean_brightness(gray: &[u8]) -> f32 {
+    if gray.is_empty() {
+        return 0.0;
+    }
+    gray.iter().map(|&p| u64::from(p)).sum::<u64>() as f32 / gray.len() as f32
+}
+
 /// Check if a frame is dark using an 8-bucket histogram.
 ///
 /// Returns true if >95% of pixels fall in the darkest bucket (0–31).
@@ -48,11 +157,209 @@ pub fn is_dark_frame(gray: &[u8], threshold_pct: f32) -> bool {
     (dark_count as f32 / gray.len() as f32) > threshold_pct
 }
 
+/// Why the quality gate rejected a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameReject {
+    /// Nearly every pixel is in the brightest bucket (emitter glare, AE overshoot).
+    Saturated,
+    /// Too little high-frequency detail (defocus, smear, covered lens).
+    Blurry,
+    /// Large change from the previous frame (subject moving, exposure still ramping).
+    Motion,
+}
+
+/// Cheap per-frame checks that run before CLAHE and the detector, so frames
+/// that cannot produce a usable face never reach SCRFD.
+///
+/// All checks read the raw grayscale frame. Saturation counts every pixel;
+/// sharpness samples a 2× subsampled grid and motion every other pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityGate {
+    /// Fraction of pixels ≥ 224 above which a frame counts as saturated.
+    pub max_saturated_pct: f32,
+    /// Minimum mean absolute Laplacian response. 0.0 disables the check.
+    pub min_sharpness: f32,
+    /// Maximum mean absolute difference from the previous frame. 0.0 disables the check.
+    pub max_motion: f32,
+    /// Minimum largest frame-to-frame difference across a burst, below which
+    /// the burst is treated as a replayed still. 0.0 disables the check.
+    pub min_scene_delta: f32,
+}
+
+impl Default for QualityGate {
+    /// Conservative defaults: only frames that are clearly unusable are dropped.
+    fn default() -> Self {
+        Self {
+            max_saturated_pct: 0.95,
+            min_sharpness: 1.5,
+            max_motion: 30.0,
+            min_scene_delta: 0.25,
+        }
+    }
+}
+
+impl QualityGate {
+    /// A gate that only rejects saturated frames (sharpness, motion and
+    /// static-scene checks disabled).
+    pub fn permissive() -> Self {
+        Self {
+            min_sharpness: 0.0,
+            max_motion: 0.0,
+            min_scene_delta: 0.0,
+            ..Self::default()
+        }
+    }
+
+    /// Check one raw grayscale frame. `prev` is the previous raw frame from
+    /// the same stream, if any.
+    pub fn check(
+        &self,
+        gray: &[u8],
+        width: u32,
+        height: u32,
+        prev: Option<&[u8]>,
+    ) -> Result<(), FrameReject> {
+        if is_saturated_frame(gray, self.max_saturated_pct) {
+            return Err(FrameReject::Saturated);
+        }
+        if self.max_motion > 0.0 {
+            if let Some(prev) = prev {
+                if frame_difference(prev, gray) > self.max_motion {
+                    return Err(FrameReject::Motion);
+                }
+            }
+        }
+        if self.min_sharpness > 0.0 && sharpness(gray, width, height) < self.min_sharpness {
+            return Err(FrameReject::Blurry);
+        }
+        Ok(())
+    }
+
+    /// Check that a burst of accepted frames was freshly captured: driver
+    /// sequence numbers and timestamps must strictly increase, and at least
+    /// one consecutive pair must differ by `min_scene_delta`. A real sensor
+    /// always shows some noise between frames; a looped still image does not.
+    pub fn check_burst(&self, frames: &[Frame]) -> Result<(), StaleBurst> {
+        for pair in frames.windows(2) {
+            if pair[1].sequence <= pair[0].sequence {
+                return Err(StaleBurst::SequenceReplayed {
+                    prev: pair[0].sequence,
+                    next: pair[1].sequence,
+                });
+            }
+            // Some drivers leave the timestamp unset; only compare real ones.
+            let (a, b) = (pair[0].device_timestamp, pair[1].device_timestamp);
+            if !a.is_zero() && !b.is_zero() && b <= a {
+                return Err(StaleBurst::TimestampReplayed);
+            }
+        }
+        if self.min_scene_delta > 0.0 && frames.len() >= 2 {
+            let max_delta = frames
+                .windows(2)
+                .map(|pair| frame_difference(&pair[0].data, &pair[1].data))
+                .fold(0.0f32, f32::max);
+            if max_delta < self.min_scene_delta {
+                return Err(StaleBurst::StaticScene { max_delta });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why [`QualityGate::check_burst`] rejected a burst of frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StaleBurst {
+    /// Sequence numbers went backwards or repeated.
+    SequenceReplayed { prev: u32, next: u32 },
+    /// Driver timestamps went backwards or repeated.
+    TimestampReplayed,
+    /// No frame differed measurably from the one before it.
+    StaticScene { max_delta: f32 },
+}
+
+impl std::fmt::Display for StaleBurst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SequenceReplayed { prev, next } => {
+                write!(f, "frame sequence did not advance ({prev} -> {next})")
+            }
+            Self::TimestampReplayed => write!(f, "frame timestamps did not advance"),
+            Self::StaticScene { max_delta } => {
+                write!(f, "static scene (max inter-frame delta {max_delta:.3})")
+            }
+        }
+    }
+}
+
+/// Check if a frame is saturated — the bright-side mirror of [`is_dark_frame`].
+///
+/// Returns true if more than `threshold_pct` of pixels fall in the brightest bucket (224–255).
+pub fn is_saturated_frame(gray: &[u8], threshold_pct: f32) -> bool {
+    if gray.is_empty() {
+        return false;
+    }
+    let bright_count = gray.iter().filter(|&&p| p >= 224).count();
+    (bright_count as f32 / gray.len() as f32) > threshold_pct
+}
+
+/// Sharpness estimate: mean absolute 4-neighbour Laplacian over every other
+/// pixel of every other row. Flat or defocused frames score near zero.
+pub fn sharpness(gray: &[u8], width: u32, height: u32) -> f32 {
+    let w = width as usize;
+    let h = height as usize;
+    if w < 3 || h < 3 || gray.len() < w * h {
+        return 0.0;
+    }
+    let mut sum = 0u64;
+    let mut n = 0u64;
+    for y in (1..h - 1).step_by(2) {
+        for x in (1..w - 1).step_by(2) {
+            let c = gray[y * w + x] as i32 * 4;
+            let lap = c
+                - gray[(y - 1) * w + x] as i32
+                - gray[(y + 1) * w + x] as i32
+                - gray[y * w + x - 1] as i32
+                - gray[y * w + x + 1] as i32;
+            sum += lap.unsigned_abs() as u64;
+            n += 1;
+        }
+    }
+    if n == 0 {
+        0.0
+    } else {
+        sum as f32 / n as f32
+    }
+}
+
+/// Mean absolute per-pixel difference between two frames, sampled on every
+/// other pixel. Returns 0.0 if the frames differ in size.
+pub fn frame_difference(a: &[u8], b: &[u8]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (sum, n) = a
+        .iter()
+        .zip(b)
+        .step_by(2)
+        .fold((0u64, 0u64), |(sum, n), (&x, &y)| {
+            (sum + x.abs_diff(y) as u64, n + 1)
+        });
+    sum as f32 / n as f32
+}
+
+#[cfg(feature = "clahe")]
 /// Apply Contrast-Limited Adaptive Histogram Equalization (CLAHE) in-place.
 ///
 /// Divides the image into a grid of tiles, computes a clipped histogram
-/// per tile, builds CDFs, and uses bilinear interpolation between tile
-/// CDFs for smooth output.
+/// per tile, and turns each into a lookup table; every pixel is then mapped
+/// through the four nearest tiles' tables with bilinear interpolation for
+/// smooth output.
+///
+/// Runs on every captured frame, so the mapping pass is integer-only: tables
+/// hold 8.8 fixed-point values, and the interpolation weights (8-bit) and
+/// neighbouring tiles of each column and row are computed once per frame
+/// rather than per pixel. Output is within one grey level of an `f32`
+/// implementation.
 pub fn clahe_enhance(gray: &mut [u8], width: u32, height: u32, tiles_x: u32, clip_limit: f32) {
     let w = width as usize;
     let h = height as usize;
@@ -63,101 +370,113 @@ pub fn clahe_enhance(gray: &mut [u8], width: u32, height: u32, tiles_x: u32, cli
     let tiles_y = tiles_x; // square grid
     let tx = tiles_x as usize;
     let ty = tiles_y as usize;
+    if tx == 0 {
+        return;
+    }
     let tile_w = w / tx;
     let tile_h = h / ty;
     if tile_w == 0 || tile_h == 0 {
         return;
     }
-    let tile_pixels = tile_w * tile_h;
-
-    // Build per-tile CDFs
-    let mut cdfs: Vec<[f32; 256]> = Vec::with_capacity(tx * ty);
 
+    let mut luts: Vec<[u16; 256]> = Vec::with_capacity(tx * ty);
     for row in 0..ty {
         for col in 0..tx {
-            let mut hist = [0u32; 256];
-            let y0 = row * tile_h;
-            let x0 = col * tile_w;
-
-            for y in y0..y0 + tile_h {
-                for x in x0..x0 + tile_w {
-                    hist[gray[y * w + x] as usize] += 1;
-                }
-            }
-
-            // Clip histogram
-            let clip = (clip_limit * tile_pixels as f32) as u32;
-            let mut excess = 0u32;
-            for bin in hist.iter_mut() {
-                if *bin > clip {
-                    excess += *bin - clip;
-                    *bin = clip;
-                }
-            }
-            let redist = excess / 256;
-            let leftover = (excess % 256) as usize;
-            for (i, bin) in hist.iter_mut().enumerate() {
-                *bin += redist;
-                if i < leftover {
-                    *bin += 1;
-                }
-            }
-
-            // Build CDF
-            let mut cdf = [0f32; 256];
-            cdf[0] = hist[0] as f32;
-            for i in 1..256 {
-                cdf[i] = cdf[i - 1] + hist[i] as f32;
-            }
-            // Normalize to 0–255
-            let cdf_min = cdf.iter().find(|&&v| v > 0.0).copied().unwrap_or(0.0);
-            let denom = (tile_pixels as f32) - cdf_min;
-            if denom > 0.0 {
-                for v in cdf.iter_mut() {
-                    *v = ((*v - cdf_min) / denom * 255.0).clamp(0.0, 255.0);
-                }
-            }
-            cdfs.push(cdf);
+            luts.push(tile_lut(
+                gray,
+                w,
+                (col * tile_w, row * tile_h),
+                (tile_w, tile_h),
+                clip_limit,
+            ));
         }
     }
 
-    // Map each pixel using bilinear interpolation between tile CDFs
-    for y in 0..h {
-        for x in 0..w {
-            let pixel = gray[y * w + x] as usize;
-
-            // Which tile center is this pixel near?
-            let fy = (y as f32 / tile_h as f32) - 0.5;
-            let fx = (x as f32 / tile_w as f32) - 0.5;
-
-            let fy = fy.clamp(0.0, (ty - 1) as f32);
-            let fx = fx.clamp(0.0, (tx - 1) as f32);
-
-            let r0 = fy as usize;
-            let c0 = fx as usize;
-            let r1 = (r0 + 1).min(ty - 1);
-            let c1 = (c0 + 1).min(tx - 1);
-
-            let dy = fy - r0 as f32;
-            let dx = fx - c0 as f32;
-
-            let tl = cdfs[r0 * tx + c0][pixel];
-            let tr = cdfs[r0 * tx + c1][pixel];
-            let bl = cdfs[r1 * tx + c0][pixel];
-            let br = cdfs[r1 * tx + c1][pixel];
+    let cols = axis_weights(w, tile_w, tx);
+    let rows = axis_weights(h, tile_h, ty);
+
+    for (y, &(r0, r1, wy)) in rows.iter().enumerate() {
+        let (top, bottom) = (&luts[r0 * tx..][..tx], &luts[r1 * tx..][..tx]);
+        let line = &mut gray[y * w..][..w];
+        for (pixel, &(c0, c1, wx)) in line.iter_mut().zip(&cols) {
+            let v = *pixel as usize;
+            // 8.8 table values × two 8-bit weights: at most 65280 << 16, which
+            // fits u32 together with the rounding term.
+            let upper = top[c0][v] as u32 * (256 - wx) + top[c1][v] as u32 * wx;
+            let lower = bottom[c0][v] as u32 * (256 - wx) + bottom[c1][v] as u32 * wx;
+            let value = upper * (256 - wy) + lower * wy;
+            *pixel = ((value + (1 << 23)) >> 24) as u8;
+        }
+    }
+}
 
-            let top = tl * (1.0 - dx) + tr * dx;
-            let bot = bl * (1.0 - dx) + br * dx;
-            let val = top * (1.0 - dy) + bot * dy;
+#[cfg(feature = "clahe")]
+/// Clipped-histogram equalization table for one tile, as 8.8 fixed point.
+fn tile_lut(
+    gray: &[u8],
+    stride: usize,
+    (x0, y0): (usize, usize),
+    (tile_w, tile_h): (usize, usize),
+    clip_limit: f32,
+) -> [u16; 256] {
+    let mut hist = [0u32; 256];
+    for line in gray[y0 * stride..].chunks(stride).take(tile_h) {
+        for &p in &line[x0..x0 + tile_w] {
+            hist[p as usize] += 1;
+        }
+    }
 
-            gray[y * w + x] = val.round().clamp(0.0, 255.0) as u8;
+    // Clip and redistribute the excess evenly.
+    let tile_pixels = (tile_w * tile_h) as u64;
+    let clip = (clip_limit * tile_pixels as f32) as u32;
+    let mut excess = 0u32;
+    for bin in hist.iter_mut() {
+        if *bin > clip {
+            excess += *bin - clip;
+            *bin = clip;
         }
     }
+    let redist = excess / 256;
+    let leftover = (excess % 256) as usize;
+    for (i, bin) in hist.iter_mut().enumerate() {
+        *bin += redist + u32::from(i < leftover);
+    }
+
+    // CDF normalized to 0–255 (scaled by 256).
+    let mut lut = [0u16; 256];
+    let mut cdf = [0u64; 256];
+    let mut running = 0u64;
+    for (c, &n) in cdf.iter_mut().zip(&hist) {
+        running += n as u64;
+        *c = running;
+    }
+    let cdf_min = cdf.iter().copied().find(|&v| v > 0).unwrap_or(0);
+    let denom = tile_pixels.saturating_sub(cdf_min);
+    for (out, &c) in lut.iter_mut().zip(&cdf) {
+        // An empty denominator (single-valued tile) keeps the raw counts.
+        let scaled = (c.saturating_sub(cdf_min) * 255 * 256 + denom / 2).checked_div(denom);
+        *out = scaled.unwrap_or(c << 8).min(255 * 256) as u16;
+    }
+    lut
+}
+
+#[cfg(feature = "clahe")]
+/// For every coordinate along one axis: the two nearest tile indices and the
+/// 8-bit weight of the second, measured from the tile centres.
+fn axis_weights(len: usize, tile_len: usize, tiles: usize) -> Vec<(usize, usize, u32)> {
+    (0..len)
+        .map(|i| {
+            let f = (i as f32 / tile_len as f32 - 0.5).clamp(0.0, (tiles - 1) as f32);
+            let t0 = f as usize;
+            let t1 = (t0 + 1).min(tiles - 1);
+            (t0, t1, ((f - t0 as f32) * 256.0).round() as u32)
+        })
+        .collect()
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum FrameError {
-    #[error("invalid YUYV length: expected {expected}, got {actual}")]
+    #[error("invalid buffer length: expected {expected}, got {actual}")]
     InvalidLength { expected: usize, actual: usize },
 }
 
@@ -190,6 +509,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_z16_to_depth() {
+        let buf = [0xe8, 0x03, 0x00, 0x00, 0x2c, 0x01];
+        let data = z16_to_depth(&buf, 3, 1).unwrap();
+        assert_eq!(data, vec![1000, 0, 300]);
+        assert!(z16_to_depth(&buf, 2, 2).is_err());
+
+        let depth = DepthFrame {
+            data,
+            width: 3,
+            height: 1,
+            units_mm: 0.5,
+            sequence: 0,
+        };
+        assert_eq!(depth.depth_mm(0, 0), Some(500.0));
+        assert_eq!(depth.depth_mm(1, 0), None, "no reading");
+        assert_eq!(depth.depth_mm(3, 0), None, "outside the frame");
+    }
+
     #[test]
     fn test_dark_frame_all_black() {
         let gray = vec![0u8; 1000];
@@ -202,6 +540,12 @@ mod tests {
         assert!(!is_dark_frame(&gray, 0.95));
     }
 
+    #[test]
+    fn test_mean_brightness() {
+        assert_eq!(mean_brightness(&[]), 0.0);
+        assert_eq!(mean_brightness(&[0, 255, 0, 255]), 127.5);
+    }
+
     #[test]
     fn test_dark_frame_empty() {
         assert!(is_dark_frame(&[], 0.95));
@@ -223,6 +567,62 @@ mod tests {
         assert!(!is_dark_frame(&gray, 0.95));
     }
 
+    #[test]
+    fn test_saturated_frame() {
+        assert!(is_saturated_frame(&[255u8; 1000], 0.95));
+        assert!(!is_saturated_frame(&[128u8; 1000], 0.95));
+        assert!(!is_saturated_frame(&[], 0.95));
+    }
+
+    #[test]
+    fn test_sharpness_flat_vs_textured() {
+        let (w, h) = (32u32, 32u32);
+        let flat = vec![128u8; (w * h) as usize];
+        let checker: Vec<u8> = (0..(w * h) as usize)
+            .map(|i| if (i % 32 + i / 32) % 2 == 0 { 60 } else { 190 })
+            .collect();
+        assert_eq!(sharpness(&flat, w, h), 0.0);
+        assert!(sharpness(&checker, w, h) > 100.0);
+    }
+
+    #[test]
+    fn test_frame_difference() {
+        let a = vec![100u8; 64];
+        let b = vec![140u8; 64];
+        assert_eq!(frame_difference(&a, &a), 0.0);
+        assert_eq!(frame_difference(&a, &b), 40.0);
+        assert_eq!(frame_difference(&a, &b[..32]), 0.0);
+    }
+
+    #[test]
+    fn test_quality_gate_rejections() {
+        let (w, h) = (32u32, 32u32);
+        let textured: Vec<u8> = (0..(w * h) as usize)
+            .map(|i| if (i % 32 + i / 32) % 2 == 0 { 60 } else { 190 })
+            .collect();
+        let gate = QualityGate::default();
+        assert_eq!(gate.check(&textured, w, h, None), Ok(()));
+        assert_eq!(
+            gate.check(&vec![250u8; (w * h) as usize], w, h, None),
+            Err(FrameReject::Saturated)
+        );
+        assert_eq!(
+            gate.check(&vec![128u8; (w * h) as usize], w, h, None),
+            Err(FrameReject::Blurry)
+        );
+        let brighter: Vec<u8> = textured.iter().map(|&p| p.saturating_add(50)).collect();
+        assert_eq!(
+            gate.check(&brighter, w, h, Some(&textured)),
+            Err(FrameReject::Motion)
+        );
+        // Permissive gate only rejects saturation.
+        assert_eq!(
+            QualityGate::permissive().check(&vec![128u8; (w * h) as usize], w, h, None),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "clahe")]
     #[test]
     fn test_clahe_increases_contrast() {
         // Low-contrast 16x16 image: all pixels between 100–110
@@ -243,10 +643,222 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "clahe")]
+    #[test]
+    fn test_clahe_matches_float_reference() {
+        let (w, h) = (64u32, 48u32);
+        // Gradient with a bright blob and some texture.
+        let image: Vec<u8> = (0..(w * h) as usize)
+            .map(|i| {
+                let (x, y) = (i % w as usize, i / w as usize);
+                let blob = if (20..36).contains(&x) && (10..30).contains(&y) {
+                    90
+                } else {
+                    0
+                };
+                (40 + x + y / 2 + blob + (i * 7919) % 13) as u8
+            })
+            .collect();
+        for (tiles, clip) in [(8, 0.02), (4, 0.01), (3, 1.0)] {
+            let mut fast = image.clone();
+            let mut reference = image.clone();
+            clahe_enhance(&mut fast, w, h, tiles, clip);
+            clahe_reference(&mut reference, w, h, tiles, clip);
+            let worst = fast
+                .iter()
+                .zip(&reference)
+                .map(|(a, b)| a.abs_diff(*b))
+                .max()
+                .unwrap();
+            assert!(worst <= 1, "tiles={tiles} clip={clip}: off by {worst}");
+        }
+    }
+
+    #[cfg(feature = "clahe")]
     fn stddev(data: &[u8]) -> f32 {
         let n = data.len() as f32;
         let mean = data.iter().map(|&b| b as f32).sum::<f32>() / n;
         let variance = data.iter().map(|&b| (b as f32 - mean).powi(2)).sum::<f32>() / n;
         variance.sqrt()
     }
+
+    fn burst_frame(sequence: u32, micros: u64, data: Vec<u8>) -> Frame {
+        Frame {
+            width: data.len() as u32,
+            height: 1,
+            data,
+            timestamp: std::time::Instant::now(),
+            sequence,
+            device_timestamp: std::time::Duration::from_micros(micros),
+            is_dark: false,
+            exposure: None,
+            night: false,
+        }
+    }
+
+    #[test]
+    fn test_frame_ring_keeps_recent_frames() {
+        let mut ring = FrameRing::new(3);
+        for i in 0..5u32 {
+            ring.push(burst_frame(i, 0, vec![100; 4]));
+        }
+        assert_eq!(ring.len(), 3);
+        let mut old = burst_frame(9, 0, vec![100; 4]);
+        old.timestamp -= std::time::Duration::from_secs(1);
+        ring.push(old);
+
+        let recent = ring.take_recent(std::time::Duration::from_millis(300));
+        let sequences: Vec<u32> = recent.iter().map(|f| f.sequence).collect();
+        assert_eq!(sequences, vec![3, 4], "oldest evicted, stale dropped");
+        assert!(ring.is_empty());
+
+        let mut none = FrameRing::new(0);
+        none.push(burst_frame(0, 0, vec![100; 4]));
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_check_burst_accepts_noisy_fresh_frames() {
+        let gate = QualityGate::default();
+        let frames: Vec<Frame> = (0..3u8)
+            .map(|i| burst_frame(i as u32, 1000 + i as u64 * 33_000, vec![100 + i; 64]))
+            .collect();
+        assert_eq!(gate.check_burst(&frames), Ok(()));
+    }
+
+    #[test]
+    fn test_check_burst_rejects_replays() {
+        let gate = QualityGate::default();
+        let still = vec![
+            burst_frame(0, 0, vec![100; 64]),
+            burst_frame(1, 0, vec![100; 64]),
+        ];
+        assert!(matches!(
+            gate.check_burst(&still),
+            Err(StaleBurst::StaticScene { .. })
+        ));
+        assert_eq!(QualityGate::permissive().check_burst(&still), Ok(()));
+
+        let repeated = vec![
+            burst_frame(5, 0, vec![100; 64]),
+            burst_frame(5, 0, vec![140; 64]),
+        ];
+        assert_eq!(
+            gate.check_burst(&repeated),
+            Err(StaleBurst::SequenceReplayed { prev: 5, next: 5 })
+        );
+
+        let rewound = vec![
+            burst_frame(0, 66_000, vec![100; 64]),
+            burst_frame(1, 33_000, vec![140; 64]),
+        ];
+        assert_eq!(
+            gate.check_burst(&rewound),
+            Err(StaleBurst::TimestampReplayed)
+        );
+    }
+
+    #[cfg(feature = "clahe")]
+    /// The original `f32` implementation, kept as a reference.
+    fn clahe_reference(gray: &mut [u8], width: u32, height: u32, tiles_x: u32, clip_limit: f32) {
+        let w = width as usize;
+        let h = height as usize;
+        if w == 0 || h == 0 || gray.len() < w * h {
+            return;
+        }
+
+        let tiles_y = tiles_x; // square grid
+        let tx = tiles_x as usize;
+        let ty = tiles_y as usize;
+        let tile_w = w / tx;
+        let tile_h = h / ty;
+        if tile_w == 0 || tile_h == 0 {
+            return;
+        }
+        let tile_pixels = tile_w * tile_h;
+
+        // Build per-tile CDFs
+        let mut cdfs: Vec<[f32; 256]> = Vec::with_capacity(tx * ty);
+
+        for row in 0..ty {
+            for col in 0..tx {
+                let mut hist = [0u32; 256];
+                let y0 = row * tile_h;
+                let x0 = col * tile_w;
+
+                for y in y0..y0 + tile_h {
+                    for x in x0..x0 + tile_w {
+                        hist[gray[y * w + x] as usize] += 1;
+                    }
+                }
+
+                // Clip histogram
+                let clip = (clip_limit * tile_pixels as f32) as u32;
+                let mut excess = 0u32;
+                for bin in hist.iter_mut() {
+                    if *bin > clip {
+                        excess += *bin - clip;
+                        *bin = clip;
+                    }
+                }
+                let redist = excess / 256;
+                let leftover = (excess % 256) as usize;
+                for (i, bin) in hist.iter_mut().enumerate() {
+                    *bin += redist;
+                    if i < leftover {
+                        *bin += 1;
+                    }
+                }
+
+                // Build CDF
+                let mut cdf = [0f32; 256];
+                cdf[0] = hist[0] as f32;
+                for i in 1..256 {
+                    cdf[i] = cdf[i - 1] + hist[i] as f32;
+                }
+                // Normalize to 0–255
+                let cdf_min = cdf.iter().find(|&&v| v > 0.0).copied().unwrap_or(0.0);
+                let denom = (tile_pixels as f32) - cdf_min;
+                if denom > 0.0 {
+                    for v in cdf.iter_mut() {
+                        *v = ((*v - cdf_min) / denom * 255.0).clamp(0.0, 255.0);
+                    }
+                }
+                cdfs.push(cdf);
+            }
+        }
+
+        // Map each pixel using bilinear interpolation between tile CDFs
+        for y in 0..h {
+            for x in 0..w {
+                let pixel = gray[y * w + x] as usize;
+
+                // Which tile center is this pixel near?
+                let fy = (y as f32 / tile_h as f32) - 0.5;
+                let fx = (x as f32 / tile_w as f32) - 0.5;
+
+                let fy = fy.clamp(0.0, (ty - 1) as f32);
+                let fx = fx.clamp(0.0, (tx - 1) as f32);
+
+                let r0 = fy as usize;
+                let c0 = fx as usize;
+                let r1 = (r0 + 1).min(ty - 1);
+                let c1 = (c0 + 1).min(tx - 1);
+
+                let dy = fy - r0 as f32;
+                let dx = fx - c0 as f32;
+
+                let tl = cdfs[r0 * tx + c0][pixel];
+                let tr = cdfs[r0 * tx + c1][pixel];
+                let bl = cdfs[r1 * tx + c0][pixel];
+                let br = cdfs[r1 * tx + c1][pixel];
+
+                let top = tl * (1.0 - dx) + tr * dx;
+                let bot = bl * (1.0 - dx) + br * dx;
+                let val = top * (1.0 - dy) + bot * dy;
+
+                gray[y * w + x] = val.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
 }
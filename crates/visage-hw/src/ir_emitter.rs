@@ -9,6 +9,7 @@ use std::cell::RefCell;
 use std::fs::{File, OpenOptions};
 use std::os::unix::io::AsRawFd;
 use thiserror::Error;
+use visage_core::error::{ErrorKind, VisageError};
 
 /// `UVCIOC_CTRL_QUERY` = `_IOWR('u', 0x21, struct uvc_xu_control_query)`
 /// where sizeof(struct uvc_xu_control_query) = 16 bytes (verified by assert below).
@@ -41,7 +42,9 @@ const _SIZE_ASSERT: () = assert!(
 /// Controls the IR emitter on a UVC camera.
 pub struct IrEmitter {
     device_path: String,
-    quirk: &'static CameraQuirk,
+    quirk: CameraQuirk,
+    /// Calibrated payload replacing the quirk's `control_bytes`.
+    control_bytes: Option<Vec<u8>>,
 
     /// Additional options for cameras with special file descriptor (fd) rules
     active_fd: RefCell<Option<File>>,
@@ -55,6 +58,25 @@ pub enum EmitterError {
     Open(std::io::Error),
     #[error("UVC ioctl failed: {0}")]
     Ioctl(std::io::Error),
+    #[error("emitter payload must be {expected} bytes, got {actual}")]
+    PayloadLength { expected: usize, actual: usize },
+}
+
+impl EmitterError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EmitterError::NoQuirk(_) => ErrorKind::NotSupported,
+            EmitterError::Open(_) | EmitterError::Ioctl(_) => ErrorKind::Camera,
+            EmitterError::PayloadLength { .. } => ErrorKind::InvalidArgument,
+        }
+    }
+}
+
+impl From<EmitterError> for VisageError {
+    fn from(e: EmitterError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
 }
 
 impl IrEmitter {
@@ -67,14 +89,29 @@ impl IrEmitter {
         Some(Self {
             device_path: device_path.to_string(),
             quirk,
+            control_bytes: None,
             active_fd: RefCell::new(None),
         })
     }
 
+    /// Send `bytes` instead of the quirk's `control_bytes` to activate the
+    /// emitter (a calibrated intensity). The length must match the control.
+    pub fn set_control_bytes(&mut self, bytes: Vec<u8>) -> Result<(), EmitterError> {
+        let expected = self.quirk.emitter.control_bytes.len();
+        if bytes.len() != expected {
+            return Err(EmitterError::PayloadLength {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        self.control_bytes = Some(bytes);
+        Ok(())
+    }
+
     /// Activate the IR emitter by sending the quirk's control bytes.
     pub fn activate(&self) -> Result<(), EmitterError> {
         tracing::debug!(device = %self.device_path, "activating IR emitter");
-        let mut payload = self.quirk.emitter.control_bytes.clone();
+        let mut payload = self.payload().to_vec();
 
         // reset_on_close devices forget the control the moment the fd closes,
         // so open a fresh fd, set it, and hold it open until deactivate().
@@ -85,7 +122,7 @@ impl IrEmitter {
                 .write(true)
                 .open(&self.device_path)
                 .map_err(EmitterError::Open)?;
-            let result = Self::send_via_fd(&file, self.quirk, &mut payload);
+            let result = Self::send_via_fd(&file, &self.quirk, &mut payload);
             *self.active_fd.borrow_mut() = Some(file);
             return result;
         }
@@ -103,7 +140,7 @@ impl IrEmitter {
         // "off" through the held fd, then close it to return control to default.
         if self.quirk.emitter.reset_on_close {
             let result = match self.active_fd.borrow().as_ref() {
-                Some(file) => Self::send_via_fd(file, self.quirk, &mut payload),
+                Some(file) => Self::send_via_fd(file, &self.quirk, &mut payload),
                 None => Ok(()),
             };
             self.active_fd.borrow_mut().take();
@@ -119,6 +156,15 @@ impl IrEmitter {
         &self.device_path
     }
 
+    /// The payload [`activate`](Self::activate) sends: the calibrated one if
+    /// set, else the quirk's `control_bytes`. Its bytes set the emitter's
+    /// level on cameras that have more than on and off.
+    pub fn payload(&self) -> &[u8] {
+        self.control_bytes
+            .as_deref()
+            .unwrap_or(&self.quirk.emitter.control_bytes)
+    }
+
     /// Human-readable name from the quirk database.
     pub fn name(&self) -> &str {
         &self.quirk.device.name
@@ -141,7 +187,7 @@ impl IrEmitter {
             .write(true)
             .open(&self.device_path)
             .map_err(EmitterError::Open)?;
-        Self::send_via_fd(&file, self.quirk, payload)
+        Self::send_via_fd(&file, &self.quirk, payload)
     }
 
     /// Send one UVC `SET_CUR` control over an already-open fd.
@@ -1,14 +1,21 @@
 //! visage-hw — Hardware abstraction for camera capture and IR emitter control.
 //!
-//! Provides V4L2-based camera access and UVC control byte management
-//! for IR emitter activation.
+//! Provides V4L2-based camera access, UVC control byte management
+//! for IR emitter activation, depth streams of depth-capable cameras, and
+//! ambient light sensor readings.
 
+pub mod als;
+pub mod calibration;
 pub mod camera;
+pub mod depth;
 pub mod frame;
 pub mod ir_emitter;
 pub mod quirks;
 
-pub use camera::{Camera, CameraError, PixelFormat};
-pub use frame::Frame;
+pub use als::AmbientLight;
+pub use calibration::Calibration;
+pub use camera::{Camera, CameraError, CaptureProfile, CaptureStats, NightMode, PixelFormat};
+pub use depth::DepthCamera;
+pub use frame::{DepthFrame, Frame, FrameRing, QualityGate, StaleBurst};
 pub use ir_emitter::{EmitterError, IrEmitter};
-pub use quirks::{get_driver, is_ipu6_camera, CameraQuirk};
+pub use quirks::{get_driver, is_ipu6_camera, CameraQuirk, DepthInfo};
@@ -2,10 +2,13 @@
 //!
 //! Maps camera USB VID:PID to UVC extension unit control parameters
 //! needed to activate their IR emitters. Quirk files are embedded at
-//! compile time from `contrib/hw/*.toml`.
+//! compile time from `contrib/hw/*.toml`; [`load_dirs`] adds (or overrides)
+//! quirks from `*.toml` files in directories read at runtime, and can be
+//! called again to pick up edited files.
 
 use serde::Deserialize;
-use std::sync::OnceLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
 
 /// Compile-time embedded quirk for the ASUS Zenbook 14 UM3406HA IR camera.
 const QUIRK_04F2_B6D9: &str = include_str!("../../../contrib/hw/04f2-b6d9.toml");
@@ -16,13 +19,19 @@ const QUIRK_30C9_00C2: &str = include_str!("../../../contrib/hw/30c9-00c2.toml")
 /// Compile-time embedded quirk for the HP OmniBook X Flip IR camera (Luxvisions 30c9:0120).
 const QUIRK_30C9_0120: &str = include_str!("../../../contrib/hw/30c9-0120.toml");
 
-static QUIRK_DB: OnceLock<Vec<QuirkFile>> = OnceLock::new();
+/// Directory of local quirk files when none is configured.
+pub const DEFAULT_QUIRKS_DIR: &str = "/etc/visage/quirks.d";
+
+static QUIRK_DB: OnceLock<RwLock<Arc<Vec<QuirkFile>>>> = OnceLock::new();
 
 /// Top-level quirk file structure (one per `contrib/hw/*.toml`).
 #[derive(Debug, Clone, Deserialize)]
 pub struct QuirkFile {
     pub device: DeviceInfo,
     pub emitter: EmitterInfo,
+    /// Depth stream of a depth-capable camera, if it has one.
+    #[serde(default)]
+    pub depth: Option<DepthInfo>,
 }
 
 /// Camera identification fields from the `[device]` section.
@@ -51,38 +60,146 @@ pub struct EmitterInfo {
     pub reset_on_close: bool,
 }
 
+/// Depth stream parameters from the optional `[depth]` section.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DepthInfo {
+    /// Millimetres per `Z16` depth unit.
+    #[serde(default = "default_depth_units_mm")]
+    pub units_mm: f32,
+    /// Nearest reading the sensor resolves, in millimetres; closer values are noise.
+    #[serde(default = "default_depth_min_mm")]
+    pub min_mm: f32,
+    /// Farthest reading used, in millimetres.
+    #[serde(default = "default_depth_max_mm")]
+    pub max_mm: f32,
+}
+
+fn default_depth_units_mm() -> f32 {
+    1.0
+}
+
+fn default_depth_min_mm() -> f32 {
+    150.0
+}
+
+fn default_depth_max_mm() -> f32 {
+    1500.0
+}
+
+impl Default for DepthInfo {
+    fn default() -> Self {
+        Self {
+            units_mm: default_depth_units_mm(),
+            min_mm: default_depth_min_mm(),
+            max_mm: default_depth_max_mm(),
+        }
+    }
+}
+
 /// Public alias used by `IrEmitter`.
 pub type CameraQuirk = QuirkFile;
 
-fn quirk_db() -> &'static Vec<QuirkFile> {
-    QUIRK_DB.get_or_init(|| {
-        let mut db = Vec::new();
-        for src in [
-            QUIRK_04F2_B6D9,
-            QUIRK_174F_2454,
-            QUIRK_30C9_00C2,
-            QUIRK_30C9_0120,
-        ] {
-            match toml::from_str::<QuirkFile>(src) {
-                Ok(q) => db.push(q),
-                Err(e) => eprintln!("visage-hw: bad quirk TOML: {e}"),
-            }
+fn embedded_quirks() -> Vec<QuirkFile> {
+    let mut db = Vec::new();
+    for src in [
+        QUIRK_04F2_B6D9,
+        QUIRK_174F_2454,
+        QUIRK_30C9_00C2,
+        QUIRK_30C9_0120,
+    ] {
+        match toml::from_str::<QuirkFile>(src) {
+            Ok(q) => db.push(q),
+            Err(e) => eprintln!("visage-hw: bad quirk TOML: {e}"),
         }
-        db
-    })
+    }
+    db
+}
+
+fn quirk_db() -> &'static RwLock<Arc<Vec<QuirkFile>>> {
+    QUIRK_DB.get_or_init(|| RwLock::new(Arc::new(embedded_quirks())))
 }
 
 /// Look up a quirk by USB vendor:product ID.
-/// Returns a `'static` reference into the embedded database.
-pub fn lookup_quirk(vid: u16, pid: u16) -> Option<&'static QuirkFile> {
-    quirk_db()
+pub fn lookup_quirk(vid: u16, pid: u16) -> Option<QuirkFile> {
+    list_quirks()
         .iter()
         .find(|q| q.device.vendor_id == vid && q.device.product_id == pid)
+        .cloned()
 }
 
 /// List all known quirks.
-pub fn list_quirks() -> &'static [QuirkFile] {
+pub fn list_quirks() -> Arc<Vec<QuirkFile>> {
     quirk_db()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// What [`load_dirs`] read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    /// Quirk files loaded, in the order applied.
+    pub files: Vec<PathBuf>,
+    /// Files that could not be read or parsed, with the reason. They are
+    /// skipped; the other files still load.
+    pub errors: Vec<(PathBuf, String)>,
+    /// Quirks in the database now, embedded ones included.
+    pub quirks: usize,
+}
+
+/// Replace the quirk database with the embedded quirks plus every `*.toml`
+/// file in `dirs`, read in name order, directory by directory. A file for a
+/// VID:PID already known replaces that quirk, so a later directory overrides
+/// an earlier one and any directory overrides the embedded database. A
+/// missing directory is skipped.
+pub fn load_dirs(dirs: &[PathBuf]) -> LoadReport {
+    let mut db = embedded_quirks();
+    let mut report = LoadReport::default();
+    for dir in dirs {
+        let mut paths = match toml_files(dir) {
+            Ok(paths) => paths,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                report.errors.push((dir.clone(), e.to_string()));
+                continue;
+            }
+        };
+        paths.sort();
+        for path in paths {
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|src| toml::from_str::<QuirkFile>(&src).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(quirk) => {
+                    let same = |q: &QuirkFile| {
+                        q.device.vendor_id == quirk.device.vendor_id
+                            && q.device.product_id == quirk.device.product_id
+                    };
+                    match db.iter_mut().find(|q| same(q)) {
+                        Some(known) => *known = quirk,
+                        None => db.push(quirk),
+                    }
+                    report.files.push(path);
+                }
+                Err(e) => report.errors.push((path, e)),
+            }
+        }
+    }
+    report.quirks = db.len();
+    *quirk_db().write().unwrap_or_else(PoisonError::into_inner) = Arc::new(db);
+    report
+}
+
+/// The `*.toml` files directly in `dir`.
+fn toml_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "toml") && path.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
 }
 
 /// Read the kernel driver name for a `/dev/videoN` device from sysfs.
@@ -127,3 +244,130 @@ pub fn get_usb_ids(device_path: &str) -> Option<(u16, u16)> {
     let pid = u16::from_str_radix(pid_str.trim(), 16).ok()?;
     Some((vid, pid))
 }
+
+/// Kernel drivers that expose a software-fed video source rather than a sensor.
+/// Matched case-insensitively against the V4L2 driver name and bus info.
+const VIRTUAL_CAMERA_DRIVERS: &[&str] = &["v4l2 loopback", "v4l2loopback", "vivid", "akvcam"];
+
+/// Returns why a device looks like a virtual camera, or `None` for real hardware.
+///
+/// Anything can write frames into a virtual camera, so using one for
+/// verification turns spoofing into a one-liner. `has_hw_parent` comes from
+/// [`has_hardware_parent`].
+pub fn virtual_camera_reason(driver: &str, bus_info: &str, has_hw_parent: bool) -> Option<String> {
+    let driver_lc = driver.to_ascii_lowercase();
+    let bus_lc = bus_info.to_ascii_lowercase();
+    if VIRTUAL_CAMERA_DRIVERS
+        .iter()
+        .any(|v| driver_lc.contains(v) || bus_lc.contains(v))
+    {
+        return Some(format!("driver '{driver}' is a virtual camera"));
+    }
+    if !has_hw_parent {
+        return Some(format!("'{driver}' has no hardware parent device in sysfs"));
+    }
+    None
+}
+
+/// Returns `true` if `/dev/videoN` is backed by a device node (USB, PCI,
+/// platform) rather than living under `/sys/devices/virtual`.
+pub fn has_hardware_parent(device_path: &str) -> bool {
+    let Some(dev_name) = std::path::Path::new(device_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+    else {
+        return false;
+    };
+    std::fs::canonicalize(format!("/sys/class/video4linux/{dev_name}/device"))
+        .map(|p| !p.starts_with("/sys/devices/virtual"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_section_is_optional() {
+        assert!(list_quirks().iter().all(|q| q.depth.is_none()));
+
+        let quirk: QuirkFile = toml::from_str(
+            r#"
+            [device]
+            vendor_id = 0x8086
+            product_id = 0x0B07
+            name = "Depth camera"
+
+            [emitter]
+            unit = 3
+            selector = 2
+            control_bytes = [1]
+
+            [depth]
+            units_mm = 0.1
+            "#,
+        )
+        .unwrap();
+        let depth = quirk.depth.unwrap();
+        assert_eq!(depth.units_mm, 0.1);
+        assert_eq!(depth.min_mm, DepthInfo::default().min_mm);
+    }
+
+    #[test]
+    fn test_quirk_dirs_add_and_override() {
+        let root = std::env::temp_dir().join(format!("visage-quirks-{}", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        let quirk = |vid: u16, pid: u16, name: &str| {
+            format!(
+                "[device]\nvendor_id = {vid}\nproduct_id = {pid}\nname = \"{name}\"\n\n\
+                 [emitter]\nunit = 3\nselector = 2\ncontrol_bytes = [1]\n"
+            )
+        };
+        let write = |path: PathBuf, text: String| std::fs::write(path, text).unwrap();
+        write(
+            first.join("04f2-b6d9.toml"),
+            quirk(0x04F2, 0xB6D9, "Local Zenbook"),
+        );
+        write(
+            first.join("1234-5678.toml"),
+            quirk(0x1234, 0x5678, "Test camera"),
+        );
+        write(first.join("broken.toml"), "[device]".to_string());
+        write(first.join("notes.txt"), "ignored".to_string());
+        write(
+            second.join("1234-5678.toml"),
+            quirk(0x1234, 0x5678, "Test camera v2"),
+        );
+
+        let report = load_dirs(&[first.clone(), root.join("missing"), second.clone()]);
+        assert_eq!(report.files.len(), 3);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, first.join("broken.toml"));
+        assert_eq!(report.quirks, 5);
+        assert_eq!(
+            lookup_quirk(0x04F2, 0xB6D9).unwrap().device.name,
+            "Local Zenbook"
+        );
+        assert_eq!(
+            lookup_quirk(0x1234, 0x5678).unwrap().device.name,
+            "Test camera v2"
+        );
+
+        // Reloading without the directories restores the embedded database.
+        assert_eq!(load_dirs(&[]).quirks, 4);
+        assert!(lookup_quirk(0x1234, 0x5678).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_virtual_camera_drivers_are_refused() {
+        assert!(
+            virtual_camera_reason("v4l2 loopback", "platform:v4l2loopback-000", true).is_some()
+        );
+        assert!(virtual_camera_reason("vivid", "platform:vivid-000", true).is_some());
+        assert!(virtual_camera_reason("uvcvideo", "usb-0000:00:14.0-5", false).is_some());
+        assert!(virtual_camera_reason("uvcvideo", "usb-0000:00:14.0-5", true).is_none());
+    }
+}
@@ -0,0 +1,45 @@
+//! Error type for the facade API.
+
+use thiserror::Error;
+use visage_core::{ErrorKind, VisageError};
+
+/// Everything that can go wrong in a [`Pipeline`](crate::Pipeline).
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("camera error: {0}")]
+    Camera(#[from] visage_hw::CameraError),
+    #[error("detector error: {0}")]
+    Detector(#[from] visage_core::detector::DetectorError),
+    #[error("recognizer error: {0}")]
+    Recognizer(#[from] visage_core::recognizer::RecognizerError),
+    #[error("pipeline was built without a camera")]
+    NoCamera,
+    #[error("no usable frames captured")]
+    NoUsableFrames,
+    #[error("no face detected in any captured frame")]
+    NoFaceDetected,
+}
+
+impl Error {
+    /// The [`ErrorKind`] shared with the daemon and the other Visage crates.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Camera(e) => e.kind(),
+            Error::Detector(e) => e.kind(),
+            Error::Recognizer(e) => e.kind(),
+            Error::NoCamera => ErrorKind::InvalidArgument,
+            Error::NoUsableFrames => ErrorKind::TooDark,
+            Error::NoFaceDetected => ErrorKind::NoFace,
+        }
+    }
+}
+
+impl From<Error> for VisageError {
+    fn from(e: Error) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// `Result` alias with [`Error`] as the error type.
+pub type Result<T> = std::result::Result<T, Error>;
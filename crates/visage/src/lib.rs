@@ -0,0 +1,64 @@
+//! visage — embeddable face recognition engine.
+//!
+//! A stable facade over `visage-core` (SCRFD detection, ArcFace recognition)
+//! and `visage-hw` (V4L2 capture) for projects that want the engine without
+//! the `visaged` daemon, D-Bus, or PAM — kiosks, robots, access panels.
+//!
+//! This crate follows semver on its own: anything exported here only changes
+//! incompatibly with a major (pre-1.0: minor) version bump. The underlying
+//! crates carry no such promise.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use visage::{FaceModel, Pipeline};
+//!
+//! # fn main() -> visage::Result<()> {
+//! for camera in visage::discover_cameras() {
+//!     println!("{} — {}", camera.path, camera.name);
+//! }
+//!
+//! let mut pipeline = Pipeline::builder()
+//!     .model_dir("/var/lib/visage/models")
+//!     .camera("/dev/video2")
+//!     .threshold(0.45)
+//!     .build()?;
+//!
+//! let template = pipeline.enroll()?;
+//! let gallery = vec![FaceModel {
+//!     id: "1".into(),
+//!     user: "alice".into(),
+//!     label: "default".into(),
+//!     embedding: template,
+//!     created_at: String::new(),
+//!     tenant: String::new(),
+//!     threshold_offset: 0.0,
+//! }];
+//!
+//! let verification = pipeline.verify(&gallery)?;
+//! println!(
+//!     "matched={} similarity={:.3}",
+//!     verification.result.matched, verification.result.similarity
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+mod pipeline;
+
+pub use error::{Error, Result};
+pub use pipeline::{
+    Pipeline, PipelineBuilder, Verification, DEFAULT_LIVENESS_MIN_DISPLACEMENT, DEFAULT_THRESHOLD,
+};
+pub use visage_core::{
+    BoundingBox, CosineMatcher, Embedding, ErrorKind, FaceModel, LivenessResult, MatchResult,
+    Matcher, OpenSetMatcher, VisageError,
+};
+pub use visage_hw::camera::DeviceInfo as CameraInfo;
+pub use visage_hw::{Camera, CameraError, Frame, QualityGate};
+
+/// List V4L2 capture devices on this machine.
+pub fn discover_cameras() -> Vec<CameraInfo> {
+    Camera::list_devices()
+}
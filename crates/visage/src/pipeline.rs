@@ -0,0 +1,304 @@
+//! Capture → detect → embed → match, without the daemon.
+
+use crate::error::{Error, Result};
+use std::path::PathBuf;
+use visage_core::{
+    check_landmark_stability, BoundingBox, CosineMatcher, Embedding, FaceDetector, FaceModel,
+    FaceRecognizer, InferenceOptions, LivenessResult, MatchResult, Matcher, OpenSetMatcher,
+};
+use visage_hw::{Camera, Frame, QualityGate};
+
+/// Similarity threshold used unless [`PipelineBuilder::threshold`] is called.
+/// Same default as the daemon.
+pub const DEFAULT_THRESHOLD: f32 = 0.40;
+
+/// Minimum eye-landmark displacement (pixels) used by the default liveness check.
+pub const DEFAULT_LIVENESS_MIN_DISPLACEMENT: f32 = 0.8;
+
+/// Detector model file name inside the model directory.
+const SCRFD_MODEL: &str = "det_10g.onnx";
+/// Recognizer model file name inside the model directory.
+const ARCFACE_MODEL: &str = "w600k_r50.onnx";
+
+/// Builder for [`Pipeline`]. Obtain one with [`Pipeline::builder`].
+#[derive(Debug, Clone)]
+pub struct PipelineBuilder {
+    model_dir: PathBuf,
+    camera: Option<String>,
+    quality_gate: QualityGate,
+    warmup_frames: usize,
+    frames_per_attempt: usize,
+    threshold: f32,
+    liveness_min_displacement: Option<f32>,
+    reproducible: bool,
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> Self {
+        Self {
+            model_dir: visage_core::default_model_dir(),
+            camera: None,
+            quality_gate: QualityGate::default(),
+            warmup_frames: 4,
+            frames_per_attempt: 3,
+            threshold: DEFAULT_THRESHOLD,
+            liveness_min_displacement: Some(DEFAULT_LIVENESS_MIN_DISPLACEMENT),
+            reproducible: false,
+        }
+    }
+}
+
+impl PipelineBuilder {
+    /// Directory holding `det_10g.onnx` and `w600k_r50.onnx`.
+    /// Default: [`visage_core::default_model_dir`].
+    pub fn model_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.model_dir = dir.into();
+        self
+    }
+
+    /// V4L2 device to capture from (e.g. `/dev/video2`). Without a camera the
+    /// pipeline only works on frames you pass in.
+    pub fn camera(mut self, device: impl Into<String>) -> Self {
+        self.camera = Some(device.into());
+        self
+    }
+
+    /// Per-frame quality checks applied during capture.
+    pub fn quality_gate(mut self, gate: QualityGate) -> Self {
+        self.quality_gate = gate;
+        self
+    }
+
+    /// Frames discarded at the start of every capture while exposure settles. Default: 4.
+    pub fn warmup_frames(mut self, count: usize) -> Self {
+        self.warmup_frames = count;
+        self
+    }
+
+    /// Frames captured per enroll or verify. Default: 3.
+    pub fn frames_per_attempt(mut self, count: usize) -> Self {
+        self.frames_per_attempt = count.max(1);
+        self
+    }
+
+    /// Cosine similarity required for a match. Default: [`DEFAULT_THRESHOLD`].
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Passive liveness check on matches: `Some(min_displacement)` enables it,
+    /// `None` disables it. Default: enabled.
+    pub fn liveness(mut self, min_displacement: Option<f32>) -> Self {
+        self.liveness_min_displacement = min_displacement;
+        self
+    }
+
+    /// Reproducibility mode: run inference single-threaded with
+    /// deterministic kernels, so the same frames always give the same
+    /// detections and embeddings — for comparing versions or machines.
+    /// Slower. Default: off.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Load both models and open the camera, if one was configured.
+    pub fn build(self) -> Result<Pipeline> {
+        let camera = match &self.camera {
+            Some(device) => {
+                let mut camera = Camera::open(device)?;
+                camera.set_quality_gate(self.quality_gate);
+                camera.set_stream_warmup(self.warmup_frames);
+                Some(camera)
+            }
+            None => None,
+        };
+        let options = InferenceOptions {
+            reproducible: self.reproducible,
+        };
+        let detector =
+            FaceDetector::load_with(&self.model_dir.join(SCRFD_MODEL).to_string_lossy(), options)?;
+        let recognizer = FaceRecognizer::load_with(
+            &self.model_dir.join(ARCFACE_MODEL).to_string_lossy(),
+            options,
+        )?;
+        Ok(Pipeline {
+            camera,
+            detector,
+            recognizer,
+            frames_per_attempt: self.frames_per_attempt,
+            threshold: self.threshold,
+            liveness_min_displacement: self.liveness_min_displacement,
+        })
+    }
+}
+
+/// Outcome of [`Pipeline::verify`] or [`Pipeline::identify`].
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Best match across the frames. `matched` is already `false` if the
+    /// liveness check ran and failed.
+    pub result: MatchResult,
+    /// Liveness check result, if liveness is enabled and identity matched.
+    pub liveness: Option<LivenessResult>,
+}
+
+/// A loaded face recognition pipeline.
+pub struct Pipeline {
+    camera: Option<Camera>,
+    detector: FaceDetector,
+    recognizer: FaceRecognizer,
+    frames_per_attempt: usize,
+    threshold: f32,
+    liveness_min_displacement: Option<f32>,
+}
+
+impl Pipeline {
+    /// Start building a pipeline with default settings.
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::default()
+    }
+
+    /// The camera, if the pipeline was built with one.
+    pub fn camera(&self) -> Option<&Camera> {
+        self.camera.as_ref()
+    }
+
+    /// Capture one attempt's worth of quality-checked frames.
+    pub fn capture(&self) -> Result<Vec<Frame>> {
+        let camera = self.camera.as_ref().ok_or(Error::NoCamera)?;
+        let (frames, _stats) = camera.capture_frames(self.frames_per_attempt)?;
+        if frames.is_empty() {
+            return Err(Error::NoUsableFrames);
+        }
+        Ok(frames)
+    }
+
+    /// Detect faces in a frame, highest confidence first.
+    pub fn detect(&mut self, frame: &Frame) -> Result<Vec<BoundingBox>> {
+        Ok(self
+            .detector
+            .detect(&frame.data, frame.width, frame.height)?)
+    }
+
+    /// Detect the most confident face in a frame and compute its embedding.
+    /// Returns `None` if no face with landmarks is found.
+    pub fn embed(&mut self, frame: &Frame) -> Result<Option<(BoundingBox, Embedding)>> {
+        let Some(face) = self.detect(frame)?.into_iter().next() else {
+            return Ok(None);
+        };
+        if face.landmarks.is_none() {
+            return Ok(None);
+        }
+        let embedding = self
+            .recognizer
+            .extract(&frame.data, frame.width, frame.height, &face)?;
+        Ok(Some((face, embedding)))
+    }
+
+    /// Capture frames and build an enrollment template from them.
+    pub fn enroll(&mut self) -> Result<Embedding> {
+        let frames = self.capture()?;
+        self.enroll_frames(&frames)
+    }
+
+    /// Build an enrollment template from frames you supply: the
+    /// confidence-weighted mean of every detected face's embedding.
+    pub fn enroll_frames(&mut self, frames: &[Frame]) -> Result<Embedding> {
+        let mut embeddings = Vec::with_capacity(frames.len());
+        for frame in frames {
+            if let Some((face, embedding)) = self.embed(frame)? {
+                embeddings.push((embedding, face.confidence.max(0.0)));
+            }
+        }
+        Embedding::weighted_mean(&embeddings).ok_or(Error::NoFaceDetected)
+    }
+
+    /// Capture frames and verify them against one user's gallery (1:1).
+    pub fn verify(&mut self, gallery: &[FaceModel]) -> Result<Verification> {
+        let frames = self.capture()?;
+        self.verify_frames(&frames, gallery)
+    }
+
+    /// Verify frames you supply against one user's gallery (1:1).
+    pub fn verify_frames(
+        &mut self,
+        frames: &[Frame],
+        gallery: &[FaceModel],
+    ) -> Result<Verification> {
+        self.match_frames(frames, gallery, &CosineMatcher)
+    }
+
+    /// Capture frames and identify who is in front of the camera among
+    /// several users (1:N). The best user must lead every other user by `margin`.
+    pub fn identify(&mut self, gallery: &[FaceModel], margin: f32) -> Result<Verification> {
+        let frames = self.capture()?;
+        self.match_frames(&frames, gallery, &OpenSetMatcher { margin })
+    }
+
+    fn match_frames(
+        &mut self,
+        frames: &[Frame],
+        gallery: &[FaceModel],
+        matcher: &dyn Matcher,
+    ) -> Result<Verification> {
+        let mut best: Option<MatchResult> = None;
+        let mut landmarks = Vec::new();
+
+        for frame in frames {
+            let Some((face, embedding)) = self.embed(frame)? else {
+                continue;
+            };
+            landmarks.extend(face.landmarks);
+            let result = matcher.compare(&embedding, gallery, self.threshold);
+            let is_better = match &best {
+                None => true,
+                Some(prev) => result.similarity > prev.similarity,
+            };
+            if is_better {
+                best = Some(result);
+            }
+        }
+
+        let mut result = best.ok_or(Error::NoFaceDetected)?;
+        let liveness = match self.liveness_min_displacement {
+            Some(min) if result.matched => {
+                let liveness = check_landmark_stability(&landmarks, Some(min));
+                if !liveness.is_live {
+                    result.matched = false;
+                    result.model_id = None;
+                    result.model_label = None;
+                }
+                Some(liveness)
+            }
+            _ => None,
+        };
+        Ok(Verification { result, liveness })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_defaults_match_daemon() {
+        let builder = Pipeline::builder();
+        assert_eq!(builder.threshold, DEFAULT_THRESHOLD);
+        assert_eq!(builder.frames_per_attempt, 3);
+        assert_eq!(
+            builder.liveness_min_displacement,
+            Some(DEFAULT_LIVENESS_MIN_DISPLACEMENT)
+        );
+        assert!(builder.camera.is_none());
+
+        let builder = builder
+            .camera("/dev/video2")
+            .frames_per_attempt(0)
+            .liveness(None);
+        assert_eq!(builder.camera.as_deref(), Some("/dev/video2"));
+        assert_eq!(builder.frames_per_attempt, 1);
+        assert!(builder.liveness_min_displacement.is_none());
+    }
+}
@@ -0,0 +1,305 @@
+//! Audit log — an append-only record of every biometric decision.
+//!
+//! The tracing output is for debugging: its level and format change with
+//! `RUST_LOG`, and the journal rotates it away. Compliance review needs a
+//! stable record instead. Every verify, identification, enrollment and model
+//! removal appends one JSON line to `VISAGE_AUDIT_LOG`
+//! (`/var/log/visage/audit.log` by default, mode `0600`):
+//!
+//! ```json
+//! {"timestamp":"2026-03-02T08:01:13.402+00:00","operation":"verify","tenant":"","user":"alice","result":"matched","similarity":0.6123,"model_id":"3f1c…","frame":{"sequence":41,"timestamp_us":5120334012,"exposure":null,"night":false,"emitter":[1,3,3,0],"precaptured":false},"caller_uid":0,"duration_ms":840}
+//! ```
+//!
+//! `result` is `matched`, a failure reason or error code (`below_threshold`,
+//! `rate_limited`, ...), `enrolled`, `removed` or `not_found`. A verify that
+//! compared a face records in `frame` which frame decided it and how it was
+//! captured: the driver's sequence number and timestamp, the manual exposure
+//! (`null` for auto), the night profile, and the IR emitter payload (`null`
+//! when the emitter was off). With a PAD model configured (see
+//! `visage_core::pad`), `pad_score` is the live probability it gave a face
+//! that matched, whether or not it passed. `caller_uid` is
+//! `null` when the daemon acted on its own (a retention purge), on the session
+//! bus, and for the vsock/TCP transports. Never an embedding or an image.
+//!
+//! The file is only ever appended to. Once it would exceed
+//! `VISAGE_AUDIT_MAX_BYTES` it is renamed to `audit.log.1` (the older ones
+//! shifting up to `VISAGE_AUDIT_KEEP`, the oldest deleted) and a new one is
+//! started. A record that cannot be written is logged as an error; the
+//! decision itself stands.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::engine::FrameMeta;
+
+/// One audited operation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEvent {
+    /// `verify`, `identify`, `enroll`, `remove_model`, `export` or `import`.
+    pub operation: &'static str,
+    pub tenant: String,
+    /// Empty for an identification that recognized no one.
+    pub user: String,
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    /// Sensor conditions of the frame a verify decision rests on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<FrameMeta>,
+    /// Live probability the PAD model gave the matched face.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pad_score: Option<f32>,
+    pub caller_uid: Option<u32>,
+    #[serde(serialize_with = "as_millis", rename = "duration_ms")]
+    pub duration: Duration,
+}
+
+fn as_millis<S: serde::Serializer>(duration: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_u64(duration.as_millis() as u64)
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+}
+
+/// Clone-safe audit log writer. Does nothing when no log is configured.
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    inner: Option<Arc<Mutex<Writer>>>,
+}
+
+impl AuditLog {
+    /// Open the log configured by `VISAGE_AUDIT_*`. A log that cannot be
+    /// opened is disabled with an error rather than stopping the daemon.
+    pub fn from_config(config: &Config) -> Self {
+        let Some(path) = &config.audit_log else {
+            return Self::default();
+        };
+        match Writer::open(path, config.audit_max_bytes, config.audit_keep) {
+            Ok(writer) => {
+                tracing::info!(path = %path.display(), "audit log open");
+                Self {
+                    inner: Some(Arc::new(Mutex::new(writer))),
+                }
+            }
+            Err(e) => {
+                tracing::error!(error = %e, path = %path.display(), "cannot open audit log; decisions are not audited");
+                Self::default()
+            }
+        }
+    }
+
+    /// Append `event`, stamped with the current time.
+    pub fn record(&self, event: &AuditEvent) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let line = Line {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            event,
+        };
+        let mut line = match serde_json::to_vec(&line) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!(error = %e, "audit: cannot encode record");
+                return;
+            }
+        };
+        line.push(b'\n');
+        let mut writer = inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = writer.append(&line) {
+            tracing::error!(error = %e, operation = event.operation, "audit: cannot write record");
+        }
+    }
+}
+
+/// The open log file and its rotation settings.
+struct Writer {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// Rotate before the file would grow past this; 0 never rotates.
+    max_bytes: u64,
+    /// Rotated files kept besides the current one (at least 1).
+    keep: u32,
+}
+
+impl Writer {
+    fn open(path: &Path, max_bytes: u64, keep: u32) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+        }
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            keep: keep.max(1),
+        })
+    }
+
+    fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.max_bytes > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// `audit.log` becomes `audit.log.1`, `.1` becomes `.2`, and so on; the
+    /// file past `keep` is overwritten.
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..self.keep).rev() {
+            let from = rotated(&self.path, n);
+            if from.exists() {
+                std::fs::rename(&from, rotated(&self.path, n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated(&self.path, 1))?;
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        tracing::info!(path = %self.path.display(), "audit log rotated");
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// `path` with `.n` appended.
+fn rotated(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(user: &str) -> AuditEvent {
+        AuditEvent {
+            operation: "verify",
+            tenant: String::new(),
+            user: user.to_string(),
+            result: "matched".to_string(),
+            similarity: Some(0.5),
+            model_id: Some("m1".to_string()),
+            frame: Some(FrameMeta {
+                sequence: 41,
+                timestamp_us: 5_120_334_012,
+                exposure: None,
+                night: false,
+                emitter: Some(vec![1, 3, 3, 0]),
+                precaptured: false,
+            }),
+            pad_score: Some(0.75),
+            caller_uid: Some(1000),
+            duration: Duration::from_millis(840),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("visage-audit-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn records_are_json_lines() {
+        let dir = temp_dir("lines");
+        let path = dir.join("audit.log");
+        let log = AuditLog {
+            inner: Some(Arc::new(Mutex::new(Writer::open(&path, 0, 1).unwrap()))),
+        };
+        log.record(&event("alice"));
+        log.record(&AuditEvent {
+            operation: "identify",
+            user: String::new(),
+            result: "no_face".to_string(),
+            similarity: None,
+            model_id: None,
+            frame: None,
+            pad_score: None,
+            caller_uid: None,
+            ..event("")
+        });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["user"], "alice");
+        assert_eq!(lines[0]["model_id"], "m1");
+        assert_eq!(lines[0]["frame"]["sequence"], 41);
+        assert_eq!(
+            lines[0]["frame"]["emitter"],
+            serde_json::json!([1, 3, 3, 0])
+        );
+        assert_eq!(lines[0]["pad_score"], 0.75);
+        assert_eq!(lines[0]["caller_uid"], 1000);
+        assert_eq!(lines[0]["duration_ms"], 840);
+        assert!(lines[0]["timestamp"].is_string());
+        assert!(text.starts_with("{\"timestamp\":"));
+        assert_eq!(lines[1]["caller_uid"], serde_json::Value::Null);
+        assert!(lines[1].get("similarity").is_none());
+        assert!(lines[1].get("frame").is_none());
+        assert!(lines[1].get("pad_score").is_none());
+
+        let mode = std::os::unix::fs::PermissionsExt::mode(
+            &std::fs::metadata(&path).unwrap().permissions(),
+        );
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_logs_rotate_and_keep_the_newest() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("audit.log");
+        let line = b"0123456789\n";
+        let mut writer = Writer::open(&path, 25, 2).unwrap();
+        for _ in 0..7 {
+            writer.append(line).unwrap();
+        }
+        let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).ok();
+        // Two lines per file: 3 rotations, the oldest pair dropped.
+        assert_eq!(size(&path), Some(11));
+        assert_eq!(size(&rotated(&path, 1)), Some(22));
+        assert_eq!(size(&rotated(&path, 2)), Some(22));
+        assert_eq!(size(&rotated(&path, 3)), None);
+
+        // Reopening continues the current file.
+        let mut writer = Writer::open(&path, 25, 2).unwrap();
+        writer.append(line).unwrap();
+        assert_eq!(size(&path), Some(22));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
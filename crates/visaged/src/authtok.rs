@@ -0,0 +1,73 @@
+//! Face-unlocked keyring — release a stored password after a face match.
+//!
+//! gnome-keyring and kwallet unlock at login with the password the user typed,
+//! which PAM hands along as `PAM_AUTHTOK`. A face login has no password, so
+//! the keyring stays locked. A user can store their login password with
+//! `SetAuthtok`, encrypted in the face database under the same key as the
+//! embeddings. A verify with the `release_authtok` option issues a
+//! short-lived ticket on a match, and `pam_visage.so authtok` redeems it with
+//! `ReleaseAuthtok` to set `PAM_AUTHTOK`.
+//!
+//! Both steps are root-only. A ticket is good for one release within
+//! [`TICKET_TTL`] of the match, so the secret never leaves the daemon
+//! without a verification just before it. Default tenant only.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long after a match its ticket can be redeemed.
+pub const TICKET_TTL: Duration = Duration::from_secs(10);
+
+/// Longest stored secret, in bytes.
+pub const MAX_SECRET_LEN: usize = 1024;
+
+/// One-shot release tickets by user, issued on a match.
+#[derive(Default)]
+pub struct AuthtokTickets {
+    issued: HashMap<String, Instant>,
+}
+
+impl AuthtokTickets {
+    /// Record a match for `user` that allows one release.
+    pub fn issue(&mut self, user: &str) {
+        let now = Instant::now();
+        self.issued
+            .retain(|_, at| now.duration_since(*at) < TICKET_TTL);
+        self.issued.insert(user.to_string(), now);
+    }
+
+    /// Consume `user`'s ticket; `true` if it was issued within [`TICKET_TTL`].
+    pub fn redeem(&mut self, user: &str) -> bool {
+        self.issued
+            .remove(user)
+            .is_some_and(|at| at.elapsed() < TICKET_TTL)
+    }
+
+    /// Whether a ticket issued within [`TICKET_TTL`] is still unredeemed.
+    pub fn pending(&self) -> bool {
+        self.issued.values().any(|at| at.elapsed() < TICKET_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tickets_are_single_use_and_per_user() {
+        let mut tickets = AuthtokTickets::default();
+        assert!(!tickets.redeem("alice"));
+        tickets.issue("alice");
+        assert!(tickets.pending());
+        assert!(!tickets.redeem("bob"));
+        assert!(tickets.redeem("alice"));
+        assert!(!tickets.redeem("alice"), "a ticket releases once");
+        assert!(!tickets.pending());
+
+        if let Some(stale) = Instant::now().checked_sub(TICKET_TTL + Duration::from_secs(1)) {
+            tickets.issued.insert("carol".into(), stale);
+            assert!(!tickets.pending(), "expired tickets are not pending");
+            assert!(!tickets.redeem("carol"), "expired ticket");
+        }
+    }
+}
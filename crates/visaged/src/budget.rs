@@ -0,0 +1,152 @@
+//! Per-request resource budgets — keep one bad capture from stalling logins.
+//!
+//! The engine serves one request at a time, so a frame that sends the
+//! detector or the preprocessing into a pathological case holds up every
+//! login queued behind it. A [`Meter`] is started for each enroll and verify;
+//! it measures wall time and the daemon's CPU time (which includes the
+//! inference thread pool) and is checked between pipeline stages. A request
+//! over either ceiling is aborted with [`EngineError::ResourceLimit`], logged
+//! as a warning, and counted in `Status`.
+//!
+//! A single inference call cannot be interrupted, so a request can overrun
+//! its budget by at most one stage.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use nix::time::{clock_gettime, ClockId};
+
+use crate::engine::EngineError;
+
+/// Requests aborted for exceeding their budget since the daemon started.
+static ABORTS: AtomicU64 = AtomicU64::new(0);
+
+/// Requests aborted for exceeding their budget since the daemon started.
+pub fn abort_count() -> u64 {
+    ABORTS.load(Ordering::Relaxed)
+}
+
+/// Ceilings for one engine request; `None` disables a ceiling.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestBudget {
+    pub max_cpu: Option<Duration>,
+    pub max_wall: Option<Duration>,
+}
+
+/// Resources used by a request so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub cpu: Duration,
+    pub wall: Duration,
+}
+
+impl RequestBudget {
+    /// The first ceiling `usage` exceeds, as `(resource, used, limit)`.
+    fn exceeded(&self, usage: Usage) -> Option<(&'static str, Duration, Duration)> {
+        let over = |used: Duration, limit: Option<Duration>| limit.filter(|l| used > *l);
+        if let Some(limit) = over(usage.cpu, self.max_cpu) {
+            return Some(("cpu", usage.cpu, limit));
+        }
+        over(usage.wall, self.max_wall).map(|limit| ("wall", usage.wall, limit))
+    }
+}
+
+/// Measures one request against a [`RequestBudget`].
+pub struct Meter {
+    kind: &'static str,
+    budget: RequestBudget,
+    started: Instant,
+    cpu_started: Duration,
+}
+
+impl Meter {
+    /// Start measuring a request of `kind` (`"enroll"` or `"verify"`).
+    pub fn start(kind: &'static str, budget: RequestBudget) -> Self {
+        Self {
+            kind,
+            budget,
+            started: Instant::now(),
+            cpu_started: process_cpu_time(),
+        }
+    }
+
+    pub fn usage(&self) -> Usage {
+        Usage {
+            cpu: process_cpu_time().saturating_sub(self.cpu_started),
+            wall: self.started.elapsed(),
+        }
+    }
+
+    /// Whether the request is over budget. Cheap enough to poll per frame.
+    pub fn over_budget(&self) -> bool {
+        self.budget.exceeded(self.usage()).is_some()
+    }
+
+    /// `Err` once the request is over budget; `stage` names the work just
+    /// finished, for the log.
+    pub fn check(&self, stage: &str) -> Result<(), EngineError> {
+        let Some((resource, used, limit)) = self.budget.exceeded(self.usage()) else {
+            return Ok(());
+        };
+        ABORTS.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            request = self.kind,
+            stage,
+            resource,
+            used_ms = used.as_millis() as u64,
+            limit_ms = limit.as_millis() as u64,
+            "request exceeded its resource budget; aborting"
+        );
+        Err(EngineError::ResourceLimit {
+            resource,
+            used_ms: used.as_millis() as u64,
+            limit_ms: limit.as_millis() as u64,
+        })
+    }
+
+    /// Log what the finished request used.
+    pub fn finish(&self) {
+        let usage = self.usage();
+        tracing::debug!(
+            request = self.kind,
+            cpu_ms = usage.cpu.as_millis() as u64,
+            wall_ms = usage.wall.as_millis() as u64,
+            "request resource usage"
+        );
+    }
+}
+
+/// CPU time consumed by the whole daemon; zero if the clock is unavailable,
+/// which leaves only the wall-time ceiling in force.
+fn process_cpu_time() -> Duration {
+    clock_gettime(ClockId::CLOCK_PROCESS_CPUTIME_ID)
+        .map(Duration::from)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_exceeded_ceiling_is_reported() {
+        let budget = RequestBudget {
+            max_cpu: Some(Duration::from_millis(500)),
+            max_wall: Some(Duration::from_secs(2)),
+        };
+        let usage = |cpu, wall| Usage {
+            cpu: Duration::from_millis(cpu),
+            wall: Duration::from_millis(wall),
+        };
+        assert_eq!(budget.exceeded(usage(400, 1_900)), None);
+        assert_eq!(
+            budget.exceeded(usage(600, 2_100)).map(|(r, _, _)| r),
+            Some("cpu")
+        );
+        assert_eq!(
+            budget.exceeded(usage(100, 2_100)).map(|(r, _, _)| r),
+            Some("wall")
+        );
+        assert_eq!(RequestBudget::default().exceeded(usage(u64::MAX, 0)), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Export bundles — a user's face models sealed with a passphrase, for
+//! moving them to another machine or restoring them after a reinstall
+//! without enrolling again.
+//!
+//! The templates in the database are sealed with the installation's key
+//! (`.key` next to the database), which never leaves the machine, so a
+//! bundle is sealed afresh: AES-256-GCM under a key derived from a
+//! passphrase with Argon2id. The layout is
+//!
+//! ```text
+//! "VISAGE-BUNDLE" | format (u8) | m_cost, t_cost, p_cost (u32 LE) | salt (16) | nonce (12) | ciphertext + tag
+//! ```
+//!
+//! with everything before the ciphertext authenticated as associated data,
+//! so the Argon2 parameters travel with the bundle and a newer release can
+//! raise them. The plaintext is the JSON [`Contents`]: each model's
+//! embedding, label, pose, quality, threshold offset, enrollment and last
+//! match time, as [`ExportedModel`]. A wrong passphrase and a damaged bundle
+//! fail alike, with [`BundleError::Sealed`].
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use visage_core::{ErrorKind, VisageError};
+
+use crate::store::ExportedModel;
+
+const MAGIC: &[u8] = b"VISAGE-BUNDLE";
+/// Layout version written after the magic; see the module docs.
+pub const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Largest bundle accepted, well above any real gallery.
+pub const MAX_BUNDLE_LEN: usize = 16 << 20;
+/// Shortest passphrase an export accepts.
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+/// Longest passphrase either direction accepts.
+pub const MAX_PASSPHRASE_LEN: usize = 1024;
+
+/// Argon2 memory cost (KiB) a bundle may ask for; more is refused rather
+/// than letting a crafted bundle exhaust the daemon's memory.
+const MAX_M_COST: u32 = 1 << 20;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+#[derive(Error, Debug)]
+pub enum BundleError {
+    #[error("not a Visage export bundle")]
+    NotABundle,
+    #[error("export bundle format {0} is newer than this visaged supports ({FORMAT_VERSION})")]
+    UnsupportedFormat(u8),
+    #[error("passphrase must be {MIN_PASSPHRASE_LEN}-{MAX_PASSPHRASE_LEN} bytes")]
+    Passphrase,
+    #[error("export bundle is larger than {MAX_BUNDLE_LEN} bytes")]
+    TooLarge,
+    #[error("unusable key derivation parameters: {0}")]
+    Kdf(String),
+    #[error("wrong passphrase, or the bundle is damaged")]
+    Sealed,
+    #[error("bundle encryption failed")]
+    EncryptionFailed,
+    #[error("bundle contents: {0}")]
+    Contents(#[from] serde_json::Error),
+}
+
+impl BundleError {
+    /// The shared [`ErrorKind`] of this error: the bundle or passphrase the
+    /// caller supplied is at fault ([`ErrorKind::InvalidArgument`]) unless
+    /// sealing itself failed ([`ErrorKind::Internal`]).
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BundleError::EncryptionFailed => ErrorKind::Internal,
+            _ => ErrorKind::InvalidArgument,
+        }
+    }
+}
+
+impl From<BundleError> for VisageError {
+    fn from(e: BundleError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// What a bundle holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contents {
+    /// The account the models were exported from; an import may store them
+    /// under another.
+    pub user: String,
+    /// RFC 3339.
+    pub exported_at: String,
+    /// Version of the visaged that wrote the bundle.
+    pub visage_version: String,
+    pub models: Vec<ExportedModel>,
+}
+
+/// Seal `contents` under `passphrase` with the default Argon2id parameters.
+pub fn seal(contents: &Contents, passphrase: &str) -> Result<Vec<u8>, BundleError> {
+    if !(MIN_PASSPHRASE_LEN..=MAX_PASSPHRASE_LEN).contains(&passphrase.len()) {
+        return Err(BundleError::Passphrase);
+    }
+    seal_with(contents, passphrase, &Params::default())
+}
+
+fn seal_with(
+    contents: &Contents,
+    passphrase: &str,
+    params: &Params,
+) -> Result<Vec<u8>, BundleError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut bundle = Vec::with_capacity(HEADER_LEN);
+    bundle.extend_from_slice(MAGIC);
+    bundle.push(FORMAT_VERSION);
+    for cost in [params.m_cost(), params.t_cost(), params.p_cost()] {
+        bundle.extend_from_slice(&cost.to_le_bytes());
+    }
+    bundle.extend_from_slice(&salt);
+    bundle.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, params.clone())?;
+    let plaintext = serde_json::to_vec(contents)?;
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &bundle,
+            },
+        )
+        .map_err(|_| BundleError::EncryptionFailed)?;
+    bundle.extend_from_slice(&ciphertext);
+    Ok(bundle)
+}
+
+/// Check and decrypt a bundle written by [`seal`].
+pub fn open(bundle: &[u8], passphrase: &str) -> Result<Contents, BundleError> {
+    if bundle.len() > MAX_BUNDLE_LEN {
+        return Err(BundleError::TooLarge);
+    }
+    if passphrase.is_empty() || passphrase.len() > MAX_PASSPHRASE_LEN {
+        return Err(BundleError::Passphrase);
+    }
+    if bundle.len() < HEADER_LEN || !bundle.starts_with(MAGIC) {
+        return Err(BundleError::NotABundle);
+    }
+    let (header, ciphertext) = bundle.split_at(HEADER_LEN);
+    let format = header[MAGIC.len()];
+    if format != FORMAT_VERSION {
+        return Err(BundleError::UnsupportedFormat(format));
+    }
+    let mut fields = header[MAGIC.len() + 1..].chunks_exact(4);
+    let mut cost = || {
+        let bytes = fields.next().expect("header holds three costs");
+        u32::from_le_bytes(bytes.try_into().expect("chunks of four"))
+    };
+    let (m_cost, t_cost, p_cost) = (cost(), cost(), cost());
+    if m_cost > MAX_M_COST || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err(BundleError::Kdf(format!(
+            "m={m_cost} t={t_cost} p={p_cost} exceeds the limits"
+        )));
+    }
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| BundleError::Kdf(e.to_string()))?;
+    let salt_at = MAGIC.len() + 1 + 3 * 4;
+    let salt = &header[salt_at..salt_at + SALT_LEN];
+    let nonce = &header[salt_at + SALT_LEN..];
+
+    let key = derive_key(passphrase, salt, params)?;
+    let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| BundleError::Sealed)?;
+    // Authenticated, so not damaged: contents that do not parse were
+    // written wrongly, not tampered with.
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// The AES key for `passphrase` and `salt`.
+fn derive_key(passphrase: &str, salt: &[u8], params: Params) -> Result<[u8; 32], BundleError> {
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| BundleError::Kdf(e.to_string()))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters: the default ones take seconds in a debug build.
+    fn params() -> Params {
+        Params::new(64, 1, 1, Some(32)).unwrap()
+    }
+
+    fn contents() -> Contents {
+        Contents {
+            user: "alice".into(),
+            exported_at: "2026-01-01T00:00:00+00:00".into(),
+            visage_version: "0.0.0".into(),
+            models: vec![ExportedModel {
+                label: "glasses".into(),
+                model_version: "w600k_r50".into(),
+                embedding: vec![0.25; 512],
+                quality_score: 0.9,
+                pose_label: "frontal".into(),
+                threshold_offset: 0.05,
+                created_at: "2025-06-01T00:00:00+00:00".into(),
+                last_matched_at: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trips_under_the_passphrase() {
+        let bundle = seal_with(&contents(), "correct horse", &params()).unwrap();
+        assert!(bundle.starts_with(MAGIC));
+        assert_eq!(open(&bundle, "correct horse").unwrap(), contents());
+        assert!(matches!(
+            open(&bundle, "wrong horse"),
+            Err(BundleError::Sealed)
+        ));
+    }
+
+    #[test]
+    fn header_is_authenticated() {
+        let bundle = seal_with(&contents(), "correct horse", &params()).unwrap();
+        // A salt bit flipped still derives a key, just the wrong one.
+        let mut tampered = bundle.clone();
+        tampered[MAGIC.len() + 1 + 12] ^= 1;
+        assert!(matches!(
+            open(&tampered, "correct horse"),
+            Err(BundleError::Sealed)
+        ));
+
+        let mut newer = bundle.clone();
+        newer[MAGIC.len()] = FORMAT_VERSION + 1;
+        assert!(matches!(
+            open(&newer, "correct horse"),
+            Err(BundleError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            open(b"P5\n3 2\n255\n", "correct horse"),
+            Err(BundleError::NotABundle)
+        ));
+        assert!(matches!(
+            seal(&contents(), "short"),
+            Err(BundleError::Passphrase)
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Camera fallback list — `VISAGE_CAMERA_DEVICE` as a prioritized list.
+//!
+//! Docks and USB hubs shuffle `/dev/videoN` numbering, so the camera may be
+//! configured as several comma-separated entries, most preferred first:
+//!
+//! ```text
+//! camera_device = "/dev/v4l/by-id/usb-Chicony*-video-index0,/dev/video2,/dev/video0"
+//! ```
+//!
+//! An entry may use `*` and `?` in its last path component; it stands for the
+//! matching devices in that directory, in name order. Entries are expanded
+//! each time the engine opens the camera — at startup and whenever the one in
+//! use fails or goes away — so a device that appeared since counts. The
+//! engine takes the first device that opens and shows a lit frame (see
+//! `engine::open_first`).
+
+use std::path::Path;
+
+/// The entries of a configured camera list, in order. Empty entries are
+/// dropped.
+pub fn parse(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Whether `entry` is a pattern rather than one device path.
+pub fn is_pattern(entry: &str) -> bool {
+    entry.contains(['*', '?'])
+}
+
+/// The device paths `entries` stand for now, in priority order and without
+/// duplicates. A pattern that matches nothing contributes nothing.
+pub fn expand(entries: &[String]) -> Vec<String> {
+    let mut devices: Vec<String> = Vec::new();
+    for entry in entries {
+        let matches = if is_pattern(entry) {
+            glob(entry)
+        } else {
+            vec![entry.clone()]
+        };
+        for device in matches {
+            if !devices.contains(&device) {
+                devices.push(device);
+            }
+        }
+    }
+    devices
+}
+
+/// The paths in the directory of `pattern` whose names match its last
+/// component.
+fn glob(pattern: &str) -> Vec<String> {
+    let path = Path::new(pattern);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|candidate| wildcard_match(name, candidate))
+        })
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Shell-style match of `name` against `pattern`: `*` is any run of
+/// characters, `?` any one character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of `name` it has taken so far.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_split_on_commas() {
+        assert_eq!(parse("/dev/video2"), vec!["/dev/video2"]);
+        assert_eq!(
+            parse(" /dev/video2, /dev/video0 ,,"),
+            vec!["/dev/video2", "/dev/video0"]
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn wildcards_match_like_a_shell() {
+        assert!(wildcard_match(
+            "usb-Chicony*-video-index0",
+            "usb-Chicony_IR_Camera-video-index0"
+        ));
+        assert!(!wildcard_match(
+            "usb-Chicony*-video-index0",
+            "usb-Chicony_IR_Camera-video-index1"
+        ));
+        assert!(wildcard_match("video?", "video2"));
+        assert!(!wildcard_match("video?", "video12"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXbYbZc"));
+        assert!(!wildcard_match("a*b", "aXbY"));
+    }
+
+    #[test]
+    fn patterns_expand_in_order_without_duplicates() {
+        let dir = std::env::temp_dir().join(format!("visage-cameras-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["cam-b", "cam-a", "other"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let d = dir.display();
+        let entries = parse(&format!("{d}/cam-b,{d}/cam-*,{d}/missing-*,/dev/video0"));
+        assert_eq!(
+            expand(&entries),
+            vec![
+                format!("{d}/cam-b"),
+                format!("{d}/cam-a"),
+                "/dev/video0".to_string()
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+//! Re-verification challenge — a stricter verify after suspicious matches.
+//!
+//! A match that only just clears the threshold is what a good look-alike, a
+//! printed photo or a mask tends to produce. One such *narrow* match means
+//! little — bad light does the same — but several in a short time, at an hour
+//! the machine is not normally used, are worth a second look. With
+//! `VISAGE_CHALLENGE_NARROW_MATCHES` set, the daemon counts, per user, matches
+//! scoring less than `VISAGE_CHALLENGE_MARGIN` above the threshold. Only those
+//! in `VISAGE_CHALLENGE_HOURS` count, if set. When that many fall within
+//! `VISAGE_CHALLENGE_WINDOW_SECS`, the user's next verify is a challenge:
+//!
+//! - liveness is checked even if `VISAGE_LIVENESS_ENABLED=0` (when built in);
+//! - the threshold is raised by `VISAGE_CHALLENGE_THRESHOLD_BOOST`;
+//! - the burst captures twice the frames.
+//!
+//! The match that triggers the challenge still succeeds: it met the
+//! configured policy. The user is told with a `ChallengeRequired` signal
+//! (shown by `visage notify`) and a `challenge_required` hook. The challenge
+//! holds until a verify passes under the strict settings; failures are
+//! rate-limited as usual. Challenges live in memory, so the idle exit waits
+//! while one is pending.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::night;
+
+/// The configured rules; see `Config::challenge_policy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChallengePolicy {
+    /// Narrow matches within `window` that trigger a challenge; 0 disables.
+    pub narrow_matches: u32,
+    /// A match scoring less than this above the threshold is narrow.
+    pub margin: f32,
+    pub window: Duration,
+    /// Local hours `[start, end)` in which narrow matches count; every hour
+    /// when `None`.
+    pub hours: Option<(u32, u32)>,
+    /// Added to the threshold of a challenged verify.
+    pub threshold_boost: f32,
+}
+
+impl ChallengePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.narrow_matches > 0
+    }
+
+    /// Whether a match at `similarity` against `threshold`, at local `hour`,
+    /// counts toward a challenge.
+    fn counts(&self, similarity: f32, threshold: f32, hour: u32) -> bool {
+        similarity < threshold + self.margin
+            && !matches!(self.hours, Some(hours) if !night::in_hours(hours, hour))
+    }
+}
+
+/// Recent narrow matches and pending challenges, by rate-limiter key
+/// (tenant and user).
+#[derive(Debug, Default)]
+pub struct Challenges {
+    narrow: HashMap<String, VecDeque<Instant>>,
+    pending: HashMap<String, Instant>,
+}
+
+impl Challenges {
+    /// Whether the next verify of `key` is a challenge.
+    pub fn is_pending(&self, key: &str) -> bool {
+        self.pending.contains_key(key)
+    }
+
+    /// Whether any challenge is waiting for its user.
+    pub fn any_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Note a match of `key` at `similarity` against the configured
+    /// `threshold`, at local `hour`. Returns the number of narrow matches
+    /// when this one makes the next verify a challenge.
+    pub fn record_match(
+        &mut self,
+        policy: &ChallengePolicy,
+        key: &str,
+        similarity: f32,
+        threshold: f32,
+        hour: u32,
+        now: Instant,
+    ) -> Option<u32> {
+        if !policy.is_enabled()
+            || self.is_pending(key)
+            || !policy.counts(similarity, threshold, hour)
+        {
+            return None;
+        }
+        let recent = self.narrow.entry(key.to_string()).or_default();
+        recent.retain(|&at| now.duration_since(at) < policy.window);
+        recent.push_back(now);
+        let count = u32::try_from(recent.len()).unwrap_or(u32::MAX);
+        if count < policy.narrow_matches {
+            return None;
+        }
+        self.narrow.remove(key);
+        self.pending.insert(key.to_string(), now);
+        Some(count)
+    }
+
+    /// A challenged verify of `key` passed: back to the normal settings.
+    /// Returns how long the challenge was pending.
+    pub fn passed(&mut self, key: &str, now: Instant) -> Option<Duration> {
+        self.pending
+            .remove(key)
+            .map(|since| now.duration_since(since))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ChallengePolicy {
+        ChallengePolicy {
+            narrow_matches: 2,
+            margin: 0.05,
+            window: Duration::from_secs(3600),
+            hours: None,
+            threshold_boost: 0.05,
+        }
+    }
+
+    #[test]
+    fn repeated_narrow_matches_trigger_a_challenge() {
+        let (policy, mut challenges, t0) = (policy(), Challenges::default(), Instant::now());
+        let later = |secs| t0 + Duration::from_secs(secs);
+
+        // A clear match does not count; narrow ones too far apart do not add up.
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.70, 0.40, 12, t0),
+            None
+        );
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.42, 0.40, 12, t0),
+            None
+        );
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.43, 0.40, 12, later(4000)),
+            None
+        );
+        assert_eq!(
+            challenges.record_match(&policy, "bob", 0.43, 0.40, 12, later(4001)),
+            None
+        );
+        assert!(!challenges.any_pending());
+
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.41, 0.40, 12, later(4100)),
+            Some(2)
+        );
+        assert!(challenges.is_pending("alice") && !challenges.is_pending("bob"));
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.41, 0.40, 12, later(4200)),
+            None
+        );
+
+        assert_eq!(
+            challenges.passed("alice", later(4300)),
+            Some(Duration::from_secs(200))
+        );
+        assert_eq!(challenges.passed("alice", later(4300)), None);
+        assert!(!challenges.any_pending());
+    }
+
+    #[test]
+    fn only_narrow_matches_in_the_hours_count() {
+        let policy = ChallengePolicy {
+            narrow_matches: 1,
+            hours: Some((23, 6)),
+            ..policy()
+        };
+        let (mut challenges, now) = (Challenges::default(), Instant::now());
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.41, 0.40, 14, now),
+            None
+        );
+        assert_eq!(
+            challenges.record_match(&policy, "alice", 0.41, 0.40, 3, now),
+            Some(1)
+        );
+
+        let off = ChallengePolicy {
+            narrow_matches: 0,
+            ..policy
+        };
+        assert_eq!(
+            challenges.record_match(&off, "bob", 0.41, 0.40, 3, now),
+            None
+        );
+    }
+}
@@ -1,9 +1,79 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-/// Daemon configuration, loaded from environment variables.
+use crate::config_file::Settings;
+
+/// Largest `VISAGE_CONFIRM_MARGIN`. Each confirmation is a second comparison
+/// against the threshold, so a wide band would double an impostor's chances
+/// for every rejection instead of only the near misses.
+pub const MAX_CONFIRM_MARGIN: f32 = 0.10;
+
+/// Largest `VISAGE_PRECAPTURE_SECS`. The camera (and the IR emitter) stay on
+/// for the whole window, so it is meant to cover a retry, not an idle desk.
+pub const MAX_PRECAPTURE_SECS: u64 = 30;
+
+/// Largest `VISAGE_CHALLENGE_THRESHOLD_BOOST`. Above it even the genuine
+/// user rarely scores, and a challenge would lock them out for good.
+pub const MAX_CHALLENGE_THRESHOLD_BOOST: f32 = 0.20;
+
+/// Largest `VISAGE_KEEP_PROBES`. Probes are biometric data like templates;
+/// a few dozen recent verifies are enough to tune a threshold.
+pub const MAX_KEEP_PROBES: usize = 100;
+
+/// Largest `VISAGE_FAILURE_SNAPSHOTS`. Snapshots are face images; a few
+/// dozen recent failures show a pattern.
+pub const MAX_FAILURE_SNAPSHOTS: usize = 100;
+
+/// Outcome of [`Config::apply_reload`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reload {
+    /// Settings that now have a new value.
+    pub changed: Vec<&'static str>,
+    /// Whether other settings changed too, which take effect only after a
+    /// restart.
+    pub restart_needed: bool,
+}
+
+/// Greeter accounts of the common display managers (GDM, LightDM, SDDM).
+const DEFAULT_IDENTIFY_CALLERS: &str = "gdm,gdm-greeter,lightdm,sddm";
+
+/// Daemon configuration, loaded from the config files and environment
+/// variables.
+#[derive(Debug, PartialEq)]
 pub struct Config {
-    /// V4L2 device path (default: /dev/video2).
+    /// Instance name from `VISAGE_INSTANCE`; `None` for the default
+    /// instance. See `instance`.
+    pub instance: Option<String>,
+    /// V4L2 device path (default: /dev/video2, or `/dev/INSTANCE` for an
+    /// instance), or a comma-separated list of paths and patterns in order
+    /// of preference; see `camera_list`.
     pub camera_device: String,
+    /// Colour camera of IR + RGB mode: an IR match must also be confirmed by
+    /// a face on this stream (see `fusion`). `auto` pairs the colour node of
+    /// the IR camera's device, if any. Unset disables the mode.
+    pub rgb_camera_device: Option<String>,
+    /// Highest IR/RGB correlation of a matched face before it counts as a
+    /// flat print or screen; 1 disables the comparison.
+    pub rgb_max_correlation: f32,
+    /// Depth stream of a depth-capable camera: a matched face must not be
+    /// flat (see `depth`). Unset disables the check.
+    pub depth_camera_device: Option<String>,
+    /// Minimum relief (mm) of the face region out of its best-fit plane.
+    pub depth_min_relief_mm: f32,
+    /// Anti-spoofing ONNX model run on a matched face (see
+    /// `visage_core::pad`); relative to `model_dir` unless absolute. Unset
+    /// disables the check.
+    pub pad_model: Option<PathBuf>,
+    /// Lowest live probability the PAD model may give a matched face.
+    pub pad_threshold: f32,
+    /// Directory of per-device calibration files written by `visage calibrate`
+    /// (default: /var/lib/visage/calibration).
+    pub calibration_dir: PathBuf,
+    /// Directories of local quirk files, read in order over the built-in
+    /// quirks (default: /etc/visage/quirks.d); see `quirk_reload`.
+    pub quirks_dirs: Vec<PathBuf>,
+    /// Reload the quirks when a file in `quirks_dirs` changes.
+    pub quirks_watch: bool,
     /// Directory containing ONNX model files.
     pub model_dir: PathBuf,
     /// Path to the SQLite database file.
@@ -12,12 +82,61 @@ pub struct Config {
     pub similarity_threshold: f32,
     /// Timeout in seconds for a verify operation.
     pub verify_timeout_secs: u64,
-    /// Number of warmup frames to discard at startup (camera AGC/AE stabilization).
+    /// Number of frames to discard at the start of every capture stream
+    /// (camera AGC/AE stabilization).
     pub warmup_frames: usize,
     /// Number of frames to capture per verify attempt.
     pub frames_per_verify: usize,
     /// Number of frames to capture per enroll attempt.
     pub frames_per_enroll: usize,
+    /// Verify latency target in milliseconds; when non-zero the verify burst
+    /// size adapts to this machine (see `latency`) instead of using
+    /// `frames_per_verify`. 0 disables it.
+    pub verify_latency_ms: u64,
+    /// Seconds the camera keeps streaming into the pre-capture buffer after
+    /// a verify, so a retry starts on frames already captured (see
+    /// `engine`). 0 disables it.
+    pub precapture_secs: u64,
+    /// Width of the band below `similarity_threshold` in which a rejected
+    /// verify captures one confirmation burst before deciding (see
+    /// [`Config::is_borderline`]). 0.0 disables it; capped at
+    /// [`MAX_CONFIRM_MARGIN`].
+    pub confirm_margin: f32,
+    /// Local hours during which every capture uses the night profile, from
+    /// `VISAGE_NIGHT_HOURS=START-END` (e.g. `20-7`). Unset disables the clock.
+    pub night_hours: Option<(u32, u32)>,
+    /// Mean brightness (0–255) of the first frame below which a capture
+    /// switches to the night profile. 0.0 disables the check.
+    pub night_brightness: f32,
+    /// CLAHE clip limit of the night profile.
+    pub night_clahe_clip: f32,
+    /// Manual exposure of the night profile, in the driver's
+    /// `exposure_absolute` units; unset keeps auto exposure.
+    pub night_exposure: Option<i64>,
+    /// Frames added to a burst that runs with the night profile.
+    pub night_extra_frames: usize,
+    /// Warmup frames of a burst that starts on the night profile; `None`
+    /// keeps `warmup_frames`.
+    pub night_warmup_frames: Option<usize>,
+    /// Verifies in a row that must match only after a mid-burst switch to the
+    /// night profile before bursts start on it (see `night::NightLearner`).
+    /// 0 disables learning.
+    pub night_learn_after: u32,
+    /// Ambient light (lux) below which a capture starts on the night profile.
+    /// 0.0 leaves the ambient light sensor unused.
+    pub als_dark_lux: f32,
+    /// Minimum frame sharpness (mean absolute Laplacian) for a frame to reach
+    /// the detector. 0.0 disables the check.
+    pub min_frame_sharpness: f32,
+    /// Maximum mean absolute difference from the previous frame before a frame
+    /// is dropped as moving / still ramping exposure. 0.0 disables the check.
+    pub max_frame_motion: f32,
+    /// Minimum largest inter-frame difference across a verify burst; a burst
+    /// that stays below it is rejected as a replayed still. 0.0 disables the check.
+    pub min_scene_delta: f32,
+    /// Whether a virtual camera (v4l2loopback, vivid, …) may be used. Off by
+    /// default — anything can feed frames into one. For testing only.
+    pub allow_virtual_camera: bool,
     /// Whether to activate the IR emitter around each capture sequence.
     pub emitter_enabled: bool,
     /// Whether passive liveness detection (landmark stability) is enabled.
@@ -26,17 +145,169 @@ pub struct Config {
     /// Lower values are more permissive; higher values reject more aggressively.
     /// Only used when `liveness_enabled` is true.
     pub liveness_min_displacement: f32,
+    /// Whether the `IdentifyAny` method (1:N match across all enrolled users) is
+    /// enabled. Off by default — identification is a greeter feature and must be
+    /// opted into explicitly.
+    pub identify_enabled: bool,
+    /// Accounts (besides root) allowed to call `IdentifyAny` — the display
+    /// manager greeter users.
+    pub identify_callers: Vec<String>,
+    /// Similarity threshold for `IdentifyAny`. Never applied below
+    /// `similarity_threshold` — see [`Config::identify_threshold`].
+    pub identify_similarity_threshold: f32,
+    /// Minimum lead of the best user's similarity over any other user's for
+    /// `IdentifyAny` to return a match.
+    pub identify_margin: f32,
+    /// Gallery size from which `IdentifyAny` shortlists candidates with an
+    /// HNSW index instead of scanning every model. 0 disables the index.
+    pub identify_index_min_models: usize,
+    /// Minimum seconds between index rebuilds. Until a rebuild is due, a
+    /// changed gallery is scanned exhaustively.
+    pub identify_index_rebuild_secs: u64,
+    /// A model whose median similarity to the user's other models is below
+    /// this is flagged as a possible poisoned or corrupted template; see
+    /// `consistency`. 0 disables the check.
+    pub gallery_outlier_similarity: f32,
+    /// Narrow matches within `challenge_window_secs` after which a user's
+    /// next verify is a challenge; see `challenge`. 0 disables challenges.
+    pub challenge_narrow_matches: u32,
+    /// A match scoring less than this above the threshold is narrow.
+    pub challenge_margin: f32,
+    pub challenge_window_secs: u64,
+    /// Local hours in which narrow matches count, from
+    /// `VISAGE_CHALLENGE_HOURS=START-END`; unset counts every hour.
+    pub challenge_hours: Option<(u32, u32)>,
+    /// Added to the threshold of a challenged verify; capped at
+    /// [`MAX_CHALLENGE_THRESHOLD_BOOST`].
+    pub challenge_threshold_boost: f32,
+    /// Probe embeddings kept per user for `visage export-scores`, newest
+    /// first; 0 keeps none. Capped at [`MAX_KEEP_PROBES`]. See `scores`.
+    pub keep_probes: usize,
+    /// Frames of failed verifies kept per user, newest first; 0 keeps none.
+    /// Capped at [`MAX_FAILURE_SNAPSHOTS`]. See `snapshots`.
+    pub failure_snapshots: usize,
+    /// Days a failure snapshot is kept; 0 keeps it until newer ones push it
+    /// out.
+    pub failure_snapshot_days: u64,
+    /// Directory for failure snapshots (default: `failures/` next to the
+    /// database).
+    pub failure_dir: PathBuf,
+    /// Days a near-miss record is kept after its last update; 0 keeps them.
+    /// See `retention`.
+    pub retain_history_days: u64,
+    /// Months (of 30 days) after which a model no verify or identification
+    /// has matched is purged; 0 never purges.
+    pub purge_unused_months: u64,
+    /// Days between the notice that a model will be purged and the purge.
+    pub purge_notice_days: u64,
+    /// Named tenants (segregated galleries, e.g. one per kiosk location) and
+    /// the accounts besides root allowed to use each. A tenant not listed here
+    /// cannot be used; the default tenant is always available.
+    pub tenants: BTreeMap<String, Vec<String>>,
+    /// Script run on daemon events with the event JSON on stdin; see `hooks`.
+    pub hook_command: Option<PathBuf>,
+    /// Unprivileged account the hook script runs as. Never root.
+    pub hook_user: String,
+    /// Unix stream socket that receives one JSON line per event.
+    pub hook_socket: Option<PathBuf>,
+    /// Event names delivered to hooks; empty means all.
+    pub hook_events: Vec<String>,
+    /// Seconds a hook delivery may take before it is abandoned.
+    pub hook_timeout_secs: u64,
+    /// JSONL record of every biometric decision; see `audit`. Off when
+    /// `VISAGE_AUDIT_LOG` is empty, and by default on the session bus.
+    pub audit_log: Option<PathBuf>,
+    /// Size at which the audit log is rotated; 0 never rotates.
+    pub audit_max_bytes: u64,
+    /// Rotated audit logs kept (at least 1).
+    pub audit_keep: u32,
+    /// `AF_UNIX` socket serving verify requests without a D-Bus broker
+    /// (early boot); see `socket`. Off when unset.
+    pub socket_path: Option<PathBuf>,
+    /// vsock port serving verify requests to guest VMs; see `remote`. Off
+    /// when unset.
+    pub vsock_port: Option<u32>,
+    /// Loopback address serving token-authenticated verify requests to
+    /// containers; see `remote`. Off when unset.
+    pub tcp_addr: Option<std::net::SocketAddr>,
+    /// File holding the secret TCP clients must present.
+    pub tcp_token_file: Option<PathBuf>,
+    /// Accounts each vsock guest (`cid:N`) or TCP client (`tcp`) may verify.
+    pub remote_users: crate::remote::RemotePolicy,
+    /// Seconds with nothing to do after which the daemon exits, for a daemon
+    /// started on demand by D-Bus activation; see `idle`. 0 (the default)
+    /// keeps it running.
+    pub idle_exit_secs: u64,
+    /// Loopback address for the HTTP `/healthz` endpoint (`healthz` feature).
+    pub healthz_addr: Option<std::net::SocketAddr>,
+    /// Loopback address for the HTTP `/metrics` endpoint (`metrics` feature).
+    pub metrics_addr: Option<std::net::SocketAddr>,
+    /// node_exporter textfile the metrics are written to (`metrics` feature).
+    pub metrics_textfile: Option<PathBuf>,
+    /// CPUs the engine and its inference threads are pinned to; see
+    /// `scheduling`. Empty (the default) leaves them unpinned.
+    pub engine_cpus: Vec<usize>,
+    /// Nice value of the engine, -20..=19.
+    pub engine_nice: Option<i32>,
+    /// `SCHED_RR` priority of the engine, 1..=99; overrides `engine_nice`.
+    pub engine_rt_priority: Option<i32>,
+    /// CPU-time ceiling for one enroll or verify, in milliseconds; 0 disables it.
+    pub max_request_cpu_ms: u64,
+    /// Wall-time ceiling for one enroll or verify, in milliseconds; 0 disables it.
+    pub max_request_wall_ms: u64,
+    /// Answer only verifies whose caller declares the match one factor of
+    /// two (the `second_factor` option, sent by pam_visage `second_factor`),
+    /// so a face never authenticates alone. Off by default.
+    pub require_second_factor: bool,
+    /// Reproducibility mode: inference settings that vary run to run or
+    /// machine to machine are fixed (see `visage_core::inference`) and logged
+    /// at startup. Off by default — slower; for accuracy investigations.
+    pub reproducible: bool,
+    /// Where `DebugProfile` writes ONNX Runtime profiles; `DebugProfile` is
+    /// refused when unset (the default).
+    pub profile_dir: Option<PathBuf>,
+    /// Directory for panic reports (default: `crashes/` next to the database).
+    pub crash_dir: PathBuf,
     /// Whether the daemon is running on the session bus (development mode).
     /// UID validation is skipped on the session bus — all callers share the same user.
     pub session_bus: bool,
 }
 
 impl Config {
-    /// Load configuration from `VISAGE_*` environment variables with defaults.
-    pub fn from_env() -> Self {
-        let model_dir = std::env::var("VISAGE_MODEL_DIR")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| visage_core::default_model_dir());
+    /// Load configuration from the config files (see `config_file`) and
+    /// `VISAGE_*` environment variables, which override them, with defaults
+    /// for the rest. `Err` when a config file is untrusted or malformed.
+    pub fn load() -> Result<Self, String> {
+        let settings = Settings::load()?;
+        let mut config = Self::from_settings(&settings);
+        for (file, key) in settings.unknown() {
+            tracing::warn!(file = %file.display(), key, "ignoring unknown setting");
+        }
+        if !cfg!(feature = "liveness") {
+            if config.depth_camera_device.is_some() {
+                return Err(
+                    "VISAGE_DEPTH_CAMERA_DEVICE needs a visaged built with the `liveness` feature"
+                        .into(),
+                );
+            }
+            if config.liveness_enabled {
+                tracing::warn!("visaged built without the `liveness` feature; liveness is off");
+                config.liveness_enabled = false;
+            }
+        }
+        Ok(config)
+    }
+
+    /// The defaults, with no config file or environment; for tests.
+    #[cfg(test)]
+    pub fn defaults() -> Self {
+        Self::from_settings(&Settings::default())
+    }
+
+    fn from_settings(s: &Settings) -> Self {
+        let model_dir = s
+            .path("VISAGE_MODEL_DIR")
+            .unwrap_or_else(visage_core::default_model_dir);
 
         let data_dir = std::env::var("XDG_DATA_HOME")
             .map(PathBuf::from)
@@ -46,28 +317,351 @@ impl Config {
             })
             .join("visage");
 
-        let db_path = std::env::var("VISAGE_DB_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| data_dir.join("faces.db"));
+        let db_path = s
+            .path("VISAGE_DB_PATH")
+            .unwrap_or_else(|| data_dir.join("faces.db"));
+
+        let crash_dir = s
+            .path("VISAGE_CRASH_DIR")
+            .unwrap_or_else(|| db_path.parent().unwrap_or(&data_dir).join("crashes"));
+
+        let failure_dir = s
+            .path("VISAGE_FAILURE_DIR")
+            .unwrap_or_else(|| db_path.parent().unwrap_or(&data_dir).join("failures"));
+
+        let instance = s.instance().map(String::from);
+        let session_bus = parse_session_bus(s.get("VISAGE_SESSION_BUS"));
+        let audit_log = match (s.get("VISAGE_AUDIT_LOG"), &instance) {
+            (Some(""), _) => None,
+            (Some(path), _) => Some(PathBuf::from(path)),
+            (None, _) if session_bus => None,
+            (None, Some(name)) => Some(PathBuf::from(format!("/var/log/visage/audit-{name}.log"))),
+            (None, None) => Some(PathBuf::from("/var/log/visage/audit.log")),
+        };
+        let camera_device = match (s.get("VISAGE_CAMERA_DEVICE"), &instance) {
+            (Some(device), _) => device.to_string(),
+            (None, Some(name)) => format!("/dev/{name}"),
+            (None, None) => "/dev/video2".to_string(),
+        };
 
         Self {
-            camera_device: std::env::var("VISAGE_CAMERA_DEVICE")
-                .unwrap_or_else(|_| "/dev/video2".to_string()),
+            instance,
+            camera_device,
+            rgb_camera_device: s
+                .get("VISAGE_RGB_CAMERA_DEVICE")
+                .filter(|v| !v.is_empty())
+                .map(String::from),
+            rgb_max_correlation: s
+                .parse_or("VISAGE_RGB_MAX_CORRELATION", 0.9f32)
+                .clamp(0.0, 1.0),
+            depth_camera_device: s
+                .get("VISAGE_DEPTH_CAMERA_DEVICE")
+                .filter(|v| !v.is_empty())
+                .map(String::from),
+            depth_min_relief_mm: s.parse_or("VISAGE_DEPTH_MIN_RELIEF_MM", 15.0),
+            pad_model: s.path("VISAGE_PAD_MODEL"),
+            pad_threshold: s.parse_or("VISAGE_PAD_THRESHOLD", 0.5f32).clamp(0.0, 1.0),
+            calibration_dir: s
+                .path("VISAGE_CALIBRATION_DIR")
+                .unwrap_or_else(|| PathBuf::from(visage_hw::calibration::DEFAULT_CALIBRATION_DIR)),
+            quirks_dirs: parse_list(
+                s.get("VISAGE_QUIRKS_DIRS")
+                    .unwrap_or(visage_hw::quirks::DEFAULT_QUIRKS_DIR),
+            )
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+            quirks_watch: s.get("VISAGE_QUIRKS_WATCH") != Some("0"),
             model_dir,
             db_path,
-            similarity_threshold: env_f32("VISAGE_SIMILARITY_THRESHOLD", 0.40),
-            verify_timeout_secs: env_u64("VISAGE_VERIFY_TIMEOUT_SECS", 10),
-            warmup_frames: env_usize("VISAGE_WARMUP_FRAMES", 4),
-            frames_per_verify: env_usize("VISAGE_FRAMES_PER_VERIFY", 3),
-            frames_per_enroll: env_usize("VISAGE_FRAMES_PER_ENROLL", 5),
-            emitter_enabled: std::env::var("VISAGE_EMITTER_ENABLED")
-                .map(|v| v != "0")
-                .unwrap_or(true),
-            liveness_enabled: std::env::var("VISAGE_LIVENESS_ENABLED")
-                .map(|v| v != "0")
-                .unwrap_or(true),
-            liveness_min_displacement: env_f32("VISAGE_LIVENESS_MIN_DISPLACEMENT", 0.8),
-            session_bus: parse_session_bus(std::env::var("VISAGE_SESSION_BUS").ok().as_deref()),
+            similarity_threshold: s.parse_or("VISAGE_SIMILARITY_THRESHOLD", 0.40),
+            verify_timeout_secs: s.parse_or("VISAGE_VERIFY_TIMEOUT_SECS", 10),
+            warmup_frames: s.parse_or("VISAGE_WARMUP_FRAMES", 4),
+            frames_per_verify: s.parse_or("VISAGE_FRAMES_PER_VERIFY", 3),
+            frames_per_enroll: s.parse_or("VISAGE_FRAMES_PER_ENROLL", 5),
+            verify_latency_ms: s.parse_or("VISAGE_VERIFY_LATENCY_MS", 0),
+            precapture_secs: s
+                .parse_or("VISAGE_PRECAPTURE_SECS", 0u64)
+                .min(MAX_PRECAPTURE_SECS),
+            confirm_margin: s
+                .parse_or("VISAGE_CONFIRM_MARGIN", 0.0f32)
+                .clamp(0.0, MAX_CONFIRM_MARGIN),
+            night_hours: s
+                .get("VISAGE_NIGHT_HOURS")
+                .and_then(crate::night::parse_hours),
+            night_brightness: s.parse_or("VISAGE_NIGHT_BRIGHTNESS", 0.0f32).max(0.0),
+            night_clahe_clip: s.parse_or("VISAGE_NIGHT_CLAHE_CLIP", 0.04),
+            night_exposure: s.get("VISAGE_NIGHT_EXPOSURE").and_then(|v| v.parse().ok()),
+            night_extra_frames: s.parse_or("VISAGE_NIGHT_EXTRA_FRAMES", 2),
+            night_warmup_frames: s
+                .get("VISAGE_NIGHT_WARMUP_FRAMES")
+                .and_then(|v| v.parse().ok()),
+            night_learn_after: u32::try_from(s.parse_or("VISAGE_NIGHT_LEARN_AFTER", 3u64))
+                .unwrap_or(u32::MAX),
+            als_dark_lux: s.parse_or("VISAGE_ALS_DARK_LUX", 0.0f32).max(0.0),
+            min_frame_sharpness: s.parse_or("VISAGE_MIN_FRAME_SHARPNESS", 1.5),
+            max_frame_motion: s.parse_or("VISAGE_MAX_FRAME_MOTION", 30.0),
+            min_scene_delta: s.parse_or("VISAGE_MIN_SCENE_DELTA", 0.25),
+            allow_virtual_camera: parse_opt_in(s.get("VISAGE_ALLOW_VIRTUAL_CAMERA")),
+            emitter_enabled: s.get("VISAGE_EMITTER_ENABLED") != Some("0"),
+            liveness_enabled: s.get("VISAGE_LIVENESS_ENABLED") != Some("0"),
+            liveness_min_displacement: s.parse_or("VISAGE_LIVENESS_MIN_DISPLACEMENT", 0.8),
+            identify_enabled: parse_opt_in(s.get("VISAGE_IDENTIFY_ENABLED")),
+            identify_callers: parse_list(
+                s.get("VISAGE_IDENTIFY_CALLERS")
+                    .unwrap_or(DEFAULT_IDENTIFY_CALLERS),
+            ),
+            identify_similarity_threshold: s.parse_or("VISAGE_IDENTIFY_THRESHOLD", 0.50),
+            identify_margin: s.parse_or("VISAGE_IDENTIFY_MARGIN", 0.08),
+            identify_index_min_models: s.parse_or("VISAGE_IDENTIFY_INDEX_MIN_MODELS", 200),
+            identify_index_rebuild_secs: s.parse_or("VISAGE_IDENTIFY_INDEX_REBUILD_SECS", 60),
+            gallery_outlier_similarity: s.parse_or("VISAGE_GALLERY_OUTLIER_SIMILARITY", 0.30),
+            challenge_narrow_matches: s.parse_or("VISAGE_CHALLENGE_NARROW_MATCHES", 0),
+            challenge_margin: s.parse_or("VISAGE_CHALLENGE_MARGIN", 0.05f32).max(0.0),
+            challenge_window_secs: s.parse_or("VISAGE_CHALLENGE_WINDOW_SECS", 86_400),
+            challenge_hours: s
+                .get("VISAGE_CHALLENGE_HOURS")
+                .and_then(crate::night::parse_hours),
+            challenge_threshold_boost: s
+                .parse_or("VISAGE_CHALLENGE_THRESHOLD_BOOST", 0.05f32)
+                .clamp(0.0, MAX_CHALLENGE_THRESHOLD_BOOST),
+            keep_probes: s
+                .parse_or("VISAGE_KEEP_PROBES", 0usize)
+                .min(MAX_KEEP_PROBES),
+            failure_snapshots: s
+                .parse_or("VISAGE_FAILURE_SNAPSHOTS", 0usize)
+                .min(MAX_FAILURE_SNAPSHOTS),
+            failure_snapshot_days: s.parse_or("VISAGE_FAILURE_SNAPSHOT_DAYS", 7),
+            failure_dir,
+            retain_history_days: s.parse_or("VISAGE_RETAIN_HISTORY_DAYS", 0),
+            purge_unused_months: s.parse_or("VISAGE_PURGE_UNUSED_MONTHS", 0),
+            purge_notice_days: s.parse_or("VISAGE_PURGE_NOTICE_DAYS", 14),
+            tenants: parse_tenants(s.get("VISAGE_TENANTS").unwrap_or_default()),
+            hook_command: s.path("VISAGE_HOOK_COMMAND"),
+            hook_user: s.get("VISAGE_HOOK_USER").unwrap_or("nobody").to_string(),
+            hook_socket: s.path("VISAGE_HOOK_SOCKET"),
+            hook_events: parse_list(s.get("VISAGE_HOOK_EVENTS").unwrap_or_default()),
+            hook_timeout_secs: s.parse_or("VISAGE_HOOK_TIMEOUT_SECS", 5),
+            audit_log,
+            audit_max_bytes: s.parse_or("VISAGE_AUDIT_MAX_BYTES", 10 * 1024 * 1024),
+            audit_keep: s.parse_or("VISAGE_AUDIT_KEEP", 5u32).max(1),
+            socket_path: s.path("VISAGE_SOCKET_PATH"),
+            vsock_port: s.get("VISAGE_VSOCK_PORT").and_then(|v| v.parse().ok()),
+            tcp_addr: s.get("VISAGE_TCP_ADDR").and_then(|v| v.parse().ok()),
+            tcp_token_file: s.path("VISAGE_TCP_TOKEN_FILE"),
+            remote_users: parse_remote_users(s.get("VISAGE_REMOTE_USERS").unwrap_or_default()),
+            idle_exit_secs: s.parse_or("VISAGE_IDLE_EXIT_SECS", 0),
+            healthz_addr: s.get("VISAGE_HEALTHZ_ADDR").and_then(|v| v.parse().ok()),
+            metrics_addr: s.get("VISAGE_METRICS_ADDR").and_then(|v| v.parse().ok()),
+            metrics_textfile: s.path("VISAGE_METRICS_TEXTFILE"),
+            engine_cpus: s
+                .get("VISAGE_ENGINE_CPUS")
+                .and_then(crate::scheduling::parse_cpus)
+                .unwrap_or_default(),
+            engine_nice: s
+                .get("VISAGE_ENGINE_NICE")
+                .and_then(|v| v.parse::<i32>().ok())
+                .map(|nice| nice.clamp(-20, 19)),
+            engine_rt_priority: s
+                .get("VISAGE_ENGINE_RT_PRIORITY")
+                .and_then(|v| v.parse::<i32>().ok())
+                .filter(|priority| *priority > 0)
+                .map(|priority| priority.min(crate::scheduling::MAX_RT_PRIORITY)),
+            max_request_cpu_ms: s.parse_or("VISAGE_MAX_REQUEST_CPU_MS", 5000),
+            max_request_wall_ms: s.parse_or("VISAGE_MAX_REQUEST_WALL_MS", 15000),
+            require_second_factor: parse_opt_in(s.get("VISAGE_REQUIRE_SECOND_FACTOR")),
+            reproducible: parse_opt_in(s.get("VISAGE_REPRODUCIBLE")),
+            profile_dir: s.path("VISAGE_PROFILE_DIR"),
+            crash_dir,
+            session_bus,
+        }
+    }
+
+    /// Take from `fresh`, a newly loaded configuration, the settings a
+    /// running daemon can change without re-opening the camera or reloading
+    /// the models: the similarity threshold, the frame counts, the IR
+    /// emitter, the profile directory and the failure snapshot limits. Any
+    /// other difference is left for a restart.
+    pub fn apply_reload(&mut self, fresh: Config) -> Reload {
+        let mut changed = Vec::new();
+        take(
+            "similarity_threshold",
+            &mut self.similarity_threshold,
+            fresh.similarity_threshold,
+            &mut changed,
+        );
+        take(
+            "frames_per_verify",
+            &mut self.frames_per_verify,
+            fresh.frames_per_verify,
+            &mut changed,
+        );
+        take(
+            "frames_per_enroll",
+            &mut self.frames_per_enroll,
+            fresh.frames_per_enroll,
+            &mut changed,
+        );
+        take(
+            "warmup_frames",
+            &mut self.warmup_frames,
+            fresh.warmup_frames,
+            &mut changed,
+        );
+        take(
+            "emitter_enabled",
+            &mut self.emitter_enabled,
+            fresh.emitter_enabled,
+            &mut changed,
+        );
+        take(
+            "profile_dir",
+            &mut self.profile_dir,
+            fresh.profile_dir.clone(),
+            &mut changed,
+        );
+        take(
+            "failure_snapshots",
+            &mut self.failure_snapshots,
+            fresh.failure_snapshots,
+            &mut changed,
+        );
+        take(
+            "failure_snapshot_days",
+            &mut self.failure_snapshot_days,
+            fresh.failure_snapshot_days,
+            &mut changed,
+        );
+        Reload {
+            changed,
+            restart_needed: *self != fresh,
+        }
+    }
+
+    /// Re-verification challenge rules; see `challenge`.
+    pub fn challenge_policy(&self) -> crate::challenge::ChallengePolicy {
+        crate::challenge::ChallengePolicy {
+            narrow_matches: self.challenge_narrow_matches,
+            margin: self.challenge_margin,
+            window: std::time::Duration::from_secs(self.challenge_window_secs),
+            hours: self.challenge_hours,
+            threshold_boost: self.challenge_threshold_boost,
+        }
+    }
+
+    /// CPU placement and priority of the engine; see `scheduling`.
+    pub fn engine_scheduling(&self) -> crate::scheduling::Scheduling {
+        crate::scheduling::Scheduling {
+            cpus: self.engine_cpus.clone(),
+            nice: self.engine_nice,
+            rt_priority: self.engine_rt_priority,
+        }
+    }
+
+    /// Retention rules; see `retention`.
+    pub fn retention_policy(&self) -> crate::retention::RetentionPolicy {
+        crate::retention::RetentionPolicy {
+            history_days: self.retain_history_days,
+            unused_months: self.purge_unused_months,
+            notice_days: self.purge_notice_days,
+        }
+    }
+
+    /// Where and how many failure snapshots are kept; see `snapshots`.
+    pub fn snapshot_policy(&self) -> crate::snapshots::SnapshotPolicy {
+        crate::snapshots::SnapshotPolicy {
+            dir: self.failure_dir.clone(),
+            keep: self.failure_snapshots,
+            max_age_days: self.failure_snapshot_days,
+        }
+    }
+
+    /// The entries of `camera_device`, most preferred first.
+    pub fn camera_devices(&self) -> Vec<String> {
+        crate::camera_list::parse(&self.camera_device)
+    }
+
+    /// Whether `device` is one of the configured cameras.
+    pub fn serves_camera(&self, device: &str) -> bool {
+        device == self.camera_device
+            || crate::camera_list::expand(&self.camera_devices())
+                .iter()
+                .any(|entry| entry == device)
+    }
+
+    /// Frame quality gate applied before detection.
+    pub fn quality_gate(&self) -> visage_hw::QualityGate {
+        visage_hw::QualityGate {
+            min_sharpness: self.min_frame_sharpness,
+            max_motion: self.max_frame_motion,
+            min_scene_delta: self.min_scene_delta,
+            ..visage_hw::QualityGate::default()
+        }
+    }
+
+    /// Per-request resource ceilings for the engine.
+    pub fn request_budget(&self) -> crate::budget::RequestBudget {
+        let ceiling = |ms: u64| (ms > 0).then(|| std::time::Duration::from_millis(ms));
+        crate::budget::RequestBudget {
+            max_cpu: ceiling(self.max_request_cpu_ms),
+            max_wall: ceiling(self.max_request_wall_ms),
+        }
+    }
+
+    /// Effective `IdentifyAny` threshold: the configured identification
+    /// threshold, but never looser than the 1:1 verification threshold, so
+    /// enabling household identification cannot weaken login security.
+    pub fn identify_threshold(&self) -> f32 {
+        self.identify_similarity_threshold
+            .max(self.similarity_threshold)
+    }
+
+    /// Whether a best similarity of `similarity` is a near-threshold rejection
+    /// worth a confirmation burst: below the threshold by at most
+    /// `confirm_margin`.
+    pub fn is_borderline(&self, similarity: f32) -> bool {
+        in_confirm_band(similarity, self.similarity_threshold, self.confirm_margin)
+    }
+
+    /// Verify latency target, if one is set.
+    pub fn verify_latency(&self) -> Option<std::time::Duration> {
+        (self.verify_latency_ms > 0)
+            .then(|| std::time::Duration::from_millis(self.verify_latency_ms))
+    }
+
+    /// How long the camera stays warm after a verify, if pre-capture is on.
+    pub fn precapture_window(&self) -> Option<std::time::Duration> {
+        (self.precapture_secs > 0).then(|| std::time::Duration::from_secs(self.precapture_secs))
+    }
+
+    /// The night capture profile and when it applies; `None` when none of
+    /// `VISAGE_NIGHT_HOURS`, `VISAGE_NIGHT_BRIGHTNESS` and
+    /// `VISAGE_ALS_DARK_LUX` is set. The ambient light sensor is attached by
+    /// [`NightSchedule::with_light_sensor`](crate::night::NightSchedule::with_light_sensor).
+    pub fn night_schedule(&self) -> Option<crate::night::NightSchedule> {
+        if self.night_hours.is_none() && self.night_brightness <= 0.0 && self.als_dark_lux <= 0.0 {
+            return None;
+        }
+        Some(crate::night::NightSchedule {
+            mode: visage_hw::NightMode {
+                profile: visage_hw::CaptureProfile {
+                    clahe_clip_limit: self.night_clahe_clip,
+                    exposure: self.night_exposure,
+                    extra_frames: self.night_extra_frames,
+                    warmup_frames: self.night_warmup_frames,
+                },
+                below_brightness: (self.night_brightness > 0.0).then_some(self.night_brightness),
+            },
+            hours: self.night_hours,
+            light: None,
+            learn_after: self.night_learn_after,
+        })
+    }
+
+    /// Session settings for both models.
+    pub fn inference_options(&self) -> visage_core::InferenceOptions {
+        visage_core::InferenceOptions {
+            reproducible: self.reproducible,
         }
     }
 
@@ -86,27 +680,13 @@ impl Config {
             .to_string_lossy()
             .into_owned()
     }
-}
-
-fn env_f32(key: &str, default: f32) -> f32 {
-    std::env::var(key)
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(default)
-}
-
-fn env_u64(key: &str, default: u64) -> u64 {
-    std::env::var(key)
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(default)
-}
 
-fn env_usize(key: &str, default: usize) -> usize {
-    std::env::var(key)
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(default)
+    /// Path to the PAD model, if one is configured.
+    pub fn pad_model_path(&self) -> Option<String> {
+        self.pad_model
+            .as_ref()
+            .map(|path| self.model_dir.join(path).to_string_lossy().into_owned())
+    }
 }
 
 /// Parse the `VISAGE_SESSION_BUS` value into the session-bus flag.
@@ -122,12 +702,111 @@ fn env_usize(key: &str, default: usize) -> usize {
 /// off — enabled session-bus mode and silently disabled UID validation: a
 /// fail-open trap. This helper closes it.
 fn parse_session_bus(value: Option<&str>) -> bool {
+    parse_opt_in(value)
+}
+
+/// Parse a security-sensitive opt-in flag: only a non-empty, non-`"0"` value
+/// enables it. Same fail-closed rule as [`parse_session_bus`].
+fn parse_opt_in(value: Option<&str>) -> bool {
     matches!(value, Some(v) if !v.is_empty() && v != "0")
 }
 
+/// Split a comma-separated list, trimming whitespace and dropping empty entries.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Whether `name` can be used as a tenant: 1–64 ASCII letters, digits, `.`,
+/// `_` or `-`. The empty string is the default tenant and is not a name.
+pub fn is_valid_tenant(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+}
+
+/// Parse `VISAGE_TENANTS`: `tenant=user,user;tenant=...`. A tenant with no
+/// users after `=` is root-only. Invalid tenant names are dropped.
+fn parse_tenants(value: &str) -> BTreeMap<String, Vec<String>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (name, callers) = entry.split_once('=').unwrap_or((entry, ""));
+            let name = name.trim();
+            if !is_valid_tenant(name) {
+                tracing::warn!(
+                    tenant = name,
+                    "VISAGE_TENANTS: ignoring invalid tenant name"
+                );
+                return None;
+            }
+            Some((name.to_string(), parse_list(callers)))
+        })
+        .collect()
+}
+
+/// Parse `VISAGE_REMOTE_USERS`: `peer=user,user;peer=...` where a peer is
+/// `cid:N` or `tcp`. Unrecognised peers are dropped.
+fn parse_remote_users(value: &str) -> crate::remote::RemotePolicy {
+    let users = value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let (peer, users) = entry.split_once('=').unwrap_or((entry, ""));
+            let Some(peer) = crate::remote::Peer::parse(peer.trim()) else {
+                tracing::warn!(
+                    peer = peer.trim(),
+                    "VISAGE_REMOTE_USERS: ignoring unrecognised peer"
+                );
+                return None;
+            };
+            Some((peer, parse_list(users)))
+        })
+        .collect();
+    crate::remote::RemotePolicy { users }
+}
+
+/// Copy `fresh` into `current`, noting `name` if that changed it.
+fn take<T: PartialEq>(
+    name: &'static str,
+    current: &mut T,
+    fresh: T,
+    changed: &mut Vec<&'static str>,
+) {
+    if *current != fresh {
+        *current = fresh;
+        changed.push(name);
+    }
+}
+
+fn in_confirm_band(similarity: f32, threshold: f32, margin: f32) -> bool {
+    margin > 0.0 && similarity < threshold && similarity >= threshold - margin
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_session_bus;
+    use super::{
+        in_confirm_band, is_valid_tenant, parse_list, parse_remote_users, parse_session_bus,
+        parse_tenants, Config, Reload,
+    };
+    use crate::config_file::Settings;
+
+    #[test]
+    fn confirm_band_lies_just_below_the_threshold() {
+        assert!(in_confirm_band(0.37, 0.40, 0.05));
+        assert!(in_confirm_band(0.35, 0.40, 0.05));
+        assert!(!in_confirm_band(0.34, 0.40, 0.05), "too far below");
+        assert!(!in_confirm_band(0.40, 0.40, 0.05), "already a match");
+        assert!(!in_confirm_band(0.39, 0.40, 0.0), "disabled");
+    }
 
     #[test]
     fn session_bus_defaults_off_and_respects_zero() {
@@ -154,4 +833,73 @@ mod tests {
             "any other non-empty value enables session bus"
         );
     }
+
+    #[test]
+    fn list_parsing_trims_and_drops_empty() {
+        assert_eq!(
+            parse_list("gdm, lightdm,,sddm "),
+            vec!["gdm", "lightdm", "sddm"]
+        );
+        assert!(parse_list("").is_empty());
+    }
+
+    #[test]
+    fn tenant_parsing() {
+        let tenants = parse_tenants("lobby=kiosk, ops; vault= ;bad name=x;;door");
+        assert_eq!(tenants.len(), 3);
+        assert_eq!(tenants["lobby"], vec!["kiosk", "ops"]);
+        assert!(tenants["vault"].is_empty());
+        assert!(tenants["door"].is_empty());
+        assert!(parse_tenants("").is_empty());
+
+        assert!(is_valid_tenant("site-1.east_wing"));
+        assert!(!is_valid_tenant(""));
+        assert!(!is_valid_tenant("a:b"));
+        assert!(!is_valid_tenant(&"x".repeat(65)));
+    }
+
+    #[test]
+    fn remote_user_parsing() {
+        use crate::remote::Peer;
+
+        let policy = parse_remote_users("cid:3=alice; tcp=alice, bob;vm=x;cid:4");
+        assert_eq!(policy.users.len(), 3);
+        assert_eq!(policy.users[&Peer::Vsock(3)], vec!["alice"]);
+        assert_eq!(policy.users[&Peer::Tcp], vec!["alice", "bob"]);
+        assert!(policy.users[&Peer::Vsock(4)].is_empty());
+        assert!(parse_remote_users("").users.is_empty());
+    }
+
+    #[test]
+    fn reload_takes_only_live_settings() {
+        let defaults = || Config::from_settings(&Settings::default());
+        let mut config = defaults();
+
+        let mut fresh = defaults();
+        fresh.similarity_threshold = 0.45;
+        fresh.emitter_enabled = false;
+        assert_eq!(
+            config.apply_reload(fresh),
+            Reload {
+                changed: vec!["similarity_threshold", "emitter_enabled"],
+                restart_needed: false,
+            }
+        );
+        assert_eq!(config.similarity_threshold, 0.45);
+
+        let mut fresh = defaults();
+        fresh.frames_per_verify = 5;
+        fresh.camera_device = "/dev/video0".to_string();
+        let reload = config.apply_reload(fresh);
+        assert_eq!(
+            reload.changed,
+            [
+                "similarity_threshold",
+                "frames_per_verify",
+                "emitter_enabled"
+            ]
+        );
+        assert!(reload.restart_needed);
+        assert_eq!(config.camera_device, "/dev/video2", "needs a restart");
+    }
 }
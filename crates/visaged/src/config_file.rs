@@ -0,0 +1,341 @@
+//! `/etc/visage/config.toml` — daemon settings a package can ship.
+//!
+//! Every `VISAGE_*` environment variable can also be set in
+//! `/etc/visage/config.toml` under its name without the prefix, in lower
+//! case:
+//!
+//! ```toml
+//! # /etc/visage/config.toml
+//! camera_device = "/dev/video2"
+//! similarity_threshold = 0.45
+//! identify_enabled = true
+//! hook_events = ["verify_failure", "camera_error"]
+//!
+//! [tenants]
+//! lobby = ["kiosk", "ops"]
+//! ```
+//!
+//! Files in `/etc/visage/config.d/` ending in `.toml` are read after it, in
+//! file-name order, and override it key by key, so a package or an
+//! administrator can change one setting without editing the shipped file.
+//! A daemon started as an instance (`VISAGE_INSTANCE`, see `instance`) then
+//! reads its profile, `/etc/visage/instances/NAME.toml`. The environment
+//! comes last: a `VISAGE_*` variable set for the daemon
+//! (`Environment=` in a unit drop-in) overrides every file, and an empty one
+//! clears a file's value.
+//!
+//! A value is written as it would be in the variable: a string or number, a
+//! boolean for a flag (`true` is `1`, `false` is `0`), an array of strings for
+//! a comma-separated list, and a table of lists for the `name=a,b;name=c`
+//! settings (`tenants`, `remote_users`).
+//!
+//! The files choose the camera, the database and who may verify, so like
+//! `pam_visage.conf` they — and the drop-in directory — must be owned by
+//! root (or the daemon's own user) and not writable by group or others. An
+//! untrusted or malformed file stops the daemon at startup rather than
+//! letting it run with half its configuration. Missing files are the same as
+//! empty ones. A key that names no setting is logged and ignored.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_PATH: &str = "/etc/visage/config.toml";
+pub const DROP_IN_DIR: &str = "/etc/visage/config.d";
+
+/// Prefix of the environment variable behind each setting.
+const ENV_PREFIX: &str = "VISAGE_";
+
+/// One setting's value and the file it came from (`None`: the environment).
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    value: String,
+    file: Option<PathBuf>,
+}
+
+/// The layered settings, keyed by environment variable name.
+#[derive(Debug, Default)]
+pub struct Settings {
+    entries: BTreeMap<String, Entry>,
+    /// Names looked up so far, to tell unknown file keys from settings.
+    queried: RefCell<BTreeSet<String>>,
+    /// `VISAGE_INSTANCE`, which only the environment can set.
+    instance: Option<String>,
+}
+
+impl Settings {
+    /// The config files, the instance profile and the process environment.
+    pub fn load() -> Result<Self, String> {
+        let mut settings = Self::default();
+        settings.read_files(Path::new(CONFIG_PATH), Path::new(DROP_IN_DIR))?;
+        if let Some(name) = std::env::var("VISAGE_INSTANCE")
+            .ok()
+            .filter(|v| !v.is_empty())
+        {
+            if !crate::instance::is_valid(&name) {
+                return Err(format!(
+                    "VISAGE_INSTANCE: '{name}' is not a valid instance name"
+                ));
+            }
+            let profile = crate::instance::profile_path(&name);
+            if let Some(text) = read_trusted(&profile)? {
+                settings.merge(&profile, &text)?;
+            }
+            settings.instance = Some(name);
+        }
+        settings.overlay_env(
+            std::env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?))),
+        );
+        Ok(settings)
+    }
+
+    /// Read `path`, then each `*.toml` file of `drop_in_dir` in name order.
+    fn read_files(&mut self, path: &Path, drop_in_dir: &Path) -> Result<(), String> {
+        if let Some(text) = read_trusted(path)? {
+            self.merge(path, &text)?;
+        }
+        for path in drop_ins(drop_in_dir)? {
+            if let Some(text) = read_trusted(&path)? {
+                self.merge(&path, &text)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply one file over what is already loaded.
+    fn merge(&mut self, path: &Path, text: &str) -> Result<(), String> {
+        for (name, value) in parse(text).map_err(|e| format!("{}: {e}", path.display()))? {
+            let file = Some(path.to_path_buf());
+            self.entries.insert(name, Entry { value, file });
+        }
+        Ok(())
+    }
+
+    /// Apply the `VISAGE_*` variables of `vars` over the files.
+    fn overlay_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        for (name, value) in vars {
+            if name.starts_with(ENV_PREFIX) {
+                self.entries.insert(name, Entry { value, file: None });
+            }
+        }
+    }
+
+    /// The value of the setting behind environment variable `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.queried.borrow_mut().insert(name.to_string());
+        self.entries.get(name).map(|e| e.value.as_str())
+    }
+
+    /// The instance this daemon runs as; `None` for the default instance.
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    /// A number, or `default` when unset or unparseable.
+    pub fn parse_or<T: std::str::FromStr>(&self, name: &str, default: T) -> T {
+        self.get(name)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// A non-empty path, or `None`.
+    pub fn path(&self, name: &str) -> Option<PathBuf> {
+        self.get(name).filter(|v| !v.is_empty()).map(PathBuf::from)
+    }
+
+    /// File keys no setting looked up, as `(file, key)`: typos, or options
+    /// of another version.
+    pub fn unknown(&self) -> Vec<(PathBuf, String)> {
+        let queried = self.queried.borrow();
+        self.entries
+            .iter()
+            .filter(|(name, _)| !queried.contains(*name))
+            .filter_map(|(name, entry)| {
+                let key = name.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase();
+                Some((entry.file.clone()?, key))
+            })
+            .collect()
+    }
+}
+
+/// The contents of `path`, `None` if it does not exist, or `Err` if it is
+/// not a trusted regular file.
+fn read_trusted(path: &Path) -> Result<Option<String>, String> {
+    let display = path.display();
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{display}: {e}")),
+    };
+    if !meta.file_type().is_file() {
+        return Err(format!("{display}: not a regular file"));
+    }
+    check_owner(path, &meta)?;
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("{display}: {e}"))
+}
+
+/// The `*.toml` files of `dir` in name order; none if it does not exist.
+fn drop_ins(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let display = dir.display();
+    let meta = match fs::metadata(dir) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{display}: {e}")),
+    };
+    check_owner(dir, &meta)?;
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("{display}: {e}"))? {
+        let path = entry.map_err(|e| format!("{display}: {e}"))?.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Owned by root or by the daemon's user, and not group/world-writable.
+fn check_owner(path: &Path, meta: &fs::Metadata) -> Result<(), String> {
+    let euid = nix::unistd::geteuid().as_raw();
+    if (meta.uid() != 0 && meta.uid() != euid) || meta.mode() & 0o022 != 0 {
+        return Err(format!(
+            "{}: must be owned by root and not group/world-writable",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// The settings in one file, keyed by environment variable name, with each
+/// value in its variable form.
+fn parse(text: &str) -> Result<Vec<(String, String)>, String> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let valid = !key.is_empty()
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+            if !valid {
+                return Err(format!("'{key}' is not a setting name"));
+            }
+            let value = match value {
+                toml::Value::Table(groups) => groups
+                    .into_iter()
+                    .map(|(name, list)| Some(format!("{name}={}", scalar_or_list(list)?)))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|groups| groups.join(";")),
+                value => scalar_or_list(value),
+            }
+            .ok_or_else(|| format!("'{key}' has a value of the wrong type"))?;
+            Ok((format!("{ENV_PREFIX}{}", key.to_ascii_uppercase()), value))
+        })
+        .collect()
+}
+
+/// A string, number or boolean as the variable would hold it, or an array
+/// of them as a comma-separated list.
+fn scalar_or_list(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(x) => Some(x.to_string()),
+        toml::Value::Boolean(b) => Some(if b { "1" } else { "0" }.to_string()),
+        toml::Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                toml::Value::Array(_) | toml::Value::Table(_) => None,
+                item => scalar_or_list(item),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(",")),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_take_their_variable_form() {
+        let text = r#"
+            camera_device = "/dev/video0"
+            similarity_threshold = 0.45
+            verify_timeout_secs = 8
+            identify_enabled = true
+            emitter_enabled = false
+            hook_events = ["verify_failure", "camera_error"]
+
+            [tenants]
+            lobby = ["kiosk", "ops"]
+            vault = []
+        "#;
+        let values: BTreeMap<_, _> = parse(text).unwrap().into_iter().collect();
+        assert_eq!(values["VISAGE_CAMERA_DEVICE"], "/dev/video0");
+        assert_eq!(values["VISAGE_SIMILARITY_THRESHOLD"], "0.45");
+        assert_eq!(values["VISAGE_VERIFY_TIMEOUT_SECS"], "8");
+        assert_eq!(values["VISAGE_IDENTIFY_ENABLED"], "1");
+        assert_eq!(values["VISAGE_EMITTER_ENABLED"], "0");
+        assert_eq!(values["VISAGE_HOOK_EVENTS"], "verify_failure,camera_error");
+        assert_eq!(values["VISAGE_TENANTS"], "lobby=kiosk,ops;vault=");
+
+        assert!(parse("Camera_Device = \"x\"").is_err());
+        assert!(parse("night_hours = 2026-01-01").is_err());
+        assert!(parse("hook_events = [[\"a\"]]").is_err());
+        assert!(parse("camera_device = ").is_err());
+    }
+
+    #[test]
+    fn later_layers_win_and_unknown_keys_are_reported() {
+        let mut settings = Settings::default();
+        let base = Path::new("/etc/visage/config.toml");
+        let drop_in = Path::new("/etc/visage/config.d/50-site.toml");
+        settings
+            .merge(
+                base,
+                "camera_device = \"/dev/video2\"\ndb_path = \"/a.db\"\ncamra = 1",
+            )
+            .unwrap();
+        settings
+            .merge(
+                drop_in,
+                "camera_device = \"/dev/video4\"\nhook_socket = \"/run/h\"",
+            )
+            .unwrap();
+        settings.overlay_env([
+            ("VISAGE_DB_PATH".to_string(), "/b.db".to_string()),
+            ("VISAGE_HOOK_SOCKET".to_string(), String::new()),
+            ("HOME".to_string(), "/root".to_string()),
+        ]);
+
+        assert_eq!(settings.get("VISAGE_CAMERA_DEVICE"), Some("/dev/video4"));
+        assert_eq!(settings.get("VISAGE_DB_PATH"), Some("/b.db"));
+        assert_eq!(settings.path("VISAGE_HOOK_SOCKET"), None);
+        assert_eq!(settings.get("HOME"), None);
+        assert_eq!(settings.parse_or("VISAGE_WARMUP_FRAMES", 4usize), 4);
+        assert_eq!(
+            settings.unknown(),
+            vec![(base.to_path_buf(), "camra".to_string())]
+        );
+    }
+
+    #[test]
+    fn missing_files_are_empty() {
+        let mut settings = Settings::default();
+        settings
+            .read_files(
+                Path::new("/nonexistent/config.toml"),
+                Path::new("/nonexistent/config.d"),
+            )
+            .unwrap();
+        assert!(settings.entries.is_empty());
+    }
+}
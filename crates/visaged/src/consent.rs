@@ -0,0 +1,119 @@
+//! Enrollment consent records — who asked for each face model, and how.
+//!
+//! Organisations that deploy face authentication often have to show that
+//! every biometric enrollment was initiated by the person enrolled. Each
+//! model therefore keeps a record taken when its enrollment was requested:
+//! the time, the D-Bus method, the calling UID and program, and the audit
+//! login UID of the calling process (`/proc/<pid>/loginuid`), which names
+//! the account that logged in even when the call was made through `sudo`.
+//! `ListModels` returns the record and `visage list --details` shows it.
+//!
+//! For a step-wise enrollment the record is taken at `EnrollBegin`. Models
+//! enrolled before records existed have none.
+
+use nix::unistd::{Uid, User};
+
+/// `/proc/<pid>/loginuid` of a process with no login session (a daemon).
+const UNSET_LOGIN_UID: u32 = u32::MAX;
+
+/// How and by whom an enrollment was requested.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct Consent {
+    /// When the enrollment was requested (RFC 3339).
+    pub recorded_at: String,
+    /// D-Bus method that requested it: `Enroll`, `EnrollIn` or `EnrollBegin`.
+    pub method: String,
+    /// UID of the D-Bus caller, and its account name at the time.
+    pub caller_uid: Option<u32>,
+    pub caller_user: Option<String>,
+    /// Audit login UID of the calling process, and its account name: who
+    /// logged in, whatever the process later switched to.
+    pub login_uid: Option<u32>,
+    pub login_user: Option<String>,
+    /// Name of the calling program (`/proc/<pid>/comm`).
+    pub command: Option<String>,
+}
+
+impl Consent {
+    /// Record a call of `method` from the sender of `header`. Any detail
+    /// that cannot be read (a peer that already exited, the session bus
+    /// without credentials) is left out rather than failing the enrollment.
+    pub async fn capture(
+        method: &str,
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+    ) -> Self {
+        let (caller_uid, caller_pid) = match peer_credentials(header, conn).await {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                tracing::warn!(method, error = %e, "consent: cannot identify the caller");
+                (None, None)
+            }
+        };
+        let login_uid = caller_pid.and_then(|pid| {
+            std::fs::read_to_string(format!("/proc/{pid}/loginuid"))
+                .ok()
+                .and_then(|raw| parse_login_uid(&raw))
+        });
+        let command = caller_pid.and_then(|pid| {
+            std::fs::read_to_string(format!("/proc/{pid}/comm"))
+                .ok()
+                .map(|comm| comm.trim_end().to_string())
+        });
+        Self {
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            method: method.to_string(),
+            caller_uid,
+            caller_user: caller_uid.and_then(user_name),
+            login_uid,
+            login_user: login_uid.and_then(user_name),
+            command,
+        }
+    }
+}
+
+/// UID and PID of the sender of `header`, as the bus reports them.
+async fn peer_credentials(
+    header: &zbus::message::Header<'_>,
+    conn: &zbus::Connection,
+) -> zbus::fdo::Result<(Option<u32>, Option<u32>)> {
+    let sender = header
+        .sender()
+        .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
+    let dbus = zbus::fdo::DBusProxy::new(conn)
+        .await
+        .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+    let name = zbus::names::BusName::try_from(sender.as_str())
+        .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+    let uid = dbus.get_connection_unix_user(name.clone()).await.ok();
+    let pid = dbus.get_connection_unix_process_id(name).await.ok();
+    Ok((uid, pid))
+}
+
+/// The UID in a `/proc/<pid>/loginuid` file; `None` when unset.
+fn parse_login_uid(raw: &str) -> Option<u32> {
+    raw.trim()
+        .parse()
+        .ok()
+        .filter(|&uid| uid != UNSET_LOGIN_UID)
+}
+
+fn user_name(uid: u32) -> Option<String> {
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => Some(user.name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_uid_is_parsed_unless_unset() {
+        assert_eq!(parse_login_uid("1000"), Some(1000));
+        assert_eq!(parse_login_uid("0\n"), Some(0));
+        assert_eq!(parse_login_uid("4294967295"), None);
+        assert_eq!(parse_login_uid(""), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Gallery consistency — flag templates that do not look like the others.
+//!
+//! Every model in a user's gallery is a capture of the same face, so each
+//! should resemble most of the rest. One that does not is either a bad
+//! capture (a hand over the lens, the wrong person in frame at enrollment)
+//! or a deliberate one: a second face enrolled into someone's gallery
+//! unlocks their account as surely as their own. A model whose median
+//! similarity to the user's other models is below
+//! `VISAGE_GALLERY_OUTLIER_SIMILARITY` is flagged. Galleries are checked at
+//! startup and after every enrollment; flags show in `ListModels`, are
+//! counted in `Status`, and are announced with a `GalleryOutlier` signal and
+//! a `gallery_outlier` hook.
+//!
+//! Fewer than three models cannot say which one is the odd one out, so
+//! smaller galleries are never flagged.
+
+use visage_core::FaceModel;
+
+/// Smallest gallery the check applies to.
+pub const MIN_MODELS: usize = 3;
+
+/// A model flagged as unlike the rest of its gallery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outlier {
+    pub model_id: String,
+    /// Median similarity to the user's other models.
+    pub similarity: f32,
+}
+
+/// Median similarity of each model in `gallery` (one user's models) to the
+/// others, in gallery order; `None` for fewer than [`MIN_MODELS`] models.
+pub fn consistency(gallery: &[FaceModel]) -> Option<Vec<f32>> {
+    if gallery.len() < MIN_MODELS {
+        return None;
+    }
+    let scores = gallery
+        .iter()
+        .enumerate()
+        .map(|(i, model)| {
+            let mut similarities: Vec<f32> = gallery
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, other)| model.embedding.similarity(&other.embedding))
+                .collect();
+            similarities.sort_by(f32::total_cmp);
+            let mid = similarities.len() / 2;
+            if similarities.len() % 2 == 1 {
+                similarities[mid]
+            } else {
+                (similarities[mid - 1] + similarities[mid]) / 2.0
+            }
+        })
+        .collect();
+    Some(scores)
+}
+
+/// Models of `gallery` whose median similarity to the others is below
+/// `min_similarity`.
+pub fn outliers(gallery: &[FaceModel], min_similarity: f32) -> Vec<Outlier> {
+    let Some(scores) = consistency(gallery) else {
+        return Vec::new();
+    };
+    gallery
+        .iter()
+        .zip(scores)
+        .filter(|&(_, similarity)| similarity < min_similarity)
+        .map(|(model, similarity)| Outlier {
+            model_id: model.id.clone(),
+            similarity,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visage_core::Embedding;
+
+    /// A model whose embedding points mostly along `axis`, nudged by `jitter`.
+    fn model(id: &str, axis: usize, jitter: f32) -> FaceModel {
+        let mut values = vec![0.0f32; 512];
+        values[axis] = 1.0;
+        values[511] = jitter;
+        FaceModel {
+            id: id.to_string(),
+            user: "alice".to_string(),
+            label: "default".to_string(),
+            embedding: Embedding {
+                values,
+                model_version: None,
+            },
+            created_at: String::new(),
+            tenant: String::new(),
+            threshold_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn flags_the_model_unlike_the_rest() {
+        let gallery = [
+            model("a", 0, 0.1),
+            model("b", 0, 0.2),
+            model("c", 0, 0.3),
+            model("intruder", 7, 0.0),
+        ];
+        let found = outliers(&gallery, 0.30);
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].model_id, "intruder");
+        assert!(found[0].similarity < 0.1);
+
+        let scores = consistency(&gallery).unwrap();
+        assert!(scores[..3].iter().all(|&s| s > 0.9), "{scores:?}");
+    }
+
+    #[test]
+    fn small_or_consistent_galleries_pass() {
+        assert!(outliers(&[model("a", 0, 0.0), model("b", 7, 0.0)], 0.30).is_empty());
+        assert_eq!(consistency(&[model("a", 0, 0.0)]), None);
+        let gallery = [model("a", 0, 0.1), model("b", 0, 0.2), model("c", 0, 0.3)];
+        assert!(outliers(&gallery, 0.30).is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+//! Crash reports — make panics visible after the fact.
+//!
+//! A panic on the engine thread does not take the process down: the D-Bus
+//! service keeps answering, every verify fails with `ChannelClosed`, and the
+//! only trace is one journal line that has usually rotated away by the time
+//! anyone looks. [`install`] adds a panic hook that writes a report to the
+//! crash directory and bumps a counter exposed in `Status`.
+//!
+//! A report holds the panic message and location, the thread name, a
+//! backtrace, a summary of non-sensitive settings, and the last
+//! [`LOG_TAIL_LINES`] log lines. Frames and embeddings are never logged, so
+//! they cannot reach a report. Reports are written `0600` in a `0700`
+//! directory, and only the newest [`MAX_REPORTS`] are kept.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::config::Config;
+
+/// Log lines kept in memory for the next report.
+const LOG_TAIL_LINES: usize = 200;
+/// Reports kept on disk; older ones are deleted when a new one is written.
+const MAX_REPORTS: usize = 20;
+
+/// Panics since the daemon started.
+static PANICS: AtomicU64 = AtomicU64::new(0);
+
+/// Panics since the daemon started.
+pub fn panic_count() -> u64 {
+    PANICS.load(Ordering::Relaxed)
+}
+
+/// Number of reports currently in `dir`.
+pub fn report_count(dir: &Path) -> usize {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(Result::ok).filter(is_report).count())
+        .unwrap_or(0)
+}
+
+/// The most recent log lines, filled by [`LogTail::writer`].
+#[derive(Clone, Default)]
+pub struct LogTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogTail {
+    /// A `tracing_subscriber` writer factory that copies every formatted line
+    /// to stderr and into the tail.
+    pub fn writer(&self) -> impl Fn() -> TeeWriter + Send + Sync + 'static {
+        let tail = self.clone();
+        move || TeeWriter { tail: tail.clone() }
+    }
+
+    fn push(&self, text: &str) {
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            if lines.len() == LOG_TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+
+    /// Never blocks: a panic raised while the tail is locked gets no log lines.
+    fn snapshot(&self) -> Vec<String> {
+        match self.lines.try_lock() {
+            Ok(lines) => lines.iter().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Writer handed out by [`LogTail::writer`].
+pub struct TeeWriter {
+    tail: LogTail,
+}
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tail.push(&String::from_utf8_lossy(buf));
+        io::stderr().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Install the panic hook. The default hook still runs first, so the panic
+/// also reaches the journal as before.
+pub fn install(config: &Config, tail: LogTail) {
+    let dir = config.crash_dir.clone();
+    let summary = config_summary(config);
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        PANICS.fetch_add(1, Ordering::Relaxed);
+        let report = render(
+            &info.to_string(),
+            std::thread::current().name().unwrap_or("<unnamed>"),
+            &std::backtrace::Backtrace::force_capture().to_string(),
+            &summary,
+            &tail.snapshot(),
+        );
+        match write_report(&dir, &report) {
+            Ok(path) => eprintln!("visaged: crash report written to {}", path.display()),
+            Err(e) => eprintln!("visaged: failed to write crash report: {e}"),
+        }
+    }));
+}
+
+/// Settings that help reproduce a crash. Paths and tunables only — no
+/// tenant accounts, hook targets, or anything read from the database.
+fn config_summary(config: &Config) -> String {
+    format!(
+        "version: {}\ncamera: {}\nmodel_dir: {}\nsimilarity_threshold: {}\n\
+         frames_per_verify: {}\nframes_per_enroll: {}\nwarmup_frames: {}\n\
+         emitter_enabled: {}\nliveness_enabled: {}\nidentify_enabled: {}\n\
+         tenants: {}\nhooks_configured: {}\nsession_bus: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        config.camera_device,
+        config.model_dir.display(),
+        config.similarity_threshold,
+        config.frames_per_verify,
+        config.frames_per_enroll,
+        config.warmup_frames,
+        config.emitter_enabled,
+        config.liveness_enabled,
+        config.identify_enabled,
+        config.tenants.len(),
+        config.hook_command.is_some() || config.hook_socket.is_some(),
+        config.session_bus,
+    )
+}
+
+fn render(panic: &str, thread: &str, backtrace: &str, summary: &str, log: &[String]) -> String {
+    let mut report = format!(
+        "visaged crash report\ntime: {}\nthread: {thread}\npanic: {panic}\n\n\
+         == configuration\n{summary}\n== backtrace\n{backtrace}\n\n== last {} log lines\n",
+        chrono::Utc::now().to_rfc3339(),
+        log.len(),
+    );
+    for line in log {
+        report.push_str(line);
+        report.push('\n');
+    }
+    report
+}
+
+/// Write `report` as a new file in `dir` and prune old reports.
+fn write_report(dir: &Path, report: &str) -> io::Result<PathBuf> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let name = format!(
+        "crash-{}-{}.txt",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+        std::process::id()
+    );
+    let path = dir.join(name);
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?
+        .write_all(report.as_bytes())?;
+    prune(dir);
+    Ok(path)
+}
+
+/// Delete all but the newest [`MAX_REPORTS`] reports. Names sort by time.
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut reports: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(is_report)
+        .map(|e| e.path())
+        .collect();
+    reports.sort();
+    let excess = reports.len().saturating_sub(MAX_REPORTS);
+    for old in &reports[..excess] {
+        let _ = fs::remove_file(old);
+    }
+}
+
+fn is_report(entry: &fs::DirEntry) -> bool {
+    let name = entry.file_name();
+    let name = name.to_string_lossy();
+    name.starts_with("crash-") && name.ends_with(".txt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_tail_keeps_the_newest_lines() {
+        let tail = LogTail::default();
+        for i in 0..LOG_TAIL_LINES + 5 {
+            tail.push(&format!("line {i}\n"));
+        }
+        let lines = tail.snapshot();
+        assert_eq!(lines.len(), LOG_TAIL_LINES);
+        assert_eq!(lines[0], "line 5");
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some(format!("line {}", LOG_TAIL_LINES + 4).as_str())
+        );
+    }
+
+    #[test]
+    fn reports_are_written_and_pruned() {
+        let dir = std::env::temp_dir().join(format!("visage-crash-{}", uuid::Uuid::new_v4()));
+        for i in 0..MAX_REPORTS + 3 {
+            let report = render(&format!("panic {i}"), "engine", "<bt>", "", &["a".into()]);
+            write_report(&dir, &report).unwrap();
+            // Names carry millisecond timestamps.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(report_count(&dir), MAX_REPORTS);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
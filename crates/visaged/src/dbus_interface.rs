@@ -1,12 +1,34 @@
-use nix::unistd::User;
+use nix::unistd::{Uid, User};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use visage_core::{ErrorKind, FaceModel, GalleryIndex, VisageError};
 use zbus::interface;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{OwnedValue, Value};
 
-use crate::config::Config;
-use crate::engine::{EngineError, EngineHandle};
+use crate::audit::{AuditEvent, AuditLog};
+use crate::authtok::{AuthtokTickets, MAX_SECRET_LEN};
+use crate::challenge::Challenges;
+use crate::config::{is_valid_tenant, Config};
+use crate::consent::Consent;
+use crate::consistency::{self, Outlier};
+use crate::engine::{
+    CaptureTally, EngineError, EngineHandle, EnrollResult, FrameMeta, ProgressSender, VerifyStage,
+};
+use crate::enrollment::{EnrollSessions, Pose, SessionError, StepPlan, MAX_STEPS};
+use crate::failure::FailureReason;
+use crate::hooks::{HookEvent, Hooks};
+use crate::polkit;
+use crate::quirk_reload::{self, Reloaded};
 use crate::rate_limiter::RateLimiter;
-use crate::store::FaceModelStore;
+use crate::retention;
+use crate::store::{
+    Batch, ExportedModel, FaceModelStore, ModelInfo, NewModel, StoreError, VerifyAttempt,
+    DEFAULT_POSE_LABEL, DEFAULT_TENANT,
+};
+use crate::warm::WarmSessions;
 
 /// Shared state accessible by D-Bus method handlers.
 pub struct AppState {
@@ -14,31 +36,90 @@ pub struct AppState {
     pub engine: EngineHandle,
     pub store: FaceModelStore,
     pub rate_limiter: RateLimiter,
+    /// Cached identification index per tenant and when it was built; see
+    /// [`VisageService::identify_index`].
+    pub identify_index: HashMap<String, (Arc<GalleryIndex>, std::time::Instant)>,
+    /// Script / socket notifications for daemon events.
+    pub hooks: Hooks,
+    /// Append-only record of biometric decisions; see [`crate::audit`].
+    pub audit: AuditLog,
+    /// Cancel flags of in-flight verifies by caller cookie, with the caller's
+    /// UID (`None` on the session bus); see [`VisageService::cancel`].
+    pub cancels: HashMap<String, (Option<u32>, Arc<AtomicBool>)>,
+    /// Open login sessions and their users' cached galleries; see
+    /// [`crate::warm`].
+    pub warm: WarmSessions,
+    /// Open step-wise enrollments; see [`crate::enrollment`].
+    pub enrollments: EnrollSessions,
+    /// Keyring secret release tickets from recent matches; see [`crate::authtok`].
+    pub authtok_tickets: AuthtokTickets,
+    /// Models flagged by the last gallery consistency check, by tenant and
+    /// user; see [`crate::consistency`].
+    pub gallery_outliers: HashMap<(String, String), Vec<Outlier>>,
+    /// Narrow matches and pending re-verification challenges; see
+    /// [`crate::challenge`].
+    pub challenges: Challenges,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// The state of a daemon with the default configuration, an in-memory
+    /// database and a [detached](EngineHandle::detached) engine.
+    pub async fn for_tests() -> Self {
+        Self {
+            config: Config::defaults(),
+            engine: EngineHandle::detached(),
+            store: FaceModelStore::open(std::path::Path::new(":memory:"))
+                .await
+                .unwrap(),
+            rate_limiter: RateLimiter::new(),
+            identify_index: HashMap::new(),
+            hooks: Hooks::default(),
+            audit: AuditLog::default(),
+            cancels: HashMap::new(),
+            warm: WarmSessions::default(),
+            enrollments: EnrollSessions::default(),
+            authtok_tickets: AuthtokTickets::default(),
+            gallery_outliers: HashMap::new(),
+            challenges: Challenges::default(),
+        }
+    }
 }
 
 /// D-Bus interface for the Visage biometric daemon.
 ///
-/// Bus name: org.freedesktop.Visage1
-/// Object path: /org/freedesktop/Visage1
+/// Bus name: org.freedesktop.Visage1 (`org.freedesktop.Visage1.NAME` for
+/// instance `NAME`; see `instance`)
+/// Object path: /org/freedesktop/Visage1 (`/org/freedesktop/Visage1/NAME`)
 pub struct VisageService {
     pub state: Arc<Mutex<AppState>>,
 }
 
-/// Retrieve the UID of the D-Bus peer identified by `sender_str` (a unique bus name).
+/// Retrieve the UID of the D-Bus peer identified by `sender_str` (a unique
+/// bus name) from the broker's `GetConnectionCredentials`, which the broker
+/// records from the peer's socket when it connects — a client cannot claim
+/// another UID. A peer without a UNIX user ID is refused.
 async fn get_caller_uid(sender_str: &str, conn: &zbus::Connection) -> zbus::fdo::Result<u32> {
     let dbus_proxy = zbus::fdo::DBusProxy::new(conn)
         .await
         .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
     let bus_name = zbus::names::BusName::try_from(sender_str)
         .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
-    dbus_proxy
-        .get_connection_unix_user(bus_name)
+    let credentials = dbus_proxy
+        .get_connection_credentials(bus_name)
         .await
-        .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+        .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+    credentials.unix_user_id().ok_or_else(|| {
+        zbus::fdo::Error::AccessDenied(format!("no UNIX user ID for caller {sender_str}"))
+    })
 }
 
-/// Look up the numeric UID for a username via NSS.
-fn uid_for_name(name: &str) -> Option<u32> {
+/// Look up the numeric UID for a username via NSS. `name` is the exact
+/// account name, which need not be UTF-8 (see [`account_key`]).
+fn uid_for_name(name: &[u8]) -> Option<u32> {
+    let Ok(name) = std::str::from_utf8(name) else {
+        return uid_for_raw_name(name);
+    };
     match User::from_name(name) {
         Ok(Some(user)) => Some(user.uid.as_raw()),
         Ok(None) => None,
@@ -46,8 +127,45 @@ fn uid_for_name(name: &str) -> Option<u32> {
     }
 }
 
+/// `getpwnam_r` for a name `nix` cannot pass: one that is not UTF-8.
+fn uid_for_raw_name(name: &[u8]) -> Option<u32> {
+    use nix::libc;
+
+    let name = std::ffi::CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: passwd is a plain C struct; all-zero is a valid initial value.
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    // SAFETY: all pointers are valid for the duration of the call; buf.len() is its size.
+    let rc = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    (rc == 0 && !result.is_null()).then_some(pwd.pw_uid)
+}
+
+/// The name a system account is stored, rate-limited, and logged under.
+///
+/// A UTF-8 account name is used as is. A legacy name that is not UTF-8
+/// (e.g. Latin-1 `jos\xe9`) is escaped with [`<[u8]>::escape_ascii`]: printable
+/// ASCII is kept and every other byte becomes `\xNN`. Account names cannot
+/// contain a backslash, so the escaped form never collides with a real
+/// account; enroll such a user with that form, e.g.
+/// `visage enroll --user 'jos\xe9'`.
+pub(crate) fn account_key(name: &[u8]) -> String {
+    match std::str::from_utf8(name) {
+        Ok(name) => name.to_string(),
+        Err(_) => name.escape_ascii().to_string(),
+    }
+}
+
 /// Defense-in-depth: require the D-Bus caller to be root (UID 0) for a
-/// privileged method (`Enroll`, `RemoveModel`, `ListModels`).
+/// privileged method (`ListModels`, `SetThresholdOffset`, ...).
 ///
 /// The system-bus policy (`org.freedesktop.Visage1.conf`) already restricts
 /// these methods to root by omission from the `default` context. This re-checks
@@ -82,277 +200,3641 @@ async fn require_root_caller(
     Ok(())
 }
 
-#[interface(name = "org.freedesktop.Visage1")]
-impl VisageService {
-    /// Enroll a new face model for the given user.
-    ///
-    /// Returns the UUID of the newly created model.
-    async fn enroll(
-        &self,
-        user: &str,
-        label: &str,
-        #[zbus(header)] header: zbus::message::Header<'_>,
-        #[zbus(connection)] conn: &zbus::Connection,
-    ) -> zbus::fdo::Result<String> {
-        tracing::info!(user, label, "enroll requested");
+/// Like [`require_root_caller`], but a caller that is not root may also act on
+/// its own account `user` once authorized through the polkit `action` (see
+/// [`crate::polkit`]), which can prompt for an administrator password. Another
+/// user's account is refused before polkit is asked. The system-bus policy
+/// lets any client call the methods checked this way, so this is their only
+/// gate.
+async fn require_root_or_polkit(
+    method: &str,
+    action: &str,
+    user: &str,
+    session_bus: bool,
+    header: &zbus::message::Header<'_>,
+    conn: &zbus::Connection,
+) -> Result<(), BusError> {
+    if session_bus {
+        return Ok(());
+    }
+    let sender = header
+        .sender()
+        .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
+    let caller_uid = get_caller_uid(sender.as_str(), conn).await?;
+    if caller_uid == 0 {
+        return Ok(());
+    }
+    check_account_caller(method, caller_uid, user.as_bytes())?;
+    match crate::polkit::check(conn, sender.as_str(), action, user).await {
+        Ok(polkit::Answer::Authorized) => {
+            tracing::info!(method, caller_uid, action, user, "authorized by polkit");
+            Ok(())
+        }
+        Ok(polkit::Answer::Challenge) => {
+            tracing::warn!(
+                method,
+                caller_uid,
+                action,
+                user,
+                "privileged method denied: polkit wanted authentication that no agent gave"
+            );
+            Err(zbus::fdo::Error::AccessDenied(format!(
+                "method '{method}' requires authentication for {action}; is a polkit agent running?"
+            ))
+            .into())
+        }
+        Ok(polkit::Answer::Denied) => {
+            tracing::warn!(
+                method,
+                caller_uid,
+                action,
+                user,
+                "privileged method denied by polkit"
+            );
+            Err(zbus::fdo::Error::AccessDenied(format!(
+                "method '{method}' requires root or polkit authorization for {action}"
+            ))
+            .into())
+        }
+        Err(e) => {
+            tracing::warn!(method, caller_uid, action, error = %e, "privileged method denied: polkit check failed");
+            Err(zbus::fdo::Error::AccessDenied(format!(
+                "method '{method}' requires root or polkit authorization for {action} (polkit unavailable: {e})"
+            ))
+            .into())
+        }
+    }
+}
 
-        // Copy values while holding lock, then release
-        let (engine, frames_count, session_bus) = {
-            let state = self.state.lock().await;
-            (
-                state.engine.clone(),
-                state.config.frames_per_enroll,
-                state.config.session_bus,
-            )
-        };
+/// Rate-limiter key for `IdentifyAny`. A colon can never appear in a POSIX
+/// username (it is the passwd field separator), so this cannot collide with a
+/// real user's lockout state.
+const IDENTIFY_RATE_KEY: &str = ":identify";
 
-        // Defense-in-depth (enrollment is a privileged mutation).
-        require_root_caller("Enroll", session_bus, &header, conn).await?;
+/// Rate-limiter key for `user` in `tenant`. Tenant names cannot contain a
+/// colon either, so each tenant's users get their own lockout state.
+fn rate_key(tenant: &str, user: &str) -> String {
+    if tenant == DEFAULT_TENANT {
+        user.to_string()
+    } else {
+        format!("{tenant}:{user}")
+    }
+}
 
-        // Run engine (no lock held)
-        let result = engine.enroll(frames_count).await.map_err(|e| {
-            tracing::error!(error = %e, "enroll failed");
-            zbus::fdo::Error::Failed(e.to_string())
-        })?;
+/// Rate-limiter key for identification in `tenant`.
+fn identify_rate_key(tenant: &str) -> String {
+    if tenant == DEFAULT_TENANT {
+        IDENTIFY_RATE_KEY.to_string()
+    } else {
+        format!("{IDENTIFY_RATE_KEY}:{tenant}")
+    }
+}
 
-        tracing::info!(
-            quality = result.quality_score,
-            "enroll: embedding extracted"
-        );
+/// Forward engine progress as `VerifyProgress` signals addressed to the
+/// method's caller, so other bus clients never learn who is authenticating,
+/// and broadcast `FaceDetected` and `CameraDisconnected`, which name nobody.
+/// Returns once the engine drops its sender.
+async fn relay_progress(
+    mut progress: tokio::sync::mpsc::UnboundedReceiver<VerifyStage>,
+    emitter: &SignalEmitter<'_>,
+    header: &zbus::message::Header<'_>,
+    user: &str,
+) {
+    let caller = header
+        .sender()
+        .map(|sender| emitter.clone().set_destination(sender.clone().into()));
+    while let Some(stage) = progress.recv().await {
+        match stage {
+            VerifyStage::FaceDetected { confidence } => {
+                if let Err(e) = VisageService::face_detected(emitter, f64::from(confidence)).await {
+                    tracing::debug!(error = %e, "failed to emit FaceDetected signal");
+                }
+            }
+            VerifyStage::CameraDisconnected => {
+                if let Err(e) = VisageService::camera_disconnected(emitter).await {
+                    tracing::debug!(error = %e, "failed to emit CameraDisconnected signal");
+                }
+            }
+            _ => {}
+        }
+        let Some(caller) = &caller else { continue };
+        if let Err(e) = VisageService::verify_progress(caller, user, stage.code()).await {
+            tracing::debug!(error = %e, "failed to emit VerifyProgress signal");
+        }
+    }
+}
 
-        // Store result (re-acquire lock)
-        let state = self.state.lock().await;
-        let model_id = state
-            .store
-            .insert(user, label, &result.embedding, result.quality_score)
-            .await
-            .map_err(|e| {
-                tracing::error!(error = %e, "enroll: store insert failed");
-                zbus::fdo::Error::Failed(e.to_string())
-            })?;
+/// UID of the method's sender.
+async fn caller_uid(
+    header: &zbus::message::Header<'_>,
+    conn: &zbus::Connection,
+) -> zbus::fdo::Result<u32> {
+    let sender = header
+        .sender()
+        .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
+    get_caller_uid(sender.as_str(), conn).await
+}
 
-        tracing::info!(model_id = %model_id, user, label, "enrolled successfully");
-        Ok(model_id)
+/// Whether `caller_uid` may verify the system account named `account`
+/// (exact bytes): root may verify anyone, everyone else only themselves.
+pub(crate) fn check_verify_caller(caller_uid: u32, account: &[u8]) -> Result<(), BusError> {
+    check_account_caller("Verify", caller_uid, account)
+}
+
+/// Whether `caller_uid` may call `method` for the system account named
+/// `account` (exact bytes): root may name anyone, everyone else only
+/// themselves. An account that does not exist is `NotFound`.
+fn check_account_caller(method: &str, caller_uid: u32, account: &[u8]) -> Result<(), BusError> {
+    if caller_uid == 0 {
+        return Ok(());
+    }
+    let user = account_key(account);
+    let user = user.as_str();
+    match uid_for_name(account) {
+        Some(expected_uid) if caller_uid == expected_uid => Ok(()),
+        Some(_) => {
+            tracing::warn!(
+                method,
+                user,
+                caller_uid,
+                "caller UID does not match target user UID"
+            );
+            Err(zbus::fdo::Error::AccessDenied(format!(
+                "caller is not permitted to call {method} for user '{user}'"
+            ))
+            .into())
+        }
+        None => {
+            tracing::warn!(method, user, "unknown user");
+            Err(VisageError::new(ErrorKind::NotFound, format!("unknown user '{user}'")).into())
+        }
     }
+}
 
-    /// Verify the current face against enrolled models for the given user.
-    ///
-    /// Returns true if the face matches any enrolled model above the threshold.
-    ///
-    /// Security: on the system bus the caller UID is validated against the target
-    /// username before any camera access or rate-limit check.  Root (UID 0) is always
-    /// permitted.  On the session bus (development mode) UID validation is skipped.
-    async fn verify(
-        &self,
-        user: &str,
-        #[zbus(header)] header: zbus::message::Header<'_>,
-        #[zbus(connection)] conn: &zbus::Connection,
-    ) -> zbus::fdo::Result<bool> {
-        tracing::info!(user, "verify requested");
+/// Age in seconds of the most recently enrolled of `models`, or `None` when
+/// there are none. A timestamp that does not parse counts as enrolled now, so
+/// a damaged row never makes an enrollment look stale.
+fn newest_model_age(models: &[ModelInfo], now: chrono::DateTime<chrono::Utc>) -> Option<u64> {
+    models
+        .iter()
+        .map(
+            |model| match chrono::DateTime::parse_from_rfc3339(&model.created_at) {
+                Ok(created) => (now - created.with_timezone(&chrono::Utc))
+                    .num_seconds()
+                    .max(0) as u64,
+                Err(_) => 0,
+            },
+        )
+        .min()
+}
 
-        // Read session_bus flag without holding lock across the async UID lookup
-        let session_bus = self.state.lock().await.config.session_bus;
+/// Whether `caller` may cancel a verify started by `owner`. `None` means the
+/// session bus, where UIDs are not checked.
+fn may_cancel(caller: Option<u32>, owner: Option<u32>) -> bool {
+    match (caller, owner) {
+        (None, _) | (Some(0), _) => true,
+        (Some(caller), Some(owner)) => caller == owner,
+        (Some(_), None) => false,
+    }
+}
 
-        // --- UID validation (system bus only) ---
-        if !session_bus {
-            let sender = header
-                .sender()
-                .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
-            let caller_uid = get_caller_uid(sender.as_str(), conn).await?;
-            if caller_uid != 0 {
-                match uid_for_name(user) {
-                    Some(expected_uid) if caller_uid == expected_uid => {}
-                    Some(_) => {
-                        tracing::warn!(
-                            user,
-                            caller_uid,
-                            "verify: caller UID does not match target user UID"
-                        );
-                        return Err(zbus::fdo::Error::AccessDenied(format!(
-                            "caller is not permitted to verify user '{user}'"
+/// Whether `uid` is root or belongs to one of the `allowed` account names.
+fn caller_is_listed(uid: u32, allowed: &[String]) -> bool {
+    if uid == 0 {
+        return true;
+    }
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => allowed.contains(&user.name),
+        _ => false,
+    }
+}
+
+/// Require the caller of `IdentifyAny` to be root or one of the configured
+/// display-manager accounts. Skipped on the session bus (development mode).
+async fn require_identify_caller(
+    session_bus: bool,
+    allowed: &[String],
+    header: &zbus::message::Header<'_>,
+    conn: &zbus::Connection,
+) -> zbus::fdo::Result<()> {
+    if session_bus {
+        return Ok(());
+    }
+    let sender = header
+        .sender()
+        .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
+    let caller_uid = get_caller_uid(sender.as_str(), conn).await?;
+    if caller_is_listed(caller_uid, allowed) {
+        return Ok(());
+    }
+    tracing::warn!(
+        caller_uid,
+        "IdentifyAny denied: caller is not a display manager"
+    );
+    Err(zbus::fdo::Error::AccessDenied(
+        "method 'IdentifyAny' is restricted to display managers".to_string(),
+    ))
+}
+
+/// Longest accepted `cookie` option.
+pub(crate) const MAX_COOKIE_LEN: usize = 64;
+
+/// Per-call options accepted by `VerifyWithOptions` (an `a{sv}` dict).
+///
+/// Unknown keys are ignored so older daemons keep working with newer clients.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct VerifyOptions {
+    /// `device` (s): camera the caller expects; must be the daemon's camera.
+    device: Option<String>,
+    /// `timeout` (u): verify deadline in seconds. Can only shorten the
+    /// configured `VISAGE_VERIFY_TIMEOUT_SECS`, never extend it.
+    timeout_secs: Option<u64>,
+    /// `tenant` (s): named tenant whose gallery to verify against. Empty or
+    /// absent means the default tenant.
+    tenant: Option<String>,
+    /// `cookie` (s): caller-chosen token that `Cancel` can later name to
+    /// stop this verify.
+    cookie: Option<String>,
+    /// `release_authtok` (b): on a match, allow one `ReleaseAuthtok` for the
+    /// user. Honoured for root callers in the default tenant only.
+    release_authtok: bool,
+    /// `second_factor` (b): the caller uses a match as one factor of two.
+    /// Required when `VISAGE_REQUIRE_SECOND_FACTOR` is set.
+    second_factor: bool,
+}
+
+impl VerifyOptions {
+    fn from_dict(dict: &HashMap<String, OwnedValue>) -> zbus::fdo::Result<Self> {
+        let mut opts = Self::default();
+        for (key, value) in dict {
+            match key.as_str() {
+                "device" => {
+                    let device: &str = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'device' must be a string".into())
+                    })?;
+                    opts.device = Some(device.to_string());
+                }
+                "timeout" => {
+                    let secs: u32 = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'timeout' must be a uint32".into())
+                    })?;
+                    opts.timeout_secs = Some(u64::from(secs));
+                }
+                "tenant" => opts.tenant = tenant_option(value)?,
+                "cookie" => {
+                    let cookie: &str = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'cookie' must be a string".into())
+                    })?;
+                    if cookie.is_empty() || cookie.len() > MAX_COOKIE_LEN {
+                        return Err(zbus::fdo::Error::InvalidArgs(format!(
+                            "option 'cookie' must be 1-{MAX_COOKIE_LEN} bytes"
                         )));
                     }
-                    None => {
-                        tracing::warn!(user, "verify: unknown user");
-                        return Err(zbus::fdo::Error::Failed(format!("unknown user '{user}'")));
-                    }
+                    opts.cookie = Some(cookie.to_string());
+                }
+                "release_authtok" => {
+                    opts.release_authtok = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs(
+                            "option 'release_authtok' must be a boolean".into(),
+                        )
+                    })?;
                 }
+                "second_factor" => {
+                    opts.second_factor = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs(
+                            "option 'second_factor' must be a boolean".into(),
+                        )
+                    })?;
+                }
+                other => tracing::debug!(option = other, "ignoring unknown verify option"),
             }
         }
+        Ok(opts)
+    }
 
-        // --- Rate limit check ---
-        {
-            let mut state = self.state.lock().await;
-            state.rate_limiter.check(user).map_err(|msg| {
-                tracing::warn!(user, "verify: rate limited");
-                zbus::fdo::Error::Failed(msg)
-            })?;
+    /// Reject options this daemon cannot honour, or a verify its
+    /// configuration does not allow.
+    fn check(&self, config: &Config) -> zbus::fdo::Result<()> {
+        check_second_factor(config, self.second_factor)?;
+        match &self.device {
+            Some(device) if !config.serves_camera(device) => {
+                Err(zbus::fdo::Error::InvalidArgs(format!(
+                    "device '{device}' is not served by this daemon (camera: {})",
+                    config.camera_device
+                )))
+            }
+            _ => Ok(()),
         }
+    }
 
-        // --- Fetch gallery and config (release lock before engine call) ---
-        let (
-            engine,
-            gallery,
-            threshold,
-            frames_count,
-            timeout_secs,
-            liveness_enabled,
-            liveness_min_displacement,
-        ) = {
-            let state = self.state.lock().await;
-            let gallery = state.store.get_gallery_for_user(user).await.map_err(|e| {
-                tracing::error!(error = %e, "verify: gallery fetch failed");
-                zbus::fdo::Error::Failed(e.to_string())
-            })?;
-            (
-                state.engine.clone(),
-                gallery,
-                state.config.similarity_threshold,
-                state.config.frames_per_verify,
-                state.config.verify_timeout_secs,
-                state.config.liveness_enabled,
-                state.config.liveness_min_displacement,
-            )
-        };
+    /// Tenant to verify in.
+    fn tenant(&self) -> &str {
+        self.tenant.as_deref().unwrap_or(DEFAULT_TENANT)
+    }
+}
 
-        if gallery.is_empty() {
-            tracing::warn!(user, "verify: no enrolled models");
-            return Err(zbus::fdo::Error::Failed(format!(
-                "no enrolled models for user '{user}'"
-            )));
-        }
+/// With `VISAGE_REQUIRE_SECOND_FACTOR`, refuse a verify whose caller did not
+/// declare the match a second factor.
+pub(crate) fn check_second_factor(config: &Config, declared: bool) -> zbus::fdo::Result<()> {
+    if config.require_second_factor && !declared {
+        tracing::warn!("verify refused: not declared a second factor");
+        return Err(zbus::fdo::Error::AccessDenied(
+            "this daemon verifies faces only as a second factor".to_string(),
+        ));
+    }
+    Ok(())
+}
 
-        // --- Run engine with timeout (no lock held) ---
-        // Runtime errors (camera failure, timeout) are returned as Err and do NOT count
-        // as rate-limit failures. Liveness failures are treated as deliberate auth failures
-        // and converted to non-match so they are rate-limited like other failed attempts.
-        let timeout = std::time::Duration::from_secs(timeout_secs);
-        let result = match engine
-            .verify(
-                gallery,
-                threshold,
-                frames_count,
-                timeout,
-                liveness_enabled,
-                liveness_min_displacement,
-            )
-            .await
-        {
-            Ok(result) => result,
-            Err(EngineError::LivenessCheckFailed {
-                displacement,
-                threshold,
-            }) => {
-                tracing::warn!(
-                    user,
-                    displacement,
-                    threshold,
-                    "verify: liveness check failed — treating as non-match"
-                );
-                crate::engine::VerifyResult {
-                    result: visage_core::MatchResult {
-                        matched: false,
-                        similarity: 0.0,
-                        model_id: None,
-                        model_label: None,
-                    },
-                    best_quality: 0.0,
-                }
-            }
-            Err(e) => {
-                tracing::error!(error = %e, "verify failed");
-                return Err(zbus::fdo::Error::Failed(e.to_string()));
-            }
-        };
+/// The `tenant` (s) option: `None` for the default tenant (empty string).
+fn tenant_option(value: &OwnedValue) -> zbus::fdo::Result<Option<String>> {
+    let tenant: &str = value
+        .downcast_ref()
+        .map_err(|_| zbus::fdo::Error::InvalidArgs("option 'tenant' must be a string".into()))?;
+    if !tenant.is_empty() && !is_valid_tenant(tenant) {
+        return Err(zbus::fdo::Error::InvalidArgs(format!(
+            "invalid tenant name '{tenant}'"
+        )));
+    }
+    Ok((!tenant.is_empty()).then(|| tenant.to_string()))
+}
 
-        // --- Record rate-limit outcome ---
-        {
-            let mut state = self.state.lock().await;
-            if result.result.matched {
-                state.rate_limiter.record_success(user);
-            } else {
-                state.rate_limiter.record_failure(user);
+/// Options accepted by `EnrollBegin` (an `a{sv}` dict); unknown keys are
+/// ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct EnrollOptions {
+    /// `tenant` (s): named tenant to enroll into; absent means the default.
+    tenant: Option<String>,
+    /// `steps` (u): accepted captures needed before `EnrollCommit` (1–10).
+    steps: Option<u32>,
+    /// `guided` (b): one capture per pose (frontal, left, right, glasses);
+    /// see [`crate::enrollment`]. Excludes `steps`.
+    guided: bool,
+    /// `replace` (b): re-enrollment; the commit replaces the user's models.
+    replace: bool,
+}
+
+impl EnrollOptions {
+    fn from_dict(dict: &HashMap<String, OwnedValue>) -> zbus::fdo::Result<Self> {
+        let mut opts = Self::default();
+        for (key, value) in dict {
+            match key.as_str() {
+                "tenant" => opts.tenant = tenant_option(value)?,
+                "steps" => {
+                    let steps: u32 = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'steps' must be a uint32".into())
+                    })?;
+                    if !(1..=MAX_STEPS).contains(&steps) {
+                        return Err(zbus::fdo::Error::InvalidArgs(format!(
+                            "option 'steps' must be 1-{MAX_STEPS}"
+                        )));
+                    }
+                    opts.steps = Some(steps);
+                }
+                "guided" => {
+                    opts.guided = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'guided' must be a boolean".into())
+                    })?;
+                }
+                "replace" => {
+                    opts.replace = value.downcast_ref().map_err(|_| {
+                        zbus::fdo::Error::InvalidArgs("option 'replace' must be a boolean".into())
+                    })?;
+                }
+                other => tracing::debug!(option = other, "ignoring unknown enroll option"),
             }
         }
+        if opts.guided && opts.steps.is_some() {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "options 'guided' and 'steps' cannot be combined".into(),
+            ));
+        }
+        Ok(opts)
+    }
 
-        tracing::info!(
-            user,
-            matched = result.result.matched,
-            similarity = result.result.similarity,
-            model_id = ?result.result.model_id,
-            "verify complete"
-        );
+    fn plan(&self) -> StepPlan {
+        if self.guided {
+            StepPlan::Guided
+        } else {
+            StepPlan::Captures(self.steps.unwrap_or(1))
+        }
+    }
+}
 
-        Ok(result.result.matched)
+/// Send the caller of an enrollment-session method its `EnrollProgress`.
+async fn send_enroll_progress(
+    emitter: &SignalEmitter<'_>,
+    header: &zbus::message::Header<'_>,
+    progress: Option<(u32, u32, &str)>,
+) {
+    let (Some(sender), Some((step, total, hint))) = (header.sender(), progress) else {
+        return;
+    };
+    let caller = emitter.clone().set_destination(sender.clone().into());
+    if let Err(e) = VisageService::enroll_progress(&caller, step, total, hint).await {
+        tracing::debug!(error = %e, "failed to emit EnrollProgress signal");
     }
+}
 
-    /// Return daemon status information as JSON.
-    async fn status(&self) -> zbus::fdo::Result<String> {
-        let state = self.state.lock().await;
-        let model_count = state.store.count_all().await.unwrap_or(0);
+/// Range of a per-model threshold offset. Loosening is bounded more tightly
+/// than tightening: a negative offset weakens that template for everyone.
+const MIN_THRESHOLD_OFFSET: f64 = -0.10;
+const MAX_THRESHOLD_OFFSET: f64 = 0.30;
 
-        Ok(serde_json::json!({
-            "version": env!("CARGO_PKG_VERSION"),
-            "camera": state.config.camera_device,
-            "model_dir": state.config.model_dir.display().to_string(),
-            "db_path": state.config.db_path.display().to_string(),
-            "models_enrolled": model_count,
-            "similarity_threshold": state.config.similarity_threshold,
-            "verify_timeout_secs": state.config.verify_timeout_secs,
-            "warmup_frames": state.config.warmup_frames,
-            "frames_per_verify": state.config.frames_per_verify,
-            "frames_per_enroll": state.config.frames_per_enroll,
-            "emitter_enabled": state.config.emitter_enabled,
-            "liveness_enabled": state.config.liveness_enabled,
-            "liveness_min_displacement": state.config.liveness_min_displacement,
-            "session_bus": state.config.session_bus,
-        })
-        .to_string())
+/// Validate a requested per-model threshold offset.
+fn checked_threshold_offset(offset: f64) -> zbus::fdo::Result<f32> {
+    if (MIN_THRESHOLD_OFFSET..=MAX_THRESHOLD_OFFSET).contains(&offset) {
+        Ok(offset as f32)
+    } else {
+        Err(zbus::fdo::Error::InvalidArgs(format!(
+            "threshold offset must be between {MIN_THRESHOLD_OFFSET} and {MAX_THRESHOLD_OFFSET}"
+        )))
     }
+}
 
-    /// List enrolled face models for the given user as JSON.
-    async fn list_models(
-        &self,
-        user: &str,
-        #[zbus(header)] header: zbus::message::Header<'_>,
-        #[zbus(connection)] conn: &zbus::Connection,
-    ) -> zbus::fdo::Result<String> {
-        tracing::info!(user, "list_models requested");
-        // Defense-in-depth: enrollment listing is a root-only operation.
-        let session_bus = self.state.lock().await.config.session_bus;
-        require_root_caller("ListModels", session_bus, &header, conn).await?;
-        let state = self.state.lock().await;
-        let models = state
-            .store
-            .list_by_user(user)
-            .await
-            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
-        serde_json::to_string(&models).map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+/// The models of an opened bundle, checked as a `SetThresholdOffset` and the
+/// store would check them: a bundle is sealed, not trusted.
+fn checked_import(models: Vec<ExportedModel>) -> Result<Vec<NewModel>, BusError> {
+    if models.is_empty() {
+        return Err(zbus::fdo::Error::InvalidArgs("bundle holds no face models".into()).into());
+    }
+    let time = |at: &str| {
+        chrono::DateTime::parse_from_rfc3339(at)
+            .map(|_| ())
+            .map_err(|e| zbus::fdo::Error::InvalidArgs(format!("bundle time '{at}': {e}")))
+    };
+    for model in &models {
+        checked_threshold_offset(f64::from(model.threshold_offset))?;
+        time(&model.created_at)?;
+        if let Some(at) = &model.last_matched_at {
+            time(at)?;
+        }
     }
+    Ok(models.into_iter().map(NewModel::from).collect())
+}
 
-    /// Remove an enrolled face model by ID (scoped to user).
-    async fn remove_model(
-        &self,
-        user: &str,
-        model_id: &str,
-        #[zbus(header)] header: zbus::message::Header<'_>,
-        #[zbus(connection)] conn: &zbus::Connection,
-    ) -> zbus::fdo::Result<bool> {
+/// Least time left on a verify deadline for a confirmation burst to be worth
+/// starting: camera warmup plus a few frames.
+const MIN_CONFIRM_TIME: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Effective verify timeout in seconds: a requested timeout can only shorten
+/// the configured one.
+fn effective_timeout(requested: Option<u64>, configured: u64) -> u64 {
+    requested.map_or(configured, |t| t.min(configured))
+}
+
+/// D-Bus error of a Visage method: a standard `org.freedesktop.DBus.Error`
+/// raised by the interface itself (bad options, caller checks), or a
+/// [`VisageError`] sent under the [D-Bus name](ErrorKind::dbus_name) of its
+/// kind, so a client can tell a dark room from a missing model by error name
+/// rather than by message text.
+#[derive(Debug)]
+pub enum BusError {
+    Fdo(zbus::fdo::Error),
+    Visage(VisageError),
+}
+
+impl From<zbus::fdo::Error> for BusError {
+    fn from(e: zbus::fdo::Error) -> Self {
+        Self::Fdo(e)
+    }
+}
+
+impl From<VisageError> for BusError {
+    fn from(e: VisageError) -> Self {
+        Self::Visage(e)
+    }
+}
+
+impl From<EngineError> for BusError {
+    fn from(e: EngineError) -> Self {
+        Self::Visage(e.into())
+    }
+}
+
+impl From<StoreError> for BusError {
+    fn from(e: StoreError) -> Self {
+        Self::Visage(e.into())
+    }
+}
+
+impl From<SessionError> for BusError {
+    fn from(e: SessionError) -> Self {
+        Self::Visage(e.into())
+    }
+}
+
+impl zbus::DBusError for BusError {
+    fn create_reply(&self, call: &zbus::message::Header<'_>) -> zbus::Result<zbus::Message> {
+        match self {
+            Self::Fdo(e) => e.create_reply(call),
+            Self::Visage(e) => zbus::Message::error(call, self.name())?.build(&(e.message(),)),
+        }
+    }
+
+    fn name(&self) -> zbus::names::ErrorName<'_> {
+        match self {
+            Self::Fdo(e) => e.name(),
+            Self::Visage(e) => {
+                zbus::names::ErrorName::from_static_str_unchecked(e.kind().dbus_name())
+            }
+        }
+    }
+
+    fn description(&self) -> Option<&str> {
+        match self {
+            Self::Fdo(e) => e.description(),
+            Self::Visage(e) => Some(e.message()),
+        }
+    }
+}
+
+impl BusError {
+    /// Stable code of the error: the [`ErrorKind::code`] of its kind, or of
+    /// the kind a standard D-Bus error stands for.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::Fdo(e) => ErrorKind::from_dbus_name(zbus::DBusError::name(e).as_str())
+                .unwrap_or(ErrorKind::Internal)
+                .code(),
+            Self::Visage(e) => e.code(),
+        }
+    }
+}
+
+/// `NAME: description`, like the `zbus::fdo::Error` it may wrap; the socket
+/// transports send this text.
+impl std::fmt::Display for BusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fdo(e) => e.fmt(f),
+            Self::Visage(e) => write!(f, "{}: {}", e.kind().dbus_name(), e.message()),
+        }
+    }
+}
+
+/// Outcome of a verify attempt that got past the caller, rate-limit, and
+/// enrollment checks.
+pub(crate) enum VerifyOutcome {
+    /// A match, with the best similarity.
+    Matched(f32),
+    /// A deliberate authentication failure, with the best similarity (0.0
+    /// when no face was compared); counted by the rate limiter.
+    Rejected(FailureReason, f32),
+    /// The attempt could not complete (dark frames, no face, camera error, ...);
+    /// not counted by the rate limiter.
+    Aborted(FailureReason, EngineError),
+}
+
+impl VerifyOutcome {
+    /// `(matched, reason)` reply shared by `VerifyWithReason` and `VerifyWithOptions`.
+    pub(crate) fn into_reply(self) -> (bool, String) {
+        match self {
+            VerifyOutcome::Matched(_) => (true, String::new()),
+            VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _) => {
+                (false, reason.code().to_string())
+            }
+        }
+    }
+
+    /// `matched`, or the failure reason code.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            VerifyOutcome::Matched(_) => crate::stats::MATCHED,
+            VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _) => reason.code(),
+        }
+    }
+
+    /// Best similarity of the attempt; 0.0 if it was aborted.
+    pub(crate) fn similarity(&self) -> f32 {
+        match self {
+            VerifyOutcome::Matched(similarity) | VerifyOutcome::Rejected(_, similarity) => {
+                *similarity
+            }
+            VerifyOutcome::Aborted(..) => 0.0,
+        }
+    }
+}
+
+/// What a verify decided on besides its [`VerifyOutcome`], for the audit log
+/// and `VerifyDetailed`.
+#[derive(Debug, Default)]
+pub(crate) struct AttemptDetails {
+    /// The matching model.
+    model_id: Option<String>,
+    model_label: Option<String>,
+    /// Detector confidence of the best-matching face; 0.0 when none was
+    /// compared.
+    confidence: f32,
+    /// Frames captured, usable or not, and how many were too dark.
+    frames: u64,
+    dark_frames: u64,
+    /// Capture and inference time of the burst that decided.
+    capture_time: std::time::Duration,
+    inference_time: std::time::Duration,
+    /// The frame the decision rests on.
+    frame: Option<FrameMeta>,
+    /// Live probability from the PAD model, when it scored a matched face.
+    pad_score: Option<f32>,
+}
+
+/// The `VerifyDetailed` reply for `outcome`, which took `total`.
+fn detailed_reply(
+    outcome: &VerifyOutcome,
+    details: &AttemptDetails,
+    total: std::time::Duration,
+) -> HashMap<String, OwnedValue> {
+    let (matched, reason) = match outcome {
+        VerifyOutcome::Matched(_) => (true, ""),
+        VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _) => {
+            (false, reason.code())
+        }
+    };
+    let millis = |d: std::time::Duration| d.as_millis() as u64;
+    [
+        ("matched", Value::from(matched)),
+        ("reason", Value::from(reason)),
+        ("similarity", Value::from(f64::from(outcome.similarity()))),
+        (
+            "model_id",
+            Value::from(details.model_id.clone().unwrap_or_default()),
+        ),
+        (
+            "model_label",
+            Value::from(details.model_label.clone().unwrap_or_default()),
+        ),
+        ("confidence", Value::from(f64::from(details.confidence))),
+        ("frames", Value::from(details.frames)),
+        ("dark_frames", Value::from(details.dark_frames)),
+        ("capture_ms", Value::from(millis(details.capture_time))),
+        ("inference_ms", Value::from(millis(details.inference_time))),
+        ("total_ms", Value::from(millis(total))),
+        (
+            "pad_score",
+            Value::from(details.pad_score.map_or(-1.0, f64::from)),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key.to_string(), OwnedValue::try_from(value).ok()?)))
+    .collect()
+}
+
+impl VisageService {
+    /// Shared implementation of `Verify`, `VerifyWithReason`,
+    /// `VerifyWithOptions`, and `VerifyBytes`. `account` is the target's
+    /// exact name; the gallery is looked up under its [`account_key`].
+    async fn run_verify(
+        &self,
+        account: &[u8],
+        opts: &VerifyOptions,
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+        emitter: &SignalEmitter<'_>,
+    ) -> Result<(VerifyOutcome, AttemptDetails), BusError> {
+        let tenant = opts.tenant();
+        let user = account_key(account);
+        let user = user.as_str();
+        tracing::info!(user, tenant, "verify requested");
+
+        // Read session_bus flag without holding lock across the async UID lookup
+        let session_bus = self.state.lock().await.config.session_bus;
+
+        // --- Caller validation ---
+        // Users in a named tenant are not system accounts: the tenant's
+        // configured callers may verify any of them. In the default tenant the
+        // caller UID must match the target user (system bus only).
+        let mut caller_is_root = session_bus && tenant == DEFAULT_TENANT;
+        let caller_uid = self.optional_caller(session_bus, header, conn).await?;
+        if tenant != DEFAULT_TENANT {
+            self.authorize_tenant("VerifyWithOptions", tenant, header, conn)
+                .await?;
+        } else if let Some(caller_uid) = caller_uid {
+            check_verify_caller(caller_uid, account)?;
+            caller_is_root = caller_uid == 0;
+        }
+        opts.check(&self.state.lock().await.config)?;
+
+        let cancel = match &opts.cookie {
+            Some(cookie) => Some(
+                self.register_cancel(cookie, session_bus, header, conn)
+                    .await?,
+            ),
+            None => None,
+        };
+        // Addressed to the caller, like `VerifyProgress`: broadcast, these
+        // would tell every bus client who is authenticating and how close
+        // each attempt came.
+        let caller = header
+            .sender()
+            .map(|sender| emitter.clone().set_destination(sender.clone().into()));
+        if let Some(caller) = &caller {
+            if let Err(e) = Self::verify_started(caller, user).await {
+                tracing::warn!(error = %e, "failed to emit VerifyStarted signal");
+            }
+        }
+        let (progress_tx, progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let verify = self.verify_checked(
+            user,
+            tenant,
+            opts.timeout_secs,
+            Some(progress_tx),
+            cancel,
+            Some(emitter),
+            caller_uid,
+        );
+        let relay = relay_progress(progress_rx, emitter, header, user);
+        let (result, ()) = tokio::join!(verify, relay);
+        if let Some(cookie) = &opts.cookie {
+            self.state.lock().await.cancels.remove(cookie);
+        }
+
+        if let Ok((VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _), _)) =
+            &result
+        {
+            if let Some(caller) = &caller {
+                if let Err(e) = Self::verify_failed(caller, user, reason.code()).await {
+                    tracing::warn!(error = %e, "failed to emit VerifyFailed signal");
+                }
+            }
+        }
+        // Also sent when the attempt ends in an error (rate limited, nothing
+        // enrolled), so every VerifyStarted is paired.
+        let (matched, similarity) = match &result {
+            Ok((outcome, _)) => (
+                matches!(outcome, VerifyOutcome::Matched(_)),
+                f64::from(outcome.similarity()),
+            ),
+            Err(_) => (false, 0.0),
+        };
+        if let Some(caller) = &caller {
+            if let Err(e) = Self::verify_completed(caller, user, matched, similarity).await {
+                tracing::warn!(error = %e, "failed to emit VerifyCompleted signal");
+            }
+        }
+        let (outcome, details) = result?;
+
+        if matches!(outcome, VerifyOutcome::Matched(_)) && opts.release_authtok {
+            if caller_is_root {
+                self.state.lock().await.authtok_tickets.issue(user);
+            } else {
+                tracing::warn!(user, "release_authtok ignored: caller is not root");
+            }
+        }
+        Ok((outcome, details))
+    }
+
+    /// Verify `user` for a caller that has already been authorized: rate
+    /// limit, gallery lookup, engine call, and outcome bookkeeping (rate
+    /// limiter, challenges, hooks, audit log). Shared by the D-Bus methods
+    /// and the socket transports, which have no `emitter` for signals.
+    /// `timeout_secs` can only shorten the configured deadline. `caller_uid`
+    /// (`None` when unknown) goes into the audit log.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn verify_checked(
+        &self,
+        user: &str,
+        tenant: &str,
+        timeout_secs: Option<u64>,
+        progress: Option<ProgressSender>,
+        cancel: Option<Arc<AtomicBool>>,
+        emitter: Option<&SignalEmitter<'_>>,
+        caller_uid: Option<u32>,
+    ) -> Result<(VerifyOutcome, AttemptDetails), BusError> {
+        let started = std::time::Instant::now();
+        let result = self
+            .verify_attempt(user, tenant, timeout_secs, progress, cancel, emitter)
+            .await;
+        let (code, similarity, details) = match &result {
+            Ok((VerifyOutcome::Aborted(reason, _), _)) => (reason.code(), None, None),
+            Ok((outcome, details)) => (outcome.code(), Some(outcome.similarity()), Some(details)),
+            Err(e) => (e.code(), None, None),
+        };
+        crate::metrics::global().record_verify(code, started.elapsed());
+        self.audit(AuditEvent {
+            operation: "verify",
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            result: code.to_string(),
+            similarity,
+            model_id: details.and_then(|d| d.model_id.clone()),
+            frame: details.and_then(|d| d.frame.clone()),
+            pad_score: details.and_then(|d| d.pad_score),
+            caller_uid,
+            duration: started.elapsed(),
+        })
+        .await;
+        result
+    }
+
+    /// The body of [`verify_checked`](Self::verify_checked). Also returns
+    /// the ID of the matching model and the frame the decision rests on.
+    async fn verify_attempt(
+        &self,
+        user: &str,
+        tenant: &str,
+        timeout_secs: Option<u64>,
+        progress: Option<ProgressSender>,
+        cancel: Option<Arc<AtomicBool>>,
+        emitter: Option<&SignalEmitter<'_>>,
+    ) -> Result<(VerifyOutcome, AttemptDetails), BusError> {
+        // --- Rate limit check ---
+        let rate_key = rate_key(tenant, user);
+        {
+            let mut state = self.state.lock().await;
+            state.rate_limiter.check(&rate_key).map_err(|msg| {
+                tracing::warn!(user, "verify: rate limited");
+                VisageError::new(ErrorKind::RateLimited, msg)
+            })?;
+        }
+
+        // --- Fetch gallery and config (release lock before engine call) ---
+        let (
+            engine,
+            gallery,
+            threshold,
+            frames_count,
+            timeout_secs,
+            liveness_enabled,
+            liveness_min_displacement,
+            confirm_gallery,
+            challenged,
+            snapshots,
+        ) = {
+            let mut state = self.state.lock().await;
+            let gallery = match state
+                .warm
+                .gallery(user)
+                .filter(|_| tenant == DEFAULT_TENANT)
+            {
+                Some(gallery) => gallery.to_vec(),
+                None => {
+                    let gallery = state
+                        .store
+                        .get_gallery_for_user(tenant, user)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!(error = %e, "verify: gallery fetch failed");
+                            e
+                        })?;
+                    if tenant == DEFAULT_TENANT {
+                        state.warm.store_gallery(user, gallery.clone());
+                    }
+                    gallery
+                }
+            };
+            let configured = state.config.verify_timeout_secs;
+            // Kept for a confirmation burst; see `Config::is_borderline`.
+            let confirm_gallery = (state.config.confirm_margin > 0.0).then(|| gallery.clone());
+            let config = &state.config;
+            let (mut threshold, mut frames_count, mut liveness_enabled) = (
+                config.similarity_threshold,
+                config.frames_per_verify,
+                config.liveness_enabled,
+            );
+            // A pending challenge makes this verify strict; see `challenge`.
+            let policy = config.challenge_policy();
+            let challenged = policy.is_enabled() && state.challenges.is_pending(&rate_key);
+            if challenged {
+                threshold += policy.threshold_boost;
+                frames_count *= 2;
+                liveness_enabled = cfg!(feature = "liveness");
+                tracing::info!(
+                    user,
+                    threshold,
+                    frames_count,
+                    "verify: challenged, strict settings"
+                );
+            }
+            (
+                state.engine.clone(),
+                gallery,
+                threshold,
+                frames_count,
+                effective_timeout(timeout_secs, configured),
+                liveness_enabled,
+                config.liveness_min_displacement,
+                confirm_gallery,
+                challenged,
+                config.snapshot_policy(),
+            )
+        };
+
+        if gallery.is_empty() {
+            tracing::warn!(user, "verify: no enrolled models");
+            return Err(VisageError::new(
+                ErrorKind::NotEnrolled,
+                format!("no enrolled models for user '{user}'"),
+            )
+            .into());
+        }
+
+        // --- Run engine with timeout (no lock held) ---
+        // Every failure is classified. Liveness failures and below-threshold faces
+        // are deliberate auth failures and are rate-limited; runtime errors (camera
+        // failure, dark frames, timeout) are not.
+        let timeout = std::time::Duration::from_secs(timeout_secs);
+        let started = std::time::Instant::now();
+        let tally = Arc::new(if snapshots.is_enabled() && tenant == DEFAULT_TENANT {
+            CaptureTally::keeping_frame()
+        } else {
+            CaptureTally::default()
+        });
+        let mut result = engine
+            .verify(
+                gallery,
+                threshold,
+                frames_count,
+                timeout,
+                liveness_enabled,
+                liveness_min_displacement,
+                progress.clone(),
+                cancel.clone(),
+                Some(tally.clone()),
+            )
+            .await;
+
+        // --- Borderline rejection: decide on one more burst ---
+        // Only a plain below-threshold score within the confirm margin, with
+        // time left on the caller's deadline, gets a second look.
+        let remaining = timeout.saturating_sub(started.elapsed());
+        let borderline = match &result {
+            Ok(first) => {
+                FailureReason::from_result(first) == Some(FailureReason::BelowThreshold)
+                    && self
+                        .state
+                        .lock()
+                        .await
+                        .config
+                        .is_borderline(first.result.similarity)
+            }
+            Err(_) => false,
+        };
+        let cancelled = cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed));
+        if let Some(gallery) =
+            confirm_gallery.filter(|_| borderline && !cancelled && remaining >= MIN_CONFIRM_TIME)
+        {
+            tracing::info!(
+                user,
+                similarity = result.as_ref().map_or(0.0, |r| r.result.similarity),
+                "verify: borderline score, capturing a confirmation burst"
+            );
+            let confirmation = engine
+                .verify(
+                    gallery,
+                    threshold,
+                    frames_count,
+                    remaining,
+                    liveness_enabled,
+                    liveness_min_displacement,
+                    progress,
+                    cancel,
+                    Some(tally.clone()),
+                )
+                .await;
+            // A confirmation that fails outright (dark, no face, timeout)
+            // leaves the first burst's rejection standing.
+            if confirmation.is_ok() {
+                result = confirmation;
+            }
+        }
+        let mut near_miss = None;
+        let mut matched_model = None;
+        let mut probe = None;
+        let mut details = AttemptDetails::default();
+        let outcome = match result {
+            Ok(result) => {
+                tracing::info!(
+                    user,
+                    matched = result.result.matched,
+                    similarity = result.result.similarity,
+                    face_size = result.best_face_size,
+                    model_id = ?result.result.model_id,
+                    frame = ?result.frame,
+                    "verify complete"
+                );
+                details.confidence = result.best_quality;
+                details.capture_time = result.capture_time;
+                details.inference_time = result.inference_time;
+                details.frame = result.frame.clone();
+                details.pad_score = result.pad_score;
+                let similarity = result.result.similarity;
+                let reason = FailureReason::from_result(&result);
+                probe = result
+                    .probe
+                    .map(|embedding| (embedding, similarity, reason.is_none()));
+                match reason {
+                    None => {
+                        matched_model = result.result.model_id;
+                        details.model_label = result.result.model_label;
+                        VerifyOutcome::Matched(similarity)
+                    }
+                    Some(reason) => {
+                        if reason == FailureReason::BelowThreshold {
+                            near_miss = result.result.closest_model_id.map(|id| (id, similarity));
+                        }
+                        VerifyOutcome::Rejected(reason, similarity)
+                    }
+                }
+            }
+            Err(e) => {
+                if let EngineError::PresentationAttack { score, .. } = e {
+                    details.pad_score = Some(score);
+                }
+                let reason = FailureReason::from_engine_error(&e);
+                if reason.counts_as_attempt() {
+                    tracing::warn!(user, error = %e, %reason, "verify: treating as non-match");
+                    VerifyOutcome::Rejected(reason, 0.0)
+                } else {
+                    tracing::error!(error = %e, %reason, "verify failed");
+                    VerifyOutcome::Aborted(reason, e)
+                }
+            }
+        };
+
+        // --- Add the attempt to the history (see `stats`) ---
+        let attempt = VerifyAttempt {
+            at: chrono::Utc::now().to_rfc3339(),
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            camera: engine.camera_device(),
+            outcome: outcome.code().to_string(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            frames: tally.frames() as u64,
+            dark_frames: tally.dark() as u64,
+        };
+        if let Err(e) = self.state.lock().await.store.record_attempt(attempt).await {
+            tracing::warn!(error = %e, "verify: failed to record attempt");
+        }
+
+        // --- Record rate-limit outcome ---
+        match &outcome {
+            VerifyOutcome::Matched(_) => self
+                .state
+                .lock()
+                .await
+                .rate_limiter
+                .record_success(&rate_key),
+            VerifyOutcome::Rejected(..) => self
+                .state
+                .lock()
+                .await
+                .rate_limiter
+                .record_failure(&rate_key),
+            VerifyOutcome::Aborted(..) => {}
+        }
+
+        // --- Challenges: a strict pass clears one, narrow matches set one ---
+        if let VerifyOutcome::Matched(similarity) = &outcome {
+            self.track_challenge(tenant, user, *similarity, challenged, emitter)
+                .await;
+        }
+
+        // --- Attribute a below-threshold score to the closest model ---
+        if let Some((model_id, similarity)) = near_miss {
+            let state = self.state.lock().await;
+            if let Err(e) = state.store.record_near_miss(&model_id, similarity).await {
+                tracing::warn!(error = %e, "verify: failed to record near miss");
+            }
+        }
+        // --- ...and a match to the matching one (see `retention`) ---
+        if let Some(model_id) = &matched_model {
+            let state = self.state.lock().await;
+            if let Err(e) = state.store.record_match(model_id).await {
+                tracing::warn!(error = %e, "verify: failed to record match");
+            }
+        }
+        // --- Keep the probe for `visage export-scores` (see `scores`) ---
+        if let Some((embedding, similarity, matched)) = probe {
+            let state = self.state.lock().await;
+            let keep = state.config.keep_probes;
+            if keep > 0 {
+                if let Err(e) = state
+                    .store
+                    .record_probe(tenant, user, &embedding, similarity, matched, keep)
+                    .await
+                {
+                    tracing::warn!(error = %e, "verify: failed to keep probe");
+                }
+            }
+        }
+
+        let hooks = self.state.lock().await.hooks.clone();
+        match &outcome {
+            VerifyOutcome::Matched(_) => hooks.emit(HookEvent::VerifySuccess {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+            }),
+            VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _) => {
+                hooks.emit(HookEvent::VerifyFailure {
+                    tenant: tenant.to_string(),
+                    user: user.to_string(),
+                    reason: reason.code(),
+                })
+            }
+        }
+        if let VerifyOutcome::Aborted(FailureReason::CameraError, e) = &outcome {
+            hooks.emit(HookEvent::CameraError {
+                error: e.to_string(),
+            });
+        }
+
+        // --- Keep the frame of a failed attempt (see `snapshots`) ---
+        if let VerifyOutcome::Rejected(reason, _) | VerifyOutcome::Aborted(reason, _) = &outcome {
+            if let Some(frame) = tally
+                .take_frame()
+                .filter(|_| *reason != FailureReason::Cancelled)
+            {
+                let (user, reason) = (user.to_string(), reason.code());
+                tokio::task::spawn_blocking(move || {
+                    match snapshots.save(&user, reason, &frame, chrono::Utc::now()) {
+                        Ok(path) => {
+                            tracing::info!(user, path = %path.display(), "verify: failure snapshot saved")
+                        }
+                        Err(e) => {
+                            tracing::warn!(user, error = %e, "verify: cannot save failure snapshot")
+                        }
+                    }
+                });
+            }
+        }
+
+        details.model_id = matched_model;
+        details.frames = tally.frames() as u64;
+        details.dark_frames = tally.dark() as u64;
+        Ok((outcome, details))
+    }
+
+    /// Append `event` to the audit log.
+    async fn audit(&self, event: AuditEvent) {
+        let audit = self.state.lock().await.audit.clone();
+        audit.record(&event);
+    }
+
+    /// Challenge bookkeeping after `user` matched at `similarity`: clear a
+    /// challenge the verify passed under strict settings, or count a narrow
+    /// match and announce the challenge it triggers. See [`crate::challenge`].
+    async fn track_challenge(
+        &self,
+        tenant: &str,
+        user: &str,
+        similarity: f32,
+        challenged: bool,
+        emitter: Option<&SignalEmitter<'_>>,
+    ) {
+        let key = rate_key(tenant, user);
+        let now = std::time::Instant::now();
+        let (triggered, hooks) = {
+            let mut state = self.state.lock().await;
+            if challenged {
+                if let Some(pending) = state.challenges.passed(&key, now) {
+                    tracing::info!(
+                        tenant,
+                        user,
+                        pending_secs = pending.as_secs(),
+                        "challenge passed"
+                    );
+                }
+                return;
+            }
+            let policy = state.config.challenge_policy();
+            let threshold = state.config.similarity_threshold;
+            let hour = chrono::Timelike::hour(&chrono::Local::now());
+            let triggered = state
+                .challenges
+                .record_match(&policy, &key, similarity, threshold, hour, now);
+            (triggered, state.hooks.clone())
+        };
+        let Some(narrow_matches) = triggered else {
+            return;
+        };
+        tracing::warn!(
+            tenant,
+            user,
+            narrow_matches,
+            similarity,
+            "repeated narrow matches; the next verify is a challenge"
+        );
+        if let Some(emitter) = emitter {
+            if let Err(e) = Self::challenge_required(emitter, tenant, user, narrow_matches).await {
+                tracing::warn!(error = %e, "failed to emit ChallengeRequired signal");
+            }
+        }
+        hooks.emit(HookEvent::ChallengeRequired {
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            narrow_matches,
+        });
+    }
+
+    /// The caller's UID, or `None` on the session bus where UIDs are not
+    /// checked.
+    async fn optional_caller(
+        &self,
+        session_bus: bool,
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+    ) -> zbus::fdo::Result<Option<u32>> {
+        if session_bus {
+            Ok(None)
+        } else {
+            Ok(Some(caller_uid(header, conn).await?))
+        }
+    }
+
+    /// Record a cancel flag for the verify identified by `cookie`, owned by
+    /// the calling UID.
+    async fn register_cancel(
+        &self,
+        cookie: &str,
+        session_bus: bool,
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+    ) -> zbus::fdo::Result<Arc<AtomicBool>> {
+        let owner = self.optional_caller(session_bus, header, conn).await?;
+        self.add_cancel(cookie, owner).await
+    }
+
+    /// Record a cancel flag for the verify identified by `cookie`, owned by
+    /// `owner` (`None` when unknown). Shared with the socket transport.
+    pub(crate) async fn add_cancel(
+        &self,
+        cookie: &str,
+        owner: Option<u32>,
+    ) -> zbus::fdo::Result<Arc<AtomicBool>> {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut state = self.state.lock().await;
+        if state.cancels.contains_key(cookie) {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "option 'cookie' is already in use".to_string(),
+            ));
+        }
+        state
+            .cancels
+            .insert(cookie.to_string(), (owner, Arc::clone(&flag)));
+        Ok(flag)
+    }
+
+    /// Set the cancel flag of the verify started with `cookie`, if `caller`
+    /// (`None` when unknown) may stop it. Shared by `Cancel` and the socket
+    /// transport.
+    pub(crate) async fn cancel_checked(&self, cookie: &str, caller: Option<u32>) -> bool {
+        let state = self.state.lock().await;
+        let Some((owner, flag)) = state.cancels.get(cookie) else {
+            return false;
+        };
+        if !may_cancel(caller, *owner) {
+            tracing::warn!(?caller, "cancel: caller does not own the verify");
+            return false;
+        }
+        flag.store(true, Ordering::Relaxed);
+        tracing::info!("verify cancelled by caller");
+        true
+    }
+
+    /// ANN index for a tenant's identification gallery, or `None` to scan it
+    /// exhaustively.
+    ///
+    /// Only galleries of at least `VISAGE_IDENTIFY_INDEX_MIN_MODELS` are indexed.
+    /// The cached index is reused while the gallery is unchanged; after an
+    /// enroll or removal it is rebuilt at most once per
+    /// `VISAGE_IDENTIFY_INDEX_REBUILD_SECS`, so a burst of enrollments does not
+    /// trigger a rebuild per request. Until then the scan stays exhaustive.
+    async fn identify_index(
+        &self,
+        tenant: &str,
+        gallery: &[FaceModel],
+    ) -> Option<Arc<GalleryIndex>> {
+        {
+            let state = self.state.lock().await;
+            let min_models = state.config.identify_index_min_models;
+            if min_models == 0 || gallery.len() < min_models {
+                return None;
+            }
+            match state.identify_index.get(tenant) {
+                Some((index, _)) if index.matches(gallery) => return Some(index.clone()),
+                Some((_, built_at))
+                    if built_at.elapsed().as_secs() < state.config.identify_index_rebuild_secs =>
+                {
+                    return None;
+                }
+                _ => {}
+            }
+        }
+
+        let models = gallery.to_vec();
+        let started = std::time::Instant::now();
+        let index = match tokio::task::spawn_blocking(move || GalleryIndex::build(&models)).await {
+            Ok(index) => Arc::new(index),
+            Err(e) => {
+                tracing::error!(error = %e, "identify: index build failed");
+                return None;
+            }
+        };
+        tracing::info!(
+            tenant,
+            models = gallery.len(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "identify: gallery index rebuilt"
+        );
+        self.state.lock().await.identify_index.insert(
+            tenant.to_string(),
+            (index.clone(), std::time::Instant::now()),
+        );
+        Some(index)
+    }
+
+    /// Require `tenant` to be configured in `VISAGE_TENANTS` and the caller to
+    /// be root or one of its accounts (the account check is skipped on the
+    /// session bus).
+    async fn authorize_tenant(
+        &self,
+        method: &str,
+        tenant: &str,
+        header: &zbus::message::Header<'_>,
+        conn: &zbus::Connection,
+    ) -> zbus::fdo::Result<()> {
+        let (session_bus, allowed) = {
+            let state = self.state.lock().await;
+            (
+                state.config.session_bus,
+                state.config.tenants.get(tenant).cloned(),
+            )
+        };
+        let Some(allowed) = allowed else {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "unknown tenant '{tenant}' (not in VISAGE_TENANTS)"
+            )));
+        };
+        if session_bus {
+            return Ok(());
+        }
+        let sender = header
+            .sender()
+            .ok_or_else(|| zbus::fdo::Error::Failed("no sender in message".to_string()))?;
+        let caller_uid = get_caller_uid(sender.as_str(), conn).await?;
+        if caller_is_listed(caller_uid, &allowed) {
+            return Ok(());
+        }
+        tracing::warn!(method, tenant, caller_uid, "tenant access denied");
+        Err(zbus::fdo::Error::AccessDenied(format!(
+            "caller may not use tenant '{tenant}'"
+        )))
+    }
+
+    /// Shared implementation of `Enroll` and `EnrollIn`, after the caller check.
+    async fn run_enroll(
+        &self,
+        tenant: &str,
+        user: &str,
+        label: &str,
+        consent: &Consent,
+    ) -> Result<String, BusError> {
+        let started = std::time::Instant::now();
+        let stored = async {
+            // Copy values while holding lock, then release
+            let (engine, frames_count) = {
+                let state = self.state.lock().await;
+                (state.engine.clone(), state.config.frames_per_enroll)
+            };
+
+            // Run engine (no lock held)
+            let result = match engine.enroll(frames_count, None).await {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::error!(error = %e, "enroll failed");
+                    if let EngineError::Camera(_) = e {
+                        let hooks = self.state.lock().await.hooks.clone();
+                        hooks.emit(HookEvent::CameraError {
+                            error: e.to_string(),
+                        });
+                    }
+                    return Err(e.into());
+                }
+            };
+
+            tracing::info!(
+                quality = result.quality_score,
+                "enroll: embedding extracted"
+            );
+
+            // Store result (re-acquire lock)
+            let mut batch = self
+                .store_models(tenant, user, label, &[(&result, None)], consent, false)
+                .await?;
+            Ok(batch.inserted.remove(0))
+        }
+        .await;
+        self.audit_enroll(tenant, user, stored.as_deref(), consent, started)
+            .await;
+        stored
+    }
+
+    /// Audit the enrollment of `model_id` (or its failure) for `consent`'s
+    /// caller.
+    async fn audit_enroll(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: Result<&str, &BusError>,
+        consent: &Consent,
+        started: std::time::Instant,
+    ) {
+        self.audit(AuditEvent {
+            operation: "enroll",
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            result: match model_id {
+                Ok(_) => "enrolled",
+                Err(e) => e.code(),
+            }
+            .to_string(),
+            similarity: None,
+            model_id: model_id.ok().map(str::to_string),
+            frame: None,
+            pad_score: None,
+            caller_uid: consent.caller_uid,
+            duration: started.elapsed(),
+        })
+        .await;
+    }
+
+    /// Save enrollment captures, each taken in its guided pose if any, as
+    /// face models with the enrollment's consent record, in one store
+    /// transaction. With `replace` the user's existing models are deleted in
+    /// that transaction (a re-enrollment).
+    async fn store_models(
+        &self,
+        tenant: &str,
+        user: &str,
+        label: &str,
+        captures: &[(&EnrollResult, Option<Pose>)],
+        consent: &Consent,
+        replace: bool,
+    ) -> Result<Batch, BusError> {
+        let models: Vec<NewModel> = captures
+            .iter()
+            .map(|(result, pose)| NewModel {
+                label: label.to_string(),
+                embedding: result.embedding.clone(),
+                quality_score: result.quality_score,
+                pose_label: pose.map_or(DEFAULT_POSE_LABEL, Pose::code).to_string(),
+                history: None,
+            })
+            .collect();
+        let batch = self
+            .save_models(tenant, user, &models, consent, replace, "enrolled")
+            .await?;
+        for model_id in &batch.inserted {
+            tracing::info!(model_id = %model_id, tenant, user, label, "enrolled successfully");
+        }
+        Ok(batch)
+    }
+
+    /// Store `models` for `user` in one transaction (see `store_models`), and
+    /// announce each inserted one to the hooks with `action`.
+    async fn save_models(
+        &self,
+        tenant: &str,
+        user: &str,
+        models: &[NewModel],
+        consent: &Consent,
+        replace: bool,
+        action: &'static str,
+    ) -> Result<Batch, BusError> {
+        let mut state = self.state.lock().await;
+        let batch = state
+            .store
+            .insert_batch(tenant, user, models, Some(consent), replace)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, action, "store insert failed");
+                e
+            })?;
+
+        crate::properties::changed();
+        if tenant == DEFAULT_TENANT {
+            state.warm.invalidate(user);
+        }
+        for model_id in &batch.replaced {
+            tracing::info!(model_id = %model_id, tenant, user, "model replaced by re-enrollment");
+            state.hooks.emit(HookEvent::EnrollmentChanged {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                model_id: model_id.clone(),
+                action: "removed",
+            });
+        }
+        for model_id in &batch.inserted {
+            state.hooks.emit(HookEvent::EnrollmentChanged {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                model_id: model_id.clone(),
+                action,
+            });
+        }
+        Ok(batch)
+    }
+
+    /// Shared implementation of `ListModels` and `ListModelsIn`.
+    async fn run_list_models(&self, tenant: &str, user: &str) -> Result<String, BusError> {
+        let state = self.state.lock().await;
+        let mut models = state.store.list_by_user(tenant, user).await?;
+        let gallery = state.store.get_gallery_for_user(tenant, user).await?;
+        let min_similarity = state.config.gallery_outlier_similarity;
+        if let Some(scores) = consistency::consistency(&gallery) {
+            for (model, score) in gallery.iter().zip(scores) {
+                if let Some(info) = models.iter_mut().find(|info| info.id == model.id) {
+                    info.consistency = Some(f64::from(score));
+                    info.outlier = min_similarity > 0.0 && score < min_similarity;
+                }
+            }
+        }
+        serde_json::to_string(&models).map_err(|e| VisageError::wrap(ErrorKind::Internal, e).into())
+    }
+
+    /// Re-run the consistency check on `user`'s gallery and record the
+    /// result for `Status`. Each newly flagged model is logged, sent to the
+    /// hooks and, with a `conn`, announced with `GalleryOutlier`.
+    pub(crate) async fn check_gallery(
+        &self,
+        tenant: &str,
+        user: &str,
+        conn: Option<&zbus::Connection>,
+    ) {
+        let (flagged, hooks, path) = {
+            let mut state = self.state.lock().await;
+            let min_similarity = state.config.gallery_outlier_similarity;
+            if min_similarity <= 0.0 {
+                return;
+            }
+            let gallery = match state.store.get_gallery_for_user(tenant, user).await {
+                Ok(gallery) => gallery,
+                Err(e) => {
+                    tracing::warn!(tenant, user, error = %e, "gallery check: cannot read gallery");
+                    return;
+                }
+            };
+            let outliers = consistency::outliers(&gallery, min_similarity);
+            let key = (tenant.to_string(), user.to_string());
+            let previous = state.gallery_outliers.remove(&key).unwrap_or_default();
+            let flagged: Vec<Outlier> = outliers
+                .iter()
+                .filter(|o| !previous.iter().any(|p| p.model_id == o.model_id))
+                .cloned()
+                .collect();
+            if !outliers.is_empty() {
+                state.gallery_outliers.insert(key, outliers);
+            }
+            let path = crate::instance::object_path(state.config.instance.as_deref());
+            (flagged, state.hooks.clone(), path)
+        };
+        for outlier in flagged {
+            tracing::warn!(
+                tenant,
+                user,
+                model_id = %outlier.model_id,
+                similarity = outlier.similarity,
+                "gallery check: model does not resemble the user's other models"
+            );
+            if let Some(conn) = conn {
+                let sent = match SignalEmitter::new(conn, path.as_str()) {
+                    Ok(emitter) => {
+                        Self::gallery_outlier(
+                            &emitter,
+                            tenant,
+                            user,
+                            &outlier.model_id,
+                            f64::from(outlier.similarity),
+                        )
+                        .await
+                    }
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = sent {
+                    tracing::warn!(error = %e, "failed to emit GalleryOutlier signal");
+                }
+            }
+            hooks.emit(HookEvent::GalleryOutlier {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                model_id: outlier.model_id,
+                similarity: outlier.similarity,
+            });
+        }
+    }
+
+    /// Re-read the configuration and apply what a running daemon can (see
+    /// [`Config::apply_reload`]). Shared by `Reload` and SIGHUP. A file that
+    /// fails to load leaves the running configuration untouched.
+    pub(crate) async fn reload_config(&self) -> Result<crate::config::Reload, BusError> {
+        let fresh = Config::load().map_err(|e| {
+            tracing::error!(error = %e, "reload failed; keeping the running configuration");
+            VisageError::new(ErrorKind::InvalidArgument, e)
+        })?;
+        let (reload, engine, emitter_enabled, warmup_frames) = {
+            let mut state = self.state.lock().await;
+            let reload = state.config.apply_reload(fresh);
+            let config = &state.config;
+            let (emitter_enabled, warmup_frames) = (config.emitter_enabled, config.warmup_frames);
+            (reload, state.engine.clone(), emitter_enabled, warmup_frames)
+        };
+        if reload
+            .changed
+            .iter()
+            .any(|name| matches!(*name, "emitter_enabled" | "warmup_frames"))
+        {
+            engine.reconfigure(emitter_enabled, warmup_frames).await?;
+        }
+        tracing::info!(changed = ?reload.changed, "configuration reloaded");
+        crate::properties::changed();
+        if reload.restart_needed {
+            tracing::warn!("other settings changed; restart visaged to apply them");
+        }
+        Ok(reload)
+    }
+
+    /// Read the quirk files again and re-probe the IR emitter of the open
+    /// camera; see [`crate::quirk_reload`].
+    pub(crate) async fn reload_quirk_files(&self) -> Result<Reloaded, BusError> {
+        let (dirs, engine) = {
+            let state = self.state.lock().await;
+            (state.config.quirks_dirs.clone(), state.engine.clone())
+        };
+        let mut reloaded = tokio::task::spawn_blocking(move || quirk_reload::load(&dirs))
+            .await
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?;
+        reloaded.emitter = engine.reprobe_emitter().await?;
+        tracing::info!(emitter = ?reloaded.emitter, "quirks reloaded");
+        Ok(reloaded)
+    }
+
+    /// Check every gallery in every tenant; run once at startup.
+    pub(crate) async fn check_all_galleries(&self, conn: &zbus::Connection) {
+        let tenants: Vec<String> = {
+            let state = self.state.lock().await;
+            std::iter::once(DEFAULT_TENANT.to_string())
+                .chain(state.config.tenants.keys().cloned())
+                .collect()
+        };
+        for tenant in tenants {
+            let gallery = self.state.lock().await.store.get_gallery_all(&tenant).await;
+            let mut users: Vec<String> = match gallery {
+                Ok(gallery) => gallery.into_iter().map(|model| model.user).collect(),
+                Err(e) => {
+                    tracing::warn!(tenant, error = %e, "gallery check: cannot read gallery");
+                    continue;
+                }
+            };
+            users.sort();
+            users.dedup();
+            for user in users {
+                self.check_gallery(&tenant, &user, Some(conn)).await;
+            }
+        }
+    }
+
+    /// Apply the retention rules once; see [`crate::retention`]. Purge
+    /// notices are announced with `ModelExpiring` when there is a `conn`.
+    pub(crate) async fn enforce_retention(&self, conn: Option<&zbus::Connection>) {
+        let now = chrono::Utc::now();
+        // With snapshots turned off (`keep` 0), any left over are deleted.
+        let snapshots = self.state.lock().await.config.snapshot_policy();
+        let pruned = tokio::task::spawn_blocking(move || snapshots.prune_all(now))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        match pruned {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!(deleted, "retention: failure snapshots expired"),
+            Err(e) => tracing::warn!(error = %e, "retention: cannot prune failure snapshots"),
+        }
+        let policy = self.state.lock().await.config.retention_policy();
+        if let Some(cutoff) = policy.history_cutoff(now) {
+            let pruned = self
+                .state
+                .lock()
+                .await
+                .store
+                .prune_near_misses(&cutoff.to_rfc3339())
+                .await;
+            match pruned {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!(deleted, "retention: near-miss records expired"),
+                Err(e) => tracing::warn!(error = %e, "retention: cannot prune near misses"),
+            }
+            let pruned = self
+                .state
+                .lock()
+                .await
+                .store
+                .prune_attempts(&cutoff.to_rfc3339())
+                .await;
+            match pruned {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!(deleted, "retention: verify attempts expired"),
+                Err(e) => tracing::warn!(error = %e, "retention: cannot prune verify attempts"),
+            }
+            let pruned = self
+                .state
+                .lock()
+                .await
+                .store
+                .prune_probes(&cutoff.to_rfc3339())
+                .await;
+            match pruned {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!(deleted, "retention: probes expired"),
+                Err(e) => tracing::warn!(error = %e, "retention: cannot prune probes"),
+            }
+        }
+        let Some(cutoff) = policy.notice_cutoff(now) else {
+            return;
+        };
+        let unused = {
+            let state = self.state.lock().await;
+            if let Err(e) = state.store.clear_used_purge_notices().await {
+                tracing::warn!(error = %e, "retention: cannot withdraw purge notices");
+            }
+            state.store.unused_models(&cutoff.to_rfc3339()).await
+        };
+        let unused = match unused {
+            Ok(unused) => unused,
+            Err(e) => {
+                tracing::warn!(error = %e, "retention: cannot list unused models");
+                return;
+            }
+        };
+        for model in unused {
+            match policy.decide(&model, now) {
+                retention::Action::Wait => {}
+                retention::Action::Notify { purge_after } => {
+                    let (recorded, hooks, path) = {
+                        let state = self.state.lock().await;
+                        let recorded = state.store.record_purge_notice(&model.id).await;
+                        let path = crate::instance::object_path(state.config.instance.as_deref());
+                        (recorded, state.hooks.clone(), path)
+                    };
+                    if let Err(e) = recorded {
+                        tracing::warn!(model_id = %model.id, error = %e, "retention: cannot record purge notice");
+                        continue;
+                    }
+                    let purge_after = purge_after.to_rfc3339();
+                    tracing::warn!(
+                        tenant = %model.tenant,
+                        user = %model.user,
+                        model_id = %model.id,
+                        last_used = %model.last_used,
+                        purge_after = %purge_after,
+                        "retention: unused model will be purged"
+                    );
+                    if let Some(conn) = conn {
+                        let sent = match SignalEmitter::new(conn, path.as_str()) {
+                            Ok(emitter) => {
+                                Self::model_expiring(
+                                    &emitter,
+                                    &model.tenant,
+                                    &model.user,
+                                    &model.id,
+                                    &purge_after,
+                                )
+                                .await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = sent {
+                            tracing::warn!(error = %e, "failed to emit ModelExpiring signal");
+                        }
+                    }
+                    hooks.emit(HookEvent::ModelExpiring {
+                        tenant: model.tenant,
+                        user: model.user,
+                        model_id: model.id,
+                        purge_after,
+                    });
+                }
+                retention::Action::Purge => {
+                    tracing::info!(
+                        tenant = %model.tenant,
+                        user = %model.user,
+                        model_id = %model.id,
+                        label = %model.label,
+                        last_used = %model.last_used,
+                        "retention: purging unused model"
+                    );
+                    if let Err(e) = self
+                        .run_remove_model(&model.tenant, &model.user, &model.id, None)
+                        .await
+                    {
+                        tracing::warn!(model_id = %model.id, error = %e, "retention: purge failed");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Shared implementation of `RemoveModel` and `RemoveModelIn`, and of
+    /// retention purges (no `caller_uid`).
+    async fn run_remove_model(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        caller_uid: Option<u32>,
+    ) -> Result<bool, BusError> {
+        let started = std::time::Instant::now();
+        let mut state = self.state.lock().await;
+        let removed = state.store.remove(tenant, user, model_id).await;
+        state.audit.record(&AuditEvent {
+            operation: "remove_model",
+            tenant: tenant.to_string(),
+            user: user.to_string(),
+            result: match &removed {
+                Ok(true) => "removed",
+                Ok(false) => ErrorKind::NotFound.code(),
+                Err(e) => e.kind().code(),
+            }
+            .to_string(),
+            similarity: None,
+            model_id: Some(model_id.to_string()),
+            frame: None,
+            pad_score: None,
+            caller_uid,
+            duration: started.elapsed(),
+        });
+        let removed = removed?;
+        if removed {
+            tracing::info!(model_id, "model removed");
+            crate::properties::changed();
+            if tenant == DEFAULT_TENANT {
+                state.warm.invalidate(user);
+            }
+            state.hooks.emit(HookEvent::EnrollmentChanged {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                model_id: model_id.to_string(),
+                action: "removed",
+            });
+            drop(state);
+            // Removing the flagged model (or enough others) clears the flag.
+            self.check_gallery(tenant, user, None).await;
+        } else {
+            tracing::warn!(
+                model_id,
+                tenant,
+                user,
+                "model not found or not owned by user"
+            );
+        }
+        Ok(removed)
+    }
+
+    /// Shared implementation of `SetThresholdOffset` and
+    /// `SetThresholdOffsetIn`, after the caller check.
+    async fn run_set_threshold_offset(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        offset: f64,
+    ) -> Result<bool, BusError> {
+        let offset = checked_threshold_offset(offset)?;
+        let mut state = self.state.lock().await;
+        let updated = state
+            .store
+            .set_threshold_offset(tenant, user, model_id, offset)
+            .await?;
+        if updated {
+            tracing::info!(model_id, offset, "model threshold offset set");
+            if tenant == DEFAULT_TENANT {
+                state.warm.invalidate(user);
+            }
+            state.hooks.emit(HookEvent::EnrollmentChanged {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                model_id: model_id.to_string(),
+                action: "updated",
+            });
+        } else {
+            tracing::warn!(
+                model_id,
+                tenant,
+                user,
+                "model not found or not owned by user"
+            );
+        }
+        Ok(updated)
+    }
+
+    /// Shared implementation of `IdentifyAny` and `IdentifyIn`, after the
+    /// caller check. `caller_uid` goes into the audit log.
+    async fn run_identify(
+        &self,
+        tenant: &str,
+        caller_uid: Option<u32>,
+    ) -> Result<(String, String), BusError> {
+        let started = std::time::Instant::now();
+        let result = self.identify_attempt(tenant).await;
+        let (code, similarity, model_id) = match &result {
+            Ok(identified) => (
+                identified
+                    .reason
+                    .map_or(crate::stats::MATCHED, FailureReason::code),
+                identified.similarity,
+                identified.model_id.clone(),
+            ),
+            Err(e) => (e.code(), None, None),
+        };
+        crate::metrics::global().record_identify(code, started.elapsed());
+        self.audit(AuditEvent {
+            operation: "identify",
+            tenant: tenant.to_string(),
+            user: result
+                .as_ref()
+                .map(|identified| identified.user.clone())
+                .unwrap_or_default(),
+            result: code.to_string(),
+            similarity,
+            model_id,
+            frame: None,
+            pad_score: None,
+            caller_uid,
+            duration: started.elapsed(),
+        })
+        .await;
+        let identified = result?;
+        Ok((
+            identified.user,
+            identified
+                .reason
+                .map(|r| r.code().to_string())
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// The body of [`run_identify`](Self::run_identify).
+    async fn identify_attempt(&self, tenant: &str) -> Result<Identified, BusError> {
+        // --- Rate limit check ---
+        let rate_key = identify_rate_key(tenant);
+        {
+            let mut state = self.state.lock().await;
+            state.rate_limiter.check(&rate_key).map_err(|msg| {
+                tracing::warn!("identify: rate limited");
+                VisageError::new(ErrorKind::RateLimited, msg)
+            })?;
+        }
+
+        // --- Fetch gallery and config (release lock before engine call) ---
+        // Identification has its own threshold and margin, and liveness is
+        // mandatory regardless of VISAGE_LIVENESS_ENABLED.
+        let (engine, gallery, threshold, margin, frames_count, timeout_secs, min_displacement) = {
+            let state = self.state.lock().await;
+            let gallery = state.store.get_gallery_all(tenant).await.map_err(|e| {
+                tracing::error!(error = %e, "identify: gallery fetch failed");
+                e
+            })?;
+            (
+                state.engine.clone(),
+                gallery,
+                state.config.identify_threshold(),
+                state.config.identify_margin,
+                state.config.frames_per_verify,
+                state.config.verify_timeout_secs,
+                state.config.liveness_min_displacement,
+            )
+        };
+
+        if gallery.is_empty() {
+            return Err(VisageError::new(ErrorKind::NotEnrolled, "no enrolled models").into());
+        }
+
+        let index = self.identify_index(tenant, &gallery).await;
+        let timeout = std::time::Duration::from_secs(timeout_secs);
+        let result = engine
+            .identify(
+                gallery.clone(),
+                index,
+                threshold,
+                margin,
+                frames_count,
+                timeout,
+                min_displacement,
+            )
+            .await;
+
+        let (user, reason) = match &result {
+            Ok(result) => match FailureReason::from_result(result) {
+                None => {
+                    let user = result
+                        .result
+                        .model_id
+                        .as_deref()
+                        .and_then(|id| gallery.iter().find(|m| m.id == id))
+                        .map(|m| m.user.clone());
+                    match user {
+                        Some(user) => (user, None),
+                        None => (String::new(), Some(FailureReason::Internal)),
+                    }
+                }
+                Some(reason) => (String::new(), Some(reason)),
+            },
+            Err(e) => (String::new(), Some(FailureReason::from_engine_error(e))),
+        };
+
+        {
+            let mut state = self.state.lock().await;
+            if let (None, Ok(result)) = (reason, &result) {
+                if let Some(model_id) = &result.result.model_id {
+                    if let Err(e) = state.store.record_match(model_id).await {
+                        tracing::warn!(error = %e, "identify: failed to record match");
+                    }
+                }
+            }
+            match reason {
+                None => state.rate_limiter.record_success(&rate_key),
+                Some(r) if r.counts_as_attempt() => state.rate_limiter.record_failure(&rate_key),
+                Some(_) => {}
+            }
+            state.hooks.emit(match reason {
+                None => HookEvent::IdentifySuccess {
+                    tenant: tenant.to_string(),
+                    user: user.clone(),
+                },
+                Some(reason) => HookEvent::IdentifyFailure {
+                    tenant: tenant.to_string(),
+                    reason: reason.code(),
+                },
+            });
+            if let Err(e) = &result {
+                if reason == Some(FailureReason::CameraError) {
+                    state.hooks.emit(HookEvent::CameraError {
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        match reason {
+            None => tracing::info!(user, tenant, "identify complete"),
+            Some(reason) => tracing::info!(%reason, tenant, "identify: no one recognized"),
+        }
+        let result = result.ok();
+        Ok(Identified {
+            user,
+            reason,
+            similarity: result.as_ref().map(|r| r.result.similarity),
+            model_id: result
+                .and_then(|r| r.result.model_id)
+                .filter(|_| reason.is_none()),
+        })
+    }
+}
+
+/// What an identification found, for the reply and the audit log.
+struct Identified {
+    /// Empty unless someone was recognized.
+    user: String,
+    reason: Option<FailureReason>,
+    similarity: Option<f32>,
+    model_id: Option<String>,
+}
+
+#[interface(name = "org.freedesktop.Visage1")]
+impl VisageService {
+    /// Enroll a new face model for the given user.
+    ///
+    /// Returns the UUID of the newly created model. Callable by root, and by
+    /// other callers for their own account once polkit authorizes them for
+    /// `org.freedesktop.visage1.enroll`.
+    async fn enroll(
+        &self,
+        user: &str,
+        label: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        tracing::info!(user, label, "enroll requested");
+
+        // Enrollment is a privileged mutation: root, or the user themselves
+        // with an administrator's approval through polkit.
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_or_polkit("Enroll", polkit::ENROLL, user, session_bus, &header, conn).await?;
+
+        let consent = Consent::capture("Enroll", &header, conn).await;
+        let model_id = self
+            .run_enroll(DEFAULT_TENANT, user, label, &consent)
+            .await?;
+        self.check_gallery(DEFAULT_TENANT, user, Some(conn)).await;
+        Ok(model_id)
+    }
+
+    /// Enroll a new face model for `user` in a named tenant.
+    ///
+    /// Callable by root and the tenant's accounts in `VISAGE_TENANTS`; fails
+    /// with `InvalidArgs` for a tenant that is not configured. Tenant users
+    /// need not be system accounts.
+    async fn enroll_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        label: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        tracing::info!(tenant, user, label, "enroll requested");
+        self.authorize_tenant("EnrollIn", tenant, &header, conn)
+            .await?;
+        let consent = Consent::capture("EnrollIn", &header, conn).await;
+        let model_id = self.run_enroll(tenant, user, label, &consent).await?;
+        self.check_gallery(tenant, user, Some(conn)).await;
+        Ok(model_id)
+    }
+
+    /// Open a step-wise enrollment of `user` under `label` (see
+    /// [`crate::enrollment`]) and return the session ID. Nothing is captured
+    /// until `EnrollStep`. Options: `steps` (u) — accepted captures required
+    /// before `EnrollCommit`, 1–10, default 1; `guided` (b) — require one
+    /// capture per pose (frontal, slight left, slight right, glasses on or
+    /// off), each above the guided quality bar; `replace` (b) — re-enroll:
+    /// the commit deletes the user's existing models in the same transaction
+    /// that stores the new ones; `tenant` (s) — enroll into a
+    /// named tenant, with the same callers as `EnrollIn`. Without `tenant`,
+    /// the same callers as `Enroll`. Only the caller that began a session (or
+    /// root) may drive it. The caller is sent `EnrollProgress` with the first
+    /// hint.
+    async fn enroll_begin(
+        &self,
+        user: &str,
+        label: &str,
+        options: HashMap<String, OwnedValue>,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<String, BusError> {
+        let opts = EnrollOptions::from_dict(&options)?;
+        let tenant = opts.tenant.as_deref().unwrap_or(DEFAULT_TENANT);
+        tracing::info!(tenant, user, label, "enroll session requested");
+        let session_bus = self.state.lock().await.config.session_bus;
+        if tenant == DEFAULT_TENANT {
+            require_root_or_polkit(
+                "EnrollBegin",
+                polkit::ENROLL,
+                user,
+                session_bus,
+                &header,
+                conn,
+            )
+            .await?;
+        } else {
+            self.authorize_tenant("EnrollBegin", tenant, &header, conn)
+                .await?;
+        }
+        let owner = self.optional_caller(session_bus, &header, conn).await?;
+        let consent = Consent::capture("EnrollBegin", &header, conn).await;
+        let (session, progress) = {
+            let mut state = self.state.lock().await;
+            let session = state.enrollments.begin(
+                tenant,
+                user,
+                label,
+                owner,
+                opts.plan(),
+                opts.replace,
+                consent,
+                std::time::Instant::now(),
+            )?;
+            let progress = state.enrollments.progress(&session);
+            (session, progress)
+        };
+        tracing::info!(session = %session, tenant, user, guided = opts.guided, replace = opts.replace, "enroll session opened");
+        send_enroll_progress(&emitter, &header, progress).await;
+        Ok(session)
+    }
+
+    /// Run one capture for enrollment session `session` and return JSON
+    /// feedback: `accepted` (b), `reason` (a failure code such as `too_dark`
+    /// or `wrong_pose`, empty when accepted), `quality`, `steps_accepted`,
+    /// `steps_required`, `attempts`, `ready` (the session can be committed),
+    /// and for guided sessions the `pose` just asked for and the `hint` for
+    /// the next step. A failed capture leaves the session open for another
+    /// step. The caller is also sent `EnrollProgress`.
+    async fn enroll_step(
+        &self,
+        session: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller = self.optional_caller(session_bus, &header, conn).await?;
+        let (engine, frames_count, target) = {
+            let mut state = self.state.lock().await;
+            let target =
+                state
+                    .enrollments
+                    .start_step(session, caller, std::time::Instant::now())?;
+            (state.engine.clone(), state.config.frames_per_enroll, target)
+        };
+
+        // Run engine (no lock held)
+        let outcome = match engine.enroll(frames_count, target).await {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                tracing::warn!(session, error = %e, "enroll step failed");
+                if let EngineError::Camera(_) = e {
+                    let hooks = self.state.lock().await.hooks.clone();
+                    hooks.emit(HookEvent::CameraError {
+                        error: e.to_string(),
+                    });
+                }
+                Err(FailureReason::from_engine_error(&e))
+            }
+        };
+        let (feedback, progress) = {
+            let mut state = self.state.lock().await;
+            let feedback = state
+                .enrollments
+                .finish_step(session, outcome, std::time::Instant::now())
+                .ok_or(SessionError::Unknown)?;
+            (feedback, state.enrollments.progress(session))
+        };
+        tracing::info!(
+            session,
+            accepted = feedback.accepted,
+            reason = feedback.reason,
+            steps_accepted = feedback.steps_accepted,
+            "enroll step finished"
+        );
+        let json = serde_json::to_string(&feedback)
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?;
+        send_enroll_progress(&emitter, &header, progress).await;
+        Ok(json)
+    }
+
+    /// Store every accepted capture of enrollment session `session` as a face
+    /// model and close the session; returns the new model IDs. The models
+    /// are stored in one transaction (replacing the user's gallery for a
+    /// `replace` session): all of them or none. Fails, leaving the session
+    /// open, until `steps` captures have been accepted.
+    async fn enroll_commit(
+        &self,
+        session: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<Vec<String>, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller = self.optional_caller(session_bus, &header, conn).await?;
+        let enrollment = self.state.lock().await.enrollments.commit(
+            session,
+            caller,
+            std::time::Instant::now(),
+        )?;
+        let started = std::time::Instant::now();
+        let captures: Vec<(&EnrollResult, Option<Pose>)> = enrollment
+            .accepted
+            .iter()
+            .map(|capture| (&capture.result, capture.pose))
+            .collect();
+        let stored = self
+            .store_models(
+                &enrollment.tenant,
+                &enrollment.user,
+                &enrollment.label,
+                &captures,
+                &enrollment.consent,
+                enrollment.replace,
+            )
+            .await;
+        let batch = match stored {
+            Ok(batch) => batch,
+            Err(e) => {
+                self.audit_enroll(
+                    &enrollment.tenant,
+                    &enrollment.user,
+                    Err(&e),
+                    &enrollment.consent,
+                    started,
+                )
+                .await;
+                return Err(e);
+            }
+        };
+        for model_id in &batch.replaced {
+            self.audit(AuditEvent {
+                operation: "remove_model",
+                tenant: enrollment.tenant.clone(),
+                user: enrollment.user.clone(),
+                result: "removed".to_string(),
+                similarity: None,
+                model_id: Some(model_id.clone()),
+                frame: None,
+                pad_score: None,
+                caller_uid: enrollment.consent.caller_uid,
+                duration: started.elapsed(),
+            })
+            .await;
+        }
+        for model_id in &batch.inserted {
+            self.audit_enroll(
+                &enrollment.tenant,
+                &enrollment.user,
+                Ok(model_id),
+                &enrollment.consent,
+                started,
+            )
+            .await;
+        }
+        let model_ids = batch.inserted;
+        tracing::info!(
+            session,
+            models = model_ids.len(),
+            replaced = batch.replaced.len(),
+            "enroll session committed"
+        );
+        self.check_gallery(&enrollment.tenant, &enrollment.user, Some(conn))
+            .await;
+        Ok(model_ids)
+    }
+
+    /// Close enrollment session `session` without storing anything. Returns
+    /// `false` if there is no such session (already committed, aborted, or
+    /// expired).
+    async fn enroll_abort(
+        &self,
+        session: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller = self.optional_caller(session_bus, &header, conn).await?;
+        let aborted =
+            self.state
+                .lock()
+                .await
+                .enrollments
+                .abort(session, caller, std::time::Instant::now());
+        match aborted {
+            Ok(()) => {
+                tracing::info!(session, "enroll session aborted");
+                Ok(true)
+            }
+            Err(SessionError::Unknown) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Verify the current face against enrolled models for the given user.
+    ///
+    /// Returns true if the face matches any enrolled model above the threshold.
+    /// Environmental failures (dark frames, no face, camera error, timeout) are
+    /// returned as D-Bus errors; use `VerifyWithReason` for a classified outcome.
+    /// Each error is named after its kind: an attempt that saw only dark
+    /// frames fails with `org.freedesktop.Visage1.Error.TooDark`, a timeout
+    /// with `org.freedesktop.Visage1.Error.Timeout`, and so on.
+    ///
+    /// Security: on the system bus the caller UID is validated against the target
+    /// username before any camera access or rate-limit check.  Root (UID 0) is always
+    /// permitted.  On the session bus (development mode) UID validation is skipped.
+    async fn verify(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<bool, BusError> {
+        let opts = VerifyOptions::default();
+        match self
+            .run_verify(user.as_bytes(), &opts, &header, conn, &emitter)
+            .await?
+            .0
+        {
+            VerifyOutcome::Matched(_) => Ok(true),
+            VerifyOutcome::Rejected(..) => Ok(false),
+            VerifyOutcome::Aborted(_, e) => Err(e.into()),
+        }
+    }
+
+    /// Verify like `Verify`, but report why a failed attempt failed.
+    ///
+    /// Returns `(matched, reason)`. `reason` is empty on a match, otherwise one of
+    /// the stable codes from `FailureReason::code` (`too_dark`, `no_face`,
+    /// `face_too_small`, `below_threshold`, `liveness_failed`, `static_scene`, `camera_error`,
+    /// `camera_busy`, `timeout`, `internal_error`). Access, rate-limit, and enrollment errors are
+    /// still returned as D-Bus errors. Same caller checks as `Verify`.
+    async fn verify_with_reason(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<(bool, String), BusError> {
+        let opts = VerifyOptions::default();
+        let (outcome, _) = self
+            .run_verify(user.as_bytes(), &opts, &header, conn, &emitter)
+            .await?;
+        Ok(outcome.into_reply())
+    }
+
+    /// Verify like `VerifyWithReason`, but return what the attempt saw as an
+    /// `a{sv}` dict for diagnostics: `matched` (b), `reason` (s, empty on a
+    /// match), `similarity` (d), `model_id` and `model_label` (s, of the
+    /// matching model; empty otherwise), `confidence` (d, detector confidence
+    /// of the best face), `frames` and `dark_frames` (t, captured and skipped
+    /// as too dark), and `capture_ms`, `inference_ms` and `total_ms` (t).
+    /// Same caller checks and errors as `VerifyWithReason`.
+    async fn verify_detailed(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<HashMap<String, OwnedValue>, BusError> {
+        let started = std::time::Instant::now();
+        let opts = VerifyOptions::default();
+        let (outcome, details) = self
+            .run_verify(user.as_bytes(), &opts, &header, conn, &emitter)
+            .await?;
+        Ok(detailed_reply(&outcome, &details, started.elapsed()))
+    }
+
+    /// `VerifyWithReason` with per-call options (`a{sv}`).
+    ///
+    /// Recognised keys: `device` (s) — fail with `InvalidArgs` unless it names
+    /// the daemon's camera; `timeout` (u) — shorten the verify deadline to this
+    /// many seconds; `tenant` (s) — verify against a named tenant's gallery,
+    /// allowed for root and the tenant's accounts in `VISAGE_TENANTS`. Unknown
+    /// keys are ignored. Without `tenant`, same caller checks as `Verify`.
+    async fn verify_with_options(
+        &self,
+        user: &str,
+        options: HashMap<String, OwnedValue>,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<(bool, String), BusError> {
+        let opts = VerifyOptions::from_dict(&options)?;
+        let (outcome, _) = self
+            .run_verify(user.as_bytes(), &opts, &header, conn, &emitter)
+            .await?;
+        Ok(outcome.into_reply())
+    }
+
+    /// `VerifyWithOptions` for an account whose name is not UTF-8: `user` is
+    /// the name's exact bytes. The caller check uses the exact name; the
+    /// gallery, rate limit, signals, and logs use its [`account_key`]: Latin-1
+    /// "josé" (bytes `6a 6f 73 e9`) is stored as the text `jos\xe9`, which is
+    /// also the name to enroll it under. A UTF-8 name behaves exactly as in
+    /// `VerifyWithOptions`.
+    async fn verify_bytes(
+        &self,
+        user: Vec<u8>,
+        options: HashMap<String, OwnedValue>,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> Result<(bool, String), BusError> {
+        let opts = VerifyOptions::from_dict(&options)?;
+        let (outcome, _) = self
+            .run_verify(&user, &opts, &header, conn, &emitter)
+            .await?;
+        Ok(outcome.into_reply())
+    }
+
+    /// Whether `user` has face models in the default tenant, and how many
+    /// seconds ago the newest was enrolled (0 when there are none). Used by
+    /// `pam_sm_acct_mgmt` to flag stale enrollments. Same callers as
+    /// `Verify`: root, or the user themselves.
+    async fn enrollment_age(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(bool, u64), BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        if !session_bus {
+            check_verify_caller(caller_uid(&header, conn).await?, user.as_bytes())?;
+        }
+        let models = self
+            .state
+            .lock()
+            .await
+            .store
+            .list_by_user(DEFAULT_TENANT, user)
+            .await?;
+        let age = newest_model_age(&models, chrono::Utc::now());
+        tracing::debug!(user, age_secs = ?age, "enrollment age requested");
+        Ok((age.is_some(), age.unwrap_or(0)))
+    }
+
+    /// Emitted to the caller of a verify method — and only to it — when the
+    /// attempt for `user` passes the caller checks and goes to the camera.
+    #[zbus(signal)]
+    async fn verify_started(emitter: &SignalEmitter<'_>, user: &str) -> zbus::Result<()>;
+
+    /// Emitted when a verify attempt finds a face, with the detector's
+    /// confidence in it (0.0–1.0). Carries no user name.
+    #[zbus(signal)]
+    async fn face_detected(emitter: &SignalEmitter<'_>, confidence: f64) -> zbus::Result<()>;
+
+    /// Emitted when the camera disconnects during a verify (unplugged, USB
+    /// reset). The daemon re-opens it and captures again if it returns
+    /// before the verify's deadline. Names no user.
+    #[zbus(signal)]
+    async fn camera_disconnected(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    /// Emitted to the caller of a verify method — and only to it — when the
+    /// attempt for `user` that sent `VerifyStarted` ends, whatever the
+    /// result: whether it matched, and the best similarity (0.0 if no face
+    /// was compared).
+    #[zbus(signal)]
+    async fn verify_completed(
+        emitter: &SignalEmitter<'_>,
+        user: &str,
+        matched: bool,
+        similarity: f64,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when the gallery consistency check flags `model_id` of
+    /// `user` in `tenant` as unlike the user's other models, with its median
+    /// similarity to them. `visage notify` turns it into a desktop
+    /// notification.
+    #[zbus(signal)]
+    async fn gallery_outlier(
+        emitter: &SignalEmitter<'_>,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        similarity: f64,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when the retention rules schedule `model_id` of `user` in
+    /// `tenant` for purging because nothing has matched it for
+    /// `VISAGE_PURGE_UNUSED_MONTHS`. It is deleted at the first sweep after
+    /// `purge_after` (RFC 3339) unless it matches before then. `visage
+    /// notify` turns it into a desktop notification.
+    #[zbus(signal)]
+    async fn model_expiring(
+        emitter: &SignalEmitter<'_>,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        purge_after: &str,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when `narrow_matches` matches of `user` in `tenant` that only
+    /// just cleared the threshold make the next verify a strict challenge
+    /// (see `challenge`). `visage notify` turns it into a desktop
+    /// notification.
+    #[zbus(signal)]
+    async fn challenge_required(
+        emitter: &SignalEmitter<'_>,
+        tenant: &str,
+        user: &str,
+        narrow_matches: u32,
+    ) -> zbus::Result<()>;
+
+    /// Emitted to the caller of a verify method — and only to it — whenever
+    /// the attempt for `user` fails, with the same reason code
+    /// `VerifyWithReason` returns.
+    #[zbus(signal)]
+    async fn verify_failed(
+        emitter: &SignalEmitter<'_>,
+        user: &str,
+        reason: &str,
+    ) -> zbus::Result<()>;
+
+    /// Emitted to the caller of `EnrollBegin` and `EnrollStep` — and only
+    /// to it — with the captures accepted so far, the total the session
+    /// needs, and what to do next (`hint`, e.g. "Turn your head slightly to
+    /// the left"; empty once the session can be committed).
+    #[zbus(signal)]
+    async fn enroll_progress(
+        emitter: &SignalEmitter<'_>,
+        step: u32,
+        total: u32,
+        hint: &str,
+    ) -> zbus::Result<()>;
+
+    /// Emitted to the caller of a verify method — and only to it — as the
+    /// attempt advances: `capture_started`, `face_detected`, `matching`,
+    /// `camera_disconnected`.
+    /// Lets a PAM prompt say what is happening during the capture.
+    #[zbus(signal)]
+    async fn verify_progress(
+        emitter: &SignalEmitter<'_>,
+        user: &str,
+        stage: &str,
+    ) -> zbus::Result<()>;
+
+    /// Identify whoever is in front of the camera among ALL enrolled users.
+    ///
+    /// Returns `(user, reason)`: the matched username and an empty reason, or an
+    /// empty username and a failure code (same codes as `VerifyWithReason`).
+    /// Intended for greeters ("walk up and it picks your account"), so it is
+    /// disabled unless `VISAGE_IDENTIFY_ENABLED=1` and callable only by root or
+    /// the accounts in `VISAGE_IDENTIFY_CALLERS`. Rate-limited as a single
+    /// shared identity.
+    ///
+    /// Open-set safeguards: a stricter threshold (`VISAGE_IDENTIFY_THRESHOLD`,
+    /// never below the verify threshold), a required lead over every other
+    /// user (`VISAGE_IDENTIFY_MARGIN`), and liveness that cannot be disabled.
+    async fn identify_any(
+        &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(String, String), BusError> {
+        tracing::info!("identify requested");
+
+        let (enabled, session_bus, allowed) = {
+            let state = self.state.lock().await;
+            (
+                state.config.identify_enabled,
+                state.config.session_bus,
+                state.config.identify_callers.clone(),
+            )
+        };
+        if !enabled {
+            return Err(VisageError::new(
+                ErrorKind::NotSupported,
+                "IdentifyAny is disabled (set VISAGE_IDENTIFY_ENABLED=1)",
+            )
+            .into());
+        }
+        require_identify_caller(session_bus, &allowed, &header, conn).await?;
+        let caller_uid = self.optional_caller(session_bus, &header, conn).await?;
+
+        self.run_identify(DEFAULT_TENANT, caller_uid).await
+    }
+
+    /// `IdentifyAny` within a named tenant's gallery.
+    ///
+    /// Callable by root and the tenant's accounts in `VISAGE_TENANTS`; the
+    /// tenant configuration is the opt-in, so `VISAGE_IDENTIFY_ENABLED` is not
+    /// required. Same threshold, margin, and mandatory liveness as
+    /// `IdentifyAny`, rate-limited per tenant.
+    async fn identify_in(
+        &self,
+        tenant: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(String, String), BusError> {
+        tracing::info!(tenant, "identify requested");
+        self.authorize_tenant("IdentifyIn", tenant, &header, conn)
+            .await?;
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller_uid = self.optional_caller(session_bus, &header, conn).await?;
+        self.run_identify(tenant, caller_uid).await
+    }
+
+    /// Stop the in-flight verify started with the `cookie` option, switching
+    /// the camera and emitter off. The verify then returns `(false,
+    /// "cancelled")`. Returns `false` if no such verify is running or it was
+    /// started by another UID; root may cancel any verify.
+    async fn cancel(
+        &self,
+        cookie: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller = self.optional_caller(session_bus, &header, conn).await?;
+        Ok(self.cancel_checked(cookie, caller).await)
+    }
+
+    /// A login session for `user` has opened (called by `pam_visage.so` in
+    /// the `session` stack). Caches the user's gallery until their last
+    /// session closes and warms up the engine in the background, so the
+    /// first unlock does not pay for either. Root only.
+    async fn session_opened(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(), BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("SessionOpened", session_bus, &header, conn).await?;
+
+        let mut state = self.state.lock().await;
+        if !state.warm.open(user) {
+            tracing::warn!(user, "session opened: too many users tracked; not caching");
+            return Ok(());
+        }
+        if state.warm.gallery(user).is_none() {
+            match state.store.get_gallery_for_user(DEFAULT_TENANT, user).await {
+                Ok(gallery) => state.warm.store_gallery(user, gallery),
+                Err(e) => tracing::warn!(user, error = %e, "session opened: gallery fetch failed"),
+            }
+        }
+        tracing::info!(user, "session opened; pre-warming");
+        let engine = state.engine.clone();
+        tokio::spawn(async move {
+            if let Err(e) = engine.prewarm().await {
+                tracing::warn!(error = %e, "engine pre-warm failed");
+            }
+        });
+        Ok(())
+    }
+
+    /// The login session for `user` opened with `SessionOpened` has closed.
+    /// The cached gallery is dropped with the user's last session. Root only.
+    async fn session_closed(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(), BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("SessionClosed", session_bus, &header, conn).await?;
+        self.state.lock().await.warm.close(user);
+        tracing::info!(user, "session closed");
+        Ok(())
+    }
+
+    /// Re-read the configuration files and `VISAGE_*` environment, and apply
+    /// the similarity threshold, frame counts and IR emitter setting without
+    /// re-opening the camera or reloading the models. Returns the settings
+    /// that changed and whether others changed that need a restart. Root
+    /// only; SIGHUP does the same.
+    async fn reload(
+        &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(Vec<String>, bool), BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("Reload", session_bus, &header, conn).await?;
+        let reload = self.reload_config().await?;
+        let changed = reload.changed.iter().map(|name| name.to_string()).collect();
+        Ok((changed, reload.restart_needed))
+    }
+
+    /// Re-read the quirk files in `VISAGE_QUIRKS_DIRS` and look up the IR
+    /// emitter of the open camera again, without re-opening the camera.
+    /// Returns JSON: the `files` loaded, the `errors` of files skipped, the
+    /// number of `quirks` known and the `emitter` now in use. Root only; see
+    /// [`crate::quirk_reload`].
+    async fn reload_quirks(
+        &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ReloadQuirks", session_bus, &header, conn).await?;
+        let reloaded = self.reload_quirk_files().await?;
+        serde_json::to_string(&reloaded)
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e).into())
+    }
+
+    /// Aggregate statistics over the verify attempts of the last
+    /// `since_secs` seconds, as JSON: `stats::Report` plus the `since`
+    /// timestamp. Root only.
+    async fn stats(
+        &self,
+        since_secs: u64,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("Stats", session_bus, &header, conn).await?;
+        let since = i64::try_from(since_secs)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|period| chrono::Utc::now().checked_sub_signed(period))
+            .ok_or_else(|| {
+                VisageError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("period of {since_secs}s is out of range"),
+                )
+            })?;
+        let store = self.state.lock().await.store.clone();
+        let attempts = store.attempts_since(&since.to_rfc3339()).await?;
+        let mut json = serde_json::to_value(crate::stats::summarize(&attempts))
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?;
+        json["since"] = since.to_rfc3339().into();
+        Ok(json.to_string())
+    }
+
+    /// Pairwise similarities among `user`'s templates and kept probes in
+    /// `tenant` (empty for the default), as JSON: `scores::Export`. Root
+    /// only; see [`crate::scores`].
+    async fn export_scores(
+        &self,
+        tenant: &str,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ExportScores", session_bus, &header, conn).await?;
+        let state = self.state.lock().await;
+        if !tenant.is_empty() && !state.config.tenants.contains_key(tenant) {
+            return Err(VisageError::new(
+                ErrorKind::InvalidArgument,
+                format!("unknown tenant '{tenant}' (not in VISAGE_TENANTS)"),
+            )
+            .into());
+        }
+        tracing::info!(tenant, user, "export_scores requested");
+        let templates = state.store.get_gallery_for_user(tenant, user).await?;
+        let probes = state.store.probes_for_user(tenant, user).await?;
+        serde_json::to_string(&crate::scores::export(&templates, &probes))
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e).into())
+    }
+
+    /// `user`'s face models in the default tenant, with their embeddings,
+    /// as an export bundle sealed with `passphrase` (8 bytes or more) for
+    /// `ImportUser` on another machine or after a reinstall. Root only; see
+    /// [`crate::bundle`].
+    async fn export_user(
+        &self,
+        user: &str,
+        passphrase: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<Vec<u8>, BusError> {
+        tracing::info!(user, "export_user requested");
+        let started = std::time::Instant::now();
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ExportUser", session_bus, &header, conn).await?;
+        let store = self.state.lock().await.store.clone();
+        let models = store.export_models(DEFAULT_TENANT, user).await?;
+        let count = models.len();
+        let exported = async {
+            if models.is_empty() {
+                return Err(BusError::from(VisageError::new(
+                    ErrorKind::NotEnrolled,
+                    format!("user '{user}' has no face models to export"),
+                )));
+            }
+            let contents = crate::bundle::Contents {
+                user: user.to_string(),
+                exported_at: chrono::Utc::now().to_rfc3339(),
+                visage_version: env!("CARGO_PKG_VERSION").to_string(),
+                models,
+            };
+            let passphrase = passphrase.to_string();
+            // Argon2 takes a moment and most of a core; keep it off the
+            // D-Bus executor.
+            let sealed =
+                tokio::task::spawn_blocking(move || crate::bundle::seal(&contents, &passphrase))
+                    .await
+                    .map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?;
+            Ok(sealed.map_err(VisageError::from)?)
+        }
+        .await;
+        let caller_uid = caller_uid(&header, conn).await.ok();
+        self.audit(AuditEvent {
+            operation: "export",
+            tenant: DEFAULT_TENANT.to_string(),
+            user: user.to_string(),
+            result: match &exported {
+                Ok(_) => "exported",
+                Err(e) => e.code(),
+            }
+            .to_string(),
+            similarity: None,
+            model_id: None,
+            frame: None,
+            pad_score: None,
+            caller_uid,
+            duration: started.elapsed(),
+        })
+        .await;
+        if exported.is_ok() {
+            tracing::info!(user, models = count, "face models exported");
+        }
+        exported
+    }
+
+    /// Add the models of an `ExportUser` bundle to `user`'s gallery in the
+    /// default tenant, keeping their labels, threshold offsets, enrollment
+    /// and last match times, and return their new IDs. `user` need not be
+    /// the account they were exported from. The consent record is that of
+    /// this call (method `ImportUser`). Nothing is stored unless every model
+    /// is. Root only; see [`crate::bundle`].
+    async fn import_user(
+        &self,
+        user: &str,
+        bundle: Vec<u8>,
+        passphrase: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<Vec<String>, BusError> {
+        tracing::info!(user, bytes = bundle.len(), "import_user requested");
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ImportUser", session_bus, &header, conn).await?;
+        let started = std::time::Instant::now();
+        let consent = Consent::capture("ImportUser", &header, conn).await;
+
+        let imported = async {
+            let passphrase = passphrase.to_string();
+            let contents =
+                tokio::task::spawn_blocking(move || crate::bundle::open(&bundle, &passphrase))
+                    .await
+                    .map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?
+                    .map_err(VisageError::from)?;
+            let models = checked_import(contents.models)?;
+            tracing::info!(
+                user,
+                from_user = %contents.user,
+                exported_at = %contents.exported_at,
+                visage_version = %contents.visage_version,
+                models = models.len(),
+                "import_user: bundle opened"
+            );
+            self.save_models(DEFAULT_TENANT, user, &models, &consent, false, "imported")
+                .await
+        }
+        .await;
+
+        match &imported {
+            Ok(batch) => {
+                for model_id in &batch.inserted {
+                    tracing::info!(model_id = %model_id, user, "imported successfully");
+                    self.audit(AuditEvent {
+                        operation: "import",
+                        tenant: DEFAULT_TENANT.to_string(),
+                        user: user.to_string(),
+                        result: "imported".to_string(),
+                        similarity: None,
+                        model_id: Some(model_id.clone()),
+                        frame: None,
+                        pad_score: None,
+                        caller_uid: consent.caller_uid,
+                        duration: started.elapsed(),
+                    })
+                    .await;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(user, error = %e, "import_user failed");
+                self.audit(AuditEvent {
+                    operation: "import",
+                    tenant: DEFAULT_TENANT.to_string(),
+                    user: user.to_string(),
+                    result: e.code().to_string(),
+                    similarity: None,
+                    model_id: None,
+                    frame: None,
+                    pad_score: None,
+                    caller_uid: consent.caller_uid,
+                    duration: started.elapsed(),
+                })
+                .await;
+            }
+        }
+        let batch = imported?;
+        self.check_gallery(DEFAULT_TENANT, user, Some(conn)).await;
+        Ok(batch.inserted)
+    }
+
+    /// No-op round-trip: proves the daemon is answering D-Bus calls.
+    async fn ping(&self) {}
+
+    /// Engine-thread liveness and database access as JSON
+    /// (`{"healthy", "engine", "database", "panics"}`). Never touches the
+    /// camera, so it is safe to call from watchdogs at any rate.
+    async fn health(&self) -> String {
+        let health = crate::health::check(&self.state).await;
+        if !health.healthy {
+            tracing::warn!(health = %health.json, "health check failed");
+        }
+        health.json.to_string()
+    }
+
+    /// Attempt counters and latency histograms since daemon start as JSON
+    /// (see `metrics::Snapshot`). Counts only, no user names, so any caller
+    /// may read them.
+    async fn get_metrics(&self) -> Result<String, BusError> {
+        serde_json::to_string(&crate::metrics::global().snapshot())
+            .map_err(|e| VisageError::wrap(ErrorKind::Internal, e).into())
+    }
+
+    /// Verify `user` once on models that record ONNX Runtime's per-operator
+    /// profile, for diagnosing slow inference, and return JSON `matched`,
+    /// `reason`, `total_ms` and `files`: the profile of each model, Chrome
+    /// trace JSON in `VISAGE_PROFILE_DIR`. The verify is otherwise a normal
+    /// one (rate limit, audit log). Root only; refused unless
+    /// `VISAGE_PROFILE_DIR` is set.
+    async fn debug_profile(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("DebugProfile", session_bus, &header, conn).await?;
+        let (dir, engine) = {
+            let state = self.state.lock().await;
+            let dir = state.config.profile_dir.clone().ok_or_else(|| {
+                VisageError::new(
+                    ErrorKind::NotSupported,
+                    "profiling is off; set VISAGE_PROFILE_DIR",
+                )
+            })?;
+            (dir, state.engine.clone())
+        };
+        std::fs::create_dir_all(&dir).map_err(|e| VisageError::wrap(ErrorKind::Internal, e))?;
+        // Dropped if the verify fails before reaching the engine, which then
+        // leaves the next verify alone.
+        let written = engine.profile_next_verify(dir).await?;
+        let started = std::time::Instant::now();
+        let (outcome, _) = self
+            .verify_checked(
+                user,
+                DEFAULT_TENANT,
+                None,
+                None,
+                None,
+                None,
+                (!session_bus).then_some(0),
+            )
+            .await?;
+        let total = started.elapsed();
+        let files = written.await.map_err(|_| EngineError::ChannelClosed)??;
+        let (matched, reason) = outcome.into_reply();
+        Ok(serde_json::json!({
+            "matched": matched,
+            "reason": reason,
+            "total_ms": total.as_millis() as u64,
+            "files": files,
+        })
+        .to_string())
+    }
+
+    /// Return daemon status information as JSON.
+    async fn status(&self) -> Result<String, BusError> {
+        let state = self.state.lock().await;
+        let model_count = state.store.count_all().await.unwrap_or(0);
+        let schema_version = state.store.schema_version().await.unwrap_or(0);
+
+        Ok(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "instance": state.config.instance,
+            "idle_exit_secs": crate::idle::exit_after(&state.config).map_or(0, |d| d.as_secs()),
+            "camera": state.config.camera_device,
+            "camera_in_use": state.engine.camera_device(),
+            "camera_present": state.engine.camera_present(),
+            "emitter_present": state.engine.emitter_present(),
+            "busy": state.engine.busy_for().is_some(),
+            "rgb_camera": state.config.rgb_camera_device,
+            "rgb_max_correlation": state.config.rgb_max_correlation,
+            "depth_camera": state.config.depth_camera_device,
+            "depth_min_relief_mm": state.config.depth_min_relief_mm,
+            "pad_model": state.config.pad_model_path(),
+            "pad_threshold": state.config.pad_threshold,
+            "calibration_dir": state.config.calibration_dir.display().to_string(),
+            "quirks_dirs": state.config.quirks_dirs.iter().map(|dir| dir.display().to_string()).collect::<Vec<_>>(),
+            "quirks": visage_hw::quirks::list_quirks().len(),
+            "model_dir": state.config.model_dir.display().to_string(),
+            "db_path": state.config.db_path.display().to_string(),
+            "models_enrolled": model_count,
+            "schema_version": schema_version,
+            "similarity_threshold": state.config.similarity_threshold,
+            "verify_timeout_secs": state.config.verify_timeout_secs,
+            "warmup_frames": state.config.warmup_frames,
+            "frames_per_verify": state.config.frames_per_verify,
+            "verify_latency_ms": state.config.verify_latency_ms,
+            "precapture_secs": state.config.precapture_secs,
+            "confirm_margin": state.config.confirm_margin,
+            "verify_frames_adaptive": crate::latency::current_frames(),
+            "frames_per_enroll": state.config.frames_per_enroll,
+            "night_hours": state.config.night_hours.map(|(start, end)| format!("{start}-{end}")),
+            "night_brightness": state.config.night_brightness,
+            "night_last_capture": crate::night::last_capture_was_night(),
+            "als_dark_lux": state.config.als_dark_lux,
+            "ambient_lux": crate::night::last_lux(),
+            "night_learned": crate::night::learned().is_some(),
+            "night_learned_exposure": crate::night::learned().flatten(),
+            "min_frame_sharpness": state.config.min_frame_sharpness,
+            "max_frame_motion": state.config.max_frame_motion,
+            "min_scene_delta": state.config.min_scene_delta,
+            "allow_virtual_camera": state.config.allow_virtual_camera,
+            "emitter_enabled": state.config.emitter_enabled,
+            "liveness_enabled": state.config.liveness_enabled,
+            "liveness_min_displacement": state.config.liveness_min_displacement,
+            "identify_enabled": state.config.identify_enabled,
+            "identify_threshold": state.config.identify_threshold(),
+            "identify_margin": state.config.identify_margin,
+            "identify_index_min_models": state.config.identify_index_min_models,
+            "identify_index_rebuild_secs": state.config.identify_index_rebuild_secs,
+            "tenants": state.config.tenants.keys().collect::<Vec<_>>(),
+            "session_bus": state.config.session_bus,
+            "reproducible": state.config.reproducible,
+            "require_second_factor": state.config.require_second_factor,
+            "panics": crate::crash::panic_count(),
+            "crash_reports": crate::crash::report_count(&state.config.crash_dir),
+            "crash_dir": state.config.crash_dir.display().to_string(),
+            "max_request_cpu_ms": state.config.max_request_cpu_ms,
+            "max_request_wall_ms": state.config.max_request_wall_ms,
+            "resource_aborts": crate::budget::abort_count(),
+            "warm_sessions": state.warm.user_count(),
+            "enroll_sessions": state.enrollments.open_count(),
+            "gallery_outlier_similarity": state.config.gallery_outlier_similarity,
+            "gallery_outliers": state.gallery_outliers.values().map(Vec::len).sum::<usize>(),
+            "keep_probes": state.config.keep_probes,
+            "audit_log": state.config.audit_log.as_ref().map(|path| path.display().to_string()),
+            "retain_history_days": state.config.retain_history_days,
+            "purge_unused_months": state.config.purge_unused_months,
+            "purge_notice_days": state.config.purge_notice_days,
+        })
+        .to_string())
+    }
+
+    /// The similarity threshold; see `properties`.
+    #[zbus(property)]
+    async fn threshold(&self) -> f64 {
+        f64::from(self.state.lock().await.config.similarity_threshold)
+    }
+
+    /// The camera in use, from the configured list.
+    #[zbus(property)]
+    async fn camera_device(&self) -> String {
+        self.state.lock().await.engine.camera_device()
+    }
+
+    /// Face models enrolled, all tenants.
+    #[zbus(property)]
+    async fn models_enrolled(&self) -> u32 {
+        let count = self.state.lock().await.store.count_all().await.unwrap_or(0);
+        u32::try_from(count).unwrap_or(u32::MAX)
+    }
+
+    /// Whether captures light the IR emitter.
+    #[zbus(property)]
+    async fn emitter_present(&self) -> bool {
+        self.state.lock().await.engine.emitter_present()
+    }
+
+    /// Whether the camera is in use: a request, or the pre-capture window.
+    #[zbus(property)]
+    async fn busy(&self) -> bool {
+        self.state.lock().await.engine.busy_for().is_some()
+    }
+
+    /// List enrolled face models for the given user as JSON. Root may list
+    /// anyone's models, other callers only their own.
+    async fn list_models(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        tracing::info!(user, "list_models requested");
+        // Root may list anyone's models, other callers only their own.
+        let session_bus = self.state.lock().await.config.session_bus;
+        if !session_bus {
+            check_account_caller(
+                "ListModels",
+                caller_uid(&header, conn).await?,
+                user.as_bytes(),
+            )?;
+        }
+        self.run_list_models(DEFAULT_TENANT, user).await
+    }
+
+    /// List `user`'s face models in a named tenant as JSON. Same callers as
+    /// `EnrollIn`.
+    async fn list_models_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        tracing::info!(tenant, user, "list_models requested");
+        self.authorize_tenant("ListModelsIn", tenant, &header, conn)
+            .await?;
+        self.run_list_models(tenant, user).await
+    }
+
+    /// Remove an enrolled face model by ID (scoped to user). Callable by
+    /// root, and by other callers for their own account once polkit
+    /// authorizes them for `org.freedesktop.visage1.remove`.
+    async fn remove_model(
+        &self,
+        user: &str,
+        model_id: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
         tracing::info!(user, model_id, "remove_model requested");
-        // Defense-in-depth (removal is a privileged mutation).
+        // Removal is a privileged mutation: root, or the user themselves
+        // with an administrator's approval through polkit.
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_or_polkit(
+            "RemoveModel",
+            polkit::REMOVE,
+            user,
+            session_bus,
+            &header,
+            conn,
+        )
+        .await?;
+        let caller_uid = self.optional_caller(session_bus, &header, conn).await?;
+        self.run_remove_model(DEFAULT_TENANT, user, model_id, caller_uid)
+            .await
+    }
+
+    /// Require `offset` more similarity (or, if negative, accept that much
+    /// less) for one of `user`'s models to match. Returns `false` if the model
+    /// does not exist or belongs to another user. Root only, like `RemoveModel`.
+    async fn set_threshold_offset(
+        &self,
+        user: &str,
+        model_id: &str,
+        offset: f64,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        tracing::info!(user, model_id, offset, "set_threshold_offset requested");
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("SetThresholdOffset", session_bus, &header, conn).await?;
+        self.run_set_threshold_offset(DEFAULT_TENANT, user, model_id, offset)
+            .await
+    }
+
+    /// Store `user`'s keyring password, encrypted, for release after a face
+    /// match (see `ReleaseAuthtok`). Replaces an earlier one. Root only.
+    async fn set_authtok(
+        &self,
+        user: &str,
+        secret: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<(), BusError> {
+        tracing::info!(user, "set_authtok requested");
         let session_bus = self.state.lock().await.config.session_bus;
-        require_root_caller("RemoveModel", session_bus, &header, conn).await?;
+        require_root_caller("SetAuthtok", session_bus, &header, conn).await?;
+        if secret.is_empty() || secret.len() > MAX_SECRET_LEN {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "secret must be 1-{MAX_SECRET_LEN} bytes"
+            ))
+            .into());
+        }
         let state = self.state.lock().await;
-        let removed = state
-            .store
-            .remove(user, model_id)
+        Ok(state.store.set_authtok(user, secret).await?)
+    }
+
+    /// Delete `user`'s stored keyring password. Returns `false` if there was
+    /// none. Root only.
+    async fn clear_authtok(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        tracing::info!(user, "clear_authtok requested");
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ClearAuthtok", session_bus, &header, conn).await?;
+        let state = self.state.lock().await;
+        Ok(state.store.clear_authtok(user).await?)
+    }
+
+    /// `user`'s keyring password, once, within seconds of a match from a
+    /// verify with the `release_authtok` option. Root only; used by
+    /// `pam_visage.so authtok` to set `PAM_AUTHTOK`.
+    async fn release_authtok(
+        &self,
+        user: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<String, BusError> {
+        let session_bus = self.state.lock().await.config.session_bus;
+        require_root_caller("ReleaseAuthtok", session_bus, &header, conn).await?;
+        let mut state = self.state.lock().await;
+        if !state.authtok_tickets.redeem(user) {
+            tracing::warn!(user, "release_authtok: no recent face match");
+            return Err(zbus::fdo::Error::AccessDenied(format!(
+                "no recent face match for user '{user}'"
+            ))
+            .into());
+        }
+        match state.store.authtok(user).await {
+            Ok(Some(secret)) => {
+                tracing::info!(user, "keyring secret released after face match");
+                Ok(secret)
+            }
+            Ok(None) => Err(VisageError::new(
+                ErrorKind::NotFound,
+                format!("no keyring secret stored for user '{user}'"),
+            )
+            .into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// `SetThresholdOffset` for a model in a named tenant. Same callers as
+    /// `EnrollIn`.
+    async fn set_threshold_offset_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        offset: f64,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        tracing::info!(
+            tenant,
+            user,
+            model_id,
+            offset,
+            "set_threshold_offset requested"
+        );
+        self.authorize_tenant("SetThresholdOffsetIn", tenant, &header, conn)
+            .await?;
+        self.run_set_threshold_offset(tenant, user, model_id, offset)
             .await
-            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
-        if removed {
-            tracing::info!(model_id, "model removed");
-        } else {
-            tracing::warn!(model_id, user, "model not found or not owned by user");
+    }
+
+    /// Remove a face model from a named tenant (scoped to user). Same callers
+    /// as `EnrollIn`.
+    async fn remove_model_in(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        #[zbus(connection)] conn: &zbus::Connection,
+    ) -> Result<bool, BusError> {
+        tracing::info!(tenant, user, model_id, "remove_model requested");
+        self.authorize_tenant("RemoveModelIn", tenant, &header, conn)
+            .await?;
+        let session_bus = self.state.lock().await.config.session_bus;
+        let caller_uid = self.optional_caller(session_bus, &header, conn).await?;
+        self.run_remove_model(tenant, user, model_id, caller_uid)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    fn dict(entries: Vec<(&str, Value<'static>)>) -> HashMap<String, OwnedValue> {
+        entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), OwnedValue::try_from(v).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn dark_captures_fail_with_a_named_error() {
+        use zbus::DBusError;
+        let dark = BusError::from(EngineError::NoUsableFrames);
+        assert_eq!(
+            dark.name().as_str(),
+            "org.freedesktop.Visage1.Error.TooDark"
+        );
+        let timeout = BusError::from(EngineError::VerifyTimeout);
+        assert_eq!(
+            timeout.name().as_str(),
+            "org.freedesktop.Visage1.Error.Timeout"
+        );
+        assert_eq!(timeout.description(), Some("verification timed out"));
+        assert_eq!(
+            timeout.to_string(),
+            "org.freedesktop.Visage1.Error.Timeout: verification timed out"
+        );
+    }
+
+    #[test]
+    fn errors_keep_their_kind_across_the_bus() {
+        use zbus::DBusError;
+        let busy = BusError::from(EngineError::Camera(visage_hw::CameraError::DeviceBusy));
+        assert_eq!(
+            busy.name().as_str(),
+            "org.freedesktop.Visage1.Error.CameraBusy"
+        );
+        let session = BusError::from(SessionError::NotOwner);
+        assert_eq!(
+            session.name().as_str(),
+            "org.freedesktop.DBus.Error.AccessDenied"
+        );
+        let denied = BusError::from(zbus::fdo::Error::AccessDenied("no".into()));
+        assert_eq!(
+            denied.name().as_str(),
+            "org.freedesktop.DBus.Error.AccessDenied"
+        );
+    }
+
+    #[test]
+    fn outcomes_carry_reply_and_similarity() {
+        let matched = VerifyOutcome::Matched(0.71);
+        assert_eq!(matched.similarity(), 0.71);
+        assert_eq!(matched.into_reply(), (true, String::new()));
+
+        let rejected = VerifyOutcome::Rejected(FailureReason::BelowThreshold, 0.32);
+        assert_eq!(rejected.similarity(), 0.32);
+        assert_eq!(
+            rejected.into_reply(),
+            (false, "below_threshold".to_string())
+        );
+
+        let aborted = VerifyOutcome::Aborted(FailureReason::Timeout, EngineError::VerifyTimeout);
+        assert_eq!(aborted.similarity(), 0.0);
+    }
+
+    #[test]
+    fn detailed_reply_covers_the_attempt() {
+        let details = AttemptDetails {
+            model_id: Some("m1".to_string()),
+            model_label: Some("desk".to_string()),
+            confidence: 0.5,
+            frames: 6,
+            dark_frames: 2,
+            capture_time: std::time::Duration::from_millis(310),
+            inference_time: std::time::Duration::from_millis(120),
+            frame: None,
+            pad_score: Some(0.9),
+        };
+        let total = std::time::Duration::from_millis(480);
+        let reply = detailed_reply(&VerifyOutcome::Matched(0.75), &details, total);
+        let get = |key: &str| Value::from(reply[key].clone());
+        assert_eq!(get("matched"), Value::from(true));
+        assert_eq!(get("reason"), Value::from(""));
+        assert_eq!(get("similarity"), Value::from(0.75));
+        assert_eq!(get("model_label"), Value::from("desk"));
+        assert_eq!(get("confidence"), Value::from(0.5));
+        assert_eq!(get("dark_frames"), Value::from(2u64));
+        assert_eq!(get("capture_ms"), Value::from(310u64));
+        assert_eq!(get("total_ms"), Value::from(480u64));
+        assert_eq!(get("pad_score"), Value::from(f64::from(0.9f32)));
+        assert_eq!(reply.len(), 12);
+
+        let rejected = VerifyOutcome::Rejected(FailureReason::BelowThreshold, 0.25);
+        let reply = detailed_reply(&rejected, &AttemptDetails::default(), total);
+        assert_eq!(Value::from(reply["matched"].clone()), Value::from(false));
+        assert_eq!(
+            Value::from(reply["reason"].clone()),
+            Value::from("below_threshold")
+        );
+        assert_eq!(Value::from(reply["model_id"].clone()), Value::from(""));
+        assert_eq!(Value::from(reply["pad_score"].clone()), Value::from(-1.0));
+    }
+
+    #[test]
+    fn verify_options_parse_known_keys_and_ignore_unknown() {
+        let opts = VerifyOptions::from_dict(&dict(vec![
+            ("device", Value::from("/dev/video2")),
+            ("timeout", Value::from(5u32)),
+            ("future_option", Value::from(true)),
+        ]))
+        .unwrap();
+        assert_eq!(opts.device.as_deref(), Some("/dev/video2"));
+        assert_eq!(opts.timeout_secs, Some(5));
+    }
+
+    #[test]
+    fn verify_options_tenant() {
+        let opts = VerifyOptions::from_dict(&dict(vec![("tenant", Value::from("lobby"))])).unwrap();
+        assert_eq!(opts.tenant(), "lobby");
+        let opts = VerifyOptions::from_dict(&dict(vec![("tenant", Value::from(""))])).unwrap();
+        assert_eq!(opts.tenant(), DEFAULT_TENANT);
+        assert!(VerifyOptions::from_dict(&dict(vec![("tenant", Value::from("a:b"))])).is_err());
+    }
+
+    #[test]
+    fn rate_keys_are_tenant_scoped() {
+        assert_eq!(rate_key(DEFAULT_TENANT, "alice"), "alice");
+        assert_eq!(rate_key("lobby", "alice"), "lobby:alice");
+        assert_eq!(identify_rate_key(DEFAULT_TENANT), IDENTIFY_RATE_KEY);
+        assert_eq!(identify_rate_key("lobby"), ":identify:lobby");
+    }
+
+    #[test]
+    fn verify_options_reject_wrong_types() {
+        assert!(VerifyOptions::from_dict(&dict(vec![("timeout", Value::from("5"))])).is_err());
+        assert!(VerifyOptions::from_dict(&dict(vec![("device", Value::from(2u32))])).is_err());
+        assert!(
+            VerifyOptions::from_dict(&dict(vec![("release_authtok", Value::from(1u32))])).is_err()
+        );
+        let opts =
+            VerifyOptions::from_dict(&dict(vec![("release_authtok", Value::from(true))])).unwrap();
+        assert!(opts.release_authtok);
+        assert!(
+            VerifyOptions::from_dict(&dict(vec![("second_factor", Value::from("yes"))])).is_err()
+        );
+        let opts =
+            VerifyOptions::from_dict(&dict(vec![("second_factor", Value::from(true))])).unwrap();
+        assert!(opts.second_factor);
+    }
+
+    #[test]
+    fn verify_option_timeout_can_only_shorten() {
+        assert_eq!(effective_timeout(Some(30), 10), 10);
+        assert_eq!(effective_timeout(Some(4), 10), 4);
+        assert_eq!(
+            effective_timeout(VerifyOptions::default().timeout_secs, 10),
+            10
+        );
+    }
+
+    #[test]
+    fn non_utf8_account_names_get_a_distinct_key() {
+        assert_eq!(account_key(b"alice"), "alice");
+        assert_eq!(account_key("josé".as_bytes()), "josé");
+        assert_eq!(account_key(b"jos\xe9"), r"jos\xe9");
+        assert_ne!(account_key(b"jos\xe9"), account_key(b"jos\xe8"));
+        assert!(uid_for_name(b"no-such-user-\xe9").is_none());
+    }
+
+    #[test]
+    fn threshold_offsets_are_bounded() {
+        assert_eq!(checked_threshold_offset(0.1).unwrap(), 0.1);
+        assert_eq!(checked_threshold_offset(-0.1).unwrap(), -0.1);
+        assert!(checked_threshold_offset(0.31).is_err());
+        assert!(checked_threshold_offset(-0.2).is_err());
+        assert!(checked_threshold_offset(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn enrollment_age_is_that_of_the_newest_model() {
+        let model = |created_at: &str| ModelInfo {
+            id: String::new(),
+            label: String::new(),
+            model_version: String::new(),
+            quality_score: 1.0,
+            created_at: created_at.to_string(),
+            pose_label: String::new(),
+            last_matched_at: None,
+            near_misses: 0,
+            near_miss_mean_similarity: None,
+            near_miss_max_similarity: None,
+            last_near_miss_at: None,
+            threshold_offset: 0.0,
+            consistency: None,
+            outlier: false,
+            consent: None,
+        };
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-01T00:00:00+00:00")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(newest_model_age(&[], now), None);
+        let models = [
+            model("2025-01-01T00:00:00+00:00"),
+            model("2026-02-28T00:00:00+00:00"),
+        ];
+        assert_eq!(newest_model_age(&models, now), Some(86_400));
+        assert_eq!(
+            newest_model_age(&[model("2026-03-02T00:00:00+00:00")], now),
+            Some(0),
+            "clock skew"
+        );
+        assert_eq!(newest_model_age(&[model("garbage")], now), Some(0));
+    }
+
+    #[test]
+    fn cancel_is_limited_to_the_owner() {
+        assert!(may_cancel(Some(1000), Some(1000)));
+        assert!(!may_cancel(Some(1001), Some(1000)));
+        assert!(
+            may_cancel(Some(0), Some(1000)),
+            "root may cancel any verify"
+        );
+        assert!(may_cancel(None, None), "session bus");
+        assert!(!may_cancel(Some(1000), None));
+
+        let long = "x".repeat(MAX_COOKIE_LEN + 1);
+        assert!(VerifyOptions::from_dict(&dict(vec![("cookie", Value::from(long))])).is_err());
+        let opts = VerifyOptions::from_dict(&dict(vec![("cookie", Value::from("abc"))])).unwrap();
+        assert_eq!(opts.cookie.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn enroll_options() {
+        assert_eq!(
+            EnrollOptions::from_dict(&HashMap::new()).unwrap(),
+            EnrollOptions::default()
+        );
+        let opts = EnrollOptions::from_dict(&dict(vec![
+            ("steps", Value::from(3u32)),
+            ("tenant", Value::from("kiosk")),
+        ]))
+        .unwrap();
+        assert_eq!(
+            (opts.steps, opts.tenant.as_deref()),
+            (Some(3), Some("kiosk"))
+        );
+        for steps in [0u32, MAX_STEPS + 1] {
+            assert!(EnrollOptions::from_dict(&dict(vec![("steps", Value::from(steps))])).is_err());
         }
-        Ok(removed)
+        assert!(EnrollOptions::from_dict(&dict(vec![("tenant", Value::from("a:b"))])).is_err());
+        let opts = EnrollOptions::from_dict(&dict(vec![("replace", Value::from(true))])).unwrap();
+        assert!(opts.replace);
+        assert!(EnrollOptions::from_dict(&dict(vec![("replace", Value::from(1u32))])).is_err());
+    }
+
+    #[test]
+    fn callers_may_only_name_their_own_account() {
+        assert!(check_account_caller("Enroll", 0, b"no-such-visage-user").is_ok());
+        assert!(matches!(
+            check_account_caller("ListModels", 4242, b"root"),
+            Err(BusError::Fdo(zbus::fdo::Error::AccessDenied(_)))
+        ));
+        assert_eq!(
+            check_account_caller("RemoveModel", 4242, b"no-such-visage-user")
+                .unwrap_err()
+                .code(),
+            "not_found"
+        );
     }
 }
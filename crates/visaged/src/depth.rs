@@ -0,0 +1,87 @@
+//! Depth liveness — a matched face must not be flat.
+//!
+//! With `VISAGE_DEPTH_CAMERA_DEVICE` set to the depth node of a
+//! RealSense-style camera, the engine grabs one depth frame alongside the IR
+//! burst. After an IR match, the depth inside the matched face box must stand
+//! out of its best-fit plane by at least `VISAGE_DEPTH_MIN_RELIEF_MM`
+//! ([`check_depth_planarity`](visage_core::check_depth_planarity)). A photo,
+//! a phone or a monitor is flat however it is held, so this blocks them
+//! independently of the landmark liveness check.
+
+use visage_core::BoundingBox;
+use visage_hw::{DepthFrame, DepthInfo};
+
+/// Fraction of the face box, around its centre, that is sampled. The edges of
+/// the box catch hair, ears and the background behind the face.
+const FACE_CORE: f32 = 0.6;
+
+/// `(u, v, z)` samples of the depth inside `face`, a box in an IR frame of
+/// `ir_size`. The depth stream is computed from the IR imager, so the box
+/// only needs scaling to the depth resolution. Readings outside the sensor's
+/// range are dropped.
+pub fn face_samples(
+    depth: &DepthFrame,
+    face: &BoundingBox,
+    ir_size: (u32, u32),
+    info: &DepthInfo,
+) -> Vec<(f32, f32, f32)> {
+    let sx = depth.width as f32 / ir_size.0.max(1) as f32;
+    let sy = depth.height as f32 / ir_size.1.max(1) as f32;
+    let margin = (1.0 - FACE_CORE) / 2.0;
+    let x0 = (face.x + face.width * margin) * sx;
+    let y0 = (face.y + face.height * margin) * sy;
+    let x1 = (face.x + face.width * (1.0 - margin)) * sx;
+    let y1 = (face.y + face.height * (1.0 - margin)) * sy;
+
+    let mut samples = Vec::new();
+    let (x0, y0) = (x0.max(0.0) as u32, y0.max(0.0) as u32);
+    let (x1, y1) = (x1.max(0.0) as u32, y1.max(0.0) as u32);
+    for v in (y0..y1.min(depth.height)).step_by(2) {
+        for u in (x0..x1.min(depth.width)).step_by(2) {
+            if let Some(z) = depth
+                .depth_mm(u, v)
+                .filter(|z| (info.min_mm..=info.max_mm).contains(z))
+            {
+                samples.push((u as f32, v as f32, z));
+            }
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_the_scaled_face_core_in_range() {
+        // Depth at half the IR resolution; one row reads too close.
+        let (width, height) = (320u32, 180u32);
+        let mut data = vec![600u16; (width * height) as usize];
+        for u in 0..width {
+            data[(90 * width + u) as usize] = 50;
+        }
+        let depth = DepthFrame {
+            data,
+            width,
+            height,
+            units_mm: 1.0,
+            sequence: 0,
+        };
+        let face = BoundingBox {
+            x: 200.0,
+            y: 100.0,
+            width: 200.0,
+            height: 200.0,
+            confidence: 0.9,
+            landmarks: None,
+        };
+        let samples = face_samples(&depth, &face, (640, 360), &DepthInfo::default());
+
+        // Core of the box is IR x 240..360, y 140..260: depth x 120..180, y 70..130.
+        assert!(samples.iter().all(|&(u, v, z)| (120.0..180.0).contains(&u)
+            && (70.0..130.0).contains(&v)
+            && z == 600.0));
+        assert_eq!(samples.len(), 30 * 30 - 30, "row 90 is out of range");
+    }
+}
@@ -1,9 +1,29 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
+#[cfg(feature = "liveness")]
+use visage_core::{check_depth_planarity, check_landmark_stability};
 use visage_core::{
-    check_landmark_stability, CosineMatcher, Embedding, FaceModel, MatchResult, Matcher,
+    BoundingBox, CosineMatcher, Embedding, ErrorKind, FaceModel, GalleryIndex, HeadPose,
+    IndexedMatcher, MatchResult, Matcher, OpenSetMatcher, PadModel, VisageError,
+};
+use visage_hw::{
+    Calibration, Camera, CaptureProfile, CaptureStats, DepthCamera, Frame, FrameRing, IrEmitter,
+    NightMode, QualityGate,
 };
-use visage_hw::{Camera, IrEmitter};
+
+use crate::budget::{Meter, RequestBudget};
+use crate::camera_list;
+use crate::depth;
+use crate::enrollment::PoseTarget;
+use crate::fusion::RgbCheck;
+use crate::latency::FrameSizer;
+use crate::night::{self, NightLearner, NightSchedule};
+use crate::scheduling::Scheduling;
 
 #[derive(Error, Debug)]
 pub enum EngineError {
@@ -13,21 +33,111 @@ pub enum EngineError {
     Detector(#[from] visage_core::detector::DetectorError),
     #[error("recognizer error: {0}")]
     Recognizer(#[from] visage_core::recognizer::RecognizerError),
+    #[error("PAD model error: {0}")]
+    Pad(#[from] visage_core::pad::PadError),
     #[error("no face detected in any captured frame")]
     NoFaceDetected,
+    #[error("no face in the requested head pose ({faces} face(s) turned otherwise)")]
+    WrongPose { faces: usize },
     #[error("no usable frames captured (camera returned only dark or unreadable frames)")]
     NoUsableFrames,
+    #[error("no usable frames captured ({saturated} saturated, {blurry} blurry, {motion} moving)")]
+    LowQualityFrames {
+        saturated: usize,
+        blurry: usize,
+        motion: usize,
+    },
+    #[error("captured frames look replayed: {0}")]
+    StaleFrames(visage_hw::StaleBurst),
     #[error("liveness check failed: landmark displacement {displacement:.3} px < threshold {threshold:.3} px")]
     LivenessCheckFailed { displacement: f32, threshold: f32 },
+    #[error("RGB camera did not confirm the IR match: {faces} face(s), {attentive} facing the camera, or no eye movement")]
+    RgbCheckFailed { faces: usize, attentive: usize },
+    #[error("IR and RGB faces look like one flat picture: correlation {correlation:.3} > {max_correlation:.3}")]
+    FlatPicture {
+        correlation: f32,
+        max_correlation: f32,
+    },
+    #[error("RGB camera error: {0}")]
+    RgbCamera(visage_hw::CameraError),
+    #[error(
+        "depth check failed: face relief {relief_mm:.1} mm < {min_relief_mm:.1} mm (flat surface)"
+    )]
+    FlatFace { relief_mm: f32, min_relief_mm: f32 },
+    #[error("PAD model scored the face {score:.3} live < threshold {threshold:.3} (presentation attack)")]
+    PresentationAttack { score: f32, threshold: f32 },
+    #[error("depth camera error: {0}")]
+    DepthCamera(visage_hw::CameraError),
     #[error("verification timed out")]
     VerifyTimeout,
+    #[error("verification cancelled by the caller")]
+    Cancelled,
+    #[error("request exceeded its {resource} time budget ({used_ms} ms > {limit_ms} ms)")]
+    ResourceLimit {
+        resource: &'static str,
+        used_ms: u64,
+        limit_ms: u64,
+    },
     #[error("engine thread exited")]
     ChannelClosed,
 }
 
+impl EngineError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EngineError::Camera(e) | EngineError::RgbCamera(e) | EngineError::DepthCamera(e) => {
+                e.kind()
+            }
+            EngineError::Detector(e) => e.kind(),
+            EngineError::Recognizer(e) => e.kind(),
+            EngineError::Pad(e) => e.kind(),
+            EngineError::NoFaceDetected => ErrorKind::NoFace,
+            EngineError::WrongPose { .. } => ErrorKind::WrongPose,
+            EngineError::NoUsableFrames => ErrorKind::TooDark,
+            EngineError::LowQualityFrames { .. } => ErrorKind::PoorQuality,
+            EngineError::StaleFrames(_) => ErrorKind::StaticScene,
+            EngineError::LivenessCheckFailed { .. }
+            | EngineError::RgbCheckFailed { .. }
+            | EngineError::FlatPicture { .. }
+            | EngineError::FlatFace { .. }
+            | EngineError::PresentationAttack { .. } => ErrorKind::LivenessFailed,
+            EngineError::VerifyTimeout => ErrorKind::Timeout,
+            EngineError::Cancelled => ErrorKind::Cancelled,
+            EngineError::ResourceLimit { .. } => ErrorKind::LimitExceeded,
+            EngineError::ChannelClosed => ErrorKind::Internal,
+        }
+    }
+}
+
+impl From<EngineError> for VisageError {
+    fn from(e: EngineError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// Largest yaw or pitch (degrees) of a face used for an enrollment template
+/// while frontal faces are available; a template from a turned head matches
+/// the frontal faces of later logins poorly.
+const ENROLL_MAX_POSE_DEG: f32 = 30.0;
+
 /// Consecutive "camera-broken" captures before the engine re-opens the device.
 const MAX_CONSECUTIVE_CAPTURE_FAILURES: u32 = 3;
 
+/// Pre-captured frames older than this are not matched: they must show
+/// whoever is in front of the camera when the verify arrives.
+const PRECAPTURE_MAX_AGE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Frames held in the pre-capture ring (about half a second at 30 fps).
+const PRECAPTURE_RING_FRAMES: usize = 16;
+
+/// Enrollment's wait for a camera that disconnected mid-capture; a verify
+/// waits until its own deadline instead.
+const RECONNECT_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often the engine tries to re-open a disconnected camera.
+const RECONNECT_POLL: std::time::Duration = std::time::Duration::from_millis(250);
+
 /// True only when a result indicates the *camera* is broken — dark/unreadable
 /// frames or a capture error — never an absent/unrecognised user, a verify
 /// timeout, or a liveness rejection. Only these arm the self-heal re-open (#48).
@@ -38,24 +148,186 @@ fn capture_looks_broken<T>(result: &Result<T, EngineError>) -> bool {
     )
 }
 
+/// Whether the camera went away during the request (unplugged, USB reset).
+fn camera_disconnected<T>(result: &Result<T, EngineError>) -> bool {
+    matches!(
+        result,
+        Err(EngineError::Camera(visage_hw::CameraError::Disconnected(_)))
+    )
+}
+
+/// Error for a capture that produced no usable frames: "too dark" when dark
+/// frames dominate (the self-heal signal), otherwise a quality rejection.
+fn no_usable_frames(stats: &CaptureStats) -> EngineError {
+    if stats.dark >= stats.saturated + stats.blurry + stats.motion {
+        EngineError::NoUsableFrames
+    } else {
+        EngineError::LowQualityFrames {
+            saturated: stats.saturated,
+            blurry: stats.blurry,
+            motion: stats.motion,
+        }
+    }
+}
+
 /// Result of an enrollment operation.
 pub struct EnrollResult {
     pub embedding: Embedding,
     pub quality_score: f32,
+    /// Head pose of the best face, when its landmarks allow an estimate.
+    pub pose: Option<HeadPose>,
 }
 
 /// Result of a verification operation.
 pub struct VerifyResult {
     pub result: MatchResult,
-    /// Reserved for v3: surface capture quality metadata to callers without a schema change.
-    #[allow(dead_code)]
+    /// Detector confidence of the best-matching face.
     pub best_quality: f32,
+    /// Longer bbox side (px) of the largest face seen in any frame; 0.0 if none.
+    /// Lets callers tell "too far from the camera" apart from "not recognised".
+    pub best_face_size: f32,
+    /// RGB stream assessment in IR + RGB mode, after an IR match.
+    #[allow(dead_code)]
+    pub rgb: Option<RgbCheck>,
+    /// Relief (mm) of the matched face out of its best-fit plane, when a
+    /// depth camera is configured.
+    #[allow(dead_code)]
+    pub depth_relief_mm: Option<f32>,
+    /// Live probability the PAD model gave the matched face, when one is
+    /// configured.
+    pub pad_score: Option<f32>,
+    /// Embedding of the best-matching face, kept as a probe when
+    /// `VISAGE_KEEP_PROBES` is set (see `scores`).
+    pub probe: Option<Embedding>,
+    /// The frame the decision rests on, the one with the best-matching face.
+    pub frame: Option<FrameMeta>,
+    /// Time spent capturing the burst.
+    pub capture_time: std::time::Duration,
+    /// Time spent on detection, embedding and matching.
+    pub inference_time: std::time::Duration,
+}
+
+/// The sensor conditions of one captured frame, carried with a verify
+/// decision into the audit log so it can be traced to the exact frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameMeta {
+    /// Driver sequence number within the capture stream.
+    pub sequence: u32,
+    /// Driver capture timestamp in microseconds; 0 if the driver sets none.
+    pub timestamp_us: u64,
+    /// Manual exposure (`exposure_absolute`, 100 µs units) the camera was
+    /// set to; `None` under auto exposure.
+    pub exposure: Option<i64>,
+    /// Captured with the night profile.
+    pub night: bool,
+    /// Payload the IR emitter was switched on with (its level, on cameras
+    /// that have one); `None` when no emitter was lit.
+    pub emitter: Option<Vec<u8>>,
+    /// Taken from the pre-capture stream rather than the verify burst.
+    pub precaptured: bool,
+}
+
+impl FrameMeta {
+    fn of(frame: &Frame, emitter: Option<&IrEmitter>, precaptured: bool) -> Self {
+        Self {
+            sequence: frame.sequence,
+            timestamp_us: u64::try_from(frame.device_timestamp.as_micros()).unwrap_or(u64::MAX),
+            exposure: frame.exposure,
+            night: frame.night,
+            emitter: emitter.map(|e| e.payload().to_vec()),
+            precaptured,
+        }
+    }
+}
+
+/// Progress of a verify attempt, reported while the engine works.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifyStage {
+    /// The camera (and IR emitter) is capturing the burst.
+    CaptureStarted,
+    /// A face was found in the first frame that has one, with this detector
+    /// confidence.
+    FaceDetected { confidence: f32 },
+    /// Embeddings are being compared with the gallery.
+    Matching,
+    /// The camera disconnected mid-capture; the engine waits for it to
+    /// return and then captures again.
+    CameraDisconnected,
+}
+
+impl VerifyStage {
+    /// Stable code carried by the `VerifyProgress` D-Bus signal.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::CaptureStarted => "capture_started",
+            Self::FaceDetected { .. } => "face_detected",
+            Self::Matching => "matching",
+            Self::CameraDisconnected => "camera_disconnected",
+        }
+    }
+}
+
+/// Sends [`VerifyStage`]s back to the D-Bus handler; closed when the request ends.
+pub type ProgressSender = mpsc::UnboundedSender<VerifyStage>;
+
+/// Frames a verify captured, counted by the engine as the bursts come in, for
+/// the attempt statistics (see `stats`). Covers every burst of the request,
+/// including one captured again after a camera reconnect.
+#[derive(Default)]
+pub struct CaptureTally {
+    frames: AtomicUsize,
+    dark: AtomicUsize,
+    /// With [`CaptureTally::keeping_frame`], the frame the last decision
+    /// rested on, for a failure snapshot (see `snapshots`).
+    frame: Option<std::sync::Mutex<Option<Frame>>>,
+}
+
+impl CaptureTally {
+    /// A tally that also keeps the frame the last burst's decision rested
+    /// on: the one with the best-matching face, or the middle frame when no
+    /// frame had a face.
+    pub fn keeping_frame() -> Self {
+        Self {
+            frame: Some(std::sync::Mutex::new(None)),
+            ..Self::default()
+        }
+    }
+
+    fn keep_frame(&self, frame: &Frame) {
+        if let Some(slot) = &self.frame {
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(frame.clone());
+        }
+    }
+
+    /// The frame kept by a tally made with [`CaptureTally::keeping_frame`].
+    pub fn take_frame(&self) -> Option<Frame> {
+        self.frame
+            .as_ref()
+            .and_then(|slot| slot.lock().unwrap_or_else(|e| e.into_inner()).take())
+    }
+
+    fn record(&self, usable: usize, stats: &CaptureStats) {
+        self.frames
+            .fetch_add(usable + stats.rejected(), Ordering::Relaxed);
+        self.dark.fetch_add(stats.dark, Ordering::Relaxed);
+    }
+
+    /// Frames captured, usable or not.
+    pub fn frames(&self) -> usize {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    /// Frames dropped as too dark.
+    pub fn dark(&self) -> usize {
+        self.dark.load(Ordering::Relaxed)
+    }
 }
 
 /// Messages sent from D-Bus handlers to the engine thread.
 enum EngineRequest {
     Enroll {
         frames_count: usize,
+        pose: Option<PoseTarget>,
         reply: oneshot::Sender<Result<EnrollResult, EngineError>>,
     },
     Verify {
@@ -65,23 +337,240 @@ enum EngineRequest {
         timeout: std::time::Duration,
         liveness_enabled: bool,
         liveness_min_displacement: f32,
+        /// `Some(margin)` selects open-set (1:N) matching; see [`OpenSetMatcher`].
+        open_set_margin: Option<f32>,
+        /// ANN index over `gallery` for open-set matching; see [`IndexedMatcher`].
+        index: Option<Arc<GalleryIndex>>,
+        progress: Option<ProgressSender>,
+        /// Set by the D-Bus `Cancel` method; stops the capture early.
+        cancel: Option<Arc<AtomicBool>>,
+        tally: Option<Arc<CaptureTally>>,
         reply: oneshot::Sender<Result<VerifyResult, EngineError>>,
     },
+    /// Warm up before an expected verify; see [`EngineHandle::prewarm`].
+    Prewarm {
+        reply: oneshot::Sender<Result<(), EngineError>>,
+    },
+    /// Apply reloaded capture settings; see [`EngineHandle::reconfigure`].
+    Reconfigure {
+        emitter_enabled: bool,
+        warmup_frames: usize,
+        reply: oneshot::Sender<()>,
+    },
+    /// Look up the IR emitter again; see [`EngineHandle::reprobe_emitter`].
+    ReprobeEmitter {
+        reply: oneshot::Sender<Option<String>>,
+    },
+    /// The camera was unplugged; see [`EngineHandle::camera_removed`].
+    CameraRemoved { reply: oneshot::Sender<()> },
+    /// The camera was plugged back in; see [`EngineHandle::camera_attached`].
+    CameraAttached {
+        reply: oneshot::Sender<Result<(), EngineError>>,
+    },
+    /// Profile the next verify; see [`EngineHandle::profile_next_verify`].
+    Profile {
+        dir: PathBuf,
+        reply: oneshot::Sender<Result<ProfileReceiver, EngineError>>,
+    },
+}
+
+/// Yields the profile files of the profiled verify once it is done.
+pub type ProfileReceiver = oneshot::Receiver<Result<Vec<String>, EngineError>>;
+
+/// A model session recording ONNX Runtime's profile.
+trait ProfiledSession {
+    /// Stop recording and write the profile; returns its file name.
+    fn end_profiling(&mut self) -> Result<String, EngineError>;
+}
+
+impl ProfiledSession for visage_core::FaceDetector {
+    fn end_profiling(&mut self) -> Result<String, EngineError> {
+        Ok(visage_core::FaceDetector::end_profiling(self)?)
+    }
+}
+
+impl ProfiledSession for visage_core::FaceRecognizer {
+    fn end_profiling(&mut self) -> Result<String, EngineError> {
+        Ok(visage_core::FaceRecognizer::end_profiling(self)?)
+    }
+}
+
+/// Sessions recording ONNX Runtime's profile, used for the next verify in
+/// place of the engine's own unless the caller has stopped waiting; see
+/// [`EngineHandle::profile_next_verify`].
+struct Profiling<D = visage_core::FaceDetector, R = visage_core::FaceRecognizer> {
+    detector: D,
+    recognizer: R,
+    written: oneshot::Sender<Result<Vec<String>, EngineError>>,
+}
+
+impl Profiling {
+    /// Load the models again with profiling on, writing into `dir`.
+    fn load(
+        dir: &Path,
+        scrfd_path: &str,
+        arcface_path: &str,
+        inference: visage_core::InferenceOptions,
+    ) -> Result<(Self, ProfileReceiver), EngineError> {
+        let detector =
+            visage_core::FaceDetector::load_profiled(scrfd_path, inference, &dir.join("scrfd"))?;
+        let recognizer = visage_core::FaceRecognizer::load_profiled(
+            arcface_path,
+            inference,
+            &dir.join("arcface"),
+        )?;
+        let (written, receiver) = oneshot::channel();
+        let profiling = Self {
+            detector,
+            recognizer,
+            written,
+        };
+        Ok((profiling, receiver))
+    }
+}
+
+impl<D: ProfiledSession, R: ProfiledSession> Profiling<D, R> {
+    /// The sessions waiting in `pending`, for the verify about to run; none
+    /// if the caller has stopped waiting for their files. Either way the
+    /// verify after it runs on the engine's own sessions.
+    fn take(pending: &mut Option<Self>) -> Option<Self> {
+        pending.take().filter(|p| !p.written.is_closed())
+    }
+
+    /// End profiling and hand the files to the caller.
+    fn finish(mut self) {
+        let files = self.detector.end_profiling().and_then(|detector| {
+            let recognizer = self.recognizer.end_profiling()?;
+            Ok(vec![detector, recognizer])
+        });
+        match &files {
+            Ok(files) => tracing::info!(?files, "ONNX Runtime profile written"),
+            Err(e) => tracing::warn!(error = %e, "cannot write ONNX Runtime profile"),
+        }
+        let _ = self.written.send(files);
+    }
+}
+
+/// When the engine thread took up its current work, for the watchdog, and
+/// when it last finished some, for the idle exit.
+struct Activity {
+    epoch: std::time::Instant,
+    /// Milliseconds from `epoch` to the start of the current work, plus one;
+    /// 0 while the thread waits for a request.
+    busy_since: AtomicU64,
+    /// Milliseconds from `epoch` to the end of the last work.
+    idle_since: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            epoch: std::time::Instant::now(),
+            busy_since: AtomicU64::new(0),
+            idle_since: AtomicU64::new(0),
+        }
+    }
+
+    fn start(&self) {
+        let now = self.epoch.elapsed().as_millis() as u64;
+        self.busy_since.store(now + 1, Ordering::Relaxed);
+    }
+
+    fn finish(&self) {
+        if self.busy_since.swap(0, Ordering::Relaxed) != 0 {
+            let now = self.epoch.elapsed().as_millis() as u64;
+            self.idle_since.store(now, Ordering::Relaxed);
+        }
+    }
+
+    fn idle_for(&self) -> Option<std::time::Duration> {
+        if self.busy_since.load(Ordering::Relaxed) != 0 {
+            return None;
+        }
+        let since = self.idle_since.load(Ordering::Relaxed);
+        Some(
+            self.epoch
+                .elapsed()
+                .saturating_sub(std::time::Duration::from_millis(since)),
+        )
+    }
+
+    fn busy_for(&self) -> Option<std::time::Duration> {
+        let since = self.busy_since.load(Ordering::Relaxed).checked_sub(1)?;
+        Some(
+            self.epoch
+                .elapsed()
+                .saturating_sub(std::time::Duration::from_millis(since)),
+        )
+    }
 }
 
 /// Clone-safe handle to the engine thread.
 #[derive(Clone)]
 pub struct EngineHandle {
     tx: mpsc::Sender<EngineRequest>,
+    activity: Arc<Activity>,
+    /// Cleared while the camera is known to be unplugged.
+    camera_present: Arc<AtomicBool>,
+    /// Device path of the camera in use, from the configured list.
+    camera_in_use: Arc<std::sync::Mutex<String>>,
+    /// Set while captures light an IR emitter.
+    emitter_present: Arc<AtomicBool>,
 }
 
 impl EngineHandle {
+    /// Whether the engine thread is still receiving requests. `false` once it
+    /// has exited, e.g. after a panic.
+    pub fn is_running(&self) -> bool {
+        !self.tx.is_closed()
+    }
+
+    /// How long the engine thread has been on its current request (or
+    /// pre-capture window); `None` while it waits for one.
+    pub fn busy_for(&self) -> Option<std::time::Duration> {
+        self.activity.busy_for()
+    }
+
+    /// How long the engine thread has been waiting for a request since its
+    /// last one (or since it started); `None` while it works on one.
+    pub fn idle_for(&self) -> Option<std::time::Duration> {
+        self.activity.idle_for()
+    }
+
+    /// Whether the engine has the camera open: `false` from an unplug (or a
+    /// disconnect the camera did not return from) until it is re-opened.
+    pub fn camera_present(&self) -> bool {
+        self.camera_present.load(Ordering::Relaxed)
+    }
+
+    /// The device the engine opened last: the first usable one of the
+    /// configured list (see `camera_list`).
+    pub fn camera_device(&self) -> String {
+        self.camera_in_use
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Whether the engine lights an IR emitter for its captures: one was
+    /// found for the camera in use and `emitter_enabled` is on.
+    pub fn emitter_present(&self) -> bool {
+        self.emitter_present.load(Ordering::Relaxed)
+    }
+
     /// Request enrollment: capture frames, detect best face, extract embedding.
-    pub async fn enroll(&self, frames_count: usize) -> Result<EnrollResult, EngineError> {
+    /// With a `pose`, only faces in that head pose are used (guided
+    /// enrollment); without one, frontal faces are preferred.
+    pub async fn enroll(
+        &self,
+        frames_count: usize,
+        pose: Option<PoseTarget>,
+    ) -> Result<EnrollResult, EngineError> {
         let (reply_tx, reply_rx) = oneshot::channel();
         self.tx
             .send(EngineRequest::Enroll {
                 frames_count,
+                pose,
                 reply: reply_tx,
             })
             .await
@@ -90,6 +579,10 @@ impl EngineHandle {
     }
 
     /// Request verification: capture frames, detect, extract, compare against gallery.
+    /// Stages are reported on `progress`, if given, as the attempt advances;
+    /// setting `cancel` ends the attempt early with [`EngineError::Cancelled`].
+    /// The frames captured are counted into `tally`, if given.
+    #[allow(clippy::too_many_arguments)]
     pub async fn verify(
         &self,
         gallery: Vec<FaceModel>,
@@ -98,6 +591,9 @@ impl EngineHandle {
         timeout: std::time::Duration,
         liveness_enabled: bool,
         liveness_min_displacement: f32,
+        progress: Option<ProgressSender>,
+        cancel: Option<Arc<AtomicBool>>,
+        tally: Option<Arc<CaptureTally>>,
     ) -> Result<VerifyResult, EngineError> {
         let (reply_tx, reply_rx) = oneshot::channel();
         self.tx
@@ -108,6 +604,134 @@ impl EngineHandle {
                 timeout,
                 liveness_enabled,
                 liveness_min_displacement,
+                open_set_margin: None,
+                index: None,
+                progress,
+                cancel,
+                tally,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)?
+    }
+
+    /// Warm up for a verify that is likely to follow soon (a user just
+    /// logged in): run one detection and one embedding on a blank frame so
+    /// the inference runtime has allocated its buffers, and re-open the
+    /// camera now if recent captures failed. Never turns the camera on.
+    pub async fn prewarm(&self) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::Prewarm { reply: reply_tx })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)?
+    }
+
+    /// Apply capture settings from a configuration reload: turn the IR
+    /// emitter on or off and change the per-stream warmup. Takes effect from
+    /// the next request; the camera and models stay open.
+    pub async fn reconfigure(
+        &self,
+        emitter_enabled: bool,
+        warmup_frames: usize,
+    ) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::Reconfigure {
+                emitter_enabled,
+                warmup_frames,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)
+    }
+
+    /// Look up the camera's IR emitter in the quirk database again, after
+    /// the quirks were reloaded (see [`crate::quirk_reload`]). The camera
+    /// stays open. Returns the name of the emitter now in use, if any.
+    pub async fn reprobe_emitter(&self) -> Result<Option<String>, EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::ReprobeEmitter { reply: reply_tx })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)
+    }
+
+    /// Run the next verify with freshly loaded models that record ONNX
+    /// Runtime's per-operator profile into `dir`, then write it (one JSON
+    /// file per model) and go back to the engine's own models. Returns once
+    /// the profiled models are loaded; the receiver yields the files after
+    /// that verify. A second call before the verify replaces the first.
+    pub async fn profile_next_verify(&self, dir: PathBuf) -> Result<ProfileReceiver, EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::Profile {
+                dir,
+                reply: reply_tx,
+            })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)?
+    }
+
+    /// The camera was unplugged (see [`crate::hotplug`]): drop its stale
+    /// handle's pre-capture window and fail captures at once until it is
+    /// back.
+    pub async fn camera_removed(&self) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::CameraRemoved { reply: reply_tx })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)
+    }
+
+    /// The camera was plugged back in: re-open it and re-probe the IR
+    /// emitter now, waiting a few seconds for the driver to be ready.
+    pub async fn camera_attached(&self) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::CameraAttached { reply: reply_tx })
+            .await
+            .map_err(|_| EngineError::ChannelClosed)?;
+        reply_rx.await.map_err(|_| EngineError::ChannelClosed)?
+    }
+
+    /// Request open-set identification against a multi-user gallery.
+    ///
+    /// Same capture pipeline as [`verify`](Self::verify), but matches with an
+    /// [`OpenSetMatcher`] and always runs the liveness check. With an `index`
+    /// built from `gallery`, each frame is rescored against the index's
+    /// shortlist only.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn identify(
+        &self,
+        gallery: Vec<FaceModel>,
+        index: Option<Arc<GalleryIndex>>,
+        threshold: f32,
+        margin: f32,
+        frames_count: usize,
+        timeout: std::time::Duration,
+        liveness_min_displacement: f32,
+    ) -> Result<VerifyResult, EngineError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.tx
+            .send(EngineRequest::Verify {
+                gallery,
+                threshold,
+                frames_count,
+                timeout,
+                liveness_enabled: true,
+                liveness_min_displacement,
+                open_set_margin: Some(margin),
+                index,
+                progress: None,
+                cancel: None,
+                tally: None,
                 reply: reply_tx,
             })
             .await
@@ -118,85 +742,409 @@ impl EngineHandle {
 
 /// Spawn the engine on a dedicated OS thread.
 ///
-/// Opens the camera, loads both ONNX models, discards warmup frames,
+/// Opens the camera (and the RGB camera of IR + RGB mode, see
+/// [`crate::fusion`], and the depth camera, see [`crate::depth`]), loads both
+/// ONNX models, configures per-stream warmup,
 /// then enters a request loop. Fails fast at startup if any resource
 /// is unavailable.
+///
+/// With a `precapture` window the camera keeps streaming into a
+/// [`FrameRing`] for that long after each verify, until the next request
+/// arrives; the next verify starts by matching the frames of the last
+/// [`PRECAPTURE_MAX_AGE`] while its own burst is captured (see
+/// [`run_verify`]). Not used with an RGB or depth camera, whose frames must
+/// come from the same moment as the IR burst.
+///
+/// With `pad_model`, a matched face must also score `pad_threshold` or more
+/// on that anti-spoofing model (see [`visage_core::pad`]).
+///
+/// The engine thread and the inference threads of the models run with
+/// `scheduling` (see [`crate::scheduling`]).
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_engine(
-    camera_device: &str,
+    camera_devices: &[String],
     scrfd_path: &str,
     arcface_path: &str,
     warmup_frames: usize,
     emitter_enabled: bool,
+    quality_gate: QualityGate,
+    allow_virtual_camera: bool,
+    budget: RequestBudget,
+    inference: visage_core::InferenceOptions,
+    verify_latency: Option<std::time::Duration>,
+    precapture: Option<std::time::Duration>,
+    night_schedule: Option<NightSchedule>,
+    rgb_camera_device: Option<&str>,
+    rgb_max_correlation: f32,
+    depth_camera_device: Option<&str>,
+    depth_min_relief_mm: f32,
+    pad_model: Option<&str>,
+    pad_threshold: f32,
+    calibration_dir: &Path,
+    scheduling: Scheduling,
 ) -> Result<EngineHandle, EngineError> {
     // Open camera and load models synchronously (fail-fast)
-    let camera = Camera::open(camera_device)?;
+    let night_mode = night_schedule.as_ref().map(|n| n.mode);
+    let camera_devices = camera_devices.to_vec();
+    let model_paths = (scrfd_path.to_string(), arcface_path.to_string());
+    let calibration_dir = calibration_dir.to_path_buf();
+    // Every capture opens a fresh stream whose AGC/AE restarts, so warmup
+    // frames are skipped per stream rather than once here at startup.
+    let open = move |device: &str, warmup_frames: usize| {
+        open_ir_camera(
+            device,
+            allow_virtual_camera,
+            quality_gate,
+            warmup_frames,
+            night_mode,
+            &calibration_dir,
+        )
+    };
+    let camera = open_first(&camera_devices, emitter_enabled, &|device| {
+        open(device, warmup_frames)
+    })?;
     tracing::info!(
-        device = camera_device,
+        device = %camera.device_path,
         width = camera.width,
         height = camera.height,
         fourcc = ?camera.fourcc,
         "camera opened"
     );
-
-    let mut detector = visage_core::FaceDetector::load(scrfd_path)?;
-    tracing::info!(path = scrfd_path, "SCRFD detector loaded");
-
-    let mut recognizer = visage_core::FaceRecognizer::load(arcface_path)?;
-    tracing::info!(path = arcface_path, "ArcFace recognizer loaded");
-
-    // Probe for IR emitter quirk
-    let emitter: Option<IrEmitter> = if emitter_enabled {
-        match IrEmitter::for_device(camera_device) {
-            Some(e) => {
-                tracing::info!(name = %e.name(), device = %e.device_path(), "IR emitter found");
-                Some(e)
-            }
-            None => {
-                tracing::warn!(
-                    device = camera_device,
-                    "no IR emitter quirk for device; proceeding without illumination"
-                );
-                None
+    let rgb_camera = match rgb_camera_device {
+        Some(crate::fusion::AUTO_DEVICE) => {
+            let paired = pair_rgb_camera(&camera, depth_camera_device, allow_virtual_camera);
+            match &paired {
+                Some(rgb) => tracing::info!(
+                    device = %rgb.device_path,
+                    width = rgb.width,
+                    height = rgb.height,
+                    "RGB camera paired with the IR camera (IR + RGB mode)"
+                ),
+                None => tracing::info!(
+                    device = %camera.device_path,
+                    "no RGB node on the IR camera's device; IR + RGB mode off"
+                ),
             }
+            paired.map(|mut rgb| {
+                rgb.set_stream_warmup(warmup_frames);
+                rgb
+            })
         }
-    } else {
-        tracing::info!("IR emitter disabled via VISAGE_EMITTER_ENABLED=0");
-        None
+        Some(device) => {
+            let mut rgb =
+                open_camera(device, allow_virtual_camera).map_err(EngineError::RgbCamera)?;
+            rgb.set_stream_warmup(warmup_frames);
+            tracing::info!(
+                device,
+                width = rgb.width,
+                height = rgb.height,
+                "RGB camera opened (IR + RGB mode)"
+            );
+            Some(rgb)
+        }
+        None => None,
     };
-
-    // Discard warmup frames for camera AGC/AE stabilization
-    if warmup_frames > 0 {
-        tracing::info!(count = warmup_frames, "discarding warmup frames");
-        for _ in 0..warmup_frames {
-            let _ = camera.capture_frame();
+    let depth_camera = match depth_camera_device {
+        Some(device) => {
+            let mut depth = open_depth_camera(device, allow_virtual_camera)
+                .map_err(EngineError::DepthCamera)?;
+            depth.set_stream_warmup(warmup_frames);
+            tracing::info!(
+                device,
+                width = depth.width,
+                height = depth.height,
+                min_relief_mm = depth_min_relief_mm,
+                "depth camera opened"
+            );
+            Some(depth)
         }
+        None => None,
+    };
+
+    if inference.reproducible {
+        // Everything else in the pipeline is already deterministic: detection
+        // candidates, frames, and gallery models that tie keep the earliest.
+        tracing::info!(
+            session = %inference.summary(),
+            "reproducibility mode: inference settings fixed"
+        );
+    }
+    if !scheduling.is_default() {
+        tracing::info!(
+            cpus = ?scheduling.cpus,
+            nice = ?scheduling.nice,
+            rt_priority = ?scheduling.rt_priority,
+            "engine scheduling configured"
+        );
     }
+    // Loaded under the engine's scheduling, so the ONNX Runtime threads the
+    // sessions start inherit it.
+    let (mut detector, mut recognizer, mut pad) =
+        scheduling.run(|| -> Result<_, EngineError> {
+            let detector = visage_core::FaceDetector::load_with(scrfd_path, inference)?;
+            tracing::info!(path = scrfd_path, "SCRFD detector loaded");
+
+            let recognizer = visage_core::FaceRecognizer::load_with(arcface_path, inference)?;
+            tracing::info!(path = arcface_path, "ArcFace recognizer loaded");
+
+            let pad = match pad_model {
+                Some(path) => {
+                    let pad = visage_core::PadModel::load_with(path, inference)?;
+                    tracing::info!(path, threshold = pad_threshold, "PAD model loaded");
+                    Some(pad)
+                }
+                None => None,
+            };
+            Ok((detector, recognizer, pad))
+        })?;
+
+    let emitter = probe_emitter(&camera.device_path, emitter_enabled, camera.calibration());
+
+    let precapture = match precapture {
+        Some(_) if rgb_camera.is_some() || depth_camera.is_some() => {
+            tracing::warn!("pre-capture is not used with an RGB or depth camera; disabled");
+            None
+        }
+        precapture => precapture,
+    };
 
     let (tx, mut rx) = mpsc::channel::<EngineRequest>(4);
+    let activity = Arc::new(Activity::new());
+    let engine_activity = Arc::clone(&activity);
+    let camera_present = Arc::new(AtomicBool::new(true));
+    let present = Arc::clone(&camera_present);
+    let camera_in_use = Arc::new(std::sync::Mutex::new(camera.device_path.clone()));
+    let in_use = Arc::clone(&camera_in_use);
+    let emitter_present = Arc::new(AtomicBool::new(emitter.is_some()));
+    let has_emitter = Arc::clone(&emitter_present);
 
     std::thread::Builder::new()
         .name("visage-engine".into())
         .spawn(move || {
+            scheduling.apply();
             // `camera` must be reassignable so the engine can re-open the device
             // in-process (self-heal) rather than requiring a daemon restart (#48).
             let mut camera = camera;
-            let device_path = camera.device_path.clone();
+            let (mut rgb_camera, mut depth_camera) = (rgb_camera, depth_camera);
+            let mut emitter = emitter;
+            // The emitter setting at startup, for the dark-frame probe of
+            // `open_first`; a reload only changes the emitter in use.
+            let probe_emitter_enabled = emitter_enabled;
+            let mut emitter_enabled = emitter_enabled;
+            let mut warmup_frames = warmup_frames;
+            // Re-open the first usable camera of the list: the one in use if
+            // it works, else the next that does.
+            let reopen = |warmup_frames: usize| {
+                let camera = open_first(&camera_devices, probe_emitter_enabled, &|device| {
+                    open(device, warmup_frames)
+                })?;
+                let mut in_use = in_use.lock().unwrap_or_else(|e| e.into_inner());
+                if *in_use != camera.device_path {
+                    tracing::info!(from = %in_use, to = %camera.device_path, "switched camera");
+                    in_use.clone_from(&camera.device_path);
+                }
+                Ok(camera)
+            };
+            // Checked per request: the night hours start and the lights go out
+            // while the daemon runs.
+            let night_forced = || night_schedule.as_ref().is_some_and(|n| n.forced_now());
+            let mut night_learner =
+                NightLearner::new(night_schedule.as_ref().map_or(0, |n| n.learn_after));
             let mut consecutive_failures: u32 = 0;
+            let mut sizer = verify_latency.map(|target| FrameSizer::new(target, warmup_frames));
+            let mut ring = FrameRing::new(PRECAPTURE_RING_FRAMES);
+            let mut warm_until: Option<std::time::Instant> = None;
+            let mut profiling: Option<Profiling> = None;
 
             tracing::info!("engine thread started");
-            while let Some(req) = rx.blocking_recv() {
+            loop {
+                // The emitter and camera may have changed with the request;
+                // see `properties`.
+                has_emitter.store(emitter.is_some(), Ordering::Relaxed);
+                crate::properties::changed();
+                if let Some(until) = warm_until.take() {
+                    engine_activity.start();
+                    run_precapture(&camera, &emitter, &mut ring, until, &rx);
+                }
+                engine_activity.finish();
+                crate::properties::changed();
+                let Some(req) = rx.blocking_recv() else {
+                    break;
+                };
+                engine_activity.start();
+                crate::properties::changed();
+                // An unplugged camera may be back without an `add` event
+                // reaching us (no uevent socket, or a missed event).
+                if !present.load(Ordering::Relaxed)
+                    && matches!(
+                        req,
+                        EngineRequest::Enroll { .. }
+                            | EngineRequest::Verify { .. }
+                            | EngineRequest::Prewarm { .. }
+                    )
+                {
+                    if let Ok(fresh) = reopen(warmup_frames) {
+                        camera = fresh;
+                        emitter = probe_emitter(
+                            &camera.device_path,
+                            emitter_enabled,
+                            camera.calibration(),
+                        );
+                        present.store(true, Ordering::Relaxed);
+                        tracing::info!(device = %camera.device_path, "camera is back; re-opened");
+                    }
+                }
                 let broken = match req {
+                    EngineRequest::Prewarm { reply } => {
+                        // A pending self-heal is done now rather than on the
+                        // user's first unlock.
+                        if consecutive_failures > 0 {
+                            match reopen(warmup_frames) {
+                                Ok(fresh) => {
+                                    let switched = fresh.device_path != camera.device_path;
+                                    camera = fresh;
+                                    if switched {
+                                        emitter = probe_emitter(
+                                            &camera.device_path,
+                                            emitter_enabled,
+                                            camera.calibration(),
+                                        );
+                                    }
+                                    consecutive_failures = 0;
+                                    tracing::info!(
+                                        device = %camera.device_path,
+                                        "camera re-opened during pre-warm"
+                                    );
+                                }
+                                Err(e) => {
+                                    tracing::warn!(error = %e, "pre-warm camera re-open failed")
+                                }
+                            }
+                        }
+                        let _ = reply.send(run_prewarm(&camera, &mut detector, &mut recognizer));
+                        continue;
+                    }
+                    EngineRequest::Reconfigure {
+                        emitter_enabled: enabled,
+                        warmup_frames: frames,
+                        reply,
+                    } => {
+                        if enabled != emitter_enabled {
+                            emitter_enabled = enabled;
+                            emitter = probe_emitter(&camera.device_path, enabled, camera.calibration());
+                        }
+                        if frames != warmup_frames {
+                            warmup_frames = frames;
+                            camera.set_stream_warmup(frames);
+                            if let Some(rgb) = rgb_camera.as_mut() {
+                                rgb.set_stream_warmup(frames);
+                            }
+                            if let Some(depth) = depth_camera.as_mut() {
+                                depth.set_stream_warmup(frames);
+                            }
+                            tracing::info!(warmup_frames = frames, "engine warmup changed");
+                        }
+                        let _ = reply.send(());
+                        continue;
+                    }
+                    EngineRequest::ReprobeEmitter { reply } => {
+                        emitter = probe_emitter(&camera.device_path, emitter_enabled, camera.calibration());
+                        let _ = reply.send(emitter.as_ref().map(|e| e.name().to_string()));
+                        continue;
+                    }
+                    EngineRequest::Profile { dir, reply } => {
+                        let (scrfd, arcface) = (&model_paths.0, &model_paths.1);
+                        let loaded = Profiling::load(&dir, scrfd, arcface, inference).map(
+                            |(loaded, receiver)| {
+                                tracing::info!(dir = %dir.display(), "profiling the next verify");
+                                profiling = Some(loaded);
+                                receiver
+                            },
+                        );
+                        let _ = reply.send(loaded);
+                        continue;
+                    }
+                    EngineRequest::CameraRemoved { reply } => {
+                        present.store(false, Ordering::Relaxed);
+                        consecutive_failures = 0;
+                        let _ = reply.send(());
+                        continue;
+                    }
+                    EngineRequest::CameraAttached { reply } => {
+                        let result = if present.load(Ordering::Relaxed) {
+                            Ok(())
+                        } else {
+                            let deadline = std::time::Instant::now() + RECONNECT_WAIT;
+                            match wait_for_camera(
+                                &camera.device_path,
+                                &reopen,
+                                warmup_frames,
+                                deadline,
+                                None,
+                            ) {
+                                Some(fresh) => {
+                                    camera = fresh;
+                                    emitter =
+                                        probe_emitter(&camera.device_path, emitter_enabled, camera.calibration());
+                                    present.store(true, Ordering::Relaxed);
+                                    Ok(())
+                                }
+                                None => Err(camera_absent(&camera.device_path)),
+                            }
+                        };
+                        let _ = reply.send(result);
+                        continue;
+                    }
+                    EngineRequest::Enroll { reply, .. } if !present.load(Ordering::Relaxed) => {
+                        let _ = reply.send(Err(camera_absent(&camera.device_path)));
+                        continue;
+                    }
+                    EngineRequest::Verify { reply, .. } if !present.load(Ordering::Relaxed) => {
+                        let _ = reply.send(Err(camera_absent(&camera.device_path)));
+                        continue;
+                    }
                     EngineRequest::Enroll {
                         frames_count,
+                        pose,
                         reply,
                     } => {
-                        let result = run_enroll(
-                            &camera,
-                            &emitter,
-                            &mut detector,
-                            &mut recognizer,
-                            frames_count,
-                        );
+                        let meter = Meter::start("enroll", budget);
+                        let mut reconnected = false;
+                        let result = loop {
+                            let result = run_enroll(
+                                &camera,
+                                &emitter,
+                                &mut detector,
+                                &mut recognizer,
+                                frames_count,
+                                pose,
+                                night_forced(),
+                                &meter,
+                            );
+                            if reconnected || !camera_disconnected(&result) {
+                                break result;
+                            }
+                            reconnected = true;
+                            let deadline = std::time::Instant::now() + RECONNECT_WAIT;
+                            match wait_for_camera(
+                                &camera.device_path,
+                                &reopen,
+                                warmup_frames,
+                                deadline,
+                                None,
+                            ) {
+                                Some(fresh) => {
+                                    camera = fresh;
+                                    emitter =
+                                        probe_emitter(&camera.device_path, emitter_enabled, camera.calibration());
+                                }
+                                None => {
+                                    present.store(false, Ordering::Relaxed);
+                                    break result;
+                                }
+                            }
+                        };
+                        meter.finish();
                         let broken = capture_looks_broken(&result);
                         let _ = reply.send(result);
                         broken
@@ -208,23 +1156,102 @@ pub fn spawn_engine(
                         timeout,
                         liveness_enabled,
                         liveness_min_displacement,
+                        open_set_margin,
+                        index,
+                        progress,
+                        cancel,
+                        tally,
                         reply,
                     } => {
                         let deadline = std::time::Instant::now() + timeout;
-                        let result = run_verify(
-                            &camera,
-                            &emitter,
-                            &mut detector,
-                            &mut recognizer,
-                            &gallery,
-                            threshold,
-                            frames_count,
-                            deadline,
-                            liveness_enabled,
-                            liveness_min_displacement,
-                        );
+                        let meter = Meter::start("verify", budget);
+                        let frames_count =
+                            sizer.as_ref().map_or(frames_count, |s| s.frames(frames_count));
+                        camera.set_learned_exposure(night_learner.exposure());
+                        let force_night = night_forced() || night_learner.start_on_night();
+                        // The newest frames only: as many as the burst itself.
+                        let mut precaptured = ring.take_recent(PRECAPTURE_MAX_AGE);
+                        precaptured.drain(..precaptured.len().saturating_sub(frames_count));
+                        let mut precaptured = Some(precaptured);
+                        // A profiled verify runs on the profiling sessions.
+                        let mut profiled = Profiling::take(&mut profiling);
+                        let (active_detector, active_recognizer) = match profiled.as_mut() {
+                            Some(p) => (&mut p.detector, &mut p.recognizer),
+                            None => (&mut detector, &mut recognizer),
+                        };
+                        let mut reconnected = false;
+                        let result = loop {
+                            let result = run_verify(
+                                &camera,
+                                rgb_camera.as_ref().map(|rgb| (rgb, rgb_max_correlation)),
+                                depth_camera.as_ref().map(|d| (d, depth_min_relief_mm)),
+                                &emitter,
+                                active_detector,
+                                active_recognizer,
+                                pad.as_mut().map(|pad| (pad, pad_threshold)),
+                                &gallery,
+                                threshold,
+                                frames_count,
+                                force_night,
+                                precaptured.take().unwrap_or_default(),
+                                deadline,
+                                liveness_enabled,
+                                liveness_min_displacement,
+                                open_set_margin,
+                                index.as_deref(),
+                                progress.as_ref(),
+                                cancel.as_deref(),
+                                tally.as_deref(),
+                                &meter,
+                                sizer.as_mut(),
+                                &mut night_learner,
+                            );
+                            // A camera that drops mid-burst (flaky cable, dock) and
+                            // comes back before the deadline gets one more burst.
+                            if reconnected || !camera_disconnected(&result) {
+                                break result;
+                            }
+                            reconnected = true;
+                            if let Some(progress) = &progress {
+                                let _ = progress.send(VerifyStage::CameraDisconnected);
+                            }
+                            match wait_for_camera(
+                                &camera.device_path,
+                                &reopen,
+                                warmup_frames,
+                                deadline,
+                                cancel.as_deref(),
+                            ) {
+                                Some(fresh) => {
+                                    camera = fresh;
+                                    emitter =
+                                        probe_emitter(&camera.device_path, emitter_enabled, camera.calibration());
+                                }
+                                None => {
+                                    present.store(false, Ordering::Relaxed);
+                                    break result;
+                                }
+                            }
+                        };
+                        meter.finish();
+                        if let Some(profiled) = profiled {
+                            profiled.finish();
+                        }
+                        night_learner.record_outcome(match &result {
+                            Ok(verify) => Some(verify.result.matched),
+                            Err(
+                                EngineError::NoFaceDetected
+                                | EngineError::NoUsableFrames
+                                | EngineError::LowQualityFrames { .. },
+                            ) => None,
+                            Err(_) => Some(false),
+                        });
                         let broken = capture_looks_broken(&result);
                         let _ = reply.send(result);
+                        // A failed capture would fail again in the background.
+                        warm_until = precapture
+                            .filter(|_| !broken)
+                            .map(|window| std::time::Instant::now() + window);
                         broken
                     }
                 };
@@ -240,11 +1267,19 @@ pub fn spawn_engine(
                             consecutive_failures,
                             "repeated camera-broken captures — re-initializing camera (self-heal)"
                         );
-                        match Camera::open(&device_path) {
+                        match reopen(warmup_frames) {
                             Ok(fresh) => {
+                                let switched = fresh.device_path != camera.device_path;
                                 camera = fresh;
+                                if switched {
+                                    emitter = probe_emitter(
+                                        &camera.device_path,
+                                        emitter_enabled,
+                                        camera.calibration(),
+                                    );
+                                }
                                 consecutive_failures = 0;
-                                tracing::info!(device = %device_path, "camera re-opened after failures");
+                                tracing::info!(device = %camera.device_path, "camera re-opened after failures");
                             }
                             Err(e) => {
                                 // Keep the old handle and retry on the next failure;
@@ -261,24 +1296,575 @@ pub fn spawn_engine(
         })
         .expect("failed to spawn engine thread");
 
-    Ok(EngineHandle { tx })
+    Ok(EngineHandle {
+        tx,
+        activity,
+        camera_present,
+        camera_in_use,
+        emitter_present,
+    })
 }
 
-/// Activate the IR emitter and sleep briefly for AGC stabilisation.
-/// Logs a warning on failure but never propagates the error — capture
-/// continues with ambient light.
-fn activate_emitter(emitter: &Option<IrEmitter>) {
-    if let Some(e) = emitter {
-        if let Err(err) = e.activate() {
-            tracing::warn!(error = %err, "IR emitter activate failed; continuing without illumination");
+#[cfg(test)]
+impl EngineHandle {
+    /// A handle to an engine thread that never starts: it reports as running
+    /// and idle since now, and answers no request.
+    pub fn detached() -> Self {
+        let (tx, rx) = mpsc::channel(1);
+        // Kept open so the engine reads as running for the whole test.
+        std::mem::forget(rx);
+        Self {
+            tx,
+            activity: Arc::new(Activity::new()),
+            camera_present: Arc::new(AtomicBool::new(true)),
+            camera_in_use: Arc::new(std::sync::Mutex::new("/dev/video0".to_string())),
+            emitter_present: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Mark the engine as taking up a request, or as finishing it.
+    pub fn set_busy(&self, busy: bool) {
+        if busy {
+            self.activity.start();
         } else {
-            // Allow AGC (auto gain control) to stabilise before capture.
-            std::thread::sleep(std::time::Duration::from_millis(100));
+            self.activity.finish();
         }
     }
 }
 
-/// Deactivate the IR emitter. Logs a warning on failure.
+/// Open the camera, refusing virtual devices (v4l2loopback and friends) unless
+/// `allow_virtual` is set — anything can write frames into them.
+fn open_camera(device_path: &str, allow_virtual: bool) -> Result<Camera, visage_hw::CameraError> {
+    let camera = Camera::open(device_path)?;
+    if let Some(reason) = camera.virtual_reason() {
+        if !allow_virtual {
+            return Err(visage_hw::CameraError::VirtualDevice(format!(
+                "{device_path}: {reason} (set VISAGE_ALLOW_VIRTUAL_CAMERA=1 for testing)"
+            )));
+        }
+        tracing::warn!(
+            device = device_path,
+            %reason,
+            "using a virtual camera — VISAGE_ALLOW_VIRTUAL_CAMERA is set; do not use in production"
+        );
+    }
+    Ok(camera)
+}
+
+/// The first other capture node on the USB device of `ir` that opens — the
+/// colour camera of a Windows Hello module. Skips `depth_device`.
+fn pair_rgb_camera(ir: &Camera, depth_device: Option<&str>, allow_virtual: bool) -> Option<Camera> {
+    Camera::list_devices()
+        .into_iter()
+        .filter(|node| {
+            node.bus == ir.bus_info
+                && node.path != ir.device_path
+                && Some(node.path.as_str()) != depth_device
+        })
+        .find_map(|node| match open_camera(&node.path, allow_virtual) {
+            Ok(rgb) => Some(rgb),
+            Err(e) => {
+                tracing::debug!(device = %node.path, error = %e, "not usable as the RGB camera");
+                None
+            }
+        })
+}
+
+/// Open a depth camera, with the same virtual-device refusal as [`open_camera`].
+fn open_depth_camera(
+    device_path: &str,
+    allow_virtual: bool,
+) -> Result<DepthCamera, visage_hw::CameraError> {
+    let camera = DepthCamera::open(device_path)?;
+    if let Some(reason) = camera.virtual_reason() {
+        if !allow_virtual {
+            return Err(visage_hw::CameraError::VirtualDevice(format!(
+                "{device_path}: {reason} (set VISAGE_ALLOW_VIRTUAL_CAMERA=1 for testing)"
+            )));
+        }
+        tracing::warn!(device = device_path, %reason, "using a virtual depth camera");
+    }
+    Ok(camera)
+}
+
+/// The IR emitter of `camera_device`, if `enabled` and a quirk is known for
+/// it, with the calibrated control payload applied.
+fn probe_emitter(
+    camera_device: &str,
+    enabled: bool,
+    calibration: &Calibration,
+) -> Option<IrEmitter> {
+    if !enabled {
+        tracing::info!("IR emitter disabled by configuration (emitter_enabled)");
+        return None;
+    }
+    match find_emitter(camera_device, calibration) {
+        Some(e) => {
+            tracing::info!(name = %e.name(), device = %e.device_path(), "IR emitter found");
+            Some(e)
+        }
+        None => {
+            tracing::warn!(
+                device = camera_device,
+                "no IR emitter quirk for device; proceeding without illumination"
+            );
+            None
+        }
+    }
+}
+
+/// The IR emitter quirk for `camera_device`, with the calibrated payload.
+fn find_emitter(camera_device: &str, calibration: &Calibration) -> Option<IrEmitter> {
+    let mut emitter = IrEmitter::for_device(camera_device)?;
+    if let Some(bytes) = calibration.emitter_bytes.clone() {
+        if let Err(err) = emitter.set_control_bytes(bytes) {
+            tracing::warn!(error = %err, "ignoring calibrated emitter payload");
+        }
+    }
+    Some(emitter)
+}
+
+/// The error of a capture request while the camera is unplugged.
+fn camera_absent(device_path: &str) -> EngineError {
+    EngineError::Camera(visage_hw::CameraError::Disconnected(format!(
+        "{device_path} is not attached"
+    )))
+}
+
+/// Re-open a camera that disconnected, polling until `deadline` or until
+/// `cancel` is set. `None` when it did not come back in time; the next
+/// request that finds it gone tries again.
+fn wait_for_camera(
+    device_path: &str,
+    reopen: &dyn Fn(usize) -> Result<Camera, visage_hw::CameraError>,
+    warmup_frames: usize,
+    deadline: std::time::Instant,
+    cancel: Option<&AtomicBool>,
+) -> Option<Camera> {
+    tracing::warn!(
+        device = device_path,
+        "camera disconnected; waiting for it to return"
+    );
+    loop {
+        match reopen(warmup_frames) {
+            Ok(camera) => {
+                tracing::info!(device = device_path, "camera reconnected");
+                return Some(camera);
+            }
+            Err(e) => tracing::debug!(error = %e, "camera not back yet"),
+        }
+        let now = std::time::Instant::now();
+        if now + RECONNECT_POLL >= deadline || cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            tracing::warn!(
+                device = device_path,
+                "camera did not return before the deadline"
+            );
+            return None;
+        }
+        std::thread::sleep(RECONNECT_POLL);
+    }
+}
+
+/// Open the IR camera at `device_path` with the engine's capture settings and
+/// the calibration saved for it in `calibration_dir`, if any.
+fn open_ir_camera(
+    device_path: &str,
+    allow_virtual: bool,
+    quality_gate: QualityGate,
+    warmup_frames: usize,
+    night: Option<NightMode>,
+    calibration_dir: &Path,
+) -> Result<Camera, visage_hw::CameraError> {
+    let mut camera = open_camera(device_path, allow_virtual)?;
+    camera.set_quality_gate(quality_gate);
+    camera.set_stream_warmup(warmup_frames);
+    camera.set_profiles(CaptureProfile::default(), night);
+    if let Some((calibration, path)) = Calibration::load(calibration_dir, device_path) {
+        tracing::info!(path = %path.display(), "applying camera calibration");
+        camera.set_calibration(calibration);
+    }
+    Ok(camera)
+}
+
+/// The first camera of `entries` (see `camera_list`) that opens and, with its
+/// IR emitter on, shows a frame that is not dark; failing that, the first
+/// that opens, since a dark room is no reason to refuse every camera. A list
+/// of one device is opened without a test capture.
+fn open_first(
+    entries: &[String],
+    emitter_enabled: bool,
+    open: &dyn Fn(&str) -> Result<Camera, visage_hw::CameraError>,
+) -> Result<Camera, visage_hw::CameraError> {
+    let devices = camera_list::expand(entries);
+    if let [device] = devices.as_slice() {
+        return open(device);
+    }
+    let mut dark = None;
+    let mut last_error = None;
+    for device in &devices {
+        match open(device) {
+            Ok(camera) => {
+                let emitter = emitter_enabled
+                    .then(|| find_emitter(device, camera.calibration()))
+                    .flatten();
+                if shows_light(&camera, &emitter) {
+                    return Ok(camera);
+                }
+                tracing::info!(device, "camera shows only dark frames; trying the next");
+                dark.get_or_insert(camera);
+            }
+            Err(e) => {
+                tracing::debug!(device, error = %e, "camera did not open; trying the next");
+                last_error = Some(e);
+            }
+        }
+    }
+    dark.ok_or_else(|| {
+        last_error.unwrap_or_else(|| {
+            visage_hw::CameraError::DeviceNotFound(format!(
+                "no device matches {}",
+                entries.join(",")
+            ))
+        })
+    })
+}
+
+/// Whether `camera` delivers a lit frame with `emitter` on. A camera behind a
+/// closed lid, or an IR camera whose emitter does not fire, shows only dark
+/// frames.
+fn shows_light(camera: &Camera, emitter: &Option<IrEmitter>) -> bool {
+    activate_emitter(emitter);
+    let lit = matches!(camera.capture_frame(), Ok(frame) if !frame.is_dark);
+    deactivate_emitter(emitter);
+    lit
+}
+
+/// What the IR frames of one burst decided.
+pub struct BurstMatch {
+    pub result: MatchResult,
+    /// Detector confidence of the best-matching face.
+    pub best_quality: f32,
+    /// Longer bbox side (px) of the largest face seen in any frame.
+    pub best_face_size: f32,
+    /// The best-matching face and the size of its frame.
+    pub best_face: Option<(BoundingBox, (u32, u32))>,
+    /// Embedding of the best-matching face.
+    pub best_embedding: Option<Embedding>,
+    /// Index in the burst of the frame with the best-matching face.
+    pub best_frame: Option<usize>,
+    /// Live probability the PAD model gave the best-matching face; only
+    /// scored for a match.
+    pub pad_score: Option<f32>,
+}
+
+/// The identity decision on captured `frames`: detect, embed and compare
+/// each frame, keep the best comparison, then, with `liveness_min_displacement`
+/// set, require landmark movement for a match, and with `pad`, a live
+/// probability of at least its threshold from that model. Shared by
+/// [`run_verify`] and `visaged simulate`, which replays a recorded burst.
+///
+/// `check` runs before each frame and ends the attempt with its error;
+/// `on_face` runs once, with the detector confidence of the first face found.
+#[allow(clippy::too_many_arguments)]
+pub fn match_burst(
+    frames: &[Frame],
+    detector: &mut visage_core::FaceDetector,
+    recognizer: &mut visage_core::FaceRecognizer,
+    pad: Option<(&mut PadModel, f32)>,
+    matcher: &dyn Matcher,
+    gallery: &[FaceModel],
+    threshold: f32,
+    liveness_min_displacement: Option<f32>,
+    check: &dyn Fn() -> Result<(), EngineError>,
+    on_face: &dyn Fn(f32),
+) -> Result<BurstMatch, EngineError> {
+    let mut best_result: Option<MatchResult> = None;
+    let mut best_quality = 0.0f32;
+    let mut best_face_size = 0.0f32;
+    let mut best_face: Option<(BoundingBox, (u32, u32))> = None;
+    let mut best_embedding: Option<Embedding> = None;
+    let mut best_frame: Option<usize> = None;
+    let mut any_face_detected = false;
+    let mut landmark_sequence: Vec<[(f32, f32); 5]> = Vec::new();
+
+    for (i, frame) in frames.iter().enumerate() {
+        check()?;
+        let faces = detector.detect(&frame.data, frame.width, frame.height)?;
+        let Some(face) = faces.first() else {
+            tracing::debug!(frame = i, seq = frame.sequence, "verify: no face");
+            continue;
+        };
+        if !any_face_detected {
+            on_face(face.confidence);
+        }
+        any_face_detected = true;
+        best_face_size = best_face_size.max(face.width.max(face.height));
+
+        // Collect landmarks for liveness check
+        if let Some(landmarks) = face.landmarks {
+            landmark_sequence.push(landmarks);
+        }
+
+        let embedding = recognizer.extract(&frame.data, frame.width, frame.height, face)?;
+        let result = matcher.compare(&embedding, gallery, threshold);
+        tracing::debug!(
+            frame = i,
+            seq = frame.sequence,
+            faces = faces.len(),
+            confidence = face.confidence,
+            size = face.width.max(face.height),
+            similarity = result.similarity,
+            matched = result.matched,
+            model = ?result.model_label.as_ref().or(result.closest_model_id.as_ref()),
+            "verify: frame"
+        );
+
+        let is_better = match &best_result {
+            None => true,
+            Some(prev) => result.similarity > prev.similarity,
+        };
+        if is_better {
+            best_quality = face.confidence;
+            best_face = Some((face.clone(), (frame.width, frame.height)));
+            best_result = Some(result);
+            best_embedding = Some(embedding);
+            best_frame = Some(i);
+        }
+    }
+    if !any_face_detected {
+        return Err(EngineError::NoFaceDetected);
+    }
+
+    // If no match result at all, return a non-match
+    let result = best_result.unwrap_or(MatchResult {
+        matched: false,
+        similarity: 0.0,
+        model_id: None,
+        model_label: None,
+        closest_model_id: None,
+    });
+
+    // --- Passive liveness check ---
+    // Run after detection loop so we always have full landmark data.
+    // Only gates the result when a match would otherwise succeed. The check
+    // fails closed: fewer than 2 landmark frames yields `is_live = false`
+    // (rejected), so a spoof that produces only a single detectable landmark
+    // frame cannot slip past liveness by starving it of evidence.
+    if let (Some(liveness_min_displacement), true) = (liveness_min_displacement, result.matched) {
+        check_liveness(
+            &landmark_sequence,
+            liveness_min_displacement,
+            result.similarity,
+        )?;
+    }
+
+    // --- Presentation attack detection ---
+    // Scored on the frame of the best match, the face the decision rests on.
+    let mut pad_score = None;
+    if let (Some((pad, pad_threshold)), Some((face, _)), Some(i), true) =
+        (pad, &best_face, best_frame, result.matched)
+    {
+        check()?;
+        let frame = &frames[i];
+        let score = pad.score(&frame.data, frame.width, frame.height, face)?;
+        tracing::debug!(score, threshold = pad_threshold, "PAD check");
+        if score < pad_threshold {
+            tracing::warn!(
+                similarity = result.similarity,
+                score,
+                "PAD model rejected a face that matched identity — possible presentation attack"
+            );
+            return Err(EngineError::PresentationAttack {
+                score,
+                threshold: pad_threshold,
+            });
+        }
+        pad_score = Some(score);
+    }
+
+    Ok(BurstMatch {
+        result,
+        best_quality,
+        best_face_size,
+        best_face,
+        best_embedding,
+        best_frame,
+        pad_score,
+    })
+}
+
+/// The passive liveness check of a matched burst: the eye landmarks must move
+/// by `min_displacement` px on average. Fails closed with fewer than two
+/// landmark frames, so a spoof cannot slip past by starving it of evidence.
+#[cfg(feature = "liveness")]
+fn check_liveness(
+    landmark_sequence: &[[(f32, f32); 5]],
+    min_displacement: f32,
+    similarity: f32,
+) -> Result<(), EngineError> {
+    let liveness = check_landmark_stability(landmark_sequence, Some(min_displacement));
+    tracing::debug!(
+        is_live = liveness.is_live,
+        mean_eye_displacement = liveness.mean_eye_displacement,
+        frame_pairs = liveness.frame_pairs_analysed,
+        threshold = min_displacement,
+        "liveness check"
+    );
+    if !liveness.is_live {
+        tracing::warn!(
+            similarity,
+            displacement = liveness.mean_eye_displacement,
+            "liveness rejected a face that matched identity — possible spoof attempt"
+        );
+        return Err(EngineError::LivenessCheckFailed {
+            displacement: liveness.mean_eye_displacement,
+            threshold: min_displacement,
+        });
+    }
+    Ok(())
+}
+
+/// Built without the `liveness` feature, where `Config::load` keeps liveness
+/// off: fail closed should a check be asked for anyway.
+#[cfg(not(feature = "liveness"))]
+fn check_liveness(
+    _landmark_sequence: &[[(f32, f32); 5]],
+    min_displacement: f32,
+    _similarity: f32,
+) -> Result<(), EngineError> {
+    Err(EngineError::LivenessCheckFailed {
+        displacement: 0.0,
+        threshold: min_displacement,
+    })
+}
+
+/// The depth check of a matched face: its relief across `samples` must reach
+/// `min_relief_mm`. Returns the relief.
+#[cfg(feature = "liveness")]
+fn check_depth_relief(
+    samples: &[(f32, f32, f32)],
+    min_relief_mm: f32,
+    similarity: f32,
+) -> Result<f32, EngineError> {
+    let planarity = check_depth_planarity(samples, Some(min_relief_mm));
+    tracing::debug!(
+        is_live = planarity.is_live,
+        relief_mm = planarity.relief_mm,
+        samples = planarity.samples,
+        threshold = min_relief_mm,
+        "depth planarity check"
+    );
+    if !planarity.is_live {
+        tracing::warn!(
+            similarity,
+            relief_mm = planarity.relief_mm,
+            samples = planarity.samples,
+            "depth check found a flat face that matched identity — possible photo or screen"
+        );
+        return Err(EngineError::FlatFace {
+            relief_mm: planarity.relief_mm,
+            min_relief_mm,
+        });
+    }
+    Ok(planarity.relief_mm)
+}
+
+/// Built without the `liveness` feature, where `Config::load` refuses a depth
+/// camera: fail closed should one be used anyway.
+#[cfg(not(feature = "liveness"))]
+fn check_depth_relief(
+    _samples: &[(f32, f32, f32)],
+    min_relief_mm: f32,
+    _similarity: f32,
+) -> Result<f32, EngineError> {
+    Err(EngineError::FlatFace {
+        relief_mm: 0.0,
+        min_relief_mm,
+    })
+}
+
+/// Run one detection and one embedding on a blank frame of the camera's size,
+/// so the first real request does not pay for the runtime's first-run setup.
+fn run_prewarm(
+    camera: &Camera,
+    detector: &mut visage_core::FaceDetector,
+    recognizer: &mut visage_core::FaceRecognizer,
+) -> Result<(), EngineError> {
+    let started = std::time::Instant::now();
+    let (width, height) = camera.frame_size();
+    let blank = vec![128u8; width as usize * height as usize];
+    detector.detect(&blank, width, height)?;
+    let face = BoundingBox {
+        x: 0.0,
+        y: 0.0,
+        width: visage_core::alignment::ALIGNED_SIZE as f32,
+        height: visage_core::alignment::ALIGNED_SIZE as f32,
+        confidence: 1.0,
+        landmarks: Some(visage_core::alignment::REFERENCE_LANDMARKS_112),
+    };
+    recognizer.extract(&blank, width, height, &face)?;
+    tracing::debug!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "engine pre-warmed"
+    );
+    Ok(())
+}
+
+/// Keep `camera` streaming into `ring`, with the emitter on, until `until`
+/// or until a request is waiting on `rx`. A window that ends with no request
+/// leaves the ring empty; a stream error only ends the window early.
+fn run_precapture(
+    camera: &Camera,
+    emitter: &Option<IrEmitter>,
+    ring: &mut FrameRing,
+    until: std::time::Instant,
+    rx: &mpsc::Receiver<EngineRequest>,
+) {
+    if !rx.is_empty() {
+        return;
+    }
+    activate_emitter(emitter);
+    let streamed = camera.stream_into(ring, &|| {
+        !rx.is_empty() || rx.is_closed() || std::time::Instant::now() >= until
+    });
+    deactivate_emitter(emitter);
+    match streamed {
+        Ok(frames) => tracing::debug!(frames, "pre-capture window ended"),
+        Err(e) => tracing::debug!(error = %e, "pre-capture stream failed"),
+    }
+    if rx.is_empty() {
+        ring.clear();
+    }
+}
+
+/// Activate the IR emitter and sleep briefly for AGC stabilisation; `true`
+/// if it is now on. Logs a warning on failure but never propagates the
+/// error — capture continues with ambient light.
+fn activate_emitter(emitter: &Option<IrEmitter>) -> bool {
+    let on = switch_on_emitter(emitter);
+    if on {
+        // Allow AGC (auto gain control) to stabilise before capture.
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    on
+}
+
+/// Activate the IR emitter without waiting; `true` if it is now on. Logs a
+/// warning on failure.
+fn switch_on_emitter(emitter: &Option<IrEmitter>) -> bool {
+    let Some(e) = emitter else {
+        return false;
+    };
+    match e.activate() {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::warn!(error = %err, "IR emitter activate failed; continuing without illumination");
+            false
+        }
+    }
+}
+
+/// Deactivate the IR emitter. Logs a warning on failure.
 fn deactivate_emitter(emitter: &Option<IrEmitter>) {
     if let Some(e) = emitter {
         if let Err(err) = e.deactivate() {
@@ -288,39 +1874,83 @@ fn deactivate_emitter(emitter: &Option<IrEmitter>) {
 }
 
 /// Capture frames, extract embeddings from all detected faces, and return
-/// a confidence-weighted average embedding (L2-normalized).
+/// a confidence-weighted average embedding (L2-normalized). With a `pose`
+/// target only faces in that pose count, and a burst without one fails with
+/// [`EngineError::WrongPose`]. Aborts once `meter` is over budget.
+#[allow(clippy::too_many_arguments)]
 fn run_enroll(
     camera: &Camera,
     emitter: &Option<IrEmitter>,
     detector: &mut visage_core::FaceDetector,
     recognizer: &mut visage_core::FaceRecognizer,
     frames_count: usize,
+    pose: Option<PoseTarget>,
+    force_night: bool,
+    meter: &Meter,
 ) -> Result<EnrollResult, EngineError> {
     activate_emitter(emitter);
-    let capture_result = camera.capture_frames(frames_count);
+    let capture_result = camera.capture_burst(frames_count, force_night, &|| meter.over_budget());
     deactivate_emitter(emitter);
+    meter.check("capture")?;
 
-    let (frames, dark_skipped) = capture_result?;
+    let (frames, stats) = capture_result?;
     tracing::debug!(
         captured = frames.len(),
-        dark_skipped,
+        dark_skipped = stats.dark,
+        saturated_skipped = stats.saturated,
+        blurry_skipped = stats.blurry,
+        motion_skipped = stats.motion,
+        night = stats.night,
         "enroll: captured frames"
     );
+    night::record_capture(stats.night);
 
     if frames.is_empty() {
-        return Err(EngineError::NoUsableFrames);
+        return Err(no_usable_frames(&stats));
+    }
+
+    let mut faces = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        meter.check("detect")?;
+        if let Some(face) = detector
+            .detect(&frame.data, frame.width, frame.height)?
+            .into_iter()
+            .next()
+        {
+            faces.push((i, face));
+        }
+    }
+
+    if let Some(target) = pose {
+        // Guided: the requested pose or nothing.
+        let detected = faces.len();
+        faces.retain(|(_, face)| face.pose().is_some_and(|p| target.accepts(&p)));
+        if faces.is_empty() && detected > 0 {
+            return Err(EngineError::WrongPose { faces: detected });
+        }
+    } else {
+        // Prefer frontal faces; fall back to every face rather than fail.
+        let frontal = |face: &BoundingBox| {
+            face.pose()
+                .filter(|p| !p.is_frontal(ENROLL_MAX_POSE_DEG))
+                .is_none()
+        };
+        let off_axis = faces.iter().filter(|(_, face)| !frontal(face)).count();
+        if off_axis > 0 && off_axis < faces.len() {
+            tracing::debug!(off_axis, "enroll: skipping off-axis faces");
+            faces.retain(|(_, face)| frontal(face));
+        } else if off_axis > 0 {
+            tracing::warn!("enroll: no frontal face captured; using off-axis faces");
+        }
     }
 
     let mut embeddings: Vec<(Embedding, f32)> = Vec::new();
     let mut best_confidence = 0.0f32;
     let mut best_frame_idx = 0usize;
+    let mut best_pose = None;
 
-    for (i, frame) in frames.iter().enumerate() {
-        let faces = detector.detect(&frame.data, frame.width, frame.height)?;
-        let Some(face) = faces.first() else {
-            continue;
-        };
-
+    for (i, face) in &faces {
+        let frame = &frames[*i];
         let embedding = match recognizer.extract(&frame.data, frame.width, frame.height, face) {
             Ok(embedding) => embedding,
             Err(visage_core::recognizer::RecognizerError::NoLandmarks) => continue,
@@ -330,7 +1960,8 @@ fn run_enroll(
         let weight = face.confidence.max(0.0);
         if weight > best_confidence {
             best_confidence = weight;
-            best_frame_idx = i;
+            best_frame_idx = *i;
+            best_pose = face.pose();
         }
 
         embeddings.push((embedding, weight));
@@ -346,164 +1977,390 @@ fn run_enroll(
         "enroll: best face selected"
     );
 
-    let dim = embeddings[0].0.values.len();
-
-    let total_weight: f32 = embeddings.iter().map(|(_, w)| *w).sum();
-    let (denom, use_weighted) = if total_weight > 0.0 {
-        (total_weight, true)
-    } else {
-        (embeddings.len() as f32, false)
-    };
-
-    let mut avg = vec![0.0f32; dim];
-    for (emb, w) in &embeddings {
-        let w = if use_weighted { *w } else { 1.0 };
-        for (a, v) in avg.iter_mut().zip(emb.values.iter()) {
-            *a += v * w;
-        }
-    }
-    for v in &mut avg {
-        *v /= denom;
-    }
-
-    // L2-normalize the averaged embedding
-    let norm: f32 = avg.iter().map(|x| x * x).sum::<f32>().sqrt();
-    if norm > 0.0 {
-        for v in &mut avg {
-            *v /= norm;
-        }
-    }
-
-    let embedding = Embedding {
-        values: avg,
-        model_version: embeddings[0].0.model_version.clone(),
-    };
+    let embedding = Embedding::weighted_mean(&embeddings).ok_or(EngineError::NoFaceDetected)?;
 
     Ok(EnrollResult {
         embedding,
         quality_score: best_confidence,
+        pose: best_pose,
     })
 }
 
+/// Between verify stages: [`EngineError::Cancelled`] once `cancel` is set,
+/// else [`EngineError::ResourceLimit`] once `meter` is over budget. `stage`
+/// names the work just finished, for the log.
+fn checkpoint(cancel: Option<&AtomicBool>, meter: &Meter, stage: &str) -> Result<(), EngineError> {
+    if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+        return Err(EngineError::Cancelled);
+    }
+    meter.check(stage)
+}
+
 /// Capture frames, detect faces, extract embeddings, compare against gallery.
 /// Uses the best match across all captured frames.
 ///
 /// When `liveness_enabled` is true, collects eye landmarks across all frames
 /// and runs a passive stability check before accepting a match. Static images
 /// (photographs) produce near-identical landmarks and are rejected.
+///
+/// With `open_set_margin` set, the gallery spans several users and matching
+/// uses [`OpenSetMatcher`] (identification) instead of [`CosineMatcher`],
+/// narrowed to a shortlist when an `index` is supplied.
+///
+/// Each [`VerifyStage`] is sent on `progress` once, as it is reached. Once
+/// `cancel` is set the capture stops (switching off the emitter) and the
+/// attempt ends with [`EngineError::Cancelled`]; once `meter` is over budget
+/// it ends with [`EngineError::ResourceLimit`]. The capture and the
+/// per-frame work are timed into `sizer`, if given.
+///
+/// With an `rgb_camera` (IR + RGB mode) a burst is captured from it at the
+/// same time, and an IR match must also pass the [`RgbCheck`], including a
+/// cross-spectral correlation of at most the given limit. With a
+/// `depth_camera` one depth frame is captured alongside, and the matched face
+/// must have at least the given relief in millimetres.
+///
+/// `precaptured` frames, buffered by the warm camera just before the
+/// request, are matched while the burst is captured; a match (liveness
+/// included) ends the burst early and decides the attempt. Otherwise the
+/// burst decides as if they had not been there. Never given with an RGB or
+/// depth camera.
 #[allow(clippy::too_many_arguments)]
 fn run_verify(
     camera: &Camera,
+    rgb_camera: Option<(&Camera, f32)>,
+    depth_camera: Option<(&DepthCamera, f32)>,
     emitter: &Option<IrEmitter>,
     detector: &mut visage_core::FaceDetector,
     recognizer: &mut visage_core::FaceRecognizer,
+    mut pad: Option<(&mut PadModel, f32)>,
     gallery: &[FaceModel],
     threshold: f32,
     frames_count: usize,
+    force_night: bool,
+    precaptured: Vec<Frame>,
     deadline: std::time::Instant,
     liveness_enabled: bool,
     liveness_min_displacement: f32,
+    open_set_margin: Option<f32>,
+    index: Option<&GalleryIndex>,
+    progress: Option<&ProgressSender>,
+    cancel: Option<&AtomicBool>,
+    tally: Option<&CaptureTally>,
+    meter: &Meter,
+    mut sizer: Option<&mut FrameSizer>,
+    night_learner: &mut NightLearner,
 ) -> Result<VerifyResult, EngineError> {
+    // The receiver may be gone (caller disconnected); progress is best-effort.
+    let report = |stage: VerifyStage| {
+        if let Some(tx) = progress {
+            let _ = tx.send(stage);
+        }
+    };
+
+    let cancelled = || cancel.is_some_and(|c| c.load(Ordering::Relaxed));
+
+    if cancelled() {
+        return Err(EngineError::Cancelled);
+    }
     if std::time::Instant::now() > deadline {
         return Err(EngineError::VerifyTimeout);
     }
 
-    activate_emitter(emitter);
-    let capture_result = camera.capture_frames(frames_count);
-    deactivate_emitter(emitter);
+    let matcher: Box<dyn Matcher + '_> = match (open_set_margin, index) {
+        (Some(margin), Some(index)) => Box::new(IndexedMatcher {
+            index,
+            inner: OpenSetMatcher { margin },
+            shortlist: visage_core::index::DEFAULT_SHORTLIST,
+        }),
+        (Some(margin), None) => Box::new(OpenSetMatcher { margin }),
+        (None, _) => Box::new(CosineMatcher),
+    };
+    let check = || checkpoint(cancel, meter, "detect");
+    let face_reported = std::cell::Cell::new(false);
+    let on_face = |confidence: f32| {
+        if !face_reported.replace(true) {
+            report(VerifyStage::FaceDetected { confidence });
+            report(VerifyStage::Matching);
+        }
+    };
+
+    report(VerifyStage::CaptureStarted);
+    // Whether the IR emitter lit the frames, for their `FrameMeta`.
+    let lit;
+    let (capture_result, rgb_capture, depth_capture, capture_elapsed) = if precaptured.is_empty() {
+        lit = activate_emitter(emitter);
+        let capture_started = std::time::Instant::now();
+        let stop = || cancelled() || meter.over_budget();
+        // Both streams capture the same moment: the RGB burst runs alongside.
+        let (ir, rgb, depth) = std::thread::scope(|scope| {
+            let rgb = rgb_camera
+                .map(|(rgb, _)| scope.spawn(|| rgb.capture_burst(frames_count, false, &stop)));
+            let depth = depth_camera.map(|(depth, _)| scope.spawn(|| depth.capture_frame()));
+            let ir = camera.capture_burst(frames_count, force_night, &stop);
+            let rgb = rgb.map(|handle| {
+                handle.join().unwrap_or_else(|_| {
+                    Err(visage_hw::CameraError::CaptureFailed(
+                        "RGB capture thread panicked".into(),
+                    ))
+                })
+            });
+            let depth = depth.map(|handle| {
+                handle.join().unwrap_or_else(|_| {
+                    Err(visage_hw::CameraError::CaptureFailed(
+                        "depth capture thread panicked".into(),
+                    ))
+                })
+            });
+            (ir, rgb, depth)
+        });
+        let capture_elapsed = capture_started.elapsed();
+        deactivate_emitter(emitter);
+        (ir, rgb, depth, capture_elapsed)
+    } else {
+        // The buffered frames are matched while the burst is captured; a
+        // match stops the burst.
+        let matched_early = AtomicBool::new(false);
+        let stop = || cancelled() || meter.over_budget() || matched_early.load(Ordering::Relaxed);
+        // The emitter was on until the request arrived: no need to wait
+        // for the sensor to settle.
+        lit = switch_on_emitter(emitter);
+        let (early, early_elapsed, (ir, capture_elapsed)) = std::thread::scope(|scope| {
+            let ir = scope.spawn(|| {
+                let capture_started = std::time::Instant::now();
+                let ir = camera.capture_burst(frames_count, force_night, &stop);
+                (ir, capture_started.elapsed())
+            });
+            let matching_started = std::time::Instant::now();
+            let early = camera
+                .quality_gate()
+                .check_burst(&precaptured)
+                .map_err(EngineError::StaleFrames)
+                .and_then(|()| {
+                    match_burst(
+                        &precaptured,
+                        detector,
+                        recognizer,
+                        pad.as_mut()
+                            .map(|(pad, threshold)| (&mut **pad, *threshold)),
+                        matcher.as_ref(),
+                        gallery,
+                        threshold,
+                        liveness_enabled.then_some(liveness_min_displacement),
+                        &check,
+                        &on_face,
+                    )
+                });
+            let early_elapsed = matching_started.elapsed();
+            if early.as_ref().is_ok_and(|burst| burst.result.matched) {
+                matched_early.store(true, Ordering::Relaxed);
+            }
+            let ir = ir.join().unwrap_or_else(|_| {
+                let panicked =
+                    visage_hw::CameraError::CaptureFailed("capture thread panicked".into());
+                (Err(panicked), std::time::Duration::ZERO)
+            });
+            (early, early_elapsed, ir)
+        });
+        deactivate_emitter(emitter);
+        match early {
+            Ok(burst) if burst.result.matched => {
+                tracing::debug!(
+                    frames = precaptured.len(),
+                    similarity = burst.result.similarity,
+                    "verify: matched on pre-captured frames"
+                );
+                let lit = emitter.as_ref().filter(|_| lit);
+                return Ok(VerifyResult {
+                    result: burst.result,
+                    best_quality: burst.best_quality,
+                    best_face_size: burst.best_face_size,
+                    rgb: None,
+                    depth_relief_mm: None,
+                    pad_score: burst.pad_score,
+                    probe: burst.best_embedding,
+                    frame: burst
+                        .best_frame
+                        .map(|i| FrameMeta::of(&precaptured[i], lit, true)),
+                    capture_time: capture_elapsed,
+                    inference_time: early_elapsed,
+                });
+            }
+            Err(e @ (EngineError::Cancelled | EngineError::ResourceLimit { .. })) => return Err(e),
+            Err(EngineError::StaleFrames(stale)) => {
+                tracing::warn!(reason = %stale, "verify: rejecting stale pre-captured frames — possible replay");
+                return Err(EngineError::StaleFrames(stale));
+            }
+            _ => tracing::debug!("verify: no match on pre-captured frames; using the burst"),
+        }
+        (ir, None, None, capture_elapsed)
+    };
 
+    checkpoint(cancel, meter, "capture")?;
     if std::time::Instant::now() > deadline {
         return Err(EngineError::VerifyTimeout);
     }
 
-    let (frames, dark_skipped) = capture_result?;
+    let (frames, stats) = capture_result?;
+    if let Some(tally) = tally {
+        tally.record(frames.len(), &stats);
+    }
+    if let Some(sizer) = sizer.as_deref_mut() {
+        sizer.record_capture(capture_elapsed, frames.len() + stats.rejected());
+    }
+    crate::metrics::global().record_capture(
+        capture_elapsed,
+        frames.len() + stats.rejected(),
+        stats.dark,
+    );
     tracing::debug!(
         captured = frames.len(),
-        dark_skipped,
+        dark_skipped = stats.dark,
+        saturated_skipped = stats.saturated,
+        blurry_skipped = stats.blurry,
+        motion_skipped = stats.motion,
+        night = stats.night,
         "verify: captured frames"
     );
+    night::record_capture(stats.night);
+    night_learner.record_capture(&stats);
 
     if frames.is_empty() {
-        return Err(EngineError::NoUsableFrames);
+        return Err(no_usable_frames(&stats));
     }
 
-    let matcher = CosineMatcher;
-    let mut best_result: Option<MatchResult> = None;
-    let mut best_quality = 0.0f32;
-    let mut any_face_detected = false;
-    let mut landmark_sequence: Vec<[(f32, f32); 5]> = Vec::new();
-
-    for frame in &frames {
-        let faces = detector.detect(&frame.data, frame.width, frame.height)?;
-        let Some(face) = faces.first() else {
-            continue;
-        };
-        any_face_detected = true;
-
-        // Collect landmarks for liveness check
-        if let Some(landmarks) = face.landmarks {
-            landmark_sequence.push(landmarks);
-        }
-
-        let embedding = recognizer.extract(&frame.data, frame.width, frame.height, face)?;
-        let result = matcher.compare(&embedding, gallery, threshold);
-
-        let is_better = match &best_result {
-            None => true,
-            Some(prev) => result.similarity > prev.similarity,
-        };
-        if is_better {
-            best_quality = face.confidence;
-            best_result = Some(result);
-        }
+    // Bind the decision to this burst: a video loop fed through a virtual
+    // camera repeats sequence numbers or timestamps, or shows no sensor noise.
+    if let Err(stale) = camera.quality_gate().check_burst(&frames) {
+        tracing::warn!(reason = %stale, "verify: rejecting stale frame burst — possible replay");
+        return Err(EngineError::StaleFrames(stale));
     }
 
-    if !any_face_detected {
-        return Err(EngineError::NoFaceDetected);
+    let processing_started = std::time::Instant::now();
+    let burst = match_burst(
+        &frames,
+        detector,
+        recognizer,
+        pad,
+        matcher.as_ref(),
+        gallery,
+        threshold,
+        liveness_enabled.then_some(liveness_min_displacement),
+        &check,
+        &on_face,
+    );
+    let processing_elapsed = processing_started.elapsed();
+    crate::metrics::global().record_inference(processing_elapsed);
+    if let Some(sizer) = sizer {
+        sizer.record_processing(processing_elapsed, frames.len());
     }
+    if let Some(tally) = tally {
+        let kept = burst.as_ref().ok().and_then(|burst| burst.best_frame);
+        tally.keep_frame(&frames[kept.unwrap_or(frames.len() / 2)]);
+    }
+    let BurstMatch {
+        result,
+        best_quality,
+        best_face_size,
+        best_face,
+        best_embedding,
+        best_frame,
+        pad_score,
+    } = burst?;
+    let frame =
+        best_frame.map(|i| FrameMeta::of(&frames[i], emitter.as_ref().filter(|_| lit), false));
 
-    // If no match result at all, return a non-match
-    let result = best_result.unwrap_or(MatchResult {
-        matched: false,
-        similarity: 0.0,
-        model_id: None,
-        model_label: None,
-    });
-
-    // --- Passive liveness check ---
-    // Run after detection loop so we always have full landmark data.
-    // Only gates the result when a match would otherwise succeed. The check
-    // fails closed: fewer than 2 landmark frames yields `is_live = false`
-    // (rejected), so a spoof that produces only a single detectable landmark
-    // frame cannot slip past liveness by starving it of evidence.
-    if liveness_enabled && result.matched {
-        let liveness =
-            check_landmark_stability(&landmark_sequence, Some(liveness_min_displacement));
-
+    // --- RGB confirmation (IR + RGB mode) ---
+    // Like liveness, only an IR match pays for detection on the RGB frames.
+    let mut rgb = None;
+    if let (Some(capture), Some((_, max_correlation)), true) =
+        (rgb_capture, rgb_camera, result.matched)
+    {
+        let (rgb_frames, _) = capture.map_err(EngineError::RgbCamera)?;
+        let mut rgb_faces = Vec::with_capacity(rgb_frames.len());
+        for frame in &rgb_frames {
+            meter.check("detect")?;
+            let faces = detector.detect(&frame.data, frame.width, frame.height)?;
+            rgb_faces.push(faces.into_iter().next());
+        }
+        let mut check = RgbCheck::assess(
+            rgb_frames.len(),
+            &rgb_faces,
+            liveness_enabled.then_some(liveness_min_displacement),
+        );
+        if let (Some((face, _)), Some(i)) = (&best_face, best_frame) {
+            let rgb_pairs: Vec<(&Frame, &BoundingBox)> = rgb_frames
+                .iter()
+                .zip(&rgb_faces)
+                .filter_map(|(frame, face)| Some((frame, face.as_ref()?)))
+                .collect();
+            check = check.compare_spectra((&frames[i], face), &rgb_pairs, max_correlation);
+        }
         tracing::debug!(
-            is_live = liveness.is_live,
-            mean_eye_displacement = liveness.mean_eye_displacement,
-            frame_pairs = liveness.frame_pairs_analysed,
-            threshold = liveness_min_displacement,
-            "liveness check"
+            frames = check.frames,
+            faces = check.faces,
+            attentive = check.attentive,
+            eye_displacement = check.eye_displacement,
+            live = check.live,
+            correlation = ?check.correlation,
+            "RGB check"
         );
-
-        if !liveness.is_live {
+        if check.flat {
+            let correlation = check.correlation.unwrap_or(1.0);
             tracing::warn!(
                 similarity = result.similarity,
-                displacement = liveness.mean_eye_displacement,
-                "liveness rejected a face that matched identity — possible spoof attempt"
+                correlation,
+                "IR and RGB faces are one flat picture — possible print or screen"
             );
-            return Err(EngineError::LivenessCheckFailed {
-                displacement: liveness.mean_eye_displacement,
-                threshold: liveness_min_displacement,
+            return Err(EngineError::FlatPicture {
+                correlation,
+                max_correlation,
             });
         }
+        if !check.passed() {
+            tracing::warn!(
+                similarity = result.similarity,
+                faces = check.faces,
+                attentive = check.attentive,
+                "RGB stream did not confirm an IR match — possible IR-only spoof or inattentive user"
+            );
+            return Err(EngineError::RgbCheckFailed {
+                faces: check.faces,
+                attentive: check.attentive,
+            });
+        }
+        rgb = Some(check);
+    }
+
+    // --- Depth planarity (depth camera) ---
+    let mut depth_relief_mm = None;
+    if let (Some(capture), Some((depth_camera, min_relief_mm)), true) =
+        (depth_capture, depth_camera, result.matched)
+    {
+        let frame = capture.map_err(EngineError::DepthCamera)?;
+        let samples = match &best_face {
+            Some((face, ir_size)) => {
+                depth::face_samples(&frame, face, *ir_size, &depth_camera.info)
+            }
+            None => Vec::new(),
+        };
+        depth_relief_mm = Some(check_depth_relief(
+            &samples,
+            min_relief_mm,
+            result.similarity,
+        )?);
     }
 
     Ok(VerifyResult {
         result,
         best_quality,
+        best_face_size,
+        rgb,
+        depth_relief_mm,
+        pad_score,
+        probe: best_embedding,
+        frame,
+        capture_time: capture_elapsed,
+        inference_time: processing_elapsed,
     })
 }
 
@@ -536,6 +2393,151 @@ mod tests {
                 threshold: 1.0,
             }
         )));
+        assert!(!capture_looks_broken::<()>(&Err(
+            EngineError::LowQualityFrames {
+                saturated: 0,
+                blurry: 3,
+                motion: 2,
+            }
+        )));
         assert!(!capture_looks_broken::<()>(&Ok(())));
     }
+
+    #[test]
+    fn empty_capture_is_too_dark_only_when_dark_frames_dominate() {
+        let dark = CaptureStats {
+            dark: 6,
+            blurry: 2,
+            ..CaptureStats::default()
+        };
+        assert!(matches!(
+            no_usable_frames(&dark),
+            EngineError::NoUsableFrames
+        ));
+        let blurry = CaptureStats {
+            dark: 1,
+            blurry: 5,
+            ..CaptureStats::default()
+        };
+        assert!(matches!(
+            no_usable_frames(&blurry),
+            EngineError::LowQualityFrames { blurry: 5, .. }
+        ));
+    }
+
+    #[test]
+    fn over_budget_verify_aborts_at_the_next_stage() {
+        let cancel = AtomicBool::new(false);
+        let roomy = Meter::start(
+            "verify",
+            RequestBudget {
+                max_cpu: None,
+                max_wall: Some(std::time::Duration::from_secs(60)),
+            },
+        );
+        assert!(checkpoint(Some(&cancel), &roomy, "detect").is_ok());
+        assert!(checkpoint(None, &roomy, "capture").is_ok());
+
+        let spent = Meter::start(
+            "verify",
+            RequestBudget {
+                max_cpu: None,
+                max_wall: Some(std::time::Duration::ZERO),
+            },
+        );
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let aborts = crate::budget::abort_count();
+        let err = checkpoint(Some(&cancel), &spent, "detect").unwrap_err();
+        assert!(matches!(
+            err,
+            EngineError::ResourceLimit {
+                resource: "wall",
+                limit_ms: 0,
+                ..
+            }
+        ));
+        assert!(
+            crate::budget::abort_count() > aborts,
+            "the abort is counted"
+        );
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        // An abort says nothing about the camera.
+        assert!(!capture_looks_broken::<()>(&Err(err)));
+
+        // A cancelled verify ends as cancelled, whatever its budget.
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(
+            checkpoint(Some(&cancel), &spent, "capture"),
+            Err(EngineError::Cancelled)
+        ));
+        assert!(matches!(
+            checkpoint(Some(&cancel), &roomy, "capture"),
+            Err(EngineError::Cancelled)
+        ));
+    }
+
+    /// Stands in for a model session recording a profile.
+    struct Session(Option<&'static str>);
+
+    impl ProfiledSession for Session {
+        fn end_profiling(&mut self) -> Result<String, EngineError> {
+            self.0.map(str::to_string).ok_or(EngineError::ChannelClosed)
+        }
+    }
+
+    fn profiling(
+        detector: Option<&'static str>,
+        recognizer: Option<&'static str>,
+    ) -> (Profiling<Session, Session>, ProfileReceiver) {
+        let (written, receiver) = oneshot::channel();
+        let profiling = Profiling {
+            detector: Session(detector),
+            recognizer: Session(recognizer),
+            written,
+        };
+        (profiling, receiver)
+    }
+
+    #[test]
+    fn only_the_next_verify_is_profiled() {
+        let (p, mut files) = profiling(Some("scrfd.json"), Some("arcface.json"));
+        let mut pending = Some(p);
+        let profiled = Profiling::take(&mut pending).expect("the caller is waiting");
+        assert!(pending.is_none(), "the verify after runs unprofiled");
+        profiled.finish();
+        assert_eq!(
+            files.try_recv().unwrap().unwrap(),
+            vec!["scrfd.json".to_string(), "arcface.json".to_string()]
+        );
+
+        // A profile that cannot be written still answers the caller.
+        let (p, mut files) = profiling(Some("scrfd.json"), None);
+        p.finish();
+        assert!(files.try_recv().unwrap().is_err());
+
+        // A caller that stopped waiting leaves the verify on the engine's
+        // own sessions.
+        let (p, files) = profiling(Some("scrfd.json"), Some("arcface.json"));
+        drop(files);
+        let mut pending = Some(p);
+        assert!(Profiling::take(&mut pending).is_none());
+        assert!(pending.is_none());
+
+        // A second request before the verify replaces the first, whose
+        // caller then stops waiting.
+        let (first, mut first_files) = profiling(Some("a.json"), Some("b.json"));
+        let (second, mut second_files) = profiling(Some("c.json"), Some("d.json"));
+        let mut pending = Some(first);
+        assert!(pending.is_some());
+        pending = Some(second);
+        assert!(matches!(
+            first_files.try_recv(),
+            Err(oneshot::error::TryRecvError::Closed)
+        ));
+        Profiling::take(&mut pending).unwrap().finish();
+        assert_eq!(
+            second_files.try_recv().unwrap().unwrap(),
+            vec!["c.json".to_string(), "d.json".to_string()]
+        );
+    }
 }
@@ -0,0 +1,683 @@
+//! Step-wise enrollment sessions — the state behind an enrollment wizard.
+//!
+//! `Enroll` is one blocking call: one capture, and a dark or blurry burst
+//! fails the whole enrollment. A wizard instead opens a session with
+//! `EnrollBegin`, calls `EnrollStep` once per capture — each returns feedback
+//! the GUI can show ("too dark", "accepted 2 of 3") — and ends with
+//! `EnrollCommit` or `EnrollAbort`:
+//!
+//! ```text
+//! Begin ──► Step ──► Step ──► … ──► Commit (≥ steps accepted)
+//!             │ failed steps keep the session; retry
+//!             └──────────────────────────────► Abort / idle expiry
+//! ```
+//!
+//! A failed step is only feedback; the session stays open for another try.
+//! Nothing is stored until the commit, which saves every accepted step as a
+//! face model under the session's label, in one store transaction. A
+//! re-enrollment (option `replace`) swaps the user's whole gallery in that
+//! same transaction, so a crash leaves either the old models or the new
+//! ones. Sessions belong to the UID that began them (same rule as `Cancel`),
+//! run one step at a time, and expire after [`SESSION_IDLE`] without a call.
+//!
+//! A *guided* session ([`StepPlan::Guided`]) asks for one capture per pose in
+//! [`GUIDED_POSES`] — frontal, turned slightly left, turned slightly right,
+//! and frontal again with glasses taken off or put on — since a single
+//! frontal template recalls poorly at off angles. Each step tells the engine
+//! which head pose to accept ([`PoseTarget`]); a capture in another pose
+//! fails as `wrong_pose`, and one below [`MIN_GUIDED_QUALITY`] as
+//! `poor_quality`. The two side turns must be to opposite sides, whichever
+//! way the camera mirrors the image. After `EnrollBegin` and every step the
+//! daemon sends the caller `EnrollProgress(step, total, hint)` with the
+//! instruction for the next capture.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::consent::Consent;
+use crate::engine::EnrollResult;
+use crate::failure::FailureReason;
+use visage_core::{ErrorKind, HeadPose, VisageError};
+
+/// Most steps a session may require.
+pub const MAX_STEPS: u32 = 10;
+/// Most captures one session may run, accepted or not.
+const MAX_ATTEMPTS: u32 = 30;
+/// Most sessions open at once.
+const MAX_SESSIONS: usize = 8;
+/// Sessions untouched this long are dropped.
+pub const SESSION_IDLE: Duration = Duration::from_secs(300);
+/// Poses a guided session captures, in order.
+pub const GUIDED_POSES: [Pose; 4] = [Pose::Frontal, Pose::Left, Pose::Right, Pose::Glasses];
+/// Lowest detection confidence a guided capture is accepted with.
+pub const MIN_GUIDED_QUALITY: f32 = 0.6;
+/// Largest yaw and pitch (degrees) of a frontal guided capture.
+const FRONTAL_MAX_DEG: f32 = 12.0;
+/// Yaw range (degrees, either sign) of a "slightly turned" capture: enough to
+/// add a viewpoint, not so much that the far eye is hidden.
+const SIDE_MIN_YAW_DEG: f32 = 10.0;
+const SIDE_MAX_YAW_DEG: f32 = 35.0;
+/// Largest pitch (degrees) of a side capture.
+const SIDE_MAX_PITCH_DEG: f32 = 20.0;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    #[error("no such enrollment session (unknown, committed, aborted or expired)")]
+    Unknown,
+    #[error("enrollment session belongs to another caller")]
+    NotOwner,
+    #[error("a step of this enrollment session is still running")]
+    Busy,
+    #[error("enrollment session needs {required} accepted steps, has {accepted}")]
+    Incomplete { accepted: usize, required: u32 },
+    #[error("enrollment session ran its maximum of {MAX_ATTEMPTS} captures")]
+    TooManyAttempts,
+    #[error("too many enrollment sessions open (max {MAX_SESSIONS})")]
+    Full,
+}
+
+impl SessionError {
+    /// The shared [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SessionError::Unknown => ErrorKind::NotFound,
+            SessionError::NotOwner => ErrorKind::PermissionDenied,
+            SessionError::Incomplete { .. } => ErrorKind::InvalidArgument,
+            SessionError::Busy | SessionError::TooManyAttempts | SessionError::Full => {
+                ErrorKind::LimitExceeded
+            }
+        }
+    }
+}
+
+impl From<SessionError> for VisageError {
+    fn from(e: SessionError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
+}
+
+/// A pose a guided session asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pose {
+    Frontal,
+    Left,
+    Right,
+    /// Frontal, with glasses taken off (or put on).
+    Glasses,
+}
+
+impl Pose {
+    /// Stable code, also stored as the model's `pose_label`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Frontal => "frontal",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::Glasses => "glasses",
+        }
+    }
+
+    /// Instruction shown to the user before the capture.
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::Frontal => "Look straight at the camera",
+            Self::Left => "Turn your head slightly to the left",
+            Self::Right => "Turn your head slightly to the right",
+            Self::Glasses => {
+                "Take your glasses off (or put them on if you wear them) and look straight at the camera"
+            }
+        }
+    }
+}
+
+/// Head pose the engine accepts for one guided capture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoseTarget {
+    /// Range of the yaw's magnitude, in degrees.
+    pub min_yaw: f32,
+    pub max_yaw: f32,
+    /// Largest pitch magnitude, in degrees.
+    pub max_pitch: f32,
+    /// Required sign of the yaw (turn direction); `None` for either.
+    pub yaw_sign: Option<f32>,
+}
+
+impl PoseTarget {
+    pub fn accepts(&self, pose: &HeadPose) -> bool {
+        let side = match self.yaw_sign {
+            Some(sign) => pose.yaw * sign > 0.0,
+            None => true,
+        };
+        side && (self.min_yaw..=self.max_yaw).contains(&pose.yaw.abs())
+            && pose.pitch.abs() <= self.max_pitch
+    }
+}
+
+/// How many captures a session needs, and in which poses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepPlan {
+    /// This many accepted captures, in any pose.
+    Captures(u32),
+    /// One capture per pose of [`GUIDED_POSES`].
+    Guided,
+}
+
+/// An accepted capture.
+pub struct Capture {
+    pub result: EnrollResult,
+    /// The pose it was guided to; `None` outside guided sessions.
+    pub pose: Option<Pose>,
+}
+
+/// One open enrollment.
+pub struct EnrollSession {
+    pub tenant: String,
+    pub user: String,
+    pub label: String,
+    /// UID that began the session; `None` on the session bus.
+    owner: Option<u32>,
+    steps_required: u32,
+    /// Pose of each step of a guided session; empty otherwise.
+    poses: Vec<Pose>,
+    attempts: u32,
+    /// Captures that produced a usable embedding, in order.
+    pub accepted: Vec<Capture>,
+    /// Re-enrollment: the commit replaces the user's existing models.
+    pub replace: bool,
+    /// Taken at `EnrollBegin`; stored with every model of the session.
+    pub consent: Consent,
+    /// A step is running on the engine.
+    busy: bool,
+    last_activity: Instant,
+}
+
+/// What one step did, returned by `EnrollStep` as JSON.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StepFeedback {
+    /// The capture produced an embedding that will be saved on commit.
+    pub accepted: bool,
+    /// Why not, as a `FailureReason` code; empty when accepted.
+    pub reason: &'static str,
+    /// Detection confidence of the accepted capture.
+    pub quality: Option<f32>,
+    /// Pose the step asked for (guided sessions); empty otherwise.
+    pub pose: &'static str,
+    pub steps_accepted: usize,
+    pub steps_required: u32,
+    pub attempts: u32,
+    /// `EnrollCommit` will succeed now.
+    pub ready: bool,
+    /// What the user should do for the next step; empty once ready.
+    pub hint: &'static str,
+}
+
+/// Open sessions by ID.
+#[derive(Default)]
+pub struct EnrollSessions {
+    sessions: HashMap<String, EnrollSession>,
+}
+
+impl EnrollSessions {
+    /// Open a session with the captures of `plan`; with `replace` the commit
+    /// replaces the user's existing models. Returns its ID.
+    #[allow(clippy::too_many_arguments)]
+    pub fn begin(
+        &mut self,
+        tenant: &str,
+        user: &str,
+        label: &str,
+        owner: Option<u32>,
+        plan: StepPlan,
+        replace: bool,
+        consent: Consent,
+        now: Instant,
+    ) -> Result<String, SessionError> {
+        self.prune(now);
+        if self.sessions.len() >= MAX_SESSIONS {
+            return Err(SessionError::Full);
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let (steps_required, poses) = match plan {
+            StepPlan::Captures(steps) => (steps.clamp(1, MAX_STEPS), Vec::new()),
+            StepPlan::Guided => (GUIDED_POSES.len() as u32, GUIDED_POSES.to_vec()),
+        };
+        self.sessions.insert(
+            id.clone(),
+            EnrollSession {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                label: label.to_string(),
+                owner,
+                steps_required,
+                poses,
+                attempts: 0,
+                accepted: Vec::new(),
+                replace,
+                consent,
+                busy: false,
+                last_activity: now,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Claim session `id` for a capture and return the head pose the engine
+    /// should accept, if the session is guided. Must be followed by
+    /// [`finish_step`].
+    ///
+    /// [`finish_step`]: Self::finish_step
+    pub fn start_step(
+        &mut self,
+        id: &str,
+        caller: Option<u32>,
+        now: Instant,
+    ) -> Result<Option<PoseTarget>, SessionError> {
+        let session = self.get_mut(id, caller, now)?;
+        if session.busy {
+            return Err(SessionError::Busy);
+        }
+        if session.attempts >= MAX_ATTEMPTS {
+            return Err(SessionError::TooManyAttempts);
+        }
+        session.busy = true;
+        session.attempts += 1;
+        Ok(session.next_target())
+    }
+
+    /// Record the capture started by [`start_step`](Self::start_step).
+    /// `None` if the session vanished meanwhile.
+    pub fn finish_step(
+        &mut self,
+        id: &str,
+        outcome: Result<EnrollResult, FailureReason>,
+        now: Instant,
+    ) -> Option<StepFeedback> {
+        let session = self.sessions.get_mut(id)?;
+        session.busy = false;
+        session.last_activity = now;
+        let pose = session.next_pose();
+        let (reason, quality) = match outcome {
+            Ok(result) if pose.is_some() && result.quality_score < MIN_GUIDED_QUALITY => {
+                (FailureReason::PoorQuality.code(), None)
+            }
+            Ok(result) => {
+                let quality = result.quality_score;
+                session.accepted.push(Capture { result, pose });
+                ("", Some(quality))
+            }
+            Err(reason) => (reason.code(), None),
+        };
+        Some(StepFeedback {
+            accepted: quality.is_some(),
+            reason,
+            quality,
+            pose: pose.map_or("", Pose::code),
+            steps_accepted: session.accepted.len(),
+            steps_required: session.steps_required,
+            attempts: session.attempts,
+            ready: session.is_ready(),
+            hint: session.hint(),
+        })
+    }
+
+    /// `(step, total, hint)` of an `EnrollProgress` signal for session `id`:
+    /// captures accepted so far, captures required, and the instruction for
+    /// the next one.
+    pub fn progress(&self, id: &str) -> Option<(u32, u32, &'static str)> {
+        let session = self.sessions.get(id)?;
+        Some((
+            session.accepted.len() as u32,
+            session.steps_required,
+            session.hint(),
+        ))
+    }
+
+    /// Close session `id` for storing, once enough steps were accepted.
+    pub fn commit(
+        &mut self,
+        id: &str,
+        caller: Option<u32>,
+        now: Instant,
+    ) -> Result<EnrollSession, SessionError> {
+        let session = self.get_mut(id, caller, now)?;
+        if session.busy {
+            return Err(SessionError::Busy);
+        }
+        if !session.is_ready() {
+            return Err(SessionError::Incomplete {
+                accepted: session.accepted.len(),
+                required: session.steps_required,
+            });
+        }
+        self.sessions.remove(id).ok_or(SessionError::Unknown)
+    }
+
+    /// Discard session `id` without storing anything. A running step still
+    /// finishes, but its result is dropped.
+    pub fn abort(
+        &mut self,
+        id: &str,
+        caller: Option<u32>,
+        now: Instant,
+    ) -> Result<(), SessionError> {
+        self.get_mut(id, caller, now)?;
+        self.sessions.remove(id);
+        Ok(())
+    }
+
+    /// Sessions currently open.
+    pub fn open_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether a session is still open at `now`, idle ones dropped first.
+    pub fn any_open(&mut self, now: Instant) -> bool {
+        self.prune(now);
+        !self.sessions.is_empty()
+    }
+
+    fn get_mut(
+        &mut self,
+        id: &str,
+        caller: Option<u32>,
+        now: Instant,
+    ) -> Result<&mut EnrollSession, SessionError> {
+        self.prune(now);
+        let session = self.sessions.get_mut(id).ok_or(SessionError::Unknown)?;
+        let owned = match (caller, session.owner) {
+            (None, _) | (Some(0), _) => true,
+            (Some(caller), Some(owner)) => caller == owner,
+            (Some(_), None) => false,
+        };
+        if !owned {
+            return Err(SessionError::NotOwner);
+        }
+        session.last_activity = now;
+        Ok(session)
+    }
+
+    /// Drop idle sessions; one with a step running is never idle.
+    fn prune(&mut self, now: Instant) {
+        self.sessions.retain(|_, session| {
+            session.busy || now.duration_since(session.last_activity) < SESSION_IDLE
+        });
+    }
+}
+
+impl EnrollSession {
+    fn is_ready(&self) -> bool {
+        self.accepted.len() >= self.steps_required as usize
+    }
+
+    /// Pose of the next capture of a guided session.
+    fn next_pose(&self) -> Option<Pose> {
+        self.poses.get(self.accepted.len()).copied()
+    }
+
+    /// What the engine should accept for the next capture.
+    fn next_target(&self) -> Option<PoseTarget> {
+        let frontal = PoseTarget {
+            min_yaw: 0.0,
+            max_yaw: FRONTAL_MAX_DEG,
+            max_pitch: FRONTAL_MAX_DEG,
+            yaw_sign: None,
+        };
+        Some(match self.next_pose()? {
+            Pose::Frontal | Pose::Glasses => frontal,
+            pose @ (Pose::Left | Pose::Right) => {
+                // The second turn goes the other way from the first, however
+                // the camera maps the user's left to yaw.
+                let other_side = self
+                    .accepted
+                    .iter()
+                    .find(|c| {
+                        matches!(c.pose, Some(Pose::Left | Pose::Right)) && c.pose != Some(pose)
+                    })
+                    .and_then(|c| c.result.pose)
+                    .map(|p| -p.yaw.signum());
+                PoseTarget {
+                    min_yaw: SIDE_MIN_YAW_DEG,
+                    max_yaw: SIDE_MAX_YAW_DEG,
+                    max_pitch: SIDE_MAX_PITCH_DEG,
+                    yaw_sign: other_side,
+                }
+            }
+        })
+    }
+
+    /// Instruction for the next capture; empty once ready.
+    fn hint(&self) -> &'static str {
+        match self.next_pose() {
+            Some(pose) => pose.hint(),
+            None if self.is_ready() => "",
+            None => "Look at the camera",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(quality: f32) -> Result<EnrollResult, FailureReason> {
+        Ok(EnrollResult {
+            embedding: visage_core::Embedding {
+                values: vec![0.0; 512],
+                model_version: None,
+            },
+            quality_score: quality,
+            pose: None,
+        })
+    }
+
+    #[test]
+    fn failed_steps_keep_the_session_open() {
+        let now = Instant::now();
+        let mut sessions = EnrollSessions::default();
+        let id = sessions
+            .begin(
+                "default",
+                "alice",
+                "desk",
+                Some(1000),
+                StepPlan::Captures(2),
+                false,
+                Consent::default(),
+                now,
+            )
+            .unwrap();
+
+        sessions.start_step(&id, Some(1000), now).unwrap();
+        assert_eq!(
+            sessions.start_step(&id, Some(1000), now),
+            Err(SessionError::Busy)
+        );
+        let feedback = sessions
+            .finish_step(&id, Err(FailureReason::TooDark), now)
+            .unwrap();
+        assert!(!feedback.accepted);
+        assert_eq!((feedback.reason, feedback.steps_accepted), ("too_dark", 0));
+        assert_eq!(
+            sessions.commit(&id, Some(1000), now).err(),
+            Some(SessionError::Incomplete {
+                accepted: 0,
+                required: 2
+            })
+        );
+
+        sessions.start_step(&id, Some(1000), now).unwrap();
+        let feedback = sessions.finish_step(&id, capture(0.8), now).unwrap();
+        assert_eq!((feedback.quality, feedback.ready), (Some(0.8), false));
+        sessions.start_step(&id, Some(1000), now).unwrap();
+        let feedback = sessions.finish_step(&id, capture(0.9), now).unwrap();
+        assert!(feedback.ready);
+        assert_eq!(feedback.attempts, 3);
+
+        let session = sessions.commit(&id, Some(1000), now).unwrap();
+        assert_eq!(session.accepted.len(), 2);
+        assert_eq!(sessions.open_count(), 0);
+        assert_eq!(sessions.abort(&id, None, now), Err(SessionError::Unknown));
+    }
+
+    #[test]
+    fn sessions_belong_to_their_caller() {
+        let now = Instant::now();
+        let mut sessions = EnrollSessions::default();
+        let id = sessions
+            .begin(
+                "default",
+                "alice",
+                "desk",
+                Some(1000),
+                StepPlan::Captures(1),
+                false,
+                Consent::default(),
+                now,
+            )
+            .unwrap();
+        assert_eq!(
+            sessions.start_step(&id, Some(1001), now),
+            Err(SessionError::NotOwner)
+        );
+        assert_eq!(
+            sessions.abort(&id, Some(1001), now),
+            Err(SessionError::NotOwner)
+        );
+        assert_eq!(sessions.abort(&id, Some(0), now), Ok(()), "root may abort");
+    }
+
+    #[test]
+    fn sessions_are_bounded_and_expire() {
+        let now = Instant::now();
+        let mut sessions = EnrollSessions::default();
+        let ids: Vec<String> = (0..MAX_SESSIONS)
+            .map(|_| {
+                sessions
+                    .begin(
+                        "default",
+                        "alice",
+                        "desk",
+                        None,
+                        StepPlan::Captures(1),
+                        false,
+                        Consent::default(),
+                        now,
+                    )
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            sessions.begin(
+                "default",
+                "bob",
+                "desk",
+                None,
+                StepPlan::Captures(1),
+                false,
+                Consent::default(),
+                now
+            ),
+            Err(SessionError::Full)
+        );
+
+        sessions.start_step(&ids[0], None, now).unwrap();
+        let later = now + SESSION_IDLE;
+        assert!(sessions
+            .begin(
+                "default",
+                "bob",
+                "desk",
+                None,
+                StepPlan::Captures(1),
+                false,
+                Consent::default(),
+                later
+            )
+            .is_ok());
+        assert_eq!(sessions.open_count(), 2, "the busy session survives");
+        assert_eq!(
+            sessions.start_step(&ids[1], None, later),
+            Err(SessionError::Unknown)
+        );
+    }
+
+    fn turned(quality: f32, yaw: f32) -> Result<EnrollResult, FailureReason> {
+        let mut result = capture(quality)?;
+        result.pose = Some(HeadPose {
+            yaw,
+            pitch: 0.0,
+            roll: 0.0,
+        });
+        Ok(result)
+    }
+
+    #[test]
+    fn guided_sessions_walk_through_the_poses() {
+        let now = Instant::now();
+        let mut sessions = EnrollSessions::default();
+        let id = sessions
+            .begin(
+                "default",
+                "alice",
+                "desk",
+                None,
+                StepPlan::Guided,
+                false,
+                Consent::default(),
+                now,
+            )
+            .unwrap();
+        assert_eq!(sessions.progress(&id), Some((0, 4, Pose::Frontal.hint())));
+
+        let target = sessions.start_step(&id, None, now).unwrap().unwrap();
+        assert!(target.accepts(&HeadPose {
+            yaw: 5.0,
+            pitch: -5.0,
+            roll: 20.0
+        }));
+        let feedback = sessions.finish_step(&id, turned(0.3, 0.0), now).unwrap();
+        assert_eq!(
+            (feedback.accepted, feedback.reason),
+            (false, "poor_quality")
+        );
+        sessions.start_step(&id, None, now).unwrap();
+        let feedback = sessions.finish_step(&id, turned(0.9, 2.0), now).unwrap();
+        assert_eq!(
+            (feedback.pose, feedback.hint),
+            ("frontal", Pose::Left.hint())
+        );
+
+        // Either side counts as "left"; "right" must be the other one.
+        let target = sessions.start_step(&id, None, now).unwrap().unwrap();
+        assert_eq!(target.yaw_sign, None);
+        assert!(!target.accepts(&HeadPose {
+            yaw: 3.0,
+            pitch: 0.0,
+            roll: 0.0
+        }));
+        sessions.finish_step(&id, turned(0.9, -20.0), now).unwrap();
+        let target = sessions.start_step(&id, None, now).unwrap().unwrap();
+        assert_eq!(target.yaw_sign, Some(1.0));
+        let feedback = sessions
+            .finish_step(&id, Err(FailureReason::WrongPose), now)
+            .unwrap();
+        assert_eq!(
+            (feedback.reason, feedback.hint),
+            ("wrong_pose", Pose::Right.hint())
+        );
+        sessions.start_step(&id, None, now).unwrap();
+        sessions.finish_step(&id, turned(0.9, 18.0), now).unwrap();
+
+        sessions.start_step(&id, None, now).unwrap();
+        let feedback = sessions.finish_step(&id, turned(0.8, 1.0), now).unwrap();
+        assert!(feedback.ready);
+        assert_eq!((feedback.pose, feedback.hint), ("glasses", ""));
+        assert_eq!(sessions.progress(&id), Some((4, 4, "")));
+
+        let session = sessions.commit(&id, None, now).unwrap();
+        let poses: Vec<_> = session.accepted.iter().map(|c| c.pose).collect();
+        assert_eq!(poses, GUIDED_POSES.map(Some));
+    }
+}
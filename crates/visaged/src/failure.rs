@@ -0,0 +1,276 @@
+//! Failure-mode classification for verify attempts.
+//!
+//! A failed verify used to reach the user as a bare "No match" (or an opaque
+//! error string), so nobody could tell a dark room from a wrong face. Every
+//! failed attempt is now mapped to one [`FailureReason`] whose [`code`] is a
+//! stable wire string shared by the D-Bus API, the `VerifyFailed` signal, the
+//! PAM module, and the CLI. Clients own the human-readable wording.
+//!
+//! Engine errors are classified through their shared [`ErrorKind`]; a reason
+//! naming the same condition as a kind has the same code.
+//!
+//! [`code`]: FailureReason::code
+
+use visage_core::ErrorKind;
+
+use crate::engine::{EngineError, VerifyResult};
+
+/// Smallest detected face (longer bbox side, in pixels) that is still expected
+/// to produce a reliable embedding. Non-matches whose best face is smaller than
+/// this are reported as [`FailureReason::FaceTooSmall`] ("move closer") rather
+/// than as a plain below-threshold rejection.
+pub const MIN_FACE_SIZE_PX: f32 = 64.0;
+
+/// Why a verify attempt did not produce a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    /// Every captured frame was too dark to use.
+    TooDark,
+    /// Frames were lit but rejected by the quality gate (saturated, blurry, moving).
+    PoorQuality,
+    /// Frames were usable but no face was detected in any of them.
+    NoFace,
+    /// Faces were found, but none in the head pose a guided enrollment step
+    /// asked for.
+    WrongPose,
+    /// A face was found but too small (too far from the camera) to match reliably.
+    FaceTooSmall,
+    /// A face was found and compared, but similarity stayed below the threshold.
+    BelowThreshold,
+    /// The face matched identity but failed the passive liveness check.
+    LivenessFailed,
+    /// The frame burst repeated sequence numbers or timestamps, or showed no
+    /// sensor noise — a likely video loop through a virtual camera.
+    StaticSceneDetected,
+    /// The camera could not be opened or returned an error mid-capture.
+    CameraError,
+    /// Another application is streaming from the camera.
+    CameraBusy,
+    /// The verify deadline elapsed before a decision was reached.
+    Timeout,
+    /// The caller withdrew the request (e.g. the user typed a password).
+    Cancelled,
+    /// Inference failed or the engine thread is gone — not the user's fault.
+    Internal,
+}
+
+impl FailureReason {
+    /// Stable machine-readable code sent over D-Bus. Never change existing values.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::TooDark => "too_dark",
+            Self::PoorQuality => "poor_quality",
+            Self::NoFace => "no_face",
+            Self::WrongPose => "wrong_pose",
+            Self::FaceTooSmall => "face_too_small",
+            Self::BelowThreshold => "below_threshold",
+            Self::LivenessFailed => "liveness_failed",
+            Self::StaticSceneDetected => "static_scene",
+            Self::CameraError => "camera_error",
+            Self::CameraBusy => "camera_busy",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Internal => "internal_error",
+        }
+    }
+
+    /// Whether this outcome is a deliberate authentication failure that should
+    /// count against the rate limiter. Environmental failures (dark room,
+    /// blurry frames, camera error, timeout, no face in view) are not held
+    /// against the user.
+    pub fn counts_as_attempt(self) -> bool {
+        matches!(
+            self,
+            Self::FaceTooSmall
+                | Self::BelowThreshold
+                | Self::LivenessFailed
+                | Self::StaticSceneDetected
+        )
+    }
+
+    /// Classify an engine error by its [`ErrorKind`].
+    pub fn from_engine_error(err: &EngineError) -> Self {
+        match err.kind() {
+            ErrorKind::TooDark => Self::TooDark,
+            ErrorKind::PoorQuality => Self::PoorQuality,
+            ErrorKind::NoFace => Self::NoFace,
+            ErrorKind::WrongPose => Self::WrongPose,
+            ErrorKind::LivenessFailed => Self::LivenessFailed,
+            ErrorKind::StaticScene => Self::StaticSceneDetected,
+            ErrorKind::Timeout => Self::Timeout,
+            ErrorKind::Cancelled => Self::Cancelled,
+            ErrorKind::CameraBusy => Self::CameraBusy,
+            ErrorKind::Camera | ErrorKind::CameraDisconnected => Self::CameraError,
+            _ => Self::Internal,
+        }
+    }
+
+    /// Classify a completed (non-error) verify. Returns `None` on a match.
+    pub fn from_result(result: &VerifyResult) -> Option<Self> {
+        if result.result.matched {
+            None
+        } else if result.best_face_size > 0.0 && result.best_face_size < MIN_FACE_SIZE_PX {
+            Some(Self::FaceTooSmall)
+        } else {
+            Some(Self::BelowThreshold)
+        }
+    }
+}
+
+impl std::fmt::Display for FailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visage_core::MatchResult;
+
+    fn non_match(best_face_size: f32) -> VerifyResult {
+        VerifyResult {
+            result: MatchResult {
+                matched: false,
+                similarity: 0.1,
+                model_id: None,
+                model_label: None,
+                closest_model_id: None,
+            },
+            best_quality: 0.9,
+            best_face_size,
+            rgb: None,
+            depth_relief_mm: None,
+            pad_score: None,
+            probe: None,
+            frame: None,
+            capture_time: std::time::Duration::ZERO,
+            inference_time: std::time::Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn engine_errors_map_to_reasons() {
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::NoUsableFrames),
+            FailureReason::TooDark
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::NoFaceDetected),
+            FailureReason::NoFace
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::LivenessCheckFailed {
+                displacement: 0.1,
+                threshold: 0.8,
+            }),
+            FailureReason::LivenessFailed
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::RgbCheckFailed {
+                faces: 3,
+                attentive: 0,
+            }),
+            FailureReason::LivenessFailed
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::FlatPicture {
+                correlation: 0.97,
+                max_correlation: 0.9,
+            }),
+            FailureReason::LivenessFailed
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::FlatFace {
+                relief_mm: 2.0,
+                min_relief_mm: 15.0,
+            }),
+            FailureReason::LivenessFailed
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::PresentationAttack {
+                score: 0.1,
+                threshold: 0.5,
+            }),
+            FailureReason::LivenessFailed
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::Camera(
+                visage_hw::CameraError::DeviceBusy
+            )),
+            FailureReason::CameraBusy
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::Camera(
+                visage_hw::CameraError::DeviceNotFound("/dev/video2".into())
+            )),
+            FailureReason::CameraError
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::StaleFrames(
+                visage_hw::StaleBurst::TimestampReplayed
+            )),
+            FailureReason::StaticSceneDetected
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::ChannelClosed),
+            FailureReason::Internal
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::Cancelled),
+            FailureReason::Cancelled
+        );
+        assert_eq!(
+            FailureReason::from_engine_error(&EngineError::Camera(
+                visage_hw::CameraError::Disconnected("dequeue".into())
+            )),
+            FailureReason::CameraError
+        );
+    }
+
+    #[test]
+    fn error_reasons_share_the_error_kind_codes() {
+        for reason in [
+            FailureReason::TooDark,
+            FailureReason::PoorQuality,
+            FailureReason::NoFace,
+            FailureReason::WrongPose,
+            FailureReason::LivenessFailed,
+            FailureReason::StaticSceneDetected,
+            FailureReason::CameraError,
+            FailureReason::CameraBusy,
+            FailureReason::Timeout,
+            FailureReason::Cancelled,
+            FailureReason::Internal,
+        ] {
+            assert!(ErrorKind::from_code(reason.code()).is_some(), "{reason}");
+        }
+    }
+
+    #[test]
+    fn small_face_is_distinguished_from_below_threshold() {
+        assert_eq!(
+            FailureReason::from_result(&non_match(30.0)),
+            Some(FailureReason::FaceTooSmall)
+        );
+        assert_eq!(
+            FailureReason::from_result(&non_match(150.0)),
+            Some(FailureReason::BelowThreshold)
+        );
+    }
+
+    #[test]
+    fn environmental_failures_do_not_count_as_attempts() {
+        assert!(!FailureReason::TooDark.counts_as_attempt());
+        assert!(!FailureReason::PoorQuality.counts_as_attempt());
+        assert!(!FailureReason::NoFace.counts_as_attempt());
+        assert!(!FailureReason::WrongPose.counts_as_attempt());
+        assert!(!FailureReason::CameraError.counts_as_attempt());
+        assert!(!FailureReason::CameraBusy.counts_as_attempt());
+        assert!(!FailureReason::Internal.counts_as_attempt());
+        assert!(!FailureReason::Cancelled.counts_as_attempt());
+        assert!(FailureReason::BelowThreshold.counts_as_attempt());
+        assert!(FailureReason::LivenessFailed.counts_as_attempt());
+        assert!(FailureReason::StaticSceneDetected.counts_as_attempt());
+    }
+}
@@ -0,0 +1,276 @@
+//! IR + RGB verification — the IR stream decides identity, the RGB stream
+//! confirms a live person facing the screen.
+//!
+//! Laptops with Windows Hello cameras expose two V4L2 nodes: the IR sensor
+//! visage matches against, and the ordinary colour webcam. With
+//! `VISAGE_RGB_CAMERA_DEVICE` set, the engine captures a burst from both at
+//! once. The RGB frames never produce an embedding and cannot match on their
+//! own; after an IR match they must show a face turned towards the camera
+//! (within [`MAX_ATTENTION_ANGLE`]) and, with liveness enabled, the same eye
+//! movement the IR liveness check requires. An IR-only spoof (a heated mask
+//! or an IR print) tends to look nothing like a face in visible light, and a
+//! user asleep or looking away does not unlock the machine.
+//!
+//! A print that does look like a face in both is caught by comparing the two
+//! spectra ([`cross_spectral_correlation`]). A live face is lit by the emitter
+//! beside the lens in IR and by the room in visible light, reflects the two
+//! differently (lips, veins, stubble), and is seen with parallax by two lenses
+//! a few centimetres apart, so its IR and RGB crops differ beyond a shading
+//! gradient. A photo or a screen is one flat picture: once each crop's linear
+//! shading is removed, the two are nearly the same image. Above
+//! `VISAGE_RGB_MAX_CORRELATION` the match is rejected.
+//!
+//! `VISAGE_RGB_CAMERA_DEVICE=auto` pairs the IR camera with the capture node
+//! on the same USB device ([`AUTO_DEVICE`]), as Windows Hello cameras have.
+
+#[cfg(feature = "liveness")]
+use visage_core::check_landmark_stability;
+use visage_core::{alignment, BoundingBox};
+use visage_hw::Frame;
+
+/// Largest head yaw or pitch, in degrees, that still counts as facing the
+/// screen.
+pub const MAX_ATTENTION_ANGLE: f32 = 30.0;
+
+/// `VISAGE_RGB_CAMERA_DEVICE` value that picks the colour node sharing the
+/// IR camera's USB device.
+pub const AUTO_DEVICE: &str = "auto";
+
+/// The inner face of the aligned 112×112 crop — an ellipse over brows, eyes,
+/// nose and mouth, leaving out hair and background. Centre and radii.
+const FACE_ELLIPSE: (f32, f32, f32, f32) = (56.0, 62.0, 38.0, 46.0);
+
+/// What the RGB stream showed during one verify.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RgbCheck {
+    /// Usable RGB frames captured.
+    pub frames: usize,
+    /// Frames with a detected face.
+    pub faces: usize,
+    /// Frames whose face was within [`MAX_ATTENTION_ANGLE`] of frontal.
+    pub attentive: usize,
+    /// Mean eye landmark movement across the RGB frames, in pixels.
+    pub eye_displacement: f32,
+    /// Whether the RGB liveness check passed (always true when liveness is off).
+    pub live: bool,
+    /// Mean [`cross_spectral_correlation`] of the IR face with the RGB faces;
+    /// `None` when no RGB face had landmarks.
+    pub correlation: Option<f32>,
+    /// Whether the correlation was above the limit: a flat print or screen.
+    pub flat: bool,
+}
+
+impl RgbCheck {
+    /// Summarise the first face detected in each of `frames` RGB frames; `None`
+    /// entries are frames without a face. With `min_displacement` set the eye
+    /// movement must reach it, as for the IR liveness check.
+    pub fn assess(
+        frames: usize,
+        faces: &[Option<BoundingBox>],
+        min_displacement: Option<f32>,
+    ) -> Self {
+        let found: Vec<&BoundingBox> = faces.iter().flatten().collect();
+        let attentive = found
+            .iter()
+            .filter_map(|face| face.pose())
+            .filter(|pose| {
+                pose.yaw.abs() <= MAX_ATTENTION_ANGLE && pose.pitch.abs() <= MAX_ATTENTION_ANGLE
+            })
+            .count();
+        let (eye_displacement, live) = eye_movement(&found, min_displacement);
+        Self {
+            frames,
+            faces: found.len(),
+            attentive,
+            eye_displacement,
+            live,
+            correlation: None,
+            flat: false,
+        }
+    }
+
+    /// Compare the matched IR face with the RGB faces: the check is `flat`
+    /// when their mean correlation exceeds `max_correlation`.
+    pub fn compare_spectra(
+        mut self,
+        ir: (&Frame, &BoundingBox),
+        rgb: &[(&Frame, &BoundingBox)],
+        max_correlation: f32,
+    ) -> Self {
+        let correlations: Vec<f32> = rgb
+            .iter()
+            .filter_map(|&(frame, face)| cross_spectral_correlation(ir, (frame, face)))
+            .collect();
+        if !correlations.is_empty() {
+            let mean = correlations.iter().sum::<f32>() / correlations.len() as f32;
+            self.correlation = Some(mean);
+            self.flat = mean > max_correlation;
+        }
+        self
+    }
+
+    /// Whether the RGB stream backs an IR match.
+    pub fn passed(&self) -> bool {
+        self.attentive > 0 && self.live && !self.flat
+    }
+}
+
+/// Pearson correlation, -1 to 1, of the inner face in an IR and an RGB frame,
+/// each aligned to the 112×112 crop and with its best-fit linear shading
+/// removed. Near 1 for one flat picture seen in both spectra; `None` when a
+/// face has no landmarks or either crop is uniform.
+pub fn cross_spectral_correlation(
+    ir: (&Frame, &BoundingBox),
+    rgb: (&Frame, &BoundingBox),
+) -> Option<f32> {
+    let ir = shading_residual(ir.0, ir.1)?;
+    let rgb = shading_residual(rgb.0, rgb.1)?;
+    let (mut cross, mut ir_sq, mut rgb_sq) = (0.0f64, 0.0f64, 0.0f64);
+    for (a, b) in ir.iter().zip(&rgb) {
+        cross += a * b;
+        ir_sq += a * a;
+        rgb_sq += b * b;
+    }
+    let norm = (ir_sq * rgb_sq).sqrt();
+    (norm > f64::EPSILON).then(|| (cross / norm) as f32)
+}
+
+/// The inner-face pixels of `face`'s aligned crop minus the plane
+/// `a + b·x + c·y` that fits them best.
+fn shading_residual(frame: &Frame, face: &BoundingBox) -> Option<Vec<f64>> {
+    let landmarks = face.landmarks.as_ref()?;
+    let crop = alignment::align_face(&frame.data, frame.width, frame.height, landmarks);
+    let (cx, cy, rx, ry) = FACE_ELLIPSE;
+    let samples: Vec<(f64, f64, f64)> = crop
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &value)| {
+            let x = (i % alignment::ALIGNED_SIZE) as f32 + 0.5 - cx;
+            let y = (i / alignment::ALIGNED_SIZE) as f32 + 0.5 - cy;
+            ((x / rx).powi(2) + (y / ry).powi(2) <= 1.0).then_some((
+                f64::from(x),
+                f64::from(y),
+                f64::from(value),
+            ))
+        })
+        .collect();
+    // The ellipse is symmetric about its centre, so x, y and x·y sum to zero
+    // over it and the least-squares fit separates into three means.
+    let (mut sum, mut sum_x, mut sum_y, mut xx, mut yy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for &(x, y, v) in &samples {
+        sum += v;
+        sum_x += x * v;
+        sum_y += y * v;
+        xx += x * x;
+        yy += y * y;
+    }
+    let (a, b, c) = (sum / samples.len() as f64, sum_x / xx, sum_y / yy);
+    Some(
+        samples
+            .iter()
+            .map(|&(x, y, v)| v - a - b * x - c * y)
+            .collect(),
+    )
+}
+
+/// Mean eye landmark movement across `found`, and whether it reaches
+/// `min_displacement` (always live when `None`).
+#[cfg(feature = "liveness")]
+fn eye_movement(found: &[&BoundingBox], min_displacement: Option<f32>) -> (f32, bool) {
+    let landmarks: Vec<[(f32, f32); 5]> = found.iter().filter_map(|face| face.landmarks).collect();
+    let liveness = check_landmark_stability(&landmarks, min_displacement);
+    (
+        liveness.mean_eye_displacement,
+        min_displacement.is_none() || liveness.is_live,
+    )
+}
+
+/// Built without the `liveness` feature: nothing is measured, and a check
+/// asked for anyway fails closed.
+#[cfg(not(feature = "liveness"))]
+fn eye_movement(_found: &[&BoundingBox], min_displacement: Option<f32>) -> (f32, bool) {
+    (0.0, min_displacement.is_none())
+}
+
+#[cfg(all(test, feature = "liveness"))]
+mod tests {
+    use super::*;
+    use visage_core::alignment::REFERENCE_LANDMARKS_112;
+
+    fn face(shift: f32, landmarks: [(f32, f32); 5]) -> Option<BoundingBox> {
+        Some(BoundingBox {
+            x: 0.0,
+            y: 0.0,
+            width: 112.0,
+            height: 112.0,
+            confidence: 0.9,
+            landmarks: Some(landmarks.map(|(x, y)| (x + shift, y))),
+        })
+    }
+
+    #[test]
+    fn rgb_needs_an_attentive_live_face() {
+        let frontal = REFERENCE_LANDMARKS_112;
+        let moving = [face(0.0, frontal), None, face(2.0, frontal)];
+        let check = RgbCheck::assess(3, &moving, Some(0.8));
+        assert_eq!((check.frames, check.faces, check.attentive), (3, 2, 2));
+        assert!(check.live && check.passed());
+
+        // A still image passes only with liveness off.
+        let still = [face(0.0, frontal), face(0.0, frontal)];
+        assert!(!RgbCheck::assess(2, &still, Some(0.8)).passed());
+        assert!(RgbCheck::assess(2, &still, None).passed());
+
+        // Nose far to one side: the head is turned away.
+        let mut turned = frontal;
+        turned[2].0 += 40.0;
+        assert!(!RgbCheck::assess(2, &[face(0.0, turned), face(2.0, turned)], None).passed());
+
+        assert!(!RgbCheck::assess(3, &[None, None, None], None).passed());
+    }
+
+    fn frame(pixel: impl Fn(u32, u32) -> f32) -> Frame {
+        let size = alignment::ALIGNED_SIZE as u32;
+        Frame {
+            data: (0..size * size)
+                .map(|i| pixel(i % size, i / size).clamp(0.0, 255.0) as u8)
+                .collect(),
+            width: size,
+            height: size,
+            timestamp: std::time::Instant::now(),
+            sequence: 0,
+            device_timestamp: std::time::Duration::ZERO,
+            is_dark: false,
+            exposure: None,
+            night: false,
+        }
+    }
+
+    #[test]
+    fn one_picture_in_both_spectra_is_flat() {
+        let face = face(0.0, REFERENCE_LANDMARKS_112).unwrap();
+        let picture = |x: u32, y: u32| ((x * 7 + y * 13) % 31) as f32 * 4.0 + 60.0;
+        let ir = frame(picture);
+        // The same print under room light: dimmer, lit from one side.
+        let print = frame(|x, y| picture(x, y) * 0.6 + 20.0 + x as f32 * 0.4);
+        // A face whose visible-light detail differs from its IR detail.
+        let live = frame(|x, y| ((x * 11 + y * 3) % 17) as f32 * 6.0 + 50.0);
+
+        let flat = cross_spectral_correlation((&ir, &face), (&print, &face)).unwrap();
+        assert!(flat > 0.98, "print correlation {flat}");
+        let real = cross_spectral_correlation((&ir, &face), (&live, &face)).unwrap();
+        assert!(real < 0.5, "live correlation {real}");
+
+        let attentive = RgbCheck::assess(2, &[Some(face.clone()), Some(face.clone())], None);
+        let check = attentive.compare_spectra((&ir, &face), &[(&print, &face)], 0.9);
+        assert!(check.flat && !check.passed());
+        let check = attentive.compare_spectra((&ir, &face), &[(&live, &face)], 0.9);
+        assert!(!check.flat && check.passed());
+        // Uniform crops say nothing.
+        let blank = frame(|_, _| 90.0);
+        assert_eq!(
+            cross_spectral_correlation((&ir, &face), (&blank, &face)),
+            None
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Health checks for watchdog scripts and fleet monitoring.
+//!
+//! [`check`] never touches the camera: it asks whether the engine thread is
+//! still receiving requests and not stuck in one, and whether the database
+//! answers a query. It backs the `Health` D-Bus method and, with the
+//! `healthz` feature, a loopback-only HTTP endpoint (`GET /healthz`) for
+//! monitors that do not speak D-Bus. The systemd watchdog (see `systemd`)
+//! uses the engine half, [`engine_status`].
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+use crate::dbus_interface::AppState;
+use crate::engine::EngineHandle;
+
+/// Added to the longest legitimate request when deciding the engine is
+/// stuck: inference is not interruptible, so a request can overrun its
+/// budget by one stage.
+const STALL_MARGIN_SECS: u64 = 30;
+
+/// State of the engine thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineStatus {
+    Running,
+    /// Still on one request after [`stall_limit`].
+    Stalled,
+    /// The thread exited, e.g. after a panic.
+    Stopped,
+}
+
+impl EngineStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Stalled => "stalled",
+            Self::Stopped => "stopped",
+        }
+    }
+}
+
+/// How long one engine request may run before the thread counts as stuck:
+/// the verify timeout, the wall budget or the pre-capture window, whichever
+/// is longest, plus [`STALL_MARGIN_SECS`].
+pub fn stall_limit(config: &Config) -> std::time::Duration {
+    let longest = config
+        .verify_timeout_secs
+        .max(config.max_request_wall_ms.div_ceil(1000))
+        .max(config.precapture_secs);
+    std::time::Duration::from_secs(longest + STALL_MARGIN_SECS)
+}
+
+/// Whether `engine` is receiving requests and has been on none for longer
+/// than `limit`.
+pub fn engine_status(engine: &EngineHandle, limit: std::time::Duration) -> EngineStatus {
+    if !engine.is_running() {
+        EngineStatus::Stopped
+    } else if engine.busy_for().is_some_and(|busy| busy > limit) {
+        EngineStatus::Stalled
+    } else {
+        EngineStatus::Running
+    }
+}
+
+/// Result of one health check.
+pub struct Health {
+    pub healthy: bool,
+    pub json: serde_json::Value,
+}
+
+/// Check engine-thread liveness and database access. Holds the state lock
+/// only long enough to clone the handles.
+pub async fn check(state: &Mutex<AppState>) -> Health {
+    let (engine, store) = {
+        let state = state.lock().await;
+        let limit = stall_limit(&state.config);
+        (engine_status(&state.engine, limit), state.store.clone())
+    };
+    let database = store.count_all().await;
+    let healthy = engine == EngineStatus::Running && database.is_ok();
+    Health {
+        healthy,
+        json: serde_json::json!({
+            "healthy": healthy,
+            "engine": engine.as_str(),
+            "database": match &database {
+                Ok(_) => "ok".to_string(),
+                Err(e) => e.to_string(),
+            },
+            "panics": crate::crash::panic_count(),
+        }),
+    }
+}
+
+/// Serve `GET /healthz` on `addr`: `200` with the health JSON when healthy,
+/// `503` otherwise. Any other request gets `404`.
+#[cfg(feature = "healthz")]
+pub async fn serve(addr: std::net::SocketAddr, state: Arc<Mutex<AppState>>) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "healthz endpoint listening");
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            let mut request = [0u8; 512];
+            let read =
+                tokio::time::timeout(std::time::Duration::from_secs(2), stream.read(&mut request))
+                    .await;
+            let Ok(Ok(n)) = read else {
+                return;
+            };
+            let (status, body) = if request[..n].starts_with(b"GET /healthz ") {
+                let health = check(&state).await;
+                let status = if health.healthy {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                };
+                (status, health.json.to_string())
+            } else {
+                ("404 Not Found", String::new())
+            };
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Placeholder so callers need no `cfg`: without the feature the endpoint
+/// cannot be enabled.
+#[cfg(not(feature = "healthz"))]
+pub async fn serve(
+    addr: std::net::SocketAddr,
+    _state: Arc<Mutex<AppState>>,
+) -> std::io::Result<()> {
+    tracing::warn!(%addr, "VISAGE_HEALTHZ_ADDR set but visaged was built without the `healthz` feature");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stall_limit_covers_the_longest_request() {
+        let mut config = Config::defaults();
+        config.verify_timeout_secs = 10;
+        config.max_request_wall_ms = 15_500;
+        config.precapture_secs = 0;
+        assert_eq!(
+            stall_limit(&config),
+            std::time::Duration::from_secs(16 + STALL_MARGIN_SECS)
+        );
+        config.precapture_secs = 60;
+        assert_eq!(
+            stall_limit(&config),
+            std::time::Duration::from_secs(60 + STALL_MARGIN_SECS)
+        );
+    }
+
+    #[tokio::test]
+    async fn a_request_past_the_limit_marks_the_engine_stalled() {
+        let state = Mutex::new(AppState::for_tests().await);
+        let health = check(&state).await;
+        assert!(health.healthy);
+        assert_eq!(health.json["engine"], "running");
+        assert_eq!(health.json["database"], "ok");
+
+        let engine = state.lock().await.engine.clone();
+        let limit = std::time::Duration::from_millis(1);
+        assert_eq!(engine_status(&engine, limit), EngineStatus::Running);
+        engine.set_busy(true);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(engine_status(&engine, limit), EngineStatus::Stalled);
+        engine.set_busy(false);
+        assert_eq!(engine_status(&engine, limit), EngineStatus::Running);
+    }
+}
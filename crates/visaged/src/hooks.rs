@@ -0,0 +1,368 @@
+//! Event hooks — tell other software what the daemon did.
+//!
+//! On each event the daemon can run a script and/or write to a local Unix
+//! socket, so home-automation and SIEM integrations need no daemon patches.
+//! Both receive the same single-line JSON object:
+//!
+//! ```json
+//! {"event":"verify_failure","timestamp":"2026-01-01T08:00:00+00:00","tenant":"","user":"alice","reason":"below_threshold"}
+//! ```
+//!
+//! The script gets it on stdin (plus `VISAGE_EVENT=<name>` in its environment)
+//! and runs as `VISAGE_HOOK_USER`, never as root. The socket gets it as one
+//! newline-terminated line per connection. Hooks run in the background: they
+//! never delay or change an authentication result, and events are dropped
+//! (with a warning) while too many hooks are still running.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use nix::unistd::User;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+
+use crate::config::Config;
+
+/// Most hook deliveries in flight at once.
+const MAX_IN_FLIGHT: usize = 8;
+
+/// A daemon event delivered to hooks. Never carries embeddings or images.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookEvent {
+    VerifySuccess {
+        tenant: String,
+        user: String,
+    },
+    VerifyFailure {
+        tenant: String,
+        user: String,
+        reason: &'static str,
+    },
+    IdentifySuccess {
+        tenant: String,
+        user: String,
+    },
+    IdentifyFailure {
+        tenant: String,
+        reason: &'static str,
+    },
+    /// A model was enrolled (`action` = `"enrolled"`), imported from an export
+    /// bundle (`"imported"`), changed (`"updated"`) or removed (`"removed"`).
+    EnrollmentChanged {
+        tenant: String,
+        user: String,
+        model_id: String,
+        action: &'static str,
+    },
+    CameraError {
+        error: String,
+    },
+    /// A model does not resemble the rest of its user's gallery; see
+    /// `consistency`.
+    GalleryOutlier {
+        tenant: String,
+        user: String,
+        model_id: String,
+        similarity: f32,
+    },
+    /// An unused model will be purged after `purge_after`; see `retention`.
+    ModelExpiring {
+        tenant: String,
+        user: String,
+        model_id: String,
+        purge_after: String,
+    },
+    /// Narrow matches made the user's next verify a challenge; see
+    /// `challenge`.
+    ChallengeRequired {
+        tenant: String,
+        user: String,
+        narrow_matches: u32,
+    },
+}
+
+impl HookEvent {
+    /// Stable event name — the `event` field and `VISAGE_EVENT`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VerifySuccess { .. } => "verify_success",
+            Self::VerifyFailure { .. } => "verify_failure",
+            Self::IdentifySuccess { .. } => "identify_success",
+            Self::IdentifyFailure { .. } => "identify_failure",
+            Self::EnrollmentChanged { .. } => "enrollment_changed",
+            Self::CameraError { .. } => "camera_error",
+            Self::GalleryOutlier { .. } => "gallery_outlier",
+            Self::ModelExpiring { .. } => "model_expiring",
+            Self::ChallengeRequired { .. } => "challenge_required",
+        }
+    }
+
+    fn to_json(&self, timestamp: &str) -> serde_json::Value {
+        let mut body = match self {
+            Self::VerifySuccess { tenant, user } | Self::IdentifySuccess { tenant, user } => {
+                serde_json::json!({ "tenant": tenant, "user": user })
+            }
+            Self::VerifyFailure {
+                tenant,
+                user,
+                reason,
+            } => serde_json::json!({ "tenant": tenant, "user": user, "reason": reason }),
+            Self::IdentifyFailure { tenant, reason } => {
+                serde_json::json!({ "tenant": tenant, "reason": reason })
+            }
+            Self::EnrollmentChanged {
+                tenant,
+                user,
+                model_id,
+                action,
+            } => serde_json::json!({
+                "tenant": tenant,
+                "user": user,
+                "model_id": model_id,
+                "action": action,
+            }),
+            Self::CameraError { error } => serde_json::json!({ "error": error }),
+            Self::GalleryOutlier {
+                tenant,
+                user,
+                model_id,
+                similarity,
+            } => serde_json::json!({
+                "tenant": tenant,
+                "user": user,
+                "model_id": model_id,
+                "similarity": similarity,
+            }),
+            Self::ModelExpiring {
+                tenant,
+                user,
+                model_id,
+                purge_after,
+            } => serde_json::json!({
+                "tenant": tenant,
+                "user": user,
+                "model_id": model_id,
+                "purge_after": purge_after,
+            }),
+            Self::ChallengeRequired {
+                tenant,
+                user,
+                narrow_matches,
+            } => serde_json::json!({
+                "tenant": tenant,
+                "user": user,
+                "narrow_matches": narrow_matches,
+            }),
+        };
+        body["event"] = self.name().into();
+        body["timestamp"] = timestamp.into();
+        body
+    }
+}
+
+/// Clone-safe hook dispatcher. Does nothing when no hook is configured.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    /// Script and the account it runs as.
+    command: Option<(PathBuf, User)>,
+    socket: Option<PathBuf>,
+    /// Event names to deliver; empty means all.
+    events: Vec<String>,
+    timeout: Duration,
+    permits: Semaphore,
+}
+
+impl Hooks {
+    /// Build the dispatcher from `VISAGE_HOOK_*` settings. A command whose
+    /// user is unknown or root is disabled with an error rather than run.
+    pub fn from_config(config: &Config) -> Self {
+        let command = config
+            .hook_command
+            .clone()
+            .and_then(|path| Some((path, hook_account(&config.hook_user)?)));
+        if command.is_none() && config.hook_socket.is_none() {
+            return Self::default();
+        }
+        Self {
+            inner: Some(Arc::new(Inner {
+                command,
+                socket: config.hook_socket.clone(),
+                events: config.hook_events.clone(),
+                timeout: Duration::from_secs(config.hook_timeout_secs),
+                permits: Semaphore::new(MAX_IN_FLIGHT),
+            })),
+        }
+    }
+
+    /// Deliver `event` in the background.
+    pub fn emit(&self, event: HookEvent) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        if !inner.events.is_empty() && !inner.events.iter().any(|e| e == event.name()) {
+            return;
+        }
+        let inner = Arc::clone(inner);
+        tokio::spawn(async move {
+            let Ok(_permit) = inner.permits.try_acquire() else {
+                tracing::warn!(
+                    event = event.name(),
+                    "hooks: too many deliveries in flight; dropping event"
+                );
+                return;
+            };
+            let mut line = event.to_json(&chrono::Utc::now().to_rfc3339()).to_string();
+            line.push('\n');
+            let deliveries = async {
+                tokio::join!(
+                    inner.run_command(event.name(), &line),
+                    inner.post_socket(&line)
+                )
+            };
+            if tokio::time::timeout(inner.timeout, deliveries)
+                .await
+                .is_err()
+            {
+                tracing::warn!(event = event.name(), "hooks: delivery timed out");
+            }
+        });
+    }
+}
+
+/// Resolve the account hook scripts run as, refusing root.
+fn hook_account(name: &str) -> Option<User> {
+    match User::from_name(name) {
+        Ok(Some(user)) if !user.uid.is_root() => Some(user),
+        Ok(Some(_)) => {
+            tracing::error!("VISAGE_HOOK_USER must not be root; hook command disabled");
+            None
+        }
+        _ => {
+            tracing::error!(
+                user = name,
+                "VISAGE_HOOK_USER not found; hook command disabled"
+            );
+            None
+        }
+    }
+}
+
+impl Inner {
+    async fn run_command(&self, event: &str, line: &str) {
+        let Some((path, user)) = &self.command else {
+            return;
+        };
+        let child = tokio::process::Command::new(path)
+            .env_clear()
+            .env("PATH", "/usr/local/bin:/usr/bin:/bin")
+            .env("VISAGE_EVENT", event)
+            .uid(user.uid.as_raw())
+            .gid(user.gid.as_raw())
+            .current_dir("/")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                tracing::warn!(error = %e, path = %path.display(), "hooks: failed to start command");
+                return;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(line.as_bytes()).await;
+        }
+        match child.wait().await {
+            Ok(status) if !status.success() => {
+                tracing::warn!(%status, path = %path.display(), "hooks: command failed");
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "hooks: command wait failed"),
+        }
+    }
+
+    async fn post_socket(&self, line: &str) {
+        let Some(path) = &self.socket else {
+            return;
+        };
+        let result = async {
+            let mut stream = tokio::net::UnixStream::connect(path).await?;
+            stream.write_all(line.as_bytes()).await?;
+            stream.shutdown().await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!(error = %e, socket = %path.display(), "hooks: socket delivery failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_json_carries_name_and_fields() {
+        let event = HookEvent::VerifyFailure {
+            tenant: String::new(),
+            user: "alice".into(),
+            reason: "below_threshold",
+        };
+        let json = event.to_json("2026-01-01T00:00:00+00:00");
+        assert_eq!(json["event"], "verify_failure");
+        assert_eq!(json["user"], "alice");
+        assert_eq!(json["reason"], "below_threshold");
+        assert_eq!(json["timestamp"], "2026-01-01T00:00:00+00:00");
+
+        let event = HookEvent::CameraError {
+            error: "device busy".into(),
+        };
+        assert_eq!(event.to_json("t")["event"], "camera_error");
+    }
+
+    #[tokio::test]
+    async fn socket_receives_one_line_per_event() {
+        let dir = std::env::temp_dir().join(format!("visage-hooks-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("events.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let hooks = Hooks {
+            inner: Some(Arc::new(Inner {
+                command: None,
+                socket: Some(socket),
+                events: vec!["enrollment_changed".into()],
+                timeout: Duration::from_secs(5),
+                permits: Semaphore::new(MAX_IN_FLIGHT),
+            })),
+        };
+        // Filtered out: only enrollment changes are subscribed.
+        hooks.emit(HookEvent::CameraError {
+            error: "gone".into(),
+        });
+        hooks.emit(HookEvent::EnrollmentChanged {
+            tenant: String::new(),
+            user: "alice".into(),
+            model_id: "m1".into(),
+            action: "enrolled",
+        });
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut received)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let json: serde_json::Value = serde_json::from_str(received.trim_end()).unwrap();
+        assert_eq!(json["event"], "enrollment_changed");
+        assert_eq!(json["model_id"], "m1");
+    }
+}
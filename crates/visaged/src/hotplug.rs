@@ -0,0 +1,297 @@
+//! Camera hot-plug — follow the configured camera as it leaves and returns.
+//!
+//! A USB camera that goes away (dock unplugged, hub reset, a suspend that
+//! drops the bus) leaves the engine holding the fd of a device that no longer
+//! exists. The daemon listens to the kernel's device events (a
+//! `NETLINK_KOBJECT_UEVENT` socket, the same feed udev reads) and, for the
+//! configured camera:
+//!
+//! - on `remove`, marks the camera absent, so a verify fails at once with a
+//!   camera error instead of capturing from a dead fd;
+//! - on `add`, re-opens it (and re-probes the IR emitter) before the next
+//!   request, waiting a few seconds for the driver to finish probing.
+//!
+//! Events come from the kernel, before udev has created the `/dev/v4l/by-*`
+//! symlinks. The camera in use is resolved to its node at startup and after
+//! each re-attach, and its `remove` is matched by node name. While it is
+//! absent, an `add` of a node named in the camera list (`/dev/video2`)
+//! triggers a re-open; with a symlink or a pattern in the list any
+//! `video4linux` device does, and the re-open succeeds only once udev has
+//! linked a configured camera again. The re-open goes through the whole list
+//! (see `camera_list`), so it may pick up a different camera than before.
+//!
+//! Without the netlink socket (some containers) the daemon still re-opens an
+//! absent camera when the next request arrives.
+
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use nix::sys::socket::{self, AddressFamily, NetlinkAddr, SockFlag, SockProtocol, SockType};
+use tokio::io::unix::AsyncFd;
+
+use crate::engine::EngineHandle;
+
+/// Netlink multicast group of the kernel's own uevents (udev's are group 2).
+const KERNEL_GROUP: u32 = 1;
+
+/// Largest uevent the kernel sends (`UEVENT_BUFFER_SIZE`).
+const MAX_UEVENT_LEN: usize = 2048;
+
+/// What happened to a device, from one uevent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Uevent<'a> {
+    pub action: &'a str,
+    pub subsystem: &'a str,
+    /// Node name below `/dev` (`video2`), if the device has one.
+    pub devname: Option<&'a str>,
+}
+
+/// Parse a kernel uevent: an `ACTION@DEVPATH` header, then NUL-separated
+/// `KEY=value` pairs. `None` for anything else (udev's own messages).
+pub fn parse(msg: &[u8]) -> Option<Uevent<'_>> {
+    let mut fields = msg.split(|&b| b == 0).filter(|f| !f.is_empty());
+    let header = std::str::from_utf8(fields.next()?).ok()?;
+    if !header.contains('@') {
+        return None;
+    }
+    let (mut action, mut subsystem, mut devname) = (None, None, None);
+    for field in fields {
+        let Ok(field) = std::str::from_utf8(field) else {
+            continue;
+        };
+        match field.split_once('=') {
+            Some(("ACTION", value)) => action = Some(value),
+            Some(("SUBSYSTEM", value)) => subsystem = Some(value),
+            Some(("DEVNAME", value)) => devname = Some(value),
+            _ => {}
+        }
+    }
+    Some(Uevent {
+        action: action?,
+        subsystem: subsystem?,
+        devname,
+    })
+}
+
+/// A change to the configured camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Removed,
+    Attached,
+}
+
+/// The camera in use and the configured list, as hot-plug events name them.
+#[derive(Debug, Clone)]
+pub struct Watched {
+    /// Nodes directly below `/dev` named in the camera list.
+    nodes: Vec<PathBuf>,
+    /// Whether the list has a udev symlink or a pattern, which no kernel
+    /// event names.
+    indirect: bool,
+    /// The `/dev/videoN` node the camera in use resolved to when last seen.
+    node: PathBuf,
+    present: bool,
+}
+
+impl Watched {
+    /// `configured` is the camera list, `in_use` the device the engine opened.
+    pub fn new(configured: &[String], in_use: &Path) -> Self {
+        let (nodes, others): (Vec<PathBuf>, Vec<PathBuf>) =
+            configured.iter().map(PathBuf::from).partition(|path| {
+                path.parent() == Some(Path::new("/dev"))
+                    && !crate::camera_list::is_pattern(&path.to_string_lossy())
+            });
+        Self {
+            nodes,
+            indirect: !others.is_empty(),
+            node: resolve(in_use),
+            present: true,
+        }
+    }
+
+    /// The change `event` makes to the camera, if any.
+    pub fn change(&mut self, event: &Uevent<'_>) -> Option<Change> {
+        if event.subsystem != "video4linux" {
+            return None;
+        }
+        let node = Path::new("/dev").join(event.devname?);
+        let configured = node == self.node || self.indirect || self.nodes.contains(&node);
+        match event.action {
+            "remove" if self.present && node == self.node => {
+                self.present = false;
+                Some(Change::Removed)
+            }
+            "add" if !self.present && configured => Some(Change::Attached),
+            _ => None,
+        }
+    }
+
+    /// Record that the engine has a camera open again, now `in_use`.
+    pub fn reattached(&mut self, in_use: &Path) {
+        self.present = true;
+        self.node = resolve(in_use);
+    }
+}
+
+/// The node a device path (possibly a udev symlink) points to now.
+fn resolve(device: &Path) -> PathBuf {
+    std::fs::canonicalize(device).unwrap_or_else(|_| device.to_path_buf())
+}
+
+/// Follow the camera of the `devices` list that `engine` uses until the
+/// daemon exits, telling the engine when it leaves and returns. Fails only if
+/// the uevent socket cannot be opened.
+pub async fn watch(devices: &[String], engine: EngineHandle) -> io::Result<()> {
+    let flags = SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC;
+    let fd = socket::socket(
+        AddressFamily::Netlink,
+        SockType::Datagram,
+        flags,
+        SockProtocol::NetlinkKObjectUEvent,
+    )?;
+    socket::bind(fd.as_raw_fd(), &NetlinkAddr::new(0, KERNEL_GROUP))?;
+    let socket = AsyncFd::new(fd)?;
+    let in_use = engine.camera_device();
+    let mut watched = Watched::new(devices, Path::new(&in_use));
+    tracing::info!(device = %in_use, node = %watched.node.display(), "watching the camera for hot-plug");
+
+    let mut buf = vec![0u8; MAX_UEVENT_LEN];
+    loop {
+        let mut ready = socket.readable().await?;
+        let received = match ready
+            .try_io(|fd| Ok(socket::recvfrom::<NetlinkAddr>(fd.as_raw_fd(), &mut buf)?))
+        {
+            Ok(received) => received,
+            Err(_would_block) => continue,
+        };
+        let (len, from) = match received {
+            Ok(received) => received,
+            // ENOBUFS: events were dropped under load; the next ones still count.
+            Err(e) => {
+                tracing::debug!(error = %e, "uevent receive failed");
+                continue;
+            }
+        };
+        // Only the kernel (port 0) reports devices; anyone else is ignored.
+        if !matches!(from, Some(addr) if addr.pid() == 0) {
+            continue;
+        }
+        let Some(change) = parse(&buf[..len]).and_then(|event| watched.change(&event)) else {
+            continue;
+        };
+        match change {
+            Change::Removed => {
+                tracing::warn!(node = %watched.node.display(), "camera removed");
+                if engine.camera_removed().await.is_err() {
+                    return Ok(());
+                }
+            }
+            Change::Attached => match engine.camera_attached().await {
+                Ok(()) => {
+                    let in_use = engine.camera_device();
+                    watched.reattached(Path::new(&in_use));
+                    tracing::info!(device = %in_use, "camera re-attached and re-opened");
+                }
+                Err(e) => tracing::warn!(error = %e, "camera re-attached but could not be opened"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uevent(action: &str, subsystem: &str, devname: &str) -> Vec<u8> {
+        format!(
+            "{action}@/devices/pci0000:00/usb1/1-2/video4linux/{devname}\0ACTION={action}\0\
+             DEVPATH=/devices/pci0000:00/usb1/1-2/video4linux/{devname}\0\
+             SUBSYSTEM={subsystem}\0DEVNAME={devname}\0SEQNUM=4242\0"
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn kernel_uevents_parse() {
+        let msg = uevent("remove", "video4linux", "video2");
+        assert_eq!(
+            parse(&msg),
+            Some(Uevent {
+                action: "remove",
+                subsystem: "video4linux",
+                devname: Some("video2"),
+            })
+        );
+        assert_eq!(parse(b"libudev\0\xfe\xed\xca\xfe"), None);
+        assert_eq!(parse(b"add@/devices/x\0ACTION=add\0"), None);
+    }
+
+    #[test]
+    fn only_the_configured_node_is_followed() {
+        let device = "/dev/video-test-hotplug";
+        let mut watched = Watched::new(&[device.to_string()], Path::new(device));
+        let remove = |name| uevent("remove", "video4linux", name);
+        let add = |name| uevent("add", "video4linux", name);
+        let change = |w: &mut Watched, msg: Vec<u8>| w.change(&parse(&msg).unwrap());
+
+        assert_eq!(change(&mut watched, remove("video3")), None);
+        assert_eq!(change(&mut watched, add("video-test-hotplug")), None);
+        assert_eq!(
+            change(&mut watched, uevent("remove", "usb", "video-test-hotplug")),
+            None
+        );
+        assert_eq!(
+            change(&mut watched, remove("video-test-hotplug")),
+            Some(Change::Removed)
+        );
+        assert_eq!(change(&mut watched, remove("video-test-hotplug")), None);
+        assert_eq!(change(&mut watched, add("video3")), None);
+        assert_eq!(
+            change(&mut watched, add("video-test-hotplug")),
+            Some(Change::Attached)
+        );
+        watched.reattached(Path::new(device));
+        assert_eq!(change(&mut watched, add("video-test-hotplug")), None);
+    }
+
+    #[test]
+    fn any_camera_of_the_list_brings_it_back() {
+        let configured = [
+            "/dev/video-test-a".to_string(),
+            "/dev/video-test-b".to_string(),
+        ];
+        let mut watched = Watched::new(&configured, Path::new("/dev/video-test-a"));
+        let event = |action, name| uevent(action, "video4linux", name);
+        let change = |w: &mut Watched, msg: Vec<u8>| w.change(&parse(&msg).unwrap());
+
+        assert_eq!(
+            change(&mut watched, event("remove", "video-test-b")),
+            None,
+            "only the camera in use is followed out"
+        );
+        assert_eq!(
+            change(&mut watched, event("remove", "video-test-a")),
+            Some(Change::Removed)
+        );
+        assert_eq!(change(&mut watched, event("add", "video-test-c")), None);
+        assert_eq!(
+            change(&mut watched, event("add", "video-test-b")),
+            Some(Change::Attached)
+        );
+        watched.reattached(Path::new("/dev/video-test-b"));
+        assert_eq!(
+            change(&mut watched, event("remove", "video-test-b")),
+            Some(Change::Removed)
+        );
+
+        let by_id = ["/dev/v4l/by-id/usb-Test*-video-index0".to_string()];
+        let mut watched = Watched::new(&by_id, Path::new("/dev/video-test-a"));
+        change(&mut watched, event("remove", "video-test-a"));
+        assert_eq!(
+            change(&mut watched, event("add", "video-test-c")),
+            Some(Change::Attached),
+            "a pattern may name any new node"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Exit when idle, for a daemon started on demand.
+//!
+//! The bus policy ships a D-Bus activation file, so the broker starts
+//! `visaged.service` when a client (pam_visage, the CLI) calls
+//! `org.freedesktop.Visage1` and nothing owns it. With `VISAGE_IDLE_EXIT_SECS`
+//! the daemon then exits cleanly after that many seconds with nothing to do,
+//! handing back the camera and the ~300 MB of ONNX sessions until the next
+//! login starts it again. The first verify after an exit pays the model load.
+//!
+//! "Nothing to do" means every piece of state that lives only in this process
+//! can be lost without a caller noticing:
+//!
+//! - the engine thread has waited for a request (including a pre-capture
+//!   window, which counts as work) for the whole period;
+//! - no verify is in flight and no step-wise enrollment is open;
+//! - no keyring release ticket is waiting for pam_visage to redeem it;
+//! - the rate limiter holds no failures or lockouts — exiting would hand an
+//!   attacker a fresh set of attempts;
+//! - no re-verification challenge is pending (see `challenge`), for the same
+//!   reason.
+//!
+//! Warm sessions and the identification index are caches; they are rebuilt
+//! on demand and do not keep the daemon alive.
+//!
+//! Only the bus can start the daemon again, so the idle exit is off when the
+//! verify socket, the vsock or TCP servers or the health or metrics
+//! endpoints are configured: their clients would find nothing listening.
+
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::dbus_interface::AppState;
+
+/// How often the daemon checks whether it is idle.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The listener that keeps the daemon running despite `idle_exit_secs`, if
+/// any.
+pub fn blocker(config: &Config) -> Option<&'static str> {
+    if config.socket_path.is_some() {
+        Some("VISAGE_SOCKET_PATH")
+    } else if config.vsock_port.is_some() {
+        Some("VISAGE_VSOCK_PORT")
+    } else if config.tcp_addr.is_some() {
+        Some("VISAGE_TCP_ADDR")
+    } else if config.healthz_addr.is_some() {
+        Some("VISAGE_HEALTHZ_ADDR")
+    } else if config.metrics_addr.is_some() {
+        Some("VISAGE_METRICS_ADDR")
+    } else {
+        None
+    }
+}
+
+/// The idle period after which the daemon exits; `None` when it stays.
+pub fn exit_after(config: &Config) -> Option<Duration> {
+    (config.idle_exit_secs > 0 && blocker(config).is_none())
+        .then(|| Duration::from_secs(config.idle_exit_secs))
+}
+
+/// Whether the daemon has had nothing to do for `after` and holds nothing a
+/// restart would lose.
+pub fn is_idle(state: &mut AppState, after: Duration, now: Instant) -> bool {
+    state.engine.idle_for().is_some_and(|idle| idle >= after)
+        && state.cancels.is_empty()
+        && !state.enrollments.any_open(now)
+        && !state.authtok_tickets.pending()
+        && state.rate_limiter.is_clear()
+        && !state.challenges.any_pending()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exits_only_when_only_the_bus_can_start_the_daemon() {
+        let mut config = Config::defaults();
+        assert_eq!(exit_after(&config), None, "off by default");
+        config.idle_exit_secs = 300;
+        assert_eq!(exit_after(&config), Some(Duration::from_secs(300)));
+
+        config.metrics_addr = Some("127.0.0.1:9464".parse().unwrap());
+        assert_eq!(blocker(&config), Some("VISAGE_METRICS_ADDR"));
+        config.socket_path = Some("/run/visage/verify.sock".into());
+        assert_eq!(blocker(&config), Some("VISAGE_SOCKET_PATH"));
+        assert_eq!(exit_after(&config), None);
+    }
+
+    #[tokio::test]
+    async fn idle_needs_a_quiet_engine_and_nothing_to_lose() {
+        let mut state = AppState::for_tests().await;
+        let now = Instant::now();
+        assert!(is_idle(&mut state, Duration::ZERO, now));
+        assert!(
+            !is_idle(&mut state, Duration::from_secs(3600), now),
+            "not idle for the whole period yet"
+        );
+
+        state.engine.set_busy(true);
+        assert!(!is_idle(&mut state, Duration::ZERO, now), "on a request");
+        state.engine.set_busy(false);
+        assert!(is_idle(&mut state, Duration::ZERO, now));
+
+        state.authtok_tickets.issue("alice");
+        assert!(
+            !is_idle(&mut state, Duration::ZERO, now),
+            "a keyring ticket waits to be redeemed"
+        );
+        state.authtok_tickets = Default::default();
+
+        state.rate_limiter.record_failure("alice");
+        assert!(
+            !is_idle(&mut state, Duration::ZERO, now),
+            "exiting would forget the failure"
+        );
+    }
+}
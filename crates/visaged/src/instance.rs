@@ -0,0 +1,86 @@
+//! Per-camera instances — one daemon per sensor on one machine.
+//!
+//! A workstation with two cameras, or a door controller with one per door,
+//! runs `visaged@NAME.service` once per camera. The template unit sets
+//! `VISAGE_INSTANCE=NAME`, which:
+//!
+//! - adds `/etc/visage/instances/NAME.toml` to the configuration, after
+//!   `config.d` and before the environment (see `config_file`);
+//! - makes `/dev/NAME` the default camera, so `visaged@video2` needs no
+//!   profile at all;
+//! - moves the service to bus name `org.freedesktop.Visage1.NAME` and object
+//!   path `/org/freedesktop/Visage1/NAME`, so instances never answer for one
+//!   another. The interface is the same.
+//!
+//! Each instance has its own engine, camera, rate limiter and listeners. The
+//! database, the sockets and the listener addresses come from the
+//! configuration like any other setting; a profile that shares them with
+//! another instance shares them for real, so set them per profile.
+//!
+//! `NAME` becomes a bus name element and an object path element, so it is
+//! limited to ASCII letters, digits and `_`, at most 32 of them, and must
+//! not start with a digit.
+
+use std::path::PathBuf;
+
+/// Bus name of the default instance, and prefix of the others.
+pub const BUS_NAME: &str = "org.freedesktop.Visage1";
+/// Object path of the default instance, and parent of the others.
+pub const OBJECT_PATH: &str = "/org/freedesktop/Visage1";
+/// Directory of the per-instance configuration profiles.
+pub const PROFILE_DIR: &str = "/etc/visage/instances";
+
+/// Whether `name` can name an instance.
+pub fn is_valid(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// The configuration profile of instance `name`.
+pub fn profile_path(name: &str) -> PathBuf {
+    PathBuf::from(PROFILE_DIR).join(format!("{name}.toml"))
+}
+
+/// The bus name served by `instance` (`None`: the default instance).
+pub fn bus_name(instance: Option<&str>) -> String {
+    match instance {
+        Some(name) => format!("{BUS_NAME}.{name}"),
+        None => BUS_NAME.to_string(),
+    }
+}
+
+/// The object path served by `instance` (`None`: the default instance).
+pub fn object_path(instance: Option<&str>) -> String {
+    match instance {
+        Some(name) => format!("{OBJECT_PATH}/{name}"),
+        None => OBJECT_PATH.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_fit_bus_names_and_paths() {
+        assert!(is_valid("video2"));
+        assert!(is_valid("front_door"));
+        assert!(!is_valid(""));
+        assert!(!is_valid("2cam"));
+        assert!(!is_valid("front-door"));
+        assert!(!is_valid("a.b"));
+        assert!(!is_valid(&"x".repeat(33)));
+
+        assert_eq!(bus_name(None), "org.freedesktop.Visage1");
+        assert_eq!(bus_name(Some("video2")), "org.freedesktop.Visage1.video2");
+        assert_eq!(
+            object_path(Some("video2")),
+            "/org/freedesktop/Visage1/video2"
+        );
+        assert_eq!(
+            profile_path("video2"),
+            PathBuf::from("/etc/visage/instances/video2.toml")
+        );
+    }
+}
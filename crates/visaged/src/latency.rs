@@ -0,0 +1,131 @@
+//! Verify latency target — size each verify burst to this machine.
+//!
+//! `VISAGE_FRAMES_PER_VERIFY` is a fixed trade-off: too slow on a weak CPU,
+//! needlessly few frames on a fast one. With `VISAGE_VERIFY_LATENCY_MS` set,
+//! the engine measures what a verify costs here — the camera's frame interval
+//! and the detect + embed time per frame — and captures as many frames as are
+//! expected to fit in the target, between [`MIN_FRAMES`] and [`MAX_FRAMES`]:
+//!
+//! ```text
+//! latency ≈ (warmup + n) · frame_interval + n · processing_per_frame
+//! ```
+//!
+//! The first verify uses `frames_per_verify`. Measurements are smoothed, so
+//! one slow frame does not swing the count.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Fewest frames in an adaptive burst: liveness and the replay check compare
+/// consecutive frames.
+const MIN_FRAMES: usize = 2;
+/// Most frames in an adaptive burst; more add little to accuracy.
+const MAX_FRAMES: usize = 10;
+/// Weight of the newest measurement in the running averages.
+const SMOOTHING: f64 = 0.3;
+
+/// Frames the last adaptive verify captured; 0 when not adapting.
+static CURRENT_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// Frames the last adaptive verify captured, for `Status`; `None` when no
+/// latency target is set or no verify has run yet.
+pub fn current_frames() -> Option<usize> {
+    Some(CURRENT_FRAMES.load(Ordering::Relaxed)).filter(|&n| n > 0)
+}
+
+/// Picks the verify burst size from measured costs. Owned by the engine thread.
+pub struct FrameSizer {
+    target: Duration,
+    warmup_frames: usize,
+    /// Smoothed time between frames from the camera, in milliseconds.
+    interval_ms: Option<f64>,
+    /// Smoothed detect + embed time per frame, in milliseconds.
+    process_ms: Option<f64>,
+}
+
+impl FrameSizer {
+    pub fn new(target: Duration, warmup_frames: usize) -> Self {
+        Self {
+            target,
+            warmup_frames,
+            interval_ms: None,
+            process_ms: None,
+        }
+    }
+
+    /// Frames for the next verify: `configured` (clamped) until both costs
+    /// have been measured.
+    pub fn frames(&self, configured: usize) -> usize {
+        let frames = match (self.interval_ms, self.process_ms) {
+            (Some(interval), Some(process)) => {
+                let target = self.target.as_secs_f64() * 1000.0;
+                let available = target - self.warmup_frames as f64 * interval;
+                (available / (interval + process).max(f64::EPSILON)).floor() as usize
+            }
+            _ => configured,
+        };
+        let frames = frames.clamp(MIN_FRAMES, MAX_FRAMES);
+        CURRENT_FRAMES.store(frames, Ordering::Relaxed);
+        frames
+    }
+
+    /// A capture that dequeued `dequeued` frames after the warmup took `elapsed`.
+    pub fn record_capture(&mut self, elapsed: Duration, dequeued: usize) {
+        let frames = dequeued + self.warmup_frames;
+        if frames > 0 {
+            let sample = elapsed.as_secs_f64() * 1000.0 / frames as f64;
+            self.interval_ms = Some(smooth(self.interval_ms, sample));
+        }
+    }
+
+    /// Detecting and embedding `frames` frames took `elapsed`.
+    pub fn record_processing(&mut self, elapsed: Duration, frames: usize) {
+        if frames > 0 {
+            let sample = elapsed.as_secs_f64() * 1000.0 / frames as f64;
+            self.process_ms = Some(smooth(self.process_ms, sample));
+        }
+    }
+}
+
+fn smooth(previous: Option<f64>, sample: f64) -> f64 {
+    match previous {
+        Some(previous) => previous + SMOOTHING * (sample - previous),
+        None => sample,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_fits_the_target() {
+        let mut sizer = FrameSizer::new(Duration::from_millis(1500), 4);
+        assert_eq!(sizer.frames(3), 3, "configured count until measured");
+        assert_eq!(sizer.frames(50), MAX_FRAMES);
+
+        // 33 ms per frame from a 30 fps camera, 100 ms of inference per frame:
+        // (1500 - 4·33) / 133 ≈ 10.3 frames.
+        sizer.record_capture(Duration::from_millis(33 * 7), 3);
+        sizer.record_processing(Duration::from_millis(300), 3);
+        assert_eq!(sizer.frames(3), 10);
+
+        // A CPU ten times slower fits only the minimum.
+        let mut slow = FrameSizer::new(Duration::from_millis(1500), 4);
+        slow.record_capture(Duration::from_millis(33 * 7), 3);
+        slow.record_processing(Duration::from_millis(3000), 3);
+        assert_eq!(slow.frames(3), MIN_FRAMES);
+    }
+
+    #[test]
+    fn measurements_are_smoothed() {
+        let mut sizer = FrameSizer::new(Duration::from_millis(1000), 0);
+        sizer.record_capture(Duration::from_millis(100), 1);
+        sizer.record_processing(Duration::from_millis(100), 1);
+        assert_eq!(sizer.frames(3), 5);
+        // One outlier moves the estimate by SMOOTHING of the difference.
+        sizer.record_processing(Duration::from_millis(1100), 1);
+        assert!((sizer.process_ms.unwrap() - 400.0).abs() < 1e-9);
+        assert_eq!(sizer.frames(3), 2);
+    }
+}
@@ -1,32 +1,95 @@
+// The `Status` JSON object outgrew serde_json's default macro recursion depth.
+#![recursion_limit = "256"]
+
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use anyhow::{Context, Result};
 use tracing_subscriber::EnvFilter;
 
+mod audit;
+mod authtok;
+mod budget;
+mod bundle;
+mod camera_list;
+mod challenge;
 mod config;
+mod config_file;
+mod consent;
+mod consistency;
+mod crash;
 mod dbus_interface;
+mod depth;
 mod engine;
+mod enrollment;
+mod failure;
+mod fusion;
+mod health;
+mod hooks;
+mod hotplug;
+mod idle;
+mod instance;
+mod latency;
+mod metrics;
+mod migrations;
+mod night;
+mod polkit;
+mod properties;
+mod quirk_reload;
 mod rate_limiter;
+mod remote;
+mod retention;
+mod scheduling;
+mod scores;
+mod simulate;
+mod snapshots;
+mod socket;
+mod stats;
 mod store;
+mod systemd;
+mod warm;
 
 use config::Config;
 use dbus_interface::{AppState, VisageService};
 use engine::spawn_engine;
+use hooks::Hooks;
 use rate_limiter::RateLimiter;
 use store::FaceModelStore;
 
+/// Delay between attempts to reach a D-Bus broker that was down at startup.
+const BUS_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("simulate") {
+        // The decision trace is the debug log, printed with the outcome.
+        tracing_subscriber::fmt()
+            .with_env_filter(
+                EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| EnvFilter::new("visaged=debug")),
+            )
+            .with_writer(std::io::stdout)
+            .without_time()
+            .init();
+        return simulate::run(&args[1..]).await;
+    }
+
+    let log_tail = crash::LogTail::default();
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(log_tail.writer())
         .init();
 
     tracing::info!("visaged starting");
 
     // 1. Load configuration
-    let config = Config::from_env();
+    let config = Config::load()
+        .map_err(anyhow::Error::msg)
+        .context("loading configuration")?;
     tracing::info!(
+        instance = config.instance.as_deref().unwrap_or("default"),
         camera = %config.camera_device,
         model_dir = %config.model_dir.display(),
         db_path = %config.db_path.display(),
@@ -34,6 +97,7 @@ async fn main() -> Result<()> {
         session_bus = config.session_bus,
         "configuration loaded"
     );
+    crash::install(&config, log_tail);
 
     visage_models::verify_models_dir(&config.model_dir)
         .map_err(anyhow::Error::from)
@@ -44,16 +108,45 @@ async fn main() -> Result<()> {
             )
         })?;
 
+    // Local quirk files, before the engine looks up the IR emitter.
+    quirk_reload::load(&config.quirks_dirs);
+
     // 2. Spawn engine (opens camera, loads models — fail-fast)
     let engine = spawn_engine(
-        &config.camera_device,
+        &config.camera_devices(),
         &config.scrfd_model_path(),
         &config.arcface_model_path(),
         config.warmup_frames,
         config.emitter_enabled,
+        config.quality_gate(),
+        config.allow_virtual_camera,
+        config.request_budget(),
+        config.inference_options(),
+        config.verify_latency(),
+        config.precapture_window(),
+        config
+            .night_schedule()
+            .map(|night| night.with_light_sensor(config.als_dark_lux)),
+        config.rgb_camera_device.as_deref(),
+        config.rgb_max_correlation,
+        config.depth_camera_device.as_deref(),
+        config.depth_min_relief_mm,
+        config.pad_model_path().as_deref(),
+        config.pad_threshold,
+        &config.calibration_dir,
+        config.engine_scheduling(),
     )?;
     tracing::info!("engine started");
 
+    // Follow the camera through unplug and re-attach; see `hotplug`.
+    let hotplug_engine = engine.clone();
+    let hotplug_devices = config.camera_devices();
+    tokio::spawn(async move {
+        if let Err(e) = hotplug::watch(&hotplug_devices, hotplug_engine).await {
+            tracing::warn!(error = %e, "camera hot-plug events unavailable; re-opening on demand only");
+        }
+    });
+
     // 3. Open face model store (creates DB if needed)
     let store = FaceModelStore::open(&config.db_path).await?;
     let model_count = store.count_all().await.unwrap_or(0);
@@ -62,32 +155,236 @@ async fn main() -> Result<()> {
     // 4. Register D-Bus service on system bus (or session bus in development mode).
     //    Set VISAGE_SESSION_BUS=1 to use the session bus without elevated privileges.
     let session_bus = config.session_bus;
+    let service_name = instance::bus_name(config.instance.as_deref());
+    let object_path = instance::object_path(config.instance.as_deref());
+    let healthz_addr = config.healthz_addr;
+    let metrics_addr = config.metrics_addr;
+    let metrics_textfile = config.metrics_textfile.clone();
+    let idle_exit = idle::exit_after(&config);
+    if config.idle_exit_secs > 0 {
+        match idle::blocker(&config) {
+            Some(setting) => tracing::warn!(
+                setting,
+                "VISAGE_IDLE_EXIT_SECS ignored: only D-Bus can start the daemon again"
+            ),
+            None => tracing::info!(secs = config.idle_exit_secs, "exiting when idle"),
+        }
+    }
+    let socket_path = config.socket_path.clone();
+    let vsock_port = config.vsock_port;
+    let tcp_addr = config.tcp_addr;
+    let tcp_token_file = config.tcp_token_file.clone();
+    let remote_policy = Arc::new(config.remote_users.clone());
+    // Failure snapshots expire in the same pass, also once turned off.
+    let retention_enabled = config.retention_policy().is_enabled()
+        || config.snapshot_policy().is_enabled()
+        || config.failure_dir.exists();
+    let quirks_watch = config.quirks_watch.then(|| config.quirks_dirs.clone());
+    let hooks = Hooks::from_config(&config);
+    let audit = audit::AuditLog::from_config(&config);
     let state = Arc::new(Mutex::new(AppState {
         config,
         engine,
         store,
         rate_limiter: RateLimiter::new(),
+        identify_index: HashMap::new(),
+        hooks,
+        audit,
+        cancels: HashMap::new(),
+        warm: warm::WarmSessions::default(),
+        enrollments: enrollment::EnrollSessions::default(),
+        authtok_tickets: authtok::AuthtokTickets::default(),
+        gallery_outliers: HashMap::new(),
+        challenges: challenge::Challenges::default(),
     }));
 
-    let service = VisageService { state };
+    // Optional HTTP health endpoint. Loopback only: it is unauthenticated.
+    match healthz_addr {
+        Some(addr) if addr.ip().is_loopback() => {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                if let Err(e) = health::serve(addr, state).await {
+                    tracing::error!(error = %e, "healthz endpoint failed");
+                }
+            });
+        }
+        Some(addr) => {
+            tracing::error!(%addr, "VISAGE_HEALTHZ_ADDR must be a loopback address; endpoint disabled")
+        }
+        None => {}
+    }
 
-    let _conn = if session_bus {
-        zbus::connection::Builder::session()?
-    } else {
-        zbus::connection::Builder::system()?
+    // Optional Prometheus exporters; see `metrics`. Loopback only, like
+    // `/healthz`.
+    match metrics_addr {
+        Some(addr) if addr.ip().is_loopback() => {
+            tokio::spawn(async move {
+                if let Err(e) = metrics::serve(addr).await {
+                    tracing::error!(error = %e, "metrics endpoint failed");
+                }
+            });
+        }
+        Some(addr) => {
+            tracing::error!(%addr, "VISAGE_METRICS_ADDR must be a loopback address; endpoint disabled")
+        }
+        None => {}
+    }
+    if let Some(path) = metrics_textfile {
+        tokio::spawn(metrics::write_textfile(path));
+    }
+
+    // Broker-less verify socket for early boot; see `socket`.
+    if let Some(path) = socket_path.clone() {
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = socket::serve(&path, state).await {
+                tracing::error!(error = %e, path = %path.display(), "verify socket failed");
+            }
+        });
+    }
+
+    // Verify server for guest VMs and containers; see `remote`.
+    if let Some(port) = vsock_port {
+        let (policy, state) = (Arc::clone(&remote_policy), Arc::clone(&state));
+        tokio::spawn(async move {
+            if let Err(e) = remote::serve_vsock(port, policy, state).await {
+                tracing::error!(error = %e, port, "vsock verify server failed");
+            }
+        });
+    }
+    match (tcp_addr, tcp_token_file) {
+        (Some(addr), Some(path)) if addr.ip().is_loopback() => match remote::read_token(&path) {
+            Ok(token) => {
+                let (policy, state) = (Arc::clone(&remote_policy), Arc::clone(&state));
+                tokio::spawn(async move {
+                    if let Err(e) = remote::serve_tcp(addr, token, policy, state).await {
+                        tracing::error!(error = %e, %addr, "TCP verify server failed");
+                    }
+                });
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "VISAGE_TCP_TOKEN_FILE unusable; TCP verify server disabled")
+            }
+        },
+        (Some(addr), Some(_)) => {
+            tracing::error!(%addr, "VISAGE_TCP_ADDR must be a loopback address; TCP verify server disabled")
+        }
+        (Some(_), None) => {
+            tracing::error!(
+                "VISAGE_TCP_ADDR needs VISAGE_TCP_TOKEN_FILE; TCP verify server disabled"
+            )
+        }
+        (None, _) => {}
+    }
+
+    // Reload edited quirk files; see `quirk_reload`.
+    if let Some(dirs) = quirks_watch {
+        let service = VisageService {
+            state: Arc::clone(&state),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = quirk_reload::watch(&dirs, service).await {
+                tracing::warn!(error = %e, "cannot watch quirk directories; use ReloadQuirks");
+            }
+        });
     }
-    .name("org.freedesktop.Visage1")?
-    .serve_at("/org/freedesktop/Visage1", service)?
-    .build()
-    .await?;
 
     let bus_name = if session_bus { "session" } else { "system" };
-    tracing::info!(
-        bus = bus_name,
-        "visaged ready — listening on org.freedesktop.Visage1"
-    );
+    let retention_service = VisageService {
+        state: Arc::clone(&state),
+    };
+    let reload_service = VisageService {
+        state: Arc::clone(&state),
+    };
+    let idle_state = Arc::clone(&state);
+    let watchdog_state = Arc::clone(&state);
+    let conn = match connect_bus(session_bus, &service_name, &object_path, Arc::clone(&state)).await
+    {
+        Ok(conn) => {
+            tracing::info!(
+                bus = bus_name,
+                name = %service_name,
+                "visaged ready — listening on D-Bus"
+            );
+            systemd::notify(&format!("READY=1\nSTATUS=Listening on {service_name}"));
+            let service = VisageService {
+                state: Arc::clone(&state),
+            };
+            let sweep_conn = conn.clone();
+            tokio::spawn(async move { service.check_all_galleries(&sweep_conn).await });
+            Some(conn)
+        }
+        // Without a broker the socket still serves; keep trying the bus so the
+        // daemon becomes reachable over D-Bus once the broker starts.
+        Err(e) if socket_path.is_some() => {
+            tracing::warn!(error = %e, bus = bus_name, "D-Bus unavailable; serving the verify socket only until it appears");
+            systemd::notify("READY=1\nSTATUS=Serving the verify socket; waiting for D-Bus");
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(BUS_RETRY_INTERVAL).await;
+                    let connected =
+                        connect_bus(session_bus, &service_name, &object_path, Arc::clone(&state))
+                            .await;
+                    if let Ok(conn) = connected {
+                        tracing::info!(
+                            bus = bus_name,
+                            name = %service_name,
+                            "connected to D-Bus — listening"
+                        );
+                        systemd::notify(&format!("STATUS=Listening on {service_name}"));
+                        // Keep the connection (and the bus name) until exit.
+                        let _conn = conn;
+                        std::future::pending::<()>().await;
+                    }
+                }
+            });
+            None
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Retention rules; see `retention`. Without the bus (socket-only
+    // startup) purge notices reach hooks but not D-Bus.
+    if retention_enabled {
+        let conn = conn.clone();
+        tokio::spawn(async move {
+            loop {
+                retention_service.enforce_retention(conn.as_ref()).await;
+                tokio::time::sleep(retention::INTERVAL).await;
+            }
+        });
+    }
 
-    // 5. Wait for shutdown signal (SIGINT or SIGTERM).
+    // systemd watchdog (`WatchdogSec=`): ping only while the engine thread
+    // is alive and not stuck in one request; see `systemd`.
+    if let Some(interval) = systemd::watchdog_interval() {
+        let state = watchdog_state;
+        tracing::info!(
+            interval_ms = interval.as_millis() as u64,
+            "systemd watchdog enabled"
+        );
+        tokio::spawn(async move {
+            loop {
+                let status = {
+                    let state = state.lock().await;
+                    health::engine_status(&state.engine, health::stall_limit(&state.config))
+                };
+                if status == health::EngineStatus::Running {
+                    systemd::notify("WATCHDOG=1");
+                } else {
+                    tracing::error!(
+                        engine = status.as_str(),
+                        "engine thread unresponsive; withholding the watchdog ping"
+                    );
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    // 5. Wait for shutdown signal (SIGINT or SIGTERM) or, with
+    // VISAGE_IDLE_EXIT_SECS, an idle period (see `idle`), reloading the
+    // configuration on SIGHUP (`systemctl reload visaged`).
     // systemd's `systemctl stop|restart` sends SIGTERM, which `tokio::signal::ctrl_c`
     // does not catch — so a ctrl_c-only handler stalls until `TimeoutStopSec` (default
     // 90s) elapses and systemd escalates to SIGKILL. See issue #26.
@@ -97,12 +394,71 @@ async fn main() -> Result<()> {
             signal(SignalKind::terminate()).context("failed to install SIGTERM handler")?;
         let mut sigint =
             signal(SignalKind::interrupt()).context("failed to install SIGINT handler")?;
-        tokio::select! {
-            _ = sigterm.recv() => tracing::info!(signal = "SIGTERM", "received shutdown signal"),
-            _ = sigint.recv()  => tracing::info!(signal = "SIGINT",  "received shutdown signal"),
+        let mut sighup =
+            signal(SignalKind::hangup()).context("failed to install SIGHUP handler")?;
+        let mut idle_check = tokio::time::interval(idle::CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = sigterm.recv() => {
+                    tracing::info!(signal = "SIGTERM", "received shutdown signal");
+                    break;
+                }
+                _ = sigint.recv() => {
+                    tracing::info!(signal = "SIGINT", "received shutdown signal");
+                    break;
+                }
+                _ = sighup.recv() => {
+                    tracing::info!(signal = "SIGHUP", "reloading configuration");
+                    // Failures are logged by reload_config.
+                    let _ = reload_service.reload_config().await;
+                }
+                _ = idle_check.tick(), if idle_exit.is_some() => {
+                    let after = idle_exit.unwrap_or_default();
+                    let mut state = idle_state.lock().await;
+                    if idle::is_idle(&mut state, after, std::time::Instant::now()) {
+                        // Exit status 0: `Restart=on-failure` leaves the unit
+                        // stopped until the next call activates it.
+                        tracing::info!(
+                            idle_secs = after.as_secs(),
+                            "idle; exiting until activated again"
+                        );
+                        break;
+                    }
+                }
+            }
         }
     }
     tracing::info!("visaged shutting down");
+    systemd::notify("STOPPING=1");
 
     Ok(())
 }
+
+/// Claim `service_name` (`org.freedesktop.Visage1`, or an instance's name;
+/// see `instance`) on the system bus (or the session bus in development
+/// mode) and serve the interface at `object_path`, sending
+/// `PropertiesChanged` as its properties change (see `properties`).
+async fn connect_bus(
+    session_bus: bool,
+    service_name: &str,
+    object_path: &str,
+    state: Arc<Mutex<AppState>>,
+) -> zbus::Result<zbus::Connection> {
+    let service = VisageService { state };
+    let conn = if session_bus {
+        zbus::connection::Builder::session()?
+    } else {
+        zbus::connection::Builder::system()?
+    }
+    .name(service_name)?
+    .serve_at(object_path, service)?
+    .build()
+    .await?;
+    let (watch_conn, path) = (conn.clone(), object_path.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = properties::watch(watch_conn, path).await {
+            tracing::warn!(error = %e, "property change signals stopped");
+        }
+    });
+    Ok(conn)
+}
@@ -0,0 +1,412 @@
+//! Metrics — counters and latency histograms for fleet monitoring.
+//!
+//! The daemon counts verify and identify attempts by result, the frames
+//! captured for verify and how many were skipped as too dark, and times each
+//! attempt and its capture and inference stages into fixed-bucket
+//! histograms. Everything lives in one process-wide registry of atomics, so
+//! recording never takes a lock the engine thread could wait on (the
+//! per-result maps take a short `std` mutex on the D-Bus side only).
+//!
+//! The `GetMetrics` D-Bus method returns a [`Snapshot`] as JSON. With the
+//! `metrics` feature the same snapshot is also served in the Prometheus text
+//! format on a loopback-only `GET /metrics` endpoint (`VISAGE_METRICS_ADDR`)
+//! and/or written to a node_exporter textfile (`VISAGE_METRICS_TEXTFILE`)
+//! every 15 seconds. Counters start from zero at daemon start.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+pub const BUCKETS: [f64; 9] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// How often the textfile exporter rewrites its file.
+#[cfg(feature = "metrics")]
+pub const TEXTFILE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A latency histogram with the [`BUCKETS`] upper bounds.
+#[derive(Debug)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last slot is `+Inf`.
+    buckets: [AtomicU64; BUCKETS.len() + 1],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let slot = BUCKETS
+            .iter()
+            .position(|&le| secs <= le)
+            .unwrap_or(BUCKETS.len());
+        self.buckets[slot].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&le, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                Bucket {
+                    le,
+                    count: cumulative,
+                }
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6,
+        }
+    }
+}
+
+/// One cumulative histogram bucket: observations of at most `le` seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+    pub le: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramSnapshot {
+    /// Cumulative buckets; `count` is the implicit `+Inf` bucket.
+    pub buckets: Vec<Bucket>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+/// Latency per stage, in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Latency {
+    /// Whole verify attempts, D-Bus call to decision.
+    pub verify: HistogramSnapshot,
+    /// Whole identify attempts.
+    pub identify: HistogramSnapshot,
+    /// Verify frame capture, emitter on to last frame.
+    pub capture: HistogramSnapshot,
+    /// Verify detection, embedding and matching of a burst.
+    pub inference: HistogramSnapshot,
+}
+
+/// Everything [`Metrics`] has counted, as returned by `GetMetrics`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub verify_attempts: u64,
+    pub verify_matches: u64,
+    /// Verify attempts by result code (`matched`, `no_match`, ...).
+    pub verify_results: BTreeMap<String, u64>,
+    pub identify_attempts: u64,
+    pub identify_matches: u64,
+    pub identify_results: BTreeMap<String, u64>,
+    /// Frames captured for verify, usable or not.
+    pub frames_captured: u64,
+    /// Of those, frames skipped as too dark.
+    pub dark_frames_skipped: u64,
+    pub latency: Latency,
+}
+
+/// The metrics registry. The daemon records into [`global`]; tests build
+/// their own.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    verify_results: Mutex<BTreeMap<&'static str, u64>>,
+    identify_results: Mutex<BTreeMap<&'static str, u64>>,
+    frames: AtomicU64,
+    dark_frames: AtomicU64,
+    verify_seconds: Histogram,
+    identify_seconds: Histogram,
+    capture_seconds: Histogram,
+    inference_seconds: Histogram,
+}
+
+/// The daemon-wide registry.
+pub fn global() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+fn bump(results: &Mutex<BTreeMap<&'static str, u64>>, code: &'static str) {
+    let mut results = results.lock().unwrap_or_else(|e| e.into_inner());
+    *results.entry(code).or_default() += 1;
+}
+
+fn results(results: &Mutex<BTreeMap<&'static str, u64>>) -> BTreeMap<String, u64> {
+    let results = results.lock().unwrap_or_else(|e| e.into_inner());
+    results
+        .iter()
+        .map(|(code, &count)| (code.to_string(), count))
+        .collect()
+}
+
+impl Metrics {
+    /// A verify attempt ended with result `code` after `elapsed`.
+    pub fn record_verify(&self, code: &'static str, elapsed: Duration) {
+        bump(&self.verify_results, code);
+        self.verify_seconds.observe(elapsed);
+    }
+
+    /// An identify attempt ended with result `code` after `elapsed`.
+    pub fn record_identify(&self, code: &'static str, elapsed: Duration) {
+        bump(&self.identify_results, code);
+        self.identify_seconds.observe(elapsed);
+    }
+
+    /// A verify burst of `frames` frames, `dark` of them too dark, took
+    /// `elapsed` to capture.
+    pub fn record_capture(&self, elapsed: Duration, frames: usize, dark: usize) {
+        self.frames.fetch_add(frames as u64, Ordering::Relaxed);
+        self.dark_frames.fetch_add(dark as u64, Ordering::Relaxed);
+        self.capture_seconds.observe(elapsed);
+    }
+
+    /// Detection, embedding and matching of a verify burst took `elapsed`.
+    pub fn record_inference(&self, elapsed: Duration) {
+        self.inference_seconds.observe(elapsed);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let verify_results = results(&self.verify_results);
+        let identify_results = results(&self.identify_results);
+        let matched = |results: &BTreeMap<String, u64>| {
+            results
+                .get(crate::stats::MATCHED)
+                .copied()
+                .unwrap_or_default()
+        };
+        Snapshot {
+            verify_attempts: verify_results.values().sum(),
+            verify_matches: matched(&verify_results),
+            identify_attempts: identify_results.values().sum(),
+            identify_matches: matched(&identify_results),
+            verify_results,
+            identify_results,
+            frames_captured: self.frames.load(Ordering::Relaxed),
+            dark_frames_skipped: self.dark_frames.load(Ordering::Relaxed),
+            latency: Latency {
+                verify: self.verify_seconds.snapshot(),
+                identify: self.identify_seconds.snapshot(),
+                capture: self.capture_seconds.snapshot(),
+                inference: self.inference_seconds.snapshot(),
+            },
+        }
+    }
+}
+
+/// Render `snapshot` in the Prometheus text exposition format.
+#[cfg(feature = "metrics")]
+pub fn prometheus(snapshot: &Snapshot) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let mut counter = |name: &str, help: &str, label: &str, values: &BTreeMap<String, u64>| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+        for (value, count) in values {
+            let _ = writeln!(out, "{name}{{{label}=\"{value}\"}} {count}");
+        }
+    };
+    counter(
+        "visage_verify_attempts_total",
+        "Verify attempts by result.",
+        "result",
+        &snapshot.verify_results,
+    );
+    counter(
+        "visage_identify_attempts_total",
+        "Identify attempts by result.",
+        "result",
+        &snapshot.identify_results,
+    );
+    for (name, help, value) in [
+        (
+            "visage_frames_captured_total",
+            "Frames captured for verify, usable or not.",
+            snapshot.frames_captured,
+        ),
+        (
+            "visage_dark_frames_skipped_total",
+            "Verify frames skipped as too dark.",
+            snapshot.dark_frames_skipped,
+        ),
+    ] {
+        let _ = writeln!(
+            out,
+            "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}"
+        );
+    }
+    let latency = &snapshot.latency;
+    for (name, help, histogram) in [
+        (
+            "visage_verify_duration_seconds",
+            "Duration of verify attempts.",
+            &latency.verify,
+        ),
+        (
+            "visage_identify_duration_seconds",
+            "Duration of identify attempts.",
+            &latency.identify,
+        ),
+        (
+            "visage_capture_duration_seconds",
+            "Duration of verify frame capture.",
+            &latency.capture,
+        ),
+        (
+            "visage_inference_duration_seconds",
+            "Duration of verify detection, embedding and matching.",
+            &latency.inference,
+        ),
+    ] {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram");
+        for bucket in &histogram.buckets {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{le=\"{}\"}} {}",
+                bucket.le, bucket.count
+            );
+        }
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", histogram.count);
+        let _ = writeln!(out, "{name}_sum {}", histogram.sum_seconds);
+        let _ = writeln!(out, "{name}_count {}", histogram.count);
+    }
+    out
+}
+
+/// Serve `GET /metrics` on `addr` in the Prometheus text format. Any other
+/// request gets `404`.
+#[cfg(feature = "metrics")]
+pub async fn serve(addr: std::net::SocketAddr) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "metrics endpoint listening");
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut request = [0u8; 512];
+            let read =
+                tokio::time::timeout(Duration::from_secs(2), stream.read(&mut request)).await;
+            let Ok(Ok(n)) = read else {
+                return;
+            };
+            let (status, body) = if request[..n].starts_with(b"GET /metrics ") {
+                ("200 OK", prometheus(&global().snapshot()))
+            } else {
+                ("404 Not Found", String::new())
+            };
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// Rewrite `path` with the Prometheus text every [`TEXTFILE_INTERVAL`],
+/// through a temporary file and a rename so node_exporter never reads a
+/// partial file.
+#[cfg(feature = "metrics")]
+pub async fn write_textfile(path: std::path::PathBuf) {
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+    let mut interval = tokio::time::interval(TEXTFILE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let text = prometheus(&global().snapshot());
+        let written = tokio::fs::write(&tmp, text).await;
+        if let Err(e) = match written {
+            Ok(()) => tokio::fs::rename(&tmp, &path).await,
+            Err(e) => Err(e),
+        } {
+            tracing::warn!(path = %path.display(), error = %e, "failed to write metrics textfile");
+        }
+    }
+}
+
+/// Placeholder so callers need no `cfg`: without the feature the endpoint
+/// cannot be enabled.
+#[cfg(not(feature = "metrics"))]
+pub async fn serve(addr: std::net::SocketAddr) -> std::io::Result<()> {
+    tracing::warn!(%addr, "VISAGE_METRICS_ADDR set but visaged was built without the `metrics` feature");
+    Ok(())
+}
+
+/// Placeholder so callers need no `cfg`: without the feature no textfile is
+/// written.
+#[cfg(not(feature = "metrics"))]
+pub async fn write_textfile(path: std::path::PathBuf) {
+    tracing::warn!(path = %path.display(), "VISAGE_METRICS_TEXTFILE set but visaged was built without the `metrics` feature");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_attempts_by_result_and_buckets_latency() {
+        let metrics = Metrics::default();
+        metrics.record_verify(crate::stats::MATCHED, Duration::from_millis(400));
+        metrics.record_verify("no_match", Duration::from_millis(40));
+        metrics.record_verify("no_match", Duration::from_secs(30));
+        metrics.record_capture(Duration::from_millis(200), 5, 2);
+        metrics.record_capture(Duration::from_millis(200), 5, 0);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.verify_attempts, 3);
+        assert_eq!(snapshot.verify_matches, 1);
+        assert_eq!(snapshot.verify_results["no_match"], 2);
+        assert_eq!(snapshot.identify_attempts, 0);
+        assert_eq!(
+            (snapshot.frames_captured, snapshot.dark_frames_skipped),
+            (10, 2)
+        );
+
+        let verify = &snapshot.latency.verify;
+        let count = |le: f64| verify.buckets.iter().find(|b| b.le == le).unwrap().count;
+        assert_eq!(count(0.01), 0);
+        assert_eq!(count(0.05), 1);
+        assert_eq!(count(0.5), 2);
+        // The 30 s attempt only falls in `+Inf`.
+        assert_eq!(count(10.0), 2);
+        assert_eq!(verify.count, 3);
+        assert!((verify.sum_seconds - 30.44).abs() < 1e-6);
+        assert_eq!(snapshot.latency.capture.count, 2);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.record_verify(crate::stats::MATCHED, Duration::from_millis(300));
+        let text = prometheus(&metrics.snapshot());
+        assert!(text.contains("# TYPE visage_verify_attempts_total counter\n"));
+        assert!(text.contains("visage_verify_attempts_total{result=\"matched\"} 1\n"));
+        assert!(text.contains("visage_verify_duration_seconds_bucket{le=\"0.25\"} 0\n"));
+        assert!(text.contains("visage_verify_duration_seconds_bucket{le=\"0.5\"} 1\n"));
+        assert!(text.contains("visage_verify_duration_seconds_bucket{le=\"+Inf\"} 1\n"));
+        assert!(text.contains("visage_verify_duration_seconds_count 1\n"));
+        assert!(text.contains("visage_frames_captured_total 0\n"));
+    }
+}
@@ -0,0 +1,247 @@
+//! Versioned schema migrations of the face database.
+//!
+//! The schema is the numbered steps of [`MIGRATIONS`], applied in order. A
+//! database records each step applied to it as a row of `schema_version`
+//! (version, description, time), and [`migrate`] applies the missing ones
+//! when the store opens: each step runs in one transaction with its row, so a
+//! step that fails leaves the database at the version before it, data intact.
+//!
+//! A schema change is a new step at the end of the list — never an edit to a
+//! released one. Step 1 is the schema as it stood before versions were
+//! recorded; it only creates what is missing, so it also brings up databases
+//! written by those releases, whatever columns they lack. A database at a
+//! version newer than the last step, written by a newer visaged, is refused:
+//! this build does not know its schema and could lose data by writing to it.
+
+use rusqlite::{Connection, Transaction};
+
+use crate::store::StoreError;
+
+/// One schema step.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    /// 1 for the first step, then one more for each.
+    pub version: u32,
+    /// What the step changes, as recorded in `schema_version`.
+    pub description: &'static str,
+    pub up: fn(&Transaction<'_>) -> rusqlite::Result<()>,
+}
+
+/// Every schema step, oldest first.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description:
+        "baseline: faces, authtoks, near misses, consents, purge notices, verify attempts, probes",
+    up: baseline,
+}];
+
+/// The version a database has after [`migrate`].
+pub fn latest() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Bring the database up to [`latest`]. Returns the version it was at.
+pub fn migrate(conn: &mut Connection) -> Result<u32, StoreError> {
+    apply(conn, MIGRATIONS)
+}
+
+/// The version recorded in `schema_version`; 0 without one.
+pub fn current(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn apply(conn: &mut Connection, migrations: &[Migration]) -> Result<u32, StoreError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+             version INTEGER PRIMARY KEY,
+             description TEXT NOT NULL,
+             applied_at TEXT NOT NULL
+         );",
+    )?;
+    let found = current(conn)?;
+    let supported = migrations.last().map_or(0, |m| m.version);
+    if found > supported {
+        return Err(StoreError::SchemaTooNew { found, supported });
+    }
+    for migration in migrations.iter().filter(|m| m.version > found) {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![
+                migration.version,
+                migration.description,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        tx.commit()?;
+        tracing::info!(
+            version = migration.version,
+            description = migration.description,
+            "database schema migrated"
+        );
+    }
+    Ok(found)
+}
+
+/// Whether `table` has `column` (for bringing up pre-version databases).
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+/// Step 1: the schema before versions were recorded.
+fn baseline(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS faces (
+             id TEXT PRIMARY KEY,
+             user TEXT NOT NULL,
+             label TEXT NOT NULL,
+             embedding BLOB NOT NULL,
+             model_version TEXT NOT NULL,
+             quality_score REAL NOT NULL DEFAULT 0.0,
+             pose_label TEXT NOT NULL DEFAULT 'frontal',
+             created_at TEXT NOT NULL,
+             tenant TEXT NOT NULL DEFAULT '',
+             threshold_offset REAL NOT NULL DEFAULT 0.0,
+             last_matched_at TEXT
+         );
+         CREATE INDEX IF NOT EXISTS idx_faces_user ON faces(user);",
+    )?;
+    // Databases created before tenants existed: every model lands in the
+    // default tenant.
+    if !has_column(tx, "faces", "tenant")? {
+        tx.execute_batch("ALTER TABLE faces ADD COLUMN tenant TEXT NOT NULL DEFAULT ''")?;
+    }
+    // ...and before per-model thresholds: no offset.
+    if !has_column(tx, "faces", "threshold_offset")? {
+        tx.execute_batch(
+            "ALTER TABLE faces ADD COLUMN threshold_offset REAL NOT NULL DEFAULT 0.0",
+        )?;
+    }
+    // ...and before usage tracking: never matched.
+    if !has_column(tx, "faces", "last_matched_at")? {
+        tx.execute_batch("ALTER TABLE faces ADD COLUMN last_matched_at TEXT")?;
+    }
+    tx.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_faces_tenant_user ON faces(tenant, user);
+         CREATE TABLE IF NOT EXISTS authtoks (
+             user TEXT PRIMARY KEY,
+             secret BLOB NOT NULL,
+             updated_at TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS near_misses (
+             model_id TEXT PRIMARY KEY REFERENCES faces(id) ON DELETE CASCADE,
+             count INTEGER NOT NULL,
+             similarity_sum REAL NOT NULL,
+             similarity_max REAL NOT NULL,
+             last_at TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS consents (
+             model_id TEXT PRIMARY KEY REFERENCES faces(id) ON DELETE CASCADE,
+             recorded_at TEXT NOT NULL,
+             method TEXT NOT NULL,
+             caller_uid INTEGER,
+             caller_user TEXT,
+             login_uid INTEGER,
+             login_user TEXT,
+             command TEXT
+         );
+         CREATE TABLE IF NOT EXISTS purge_notices (
+             model_id TEXT PRIMARY KEY REFERENCES faces(id) ON DELETE CASCADE,
+             notified_at TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS verify_attempts (
+             id INTEGER PRIMARY KEY,
+             at TEXT NOT NULL,
+             tenant TEXT NOT NULL,
+             user TEXT NOT NULL,
+             camera TEXT NOT NULL,
+             outcome TEXT NOT NULL,
+             latency_ms INTEGER NOT NULL,
+             frames INTEGER NOT NULL,
+             dark_frames INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_verify_attempts_at ON verify_attempts(at);
+         CREATE TABLE IF NOT EXISTS probes (
+             id INTEGER PRIMARY KEY,
+             tenant TEXT NOT NULL,
+             user TEXT NOT NULL,
+             embedding BLOB NOT NULL,
+             similarity REAL NOT NULL,
+             matched INTEGER NOT NULL,
+             created_at TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_probes_tenant_user ON probes(tenant, user);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_note(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+        tx.execute_batch("ALTER TABLE faces ADD COLUMN note TEXT")
+    }
+
+    fn broken(tx: &Transaction<'_>) -> rusqlite::Result<()> {
+        tx.execute_batch("ALTER TABLE faces ADD COLUMN half TEXT; NOT SQL")
+    }
+
+    #[test]
+    fn versions_count_up_from_one() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+        }
+    }
+
+    #[test]
+    fn steps_apply_once_and_in_order() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&mut conn).unwrap(), 0);
+        assert_eq!(current(&conn).unwrap(), latest());
+        assert_eq!(migrate(&mut conn).unwrap(), latest());
+
+        let next = Migration {
+            version: latest() + 1,
+            description: "note",
+            up: add_note,
+        };
+        let steps: Vec<Migration> = MIGRATIONS.iter().copied().chain([next]).collect();
+        assert_eq!(apply(&mut conn, &steps).unwrap(), latest());
+        assert!(has_column(&conn, "faces", "note").unwrap());
+        assert_eq!(current(&conn).unwrap(), latest() + 1);
+
+        // The older build refuses the newer database.
+        assert!(matches!(
+            migrate(&mut conn),
+            Err(StoreError::SchemaTooNew { found, supported })
+                if found == latest() + 1 && supported == latest()
+        ));
+    }
+
+    #[test]
+    fn failed_step_rolls_back() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        let steps: Vec<Migration> = MIGRATIONS
+            .iter()
+            .copied()
+            .chain([Migration {
+                version: latest() + 1,
+                description: "broken",
+                up: broken,
+            }])
+            .collect();
+        assert!(apply(&mut conn, &steps).is_err());
+        assert!(!has_column(&conn, "faces", "half").unwrap());
+        assert_eq!(current(&conn).unwrap(), latest());
+    }
+}
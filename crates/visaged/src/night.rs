@@ -0,0 +1,299 @@
+//! Day/night capture profiles — different settings for a dark room.
+//!
+//! One set of capture settings cannot suit both a sunlit desk and a dark
+//! bedroom: a dim IR frame needs longer exposure, stronger contrast
+//! stretching and a few more frames to find a usable one. The camera carries
+//! a second, night [`CaptureProfile`](visage_hw::CaptureProfile) and uses it
+//! for a burst when either
+//!
+//! - the local wall clock is inside `VISAGE_NIGHT_HOURS` (e.g. `20-7`),
+//! - the ambient light sensor reads under `VISAGE_ALS_DARK_LUX`, or
+//! - the first frame after warmup is darker than `VISAGE_NIGHT_BRIGHTNESS`.
+//!
+//! The first two are decided before the stream starts, so a night exposure is
+//! already set when the sensor powers up and auto exposure has nothing to hunt
+//! for; the last costs the frames that settle after a mid-burst switch.
+//! Exposure goes back to the camera's auto mode after every night burst.
+//!
+//! When verifies keep matching only after that mid-burst switch, the
+//! [`NightLearner`] remembers it: after `VISAGE_NIGHT_LEARN_AFTER` such
+//! matches in a row, bursts start on the night profile, preset to the
+//! exposure the sensor settled at in the last one (unless
+//! `VISAGE_NIGHT_EXPOSURE` fixes one). A learned start that then fails to
+//! produce a usable face is forgotten, and the next burst hunts again.
+
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicU8, Ordering};
+
+use chrono::Timelike;
+use visage_hw::{AmbientLight, CaptureStats, NightMode};
+
+/// Whether the last capture used the night profile: 0 unknown, 1 day, 2 night.
+static LAST_CAPTURE: AtomicU8 = AtomicU8::new(0);
+
+/// Whether the last capture ran with the night profile, for `Status`; `None`
+/// before the first capture.
+pub fn last_capture_was_night() -> Option<bool> {
+    match LAST_CAPTURE.load(Ordering::Relaxed) {
+        1 => Some(false),
+        2 => Some(true),
+        _ => None,
+    }
+}
+
+/// Bits of the last ambient light reading in lux; NaN before the first.
+static LAST_LUX: AtomicU32 = AtomicU32::new(0x7fc0_0000);
+
+/// The last ambient light reading, for `Status`.
+pub fn last_lux() -> Option<f32> {
+    Some(f32::from_bits(LAST_LUX.load(Ordering::Relaxed))).filter(|lux| !lux.is_nan())
+}
+
+/// Record which profile a capture used.
+pub fn record_capture(night: bool) {
+    LAST_CAPTURE.store(if night { 2 } else { 1 }, Ordering::Relaxed);
+}
+
+/// Whether bursts currently start on a learned night profile.
+static LEARNED: AtomicBool = AtomicBool::new(false);
+/// The learned exposure; 0 when none.
+static LEARNED_EXPOSURE: AtomicI64 = AtomicI64::new(0);
+
+/// The learned night start, for `Status`: `None` when nothing is learned,
+/// else the preset exposure, if any.
+pub fn learned() -> Option<Option<i64>> {
+    LEARNED
+        .load(Ordering::Relaxed)
+        .then(|| Some(LEARNED_EXPOSURE.load(Ordering::Relaxed)).filter(|&exposure| exposure > 0))
+}
+
+/// Learns from verify outcomes when to skip the first-frame brightness check
+/// and start on the night profile. Owned by the engine thread.
+#[derive(Debug)]
+pub struct NightLearner {
+    /// Matches after a mid-burst switch needed in a row; 0 disables learning.
+    learn_after: u32,
+    streak: u32,
+    /// Exposure the sensor settled at in the last such match.
+    exposure: Option<i64>,
+    learned: bool,
+    /// The last burst, until the verify's outcome is known.
+    pending: Option<CaptureStats>,
+}
+
+impl NightLearner {
+    pub fn new(learn_after: u32) -> Self {
+        Self {
+            learn_after,
+            streak: 0,
+            exposure: None,
+            learned: false,
+            pending: None,
+        }
+    }
+
+    /// Whether the next burst should start on the night profile.
+    pub fn start_on_night(&self) -> bool {
+        self.learned
+    }
+
+    /// Exposure to preset for night bursts.
+    pub fn exposure(&self) -> Option<i64> {
+        self.exposure.filter(|_| self.learned)
+    }
+
+    /// Note the burst of the verify in progress.
+    pub fn record_capture(&mut self, stats: &CaptureStats) {
+        self.pending = Some(*stats);
+    }
+
+    /// Learn from the verify's outcome: `None` when the burst gave no usable
+    /// face (dark, blurry, no face), else whether it matched. Verifies that
+    /// never captured are ignored.
+    pub fn record_outcome(&mut self, outcome: Option<bool>) {
+        let Some(stats) = self.pending.take() else {
+            return;
+        };
+        if self.learned {
+            match outcome {
+                Some(_) => self.exposure = stats.exposure.or(self.exposure),
+                None => {
+                    tracing::info!("learned night start failed; back to the brightness check");
+                    self.forget();
+                }
+            }
+        } else if outcome == Some(true) && stats.night_switched {
+            self.streak += 1;
+            self.exposure = stats.exposure.or(self.exposure);
+            if self.learn_after > 0 && self.streak >= self.learn_after {
+                tracing::info!(
+                    exposure = ?self.exposure,
+                    matches = self.streak,
+                    "dark room learned; starting captures on the night profile"
+                );
+                self.learned = true;
+            }
+        } else if !stats.night {
+            // A bright first frame: the room is lit.
+            self.streak = 0;
+            self.exposure = None;
+        }
+        LEARNED.store(self.learned, Ordering::Relaxed);
+        LEARNED_EXPOSURE.store(self.exposure().unwrap_or(0), Ordering::Relaxed);
+    }
+
+    fn forget(&mut self) {
+        self.learned = false;
+        self.streak = 0;
+        self.exposure = None;
+    }
+}
+
+/// The night profile and what forces it before a capture.
+#[derive(Debug, Clone)]
+pub struct NightSchedule {
+    pub mode: NightMode,
+    /// Local hours `[start, end)`; wraps past midnight when `start > end`.
+    pub hours: Option<(u32, u32)>,
+    /// Ambient light sensor and the illuminance (lux) below which the room
+    /// counts as dark.
+    pub light: Option<(AmbientLight, f32)>,
+    /// Matches after a mid-burst switch before bursts start on the night
+    /// profile (see [`NightLearner`]); 0 disables learning.
+    pub learn_after: u32,
+}
+
+impl NightSchedule {
+    /// Use the machine's ambient light sensor, if it has one, with
+    /// `dark_lux` as the switch point. 0.0 leaves the sensor unused.
+    pub fn with_light_sensor(mut self, dark_lux: f32) -> Self {
+        if dark_lux <= 0.0 {
+            return self;
+        }
+        match AmbientLight::discover() {
+            Some(sensor) => {
+                tracing::info!(
+                    sensor = sensor.name(),
+                    dark_lux,
+                    "ambient light sensor found"
+                );
+                self.light = Some((sensor, dark_lux));
+            }
+            None => tracing::warn!("VISAGE_ALS_DARK_LUX is set but no ambient light sensor found"),
+        }
+        self
+    }
+
+    /// Whether the wall clock or the ambient light puts this capture on the
+    /// night profile.
+    pub fn forced_now(&self) -> bool {
+        if self
+            .hours
+            .is_some_and(|hours| in_hours(hours, chrono::Local::now().hour()))
+        {
+            return true;
+        }
+        let Some((sensor, dark_lux)) = &self.light else {
+            return false;
+        };
+        // An unreadable sensor leaves the decision to the first frame.
+        let Some(lux) = sensor.lux() else {
+            return false;
+        };
+        LAST_LUX.store(lux.to_bits(), Ordering::Relaxed);
+        tracing::debug!(lux, "ambient light");
+        lux < *dark_lux
+    }
+}
+
+pub fn in_hours((start, end): (u32, u32), hour: u32) -> bool {
+    if start <= end {
+        (start..end).contains(&hour)
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// Parse `VISAGE_NIGHT_HOURS`: `START-END` in whole local hours (0–23).
+pub fn parse_hours(value: &str) -> Option<(u32, u32)> {
+    let (start, end) = value.trim().split_once('-')?;
+    let start: u32 = start.trim().parse().ok()?;
+    let end: u32 = end.trim().parse().ok()?;
+    (start < 24 && end < 24 && start != end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn night_hours_wrap_past_midnight() {
+        let hours = parse_hours("20-7").unwrap();
+        assert!(in_hours(hours, 20));
+        assert!(in_hours(hours, 23));
+        assert!(in_hours(hours, 0));
+        assert!(in_hours(hours, 6));
+        assert!(!in_hours(hours, 7));
+        assert!(!in_hours(hours, 12));
+
+        let afternoon = parse_hours(" 13 - 15 ").unwrap();
+        assert!(in_hours(afternoon, 14));
+        assert!(!in_hours(afternoon, 15));
+
+        for bad in ["", "20", "20-24", "7-7", "a-b"] {
+            assert_eq!(parse_hours(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn learner_starts_on_night_after_repeated_switched_matches() {
+        let switched = CaptureStats {
+            night: true,
+            night_switched: true,
+            exposure: Some(450),
+            ..CaptureStats::default()
+        };
+        let day = CaptureStats::default();
+        let mut learner = NightLearner::new(2);
+
+        learner.record_capture(&switched);
+        learner.record_outcome(Some(true));
+        learner.record_capture(&day);
+        learner.record_outcome(Some(true));
+        assert!(!learner.start_on_night(), "a lit room resets the streak");
+
+        for _ in 0..2 {
+            learner.record_capture(&switched);
+            learner.record_outcome(Some(true));
+        }
+        assert!(learner.start_on_night());
+        assert_eq!(learner.exposure(), Some(450));
+
+        // A rejected face still shows the profile works; no usable face does not.
+        let learned = CaptureStats {
+            night: true,
+            exposure: Some(500),
+            ..CaptureStats::default()
+        };
+        learner.record_capture(&learned);
+        learner.record_outcome(Some(false));
+        assert_eq!(learner.exposure(), Some(500));
+        learner.record_capture(&learned);
+        learner.record_outcome(None);
+        assert!(!learner.start_on_night());
+        assert_eq!(learner.exposure(), None);
+    }
+
+    #[test]
+    fn learning_can_be_disabled() {
+        let mut learner = NightLearner::new(0);
+        for _ in 0..5 {
+            learner.record_capture(&CaptureStats {
+                night: true,
+                night_switched: true,
+                ..CaptureStats::default()
+            });
+            learner.record_outcome(Some(true));
+        }
+        assert!(!learner.start_on_night());
+    }
+}
@@ -0,0 +1,101 @@
+//! Polkit authorization — let a GUI enroll or remove faces for a user who is
+//! not root, after an administrator prompt.
+//!
+//! `Enroll`, `EnrollBegin` (default tenant) and `RemoveModel` accept root
+//! outright. Any other caller may name only their own account, and is then
+//! checked against a polkit action — [`ENROLL`] or [`REMOVE`], declared in
+//! `org.freedesktop.visage1.policy` — with user interaction allowed, so the
+//! session's authentication agent asks for an administrator password
+//! (`auth_admin_keep` by default). A caller polkit
+//! does not authorize, or any failure to ask it (polkitd not running, no
+//! agent), is denied: an unprivileged process cannot enroll a face without
+//! someone typing a password.
+//!
+//! The action file also declares `org.freedesktop.visage1.delete-all` for a
+//! future method that removes every model of a user.
+
+use std::collections::HashMap;
+
+use zbus::zvariant::Value;
+
+/// Action for `Enroll` and `EnrollBegin`.
+pub const ENROLL: &str = "org.freedesktop.visage1.enroll";
+/// Action for `RemoveModel`.
+pub const REMOVE: &str = "org.freedesktop.visage1.remove";
+
+/// `CheckAuthorizationFlags.AllowUserInteraction`.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    async fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// Polkit's answer to a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Authorized,
+    /// The action needs authentication that nobody gave: no agent was
+    /// there to ask, or the prompt was dismissed.
+    Challenge,
+    Denied,
+}
+
+impl Answer {
+    /// From `CheckAuthorization`'s `is_authorized` and `is_challenge`.
+    fn of(authorized: bool, challenge: bool) -> Self {
+        match (authorized, challenge) {
+            (true, _) => Self::Authorized,
+            (false, true) => Self::Challenge,
+            (false, false) => Self::Denied,
+        }
+    }
+}
+
+/// Ask polkit whether the bus client `sender` may perform `action` on
+/// `user`'s face models, prompting through its authentication agent if the
+/// action requires it. Returns only once the prompt is answered.
+pub async fn check(
+    conn: &zbus::Connection,
+    sender: &str,
+    action: &str,
+    user: &str,
+) -> zbus::Result<Answer> {
+    let authority = AuthorityProxy::new(conn).await?;
+    let subject = (
+        "system-bus-name",
+        HashMap::from([("name", Value::from(sender))]),
+    );
+    // Shown by agents that display details; polkit also offers it to rules
+    // as `action.lookup("user")`.
+    let details = HashMap::from([("user", user)]);
+    let (authorized, challenge, _details) = authority
+        .check_authorization(&subject, action, details, ALLOW_USER_INTERACTION, "")
+        .await?;
+    Ok(Answer::of(authorized, challenge))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_an_authorized_answer_allows() {
+        assert_eq!(Answer::of(true, false), Answer::Authorized);
+        // Polkit does not pair the two, but authorized wins if it did.
+        assert_eq!(Answer::of(true, true), Answer::Authorized);
+        assert_eq!(Answer::of(false, true), Answer::Challenge);
+        assert_eq!(Answer::of(false, false), Answer::Denied);
+    }
+}
@@ -0,0 +1,171 @@
+//! D-Bus properties — daemon state a settings panel can bind to.
+//!
+//! Besides the `Status()` JSON, `org.freedesktop.Visage1` has five read-only
+//! properties, readable by any caller:
+//!
+//! - `Threshold` (`d`) — the similarity threshold;
+//! - `CameraDevice` (`s`) — the camera in use, from the configured list;
+//! - `ModelsEnrolled` (`u`) — face models enrolled, all tenants;
+//! - `EmitterPresent` (`b`) — whether captures light the IR emitter;
+//! - `Busy` (`b`) — whether the camera is in use (a request or the
+//!   pre-capture window).
+//!
+//! Their values change in the engine thread and in request handlers, which
+//! call [`changed`]. [`watch`] then reads them all and sends
+//! `PropertiesChanged` for those that differ from the last values sent, so a
+//! burst of changes costs one signal per property at most.
+
+use std::sync::OnceLock;
+
+use tokio::sync::{Mutex, Notify};
+
+use crate::dbus_interface::{AppState, VisageService};
+
+fn notify() -> &'static Notify {
+    static NOTIFY: OnceLock<Notify> = OnceLock::new();
+    NOTIFY.get_or_init(Notify::new)
+}
+
+/// Note that a property may have changed. Cheap, and callable from any
+/// thread.
+pub fn changed() {
+    notify().notify_one();
+}
+
+/// One of the properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    Threshold,
+    CameraDevice,
+    ModelsEnrolled,
+    EmitterPresent,
+    Busy,
+}
+
+/// The property values, as last read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Properties {
+    pub threshold: f64,
+    pub camera_device: String,
+    pub models_enrolled: u32,
+    pub emitter_present: bool,
+    pub busy: bool,
+}
+
+impl Properties {
+    pub async fn read(state: &Mutex<AppState>) -> Self {
+        let state = state.lock().await;
+        let models = state.store.count_all().await.unwrap_or(0);
+        Self {
+            threshold: f64::from(state.config.similarity_threshold),
+            camera_device: state.engine.camera_device(),
+            models_enrolled: u32::try_from(models).unwrap_or(u32::MAX),
+            emitter_present: state.engine.emitter_present(),
+            busy: state.engine.busy_for().is_some(),
+        }
+    }
+
+    /// The properties whose values differ from `sent`.
+    pub fn changed_since(&self, sent: &Self) -> Vec<Property> {
+        [
+            (self.threshold != sent.threshold, Property::Threshold),
+            (
+                self.camera_device != sent.camera_device,
+                Property::CameraDevice,
+            ),
+            (
+                self.models_enrolled != sent.models_enrolled,
+                Property::ModelsEnrolled,
+            ),
+            (
+                self.emitter_present != sent.emitter_present,
+                Property::EmitterPresent,
+            ),
+            (self.busy != sent.busy, Property::Busy),
+        ]
+        .into_iter()
+        .filter_map(|(changed, property)| changed.then_some(property))
+        .collect()
+    }
+}
+
+/// Send `PropertiesChanged` for the service at `path` whenever [`changed`]
+/// reports a difference, until the connection closes.
+pub async fn watch(conn: zbus::Connection, path: String) -> zbus::Result<()> {
+    let iface = conn
+        .object_server()
+        .interface::<_, VisageService>(path.as_str())
+        .await?;
+    let mut sent = Properties::read(&iface.get().await.state).await;
+    loop {
+        notify().notified().await;
+        let service = iface.get().await;
+        let now = Properties::read(&service.state).await;
+        let emitter = iface.signal_emitter();
+        for property in now.changed_since(&sent) {
+            match property {
+                Property::Threshold => service.threshold_changed(emitter).await?,
+                Property::CameraDevice => service.camera_device_changed(emitter).await?,
+                Property::ModelsEnrolled => service.models_enrolled_changed(emitter).await?,
+                Property::EmitterPresent => service.emitter_present_changed(emitter).await?,
+                Property::Busy => service.busy_changed(emitter).await?,
+            }
+        }
+        sent = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::DEFAULT_TENANT;
+    use visage_core::Embedding;
+
+    #[tokio::test]
+    async fn changes_are_read_back_and_sent_once_each() {
+        let state = Mutex::new(AppState::for_tests().await);
+        let before = Properties::read(&state).await;
+        assert_eq!(before.models_enrolled, 0);
+        assert!(!before.busy);
+        assert!(before.changed_since(&before).is_empty());
+
+        {
+            let mut state = state.lock().await;
+            state.config.similarity_threshold += 0.05;
+            let embedding = Embedding {
+                values: vec![0.5; 512],
+                model_version: None,
+            };
+            state
+                .store
+                .insert(DEFAULT_TENANT, "alice", "desk", &embedding, 0.9)
+                .await
+                .unwrap();
+            state.engine.set_busy(true);
+        }
+        let after = Properties::read(&state).await;
+        assert_eq!(after.models_enrolled, 1);
+        assert!(after.busy);
+        assert_eq!(
+            after.changed_since(&before),
+            vec![
+                Property::Threshold,
+                Property::ModelsEnrolled,
+                Property::Busy
+            ]
+        );
+
+        state.lock().await.engine.set_busy(false);
+        let idle = Properties::read(&state).await;
+        assert_eq!(idle.changed_since(&after), vec![Property::Busy]);
+    }
+
+    #[tokio::test]
+    async fn a_change_before_the_watcher_waits_is_not_lost() {
+        changed();
+        changed();
+        tokio::time::timeout(std::time::Duration::from_secs(1), notify().notified())
+            .await
+            .expect("the watcher wakes for a change noted while it was busy");
+    }
+}
@@ -0,0 +1,151 @@
+//! Quirk reload — pick up edited emitter quirks without a restart.
+//!
+//! Besides the quirks built into visage-hw, the daemon reads the `*.toml`
+//! quirk files in `VISAGE_QUIRKS_DIRS` (comma-separated, default
+//! `/etc/visage/quirks.d`) at startup; a file for a camera that already has
+//! a quirk replaces it (see `visage_hw::quirks::load_dirs`). Finding the
+//! control bytes of a new emitter takes many tries, and restarting the daemon
+//! for each one closes and re-opens the camera. Instead:
+//!
+//! - the `ReloadQuirks` D-Bus method (root only) reads the directories again
+//!   and re-probes the IR emitter of the open camera;
+//! - unless `VISAGE_QUIRKS_WATCH=0`, an inotify watch on the directories does
+//!   the same [`SETTLE`] after a quirk file is written, moved or deleted.
+//!
+//! A file that does not parse is logged and skipped; the others still load.
+//! Only directories that exist at startup are watched.
+
+use std::io;
+use std::os::fd::{AsFd, AsRawFd};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use serde::Serialize;
+use tokio::io::unix::AsyncFd;
+
+use crate::dbus_interface::VisageService;
+
+/// Quiet time after the last change before reloading, so an editor's
+/// write-and-rename (or a copy of several files) triggers one reload.
+pub const SETTLE: Duration = Duration::from_millis(300);
+
+/// The outcome of a reload, returned by `ReloadQuirks` as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reloaded {
+    /// Quirk files loaded from the directories.
+    pub files: Vec<String>,
+    /// Files skipped, with the reason.
+    pub errors: Vec<FileError>,
+    /// Quirks known now, built-in ones included.
+    pub quirks: usize,
+    /// Name of the IR emitter of the open camera after the reload.
+    pub emitter: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// Read the quirk files in `dirs` over the built-in quirks, logging what was
+/// loaded and what was skipped. `emitter` is left empty.
+pub fn load(dirs: &[PathBuf]) -> Reloaded {
+    let report = visage_hw::quirks::load_dirs(dirs);
+    for (path, error) in &report.errors {
+        tracing::warn!(path = %path.display(), error, "skipping quirk file");
+    }
+    tracing::info!(
+        files = report.files.len(),
+        quirks = report.quirks,
+        "quirk database loaded"
+    );
+    Reloaded {
+        files: report
+            .files
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        errors: report
+            .errors
+            .into_iter()
+            .map(|(path, error)| FileError {
+                path: path.display().to_string(),
+                error,
+            })
+            .collect(),
+        quirks: report.quirks,
+        emitter: None,
+    }
+}
+
+/// Whether an event on `name` concerns a quirk file.
+fn is_quirk_file(name: &Path) -> bool {
+    name.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Reload the quirks whenever a quirk file in `dirs` changes, until the
+/// daemon exits. Fails only if the inotify instance cannot be created.
+pub async fn watch(dirs: &[PathBuf], service: VisageService) -> io::Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+    let flags = AddWatchFlags::IN_CLOSE_WRITE
+        | AddWatchFlags::IN_MOVED_TO
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_DELETE;
+    let mut watched = 0;
+    for dir in dirs {
+        match inotify.add_watch(dir.as_path(), flags) {
+            Ok(_) => watched += 1,
+            Err(e) => {
+                tracing::debug!(dir = %dir.display(), error = %e, "not watching quirk directory")
+            }
+        }
+    }
+    if watched == 0 {
+        return Ok(());
+    }
+    tracing::info!(dirs = watched, "watching quirk directories");
+    let fd = AsyncFd::new(inotify.as_fd().as_raw_fd())?;
+
+    loop {
+        let mut ready = fd.readable().await?;
+        let events = match ready.try_io(|_| inotify.read_events().map_err(io::Error::from)) {
+            Ok(Ok(events)) => events,
+            Ok(Err(e)) => {
+                tracing::debug!(error = %e, "inotify read failed");
+                continue;
+            }
+            Err(_would_block) => continue,
+        };
+        let changed = events.iter().any(|event| {
+            event
+                .name
+                .as_deref()
+                .is_some_and(|name| is_quirk_file(Path::new(name)))
+        });
+        if !changed {
+            continue;
+        }
+        // Let the burst of events settle, then drop what arrived meanwhile.
+        tokio::time::sleep(SETTLE).await;
+        let _ = inotify.read_events();
+        tracing::info!("quirk files changed; reloading");
+        if let Err(e) = service.reload_quirk_files().await {
+            tracing::warn!(error = %e, "quirk reload failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_toml_files_are_quirks() {
+        assert!(is_quirk_file(Path::new("04f2-b6d9.toml")));
+        assert!(!is_quirk_file(Path::new(".04f2-b6d9.toml.swp")));
+        assert!(!is_quirk_file(Path::new("04f2-b6d9.toml~")));
+        assert!(!is_quirk_file(Path::new("README")));
+    }
+}
@@ -100,6 +100,18 @@ impl RateLimiter {
     pub fn record_success(&mut self, user: &str) {
         self.records.remove(user);
     }
+
+    /// Whether no user has failures in the current window or a running
+    /// lockout — i.e. forgetting every record would change nothing.
+    pub fn is_clear(&self) -> bool {
+        let now = Instant::now();
+        self.records
+            .values()
+            .all(|record| match record.locked_until {
+                Some(locked_until) => now >= locked_until,
+                None => record.failures == 0 || now.duration_since(record.window_start) >= WINDOW,
+            })
+    }
 }
 
 #[cfg(test)]
@@ -146,4 +158,16 @@ mod tests {
         assert!(rl.check("bob").is_ok());
         assert!(rl.check("alice").is_err());
     }
+
+    #[test]
+    fn test_clear_only_without_failures() {
+        let mut rl = RateLimiter::new();
+        assert!(rl.is_clear());
+        rl.check("alice").unwrap();
+        assert!(rl.is_clear(), "an attempt alone leaves nothing to forget");
+        rl.record_failure("alice");
+        assert!(!rl.is_clear());
+        rl.record_success("alice");
+        assert!(rl.is_clear());
+    }
 }
@@ -0,0 +1,244 @@
+//! Verify server for guests — let a VM or container on this workstation ask
+//! the host, which owns the camera, to confirm a face.
+//!
+//! Two optional listeners speak the `socket` protocol (one length-prefixed
+//! request and one reply per connection; only `Verify` in the default tenant):
+//!
+//! - **vsock.** With `VISAGE_VSOCK_PORT` set, the daemon listens on that
+//!   `AF_VSOCK` port. A guest is identified by its context ID (CID), which
+//!   the hypervisor assigns and the guest cannot forge.
+//! - **TCP.** With `VISAGE_TCP_ADDR` set to a loopback address, the daemon
+//!   listens there for containers sharing the host network. Any local process
+//!   can connect, so each connection first sends `auth\0TOKEN` with the
+//!   secret from `VISAGE_TCP_TOKEN_FILE`; a wrong token closes it.
+//!
+//! Neither transport carries a Unix identity, so the root/self rule of the
+//! other transports cannot apply. Instead `VISAGE_REMOTE_USERS` lists, per
+//! peer (`cid:N` or `tcp`), the accounts it may ask about:
+//! `cid:3=alice;tcp=alice,bob`. A peer not listed may not verify anyone, and
+//! a verify it does request is rate-limited and logged like any other.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::Path;
+use std::sync::Arc;
+
+use nix::sys::socket::{self, AddressFamily, Backlog, SockFlag, SockType, VsockAddr};
+use tokio::io::unix::AsyncFd;
+use tokio::net::{TcpListener, TcpStream, UnixStream};
+use tokio::sync::Mutex;
+
+use crate::dbus_interface::{account_key, AppState, BusError, VisageService};
+use crate::socket::{read_request, respond, write_frame};
+
+/// A remote client, as named in `VISAGE_REMOTE_USERS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Peer {
+    /// A guest on the vsock listener, by context ID.
+    Vsock(u32),
+    /// Any client on the TCP listener that presented the token.
+    Tcp,
+}
+
+impl Peer {
+    /// Parse `cid:N` or `tcp`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "tcp" => Some(Peer::Tcp),
+            _ => s.strip_prefix("cid:")?.parse().ok().map(Peer::Vsock),
+        }
+    }
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Peer::Vsock(cid) => write!(f, "cid:{cid}"),
+            Peer::Tcp => f.write_str("tcp"),
+        }
+    }
+}
+
+/// Which accounts each peer may verify.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemotePolicy {
+    pub users: BTreeMap<Peer, Vec<String>>,
+}
+
+impl RemotePolicy {
+    /// Allow `peer` to verify `account` only if the policy lists it.
+    pub fn authorize(&self, peer: Peer, account: &[u8]) -> Result<(), BusError> {
+        let user = account_key(account);
+        let listed = self
+            .users
+            .get(&peer)
+            .is_some_and(|users| users.iter().any(|u| u.as_bytes() == account));
+        if listed {
+            Ok(())
+        } else {
+            tracing::warn!(user = %user, %peer, "remote verify: peer not allowed this user");
+            Err(zbus::fdo::Error::AccessDenied(format!("{peer} may not verify user {user}")).into())
+        }
+    }
+}
+
+/// Listen on vsock `port` (any local CID) until the daemon exits.
+pub async fn serve_vsock(
+    port: u32,
+    policy: Arc<RemotePolicy>,
+    state: Arc<Mutex<AppState>>,
+) -> io::Result<()> {
+    let flags = SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC;
+    let fd = socket::socket(AddressFamily::Vsock, SockType::Stream, flags, None)?;
+    socket::bind(
+        fd.as_raw_fd(),
+        &VsockAddr::new(nix::libc::VMADDR_CID_ANY, port),
+    )?;
+    socket::listen(&fd, Backlog::new(16)?)?;
+    let listener = AsyncFd::new(fd)?;
+    tracing::info!(port, "vsock verify server listening");
+
+    let service = Arc::new(VisageService { state });
+    loop {
+        let mut ready = listener.readable().await?;
+        let accepted = match ready.try_io(|fd| Ok(socket::accept4(fd.as_raw_fd(), flags)?)) {
+            Ok(accepted) => accepted?,
+            Err(_would_block) => continue,
+        };
+        // SAFETY: accept4 returned a new descriptor that nothing else owns.
+        let conn = unsafe { OwnedFd::from_raw_fd(accepted) };
+        let peer = Peer::Vsock(socket::getpeername::<VsockAddr>(conn.as_raw_fd())?.cid());
+        // tokio has no vsock stream type; a Unix stream reads and writes any
+        // connected stream socket alike (its peer_cred is never used here).
+        let mut stream = UnixStream::from_std(std::os::unix::net::UnixStream::from(conn))?;
+        let service = Arc::clone(&service);
+        let policy = Arc::clone(&policy);
+        tokio::spawn(async move {
+            let label = peer.to_string();
+            let result = respond(&mut stream, &service, &label, None, |account| {
+                policy.authorize(peer, account)
+            })
+            .await;
+            if let Err(e) = result {
+                tracing::debug!(error = %e, %peer, "vsock verify server: connection failed");
+            }
+        });
+    }
+}
+
+/// Listen on loopback `addr` until the daemon exits, admitting connections
+/// that present `token`.
+pub async fn serve_tcp(
+    addr: SocketAddr,
+    token: Vec<u8>,
+    policy: Arc<RemotePolicy>,
+    state: Arc<Mutex<AppState>>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(%addr, "TCP verify server listening");
+
+    let service = Arc::new(VisageService { state });
+    let token = Arc::new(token);
+    loop {
+        let (stream, from) = listener.accept().await?;
+        let service = Arc::clone(&service);
+        let policy = Arc::clone(&policy);
+        let token = Arc::clone(&token);
+        tokio::spawn(async move {
+            if let Err(e) = handle_tcp(stream, &token, &policy, &service).await {
+                tracing::debug!(error = %e, %from, "TCP verify server: connection failed");
+            }
+        });
+    }
+}
+
+async fn handle_tcp(
+    mut stream: TcpStream,
+    token: &[u8],
+    policy: &RemotePolicy,
+    service: &VisageService,
+) -> io::Result<()> {
+    let auth = read_request(&mut stream).await?;
+    let presented = auth.strip_prefix(b"auth\0").unwrap_or_default();
+    if !token_matches(presented, token) {
+        tracing::warn!(from = %stream.peer_addr()?, "TCP verify server: bad token");
+        return write_frame(&mut stream, b"error\0not authorized").await;
+    }
+    respond(&mut stream, service, "tcp", None, |account| {
+        policy.authorize(Peer::Tcp, account)
+    })
+    .await
+}
+
+/// Read the shared TCP secret. The file must not be readable by group or
+/// others, and must hold at least 16 bytes once trailing whitespace is cut.
+pub fn read_token(path: &Path) -> io::Result<Vec<u8>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is accessible by group or others", path.display()),
+        ));
+    }
+    let mut token = std::fs::read(path)?;
+    while token.last().is_some_and(u8::is_ascii_whitespace) {
+        token.pop();
+    }
+    if token.len() < 16 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} holds fewer than 16 bytes", path.display()),
+        ));
+    }
+    Ok(token)
+}
+
+/// Compare in time independent of where the inputs first differ.
+fn token_matches(presented: &[u8], token: &[u8]) -> bool {
+    presented.len() == token.len()
+        && presented
+            .iter()
+            .zip(token)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_allows_only_listed_peers_and_users() {
+        let policy = RemotePolicy {
+            users: BTreeMap::from([
+                (Peer::Vsock(3), vec!["alice".to_string()]),
+                (Peer::Tcp, vec!["bob".to_string()]),
+            ]),
+        };
+        assert!(policy.authorize(Peer::Vsock(3), b"alice").is_ok());
+        assert!(policy.authorize(Peer::Vsock(3), b"bob").is_err());
+        assert!(policy.authorize(Peer::Vsock(4), b"alice").is_err());
+        assert!(policy.authorize(Peer::Tcp, b"bob").is_ok());
+        assert!(RemotePolicy::default()
+            .authorize(Peer::Tcp, b"bob")
+            .is_err());
+
+        assert_eq!(Peer::parse("cid:3"), Some(Peer::Vsock(3)));
+        assert_eq!(Peer::parse("tcp"), Some(Peer::Tcp));
+        assert_eq!(Peer::parse("cid:x"), None);
+        assert_eq!(Peer::parse("3"), None);
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(token_matches(b"0123456789abcdef", b"0123456789abcdef"));
+        assert!(!token_matches(b"0123456789abcdeF", b"0123456789abcdef"));
+        assert!(!token_matches(b"0123456789abcde", b"0123456789abcdef"));
+        assert!(!token_matches(b"", b"0123456789abcdef"));
+    }
+}
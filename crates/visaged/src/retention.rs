@@ -0,0 +1,207 @@
+//! Data retention — delete what a privacy policy says must not be kept,
+//! without cron jobs editing the database.
+//!
+//! Two rules, both off by default and applied at startup and then hourly:
+//!
+//! - **History.** Near-miss records (when and how closely a verify almost
+//!   matched a model) not updated for `VISAGE_RETAIN_HISTORY_DAYS`, and
+//!   verify attempts (see `stats`) and kept probes (see `scores`) older than
+//!   that, are deleted.
+//! - **Unused models.** A model that no verify or identification has matched
+//!   for `VISAGE_PURGE_UNUSED_MONTHS` (30-day months; a model never matched
+//!   counts from its enrollment) is purged — but never without notice. Once
+//!   it has been unused for the period less `VISAGE_PURGE_NOTICE_DAYS`, its
+//!   owner is told with a `ModelExpiring` signal (shown by `visage notify`)
+//!   and a `model_expiring` hook, and the purge waits until the notice is
+//!   that many days old. A match in the meantime withdraws the notice.
+//!
+//! Purging goes through the same path as `RemoveModel`, so it is logged and
+//! reaches hooks as an `enrollment_changed` event. The same pass expires
+//! failure snapshots (see `snapshots`).
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::store::UnusedModel;
+
+/// Days in a retention "month".
+const DAYS_PER_MONTH: i64 = 30;
+/// How often the rules are applied.
+pub const INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// The configured rules; `0` turns a rule off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub history_days: u64,
+    pub unused_months: u64,
+    pub notice_days: u64,
+}
+
+/// What to do with one unused model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Tell the owner it will be purged, no earlier than `purge_after`.
+    Notify {
+        purge_after: DateTime<Utc>,
+    },
+    Purge,
+    /// Notified, and not due yet.
+    Wait,
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.history_days > 0 || self.unused_months > 0
+    }
+
+    /// Near-miss records last updated, and attempts made, before this are
+    /// deleted.
+    pub fn history_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (self.history_days > 0).then(|| now - days(self.history_days))
+    }
+
+    /// Models not used since this are due a notice (or, once notified, a
+    /// purge); `None` when unused models are kept.
+    pub fn notice_cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (self.unused_months > 0)
+            .then(|| now - (self.unused_period() - self.notice()).max(Duration::zero()))
+    }
+
+    /// The step for `model`, one of those [`notice_cutoff`](Self::notice_cutoff)
+    /// selected. A model whose dates cannot be read is left alone.
+    pub fn decide(&self, model: &UnusedModel, now: DateTime<Utc>) -> Action {
+        let Some(last_used) = parse(&model.last_used) else {
+            return Action::Wait;
+        };
+        let unused_until = last_used + self.unused_period();
+        match model.notified_at.as_deref() {
+            None => Action::Notify {
+                purge_after: unused_until.max(now + self.notice()),
+            },
+            Some(notified_at) => match parse(notified_at) {
+                Some(notified_at) if now >= notified_at + self.notice() && now >= unused_until => {
+                    Action::Purge
+                }
+                _ => Action::Wait,
+            },
+        }
+    }
+
+    fn unused_period(&self) -> Duration {
+        days(self.unused_months.saturating_mul(DAYS_PER_MONTH as u64))
+    }
+
+    fn notice(&self) -> Duration {
+        days(self.notice_days)
+    }
+}
+
+fn days(n: u64) -> Duration {
+    // Anything past a few thousand years is "forever" anyway.
+    Duration::days(n.min(1_000_000) as i64)
+}
+
+fn parse(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: RetentionPolicy = RetentionPolicy {
+        history_days: 30,
+        unused_months: 6,
+        notice_days: 14,
+    };
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        parse(timestamp).unwrap()
+    }
+
+    fn model(last_used: &str, notified_at: Option<&str>) -> UnusedModel {
+        UnusedModel {
+            id: "m".into(),
+            tenant: String::new(),
+            user: "alice".into(),
+            label: "desk".into(),
+            last_used: last_used.into(),
+            notified_at: notified_at.map(String::from),
+        }
+    }
+
+    #[test]
+    fn cutoffs_follow_the_policy() {
+        let now = at("2026-07-01T00:00:00+00:00");
+        assert_eq!(
+            POLICY.history_cutoff(now),
+            Some(at("2026-06-01T00:00:00+00:00"))
+        );
+        // 180 days unused, less 14 days of notice.
+        assert_eq!(
+            POLICY.notice_cutoff(now),
+            Some(at("2026-01-16T00:00:00+00:00"))
+        );
+        let off = RetentionPolicy {
+            history_days: 0,
+            unused_months: 0,
+            notice_days: 14,
+        };
+        assert!(!off.is_enabled());
+        assert_eq!(off.history_cutoff(now), None);
+        assert_eq!(off.notice_cutoff(now), None);
+    }
+
+    #[test]
+    fn models_are_purged_only_after_notice() {
+        let now = at("2026-07-01T00:00:00+00:00");
+        // First seen: notify, purge when both the period and the notice ran.
+        assert_eq!(
+            POLICY.decide(&model("2026-01-10T00:00:00+00:00", None), now),
+            Action::Notify {
+                purge_after: at("2026-07-15T00:00:00+00:00")
+            }
+        );
+        assert_eq!(
+            POLICY.decide(&model("2025-01-01T00:00:00+00:00", None), now),
+            Action::Notify {
+                purge_after: at("2026-07-15T00:00:00+00:00")
+            }
+        );
+        // Notified a week ago: wait. Two weeks ago: purge.
+        assert_eq!(
+            POLICY.decide(
+                &model(
+                    "2025-01-01T00:00:00+00:00",
+                    Some("2026-06-24T00:00:00+00:00")
+                ),
+                now
+            ),
+            Action::Wait
+        );
+        assert_eq!(
+            POLICY.decide(
+                &model(
+                    "2025-01-01T00:00:00+00:00",
+                    Some("2026-06-17T00:00:00+00:00")
+                ),
+                now
+            ),
+            Action::Purge
+        );
+        // Notice served, but the unused period has not run out yet.
+        assert_eq!(
+            POLICY.decide(
+                &model(
+                    "2026-01-10T00:00:00+00:00",
+                    Some("2026-06-01T00:00:00+00:00")
+                ),
+                now
+            ),
+            Action::Wait
+        );
+        // Unreadable dates are never purged.
+        assert_eq!(POLICY.decide(&model("2024-01-01", None), now), Action::Wait);
+    }
+}
@@ -0,0 +1,160 @@
+//! Engine scheduling — keep verify latency steady on a loaded machine.
+//!
+//! A verify shares the CPUs with whatever else runs: a kernel build or a VM
+//! can stretch inference several times over. Three settings, all off by
+//! default, give the engine its own share:
+//!
+//! - `VISAGE_ENGINE_CPUS` — the CPUs it may run on, e.g. `2,3` or `2-3`;
+//! - `VISAGE_ENGINE_NICE` — its nice value, -20 (most CPU) to 19;
+//! - `VISAGE_ENGINE_RT_PRIORITY` — 1–99: run it under the real-time
+//!   `SCHED_RR` policy at that priority instead; the nice value then does
+//!   not apply.
+//!
+//! They cover the engine thread and the ONNX Runtime threads of its models:
+//! the models are loaded by a thread that applied them first, and threads
+//! inherit the affinity and policy of the thread that starts them. A worker
+//! pool gets the same treatment by being started inside [`Scheduling::run`].
+//!
+//! Pinning needs no privilege. A negative nice value or a real-time priority
+//! needs `CAP_SYS_NICE`, which the systemd unit drops, or a high enough
+//! `LimitNICE=` / `LimitRTPRIO=`. A setting the kernel refuses is logged and
+//! skipped; the daemon runs with the rest.
+
+use nix::sched::{sched_setaffinity, CpuSet};
+use nix::unistd::Pid;
+
+/// Highest real-time priority of `SCHED_RR` on Linux.
+pub const MAX_RT_PRIORITY: i32 = 99;
+
+/// CPU placement and priority of the engine; see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scheduling {
+    /// CPUs to pin to; empty leaves the affinity alone.
+    pub cpus: Vec<usize>,
+    /// Nice value, -20..=19.
+    pub nice: Option<i32>,
+    /// `SCHED_RR` priority, 1..=99; overrides `nice`.
+    pub rt_priority: Option<i32>,
+}
+
+impl Scheduling {
+    /// Whether nothing is configured.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply to the calling thread; threads it starts afterwards inherit it.
+    pub fn apply(&self) {
+        let thread = std::thread::current().name().unwrap_or("?").to_string();
+        if !self.cpus.is_empty() {
+            match pin(&self.cpus) {
+                Ok(()) => tracing::debug!(thread, cpus = ?self.cpus, "thread pinned"),
+                Err(e) => {
+                    tracing::warn!(thread, cpus = ?self.cpus, error = %e, "cannot pin thread")
+                }
+            }
+        }
+        if let Some(priority) = self.rt_priority {
+            let param = libc::sched_param {
+                sched_priority: priority,
+            };
+            // SAFETY: pid 0 is the calling thread; `param` outlives the call.
+            if unsafe { libc::sched_setscheduler(0, libc::SCHED_RR, &param) } == 0 {
+                tracing::debug!(thread, priority, "thread runs under SCHED_RR");
+            } else {
+                let e = std::io::Error::last_os_error();
+                tracing::warn!(thread, priority, error = %e, "cannot set SCHED_RR; needs CAP_SYS_NICE or LimitRTPRIO");
+            }
+        } else if let Some(nice) = self.nice {
+            let tid = nix::unistd::gettid().as_raw() as libc::id_t;
+            // SAFETY: plain syscall; on Linux the nice value is per thread.
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } == 0 {
+                tracing::debug!(thread, nice, "thread nice value set");
+            } else {
+                let e = std::io::Error::last_os_error();
+                tracing::warn!(thread, nice, error = %e, "cannot set nice value; needs CAP_SYS_NICE or LimitNICE");
+            }
+        }
+    }
+
+    /// Run `f` on a short-lived thread with the scheduling applied, so the
+    /// threads `f` starts (ONNX Runtime's intra-op pool, a worker pool)
+    /// inherit it. Runs `f` in place when nothing is configured.
+    pub fn run<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        if self.is_default() {
+            return f();
+        }
+        std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .name("visage-load".into())
+                .spawn_scoped(scope, || {
+                    self.apply();
+                    f()
+                })
+                .expect("spawn model loading thread")
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+}
+
+fn pin(cpus: &[usize]) -> nix::Result<()> {
+    let mut set = CpuSet::new();
+    for &cpu in cpus {
+        set.set(cpu)?;
+    }
+    sched_setaffinity(Pid::from_raw(0), &set)
+}
+
+/// Parse `VISAGE_ENGINE_CPUS`: CPU numbers and inclusive ranges separated by
+/// commas, e.g. `0,2-3`. Sorted and deduplicated; `None` if any entry is
+/// not a CPU number or range, or the list is empty.
+pub fn parse_cpus(value: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        match entry.split_once('-') {
+            Some((first, last)) => {
+                let (first, last): (usize, usize) =
+                    (first.trim().parse().ok()?, last.trim().parse().ok()?);
+                if first > last || last >= CpuSet::count() {
+                    return None;
+                }
+                cpus.extend(first..=last);
+            }
+            None => {
+                let cpu: usize = entry.parse().ok()?;
+                if cpu >= CpuSet::count() {
+                    return None;
+                }
+                cpus.push(cpu);
+            }
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    (!cpus.is_empty()).then_some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_lists_and_ranges() {
+        assert_eq!(parse_cpus("2,3"), Some(vec![2, 3]));
+        assert_eq!(parse_cpus(" 4-6, 1 ,5"), Some(vec![1, 4, 5, 6]));
+        assert_eq!(parse_cpus("0"), Some(vec![0]));
+        assert_eq!(parse_cpus(""), None);
+        assert_eq!(parse_cpus("3-1"), None);
+        assert_eq!(parse_cpus("two"), None);
+        assert_eq!(parse_cpus("1,-2"), None);
+        assert_eq!(parse_cpus("100000"), None);
+    }
+
+    #[test]
+    fn default_runs_in_place() {
+        let caller = std::thread::current().id();
+        let ran_on = Scheduling::default().run(|| std::thread::current().id());
+        assert_eq!(ran_on, caller);
+    }
+}
@@ -0,0 +1,181 @@
+//! Score export — the pairwise similarities behind `visage export-scores`.
+//!
+//! Choosing a threshold, or a per-model offset, is guesswork without the
+//! scores a user actually produces. With `VISAGE_KEEP_PROBES=N` the daemon
+//! keeps the face embedding of each of a user's last `N` verifies (a
+//! *probe*), sealed like the templates, with its best similarity and whether
+//! it matched. Probes are biometric data: they are off by default, deleted
+//! with the user's last model, and expire with the other per-attempt records
+//! under `VISAGE_RETAIN_HISTORY_DAYS` (see `retention`).
+//!
+//! The `ExportScores` D-Bus method (root only) compares every template and
+//! probe of one user with every other and returns the similarities, labelled
+//! by pair kind: `template-template` shows how consistent the gallery is,
+//! `probe-template` what genuine attempts score against it, `probe-probe`
+//! how much the user varies between attempts. Only scores leave the daemon,
+//! never embeddings.
+
+use serde::Serialize;
+use visage_core::{Embedding, FaceModel};
+
+use crate::store::Probe;
+
+/// One template or probe of the export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sample {
+    /// The model id of a template; `probe-<n>` for a probe.
+    pub id: String,
+    /// `template` or `probe`.
+    pub kind: &'static str,
+    /// The label of a template; empty for a probe.
+    pub label: String,
+    /// RFC 3339: enrollment of a template, verify of a probe.
+    pub at: String,
+    /// Best similarity the probe's verify scored against the gallery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    /// Whether the probe's verify matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<bool>,
+}
+
+/// The similarity of two samples, `a` before `b` in [`Export::samples`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Score {
+    /// `template-template`, `probe-template` or `probe-probe`.
+    pub pair: &'static str,
+    pub a: String,
+    pub b: String,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Export {
+    pub samples: Vec<Sample>,
+    pub scores: Vec<Score>,
+}
+
+/// Every pairwise similarity among `templates` and `probes`: templates
+/// first, then probes, each pair once.
+pub fn export(templates: &[FaceModel], probes: &[Probe]) -> Export {
+    let mut entries: Vec<(Sample, &Embedding)> = Vec::new();
+    for model in templates {
+        let sample = Sample {
+            id: model.id.clone(),
+            kind: "template",
+            label: model.label.clone(),
+            at: model.created_at.clone(),
+            similarity: None,
+            matched: None,
+        };
+        entries.push((sample, &model.embedding));
+    }
+    for probe in probes {
+        let sample = Sample {
+            id: format!("probe-{}", probe.id),
+            kind: "probe",
+            label: String::new(),
+            at: probe.created_at.clone(),
+            similarity: Some(probe.similarity),
+            matched: Some(probe.matched),
+        };
+        entries.push((sample, &probe.embedding));
+    }
+
+    let mut scores = Vec::new();
+    for (i, (a, a_embedding)) in entries.iter().enumerate() {
+        for (b, b_embedding) in &entries[i + 1..] {
+            let pair = match (a.kind, b.kind) {
+                ("template", "template") => "template-template",
+                ("probe", "probe") => "probe-probe",
+                _ => "probe-template",
+            };
+            scores.push(Score {
+                pair,
+                a: a.id.clone(),
+                b: b.id.clone(),
+                similarity: a_embedding.similarity(b_embedding),
+            });
+        }
+    }
+    Export {
+        samples: entries.into_iter().map(|(sample, _)| sample).collect(),
+        scores,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedding(values: [f32; 2]) -> Embedding {
+        Embedding {
+            values: values.to_vec(),
+            model_version: None,
+        }
+    }
+
+    fn template(id: &str, values: [f32; 2]) -> FaceModel {
+        FaceModel {
+            id: id.to_string(),
+            user: "alice".to_string(),
+            label: "desk".to_string(),
+            embedding: embedding(values),
+            created_at: "2026-03-01T00:00:00+00:00".to_string(),
+            tenant: String::new(),
+            threshold_offset: 0.0,
+        }
+    }
+
+    fn probe(id: i64, values: [f32; 2]) -> Probe {
+        Probe {
+            id,
+            embedding: embedding(values),
+            similarity: 0.5,
+            matched: true,
+            created_at: "2026-03-02T00:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_pair_is_scored_once_by_kind() {
+        let export = export(
+            &[template("m1", [1.0, 0.0]), template("m2", [0.0, 1.0])],
+            &[probe(7, [1.0, 0.0]), probe(8, [1.0, 1.0])],
+        );
+        assert_eq!(
+            export
+                .samples
+                .iter()
+                .map(|s| s.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["m1", "m2", "probe-7", "probe-8"]
+        );
+        let pairs: Vec<(&str, &str, &str)> = export
+            .scores
+            .iter()
+            .map(|s| (s.pair, s.a.as_str(), s.b.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("template-template", "m1", "m2"),
+                ("probe-template", "m1", "probe-7"),
+                ("probe-template", "m1", "probe-8"),
+                ("probe-template", "m2", "probe-7"),
+                ("probe-template", "m2", "probe-8"),
+                ("probe-probe", "probe-7", "probe-8"),
+            ]
+        );
+        assert!(export.scores[0].similarity.abs() < 1e-6);
+        assert!((export.scores[1].similarity - 1.0).abs() < 1e-6);
+        assert!((export.scores[5].similarity - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nothing_to_compare_exports_no_scores() {
+        let export = export(&[template("m1", [1.0, 0.0])], &[]);
+        assert_eq!(export.samples.len(), 1);
+        assert!(export.scores.is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! `visaged simulate` — replay a recorded burst through the verify logic.
+//!
+//! Accuracy reports are hard to reproduce: the user's face, room and camera
+//! are not on the maintainer's desk. A recorded bundle — a directory of the
+//! frames one burst handed to the detector, as 8-bit PGM files (what
+//! `visage test --out DIR` writes) — can be replayed instead:
+//!
+//! ```text
+//! visaged simulate /tmp/visage-test alice
+//! ```
+//!
+//! The frames go through the same burst check and [`match_burst`] as a live
+//! verify, against the user's gallery from the configured database and with
+//! the configured threshold, liveness settings and PAD model, without D-Bus or
+//! a camera.
+//! The per-frame decision trace is logged to stdout at debug level, followed
+//! by the outcome and its failure code. Replaying the same bundle against two
+//! builds bisects a regression.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use visage_core::CosineMatcher;
+use visage_hw::Frame;
+
+use crate::config::Config;
+use crate::engine::{match_burst, BurstMatch, EngineError, VerifyResult};
+use crate::failure::FailureReason;
+use crate::store::{FaceModelStore, DEFAULT_TENANT};
+
+const USAGE: &str = "usage: visaged simulate BUNDLE_DIR USER [--tenant NAME]";
+
+/// Run `visaged simulate` with the arguments after the subcommand.
+pub async fn run(args: &[String]) -> Result<()> {
+    let mut positional = Vec::new();
+    let mut tenant = DEFAULT_TENANT.to_string();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--tenant" => tenant = iter.next().context(USAGE)?.clone(),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let [bundle, user] = positional.as_slice() else {
+        bail!(USAGE);
+    };
+
+    let config = Config::load()
+        .map_err(anyhow::Error::msg)
+        .context("loading configuration")?;
+    let frames = load_bundle(Path::new(bundle))?;
+    println!("bundle: {bundle} ({} frames)", frames.len());
+
+    visage_models::verify_models_dir(&config.model_dir)
+        .map_err(anyhow::Error::from)
+        .with_context(|| {
+            format!(
+                "model integrity check failed for {}",
+                config.model_dir.display()
+            )
+        })?;
+    let inference = config.inference_options();
+    let mut detector = visage_core::FaceDetector::load_with(&config.scrfd_model_path(), inference)?;
+    let mut recognizer =
+        visage_core::FaceRecognizer::load_with(&config.arcface_model_path(), inference)?;
+    let mut pad = config
+        .pad_model_path()
+        .map(|path| visage_core::PadModel::load_with(&path, inference))
+        .transpose()?;
+
+    let store = FaceModelStore::open(&config.db_path).await?;
+    let gallery = store.get_gallery_for_user(&tenant, user).await?;
+    if gallery.is_empty() {
+        bail!(
+            "no enrolled models for user '{user}' in {}",
+            config.db_path.display()
+        );
+    }
+    println!(
+        "gallery: {} model(s) for '{user}', threshold {:.2}, liveness {}",
+        gallery.len(),
+        config.similarity_threshold,
+        if config.liveness_enabled { "on" } else { "off" }
+    );
+
+    let outcome = config
+        .quality_gate()
+        .check_burst(&frames)
+        .map_err(EngineError::StaleFrames)
+        .and_then(|()| {
+            match_burst(
+                &frames,
+                &mut detector,
+                &mut recognizer,
+                pad.as_mut().map(|pad| (pad, config.pad_threshold)),
+                &CosineMatcher,
+                &gallery,
+                config.similarity_threshold,
+                config
+                    .liveness_enabled
+                    .then_some(config.liveness_min_displacement),
+                &|| Ok(()),
+                &|_| {},
+            )
+        })
+        .map(|burst| {
+            let BurstMatch {
+                result,
+                best_quality,
+                best_face_size,
+                best_face: _,
+                best_embedding,
+                best_frame: _,
+                pad_score,
+            } = burst;
+            VerifyResult {
+                result,
+                best_quality,
+                best_face_size,
+                rgb: None,
+                depth_relief_mm: None,
+                pad_score,
+                probe: best_embedding,
+                frame: None,
+                capture_time: std::time::Duration::ZERO,
+                inference_time: std::time::Duration::ZERO,
+            }
+        });
+
+    match outcome {
+        Ok(result) => {
+            let similarity = result.result.similarity;
+            match FailureReason::from_result(&result) {
+                None => println!(
+                    "decision: match (model {}, similarity {similarity:.3})",
+                    result.result.model_label.as_deref().unwrap_or("?")
+                ),
+                Some(reason) => {
+                    println!("decision: no match: {reason} (similarity {similarity:.3})");
+                    if reason == FailureReason::BelowThreshold && config.is_borderline(similarity) {
+                        println!("  borderline: the daemon would capture a confirmation burst");
+                    }
+                }
+            }
+        }
+        Err(e) => println!(
+            "decision: no match: {} ({e})",
+            FailureReason::from_engine_error(&e)
+        ),
+    }
+    Ok(())
+}
+
+/// Frames of a bundle directory: its `.pgm` files in name order, numbered as
+/// one stream.
+fn load_bundle(dir: &Path) -> Result<Vec<Frame>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("cannot read bundle {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pgm"))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        bail!("{} contains no .pgm frames", dir.display());
+    }
+    let started = std::time::Instant::now();
+    paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let bytes =
+                std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+            let (data, width, height) = parse_pgm(&bytes)
+                .with_context(|| format!("{} is not an 8-bit binary PGM", path.display()))?;
+            Ok(Frame {
+                is_dark: visage_hw::frame::is_dark_frame(&data, 0.95),
+                data,
+                width,
+                height,
+                timestamp: started,
+                sequence: i as u32,
+                device_timestamp: std::time::Duration::ZERO,
+                exposure: None,
+                night: false,
+            })
+        })
+        .collect()
+}
+
+/// Pixels, width and height of a binary (`P5`) PGM with a maximum value of
+/// 255 or less.
+fn parse_pgm(bytes: &[u8]) -> Option<(Vec<u8>, u32, u32)> {
+    let mut pos = 0;
+    let mut fields = Vec::with_capacity(4);
+    while fields.len() < 4 {
+        // Whitespace and `#` comments separate the header fields.
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'#') {
+            if bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return None;
+        }
+        fields.push(std::str::from_utf8(&bytes[start..pos]).ok()?);
+    }
+    if fields[0] != "P5" {
+        return None;
+    }
+    let width: u32 = fields[1].parse().ok()?;
+    let height: u32 = fields[2].parse().ok()?;
+    let max: u32 = fields[3].parse().ok()?;
+    if !(1..=255).contains(&max) {
+        return None;
+    }
+    // A single whitespace byte ends the header.
+    let data = bytes.get(pos + 1..)?;
+    let len = width as usize * height as usize;
+    (len > 0 && data.len() >= len).then(|| (data[..len].to_vec(), width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_binary_pgm() {
+        let mut bytes = b"P5\n# visage\n3 2\n255\n".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(parse_pgm(&bytes), Some((vec![1, 2, 3, 4, 5, 6], 3, 2)));
+
+        assert_eq!(parse_pgm(b"P2\n3 2\n255\n1 2 3 4 5 6"), None, "ASCII PGM");
+        assert_eq!(parse_pgm(b"P5\n3 2\n65535\n"), None, "16-bit");
+        assert_eq!(parse_pgm(b"P5\n3 2\n255\n\x01\x02"), None, "truncated");
+        assert_eq!(parse_pgm(b"P5\n3"), None);
+    }
+}
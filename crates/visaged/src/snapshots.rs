@@ -0,0 +1,208 @@
+//! Failure snapshots — the frame a failed verify rested on, kept so a user
+//! can see why recognition fails (glare, an angle, a hat) and tune their
+//! enrollment.
+//!
+//! Off by default. With `VISAGE_FAILURE_SNAPSHOTS=N`, a failed default-tenant
+//! verify leaves the frame with its closest face (the middle frame of the
+//! burst when none had a face) as a binary PGM,
+//! `<dir>/<user>/<UTC time>-<reason>.pgm`, where `<dir>` is
+//! `VISAGE_FAILURE_DIR` (default `failures/` next to the database:
+//! `/var/lib/visage/failures` under the packaged unit). Each user keeps the
+//! newest `N`; snapshots older than `VISAGE_FAILURE_SNAPSHOT_DAYS` (default
+//! 7, 0 keeps them) are deleted on the next save and by the hourly
+//! retention pass. A cancelled attempt, and one that captured nothing usable
+//! (too dark), leaves no snapshot.
+//!
+//! Snapshots are face images like the ones templates are made from: the
+//! directories are created `0700` and the files `0600`, owned by the daemon's
+//! user, so reading them takes root.
+
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use visage_hw::Frame;
+
+/// Extension of a snapshot file; other files in the directory are left alone.
+const EXTENSION: &str = "pgm";
+
+/// Where and how many snapshots are kept; see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    pub dir: PathBuf,
+    /// Snapshots kept per user, newest first; 0 turns snapshots off.
+    pub keep: usize,
+    /// Days a snapshot is kept; 0 keeps it until `keep` pushes it out.
+    pub max_age_days: u64,
+}
+
+impl SnapshotPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.keep > 0
+    }
+
+    /// Write `frame` of `user`'s verify that failed with `reason`, then prune
+    /// that user's snapshots. Returns the new file.
+    pub fn save(
+        &self,
+        user: &str,
+        reason: &str,
+        frame: &Frame,
+        now: DateTime<Utc>,
+    ) -> std::io::Result<PathBuf> {
+        let dir = self.user_dir(user).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("user name {user:?} cannot be a directory name"),
+            )
+        })?;
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)?;
+        let path = dir.join(format!(
+            "{}-{reason}.{EXTENSION}",
+            now.format("%Y%m%dT%H%M%S%.3fZ")
+        ));
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.write_all(&encode_pgm(frame))?;
+        self.prune_dir(&dir, now)?;
+        Ok(path)
+    }
+
+    /// Apply the count and age limits to every user's snapshots. Returns how
+    /// many files were deleted.
+    pub fn prune_all(&self, now: DateTime<Utc>) -> std::io::Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut deleted = 0;
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                deleted += self.prune_dir(&entry.path(), now)?;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// The directory of `user`, or `None` for a name that is not a single
+    /// plain path component.
+    fn user_dir(&self, user: &str) -> Option<PathBuf> {
+        let plain = !user.is_empty() && user != "." && user != ".." && !user.contains(['/', '\0']);
+        plain.then(|| self.dir.join(user))
+    }
+
+    /// Delete the snapshots in `dir` beyond the newest `keep` or older than
+    /// `max_age_days`. File names start with the time, so they sort in age
+    /// order.
+    fn prune_dir(&self, dir: &Path, now: DateTime<Utc>) -> std::io::Result<usize> {
+        let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+            .collect();
+        snapshots.sort_unstable_by(|a, b| b.cmp(a));
+        let cutoff = (self.max_age_days > 0)
+            .then(|| now - chrono::Duration::days(self.max_age_days as i64))
+            .map(|cutoff| cutoff.format("%Y%m%dT%H%M%S").to_string());
+        let mut deleted = 0;
+        for (i, path) in snapshots.iter().enumerate() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let expired = cutoff
+                .as_deref()
+                .is_some_and(|cutoff| name.get(..cutoff.len()).is_some_and(|t| t < cutoff));
+            if i >= self.keep || expired {
+                std::fs::remove_file(path)?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// `frame` as an 8-bit binary PGM (`P5`).
+fn encode_pgm(frame: &Frame) -> Vec<u8> {
+    let header = format!("P5\n{} {}\n255\n", frame.width, frame.height);
+    [header.as_bytes(), &frame.data].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame {
+            data: vec![1, 2, 3, 4, 5, 6],
+            width: 3,
+            height: 2,
+            timestamp: std::time::Instant::now(),
+            sequence: 0,
+            device_timestamp: std::time::Duration::ZERO,
+            is_dark: false,
+            exposure: None,
+            night: false,
+        }
+    }
+
+    fn policy(keep: usize, max_age_days: u64) -> SnapshotPolicy {
+        SnapshotPolicy {
+            dir: std::env::temp_dir().join(format!("visage-failures-{}", uuid::Uuid::new_v4())),
+            keep,
+            max_age_days,
+        }
+    }
+
+    #[test]
+    fn saves_pgm_and_keeps_the_newest() {
+        let policy = policy(2, 0);
+        let start = Utc::now();
+        let mut saved = Vec::new();
+        for secs in 0..3 {
+            let now = start + chrono::Duration::seconds(secs);
+            saved.push(policy.save("alice", "no_face", &frame(), now).unwrap());
+        }
+        assert!(!saved[0].exists());
+        assert!(saved[1].exists() && saved[2].exists());
+        assert_eq!(
+            std::fs::read(&saved[2]).unwrap(),
+            b"P5\n3 2\n255\n\x01\x02\x03\x04\x05\x06"
+        );
+        assert!(saved[2].to_string_lossy().ends_with("-no_face.pgm"));
+        assert!(policy.save("../etc", "no_face", &frame(), start).is_err());
+        std::fs::remove_dir_all(&policy.dir).unwrap();
+    }
+
+    #[test]
+    fn old_snapshots_expire() {
+        let policy = policy(10, 7);
+        let now = Utc::now();
+        let old = policy
+            .save(
+                "bob",
+                "below_threshold",
+                &frame(),
+                now - chrono::Duration::days(8),
+            )
+            .unwrap();
+        let recent = policy
+            .save(
+                "bob",
+                "below_threshold",
+                &frame(),
+                now - chrono::Duration::days(1),
+            )
+            .unwrap();
+        assert!(old.exists());
+        assert_eq!(policy.prune_all(now).unwrap(), 1);
+        assert!(!old.exists() && recent.exists());
+        assert_eq!(policy.prune_all(now).unwrap(), 0);
+        std::fs::remove_dir_all(&policy.dir).unwrap();
+    }
+}
@@ -0,0 +1,258 @@
+//! Unix-socket transport — verify without a D-Bus broker.
+//!
+//! In an initramfs unlock or a greeter started before `dbus-broker`, the PAM
+//! module cannot reach the daemon over the system bus. With
+//! `VISAGE_SOCKET_PATH` set, the daemon also listens on that `AF_UNIX` socket
+//! and `pam_visage.so socket=PATH` falls back to it when the bus is down.
+//!
+//! Each connection carries one request and one reply. A frame is a 4-byte
+//! big-endian length followed by that many bytes of NUL-separated fields:
+//!
+//! ```text
+//! request:  ping                 reply: ok
+//!           verify\0USER\0SECS          ok\0MATCHED(0|1)\0REASON
+//!           verify\0USER\0SECS\0FLAG...  error\0MESSAGE
+//!           cancel\0COOKIE              ok\0CANCELLED(0|1)
+//! ```
+//!
+//! A verify may carry, in any order, `second_factor` — the verify is one
+//! half of a face-and-password login, like the D-Bus `second_factor` option;
+//! with `VISAGE_REQUIRE_SECOND_FACTOR` set, a verify without it is refused —
+//! and `cookie=COOKIE`, like the D-Bus `cookie` option, which a later
+//! `cancel` request on another connection can name to stop the verify.
+//!
+//! Only `Verify` in the default tenant and `Cancel` are offered. The caller
+//! is identified by its socket peer credentials and checked like a D-Bus
+//! caller: root may verify or cancel for anyone, other users only for
+//! themselves. `USER` is the account name's exact bytes and need not be UTF-8
+//! (see `dbus_interface::account_key`).
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+
+use crate::dbus_interface::{
+    account_key, check_second_factor, check_verify_caller, AppState, BusError, VisageService,
+    MAX_COOKIE_LEN,
+};
+use crate::store::DEFAULT_TENANT;
+
+/// Largest accepted frame; a request is a user name and a number.
+const MAX_FRAME_LEN: usize = 4096;
+/// Time a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Listen on `path` until the daemon exits. A stale socket left by a previous
+/// run is replaced; any other file at `path` is an error.
+pub async fn serve(path: &Path, state: Arc<Mutex<AppState>>) -> std::io::Result<()> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(path)?;
+    // Anyone may connect; each request is authorized by peer credentials.
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o666))?;
+    tracing::info!(path = %path.display(), "verify socket listening");
+
+    let service = Arc::new(VisageService { state });
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = Arc::clone(&service);
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &service).await {
+                tracing::debug!(error = %e, "verify socket: connection failed");
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: UnixStream, service: &VisageService) -> std::io::Result<()> {
+    let caller_uid = stream.peer_cred()?.uid();
+    respond(
+        &mut stream,
+        service,
+        &format!("uid {caller_uid}"),
+        Some(caller_uid),
+        |account| check_verify_caller(caller_uid, account),
+    )
+    .await
+}
+
+/// Read one request from `stream` and write the reply. `authorize` decides
+/// whether the peer — named `peer` in the log, with `caller_uid` in the
+/// audit log if known — may verify an account.
+/// Shared with the vsock/TCP transport in `remote`.
+pub(crate) async fn respond(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    service: &VisageService,
+    peer: &str,
+    caller_uid: Option<u32>,
+    authorize: impl FnOnce(&[u8]) -> Result<(), BusError>,
+) -> std::io::Result<()> {
+    let request = read_request(stream).await?;
+    let fields: Vec<&[u8]> = request.split(|&b| b == 0).collect();
+    let reply = match fields.as_slice() {
+        [b"ping"] => vec!["ok".to_string()],
+        [b"verify", account, secs, flags @ ..] => match VerifyFlags::parse(flags) {
+            Some(flags) => {
+                let secs = std::str::from_utf8(secs).ok().and_then(|s| s.parse().ok());
+                let outcome = verify(service, account, secs, &flags, peer, caller_uid, authorize);
+                match outcome.await {
+                    Ok((matched, reason)) => {
+                        vec!["ok".to_string(), u8::from(matched).to_string(), reason]
+                    }
+                    Err(e) => vec!["error".to_string(), e.to_string()],
+                }
+            }
+            None => vec!["error".to_string(), "malformed request".to_string()],
+        },
+        // A peer without a Unix identity could cancel anyone's verify.
+        [b"cancel", cookie] if caller_uid.is_some() => {
+            let cancelled = match std::str::from_utf8(cookie) {
+                Ok(cookie) => service.cancel_checked(cookie, caller_uid).await,
+                Err(_) => false,
+            };
+            vec!["ok".to_string(), u8::from(cancelled).to_string()]
+        }
+        _ => vec!["error".to_string(), "malformed request".to_string()],
+    };
+    write_frame(stream, reply.join("\0").as_bytes()).await
+}
+
+/// The optional fields after `SECS` in a verify request.
+#[derive(Debug, Default, PartialEq)]
+struct VerifyFlags<'a> {
+    second_factor: bool,
+    cookie: Option<&'a str>,
+}
+
+impl<'a> VerifyFlags<'a> {
+    /// `None` if a field is unknown, repeated, or a cookie is empty, too long
+    /// or not UTF-8.
+    fn parse(fields: &[&'a [u8]]) -> Option<Self> {
+        let mut flags = Self::default();
+        for field in fields {
+            match *field {
+                b"second_factor" if !flags.second_factor => flags.second_factor = true,
+                field if field.starts_with(b"cookie=") && flags.cookie.is_none() => {
+                    let cookie = std::str::from_utf8(&field[b"cookie=".len()..]).ok()?;
+                    if cookie.is_empty() || cookie.len() > MAX_COOKIE_LEN {
+                        return None;
+                    }
+                    flags.cookie = Some(cookie);
+                }
+                _ => return None,
+            }
+        }
+        Some(flags)
+    }
+}
+
+/// Authorize and run a verify request. A cookie is registered like the D-Bus
+/// `cookie` option, owned by `caller_uid`, for the length of the verify.
+async fn verify(
+    service: &VisageService,
+    account: &[u8],
+    secs: Option<u64>,
+    flags: &VerifyFlags<'_>,
+    peer: &str,
+    caller_uid: Option<u32>,
+    authorize: impl FnOnce(&[u8]) -> Result<(), BusError>,
+) -> Result<(bool, String), BusError> {
+    let user = account_key(account);
+    tracing::info!(user = %user, peer, "verify requested over socket");
+    authorize(account)?;
+    // The guard is a temporary: released before the verify runs.
+    check_second_factor(&service.state.lock().await.config, flags.second_factor)?;
+    let cancel = match flags.cookie {
+        Some(cookie) => Some(service.add_cancel(cookie, caller_uid).await?),
+        None => None,
+    };
+    let outcome = service
+        .verify_checked(&user, DEFAULT_TENANT, secs, None, cancel, None, caller_uid)
+        .await;
+    if let Some(cookie) = flags.cookie {
+        service.state.lock().await.cancels.remove(cookie);
+    }
+    Ok(outcome?.0.into_reply())
+}
+
+/// Read one frame, giving the client [`REQUEST_TIMEOUT`] to send it.
+pub(crate) async fn read_request(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> std::io::Result<Vec<u8>> {
+    tokio::time::timeout(REQUEST_TIMEOUT, read_frame(stream))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "request timed out"))?
+}
+
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "frame too large",
+        ));
+    }
+    let mut buf = vec![0; len];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+pub(crate) async fn write_frame(
+    stream: &mut (impl AsyncWrite + Unpin),
+    body: &[u8],
+) -> std::io::Result<()> {
+    stream.write_u32(body.len() as u32).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frames_round_trip_and_are_bounded() {
+        let (mut a, mut b) = tokio::io::duplex(8192);
+        write_frame(&mut a, b"verify\0alice\x003").await.unwrap();
+        assert_eq!(read_frame(&mut b).await.unwrap(), b"verify\0alice\x003");
+
+        a.write_u32(MAX_FRAME_LEN as u32 + 1).await.unwrap();
+        assert!(read_frame(&mut b).await.is_err());
+    }
+
+    #[test]
+    fn verify_flags_are_parsed() {
+        assert_eq!(VerifyFlags::parse(&[]), Some(VerifyFlags::default()));
+        assert_eq!(
+            VerifyFlags::parse(&[b"cookie=pam-1-2", b"second_factor"]),
+            Some(VerifyFlags {
+                second_factor: true,
+                cookie: Some("pam-1-2"),
+            })
+        );
+        let long = format!("cookie={}", "x".repeat(MAX_COOKIE_LEN + 1));
+        for bad in [
+            &[&b"cookie="[..]][..],
+            &[long.as_bytes()],
+            &[b"second_factor", b"second_factor"],
+            &[b"cookie=a", b"cookie=b"],
+            &[b"bogus"],
+        ] {
+            assert_eq!(VerifyFlags::parse(bad), None, "{bad:?}");
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Attempt statistics — the report behind `visage admin stats`.
+//!
+//! Every verify that reaches the engine is appended to the store's attempt
+//! history: who, which camera, the outcome (`matched` or the failure reason
+//! code), how long the caller waited, and how many frames the camera
+//! delivered and how many of them were too dark. Rate-limited calls and
+//! users with no models never touch the camera and are not recorded.
+//!
+//! The `Stats` D-Bus method (root only) aggregates the history over a period:
+//! success rate and median latency per user, dark-frame ratio per camera (a
+//! rising ratio means a failing emitter or a covered lens), and failures by
+//! reason. The history is pruned with the other per-attempt records by
+//! `VISAGE_RETAIN_HISTORY_DAYS` (see `retention`).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::store::VerifyAttempt;
+
+/// The [`VerifyAttempt::outcome`] of a match.
+pub const MATCHED: &str = "matched";
+
+/// Aggregate statistics over a set of attempts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub attempts: u64,
+    pub matched: u64,
+    /// Median latency of every attempt, in milliseconds; 0 with none.
+    pub median_latency_ms: u64,
+    /// By tenant, then user.
+    pub users: Vec<UserStats>,
+    /// By device path.
+    pub cameras: Vec<CameraStats>,
+    /// Failed attempts by reason code.
+    pub failures: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserStats {
+    pub tenant: String,
+    pub user: String,
+    pub attempts: u64,
+    pub matched: u64,
+    pub success_rate: f64,
+    pub median_latency_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CameraStats {
+    pub camera: String,
+    pub attempts: u64,
+    pub frames: u64,
+    pub dark_frames: u64,
+    /// `dark_frames / frames`; 0 when no frames were captured.
+    pub dark_ratio: f64,
+}
+
+/// Aggregate `attempts`.
+pub fn summarize(attempts: &[VerifyAttempt]) -> Report {
+    let mut users: BTreeMap<(&str, &str), Vec<&VerifyAttempt>> = BTreeMap::new();
+    let mut cameras: BTreeMap<&str, CameraStats> = BTreeMap::new();
+    let mut failures = BTreeMap::new();
+    for attempt in attempts {
+        users
+            .entry((&attempt.tenant, &attempt.user))
+            .or_default()
+            .push(attempt);
+        let camera = cameras
+            .entry(&attempt.camera)
+            .or_insert_with(|| CameraStats {
+                camera: attempt.camera.clone(),
+                attempts: 0,
+                frames: 0,
+                dark_frames: 0,
+                dark_ratio: 0.0,
+            });
+        camera.attempts += 1;
+        camera.frames += attempt.frames;
+        camera.dark_frames += attempt.dark_frames;
+        if attempt.outcome != MATCHED {
+            *failures.entry(attempt.outcome.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let users = users
+        .into_iter()
+        .map(|((tenant, user), attempts)| {
+            let matched = count_matched(attempts.iter().copied());
+            UserStats {
+                tenant: tenant.to_string(),
+                user: user.to_string(),
+                attempts: attempts.len() as u64,
+                matched,
+                success_rate: matched as f64 / attempts.len() as f64,
+                median_latency_ms: median(attempts.iter().map(|a| a.latency_ms)),
+            }
+        })
+        .collect();
+    let cameras = cameras
+        .into_values()
+        .map(|mut camera| {
+            if camera.frames > 0 {
+                camera.dark_ratio = camera.dark_frames as f64 / camera.frames as f64;
+            }
+            camera
+        })
+        .collect();
+    Report {
+        attempts: attempts.len() as u64,
+        matched: count_matched(attempts.iter()),
+        median_latency_ms: median(attempts.iter().map(|a| a.latency_ms)),
+        users,
+        cameras,
+        failures,
+    }
+}
+
+fn count_matched<'a>(attempts: impl Iterator<Item = &'a VerifyAttempt>) -> u64 {
+    attempts.filter(|a| a.outcome == MATCHED).count() as u64
+}
+
+/// The median of `values` (the lower of the middle two for an even count);
+/// 0 when there are none.
+fn median(values: impl Iterator<Item = u64>) -> u64 {
+    let mut values: Vec<u64> = values.collect();
+    values.sort_unstable();
+    values
+        .get(values.len().saturating_sub(1) / 2)
+        .copied()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(user: &str, camera: &str, outcome: &str, latency_ms: u64) -> VerifyAttempt {
+        VerifyAttempt {
+            at: "2026-03-01T00:00:00+00:00".to_string(),
+            tenant: String::new(),
+            user: user.to_string(),
+            camera: camera.to_string(),
+            outcome: outcome.to_string(),
+            latency_ms,
+            frames: 4,
+            dark_frames: if outcome == "too_dark" { 4 } else { 1 },
+        }
+    }
+
+    #[test]
+    fn attempts_aggregate_by_user_camera_and_reason() {
+        let report = summarize(&[
+            attempt("alice", "/dev/video2", MATCHED, 300),
+            attempt("alice", "/dev/video2", "below_threshold", 900),
+            attempt("alice", "/dev/video2", MATCHED, 400),
+            attempt("bob", "/dev/video0", "too_dark", 2000),
+        ]);
+        assert_eq!((report.attempts, report.matched), (4, 2));
+        assert_eq!(report.median_latency_ms, 400);
+
+        let alice = &report.users[0];
+        assert_eq!(
+            (alice.user.as_str(), alice.attempts, alice.matched),
+            ("alice", 3, 2)
+        );
+        assert!((alice.success_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(alice.median_latency_ms, 400);
+        assert_eq!(report.users[1].success_rate, 0.0);
+
+        let video0 = &report.cameras[0];
+        assert_eq!(
+            (video0.camera.as_str(), video0.dark_ratio),
+            ("/dev/video0", 1.0)
+        );
+        assert_eq!(report.cameras[1].dark_ratio, 0.25);
+
+        assert_eq!(
+            report.failures.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("below_threshold".to_string(), 1),
+                ("too_dark".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn no_attempts_report_zeros() {
+        let report = summarize(&[]);
+        assert_eq!((report.attempts, report.median_latency_ms), (0, 0));
+        assert!(report.users.is_empty() && report.cameras.is_empty());
+    }
+}
@@ -1,7 +1,10 @@
 use std::path::Path;
 use thiserror::Error;
 use tokio_rusqlite::Connection;
-use visage_core::{Embedding, FaceModel};
+use visage_core::{Embedding, ErrorKind, FaceModel, VisageError};
+
+use crate::consent::Consent;
+use crate::migrations;
 
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -13,6 +16,12 @@ use rand::RngCore;
 const EMBEDDING_DIM: usize = 512;
 const EMBEDDING_BYTE_LEN: usize = EMBEDDING_DIM * 4;
 
+/// Tenant of models enrolled through the tenant-less D-Bus methods.
+pub const DEFAULT_TENANT: &str = "";
+
+/// Pose label of models not captured in a guided enrollment pose.
+pub const DEFAULT_POSE_LABEL: &str = "frontal";
+
 #[derive(Error, Debug)]
 pub enum StoreError {
     #[error("database error: {0}")]
@@ -31,6 +40,24 @@ pub enum StoreError {
     InvalidEmbeddingValue,
     #[error("encryption key I/O error: {0}")]
     KeyIo(#[source] std::io::Error),
+    #[error("database schema version {found} is newer than this visaged supports ({supported}); was it written by a newer release?")]
+    SchemaTooNew { found: u32, supported: u32 },
+    #[error("model {id} is not one of the user's {from} models")]
+    NotMigratable { id: String, from: String },
+}
+
+impl StoreError {
+    /// The shared [`ErrorKind`] of this error: always
+    /// [`ErrorKind::Storage`].
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Storage
+    }
+}
+
+impl From<StoreError> for VisageError {
+    fn from(e: StoreError) -> Self {
+        VisageError::wrap(e.kind(), e)
+    }
 }
 
 /// SQLite-backed face model storage with AES-256-GCM encryption.
@@ -41,6 +68,9 @@ pub enum StoreError {
 ///
 /// Legacy plaintext blobs (2048 bytes) are accepted transparently — they are
 /// migrated to encrypted format on the next enrollment.
+///
+/// Every model belongs to a tenant ([`DEFAULT_TENANT`] unless enrolled into a
+/// named one); all lookups and removals are scoped to a single tenant.
 #[derive(Clone)]
 pub struct FaceModelStore {
     conn: Connection,
@@ -48,7 +78,8 @@ pub struct FaceModelStore {
 }
 
 impl FaceModelStore {
-    /// Open (or create) the database at the given path and run migrations.
+    /// Open (or create) the database at the given path and bring its schema
+    /// up to date (see `migrations`).
     pub async fn open(db_path: &Path) -> Result<Self, StoreError> {
         // Ensure parent directory exists
         if let Some(parent) = db_path.parent() {
@@ -68,79 +99,184 @@ impl FaceModelStore {
 
         let conn = Connection::open(db_path).await?;
 
+        // Pragmas are per connection, and outside any migration transaction.
         conn.call(|conn| {
             conn.execute_batch(
                 "PRAGMA journal_mode = WAL;
-                 PRAGMA foreign_keys = ON;
-                 CREATE TABLE IF NOT EXISTS faces (
-                     id TEXT PRIMARY KEY,
-                     user TEXT NOT NULL,
-                     label TEXT NOT NULL,
-                     embedding BLOB NOT NULL,
-                     model_version TEXT NOT NULL,
-                     quality_score REAL NOT NULL DEFAULT 0.0,
-                     pose_label TEXT NOT NULL DEFAULT 'frontal',
-                     created_at TEXT NOT NULL
-                 );
-                 CREATE INDEX IF NOT EXISTS idx_faces_user ON faces(user);",
+                 PRAGMA foreign_keys = ON;",
             )?;
             Ok(())
         })
         .await?;
+        let from = conn.call(|conn| Ok(migrations::migrate(conn))).await??;
+        if from < migrations::latest() {
+            tracing::info!(
+                from,
+                to = migrations::latest(),
+                path = %db_path.display(),
+                "face database schema upgraded"
+            );
+        }
 
         Ok(Self { conn, enc_key })
     }
 
-    /// Insert a new face model. Returns the generated UUID.
+    /// Insert a new face model into `tenant`, without a consent record.
+    /// Returns the generated UUID.
+    #[cfg(test)]
     pub async fn insert(
         &self,
+        tenant: &str,
         user: &str,
         label: &str,
         embedding: &Embedding,
         quality_score: f32,
     ) -> Result<String, StoreError> {
-        let id = uuid::Uuid::new_v4().to_string();
-        let model_version = embedding
-            .model_version
-            .clone()
-            .unwrap_or_else(|| "unknown".to_string());
+        let model = NewModel {
+            label: label.to_string(),
+            embedding: embedding.clone(),
+            quality_score,
+            pose_label: DEFAULT_POSE_LABEL.to_string(),
+            history: None,
+        };
+        let mut batch = self
+            .insert_batch(tenant, user, &[model], None, false)
+            .await?;
+        Ok(batch.inserted.remove(0))
+    }
+
+    /// Insert `models` for `user` in `tenant`, each with a copy of `consent`,
+    /// in one transaction: either every model is stored or none is. With
+    /// `replace`, the user's existing models (and their consent, near-miss
+    /// and purge records, and kept probes) are deleted in the same
+    /// transaction, so a crash can never leave a half-replaced gallery.
+    pub async fn insert_batch(
+        &self,
+        tenant: &str,
+        user: &str,
+        models: &[NewModel],
+        consent: Option<&Consent>,
+        replace: bool,
+    ) -> Result<Batch, StoreError> {
         let created_at = chrono::Utc::now().to_rfc3339();
 
         // Encrypt before entering the SQLite closure
-        validate_embedding_values(&embedding.values)?;
-        let blob = self.encrypt_embedding(&embedding.values)?;
+        let mut rows = Vec::with_capacity(models.len());
+        for model in models {
+            validate_embedding_values(&model.embedding.values)?;
+            let blob = self.encrypt_embedding(&model.embedding.values)?;
+            let model_version = model
+                .embedding
+                .model_version
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string());
+            rows.push((
+                uuid::Uuid::new_v4().to_string(),
+                model.label.clone(),
+                blob,
+                model_version,
+                model.quality_score,
+                model.pose_label.clone(),
+                model.history.clone(),
+            ));
+        }
 
-        let id_clone = id.clone();
+        let inserted: Vec<String> = rows.iter().map(|row| row.0.clone()).collect();
+        let tenant = tenant.to_string();
         let user = user.to_string();
-        let label = label.to_string();
+        let consent = consent.cloned();
 
-        self.conn
+        let replaced = self
+            .conn
             .call(move |conn| {
-                conn.execute(
-                    "INSERT INTO faces (id, user, label, embedding, model_version, quality_score, pose_label, created_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'frontal', ?7)",
-                    rusqlite::params![id_clone, user, label, blob, model_version, quality_score, created_at],
-                )?;
-                Ok(())
+                let tx = conn.transaction()?;
+                let mut replaced = Vec::new();
+                if replace {
+                    let mut stmt =
+                        tx.prepare("SELECT id FROM faces WHERE tenant = ?1 AND user = ?2")?;
+                    replaced = stmt
+                        .query_map([&tenant, &user], |row| row.get(0))?
+                        .collect::<Result<Vec<String>, _>>()?;
+                    drop(stmt);
+                    // Consents, near misses and purge notices cascade.
+                    tx.execute(
+                        "DELETE FROM faces WHERE tenant = ?1 AND user = ?2",
+                        [&tenant, &user],
+                    )?;
+                    tx.execute(
+                        "DELETE FROM probes WHERE tenant = ?1 AND user = ?2",
+                        [&tenant, &user],
+                    )?;
+                }
+                for (id, label, blob, model_version, quality_score, pose_label, history) in &rows {
+                    let (created_at, threshold_offset, last_matched_at) = match history {
+                        Some(h) => (&h.created_at, h.threshold_offset, h.last_matched_at.as_ref()),
+                        None => (&created_at, 0.0, None),
+                    };
+                    tx.execute(
+                        "INSERT INTO faces (id, user, label, embedding, model_version, quality_score, pose_label, created_at, tenant, threshold_offset, last_matched_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                        rusqlite::params![id, user, label, blob, model_version, quality_score, pose_label, created_at, tenant, threshold_offset, last_matched_at],
+                    )?;
+                    if let Some(consent) = &consent {
+                        tx.execute(
+                            "INSERT INTO consents (model_id, recorded_at, method, caller_uid, caller_user, login_uid, login_user, command)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                            rusqlite::params![
+                                id,
+                                consent.recorded_at,
+                                consent.method,
+                                consent.caller_uid,
+                                consent.caller_user,
+                                consent.login_uid,
+                                consent.login_user,
+                                consent.command
+                            ],
+                        )?;
+                    }
+                }
+                tx.commit()?;
+                Ok(replaced)
             })
             .await?;
 
-        Ok(id)
+        Ok(Batch { inserted, replaced })
     }
 
-    /// Get all face models for a user (the gallery for verification).
-    pub async fn get_gallery_for_user(&self, user: &str) -> Result<Vec<FaceModel>, StoreError> {
-        let user = user.to_string();
+    /// Get all face models for a user in `tenant` (the gallery for verification).
+    pub async fn get_gallery_for_user(
+        &self,
+        tenant: &str,
+        user: &str,
+    ) -> Result<Vec<FaceModel>, StoreError> {
+        self.fetch_gallery(tenant, Some(user.to_string())).await
+    }
+
+    /// Get every face model enrolled in `tenant` across all its users (the
+    /// gallery for identification). Each model carries its owner in
+    /// `FaceModel::user`.
+    pub async fn get_gallery_all(&self, tenant: &str) -> Result<Vec<FaceModel>, StoreError> {
+        self.fetch_gallery(tenant, None).await
+    }
 
+    /// Fetch and decrypt a tenant's face models, optionally scoped to one user.
+    async fn fetch_gallery(
+        &self,
+        tenant: &str,
+        user: Option<String>,
+    ) -> Result<Vec<FaceModel>, StoreError> {
+        let tenant = tenant.to_string();
+        let tenant_clone = tenant.clone();
         // Fetch raw rows from SQLite; decrypt outside the blocking closure
-        let rows: Vec<(String, String, String, Vec<u8>, String, String)> = self
+        #[allow(clippy::type_complexity)]
+        let rows: Vec<(String, String, String, Vec<u8>, String, String, f64)> = self
             .conn
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT id, user, label, embedding, model_version, created_at
-                     FROM faces WHERE user = ?1",
+                    "SELECT id, user, label, embedding, model_version, created_at, threshold_offset
+                     FROM faces WHERE tenant = ?1 AND (?2 IS NULL OR user = ?2)",
                 )?;
-                let rows = stmt.query_map([&user], |row| {
+                let rows = stmt.query_map(rusqlite::params![tenant_clone, user], |row| {
                     Ok((
                         row.get::<_, String>(0)?,
                         row.get::<_, String>(1)?,
@@ -148,6 +284,7 @@ impl FaceModelStore {
                         row.get::<_, Vec<u8>>(3)?,
                         row.get::<_, String>(4)?,
                         row.get::<_, String>(5)?,
+                        row.get::<_, f64>(6)?,
                     ))
                 })?;
                 Ok(rows.collect::<Result<Vec<_>, _>>()?)
@@ -155,7 +292,7 @@ impl FaceModelStore {
             .await?;
 
         let mut models = Vec::with_capacity(rows.len());
-        for (id, user, label, blob, model_version, created_at) in rows {
+        for (id, user, label, blob, model_version, created_at, threshold_offset) in rows {
             let values = self.decrypt_embedding(&blob)?;
             models.push(FaceModel {
                 id,
@@ -166,27 +303,68 @@ impl FaceModelStore {
                     model_version: Some(model_version),
                 },
                 created_at,
+                tenant: tenant.clone(),
+                threshold_offset: threshold_offset as f32,
             });
         }
         Ok(models)
     }
 
-    /// List face models for a user (metadata only, no embeddings).
-    pub async fn list_by_user(&self, user: &str) -> Result<Vec<ModelInfo>, StoreError> {
+    /// List face models for a user in `tenant` (metadata only, no embeddings).
+    pub async fn list_by_user(
+        &self,
+        tenant: &str,
+        user: &str,
+    ) -> Result<Vec<ModelInfo>, StoreError> {
+        let tenant = tenant.to_string();
         let user = user.to_string();
         self.conn
             .call(move |conn| {
                 let mut stmt = conn.prepare(
-                    "SELECT id, label, model_version, quality_score, created_at
-                     FROM faces WHERE user = ?1 ORDER BY created_at",
+                    "SELECT f.id, f.label, f.model_version, f.quality_score, f.created_at,
+                            n.count, n.similarity_sum, n.similarity_max, n.last_at,
+                            f.threshold_offset,
+                            c.recorded_at, c.method, c.caller_uid, c.caller_user,
+                            c.login_uid, c.login_user, c.command, f.pose_label,
+                            f.last_matched_at
+                     FROM faces f
+                     LEFT JOIN near_misses n ON n.model_id = f.id
+                     LEFT JOIN consents c ON c.model_id = f.id
+                     WHERE f.tenant = ?1 AND f.user = ?2 ORDER BY f.created_at",
                 )?;
-                let rows = stmt.query_map([&user], |row| {
+                let rows = stmt.query_map([&tenant, &user], |row| {
+                    let near_misses: Option<u64> = row.get(5)?;
+                    let similarity_sum: Option<f64> = row.get(6)?;
+                    let consent = match row.get::<_, Option<String>>(10)? {
+                        Some(recorded_at) => Some(Consent {
+                            recorded_at,
+                            method: row.get(11)?,
+                            caller_uid: row.get(12)?,
+                            caller_user: row.get(13)?,
+                            login_uid: row.get(14)?,
+                            login_user: row.get(15)?,
+                            command: row.get(16)?,
+                        }),
+                        None => None,
+                    };
                     Ok(ModelInfo {
                         id: row.get(0)?,
                         label: row.get(1)?,
                         model_version: row.get(2)?,
                         quality_score: row.get(3)?,
                         created_at: row.get(4)?,
+                        pose_label: row.get(17)?,
+                        last_matched_at: row.get(18)?,
+                        near_misses: near_misses.unwrap_or(0),
+                        near_miss_mean_similarity: similarity_sum
+                            .zip(near_misses)
+                            .map(|(sum, count)| sum / count.max(1) as f64),
+                        near_miss_max_similarity: row.get(7)?,
+                        last_near_miss_at: row.get(8)?,
+                        threshold_offset: row.get(9)?,
+                        consistency: None,
+                        outlier: false,
+                        consent,
                     })
                 })?;
                 Ok(rows.collect::<Result<Vec<_>, _>>()?)
@@ -195,15 +373,55 @@ impl FaceModelStore {
             .map_err(StoreError::from)
     }
 
-    /// Remove a face model by ID, scoped to a user for cross-user protection.
-    pub async fn remove(&self, user: &str, model_id: &str) -> Result<bool, StoreError> {
+    /// Remove a face model by ID, scoped to a tenant and user for cross-user
+    /// and cross-tenant protection. Removing the user's last model also
+    /// deletes their kept probes.
+    pub async fn remove(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+    ) -> Result<bool, StoreError> {
+        let tenant = tenant.to_string();
+        let user = user.to_string();
+        let model_id = model_id.to_string();
+        self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let affected = tx.execute(
+                    "DELETE FROM faces WHERE id = ?1 AND user = ?2 AND tenant = ?3",
+                    [&model_id, &user, &tenant],
+                )?;
+                tx.execute(
+                    "DELETE FROM probes WHERE tenant = ?1 AND user = ?2
+                     AND NOT EXISTS (SELECT 1 FROM faces WHERE tenant = ?1 AND user = ?2)",
+                    [&tenant, &user],
+                )?;
+                tx.commit()?;
+                Ok(affected > 0)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Set the threshold offset of a model, scoped to a tenant and user like
+    /// [`remove`](Self::remove). Returns whether the model exists.
+    pub async fn set_threshold_offset(
+        &self,
+        tenant: &str,
+        user: &str,
+        model_id: &str,
+        offset: f32,
+    ) -> Result<bool, StoreError> {
+        let tenant = tenant.to_string();
         let user = user.to_string();
         let model_id = model_id.to_string();
         self.conn
             .call(move |conn| {
                 let affected = conn.execute(
-                    "DELETE FROM faces WHERE id = ?1 AND user = ?2",
-                    [&model_id, &user],
+                    "UPDATE faces SET threshold_offset = ?1
+                     WHERE id = ?2 AND user = ?3 AND tenant = ?4",
+                    rusqlite::params![f64::from(offset), model_id, user, tenant],
                 )?;
                 Ok(affected > 0)
             })
@@ -211,6 +429,469 @@ impl FaceModelStore {
             .map_err(StoreError::from)
     }
 
+    /// Store `user`'s keyring secret (default tenant), encrypted, replacing
+    /// any earlier one. See [`crate::authtok`].
+    pub async fn set_authtok(&self, user: &str, secret: &str) -> Result<(), StoreError> {
+        let sealed = self.seal(secret.as_bytes())?;
+        let user = user.to_string();
+        let updated_at = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO authtoks (user, secret, updated_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(user) DO UPDATE SET secret = ?2, updated_at = ?3",
+                    rusqlite::params![user, sealed, updated_at],
+                )?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Delete `user`'s keyring secret. Returns `false` if none was stored.
+    pub async fn clear_authtok(&self, user: &str) -> Result<bool, StoreError> {
+        let user = user.to_string();
+        self.conn
+            .call(move |conn| Ok(conn.execute("DELETE FROM authtoks WHERE user = ?1", [user])? > 0))
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// `user`'s decrypted keyring secret, if one is stored.
+    pub async fn authtok(&self, user: &str) -> Result<Option<String>, StoreError> {
+        let user = user.to_string();
+        let sealed: Option<Vec<u8>> = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT secret FROM authtoks WHERE user = ?1")?;
+                let mut rows = stmt.query([user])?;
+                Ok(match rows.next()? {
+                    Some(row) => Some(row.get(0)?),
+                    None => None,
+                })
+            })
+            .await?;
+        sealed
+            .map(|blob| {
+                let plaintext = self.unseal(&blob)?;
+                String::from_utf8(plaintext).map_err(|_| StoreError::DecryptionFailed)
+            })
+            .transpose()
+    }
+
+    /// Count a verify whose best match was `model_id` at `similarity`, but
+    /// below the threshold. Unknown model IDs (removed meanwhile) are ignored.
+    pub async fn record_near_miss(
+        &self,
+        model_id: &str,
+        similarity: f32,
+    ) -> Result<(), StoreError> {
+        let model_id = model_id.to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO near_misses (model_id, count, similarity_sum, similarity_max, last_at)
+                     SELECT id, 1, ?2, ?2, ?3 FROM faces WHERE id = ?1
+                     ON CONFLICT(model_id) DO UPDATE SET
+                         count = count + 1,
+                         similarity_sum = similarity_sum + excluded.similarity_sum,
+                         similarity_max = MAX(similarity_max, excluded.similarity_max),
+                         last_at = excluded.last_at",
+                    rusqlite::params![model_id, f64::from(similarity), now],
+                )?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Note that a verify or identification just matched `model_id`.
+    pub async fn record_match(&self, model_id: &str) -> Result<(), StoreError> {
+        let model_id = model_id.to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE faces SET last_matched_at = ?2 WHERE id = ?1",
+                    rusqlite::params![model_id, now],
+                )?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Delete near-miss records last updated before `cutoff` (RFC 3339).
+    /// Returns how many were deleted.
+    pub async fn prune_near_misses(&self, cutoff: &str) -> Result<usize, StoreError> {
+        let cutoff = cutoff.to_string();
+        self.conn
+            .call(move |conn| {
+                Ok(conn.execute("DELETE FROM near_misses WHERE last_at < ?1", [cutoff])?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Append one verify attempt to the history behind `visage admin stats`.
+    pub async fn record_attempt(&self, attempt: VerifyAttempt) -> Result<(), StoreError> {
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO verify_attempts
+                         (at, tenant, user, camera, outcome, latency_ms, frames, dark_frames)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    rusqlite::params![
+                        attempt.at,
+                        attempt.tenant,
+                        attempt.user,
+                        attempt.camera,
+                        attempt.outcome,
+                        attempt.latency_ms,
+                        attempt.frames,
+                        attempt.dark_frames,
+                    ],
+                )?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Every verify attempt, in every tenant, since `cutoff` (RFC 3339),
+    /// oldest first.
+    pub async fn attempts_since(&self, cutoff: &str) -> Result<Vec<VerifyAttempt>, StoreError> {
+        let cutoff = cutoff.to_string();
+        self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT at, tenant, user, camera, outcome, latency_ms, frames, dark_frames
+                     FROM verify_attempts WHERE at >= ?1 ORDER BY at",
+                )?;
+                let rows = stmt.query_map([&cutoff], |row| {
+                    Ok(VerifyAttempt {
+                        at: row.get(0)?,
+                        tenant: row.get(1)?,
+                        user: row.get(2)?,
+                        camera: row.get(3)?,
+                        outcome: row.get(4)?,
+                        latency_ms: row.get(5)?,
+                        frames: row.get(6)?,
+                        dark_frames: row.get(7)?,
+                    })
+                })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Delete verify attempts made before `cutoff` (RFC 3339). Returns how
+    /// many were deleted.
+    pub async fn prune_attempts(&self, cutoff: &str) -> Result<usize, StoreError> {
+        let cutoff = cutoff.to_string();
+        self.conn
+            .call(move |conn| {
+                Ok(conn.execute("DELETE FROM verify_attempts WHERE at < ?1", [cutoff])?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Keep the embedding of a verify of `user` in `tenant`, encrypted like
+    /// the templates, with its best `similarity` and whether it `matched`.
+    /// Only the newest `keep` probes of the user are kept.
+    pub async fn record_probe(
+        &self,
+        tenant: &str,
+        user: &str,
+        embedding: &Embedding,
+        similarity: f32,
+        matched: bool,
+        keep: usize,
+    ) -> Result<(), StoreError> {
+        let blob = self.encrypt_embedding(&embedding.values)?;
+        let (tenant, user) = (tenant.to_string(), user.to_string());
+        let now = chrono::Utc::now().to_rfc3339();
+        let keep = i64::try_from(keep).unwrap_or(i64::MAX);
+        self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "INSERT INTO probes (tenant, user, embedding, similarity, matched, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    rusqlite::params![tenant, user, blob, f64::from(similarity), matched, now],
+                )?;
+                tx.execute(
+                    "DELETE FROM probes WHERE tenant = ?1 AND user = ?2 AND id NOT IN (
+                         SELECT id FROM probes WHERE tenant = ?1 AND user = ?2
+                         ORDER BY id DESC LIMIT ?3
+                     )",
+                    rusqlite::params![tenant, user, keep],
+                )?;
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// The kept probes of `user` in `tenant`, oldest first.
+    pub async fn probes_for_user(
+        &self,
+        tenant: &str,
+        user: &str,
+    ) -> Result<Vec<Probe>, StoreError> {
+        let (tenant, user) = (tenant.to_string(), user.to_string());
+        #[allow(clippy::type_complexity)]
+        let rows: Vec<(i64, Vec<u8>, f64, bool, String)> = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, embedding, similarity, matched, created_at
+                     FROM probes WHERE tenant = ?1 AND user = ?2 ORDER BY id",
+                )?;
+                let rows = stmt.query_map(rusqlite::params![tenant, user], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            })
+            .await?;
+        rows.into_iter()
+            .map(|(id, blob, similarity, matched, created_at)| {
+                Ok(Probe {
+                    id,
+                    embedding: Embedding {
+                        values: self.decrypt_embedding(&blob)?,
+                        model_version: None,
+                    },
+                    similarity: similarity as f32,
+                    matched,
+                    created_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Delete probes kept before `cutoff` (RFC 3339). Returns how many were
+    /// deleted.
+    pub async fn prune_probes(&self, cutoff: &str) -> Result<usize, StoreError> {
+        let cutoff = cutoff.to_string();
+        self.conn
+            .call(move |conn| {
+                Ok(conn.execute("DELETE FROM probes WHERE created_at < ?1", [cutoff])?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Every model, in every tenant, not matched since `cutoff` (RFC 3339);
+    /// a model never matched counts from its enrollment.
+    pub async fn unused_models(&self, cutoff: &str) -> Result<Vec<UnusedModel>, StoreError> {
+        let cutoff = cutoff.to_string();
+        self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT f.id, f.tenant, f.user, f.label,
+                            COALESCE(f.last_matched_at, f.created_at) AS last_used,
+                            p.notified_at
+                     FROM faces f
+                     LEFT JOIN purge_notices p ON p.model_id = f.id
+                     WHERE COALESCE(f.last_matched_at, f.created_at) < ?1
+                     ORDER BY last_used",
+                )?;
+                let rows = stmt.query_map([&cutoff], |row| {
+                    Ok(UnusedModel {
+                        id: row.get(0)?,
+                        tenant: row.get(1)?,
+                        user: row.get(2)?,
+                        label: row.get(3)?,
+                        last_used: row.get(4)?,
+                        notified_at: row.get(5)?,
+                    })
+                })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Record that the owner of `model_id` was told it will be purged.
+    pub async fn record_purge_notice(&self, model_id: &str) -> Result<(), StoreError> {
+        let model_id = model_id.to_string();
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO purge_notices (model_id, notified_at) VALUES (?1, ?2)",
+                    rusqlite::params![model_id, now],
+                )?;
+                Ok(())
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// Withdraw the purge notices of models matched since they were sent.
+    pub async fn clear_used_purge_notices(&self) -> Result<usize, StoreError> {
+        self.conn
+            .call(|conn| {
+                Ok(conn.execute(
+                    "DELETE FROM purge_notices WHERE model_id IN (
+                         SELECT f.id FROM faces f JOIN purge_notices p ON p.model_id = f.id
+                         WHERE f.last_matched_at > p.notified_at
+                     )",
+                    [],
+                )?)
+            })
+            .await
+            .map_err(StoreError::from)
+    }
+
+    /// `user`'s models in `tenant` with their embeddings and history, for an
+    /// export bundle (see `bundle`); oldest first.
+    pub async fn export_models(
+        &self,
+        tenant: &str,
+        user: &str,
+    ) -> Result<Vec<ExportedModel>, StoreError> {
+        let tenant = tenant.to_string();
+        let user = user.to_string();
+        #[allow(clippy::type_complexity)]
+        let rows: Vec<(
+            String,
+            String,
+            Vec<u8>,
+            f64,
+            String,
+            f64,
+            String,
+            Option<String>,
+        )> = self
+            .conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT label, model_version, embedding, quality_score, pose_label,
+                            threshold_offset, created_at, last_matched_at
+                     FROM faces WHERE tenant = ?1 AND user = ?2
+                     ORDER BY created_at",
+                )?;
+                let rows = stmt.query_map([&tenant, &user], |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                        row.get(6)?,
+                        row.get(7)?,
+                    ))
+                })?;
+                Ok(rows.collect::<Result<Vec<_>, _>>()?)
+            })
+            .await?;
+
+        rows.into_iter()
+            .map(
+                |(
+                    label,
+                    model_version,
+                    blob,
+                    quality_score,
+                    pose_label,
+                    threshold_offset,
+                    created_at,
+                    last_matched_at,
+                )| {
+                    Ok(ExportedModel {
+                        label,
+                        model_version,
+                        embedding: self.decrypt_embedding(&blob)?,
+                        quality_score: quality_score as f32,
+                        pose_label,
+                        threshold_offset: threshold_offset as f32,
+                        created_at,
+                        last_matched_at,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Move `user`'s models in `tenant` from model version `from` to `to`,
+    /// in one transaction. Each `(model_id, embedding)` in `replacements`
+    /// swaps that model's template for one computed by the new model; every
+    /// other `from` model is only re-tagged, for a model whose embeddings did
+    /// not change. Labels, poses, threshold offsets and consent records are
+    /// kept; the replaced templates' near misses and the user's kept probes,
+    /// which belong to the old embeddings, are deleted. A replacement that
+    /// names anything but one of the user's `from` models fails the whole
+    /// migration with [`StoreError::NotMigratable`]. Returns how many models
+    /// moved.
+    #[allow(dead_code)] // No caller until the recognizer model changes.
+    pub async fn migrate_model_version(
+        &self,
+        tenant: &str,
+        user: &str,
+        from: &str,
+        to: &str,
+        replacements: &[(String, Embedding)],
+    ) -> Result<usize, StoreError> {
+        let mut rows = Vec::with_capacity(replacements.len());
+        for (id, embedding) in replacements {
+            validate_embedding_values(&embedding.values)?;
+            rows.push((id.clone(), self.encrypt_embedding(&embedding.values)?));
+        }
+
+        let tenant = tenant.to_string();
+        let user = user.to_string();
+        let (old, new) = (from.to_string(), to.to_string());
+        let migrated = self
+            .conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                for (id, blob) in &rows {
+                    let affected = tx.execute(
+                        "UPDATE faces SET embedding = ?1, model_version = ?2
+                         WHERE id = ?3 AND tenant = ?4 AND user = ?5 AND model_version = ?6",
+                        rusqlite::params![blob, new, id, tenant, user, old],
+                    )?;
+                    if affected == 0 {
+                        // Dropping `tx` rolls back the replacements made so far.
+                        return Ok(Err(id.clone()));
+                    }
+                    tx.execute("DELETE FROM near_misses WHERE model_id = ?1", [id])?;
+                }
+                let retagged = tx.execute(
+                    "UPDATE faces SET model_version = ?1
+                     WHERE tenant = ?2 AND user = ?3 AND model_version = ?4",
+                    [&new, &tenant, &user, &old],
+                )?;
+                let migrated = rows.len() + retagged;
+                if migrated > 0 {
+                    tx.execute(
+                        "DELETE FROM probes WHERE tenant = ?1 AND user = ?2",
+                        [&tenant, &user],
+                    )?;
+                }
+                tx.commit()?;
+                Ok(Ok(migrated))
+            })
+            .await?;
+        migrated.map_err(|id| StoreError::NotMigratable {
+            id,
+            from: from.to_string(),
+        })
+    }
+
     /// Count total enrolled face models across all users.
     pub async fn count_all(&self) -> Result<u64, StoreError> {
         self.conn
@@ -223,6 +904,14 @@ impl FaceModelStore {
             .map_err(StoreError::from)
     }
 
+    /// The schema version of the database; see `migrations`.
+    pub async fn schema_version(&self) -> Result<u32, StoreError> {
+        self.conn
+            .call(|conn| Ok(migrations::current(conn)?))
+            .await
+            .map_err(StoreError::from)
+    }
+
     // ── Encryption helpers ────────────────────────────────────────────────────
 
     /// Encrypt embedding values with AES-256-GCM.
@@ -230,8 +919,11 @@ impl FaceModelStore {
     /// Output: 12-byte random nonce || ciphertext || 16-byte GCM tag.
     fn encrypt_embedding(&self, values: &[f32]) -> Result<Vec<u8>, StoreError> {
         validate_embedding_values(values)?;
-        let plaintext = embedding_to_bytes(values);
+        self.seal(&embedding_to_bytes(values))
+    }
 
+    /// Encrypt `plaintext` as a 12-byte nonce + ciphertext + GCM tag.
+    fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, StoreError> {
         let mut nonce_bytes = [0u8; 12];
         OsRng.fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
@@ -240,7 +932,7 @@ impl FaceModelStore {
         let cipher = Aes256Gcm::new(key);
 
         let ciphertext = cipher
-            .encrypt(nonce, plaintext.as_slice())
+            .encrypt(nonce, plaintext)
             .map_err(|_| StoreError::EncryptionFailed)?;
 
         let mut blob = Vec::with_capacity(12 + ciphertext.len());
@@ -254,13 +946,18 @@ impl FaceModelStore {
     /// Accepts the legacy plaintext format (512 × 4 = 2048 bytes) and the
     /// current encrypted format (12-byte nonce + ciphertext + 16-byte GCM tag).
     fn decrypt_embedding(&self, blob: &[u8]) -> Result<Vec<f32>, StoreError> {
-        const NONCE_LEN: usize = 12;
-
         if blob.len() == EMBEDDING_BYTE_LEN {
             // Legacy plaintext — accept transparently; re-enrolled next time
             return bytes_to_embedding_strict(blob);
         }
 
+        bytes_to_embedding_strict(&self.unseal(blob)?)
+    }
+
+    /// Decrypt a blob written by [`seal`](Self::seal).
+    fn unseal(&self, blob: &[u8]) -> Result<Vec<u8>, StoreError> {
+        const NONCE_LEN: usize = 12;
+
         if blob.len() <= NONCE_LEN {
             return Err(StoreError::InvalidBlob(blob.len()));
         }
@@ -270,11 +967,9 @@ impl FaceModelStore {
         let key = Key::<Aes256Gcm>::from_slice(&self.enc_key);
         let cipher = Aes256Gcm::new(key);
 
-        let plaintext = cipher
+        cipher
             .decrypt(nonce, ciphertext)
-            .map_err(|_| StoreError::DecryptionFailed)?;
-
-        bytes_to_embedding_strict(&plaintext)
+            .map_err(|_| StoreError::DecryptionFailed)
     }
 }
 
@@ -363,14 +1058,150 @@ fn validate_embedding_values(values: &[f32]) -> Result<(), StoreError> {
 
 // ── Public types ──────────────────────────────────────────────────────────────
 
-/// Metadata about an enrolled face model (no embedding data).
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct ModelInfo {
-    pub id: String,
+/// One model of a [`FaceModelStore::insert_batch`].
+#[derive(Debug, Clone)]
+pub struct NewModel {
+    pub label: String,
+    pub embedding: Embedding,
+    pub quality_score: f32,
+    /// `frontal`, or the guided enrollment pose.
+    pub pose_label: String,
+    /// Enrollment time, threshold offset and last match carried over from
+    /// another store (an imported bundle); `None` for a new enrollment.
+    pub history: Option<History>,
+}
+
+/// The history of an imported model; see [`NewModel::history`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    /// RFC 3339.
+    pub created_at: String,
+    pub threshold_offset: f32,
+    /// RFC 3339.
+    pub last_matched_at: Option<String>,
+}
+
+/// A model as an export bundle carries it: the decrypted embedding and what
+/// the store knows about the model, without its id. See `bundle`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExportedModel {
     pub label: String,
     pub model_version: String,
-    pub quality_score: f64,
+    pub embedding: Vec<f32>,
+    pub quality_score: f32,
+    pub pose_label: String,
+    pub threshold_offset: f32,
+    /// RFC 3339.
     pub created_at: String,
+    /// RFC 3339; `None` if never matched.
+    pub last_matched_at: Option<String>,
+}
+
+impl From<ExportedModel> for NewModel {
+    fn from(model: ExportedModel) -> Self {
+        NewModel {
+            label: model.label,
+            embedding: Embedding {
+                values: model.embedding,
+                model_version: Some(model.model_version),
+            },
+            quality_score: model.quality_score,
+            pose_label: model.pose_label,
+            history: Some(History {
+                created_at: model.created_at,
+                threshold_offset: model.threshold_offset,
+                last_matched_at: model.last_matched_at,
+            }),
+        }
+    }
+}
+
+/// What a [`FaceModelStore::insert_batch`] changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    /// IDs of the new models, in the order given.
+    pub inserted: Vec<String>,
+    /// IDs of the models a replace deleted.
+    pub replaced: Vec<String>,
+}
+
+/// A model the retention rules may purge; see `retention`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnusedModel {
+    pub id: String,
+    pub tenant: String,
+    pub user: String,
+    pub label: String,
+    /// Last match, or the enrollment if never matched (RFC 3339).
+    pub last_used: String,
+    /// When the purge notice was sent, if it was.
+    pub notified_at: Option<String>,
+}
+
+/// One verify attempt, as `visage admin stats` counts it; see `stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyAttempt {
+    /// When the attempt ended (RFC 3339).
+    pub at: String,
+    pub tenant: String,
+    pub user: String,
+    /// The camera device in use.
+    pub camera: String,
+    /// `matched`, or the failure reason code.
+    pub outcome: String,
+    pub latency_ms: u64,
+    /// Frames captured, usable or not.
+    pub frames: u64,
+    /// Frames dropped as too dark.
+    pub dark_frames: u64,
+}
+
+/// The face embedding of one verify, kept for score analysis; see `scores`.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub id: i64,
+    pub embedding: Embedding,
+    /// Best similarity of the verify against the gallery.
+    pub similarity: f32,
+    pub matched: bool,
+    /// RFC 3339.
+    pub created_at: String,
+}
+
+/// Metadata about an enrolled face model (no embedding data).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub label: String,
+    pub model_version: String,
+    pub quality_score: f64,
+    pub created_at: String,
+    /// Head pose of the capture: `frontal`, or the guided enrollment pose
+    /// (`left`, `right`, `glasses`).
+    pub pose_label: String,
+    /// When a verify or identification last matched this model (RFC 3339);
+    /// `None` if it never has. See `retention`.
+    pub last_matched_at: Option<String>,
+    /// Verifies in which this model was the best match but scored below the
+    /// threshold — a chronically marginal template is worth re-enrolling.
+    pub near_misses: u64,
+    /// Mean similarity of those near misses.
+    pub near_miss_mean_similarity: Option<f64>,
+    /// Highest similarity of those near misses.
+    pub near_miss_max_similarity: Option<f64>,
+    /// When the last near miss happened (RFC 3339).
+    pub last_near_miss_at: Option<String>,
+    /// Added to the similarity threshold for this model; see
+    /// `FaceModel::threshold_offset`.
+    pub threshold_offset: f64,
+    /// Median similarity to the user's other models; `None` below three
+    /// models. Filled in by `ListModels`, see `consistency`.
+    pub consistency: Option<f64>,
+    /// Whether `consistency` is below `VISAGE_GALLERY_OUTLIER_SIMILARITY`.
+    pub outlier: bool,
+    /// Who requested the enrollment, and how; `None` for models enrolled
+    /// before consent records were kept. See `consent`.
+    pub consent: Option<Consent>,
 }
 
 // ── Tests ─────────────────────────────────────────────────────────────────────
@@ -391,12 +1222,15 @@ mod tests {
         };
 
         let id = store
-            .insert("alice", "default", &embedding, 0.85)
+            .insert(DEFAULT_TENANT, "alice", "default", &embedding, 0.85)
             .await
             .unwrap();
         assert!(!id.is_empty());
 
-        let gallery = store.get_gallery_for_user("alice").await.unwrap();
+        let gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
         assert_eq!(gallery.len(), 1);
         assert_eq!(gallery[0].id, id);
         assert_eq!(gallery[0].user, "alice");
@@ -417,22 +1251,217 @@ mod tests {
             model_version: None,
         };
 
-        let id = store.insert("alice", "default", &emb, 0.9).await.unwrap();
+        let id = store
+            .insert(DEFAULT_TENANT, "alice", "default", &emb, 0.9)
+            .await
+            .unwrap();
 
-        let bob_gallery = store.get_gallery_for_user("bob").await.unwrap();
+        let bob_gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "bob")
+            .await
+            .unwrap();
         assert!(bob_gallery.is_empty());
 
-        let deleted = store.remove("bob", &id).await.unwrap();
+        let deleted = store.remove(DEFAULT_TENANT, "bob", &id).await.unwrap();
         assert!(!deleted);
 
-        let deleted = store.remove("alice", &id).await.unwrap();
+        let deleted = store.remove(DEFAULT_TENANT, "alice", &id).await.unwrap();
         assert!(deleted);
 
-        let gallery = store.get_gallery_for_user("alice").await.unwrap();
+        let gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
         assert!(gallery.is_empty());
     }
 
     #[tokio::test]
+    async fn test_gallery_all_spans_users() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let emb = Embedding {
+            values: vec![1.0; EMBEDDING_DIM],
+            model_version: None,
+        };
+        store
+            .insert(DEFAULT_TENANT, "alice", "default", &emb, 0.9)
+            .await
+            .unwrap();
+        store
+            .insert(DEFAULT_TENANT, "bob", "default", &emb, 0.9)
+            .await
+            .unwrap();
+
+        let mut users: Vec<String> = store
+            .get_gallery_all(DEFAULT_TENANT)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.user)
+            .collect();
+        users.sort();
+        assert_eq!(users, vec!["alice", "bob"]);
+        assert_eq!(
+            store
+                .get_gallery_for_user(DEFAULT_TENANT, "bob")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_isolated() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let emb = Embedding {
+            values: vec![1.0; EMBEDDING_DIM],
+            model_version: None,
+        };
+        let lobby_id = store
+            .insert("lobby", "alice", "default", &emb, 0.9)
+            .await
+            .unwrap();
+        store
+            .insert(DEFAULT_TENANT, "alice", "default", &emb, 0.9)
+            .await
+            .unwrap();
+
+        let lobby = store.get_gallery_all("lobby").await.unwrap();
+        assert_eq!(lobby.len(), 1);
+        assert_eq!(lobby[0].tenant, "lobby");
+        assert_eq!(store.get_gallery_all("door").await.unwrap().len(), 0);
+        assert_eq!(
+            store
+                .list_by_user(DEFAULT_TENANT, "alice")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Same user name, wrong tenant: the model is not reachable.
+        assert!(!store
+            .remove(DEFAULT_TENANT, "alice", &lobby_id)
+            .await
+            .unwrap());
+        assert!(store.remove("lobby", "alice", &lobby_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pre_tenant_database_is_migrated() {
+        let dir = std::env::temp_dir().join(format!("visage-store-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("faces.db");
+        {
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE faces (
+                     id TEXT PRIMARY KEY, user TEXT NOT NULL, label TEXT NOT NULL,
+                     embedding BLOB NOT NULL, model_version TEXT NOT NULL,
+                     quality_score REAL NOT NULL DEFAULT 0.0,
+                     pose_label TEXT NOT NULL DEFAULT 'frontal', created_at TEXT NOT NULL
+                 );",
+            )
+            .unwrap();
+            let blob = embedding_to_bytes(&vec![0.5; EMBEDDING_DIM]);
+            conn.execute(
+                "INSERT INTO faces (id, user, label, embedding, model_version, created_at)
+                 VALUES ('old', 'alice', 'default', ?1, 'v1', '2024-01-01')",
+                [blob],
+            )
+            .unwrap();
+        }
+
+        let store = FaceModelStore::open(&db_path).await.unwrap();
+        let gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
+        let version = store.schema_version().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(version, migrations::latest());
+        assert_eq!(gallery.len(), 1);
+        assert_eq!(gallery[0].id, "old");
+        assert_eq!(gallery[0].tenant, DEFAULT_TENANT);
+        assert_eq!(gallery[0].threshold_offset, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_threshold_offset_reaches_the_gallery() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let embedding = Embedding {
+            values: vec![0.5; EMBEDDING_DIM],
+            model_version: None,
+        };
+        let id = store
+            .insert(DEFAULT_TENANT, "alice", "sunglasses", &embedding, 0.9)
+            .await
+            .unwrap();
+
+        assert!(!store
+            .set_threshold_offset(DEFAULT_TENANT, "bob", &id, 0.1)
+            .await
+            .unwrap());
+        assert!(store
+            .set_threshold_offset(DEFAULT_TENANT, "alice", &id, 0.1)
+            .await
+            .unwrap());
+        let gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
+        assert!((gallery[0].threshold_offset - 0.1).abs() < 1e-6);
+        let info = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
+        assert!((info[0].threshold_offset - 0.1).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_exported_models_import_with_their_history() {
+        let source = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let embedding = Embedding {
+            values: vec![0.25; EMBEDDING_DIM],
+            model_version: Some("v1".to_string()),
+        };
+        let id = source
+            .insert(DEFAULT_TENANT, "alice", "glasses", &embedding, 0.8)
+            .await
+            .unwrap();
+        source
+            .set_threshold_offset(DEFAULT_TENANT, "alice", &id, 0.05)
+            .await
+            .unwrap();
+        source.record_match(&id).await.unwrap();
+        let exported = source.export_models(DEFAULT_TENANT, "alice").await.unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].embedding, embedding.values);
+        assert!(exported[0].last_matched_at.is_some());
+
+        // Another installation: its own key.
+        let target = FaceModelStore {
+            enc_key: [7u8; 32],
+            ..FaceModelStore::open(Path::new(":memory:")).await.unwrap()
+        };
+        let models: Vec<NewModel> = exported.iter().cloned().map(NewModel::from).collect();
+        target
+            .insert_batch(DEFAULT_TENANT, "alice2", &models, None, false)
+            .await
+            .unwrap();
+        let info = target.list_by_user(DEFAULT_TENANT, "alice2").await.unwrap();
+        assert_eq!(info[0].label, "glasses");
+        assert_eq!(info[0].created_at, exported[0].created_at);
+        assert_eq!(info[0].last_matched_at, exported[0].last_matched_at);
+        assert!((info[0].threshold_offset - 0.05).abs() < 1e-6);
+        assert_eq!(
+            target
+                .export_models(DEFAULT_TENANT, "alice2")
+                .await
+                .unwrap(),
+            exported
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::excessive_precision)]
     async fn test_embedding_byte_fidelity() {
         // Build a 512-dim vector with interesting values at specific positions
         let mut values = vec![0.5f32; EMBEDDING_DIM];
@@ -496,8 +1525,14 @@ mod tests {
             model_version: Some("w600k_r50".to_string()),
         };
 
-        let id = store.insert("alice", "test", &emb, 0.95).await.unwrap();
-        let gallery = store.get_gallery_for_user("alice").await.unwrap();
+        let id = store
+            .insert(DEFAULT_TENANT, "alice", "test", &emb, 0.95)
+            .await
+            .unwrap();
+        let gallery = store
+            .get_gallery_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
 
         assert_eq!(gallery.len(), 1);
         assert_eq!(gallery[0].id, id);
@@ -606,11 +1641,20 @@ mod tests {
             model_version: Some("v1".to_string()),
         };
 
-        store.insert("alice", "normal", &emb, 0.9).await.unwrap();
-        store.insert("alice", "glasses", &emb, 0.8).await.unwrap();
-        store.insert("bob", "default", &emb, 0.7).await.unwrap();
+        store
+            .insert(DEFAULT_TENANT, "alice", "normal", &emb, 0.9)
+            .await
+            .unwrap();
+        store
+            .insert(DEFAULT_TENANT, "alice", "glasses", &emb, 0.8)
+            .await
+            .unwrap();
+        store
+            .insert(DEFAULT_TENANT, "bob", "default", &emb, 0.7)
+            .await
+            .unwrap();
 
-        let alice_models = store.list_by_user("alice").await.unwrap();
+        let alice_models = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
         assert_eq!(alice_models.len(), 2);
         assert_eq!(alice_models[0].label, "normal");
         assert_eq!(alice_models[1].label, "glasses");
@@ -618,4 +1662,479 @@ mod tests {
         let count = store.count_all().await.unwrap();
         assert_eq!(count, 3);
     }
+
+    #[tokio::test]
+    async fn test_authtok_is_sealed_and_replaced() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        assert_eq!(store.authtok("alice").await.unwrap(), None);
+        store.set_authtok("alice", "hunter2").await.unwrap();
+        store.set_authtok("alice", "correct horse").await.unwrap();
+        assert_eq!(
+            store.authtok("alice").await.unwrap().as_deref(),
+            Some("correct horse")
+        );
+        let raw: Vec<u8> = store
+            .conn
+            .call(|conn| Ok(conn.query_row("SELECT secret FROM authtoks", [], |r| r.get(0))?))
+            .await
+            .unwrap();
+        assert!(!raw.windows(7).any(|w| w == b"correct"), "stored encrypted");
+        assert!(store.clear_authtok("alice").await.unwrap());
+        assert!(!store.clear_authtok("alice").await.unwrap());
+        assert_eq!(store.authtok("alice").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_near_misses_are_counted_per_model() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let emb = Embedding {
+            values: vec![1.0; EMBEDDING_DIM],
+            model_version: Some("v1".to_string()),
+        };
+        let normal = store
+            .insert(DEFAULT_TENANT, "alice", "normal", &emb, 0.9)
+            .await
+            .unwrap();
+        let glasses = store
+            .insert(DEFAULT_TENANT, "alice", "glasses", &emb, 0.8)
+            .await
+            .unwrap();
+
+        store.record_near_miss(&glasses, 0.30).await.unwrap();
+        store.record_near_miss(&glasses, 0.36).await.unwrap();
+        store.record_near_miss("removed-model", 0.2).await.unwrap();
+
+        let models = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
+        assert_eq!(models[0].id, normal);
+        assert_eq!(models[0].near_misses, 0);
+        assert_eq!(models[0].near_miss_mean_similarity, None);
+        assert_eq!(models[1].near_misses, 2);
+        assert!((models[1].near_miss_mean_similarity.unwrap() - 0.33).abs() < 1e-6);
+        assert!((models[1].near_miss_max_similarity.unwrap() - 0.36).abs() < 1e-6);
+        assert!(models[1].last_near_miss_at.is_some());
+
+        assert!(store
+            .remove(DEFAULT_TENANT, "alice", &glasses)
+            .await
+            .unwrap());
+        store.record_near_miss(&glasses, 0.3).await.unwrap();
+        let rows: u64 = store
+            .conn
+            .call(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM near_misses", [], |r| r.get(0))?))
+            .await
+            .unwrap();
+        assert_eq!(rows, 0, "removing a model drops its counters");
+    }
+
+    #[tokio::test]
+    async fn test_consent_is_stored_with_the_model() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let emb = Embedding {
+            values: vec![1.0; EMBEDDING_DIM],
+            model_version: Some("v1".to_string()),
+        };
+        let consent = Consent {
+            recorded_at: "2026-03-04T22:40:02+00:00".to_string(),
+            method: "Enroll".to_string(),
+            caller_uid: Some(0),
+            caller_user: Some("root".to_string()),
+            login_uid: Some(1000),
+            login_user: Some("alice".to_string()),
+            command: Some("visage".to_string()),
+        };
+        store
+            .insert(DEFAULT_TENANT, "alice", "legacy", &emb, 0.9)
+            .await
+            .unwrap();
+        let model = NewModel {
+            label: "desk".to_string(),
+            embedding: emb.clone(),
+            quality_score: 0.9,
+            pose_label: "left".to_string(),
+            history: None,
+        };
+        let batch = store
+            .insert_batch(DEFAULT_TENANT, "alice", &[model], Some(&consent), false)
+            .await
+            .unwrap();
+        let id = &batch.inserted[0];
+
+        let models = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
+        assert_eq!(models[0].consent, None);
+        assert_eq!(models[1].consent.as_ref(), Some(&consent));
+        assert_eq!(models[0].pose_label, DEFAULT_POSE_LABEL);
+        assert_eq!(models[1].pose_label, "left");
+
+        assert!(store.remove(DEFAULT_TENANT, "alice", id).await.unwrap());
+        let rows: u64 = store
+            .conn
+            .call(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM consents", [], |r| r.get(0))?))
+            .await
+            .unwrap();
+        assert_eq!(rows, 0, "removing a model drops its consent record");
+    }
+
+    #[tokio::test]
+    async fn verify_attempts_are_read_back_and_pruned() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let attempt = |at: &str, outcome: &str| VerifyAttempt {
+            at: at.to_string(),
+            tenant: DEFAULT_TENANT.to_string(),
+            user: "alice".to_string(),
+            camera: "/dev/video2".to_string(),
+            outcome: outcome.to_string(),
+            latency_ms: 420,
+            frames: 3,
+            dark_frames: 1,
+        };
+        let old = attempt("2026-01-01T00:00:00+00:00", "too_dark");
+        let new = attempt("2026-03-01T00:00:00+00:00", "matched");
+        store.record_attempt(new.clone()).await.unwrap();
+        store.record_attempt(old.clone()).await.unwrap();
+
+        let all = store
+            .attempts_since("2000-01-01T00:00:00+00:00")
+            .await
+            .unwrap();
+        assert_eq!(all, vec![old, new.clone()]);
+        let cutoff = "2026-02-01T00:00:00+00:00";
+        assert_eq!(store.attempts_since(cutoff).await.unwrap(), vec![new]);
+        assert_eq!(store.prune_attempts(cutoff).await.unwrap(), 1);
+        assert_eq!(store.attempts_since("").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn batches_replace_a_gallery_all_or_nothing() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let model = |label: &str, v: f32| NewModel {
+            label: label.to_string(),
+            embedding: Embedding {
+                values: vec![v; EMBEDDING_DIM],
+                model_version: Some("v1".to_string()),
+            },
+            quality_score: 0.9,
+            pose_label: DEFAULT_POSE_LABEL.to_string(),
+            history: None,
+        };
+        let old = store
+            .insert_batch(
+                DEFAULT_TENANT,
+                "alice",
+                &[model("old", 0.1), model("old", 0.2)],
+                Some(&Consent::default()),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!((old.inserted.len(), old.replaced.len()), (2, 0));
+        store
+            .insert(
+                DEFAULT_TENANT,
+                "bob",
+                "desk",
+                &model("", 0.3).embedding,
+                0.9,
+            )
+            .await
+            .unwrap();
+        store
+            .record_probe(
+                DEFAULT_TENANT,
+                "alice",
+                &model("", 0.1).embedding,
+                0.5,
+                true,
+                5,
+            )
+            .await
+            .unwrap();
+
+        // A bad model fails the whole batch, replace included.
+        let mut bad = model("new", 0.4);
+        bad.embedding.values[0] = f32::NAN;
+        assert!(store
+            .insert_batch(
+                DEFAULT_TENANT,
+                "alice",
+                &[model("new", 0.4), bad],
+                None,
+                true
+            )
+            .await
+            .is_err());
+        let labels = |models: Vec<ModelInfo>| -> Vec<String> {
+            models.into_iter().map(|m| m.label).collect()
+        };
+        assert_eq!(
+            labels(store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap()),
+            vec!["old", "old"]
+        );
+
+        let new = store
+            .insert_batch(
+                DEFAULT_TENANT,
+                "alice",
+                &[model("new", 0.4), model("new", 0.5)],
+                Some(&Consent::default()),
+                true,
+            )
+            .await
+            .unwrap();
+        let mut replaced = new.replaced.clone();
+        replaced.sort();
+        let mut expected = old.inserted.clone();
+        expected.sort();
+        assert_eq!(replaced, expected);
+        assert_eq!(
+            labels(store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap()),
+            vec!["new", "new"]
+        );
+        assert_eq!(
+            store
+                .list_by_user(DEFAULT_TENANT, "bob")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(store
+            .probes_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap()
+            .is_empty());
+        let consents: u64 = store
+            .conn
+            .call(|conn| Ok(conn.query_row("SELECT COUNT(*) FROM consents", [], |r| r.get(0))?))
+            .await
+            .unwrap();
+        assert_eq!(consents, 2, "the replaced models' consents are gone");
+    }
+
+    #[tokio::test]
+    async fn model_version_migrations_are_all_or_nothing() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let embedding = |v: f32, version: &str| Embedding {
+            values: vec![v; EMBEDDING_DIM],
+            model_version: Some(version.to_string()),
+        };
+        let glasses = store
+            .insert(
+                DEFAULT_TENANT,
+                "alice",
+                "glasses",
+                &embedding(0.1, "v1"),
+                0.9,
+            )
+            .await
+            .unwrap();
+        let desk = store
+            .insert(DEFAULT_TENANT, "alice", "desk", &embedding(0.2, "v1"), 0.9)
+            .await
+            .unwrap();
+        let bob = store
+            .insert(DEFAULT_TENANT, "bob", "desk", &embedding(0.3, "v1"), 0.9)
+            .await
+            .unwrap();
+        store.record_near_miss(&glasses, 0.3).await.unwrap();
+        let versions = |gallery: Vec<FaceModel>| -> Vec<(String, Option<String>, f32)> {
+            let mut rows: Vec<_> = gallery
+                .into_iter()
+                .map(|m| (m.label, m.embedding.model_version, m.embedding.values[0]))
+                .collect();
+            rows.sort_by(|a, b| a.0.cmp(&b.0));
+            rows
+        };
+        let v1 = Some("v1".to_string());
+        let v2 = Some("v2".to_string());
+
+        // Bob's model cannot be migrated as Alice's: the replacement already
+        // made for hers is rolled back with it.
+        let err = store
+            .migrate_model_version(
+                DEFAULT_TENANT,
+                "alice",
+                "v1",
+                "v2",
+                &[
+                    (glasses.clone(), embedding(0.5, "v2")),
+                    (bob.clone(), embedding(0.6, "v2")),
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, StoreError::NotMigratable { ref id, .. } if *id == bob));
+        let alice = || store.get_gallery_for_user(DEFAULT_TENANT, "alice");
+        assert_eq!(
+            versions(alice().await.unwrap()),
+            vec![
+                ("desk".to_string(), v1.clone(), 0.2),
+                ("glasses".to_string(), v1.clone(), 0.1),
+            ]
+        );
+
+        let migrated = store
+            .migrate_model_version(
+                DEFAULT_TENANT,
+                "alice",
+                "v1",
+                "v2",
+                &[(glasses.clone(), embedding(0.5, "v2"))],
+            )
+            .await
+            .unwrap();
+        assert_eq!(migrated, 2);
+        assert_eq!(
+            versions(alice().await.unwrap()),
+            vec![
+                ("desk".to_string(), v2.clone(), 0.2),
+                ("glasses".to_string(), v2.clone(), 0.5),
+            ]
+        );
+        let listed = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
+        assert!(listed.iter().all(|m| m.near_misses == 0));
+        assert!(listed.iter().any(|m| m.id == desk));
+        assert_eq!(
+            versions(
+                store
+                    .get_gallery_for_user(DEFAULT_TENANT, "bob")
+                    .await
+                    .unwrap()
+            ),
+            vec![("desk".to_string(), v1, 0.3)]
+        );
+
+        // Nothing is left at the old version to migrate again.
+        assert_eq!(
+            store
+                .migrate_model_version(DEFAULT_TENANT, "alice", "v1", "v2", &[])
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn probes_keep_only_the_newest() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let probe = |v: f32| Embedding {
+            values: vec![v; EMBEDDING_DIM],
+            model_version: None,
+        };
+        for (v, matched) in [(0.1, false), (0.2, true), (0.3, true)] {
+            store
+                .record_probe(DEFAULT_TENANT, "alice", &probe(v), v, matched, 2)
+                .await
+                .unwrap();
+        }
+        store
+            .record_probe("kiosk", "alice", &probe(0.4), 0.4, true, 2)
+            .await
+            .unwrap();
+
+        let kept = store
+            .probes_for_user(DEFAULT_TENANT, "alice")
+            .await
+            .unwrap();
+        assert_eq!(
+            kept.iter().map(|p| p.similarity).collect::<Vec<_>>(),
+            vec![0.2, 0.3]
+        );
+        assert_eq!(kept[1].embedding.values, probe(0.3).values);
+        assert!(kept[1].matched);
+
+        assert_eq!(
+            store
+                .prune_probes("9999-01-01T00:00:00+00:00")
+                .await
+                .unwrap(),
+            3
+        );
+        assert!(store
+            .probes_for_user("kiosk", "alice")
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Removing the last model takes the probes with it.
+        let template = Embedding {
+            model_version: Some("v1".to_string()),
+            ..probe(0.5)
+        };
+        let first = store
+            .insert(DEFAULT_TENANT, "bob", "desk", &template, 0.9)
+            .await
+            .unwrap();
+        let second = store
+            .insert(DEFAULT_TENANT, "bob", "glasses", &template, 0.9)
+            .await
+            .unwrap();
+        store
+            .record_probe(DEFAULT_TENANT, "bob", &probe(0.5), 0.5, true, 2)
+            .await
+            .unwrap();
+        store.remove(DEFAULT_TENANT, "bob", &first).await.unwrap();
+        assert_eq!(
+            store
+                .probes_for_user(DEFAULT_TENANT, "bob")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        store.remove(DEFAULT_TENANT, "bob", &second).await.unwrap();
+        assert!(store
+            .probes_for_user(DEFAULT_TENANT, "bob")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retention_queries() {
+        let store = FaceModelStore::open(Path::new(":memory:")).await.unwrap();
+        let emb = Embedding {
+            values: vec![1.0; EMBEDDING_DIM],
+            model_version: Some("v1".to_string()),
+        };
+        let id = store
+            .insert(DEFAULT_TENANT, "alice", "desk", &emb, 0.9)
+            .await
+            .unwrap();
+        let past = "2000-01-01T00:00:00+00:00";
+        let future = "9999-01-01T00:00:00+00:00";
+
+        store.record_near_miss(&id, 0.3).await.unwrap();
+        assert_eq!(store.prune_near_misses(past).await.unwrap(), 0);
+        assert_eq!(store.prune_near_misses(future).await.unwrap(), 1);
+
+        assert!(store.unused_models(past).await.unwrap().is_empty());
+        let unused = store.unused_models(future).await.unwrap();
+        assert_eq!(unused.len(), 1);
+        assert_eq!(
+            (unused[0].id.as_str(), unused[0].notified_at.as_deref()),
+            (id.as_str(), None)
+        );
+
+        store.record_purge_notice(&id).await.unwrap();
+        assert!(store.unused_models(future).await.unwrap()[0]
+            .notified_at
+            .is_some());
+
+        // A match after the notice withdraws it.
+        store
+            .conn
+            .call(
+                move |conn| Ok(conn.execute("UPDATE purge_notices SET notified_at = ?1", [past])?),
+            )
+            .await
+            .unwrap();
+        store.record_match(&id).await.unwrap();
+        assert_eq!(store.clear_used_purge_notices().await.unwrap(), 1);
+        assert_eq!(
+            store.unused_models(future).await.unwrap()[0].notified_at,
+            None
+        );
+        let models = store.list_by_user(DEFAULT_TENANT, "alice").await.unwrap();
+        assert!(models[0].last_matched_at.is_some());
+    }
 }
@@ -0,0 +1,110 @@
+//! systemd service notifications (`sd_notify(3)`): readiness and watchdog.
+//!
+//! The unit runs the daemon as `Type=notify`. Once the bus name is claimed
+//! (or, with no broker, once the verify socket serves alone) the daemon sends
+//! `READY=1`, so units ordered after it — a display manager, a lock screen —
+//! start only when a verify can be answered.
+//!
+//! With `WatchdogSec=` systemd passes the interval in `WATCHDOG_USEC` and
+//! restarts the daemon when `WATCHDOG=1` stops arriving. The daemon pings at
+//! half the interval, and only while the engine thread is alive and not stuck
+//! in one request (see `health::engine_status`): a hung inference or a wedged
+//! V4L2 ioctl blocks every login behind it, and a restart is the fix.
+//!
+//! Each message is one datagram to the socket named in `NOTIFY_SOCKET`, a
+//! path or (with a leading `@`) an abstract socket name. Without the variable
+//! — the daemon was not started by systemd — nothing is sent.
+
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::time::Duration;
+
+/// Send `state` (e.g. `READY=1`, newline-separated assignments) to the
+/// service manager, if there is one. Failures are logged, never fatal.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = send(socket.as_bytes(), state) {
+        tracing::warn!(error = %e, "sd_notify failed");
+    }
+}
+
+fn send(socket: &[u8], state: &str) -> io::Result<()> {
+    let sender = UnixDatagram::unbound()?;
+    match socket.strip_prefix(b"@") {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sender.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        None => {
+            sender.send_to(
+                state.as_bytes(),
+                Path::new(std::ffi::OsStr::from_bytes(socket)),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// How often to send `WATCHDOG=1`: half of `WATCHDOG_USEC`, or `None` when
+/// systemd expects no pings from this process.
+pub fn watchdog_interval() -> Option<Duration> {
+    interval_from(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+/// [`watchdog_interval`] from the variables' values. A `WATCHDOG_PID` naming
+/// another process means the pings are not ours to send.
+fn interval_from(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    let usec: u64 = usec?.parse().ok()?;
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok()? != own_pid {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchdog_interval_is_half_and_ours_only() {
+        assert_eq!(
+            interval_from(Some("30000000"), None, 7),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            interval_from(Some("30000000"), Some("7"), 7),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(interval_from(Some("30000000"), Some("8"), 7), None);
+        assert_eq!(interval_from(Some("0"), None, 7), None);
+        assert_eq!(interval_from(Some("soon"), None, 7), None);
+        assert_eq!(interval_from(None, Some("7"), 7), None);
+    }
+
+    #[test]
+    fn notify_reaches_a_path_socket() {
+        let dir = std::env::temp_dir().join(format!("visage-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let _ = std::fs::remove_file(&path);
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        send(path.as_os_str().as_bytes(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,115 @@
+//! Login-session pre-warming — make the first unlock after login instant.
+//!
+//! `pam_visage.so` in the `session` stack calls `SessionOpened` when a user
+//! logs in and `SessionClosed` when they log out. While a user has a session
+//! open, their default-tenant gallery is kept in memory so a verify skips the
+//! database, and on each `SessionOpened` the engine runs one warm-up
+//! inference (see `EngineHandle::prewarm`).
+//!
+//! Enrolling or removing a model drops the user's cached gallery; the next
+//! verify reloads it.
+
+use std::collections::HashMap;
+
+use visage_core::FaceModel;
+
+/// Most users whose galleries are cached at once. Further sessions are
+/// counted but not cached.
+const MAX_WARM_USERS: usize = 64;
+
+/// Open-session counts and cached galleries, by user (default tenant only).
+#[derive(Default)]
+pub struct WarmSessions {
+    users: HashMap<String, WarmUser>,
+}
+
+#[derive(Default)]
+struct WarmUser {
+    sessions: u32,
+    gallery: Option<Vec<FaceModel>>,
+}
+
+impl WarmSessions {
+    /// Record a session opening for `user`. Returns `false` if the user is
+    /// not yet tracked and the table is full.
+    pub fn open(&mut self, user: &str) -> bool {
+        if !self.users.contains_key(user) && self.users.len() >= MAX_WARM_USERS {
+            return false;
+        }
+        let entry = self.users.entry(user.to_string()).or_default();
+        entry.sessions = entry.sessions.saturating_add(1);
+        true
+    }
+
+    /// Record a session closing for `user`; the cached gallery is dropped
+    /// with the last session.
+    pub fn close(&mut self, user: &str) {
+        if let Some(entry) = self.users.get_mut(user) {
+            entry.sessions = entry.sessions.saturating_sub(1);
+            if entry.sessions == 0 {
+                self.users.remove(user);
+            }
+        }
+    }
+
+    /// Cached gallery of `user`, if they have a session open and it is loaded.
+    pub fn gallery(&self, user: &str) -> Option<&[FaceModel]> {
+        self.users.get(user)?.gallery.as_deref()
+    }
+
+    /// Cache `gallery` for `user`; ignored unless they have a session open.
+    pub fn store_gallery(&mut self, user: &str, gallery: Vec<FaceModel>) {
+        if let Some(entry) = self.users.get_mut(user) {
+            entry.gallery = Some(gallery);
+        }
+    }
+
+    /// Drop `user`'s cached gallery after their enrollment changed.
+    pub fn invalidate(&mut self, user: &str) {
+        if let Some(entry) = self.users.get_mut(user) {
+            entry.gallery = None;
+        }
+    }
+
+    /// Users with at least one open session.
+    pub fn user_count(&self) -> usize {
+        self.users.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gallery_lives_while_a_session_is_open() {
+        let mut warm = WarmSessions::default();
+        warm.store_gallery("alice", Vec::new());
+        assert!(warm.gallery("alice").is_none(), "no session yet");
+
+        assert!(warm.open("alice"));
+        assert!(warm.open("alice"));
+        warm.store_gallery("alice", Vec::new());
+        assert!(warm.gallery("alice").is_some());
+        warm.invalidate("alice");
+        assert!(warm.gallery("alice").is_none());
+
+        warm.store_gallery("alice", Vec::new());
+        warm.close("alice");
+        assert!(warm.gallery("alice").is_some(), "one session left");
+        warm.close("alice");
+        assert!(warm.gallery("alice").is_none());
+        assert_eq!(warm.user_count(), 0);
+    }
+
+    #[test]
+    fn table_is_bounded() {
+        let mut warm = WarmSessions::default();
+        for i in 0..MAX_WARM_USERS {
+            assert!(warm.open(&format!("user{i}")));
+        }
+        assert!(!warm.open("late"));
+        assert!(warm.open("user0"), "known users still count sessions");
+        assert_eq!(warm.user_count(), MAX_WARM_USERS);
+    }
+}
@@ -48,16 +48,34 @@ rustPlatform.buildRustPackage {
     install -Dm644 packaging/dbus/org.freedesktop.Visage1.conf \
       $out/share/dbus-1/system.d/org.freedesktop.Visage1.conf
 
+    # D-Bus activation (starts visaged.service on the first call)
+    install -Dm644 packaging/dbus/org.freedesktop.Visage1.service \
+      $out/share/dbus-1/system-services/org.freedesktop.Visage1.service
+
+    # Polkit actions for enrollment by non-root callers
+    install -Dm644 packaging/polkit/org.freedesktop.visage1.policy \
+      $out/share/polkit-1/actions/org.freedesktop.visage1.policy
+
     # systemd units — patch ExecStart to reference the Nix store path
     install -Dm644 packaging/systemd/visaged.service \
       $out/lib/systemd/system/visaged.service
     substituteInPlace $out/lib/systemd/system/visaged.service \
       --replace-fail "/usr/bin/visaged" "$out/bin/visaged"
+    install -Dm644 packaging/systemd/visaged@.service \
+      $out/lib/systemd/system/visaged@.service
+    substituteInPlace $out/lib/systemd/system/visaged@.service \
+      --replace-fail "/usr/bin/visaged" "$out/bin/visaged"
 
     install -Dm644 packaging/systemd/visage-resume.service \
       $out/lib/systemd/system/visage-resume.service
     substituteInPlace $out/lib/systemd/system/visage-resume.service \
       --replace-fail "/usr/bin/systemctl" "systemctl"
+
+    # Desktop notifications about unexpected face models
+    install -Dm644 packaging/xdg/visage-notify.desktop \
+      $out/etc/xdg/autostart/visage-notify.desktop
+    substituteInPlace $out/etc/xdg/autostart/visage-notify.desktop \
+      --replace-fail "/usr/bin/visage" "$out/bin/visage"
   '';
 
   meta = with lib; {
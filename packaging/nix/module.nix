@@ -141,8 +141,10 @@ in
       };
 
       serviceConfig = {
-        Type = "simple";
+        Type = "notify";
+        WatchdogSec = "30s";
         ExecStart = "${cfg.package}/bin/visaged";
+        ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
         Restart = "on-failure";
         RestartSec = 5;
 
@@ -153,6 +155,8 @@ in
         PrivateTmp = true;
         DeviceAllow = [ "char-video4linux rw" ];
         ReadWritePaths = [ "/var/lib/visage" ];
+        LogsDirectory = "visage";
+        LogsDirectoryMode = "0700";
         CapabilityBoundingSet = "";
         SystemCallArchitectures = "native";
         MemoryDenyWriteExecute = false;